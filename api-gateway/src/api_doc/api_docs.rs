@@ -25,13 +25,24 @@ impl Modify for SecurityAddon {
 #[openapi(
     paths(
         health,
+        server_time,
         user_login,
+        user_login_verify,
         user_refresh,
         user_register,
         get_user_by_id,
         get_user_by_username,
         update_user,
+        get_current_user,
+        update_current_user,
+        list_current_user_devices,
+        get_current_user_storage,
+        create_attachment_upload,
+        get_search_exclusions,
+        set_conversation_search_opt_out,
         search_users,
+        check_availability,
+        send_phone_invite,
         send_friend_request,
         accept_friend_request,
         reject_friend_request,
@@ -45,18 +56,26 @@ impl Modify for SecurityAddon {
         join_group,
         leave_group,
         list_groups,
-        list_group_members
+        list_group_members,
+        list_group_media
     ),
     components(
         schemas(
             HealthResponse,
             LoginRequest,
             LoginResponse,
+            LoginChallengeResponse,
+            VerifyLoginRequest,
             RefreshTokenRequest,
             RefreshTokenResponse,
             RegisterRequest,
             UserResponse,
             UpdateUserRequest,
+            StorageUsageResponse,
+            CreateUploadRequest,
+            CreateUploadResponse,
+            ConversationSearchOptOutRequest,
+            SearchExclusionsResponse,
             SearchUsersRequest,
             SearchUsersResponse,
             FriendRequest,
@@ -113,6 +132,23 @@ pub struct HealthResponse {
 pub struct LoginRequest {
     username: String,
     password: String,
+    device_id: Option<String>,
+    device_name: Option<String>,
+}
+
+/// 登录需要二次验证时的响应
+#[derive(utoipa::ToSchema)]
+pub struct LoginChallengeResponse {
+    verification_required: bool,
+    login_id: String,
+    reasons: Vec<String>,
+}
+
+/// 登录二次验证请求
+#[derive(utoipa::ToSchema)]
+pub struct VerifyLoginRequest {
+    login_id: String,
+    code: String,
 }
 
 /// 登录响应
@@ -168,6 +204,52 @@ pub struct UpdateUserRequest {
     password: Option<String>,
 }
 
+/// 附件存储用量
+#[derive(utoipa::ToSchema)]
+pub struct StorageUsageResponse {
+    user_id: String,
+    used_bytes: i64,
+    /// 0表示不限制
+    quota_bytes: i64,
+    attachment_count: i64,
+}
+
+/// 申请附件上传地址请求
+#[derive(utoipa::ToSchema)]
+pub struct CreateUploadRequest {
+    file_name: String,
+    mime_type: String,
+    size: i64,
+}
+
+/// 申请附件上传地址响应
+#[derive(utoipa::ToSchema)]
+pub struct CreateUploadResponse {
+    object_key: String,
+    upload_url: String,
+    expires_at: String,
+    usage: StorageUsageResponse,
+}
+
+/// 设置会话消息搜索排除请求
+#[derive(utoipa::ToSchema)]
+pub struct ConversationSearchOptOutRequest {
+    /// 单聊为对方用户ID，群聊为群组ID
+    conversation_id: String,
+    /// true排除，false恢复，默认排除
+    opt_out: Option<bool>,
+}
+
+/// 消息搜索排除设置响应
+#[derive(utoipa::ToSchema)]
+pub struct SearchExclusionsResponse {
+    user_id: String,
+    /// 用户发送的消息均不进入搜索索引
+    search_opt_out: bool,
+    /// 不进入该用户搜索索引的会话
+    conversation_ids: Vec<String>,
+}
+
 /// 搜索用户请求
 #[derive(utoipa::ToSchema)]
 pub struct SearchUsersRequest {
@@ -284,29 +366,65 @@ async fn health() -> axum::Json<HealthResponse> {
     })
 }
 
+/// 对时接口
+#[utoipa::path(
+    get,
+    path = "/api/time",
+    tag = "health",
+    params(
+        ("client_time" = Option<i64>, Query, description = "客户端发送请求的本地时间（毫秒），原样返回")
+    ),
+    responses(
+        (status = 200, description = "返回 client_time、server_receive_time、server_send_time（UTC毫秒）")
+    )
+)]
+async fn server_time() {}
+
 /// 用户登录接口
 #[utoipa::path(
     post,
     path = "/api/user/login",
     tag = "auth",
     request_body = LoginRequest,
+    params(
+        ("X-Client-Type" = Option<String>, Header, description = "客户端类型，Web端（web）启用Cookie会话时令牌通过HttpOnly Cookie下发，响应中返回csrf_token")
+    ),
     responses(
         (status = 200, description = "登录成功", body = LoginResponse),
+        (status = 202, description = "异地或新设备登录，需要二次验证", body = LoginChallengeResponse),
         (status = 400, description = "请求参数错误"),
         (status = 401, description = "用户名或密码错误")
     )
 )]
 async fn user_login() {}
 
+/// 登录二次验证接口
+#[utoipa::path(
+    post,
+    path = "/api/user/login/verify",
+    tag = "auth",
+    request_body = VerifyLoginRequest,
+    responses(
+        (status = 200, description = "验证通过，登录成功", body = LoginResponse),
+        (status = 401, description = "验证码不正确或已过期")
+    )
+)]
+async fn user_login_verify() {}
+
 /// 刷新令牌接口
 #[utoipa::path(
     post,
     path = "/api/user/refresh",
     tag = "auth",
     request_body = RefreshTokenRequest,
+    params(
+        ("X-Client-Type" = Option<String>, Header, description = "客户端类型，Cookie会话下刷新令牌从Cookie读取"),
+        ("X-CSRF-Token" = Option<String>, Header, description = "Cookie会话下必须回传CSRF令牌")
+    ),
     responses(
         (status = 200, description = "刷新成功", body = RefreshTokenResponse),
         (status = 400, description = "请求参数错误"),
+        (status = 403, description = "CSRF校验失败"),
         (status = 401, description = "刷新令牌无效或已过期")
     )
 )]
@@ -385,15 +503,133 @@ async fn get_user_by_username() {}
 )]
 async fn update_user() {}
 
+/// 获取当前登录用户
+///
+/// 用户ID取自令牌，无需在路径或请求体中传递
+#[utoipa::path(
+    get,
+    path = "/api/users/me",
+    tag = "users",
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "获取用户成功", body = UserResponse),
+        (status = 401, description = "未认证")
+    )
+)]
+async fn get_current_user() {}
+
+/// 更新当前登录用户信息
+#[utoipa::path(
+    put,
+    path = "/api/users/me",
+    tag = "users",
+    request_body = UpdateUserRequest,
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "更新用户成功", body = UserResponse),
+        (status = 400, description = "请求参数错误"),
+        (status = 401, description = "未认证")
+    )
+)]
+async fn update_current_user() {}
+
+/// 获取当前登录用户的登录设备
+#[utoipa::path(
+    get,
+    path = "/api/users/me/devices",
+    tag = "users",
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "获取设备列表成功"),
+        (status = 401, description = "未认证")
+    )
+)]
+async fn list_current_user_devices() {}
+
+/// 获取当前登录用户的附件存储用量
+#[utoipa::path(
+    get,
+    path = "/api/users/me/storage",
+    tag = "users",
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "获取存储用量成功", body = StorageUsageResponse),
+        (status = 401, description = "未认证")
+    )
+)]
+async fn get_current_user_storage() {}
+
+/// 申请附件上传地址
+///
+/// 按声明的附件大小预占存储配额，客户端使用返回的签名地址直接上传到对象存储
+#[utoipa::path(
+    post,
+    path = "/api/users/me/storage/uploads",
+    tag = "users",
+    request_body = CreateUploadRequest,
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "申请上传地址成功", body = CreateUploadResponse),
+        (status = 400, description = "附件类型或大小不合法"),
+        (status = 401, description = "未认证"),
+        (status = 507, description = "超出存储配额")
+    )
+)]
+async fn create_attachment_upload() {}
+
+/// 获取当前登录用户的消息搜索排除设置
+#[utoipa::path(
+    get,
+    path = "/api/users/me/search-exclusions",
+    tag = "users",
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "获取消息搜索排除设置成功", body = SearchExclusionsResponse),
+        (status = 401, description = "未认证")
+    )
+)]
+async fn get_search_exclusions() {}
+
+/// 设置会话是否排除在消息搜索之外
+///
+/// 被排除会话的消息不进入当前用户的搜索索引
+#[utoipa::path(
+    put,
+    path = "/api/users/me/search-exclusions",
+    tag = "users",
+    request_body = ConversationSearchOptOutRequest,
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "设置成功", body = SearchExclusionsResponse),
+        (status = 400, description = "会话ID为空"),
+        (status = 401, description = "未认证")
+    )
+)]
+async fn set_conversation_search_opt_out() {}
+
 /// 搜索用户
 #[utoipa::path(
     get,
     path = "/api/users/search",
     tag = "users",
     params(
-        ("query" = String, Query, description = "搜索关键词"),
+        ("query" = String, Query, description = "搜索关键词：用户名/邮箱/手机号精确匹配，昵称支持模糊和拼音匹配"),
         ("page" = i32, Query, description = "页码，默认为1"),
-        ("page_size" = i32, Query, description = "每页数量，默认为10")
+        ("pageSize" = i32, Query, description = "每页数量，默认为10")
     ),
     security(
         ("bearer" = [])
@@ -406,6 +642,45 @@ async fn update_user() {}
 )]
 async fn search_users() {}
 
+/// 注册前检查用户名、手机号是否可用
+#[utoipa::path(
+    get,
+    path = "/api/users/availability",
+    tag = "users",
+    params(
+        ("username" = Option<String>, Query, description = "待检查的用户名"),
+        ("phone" = Option<String>, Query, description = "待检查的手机号")
+    ),
+    responses(
+        (status = 200, description = "检查成功，返回 usernameAvailable、phoneAvailable，未检查的字段为null"),
+        (status = 400, description = "用户名和手机号都为空"),
+        (status = 429, description = "检查过于频繁")
+    )
+)]
+async fn check_availability() {}
+
+/// 向手机号发送邀请消息，对方未注册时发送邀请短信并在注册后投递
+#[utoipa::path(
+    post,
+    path = "/api/users/invite",
+    tag = "users",
+    params(
+        ("senderId" = Option<String>, Query, description = "发送人ID，默认为当前用户"),
+        ("phone" = String, Query, description = "被邀请的手机号"),
+        ("content" = String, Query, description = "邀请消息内容")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "邀请成功，返回 inviteId，对方已注册时 delivered 为true"),
+        (status = 400, description = "手机号格式不正确或内容为空"),
+        (status = 401, description = "未认证"),
+        (status = 429, description = "今日邀请次数已达上限")
+    )
+)]
+async fn send_phone_invite() {}
+
 /// 发送好友请求
 #[utoipa::path(
     post,
@@ -670,6 +945,31 @@ async fn list_groups() {}
 )]
 async fn list_group_members() {}
 
+/// 分页浏览、搜索群媒体库
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/media",
+    tag = "groups",
+    params(
+        ("id" = String, Path, description = "群组ID"),
+        ("type" = Option<String>, Query, description = "媒体类型 image/video/audio/file，为空表示全部"),
+        ("senderId" = Option<String>, Query, description = "发送者ID"),
+        ("keyword" = Option<String>, Query, description = "按文件名搜索"),
+        ("page" = Option<i32>, Query, description = "页码，从1开始"),
+        ("pageSize" = Option<i32>, Query, description = "每页数量，最大100")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    responses(
+        (status = 200, description = "查询成功，返回 items、total，按发送时间倒序"),
+        (status = 400, description = "无效的媒体类型"),
+        (status = 401, description = "未认证"),
+        (status = 403, description = "不是群组成员")
+    )
+)]
+async fn list_group_media() {}
+
 /// 将API文档路由添加到Router中
 pub fn configure_docs(app: Router) -> Router {
     // 日志输出API文档访问地址
@@ -1,12 +1,25 @@
-use crate::auth::jwt;
+use crate::auth::{self, jwt, session};
 use crate::config::CONFIG;
 use crate::UserServiceGrpcClient;
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    http::{
+        header::{RETRY_AFTER, SET_COOKIE, USER_AGENT},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
 use common::error::Error;
-use common::proto::user::VerifyPasswordRequest;
+use common::grpc::maintenance::maintenance_retry_after;
+use common::grpc::tenant::with_tenant;
+use common::grpc_client::AuthServiceGrpcClient;
+use common::proto::user::{
+    EvaluateLoginRequest, EvaluateLoginResponse, User, VerifyPasswordRequest,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::sync::Arc;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 /// 登录请求
 #[derive(Debug, Deserialize)]
@@ -17,27 +30,37 @@ pub struct LoginRequest {
     pub password: String,
     /// 租户ID
     pub tenant_id: i64,
+    /// 设备标识，用于新设备登录识别
+    #[serde(default)]
+    pub device_id: Option<String>,
+    /// 设备名称
+    #[serde(default)]
+    pub device_name: Option<String>,
 }
 
 /// 登录响应
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
-    /// 访问令牌
+    /// 访问令牌，使用Cookie会话时为空
     pub access_token: String,
-    /// 刷新令牌
+    /// 刷新令牌，使用Cookie会话时为空
     pub refresh_token: String,
-    /// 令牌类型
+    /// 令牌类型，Bearer或Cookie
     pub token_type: String,
     /// 过期时间（秒）
     pub expires_in: u64,
+    /// CSRF令牌，使用Cookie会话时返回，写请求需在请求头中回传
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub csrf_token: Option<String>,
     /// 用户信息
     pub user_info: UserInfoResponse,
 }
 
 /// 刷新令牌请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct RefreshTokenRequest {
-    /// 刷新令牌
+    /// 刷新令牌，使用Cookie会话时从Cookie中读取
+    #[serde(default)]
     pub refresh_token: String,
 }
 
@@ -60,23 +83,55 @@ pub struct UserInfoResponse {
     pub avatar_url: Option<String>,
 }
 
+/// 登录需要二次验证时的响应
+#[derive(Debug, Serialize)]
+pub struct LoginChallengeResponse {
+    /// 是否需要二次验证
+    pub verification_required: bool,
+    /// 登录记录ID，提交验证码时回传
+    pub login_id: String,
+    /// 触发的风险项
+    pub reasons: Vec<String>,
+}
+
+/// 登录二次验证请求
+#[derive(Debug, Deserialize)]
+pub struct VerifyLoginRequest {
+    /// 登录记录ID
+    pub login_id: String,
+    /// 验证码
+    pub code: String,
+    /// 租户ID，与登录请求一致
+    #[serde(default)]
+    pub tenant_id: Option<i64>,
+}
+
+/// 用户服务处于只读维护模式时的响应：503并提示重试时间，不是维护错误时返回None
+fn maintenance_response(err: &Error) -> Option<Response> {
+    let Error::TonicStatus(status) = err else {
+        return None;
+    };
+    let retry_after = maintenance_retry_after(status)?;
+    let body = Json(json!({
+        "error": StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+        "message": "服务维护中，请稍后重试",
+    }));
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, body).into_response();
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    Some(response)
+}
+
 /// 处理登录请求
 pub async fn login(
     user_client: Option<axum::extract::Extension<Arc<UserServiceGrpcClient>>>,
+    headers: HeaderMap,
     Json(login_req): Json<LoginRequest>,
-) -> Result<impl IntoResponse, Error> {
+) -> Result<Response, Error> {
     debug!("登录请求：用户 {}", login_req.username);
 
-    // 需要UserServiceGrpcClient扩展
-    let client = match user_client {
-        Some(axum::extract::Extension(client)) => client,
-        None => {
-            error!("未找到UserServiceGrpcClient扩展");
-            return Err(Error::Internal(
-                "未找到UserServiceGrpcClient扩展".to_string(),
-            ));
-        }
-    };
+    let client = get_user_client(user_client)?;
 
     // 创建验证密码请求
     let verify_request = VerifyPasswordRequest {
@@ -84,11 +139,22 @@ pub async fn login(
         password: login_req.password,
     };
 
+    // 登录前没有令牌，用户服务按请求中的租户ID路由数据库
+    let tenant_id = Some(login_req.tenant_id.to_string());
+
     // 调用用户服务验证密码
-    let response = client.verify_password(verify_request).await.map_err(|e| {
-        error!("调用用户服务验证密码失败: {}", e);
-        Error::Internal(format!("验证密码服务错误: {}", e))
-    })?;
+    let verify = with_tenant(tenant_id.clone(), client.verify_password(verify_request));
+    let response = match verify.await {
+        Ok(response) => response,
+        Err(e) => {
+            if let Some(response) = maintenance_response(&e) {
+                warn!("用户服务维护中，暂时无法登录: {}", e);
+                return Ok(response);
+            }
+            error!("调用用户服务验证密码失败: {}", e);
+            return Err(Error::Internal(format!("验证密码服务错误: {}", e)));
+        }
+    };
 
     // 检查密码是否有效
     if !response.valid || response.user.is_none() {
@@ -98,6 +164,196 @@ pub async fn login(
     // 获取用户信息
     let user = response.user.unwrap();
 
+    // 登录风险评估，异地或新设备登录需要二次验证
+    let evaluate_request = EvaluateLoginRequest {
+        user_id: user.id.clone(),
+        device_id: login_req.device_id.unwrap_or_default(),
+        device_name: login_req.device_name.unwrap_or_default(),
+        ip: auth::client_ip_from_headers(&headers).unwrap_or_default(),
+        user_agent: headers
+            .get(USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+    };
+    let evaluation = with_tenant(tenant_id, client.evaluate_login(evaluate_request)).await;
+    let fail_open = CONFIG.read().await.auth.login_risk_fail_open;
+    if let Some(challenge) = login_challenge(evaluation, fail_open)? {
+        info!("用户 {} 登录需要二次验证", login_req.username);
+        return Ok((StatusCode::ACCEPTED, Json(challenge)).into_response());
+    }
+
+    let login_response = issue_tokens(user).await?;
+
+    info!("用户 {} 登录成功", login_req.username);
+
+    // 返回响应
+    Ok(token_response(&headers, login_response).await)
+}
+
+/// 按风险评估结果决定是否要求二次验证
+///
+/// 评估失败时按 `fail_open` 配置处理：放行则不做二次验证，否则登录失败
+fn login_challenge(
+    evaluation: Result<EvaluateLoginResponse, Error>,
+    fail_open: bool,
+) -> Result<Option<LoginChallengeResponse>, Error> {
+    match evaluation {
+        Ok(evaluation) if evaluation.require_verification => {
+            debug!("登录风险评分: {}", evaluation.risk_score);
+            Ok(Some(LoginChallengeResponse {
+                verification_required: true,
+                login_id: evaluation.login_id,
+                reasons: evaluation.reasons,
+            }))
+        }
+        Ok(_) => Ok(None),
+        Err(e) if fail_open => {
+            warn!("登录风险评估失败，按配置放行: {}", e);
+            Ok(None)
+        }
+        Err(e) => {
+            error!("登录风险评估失败，拒绝登录: {}", e);
+            Err(Error::Internal("登录安全检查失败，请稍后重试".to_string()))
+        }
+    }
+}
+
+/// 处理登录二次验证请求，验证通过后签发令牌
+pub async fn verify_login(
+    user_client: Option<axum::extract::Extension<Arc<UserServiceGrpcClient>>>,
+    headers: HeaderMap,
+    Json(verify_req): Json<VerifyLoginRequest>,
+) -> Result<impl IntoResponse, Error> {
+    debug!("登录二次验证请求：{}", verify_req.login_id);
+
+    let client = get_user_client(user_client)?;
+
+    let tenant_id = verify_req.tenant_id.map(|id| id.to_string());
+    let response = match with_tenant(
+        tenant_id,
+        client.verify_login_challenge(&verify_req.login_id, &verify_req.code),
+    )
+    .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            if let Some(response) = maintenance_response(&e) {
+                warn!("用户服务维护中，暂时无法完成二次验证: {}", e);
+                return Ok(response);
+            }
+            warn!("登录二次验证失败: {}", e);
+            return Err(Error::Authentication("验证码不正确或已过期".to_string()));
+        }
+    };
+
+    let user = response
+        .user
+        .ok_or_else(|| Error::Internal("用户数据为空".to_string()))?;
+    let username = user.username.clone();
+
+    let login_response = issue_tokens(user).await?;
+
+    info!("用户 {} 通过二次验证登录成功", username);
+
+    Ok(token_response(&headers, login_response).await)
+}
+
+/// 登出请求
+#[derive(Debug, Default, Deserialize)]
+pub struct LogoutRequest {
+    /// 刷新令牌，一并吊销
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// 是否登出所有设备
+    #[serde(default)]
+    pub all_sessions: bool,
+}
+
+/// 处理登出请求，吊销当前访问令牌
+pub async fn logout(
+    auth_client: Option<axum::extract::Extension<Arc<AuthServiceGrpcClient>>>,
+    headers: HeaderMap,
+    body: Option<Json<LogoutRequest>>,
+) -> Result<impl IntoResponse, Error> {
+    let auth_client = match auth_client {
+        Some(axum::extract::Extension(client)) => client,
+        None => {
+            error!("未找到AuthServiceGrpcClient扩展");
+            return Err(Error::Internal(
+                "未找到AuthServiceGrpcClient扩展".to_string(),
+            ));
+        }
+    };
+    let mut logout_req = body.map(|Json(req)| req).unwrap_or_default();
+
+    // 从请求头中提取访问令牌，Web端从会话Cookie中提取
+    let (token, clear_cookies) = {
+        let config = CONFIG.read().await;
+        let jwt_config = &config.auth.jwt;
+        let cookie_session = &config.auth.cookie_session;
+        let bearer = headers
+            .get(jwt_config.header_name.as_str())
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix(jwt_config.header_prefix.as_str()))
+            .map(|token| token.to_string());
+        let cookie_token = cookie_session
+            .enabled
+            .then(|| session::get_cookie(&headers, &cookie_session.access_cookie))
+            .flatten();
+
+        let clear_cookies = if cookie_token.is_some() {
+            if logout_req.refresh_token.is_none() {
+                logout_req.refresh_token =
+                    session::get_cookie(&headers, &cookie_session.refresh_cookie);
+            }
+            cookie_session.clear_cookies()
+        } else {
+            Vec::new()
+        };
+        let token = bearer.or(cookie_token).ok_or(Error::Unauthorized)?;
+        (token, clear_cookies)
+    };
+
+    auth_client
+        .revoke(&token, logout_req.all_sessions)
+        .await
+        .map_err(|e| {
+            error!("吊销访问令牌失败: {}", e);
+            Error::Internal(format!("登出失败: {}", e))
+        })?;
+
+    if let Some(refresh_token) = logout_req.refresh_token {
+        if let Err(e) = auth_client.revoke(&refresh_token, false).await {
+            warn!("吊销刷新令牌失败: {}", e);
+        }
+    }
+
+    info!("用户登出成功");
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    for cookie in clear_cookies {
+        response.headers_mut().append(SET_COOKIE, cookie);
+    }
+    Ok(response)
+}
+
+/// 获取用户服务客户端扩展
+fn get_user_client(
+    user_client: Option<axum::extract::Extension<Arc<UserServiceGrpcClient>>>,
+) -> Result<Arc<UserServiceGrpcClient>, Error> {
+    match user_client {
+        Some(axum::extract::Extension(client)) => Ok(client),
+        None => {
+            error!("未找到UserServiceGrpcClient扩展");
+            Err(Error::Internal(
+                "未找到UserServiceGrpcClient扩展".to_string(),
+            ))
+        }
+    }
+}
+
+/// 为已通过认证的用户签发访问令牌和刷新令牌
+async fn issue_tokens(user: User) -> Result<LoginResponse, Error> {
     // 读取JWT配置
     let config = CONFIG.read().await;
     let jwt_config = &config.auth.jwt;
@@ -152,32 +408,143 @@ pub async fn login(
     };
 
     // 构建登录响应
-    let login_response = LoginResponse {
+    Ok(LoginResponse {
         access_token,
         refresh_token,
         token_type: "Bearer".to_string(),
         expires_in: jwt_config.expiry_seconds,
+        csrf_token: None,
         user_info,
+    })
+}
+
+/// 返回签发的令牌，Web端的令牌通过HttpOnly Cookie下发，不出现在响应体中
+async fn token_response(headers: &HeaderMap, mut login_response: LoginResponse) -> Response {
+    let cookies = {
+        let config = CONFIG.read().await;
+        let cookie_session = &config.auth.cookie_session;
+        if !cookie_session.is_cookie_client(headers) {
+            return (StatusCode::OK, Json(login_response)).into_response();
+        }
+
+        let csrf_token = session::generate_csrf_token();
+        let cookies = cookie_session.session_cookies(
+            &login_response.access_token,
+            config.auth.jwt.expiry_seconds,
+            &login_response.refresh_token,
+            config.auth.jwt.refresh_expiry_seconds,
+            &csrf_token,
+        );
+        login_response.access_token.clear();
+        login_response.refresh_token.clear();
+        login_response.token_type = "Cookie".to_string();
+        login_response.csrf_token = Some(csrf_token);
+        cookies
     };
 
-    info!("用户 {} 登录成功", login_req.username);
+    let mut response = (StatusCode::OK, Json(login_response)).into_response();
+    for cookie in cookies {
+        response.headers_mut().append(SET_COOKIE, cookie);
+    }
+    response
+}
 
-    // 返回响应
-    Ok((StatusCode::OK, Json(login_response)))
+/// OpenID Connect 风格的用户信息
+///
+/// 字段遵循 OIDC UserInfo 标准声明，不可用的声明不返回
+#[derive(Debug, Serialize)]
+pub struct OidcUserInfo {
+    /// 用户ID
+    pub sub: String,
+    /// 用户名
+    pub preferred_username: String,
+    /// 展示名称，未设置昵称时使用用户名
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<String>,
+    /// 资料最后更新时间（秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<i64>,
+    /// 租户ID
+    pub tenant_id: i64,
+    /// 租户名称
+    pub tenant_name: String,
+}
+
+/// 用户信息端点，用户身份只取自访问令牌
+pub async fn userinfo(
+    user_client: Option<axum::extract::Extension<Arc<UserServiceGrpcClient>>>,
+    user_info: Option<axum::extract::Extension<jwt::UserInfo>>,
+) -> Result<impl IntoResponse, Error> {
+    // IP白名单放行的请求没有令牌，无法确定当前用户
+    let axum::extract::Extension(user_info) = user_info.ok_or(Error::Unauthorized)?;
+    let client = get_user_client(user_client)?;
+
+    let response = client
+        .get_user(&user_info.user_id.to_string())
+        .await
+        .map_err(|e| {
+            error!("获取用户信息失败: {}", e);
+            Error::Internal(format!("获取用户信息失败: {}", e))
+        })?;
+    let user = response
+        .user
+        .ok_or_else(|| Error::NotFound("用户不存在".to_string()))?;
+
+    let non_empty = |value: String| if value.is_empty() { None } else { Some(value) };
+    let userinfo = OidcUserInfo {
+        sub: user.id,
+        name: user
+            .nickname
+            .clone()
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| user.username.clone()),
+        preferred_username: user.username,
+        nickname: user.nickname,
+        picture: user.avatar_url.or(user.head_image),
+        email: non_empty(user.email),
+        phone_number: non_empty(user.phone),
+        updated_at: user.updated_at.map(|t| t.seconds),
+        tenant_id: user_info.tenant_id,
+        tenant_name: user_info.tenant_name,
+    };
+
+    Ok((StatusCode::OK, Json(userinfo)))
 }
 
 /// 处理令牌刷新请求
 pub async fn refresh_token(
-    Json(refresh_req): Json<RefreshTokenRequest>,
+    auth_client: Option<axum::extract::Extension<Arc<AuthServiceGrpcClient>>>,
+    headers: HeaderMap,
+    body: Option<Json<RefreshTokenRequest>>,
 ) -> Result<impl IntoResponse, Error> {
     debug!("刷新令牌请求");
+    let refresh_req = body.map(|Json(req)| req).unwrap_or_default();
 
     // 读取JWT配置
     let config = CONFIG.read().await;
     let jwt_config = &config.auth.jwt;
 
-    // 验证刷新令牌
-    let user_info = jwt::verify_token(refresh_req.refresh_token, jwt_config).await?;
+    // Web端的刷新令牌从会话Cookie中读取
+    let refresh_token = if refresh_req.refresh_token.is_empty()
+        && config.auth.cookie_session.enabled
+    {
+        session::get_cookie(&headers, &config.auth.cookie_session.refresh_cookie)
+            .ok_or(Error::Unauthorized)?
+    } else {
+        refresh_req.refresh_token
+    };
+
+    // 验证刷新令牌，已吊销的刷新令牌不能再换取新令牌
+    let user_info = jwt::verify_token(refresh_token.clone(), jwt_config).await?;
+    let auth_client = auth_client.map(|axum::extract::Extension(client)| client);
+    auth::ensure_not_revoked(auth_client.as_deref(), &refresh_token).await?;
 
     // 构建额外信息
     let extra = user_info.extra.clone();
@@ -221,11 +588,41 @@ pub async fn refresh_token(
         refresh_token,
         token_type: "Bearer".to_string(),
         expires_in: jwt_config.expiry_seconds,
+        csrf_token: None,
         user_info: user_info_resp,
     };
+    drop(config);
 
     info!("用户 {} 刷新令牌成功", username);
 
     // 返回响应
-    Ok((StatusCode::OK, Json(refresh_response)))
+    Ok(token_response(&headers, refresh_response).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluation(require_verification: bool) -> Result<EvaluateLoginResponse, Error> {
+        Ok(EvaluateLoginResponse {
+            login_id: "login-1".to_string(),
+            risk_score: 70,
+            require_verification,
+            reasons: vec!["new_device".to_string()],
+        })
+    }
+
+    #[test]
+    fn test_login_challenge() {
+        let challenge = login_challenge(evaluation(true), false).unwrap().unwrap();
+        assert_eq!(challenge.login_id, "login-1");
+        assert!(login_challenge(evaluation(false), false).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_login_challenge_on_evaluation_error() {
+        let failed = || Err(Error::Internal("用户服务不可用".to_string()));
+        assert!(login_challenge(failed(), false).is_err());
+        assert!(login_challenge(failed(), true).unwrap().is_none());
+    }
 }
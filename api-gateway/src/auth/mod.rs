@@ -1,12 +1,17 @@
 pub mod jwt;
 pub mod middleware;
 pub mod controller;
+pub mod session;
 
 use crate::config::CONFIG;
-use axum::http::Request;
+use axum::http::{HeaderMap, Request};
 use axum::middleware::Next;
 use axum::response::Response;
 use common::error::Error;
+use common::grpc_client::AuthServiceGrpcClient;
+use common::proto::auth::IntrospectTokenResponse;
+use std::sync::Arc;
+use tracing::{debug, error};
 
 /// 统一认证入口
 pub async fn authenticate(
@@ -36,39 +41,118 @@ pub async fn authenticate(
         }
     }
 
-    // 获取JWT token并验证
+    // 获取JWT token并验证，没有认证头时使用Web端的会话Cookie（CSRF已由中间件校验）
     let jwt_config = &config.auth.jwt;
-    let token =
-        match jwt::extract_token(&request, &jwt_config.header_name, &jwt_config.header_prefix) {
-            Some(token) => token,
-            None => return Err(Error::Unauthorized),
-        };
+    let cookie_session = &config.auth.cookie_session;
+    let token = jwt::extract_token(&request, &jwt_config.header_name, &jwt_config.header_prefix)
+        .or_else(|| {
+            cookie_session
+                .enabled
+                .then(|| session::get_cookie(request.headers(), &cookie_session.access_cookie))
+                .flatten()
+        });
+    let token = match token {
+        Some(token) => token,
+        None => return Err(Error::Unauthorized),
+    };
 
-    // 解析和验证token
-    let user_info = match jwt::verify_token(token, jwt_config).await {
+    // 解析和验证token，签名有效的令牌还需确认未被吊销
+    let user_info = match jwt::verify_token(token.clone(), jwt_config).await {
         Ok(info) => info,
         Err(err) => return Err(err),
     };
+    let auth_client = request.extensions().get::<Arc<AuthServiceGrpcClient>>();
+    ensure_not_revoked(auth_client.map(Arc::as_ref), &token).await?;
 
     // 添加用户信息到请求中
     let mut request = request;
-    request.extensions_mut().insert(user_info);
+    request.extensions_mut().insert(user_info.clone());
+
+    // 同时带回到响应中，供外层的访问日志中间件识别用户
+    let mut response = next.run(request).await;
+    response.extensions_mut().insert(user_info);
+    Ok(response)
+}
 
-    Ok(next.run(request).await)
+/// 确认令牌未被吊销
+///
+/// 登出或修改密码后吊销的令牌在过期前仍能通过签名校验，需要向令牌服务自省；
+/// 令牌服务不可用时拒绝请求
+pub(crate) async fn ensure_not_revoked(
+    auth_client: Option<&AuthServiceGrpcClient>,
+    token: &str,
+) -> Result<(), Error> {
+    let auth_client =
+        auth_client.ok_or_else(|| Error::Internal("令牌服务客户端未初始化".to_string()))?;
+    let response = auth_client.introspect(token).await.map_err(|e| {
+        error!("令牌自省失败: {}", e);
+        Error::Internal("令牌服务不可用".to_string())
+    })?;
+    check_introspection(&response)
+}
+
+/// 按自省结果判断令牌是否可用
+fn check_introspection(response: &IntrospectTokenResponse) -> Result<(), Error> {
+    if response.active {
+        return Ok(());
+    }
+    debug!("令牌不可用: {}", response.reason);
+    match response.reason.as_str() {
+        "expired" => Err(Error::TokenExpired),
+        _ => Err(Error::InvalidToken),
+    }
 }
 
 /// 从请求中获取客户端IP
 fn get_client_ip<B>(request: &Request<B>) -> Option<String> {
-    request
-        .headers()
+    client_ip_from_headers(request.headers())
+}
+
+/// 从请求头中获取客户端IP
+pub(crate) fn client_ip_from_headers(headers: &HeaderMap) -> Option<String> {
+    headers
         .get("X-Forwarded-For")
         .and_then(|value| value.to_str().ok())
         .map(|s| s.split(',').next().unwrap_or("").trim().to_string())
         .or_else(|| {
-            request
-                .headers()
+            headers
                 .get("X-Real-IP")
                 .and_then(|value| value.to_str().ok())
                 .map(|s| s.to_string())
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_introspection() {
+        let active = IntrospectTokenResponse {
+            active: true,
+            user_id: "1001".to_string(),
+            ..Default::default()
+        };
+        assert!(check_introspection(&active).is_ok());
+
+        let revoked = IntrospectTokenResponse {
+            active: false,
+            reason: "revoked".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_introspection(&revoked),
+            Err(Error::InvalidToken)
+        ));
+
+        let expired = IntrospectTokenResponse {
+            active: false,
+            reason: "expired".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            check_introspection(&expired),
+            Err(Error::TokenExpired)
+        ));
+    }
+}
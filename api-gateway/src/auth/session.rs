@@ -0,0 +1,161 @@
+use axum::http::{header, HeaderMap, HeaderValue};
+use rand::RngCore;
+
+use crate::config::auth_config::{CookieSessionConfig, SameSite};
+
+/// CSRF令牌的随机字节数
+const CSRF_TOKEN_BYTES: usize = 32;
+
+impl CookieSessionConfig {
+    /// 请求的客户端类型是否使用Cookie会话
+    pub fn is_cookie_client(&self, headers: &HeaderMap) -> bool {
+        self.enabled
+            && headers
+                .get(self.client_type_header.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(|client| {
+                    self.clients
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(client.trim()))
+                })
+                .unwrap_or(false)
+    }
+
+    /// 登录或刷新令牌后下发的会话Cookie
+    ///
+    /// CSRF令牌的有效期与刷新令牌一致，每次刷新时一并轮换
+    pub fn session_cookies(
+        &self,
+        access_token: &str,
+        access_max_age: u64,
+        refresh_token: &str,
+        refresh_max_age: u64,
+        csrf_token: &str,
+    ) -> Vec<HeaderValue> {
+        [
+            self.build_cookie(&self.access_cookie, access_token, "/", access_max_age, true),
+            self.build_cookie(
+                &self.refresh_cookie,
+                refresh_token,
+                &self.refresh_cookie_path,
+                refresh_max_age,
+                true,
+            ),
+            self.build_cookie(&self.csrf_cookie, csrf_token, "/", refresh_max_age, false),
+        ]
+        .into_iter()
+        .filter_map(|cookie| HeaderValue::from_str(&cookie).ok())
+        .collect()
+    }
+
+    /// 登出时清除会话Cookie
+    pub fn clear_cookies(&self) -> Vec<HeaderValue> {
+        [
+            self.build_cookie(&self.access_cookie, "", "/", 0, true),
+            self.build_cookie(&self.refresh_cookie, "", &self.refresh_cookie_path, 0, true),
+            self.build_cookie(&self.csrf_cookie, "", "/", 0, false),
+        ]
+        .into_iter()
+        .filter_map(|cookie| HeaderValue::from_str(&cookie).ok())
+        .collect()
+    }
+
+    fn build_cookie(
+        &self,
+        name: &str,
+        value: &str,
+        path: &str,
+        max_age: u64,
+        http_only: bool,
+    ) -> String {
+        let mut cookie = format!(
+            "{}={}; Path={}; Max-Age={}; SameSite={}",
+            name,
+            value,
+            path,
+            max_age,
+            self.same_site.as_str()
+        );
+        if let Some(domain) = self.domain.as_deref().filter(|d| !d.is_empty()) {
+            cookie.push_str("; Domain=");
+            cookie.push_str(domain);
+        }
+        // 浏览器要求SameSite=None的Cookie必须带Secure
+        if self.secure || self.same_site == SameSite::None {
+            cookie.push_str("; Secure");
+        }
+        if http_only {
+            cookie.push_str("; HttpOnly");
+        }
+        cookie
+    }
+}
+
+/// 从Cookie请求头中读取指定的Cookie
+pub fn get_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// 生成CSRF令牌
+pub fn generate_csrf_token() -> String {
+    let mut bytes = [0u8; CSRF_TOKEN_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// 常量时间比较CSRF令牌
+pub fn csrf_token_matches(cookie: &str, submitted: &str) -> bool {
+    cookie.len() == submitted.len()
+        && cookie
+            .bytes()
+            .zip(submitted.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_session() {
+        let config = CookieSessionConfig {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let mut headers = HeaderMap::new();
+        assert!(!config.is_cookie_client(&headers));
+        headers.insert("X-Client-Type", HeaderValue::from_static("Web"));
+        assert!(config.is_cookie_client(&headers));
+
+        let cookies = config.session_cookies("access", 60, "refresh", 600, "csrf");
+        assert_eq!(cookies.len(), 3);
+        let access = cookies[0].to_str().unwrap();
+        assert!(access.starts_with("im_access_token=access; Path=/; Max-Age=60; SameSite=Lax"));
+        assert!(access.ends_with("; Secure; HttpOnly"));
+        assert!(cookies[1].to_str().unwrap().contains("Path=/api/user"));
+        // CSRF令牌需要前端读取
+        assert!(!cookies[2].to_str().unwrap().contains("HttpOnly"));
+
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("a=1; im_access_token=token; im_csrf_token=abc"),
+        );
+        assert_eq!(get_cookie(&headers, "im_access_token").as_deref(), Some("token"));
+        assert_eq!(get_cookie(&headers, "missing"), None);
+
+        assert!(csrf_token_matches("abc", "abc"));
+        assert!(!csrf_token_matches("abc", "abd"));
+        assert!(!csrf_token_matches("abc", "ab"));
+        assert_eq!(generate_csrf_token().len(), CSRF_TOKEN_BYTES * 2);
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// 用户级API访问日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessLogConfig {
+    /// 是否记录访问日志
+    pub enabled: bool,
+    /// 内存缓冲队列长度，写满后丢弃新日志，不阻塞请求
+    pub buffer_size: usize,
+    /// 每批写入的最大条数
+    pub batch_size: usize,
+    /// 批量写入间隔（毫秒）
+    pub flush_interval_ms: u64,
+    /// 是否从X-Forwarded-For等请求头取客户端IP，仅在网关部署于可信代理之后时开启
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            buffer_size: 10000,
+            batch_size: 500,
+            flush_interval_ms: 2000,
+            trust_forwarded_for: false,
+        }
+    }
+}
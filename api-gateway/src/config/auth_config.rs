@@ -11,6 +11,15 @@ pub struct AuthConfig {
     /// 路径白名单（不需要认证的路径）
     #[serde(default)]
     pub path_whitelist: Vec<String>,
+    /// Web端Cookie会话配置
+    #[serde(default)]
+    pub cookie_session: CookieSessionConfig,
+    /// 登录风险评估失败时是否放行登录
+    ///
+    /// 默认不放行，用户服务无法完成评估或发送验证码时登录失败；开启后评估失败的登录
+    /// 不做二次验证直接签发令牌
+    #[serde(default)]
+    pub login_risk_fail_open: bool,
 }
 
 /// JWT配置
@@ -35,6 +44,75 @@ pub struct JwtConfig {
     pub header_prefix: String,
 }
 
+/// Cookie的SameSite策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SameSite {
+    Strict,
+    #[default]
+    Lax,
+    None,
+}
+
+impl SameSite {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// Web端Cookie会话配置
+///
+/// 请求头声明的客户端类型在 `clients` 中时，登录和刷新令牌时通过HttpOnly Cookie下发令牌，
+/// 不在响应体中返回；携带Cookie会话的写请求需要通过CSRF双重提交校验。
+/// 其他客户端继续使用Bearer令牌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CookieSessionConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 声明客户端类型的请求头
+    pub client_type_header: String,
+    /// 使用Cookie会话的客户端类型
+    pub clients: Vec<String>,
+    /// 访问令牌Cookie名称
+    pub access_cookie: String,
+    /// 刷新令牌Cookie名称
+    pub refresh_cookie: String,
+    /// 刷新令牌Cookie的路径，只在刷新和登出时发送
+    pub refresh_cookie_path: String,
+    /// CSRF令牌Cookie名称，前端可读取
+    pub csrf_cookie: String,
+    /// 提交CSRF令牌的请求头
+    pub csrf_header: String,
+    /// SameSite策略
+    pub same_site: SameSite,
+    /// 是否只通过HTTPS发送，SameSite为None时必须开启
+    pub secure: bool,
+    /// Cookie域名，为空时只对当前域名生效
+    pub domain: Option<String>,
+}
+
+impl Default for CookieSessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            client_type_header: "X-Client-Type".to_string(),
+            clients: vec!["web".to_string()],
+            access_cookie: "im_access_token".to_string(),
+            refresh_cookie: "im_refresh_token".to_string(),
+            refresh_cookie_path: "/api/user".to_string(),
+            csrf_cookie: "im_csrf_token".to_string(),
+            csrf_header: "X-CSRF-Token".to_string(),
+            same_site: SameSite::Lax,
+            secure: true,
+            domain: None,
+        }
+    }
+}
+
 impl Default for AuthConfig {
     fn default() -> Self {
         Self {
@@ -55,6 +133,8 @@ impl Default for AuthConfig {
                 "/api/auth/register".to_string(),
                 "/metrics".to_string(),
             ],
+            cookie_session: CookieSessionConfig::default(),
+            login_risk_fail_open: false,
         }
     }
 }
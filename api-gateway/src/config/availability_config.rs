@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+/// 注册前用户名、手机号可用性检查的防枚举配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvailabilityConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 存放计数的Redis地址
+    pub redis_url: String,
+    /// 受保护的请求路径
+    pub path: String,
+    /// 单个IP在一个窗口内允许的检查次数
+    pub max_requests_per_ip: u64,
+    /// 计数窗口（秒）
+    pub window_secs: u64,
+    /// 响应的最短耗时（毫秒），掩盖数据库查询的耗时差异
+    pub min_response_ms: u64,
+    /// 在最短耗时上追加的随机延迟上限（毫秒）
+    pub jitter_ms: u64,
+    /// 是否信任X-Forwarded-For等请求头中的客户端IP，只应在网关部署于可信代理之后时开启
+    pub trust_forwarded_for: bool,
+}
+
+impl AvailabilityConfig {
+    /// 判断请求是否为可用性检查
+    pub fn is_protected(&self, path: &str) -> bool {
+        self.enabled && path.trim_end_matches('/') == self.path
+    }
+}
+
+impl Default for AvailabilityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            path: "/api/users/availability".to_string(),
+            max_requests_per_ip: 30,
+            window_secs: 600,
+            min_response_ms: 200,
+            jitter_ms: 200,
+            trust_forwarded_for: false,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// 相同GET请求合并配置
+///
+/// 默认关闭，启用后只合并 `path_prefixes` 中明确列出的路径
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoalescingConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 合并的请求路径前缀，为空时不合并任何请求
+    pub path_prefixes: Vec<String>,
+    /// 响应与请求者无关、可以在同一租户的不同用户之间共享的路径前缀，如群组资料；
+    /// 其余路径只合并同一用户的请求
+    pub shared_path_prefixes: Vec<String>,
+    /// 可以共享的响应体上限（字节），超过上限的响应只返回给发起请求的客户端
+    pub max_body_bytes: usize,
+}
+
+impl CoalescingConfig {
+    /// 请求是否需要合并
+    pub fn applies(&self, method: &str, path: &str) -> bool {
+        self.enabled
+            && method.eq_ignore_ascii_case("GET")
+            && self
+                .path_prefixes
+                .iter()
+                .any(|prefix| path.starts_with(prefix))
+    }
+
+    /// 响应是否可以在不同用户之间共享
+    pub fn is_shared(&self, path: &str) -> bool {
+        self.shared_path_prefixes
+            .iter()
+            .any(|prefix| path.starts_with(prefix))
+    }
+}
+
+impl Default for CoalescingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path_prefixes: Vec::new(),
+            shared_path_prefixes: Vec::new(),
+            max_body_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
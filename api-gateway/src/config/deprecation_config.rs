@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 接口弃用配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeprecationConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 已弃用的路由
+    pub routes: Vec<DeprecatedRoute>,
+}
+
+/// 已弃用的路由规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeprecatedRoute {
+    /// 规则ID，作为指标标签
+    pub id: String,
+    /// 请求路径前缀
+    pub path_prefix: String,
+    /// 请求方法限制（如为空则表示全部方法都已弃用）
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// 弃用时间，未配置时只标记为已弃用
+    #[serde(default)]
+    pub deprecated_at: Option<DateTime<Utc>>,
+    /// 下线时间
+    #[serde(default)]
+    pub sunset_at: Option<DateTime<Utc>>,
+    /// 迁移说明或替代接口的文档地址
+    #[serde(default)]
+    pub link: Option<String>,
+    /// 写入响应体 warning 字段的提示
+    #[serde(default)]
+    pub message: Option<String>,
+    /// 超过下线时间后是否拒绝请求
+    #[serde(default)]
+    pub reject_after_sunset: bool,
+}
+
+impl DeprecatedRoute {
+    fn matches(&self, method: &str, path: &str) -> bool {
+        path.starts_with(&self.path_prefix)
+            && (self.methods.is_empty()
+                || self.methods.iter().any(|m| m.eq_ignore_ascii_case(method)))
+    }
+
+    /// 是否已超过下线时间
+    pub fn is_sunset(&self, now: DateTime<Utc>) -> bool {
+        self.sunset_at.map_or(false, |sunset_at| now >= sunset_at)
+    }
+}
+
+impl DeprecationConfig {
+    /// 请求匹配的弃用规则，多条规则匹配时取路径前缀最长的一条
+    pub fn find(&self, method: &str, path: &str) -> Option<&DeprecatedRoute> {
+        if !self.enabled {
+            return None;
+        }
+        self.routes
+            .iter()
+            .filter(|rule| rule.matches(method, path))
+            .max_by_key(|rule| rule.path_prefix.len())
+    }
+}
+
+impl Default for DeprecationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            routes: Vec::new(),
+        }
+    }
+}
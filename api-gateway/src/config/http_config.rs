@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+
+/// HTTP层配置：跨域、请求超时和请求体大小，按环境在各自的网关配置文件中设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// 跨域配置
+    pub cors: CorsConfig,
+    /// 请求超时（秒）
+    pub timeout_secs: u64,
+    /// 请求体大小上限（字节）
+    pub max_body_bytes: usize,
+    /// 按路由覆盖超时和请求体上限
+    pub routes: Vec<HttpRouteOverride>,
+}
+
+/// 按路由覆盖的超时和请求体上限，未配置的项使用全局配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpRouteOverride {
+    /// 请求路径前缀
+    pub path_prefix: String,
+    /// 请求方法限制（如为空则表示全部方法）
+    #[serde(default)]
+    pub methods: Vec<String>,
+    /// 请求超时（秒）
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// 请求体大小上限（字节）
+    #[serde(default)]
+    pub max_body_bytes: Option<usize>,
+}
+
+/// 跨域配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// 允许的来源，`*` 表示任意来源，不能与 allow_credentials 同时使用
+    pub allowed_origins: Vec<String>,
+    /// 允许的请求方法
+    pub allowed_methods: Vec<String>,
+    /// 允许的请求头
+    pub allowed_headers: Vec<String>,
+    /// 是否允许携带Cookie等凭证
+    pub allow_credentials: bool,
+    /// 预检结果的缓存时间（秒）
+    pub max_age_secs: u64,
+}
+
+impl HttpRouteOverride {
+    fn matches(&self, method: &str, path: &str) -> bool {
+        path.starts_with(&self.path_prefix)
+            && (self.methods.is_empty()
+                || self.methods.iter().any(|m| m.eq_ignore_ascii_case(method)))
+    }
+}
+
+impl HttpConfig {
+    /// 请求的超时和请求体上限，多条路由规则匹配时取路径前缀最长的一条
+    pub fn limits(&self, method: &str, path: &str) -> (Duration, usize) {
+        let route = self
+            .routes
+            .iter()
+            .filter(|route| route.matches(method, path))
+            .max_by_key(|route| route.path_prefix.len());
+        let timeout_secs = route
+            .and_then(|route| route.timeout_secs)
+            .unwrap_or(self.timeout_secs);
+        let max_body_bytes = route
+            .and_then(|route| route.max_body_bytes)
+            .unwrap_or(self.max_body_bytes);
+        (Duration::from_secs(timeout_secs), max_body_bytes)
+    }
+
+    /// 校验配置，加载和热更新时校验失败的配置不会生效
+    pub fn validate(&self) -> Result<()> {
+        self.cors.validate()?;
+        if self.timeout_secs == 0 || self.max_body_bytes == 0 {
+            return Err(anyhow!(
+                "http.timeout_secs 和 http.max_body_bytes 必须大于0"
+            ));
+        }
+        for route in &self.routes {
+            if !route.path_prefix.starts_with('/') {
+                return Err(anyhow!(
+                    "http.routes 的路径前缀必须以 / 开头: {}",
+                    route.path_prefix
+                ));
+            }
+            if route.timeout_secs == Some(0) || route.max_body_bytes == Some(0) {
+                return Err(anyhow!(
+                    "http.routes 中 {} 的超时和请求体上限必须大于0",
+                    route.path_prefix
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CorsConfig {
+    /// 是否允许该来源
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin))
+    }
+
+    fn validate(&self) -> Result<()> {
+        for origin in &self.allowed_origins {
+            if origin.contains('*') {
+                if self.allow_credentials {
+                    return Err(anyhow!(
+                        "http.cors.allow_credentials 为 true 时不能使用通配来源: {}",
+                        origin
+                    ));
+                }
+                if origin != "*" {
+                    return Err(anyhow!("http.cors 不支持部分通配的来源: {}", origin));
+                }
+            } else if HeaderValue::from_str(origin).is_err() {
+                return Err(anyhow!("http.cors 的来源无效: {}", origin));
+            }
+        }
+        for method in &self.allowed_methods {
+            Method::from_bytes(method.as_bytes())
+                .map_err(|_| anyhow!("http.cors 的请求方法无效: {}", method))?;
+        }
+        for header in &self.allowed_headers {
+            HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| anyhow!("http.cors 的请求头无效: {}", header))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            cors: CorsConfig::default(),
+            timeout_secs: 30,
+            max_body_bytes: 10 * 1024 * 1024,
+            routes: Vec::new(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: [
+                "http://localhost:3000",
+                "http://127.0.0.1:3000",
+                "http://localhost:5173",
+                "http://127.0.0.1:5173",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allowed_methods: ["GET", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"]
+                .map(String::from)
+                .to_vec(),
+            allowed_headers: [
+                "content-type",
+                "authorization",
+                "accept",
+                "origin",
+                "user-agent",
+                "x-client-type",
+                "x-csrf-token",
+                "x-client-version",
+                "x-client-platform",
+            ]
+            .map(String::from)
+            .to_vec(),
+            allow_credentials: true,
+            max_age_secs: 3600,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path_prefix: &str) -> HttpRouteOverride {
+        HttpRouteOverride {
+            path_prefix: path_prefix.to_string(),
+            methods: vec![],
+            timeout_secs: None,
+            max_body_bytes: None,
+        }
+    }
+
+    #[test]
+    fn test_route_overrides() {
+        let mut config = HttpConfig::default();
+        let mut upload = route("/api/files");
+        upload.methods = vec!["POST".to_string()];
+        upload.timeout_secs = Some(120);
+        upload.max_body_bytes = Some(100 * 1024 * 1024);
+        config.routes.push(upload);
+        let mut avatar = route("/api/files/avatar");
+        avatar.max_body_bytes = Some(2 * 1024 * 1024);
+        config.routes.push(avatar);
+
+        assert_eq!(
+            config.limits("POST", "/api/files/upload"),
+            (Duration::from_secs(120), 100 * 1024 * 1024)
+        );
+        // 最长前缀的规则只覆盖配置了的项
+        assert_eq!(
+            config.limits("POST", "/api/files/avatar"),
+            (Duration::from_secs(30), 2 * 1024 * 1024)
+        );
+        assert_eq!(
+            config.limits("GET", "/api/files/upload"),
+            (Duration::from_secs(30), 10 * 1024 * 1024)
+        );
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_wildcard_origin_with_credentials() {
+        let mut config = HttpConfig::default();
+        assert!(config.validate().is_ok());
+
+        config.cors.allowed_origins.push("*".to_string());
+        assert!(config.validate().is_err());
+
+        config.cors.allow_credentials = false;
+        assert!(config.validate().is_ok());
+        assert!(config.cors.allows_origin("https://any.example.com"));
+
+        config.cors.allowed_origins = vec!["https://*.example.com".to_string()];
+        assert!(config.validate().is_err());
+    }
+}
@@ -1,8 +1,16 @@
+pub mod access_log_config;
 pub mod auth_config;
+pub mod availability_config;
+pub mod coalescing_config;
+pub mod deprecation_config;
+pub mod http_config;
 pub mod rate_limit_config;
+pub mod replay_config;
+pub mod request_log_config;
 pub mod routes_config;
 
 use anyhow::{anyhow, Result};
+use common::config::ClientVersionConfig;
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -11,8 +19,15 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{error, info};
 
+use self::access_log_config::AccessLogConfig;
 use self::auth_config::AuthConfig;
+use self::availability_config::AvailabilityConfig;
+use self::coalescing_config::CoalescingConfig;
+use self::deprecation_config::DeprecationConfig;
+use self::http_config::HttpConfig;
 use self::rate_limit_config::RateLimitConfig;
+use self::replay_config::ReplayConfig;
+use self::request_log_config::RequestLogConfig;
 use self::routes_config::RoutesConfig;
 
 /// 网关配置
@@ -24,6 +39,30 @@ pub struct GatewayConfig {
     pub rate_limit: RateLimitConfig,
     /// 认证配置
     pub auth: AuthConfig,
+    /// 防重放配置
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    /// 用户名、手机号可用性检查的防枚举配置
+    #[serde(default)]
+    pub availability: AvailabilityConfig,
+    /// 用户级API访问日志配置
+    #[serde(default)]
+    pub access_log: AccessLogConfig,
+    /// 请求日志和脱敏配置
+    #[serde(default)]
+    pub request_log: RequestLogConfig,
+    /// 接口弃用配置
+    #[serde(default)]
+    pub deprecation: DeprecationConfig,
+    /// 客户端最低版本和强制升级配置
+    #[serde(default)]
+    pub client_version: ClientVersionConfig,
+    /// 相同GET请求合并配置
+    #[serde(default)]
+    pub coalescing: CoalescingConfig,
+    /// 跨域、请求超时和请求体大小配置
+    #[serde(default)]
+    pub http: HttpConfig,
     /// 服务发现配置
     pub consul_url: String,
     /// 服务刷新间隔
@@ -75,6 +114,14 @@ impl Default for GatewayConfig {
             routes: RoutesConfig::default(),
             rate_limit: RateLimitConfig::default(),
             auth: AuthConfig::default(),
+            replay: ReplayConfig::default(),
+            availability: AvailabilityConfig::default(),
+            access_log: AccessLogConfig::default(),
+            request_log: RequestLogConfig::default(),
+            deprecation: DeprecationConfig::default(),
+            client_version: ClientVersionConfig::default(),
+            coalescing: CoalescingConfig::default(),
+            http: HttpConfig::default(),
             consul_url: "http://localhost:8500".to_string(),
             service_refresh_interval: 30,
             metrics_endpoint: "/metrics".to_string(),
@@ -96,6 +143,13 @@ impl Default for GatewayConfig {
     }
 }
 
+impl GatewayConfig {
+    /// 校验配置
+    pub fn validate(&self) -> Result<()> {
+        self.http.validate()
+    }
+}
+
 /// 全局配置管理器
 pub static CONFIG: Lazy<Arc<RwLock<GatewayConfig>>> =
     Lazy::new(|| Arc::new(RwLock::new(GatewayConfig::default())));
@@ -115,6 +169,7 @@ pub async fn load_config(config_path: &str) -> Result<()> {
     } else {
         return Err(anyhow!("不支持的配置文件格式"));
     };
+    config.validate()?;
 
     // 更新全局配置
     let mut global_config = CONFIG.write().await;
@@ -170,6 +225,9 @@ fn setup_config_watcher(config_path: &Path) -> Result<()> {
                                             Err(anyhow!("不支持的配置文件格式"))
                                         };
 
+                                    // 校验失败时保留原配置
+                                    let config_result = config_result
+                                        .and_then(|config| config.validate().map(|_| config));
                                     match config_result {
                                         Ok(new_config) => {
                                             let mut global_config = CONFIG.write().await;
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 防重放配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    /// 是否启用
+    #[serde(default)]
+    pub enabled: bool,
+    /// 存放nonce的Redis地址
+    pub redis_url: String,
+    /// 允许的时间戳偏差（秒）
+    pub max_skew_secs: i64,
+    /// 客户端ID与签名密钥
+    #[serde(default)]
+    pub clients: HashMap<String, String>,
+    /// 需要签名校验的路由
+    #[serde(default)]
+    pub routes: Vec<ReplayRouteRule>,
+}
+
+/// 需要签名校验的路由规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayRouteRule {
+    /// 请求路径前缀
+    pub path_prefix: String,
+    /// 请求方法限制（如为空则表示全部方法都需要校验）
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+impl ReplayConfig {
+    /// 判断请求是否需要签名校验
+    pub fn is_protected(&self, method: &str, path: &str) -> bool {
+        self.enabled
+            && self.routes.iter().any(|rule| {
+                path.starts_with(&rule.path_prefix)
+                    && (rule.methods.is_empty()
+                        || rule.methods.iter().any(|m| m.eq_ignore_ascii_case(method)))
+            })
+    }
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            redis_url: "redis://127.0.0.1:6379".to_string(),
+            max_skew_secs: 300,
+            clients: HashMap::new(),
+            routes: vec![
+                // 默认规则 - 找回密码
+                ReplayRouteRule {
+                    path_prefix: "/api/users/forgetPassword".to_string(),
+                    methods: vec!["POST".to_string()],
+                },
+            ],
+        }
+    }
+}
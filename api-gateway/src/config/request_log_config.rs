@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// 请求日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLogConfig {
+    /// 是否在debug级别记录JSON请求体，记录前按 `redact_fields` 脱敏
+    pub log_payloads: bool,
+    /// 记录请求体的大小上限（字节），超过上限或未声明长度的请求体不记录
+    pub max_payload_bytes: usize,
+    /// 需要脱敏的字段名，请求体、查询参数中名称以其结尾的字段都会被替换，
+    /// 比较时忽略大小写、下划线和连字符，如 `password` 同时匹配 `newPassword` 和 `old_password`
+    pub redact_fields: Vec<String>,
+}
+
+impl Default for RequestLogConfig {
+    fn default() -> Self {
+        Self {
+            log_payloads: false,
+            max_payload_bytes: 4096,
+            redact_fields: [
+                "password",
+                "secret",
+                "token",
+                "code",
+                "captcha",
+                "otp",
+                "authorization",
+                "cookie",
+            ]
+            .iter()
+            .map(|field| field.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl RequestLogConfig {
+    /// 字段是否需要脱敏
+    pub fn is_sensitive(&self, field: &str) -> bool {
+        let field = normalize(field);
+        !field.is_empty()
+            && self.redact_fields.iter().any(|redacted| {
+                let redacted = normalize(redacted);
+                !redacted.is_empty() && field.ends_with(&redacted)
+            })
+    }
+}
+
+fn normalize(field: &str) -> String {
+    field
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
@@ -43,6 +43,20 @@ pub enum ServiceType {
     Group,
     /// 聊天服务
     Chat,
+    /// 异步任务服务
+    Job,
+    /// 消息保留策略服务
+    Retention,
+    /// 消息请求服务
+    MessageRequest,
+    /// 会话草稿服务
+    Draft,
+    /// 事务消息服务
+    Transaction,
+    /// 访问日志服务
+    AccessLog,
+    /// 历史消息服务
+    History,
     /// 静态资源服务
     Static,
     /// 自定义HTTP服务
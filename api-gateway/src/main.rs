@@ -1,17 +1,10 @@
-use axum::http::HeaderValue;
-use axum::{
-    http::{self, Method},
-    Router,
-};
+use axum::Router;
 use axum_server::{self, Handle};
 use clap::Parser;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
-use tower_http::cors::CorsLayer;
-use tower_http::limit::RequestBodyLimitLayer;
-use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 // 直接使用tracing宏
 use tracing::{error, info, warn};
@@ -21,6 +14,7 @@ mod circuit_breaker;
 mod config;
 mod metrics;
 mod middleware;
+mod ops;
 pub mod proxy;
 mod rate_limit;
 mod router;
@@ -31,7 +25,7 @@ mod api_doc;
 pub use common::grpc_client::user_client::UserServiceGrpcClient;
 pub use common::grpc_client::friend_client::FriendServiceGrpcClient;
 pub use common::grpc_client::group_client::GroupServiceGrpcClient;
-use common::service_registry::ServiceRegistry;
+use common::service_registry::{ServiceRegister, ServiceRegistry};
 use config::CONFIG;
 
 #[derive(Parser, Debug)]
@@ -50,8 +44,15 @@ struct Args {
     port: Option<u16>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
+    // 运行时参数与其他服务共用系统配置，读取失败时使用默认值
+    let tuning = common::config::AppConfig::new()
+        .map(|config| config.runtime.for_service("api-gateway"))
+        .unwrap_or_default();
+    common::runtime::build("api-gateway", &tuning)?.block_on(run())
+}
+
+async fn run() -> anyhow::Result<()> {
     // 初始化命令行参数
     let args = Args::parse();
 
@@ -112,9 +113,9 @@ async fn main() -> anyhow::Result<()> {
     info!("======================================================");
 
     // 注册到 Consul
-    let service_registry = ServiceRegistry::from_env();
+    let service_registry = ServiceRegistry::from_env().shared();
     let service_id = service_registry
-        .register_service(
+        .register(
             "api-gateway",
             &host,
             port as u32,
@@ -147,7 +148,7 @@ async fn main() -> anyhow::Result<()> {
     // 启动服务
     if let Err(err) = axum_server::bind(addr)
         .handle(handle)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
     {
         error!("服务器错误: {}", err);
@@ -172,6 +173,37 @@ async fn configure_middleware(app: Router, _service_proxy: proxy::ServiceProxy)
     // 添加链路追踪中间件
     let app = app.layer(TraceLayer::new_for_http());
     
+    // 添加防重放校验中间件，只对配置中的敏感路由生效
+    let replay_guard = middleware::ReplayGuard::new(&CONFIG.read().await.replay).await;
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        replay_guard,
+        middleware::replay_guard,
+    ));
+
+    // 添加可用性检查防枚举中间件，只对用户名、手机号可用性检查生效
+    let availability_guard =
+        middleware::AvailabilityGuard::new(&CONFIG.read().await.availability).await;
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        availability_guard,
+        middleware::availability_guard,
+    ));
+
+    // 添加客户端版本中间件，统计版本分布，低于最低版本的请求要求强制升级
+    let app = app.layer(axum::middleware::from_fn(middleware::client_version_guard));
+
+    // 添加接口弃用中间件，只对配置中已弃用的路由生效
+    let app = app.layer(axum::middleware::from_fn(middleware::deprecation_notice));
+
+    // 添加CSRF校验中间件，只对使用会话Cookie认证的写请求生效
+    let app = app.layer(axum::middleware::from_fn(middleware::csrf_guard));
+
+    // 添加用户级API访问日志中间件，只记录认证通过的请求
+    let access_logger = middleware::AccessLogger::new(&CONFIG.read().await.access_log);
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        access_logger,
+        middleware::access_log,
+    ));
+
     // 添加请求路径日志中间件
     let app = app.layer(middleware::RequestLoggerLayer);
 
@@ -181,43 +213,16 @@ async fn configure_middleware(app: Router, _service_proxy: proxy::ServiceProxy)
     // 添加指标中间件
     let app = app.layer(metrics::MetricsLayer);
 
-    // 添加CORS中间件
-    let cors = CorsLayer::new()
-        .allow_origin([
-            "http://localhost:3000".parse::<HeaderValue>().unwrap(),
-            "http://127.0.0.1:3000".parse::<HeaderValue>().unwrap(),
-            "http://localhost:5173".parse::<HeaderValue>().unwrap(),
-            "http://127.0.0.1:5173".parse::<HeaderValue>().unwrap(),
-        ])
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-            Method::PATCH,
-        ])
-        .allow_headers([
-            http::header::CONTENT_TYPE,
-            http::header::AUTHORIZATION,
-            http::header::ACCEPT,
-            http::header::ORIGIN,
-            http::header::USER_AGENT,
-        ])
-        .allow_credentials(true)
-        .max_age(Duration::from_secs(3600));
-
-    // 添加请求体大小限制和超时
-    app.layer(cors)
-        .layer(TimeoutLayer::new(Duration::from_secs(30)))
-        .layer(RequestBodyLimitLayer::new(10 * 1024 * 1024))
+    // 添加CORS、请求超时和请求体大小限制中间件，按网关配置生效并支持热更新
+    app.layer(axum::middleware::from_fn(middleware::cors))
+        .layer(axum::middleware::from_fn(middleware::request_limits))
 }
 
 /// 优雅关闭信号处理
 async fn shutdown_signal(
     handle: Handle,
     service_proxy: proxy::ServiceProxy,
-    service_registry: ServiceRegistry,
+    service_registry: Arc<dyn ServiceRegister>,
 ) {
     let ctrl_c = async {
         signal::ctrl_c().await.expect("无法安装Ctrl+C处理器");
@@ -242,7 +247,7 @@ async fn shutdown_signal(
     info!("接收到关闭信号，准备优雅关闭...");
 
     // 从 Consul 注销服务
-    match service_registry.deregister_service().await {
+    match service_registry.deregister().await {
         Ok(_) => info!("已从Consul注销服务"),
         Err(e) => error!("从Consul注销服务失败: {}", e),
     }
@@ -0,0 +1,169 @@
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use common::grpc_client::AccessLogGrpcClient;
+use common::proto::access_log::AccessLog;
+use metrics::counter;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::auth::client_ip_from_headers;
+use crate::auth::jwt::UserInfo;
+use crate::config::access_log_config::AccessLogConfig;
+use crate::proxy::services::common::datetime_to_timestamp;
+
+/// 用户级API访问日志
+///
+/// 请求完成后把已认证用户的访问记录放入内存队列，由后台任务批量写入用户服务；
+/// 队列写满或写入失败时丢弃日志，不影响请求本身
+#[derive(Clone)]
+pub struct AccessLogger {
+    tx: Option<mpsc::Sender<AccessLog>>,
+    trust_forwarded_for: bool,
+}
+
+impl AccessLogger {
+    /// 创建访问日志记录器，启用时启动后台批量写入任务
+    pub fn new(config: &AccessLogConfig) -> Self {
+        if !config.enabled {
+            return Self {
+                tx: None,
+                trust_forwarded_for: false,
+            };
+        }
+
+        let (tx, rx) = mpsc::channel(config.buffer_size.max(1));
+        tokio::spawn(flush_loop(
+            rx,
+            AccessLogGrpcClient::from_env(),
+            config.batch_size.max(1),
+            Duration::from_millis(config.flush_interval_ms.max(100)),
+        ));
+        info!(
+            "用户级API访问日志已启用，缓冲队列长度: {}",
+            config.buffer_size
+        );
+
+        Self {
+            tx: Some(tx),
+            trust_forwarded_for: config.trust_forwarded_for,
+        }
+    }
+
+    fn record(&self, log: AccessLog) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        if tx.try_send(log).is_err() {
+            counter!("gateway.access_log.dropped").increment(1);
+        }
+    }
+}
+
+/// 访问日志中间件
+pub async fn access_log(
+    State(logger): State<AccessLogger>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if logger.tx.is_none() {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let accessed_at = Utc::now();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let headers = request.headers();
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let request_id = header_value("x-request-id");
+    let user_agent = header_value("user-agent");
+    let ip = logger
+        .trust_forwarded_for
+        .then(|| client_ip_from_headers(headers))
+        .flatten()
+        .filter(|ip| !ip.is_empty())
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|info| info.0.ip().to_string())
+        })
+        .unwrap_or_default();
+
+    let response = next.run(request).await;
+
+    // 只记录认证通过的请求，用户信息由认证中间件放入响应扩展
+    if let Some(user) = response.extensions().get::<UserInfo>() {
+        logger.record(AccessLog {
+            id: 0,
+            user_id: user.user_id.to_string(),
+            tenant_id: user.tenant_id.to_string(),
+            method,
+            path,
+            status: response.status().as_u16() as i32,
+            latency_ms: started.elapsed().as_millis().min(i32::MAX as u128) as i32,
+            ip,
+            user_agent,
+            request_id,
+            accessed_at: Some(datetime_to_timestamp(accessed_at)),
+        });
+    }
+
+    response
+}
+
+// 攒够一批或到达写入间隔时写入用户服务
+async fn flush_loop(
+    mut rx: mpsc::Receiver<AccessLog>,
+    client: AccessLogGrpcClient,
+    batch_size: usize,
+    flush_interval: Duration,
+) {
+    let mut buffer = Vec::with_capacity(batch_size);
+    let mut interval = tokio::time::interval(flush_interval);
+
+    loop {
+        tokio::select! {
+            received = rx.recv() => match received {
+                Some(log) => {
+                    buffer.push(log);
+                    if buffer.len() >= batch_size {
+                        flush(&client, &mut buffer).await;
+                    }
+                }
+                None => {
+                    flush(&client, &mut buffer).await;
+                    break;
+                }
+            },
+            _ = interval.tick() => flush(&client, &mut buffer).await,
+        }
+    }
+}
+
+async fn flush(client: &AccessLogGrpcClient, buffer: &mut Vec<AccessLog>) {
+    if buffer.is_empty() {
+        return;
+    }
+    let logs = std::mem::take(buffer);
+    let count = logs.len() as u64;
+    if let Err(e) = client.record_access_logs(logs).await {
+        warn!("写入访问日志失败，丢弃 {} 条: {}", count, e);
+        counter!("gateway.access_log.dropped").increment(count);
+    }
+}
@@ -0,0 +1,173 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use rand::Rng;
+use redis::aio::ConnectionManager;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::auth::client_ip_from_headers;
+use crate::config::availability_config::AvailabilityConfig;
+use crate::config::CONFIG;
+use crate::proxy::services::common::error_response;
+
+/// 检查次数的Redis键前缀
+const COUNTER_KEY_PREFIX: &str = "gateway:availability:ip";
+
+/// 用户名、手机号可用性检查的防枚举保护
+///
+/// 按客户端IP在固定窗口内计数，超过次数的请求直接拒绝；
+/// 放行的请求补齐到最短耗时并追加随机延迟，避免通过响应时间推断账号是否存在
+#[derive(Clone)]
+pub struct AvailabilityGuard {
+    redis: Option<ConnectionManager>,
+}
+
+impl AvailabilityGuard {
+    /// 创建可用性检查保护，未启用时不连接Redis
+    pub async fn new(config: &AvailabilityConfig) -> Self {
+        if !config.enabled {
+            return Self { redis: None };
+        }
+
+        let redis = match redis::Client::open(config.redis_url.as_str()) {
+            Ok(client) => match ConnectionManager::new(client).await {
+                Ok(conn) => Some(conn),
+                Err(e) => {
+                    error!("可用性检查保护连接Redis失败: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                error!("可用性检查保护Redis地址无效: {}", e);
+                None
+            }
+        };
+        info!(
+            "可用性检查保护已启用，每个IP {} 秒内最多 {} 次",
+            config.window_secs, config.max_requests_per_ip
+        );
+
+        Self { redis }
+    }
+
+    /// 累加IP的检查次数，返回窗口内的次数和窗口剩余秒数
+    async fn hit(&self, ip: &str, window_secs: u64) -> Result<(u64, i64), redis::RedisError> {
+        let Some(redis) = &self.redis else {
+            return Err(redis::RedisError::from((
+                redis::ErrorKind::IoError,
+                "Redis连接不可用",
+            )));
+        };
+
+        let key = format!("{}:{}", COUNTER_KEY_PREFIX, ip);
+        let mut conn = redis.clone();
+        let (count, mut ttl): (u64, i64) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .ttl(&key)
+            .query_async(&mut conn)
+            .await?;
+
+        // 窗口内的第一次检查，开始计时
+        if ttl < 0 {
+            ttl = window_secs.max(1) as i64;
+            redis::cmd("EXPIRE")
+                .arg(&key)
+                .arg(ttl)
+                .query_async::<()>(&mut conn)
+                .await?;
+        }
+
+        Ok((count, ttl))
+    }
+}
+
+/// 可用性检查防枚举中间件
+pub async fn availability_guard(
+    State(guard): State<AvailabilityGuard>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let config = {
+        let config = CONFIG.read().await;
+        if !config.availability.is_protected(request.uri().path()) {
+            return next.run(request).await;
+        }
+        config.availability.clone()
+    };
+
+    let started = Instant::now();
+    let forwarded = config
+        .trust_forwarded_for
+        .then(|| client_ip_from_headers(request.headers()))
+        .flatten()
+        .filter(|ip| !ip.is_empty());
+    let ip = forwarded
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|info| info.0.ip().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+
+    match guard.hit(&ip, config.window_secs).await {
+        Ok((count, _)) if count <= config.max_requests_per_ip => {}
+        Ok((_, ttl)) => {
+            warn!("可用性检查过于频繁，IP: {}", ip);
+            let mut response =
+                error_response("请求过于频繁，请稍后重试", StatusCode::TOO_MANY_REQUESTS);
+            if ttl > 0 {
+                response
+                    .headers_mut()
+                    .insert("Retry-After", HeaderValue::from(ttl));
+            }
+            return response;
+        }
+        Err(e) => {
+            error!("记录可用性检查次数失败: {}", e);
+            return error_response("服务暂时不可用", StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+
+    let response = next.run(request).await;
+
+    // 补齐到最短耗时再追加随机延迟，命中与未命中的响应时间分布一致
+    let delay = response_delay(config.min_response_ms, config.jitter_ms, &mut rand::rng());
+    tokio::time::sleep_until(started + delay).await;
+    response
+}
+
+/// 计算本次响应的目标耗时
+fn response_delay(min_ms: u64, jitter_ms: u64, rng: &mut impl Rng) -> Duration {
+    Duration::from_millis(min_ms + rng.random_range(0..=jitter_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_protected_and_delay() {
+        let config = AvailabilityConfig::default();
+        assert!(config.is_protected("/api/users/availability"));
+        assert!(config.is_protected("/api/users/availability/"));
+        assert!(!config.is_protected("/api/users/availabilityX"));
+        assert!(!config.is_protected("/api/users/search"));
+
+        let mut rng = rand::rng();
+        for _ in 0..100 {
+            let delay = response_delay(200, 50, &mut rng);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(250));
+        }
+        assert_eq!(response_delay(100, 0, &mut rng), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,79 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use common::client_version::{self, VersionCheck};
+use metrics::counter;
+use serde_json::json;
+use tracing::debug;
+
+use crate::config::CONFIG;
+use crate::proxy::services::common::error_response_with_data;
+
+/// 客户端版本中间件
+///
+/// 按平台、版本统计请求分布；启用强制升级时，低于所在平台最低版本的请求返回426，
+/// 响应的 data 中携带最低版本和升级地址
+pub async fn client_version_guard(request: Request<Body>, next: Next) -> Response {
+    let checked = {
+        let config = CONFIG.read().await;
+        let config = &config.client_version;
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        (!config.is_exempt(request.uri().path())).then(|| {
+            let platform = client_version::label(header(&config.platform_header).as_deref());
+            let version = header(&config.version_header);
+            let check = config.check(&platform, version.as_deref());
+            (
+                platform,
+                client_version::label(version.as_deref()),
+                check,
+                config.upgrade_url.clone(),
+            )
+        })
+    };
+    let Some((platform, version, check, upgrade_url)) = checked else {
+        return next.run(request).await;
+    };
+
+    let result = match check {
+        VersionCheck::Allowed => "allowed",
+        VersionCheck::UpgradeRequired { .. } => "upgrade_required",
+    };
+    counter!("gateway.client_versions.total",
+        "platform" => platform.clone(),
+        "version" => version.clone(),
+        "result" => result
+    )
+    .increment(1);
+
+    match check {
+        VersionCheck::Allowed => next.run(request).await,
+        VersionCheck::UpgradeRequired { min_version } => {
+            debug!(
+                "客户端版本过低，要求升级: {} {}，平台: {}，版本: {}，最低版本: {}",
+                request.method(),
+                request.uri().path(),
+                platform,
+                version,
+                min_version
+            );
+            error_response_with_data(
+                json!({
+                    "platform": platform,
+                    "min_version": min_version,
+                    "upgrade_url": upgrade_url,
+                }),
+                "客户端版本过低，请升级后重试",
+                StatusCode::UPGRADE_REQUIRED,
+            )
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{header, HeaderMap, Request, StatusCode, Uri},
+    middleware::Next,
+    response::Response,
+};
+use futures::{stream, StreamExt};
+use metrics::counter;
+use parking_lot::Mutex;
+use sha2::{Digest, Sha256};
+use tokio::sync::watch;
+use tracing::debug;
+
+use crate::auth::jwt::UserInfo;
+use crate::config::CONFIG;
+
+/// 客户端传入的租户请求头，参与合并键的计算
+const TENANT_HEADER: &str = "x-tenant-id";
+
+/// 可以共享给等待中请求的响应
+#[derive(Debug)]
+struct SharedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl SharedResponse {
+    fn to_response(&self) -> Response {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response
+    }
+}
+
+/// 合并中的请求的状态
+#[derive(Debug, Clone)]
+enum Flight {
+    /// 等待后端响应
+    Pending,
+    /// 响应可以共享
+    Shared(Arc<SharedResponse>),
+    /// 响应不能共享，等待的请求各自转发
+    Unshared,
+}
+
+/// 相同GET请求合并（singleflight）
+///
+/// 同一时刻对同一资源的GET请求只有第一个转发到后端，其余请求等待并共享它的响应，
+/// 保护后端应对热点资源（如大群资料）的突发请求。合并键由路径、查询参数、请求者、
+/// 认证头和租户头组成，只有配置为共享的路径才会合并同一租户内不同已认证用户的请求。
+/// 发起请求的客户端断开或响应不能共享时，等待的请求各自转发
+#[derive(Clone, Default)]
+pub struct RequestCoalescer {
+    inflight: Arc<Mutex<HashMap<String, watch::Receiver<Flight>>>>,
+}
+
+/// 发起请求结束（包括客户端断开）时移除合并中的请求
+struct FlightGuard {
+    coalescer: RequestCoalescer,
+    key: String,
+}
+
+impl Drop for FlightGuard {
+    fn drop(&mut self) {
+        self.coalescer.inflight.lock().remove(&self.key);
+    }
+}
+
+/// 相同GET请求合并中间件，挂在认证中间件内层，按认证后的用户区分请求者
+pub async fn coalesce_get(
+    State(coalescer): State<RequestCoalescer>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let matched = {
+        let config = CONFIG.read().await;
+        let coalescing = &config.coalescing;
+        let path = request.uri().path();
+        coalescing
+            .applies(request.method().as_str(), path)
+            .then(|| (coalescing.is_shared(path), coalescing.max_body_bytes))
+    };
+    let Some((shared, max_body_bytes)) = matched else {
+        return next.run(request).await;
+    };
+
+    let user_info = request.extensions().get::<UserInfo>();
+    let key = coalescing_key(request.uri(), request.headers(), user_info, shared);
+
+    let sender = {
+        let mut inflight = coalescer.inflight.lock();
+        match inflight.get(&key) {
+            Some(receiver) => Err(receiver.clone()),
+            None => {
+                let (sender, receiver) = watch::channel(Flight::Pending);
+                inflight.insert(key.clone(), receiver);
+                Ok(sender)
+            }
+        }
+    };
+
+    let sender = match sender {
+        Ok(sender) => sender,
+        Err(mut receiver) => {
+            let flight = receiver
+                .wait_for(|flight| !matches!(flight, Flight::Pending))
+                .await
+                .map(|flight| flight.clone());
+            if let Ok(Flight::Shared(response)) = flight {
+                counter!("gateway.coalesced_requests.total", "shared" => shared.to_string())
+                    .increment(1);
+                debug!("合并GET请求: {}", key);
+                return response.to_response();
+            }
+            // 发起请求的客户端已断开或响应不能共享
+            return next.run(request).await;
+        }
+    };
+
+    let _guard = FlightGuard {
+        coalescer: coalescer.clone(),
+        key,
+    };
+    let response = next.run(request).await;
+    let (response, flight) = share(response, max_body_bytes).await;
+    sender.send_replace(flight);
+    response
+}
+
+/// 合并键：共享的路径按租户合并，其余路径按用户和认证头合并，都包含租户头
+///
+/// 认证头只保存摘要，合并键会写入日志；共享的路径只有认证通过后才忽略认证头
+fn coalescing_key(
+    uri: &Uri,
+    headers: &HeaderMap,
+    user_info: Option<&UserInfo>,
+    shared: bool,
+) -> String {
+    let scope = match user_info {
+        Some(info) if shared => format!("tenant:{}", info.tenant_id),
+        Some(info) => format!(
+            "user:{}:{}",
+            info.user_id,
+            header_digest(headers, header::AUTHORIZATION.as_str())
+        ),
+        None => format!(
+            "anonymous:{}",
+            header_digest(headers, header::AUTHORIZATION.as_str())
+        ),
+    };
+    let target = uri
+        .path_and_query()
+        .map_or(uri.path(), |path_and_query| path_and_query.as_str());
+    format!(
+        "{}|{}|{}",
+        scope,
+        header_digest(headers, TENANT_HEADER),
+        target
+    )
+}
+
+/// 请求头所有值的SHA-256摘要，没有该请求头时为空
+fn header_digest(headers: &HeaderMap, name: &str) -> String {
+    let mut values = headers.get_all(name).iter().peekable();
+    if values.peek().is_none() {
+        return String::new();
+    }
+    let mut hasher = Sha256::new();
+    for value in values {
+        hasher.update(value.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// 读取响应体生成共享的响应，设置了Cookie或响应体超过上限时不共享，
+/// 已读取的部分和剩余的响应体照常返回给发起请求的客户端
+async fn share(response: Response, max_body_bytes: usize) -> (Response, Flight) {
+    if response.headers().contains_key(header::SET_COOKIE) {
+        return (response, Flight::Unshared);
+    }
+
+    let (parts, body) = response.into_parts();
+    let mut body = body.into_data_stream();
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.next().await {
+        match chunk {
+            Ok(chunk) => buffer.extend_from_slice(&chunk),
+            Err(e) => {
+                let body = Body::from_stream(stream::iter([Ok(Bytes::from(buffer)), Err(e)]));
+                return (Response::from_parts(parts, body), Flight::Unshared);
+            }
+        }
+        if buffer.len() > max_body_bytes {
+            let head = stream::once(async move { Ok(Bytes::from(buffer)) });
+            let body = Body::from_stream(head.chain(body));
+            return (Response::from_parts(parts, body), Flight::Unshared);
+        }
+    }
+
+    let shared = SharedResponse {
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: Bytes::from(buffer),
+    };
+    let response = Response::from_parts(parts, Body::from(shared.body.clone()));
+    (response, Flight::Shared(Arc::new(shared)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    use crate::config::coalescing_config::CoalescingConfig;
+
+    #[test]
+    fn test_coalescing_key() {
+        let uri: Uri = "/api/groups/g1?lang=en".parse().unwrap();
+        let headers = |token: &str, tenant: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, token.parse().unwrap());
+            headers.insert(TENANT_HEADER, tenant.parse().unwrap());
+            headers
+        };
+        let key = |headers: &HeaderMap, user_info: Option<&UserInfo>, shared: bool| {
+            coalescing_key(&uri, headers, user_info, shared)
+        };
+
+        // 未认证的请求按认证头和租户头区分
+        let a = headers("Bearer a", "1");
+        assert_eq!(
+            key(&a, None, false),
+            key(&headers("Bearer a", "1"), None, false)
+        );
+        assert_ne!(
+            key(&a, None, false),
+            key(&headers("Bearer b", "1"), None, false)
+        );
+        assert_ne!(
+            key(&a, None, false),
+            key(&headers("Bearer a", "2"), None, false)
+        );
+        assert!(!key(&a, None, false).contains("Bearer"));
+
+        let user = |user_id| UserInfo {
+            user_id,
+            username: String::new(),
+            tenant_id: 1,
+            tenant_name: String::new(),
+            extra: Default::default(),
+        };
+        let (u1, u2) = (user(1), user(2));
+        assert_ne!(
+            key(&a, Some(&u1), false),
+            key(&headers("Bearer b", "1"), Some(&u1), false)
+        );
+        // 共享的路径合并同一租户内不同用户的请求
+        assert_eq!(
+            key(&a, Some(&u1), true),
+            key(&headers("Bearer b", "1"), Some(&u2), true)
+        );
+        assert_ne!(
+            key(&a, Some(&u1), true),
+            key(&headers("Bearer a", "2"), Some(&u1), true)
+        );
+    }
+
+    #[test]
+    fn test_applies_requires_allowlist() {
+        let mut config = CoalescingConfig::default();
+        assert!(!config.applies("GET", "/api/groups/g1"));
+        config.enabled = true;
+        assert!(!config.applies("GET", "/api/groups/g1"));
+        config.path_prefixes = vec!["/api/groups".to_string()];
+        assert!(config.applies("GET", "/api/groups/g1"));
+        assert!(!config.applies("POST", "/api/groups/g1"));
+        assert!(!config.applies("GET", "/api/users/u1"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_gets_share_one_call() {
+        CONFIG.write().await.coalescing = CoalescingConfig {
+            enabled: true,
+            path_prefixes: vec!["/api/groups".to_string()],
+            ..Default::default()
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route(
+                "/api/groups/{id}",
+                get(move || {
+                    let counter = counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        "group"
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn_with_state(
+                RequestCoalescer::default(),
+                coalesce_get,
+            ));
+        let request = || Request::get("/api/groups/g1").body(Body::empty()).unwrap();
+
+        let (first, second) = tokio::join!(
+            app.clone().oneshot(request()),
+            app.clone().oneshot(request())
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for response in [first.unwrap(), second.unwrap()] {
+            let body = axum::body::to_bytes(response.into_body(), 1024)
+                .await
+                .unwrap();
+            assert_eq!(body, "group");
+        }
+
+        // 前一个请求结束后不再共享
+        app.oneshot(request()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
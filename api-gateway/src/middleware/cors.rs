@@ -0,0 +1,69 @@
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::config::http_config::CorsConfig;
+use crate::config::CONFIG;
+
+/// 跨域中间件
+///
+/// 每个请求读取当前的跨域配置，配置热更新后立即生效。允许的来源原样写回
+/// Access-Control-Allow-Origin，预检请求直接返回204，不转发到后端
+pub async fn cors(request: Request<Body>, next: Next) -> Response {
+    let Some(origin) = request.headers().get(header::ORIGIN).cloned() else {
+        return next.run(request).await;
+    };
+    let preflight = request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    let cors = {
+        let config = CONFIG.read().await;
+        let allowed = origin
+            .to_str()
+            .map_or(false, |origin| config.http.cors.allows_origin(origin));
+        allowed.then(|| config.http.cors.clone())
+    };
+
+    let mut response = if preflight {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        if let Some(cors) = &cors {
+            insert_preflight_headers(response.headers_mut(), cors);
+        }
+        response
+    } else {
+        next.run(request).await
+    };
+
+    let headers = response.headers_mut();
+    if let Some(cors) = &cors {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        if cors.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+    headers.append(header::VARY, HeaderValue::from_static("origin"));
+    response
+}
+
+/// 设置预检响应的方法、请求头和缓存时间
+fn insert_preflight_headers(headers: &mut HeaderMap, cors: &CorsConfig) {
+    if let Ok(value) = HeaderValue::from_str(&cors.allowed_methods.join(", ")) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&cors.allowed_headers.join(", ")) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+    }
+    headers.insert(header::ACCESS_CONTROL_MAX_AGE, cors.max_age_secs.into());
+    headers.append(
+        header::VARY,
+        HeaderValue::from_static("access-control-request-method, access-control-request-headers"),
+    );
+}
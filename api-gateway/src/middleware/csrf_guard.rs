@@ -0,0 +1,56 @@
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use tracing::warn;
+
+use crate::auth::session::{csrf_token_matches, get_cookie};
+use crate::config::CONFIG;
+use crate::proxy::services::common::error_response;
+
+/// CSRF双重提交校验中间件
+///
+/// 只校验依靠会话Cookie认证的写请求：请求头中的CSRF令牌必须与CSRF Cookie一致。
+/// 携带认证头的请求不会被浏览器自动附带凭据，无需校验
+pub async fn csrf_guard(request: Request<Body>, next: Next) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    ) {
+        return next.run(request).await;
+    }
+
+    let valid = {
+        let config = CONFIG.read().await;
+        let session = &config.auth.cookie_session;
+        let headers = request.headers();
+        let has_session = get_cookie(headers, &session.access_cookie).is_some()
+            || get_cookie(headers, &session.refresh_cookie).is_some();
+
+        if !session.enabled
+            || headers.contains_key(config.auth.jwt.header_name.as_str())
+            || !has_session
+        {
+            true
+        } else {
+            let cookie = get_cookie(headers, &session.csrf_cookie);
+            let submitted = headers
+                .get(session.csrf_header.as_str())
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim());
+            match (cookie, submitted) {
+                (Some(cookie), Some(submitted)) => csrf_token_matches(&cookie, submitted),
+                _ => false,
+            }
+        }
+    };
+
+    if !valid {
+        warn!("CSRF校验失败: {} {}", request.method(), request.uri().path());
+        return error_response("CSRF校验失败", StatusCode::FORBIDDEN);
+    }
+
+    next.run(request).await
+}
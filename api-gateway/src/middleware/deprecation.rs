@@ -0,0 +1,204 @@
+use axum::{
+    body::{Body, HttpBody},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use common::client_version;
+use metrics::counter;
+use serde_json::Value;
+use tracing::{debug, error, warn};
+
+use crate::config::deprecation_config::DeprecatedRoute;
+use crate::config::CONFIG;
+use crate::proxy::services::common::error_response;
+
+/// 弃用标记响应头（RFC 9745）
+pub const DEPRECATION_HEADER: &str = "Deprecation";
+/// 下线时间响应头（RFC 8594）
+pub const SUNSET_HEADER: &str = "Sunset";
+
+/// 写入提示时读取响应体的上限，超过上限或流式响应只设置响应头
+const MAX_ENVELOPE_SIZE: u64 = 1024 * 1024;
+
+/// 接口弃用中间件
+///
+/// 对配置中已弃用的路由设置 Deprecation、Sunset、Link 响应头，在统一响应格式中写入 warning 字段，
+/// 并按客户端版本统计弃用接口的调用次数；配置了超过下线时间后拒绝的路由返回410
+pub async fn deprecation_notice(request: Request<Body>, next: Next) -> Response {
+    let matched = {
+        let config = CONFIG.read().await;
+        config
+            .deprecation
+            .find(request.method().as_str(), request.uri().path())
+            .map(|rule| {
+                let version = request
+                    .headers()
+                    .get(config.client_version.version_header.as_str())
+                    .and_then(|v| v.to_str().ok());
+                (rule.clone(), client_version::label(version))
+            })
+    };
+    let Some((rule, client_version)) = matched else {
+        return next.run(request).await;
+    };
+
+    let sunset = rule.is_sunset(Utc::now());
+    counter!("gateway.deprecated_requests.total",
+        "route" => rule.id.clone(),
+        "client_version" => client_version.clone(),
+        "sunset" => sunset.to_string()
+    )
+    .increment(1);
+    debug!(
+        "调用已弃用的接口: {} {}，规则: {}，客户端版本: {}",
+        request.method(),
+        request.uri().path(),
+        rule.id,
+        client_version
+    );
+
+    if sunset && rule.reject_after_sunset {
+        warn!(
+            "接口已下线，拒绝请求: {}，规则: {}，客户端版本: {}",
+            request.uri().path(),
+            rule.id,
+            client_version
+        );
+        let mut response = error_response("接口已下线，请升级客户端", StatusCode::GONE);
+        insert_headers(response.headers_mut(), &rule);
+        return response;
+    }
+
+    let response = next.run(request).await;
+    let mut response = match &rule.message {
+        Some(message) => attach_warning(response, message).await,
+        None => response,
+    };
+    insert_headers(response.headers_mut(), &rule);
+    response
+}
+
+/// 设置弃用相关的响应头
+fn insert_headers(headers: &mut HeaderMap, rule: &DeprecatedRoute) {
+    let deprecation = rule
+        .deprecated_at
+        .map_or_else(|| "true".to_string(), |at| format!("@{}", at.timestamp()));
+    if let Ok(value) = HeaderValue::from_str(&deprecation) {
+        headers.insert(DEPRECATION_HEADER, value);
+    }
+
+    if let Some(sunset_at) = rule.sunset_at {
+        let sunset = sunset_at.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(value) = HeaderValue::from_str(&sunset) {
+            headers.insert(SUNSET_HEADER, value);
+        }
+    }
+
+    if let Some(link) = &rule.link {
+        match HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"", link)) {
+            Ok(value) => {
+                headers.append(header::LINK, value);
+            }
+            Err(_) => warn!("弃用规则 {} 的文档地址无效: {}", rule.id, link),
+        }
+    }
+}
+
+/// 在统一响应格式中写入 warning 字段，非JSON或无法确定大小的响应保持不变
+async fn attach_warning(response: Response, message: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map_or(false, |v| v.starts_with("application/json"));
+    let size = response.body().size_hint().exact();
+    if !is_json || size.map_or(true, |size| size > MAX_ENVELOPE_SIZE) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ENVELOPE_SIZE as usize).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("读取响应体失败: {}", e);
+            return error_response("读取响应失败", StatusCode::BAD_GATEWAY);
+        }
+    };
+
+    let body = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(mut envelope)) => {
+            envelope.insert("warning".to_string(), Value::String(message.to_string()));
+            match serde_json::to_vec(&envelope) {
+                Ok(body) => {
+                    parts.headers.remove(header::CONTENT_LENGTH);
+                    Body::from(body)
+                }
+                Err(_) => Body::from(bytes),
+            }
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::deprecation_config::DeprecationConfig;
+    use chrono::TimeZone;
+
+    fn rule(id: &str, path_prefix: &str) -> DeprecatedRoute {
+        DeprecatedRoute {
+            id: id.to_string(),
+            path_prefix: path_prefix.to_string(),
+            methods: vec![],
+            deprecated_at: None,
+            sunset_at: None,
+            link: None,
+            message: None,
+            reject_after_sunset: false,
+        }
+    }
+
+    #[test]
+    fn test_find_longest_prefix() {
+        let mut config = DeprecationConfig::default();
+        config.routes.push(rule("users", "/api/users"));
+        let mut search = rule("users-search", "/api/users/search");
+        search.methods = vec!["GET".to_string()];
+        config.routes.push(search);
+
+        assert_eq!(
+            config.find("GET", "/api/users/search").unwrap().id,
+            "users-search"
+        );
+        assert_eq!(
+            config.find("POST", "/api/users/search").unwrap().id,
+            "users"
+        );
+        assert!(config.find("GET", "/api/groups").is_none());
+
+        config.enabled = false;
+        assert!(config.find("GET", "/api/users").is_none());
+    }
+
+    #[test]
+    fn test_deprecation_headers() {
+        let mut rule = rule("users", "/api/users");
+        rule.deprecated_at = Some(Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap());
+        rule.sunset_at = Some(Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap());
+        rule.link = Some("https://docs.example.com/migrate".to_string());
+
+        let mut headers = HeaderMap::new();
+        insert_headers(&mut headers, &rule);
+        assert_eq!(headers[DEPRECATION_HEADER], "@1748736000");
+        assert_eq!(headers[SUNSET_HEADER], "Mon, 01 Dec 2025 00:00:00 GMT");
+        assert_eq!(
+            headers[header::LINK],
+            "<https://docs.example.com/migrate>; rel=\"deprecation\""
+        );
+        assert!(rule.is_sunset(Utc.with_ymd_and_hms(2025, 12, 1, 0, 0, 0).unwrap()));
+    }
+}
@@ -1,3 +1,21 @@
+pub mod access_log;
+pub mod availability_guard;
+pub mod client_version;
+pub mod coalesce;
+pub mod cors;
+pub mod csrf_guard;
+pub mod deprecation;
+pub mod replay_guard;
+pub mod request_limits;
 pub mod request_logger;
 
-pub use request_logger::*; 
\ No newline at end of file
+pub use access_log::{access_log, AccessLogger};
+pub use availability_guard::{availability_guard, AvailabilityGuard};
+pub use client_version::client_version_guard;
+pub use coalesce::{coalesce_get, RequestCoalescer};
+pub use cors::cors;
+pub use csrf_guard::csrf_guard;
+pub use deprecation::deprecation_notice;
+pub use replay_guard::{replay_guard, ReplayGuard};
+pub use request_limits::request_limits;
+pub use request_logger::*;
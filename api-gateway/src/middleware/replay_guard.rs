@@ -0,0 +1,274 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use redis::aio::ConnectionManager;
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use crate::config::replay_config::ReplayConfig;
+use crate::config::CONFIG;
+use crate::proxy::services::common::error_response;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 客户端ID请求头
+pub const CLIENT_ID_HEADER: &str = "X-Client-Id";
+/// 请求时间戳（秒）请求头
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+/// 一次性随机串请求头
+pub const NONCE_HEADER: &str = "X-Nonce";
+/// 请求签名请求头
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// nonce的Redis键前缀
+const NONCE_KEY_PREFIX: &str = "gateway:replay:nonce";
+
+/// 签名校验时读取请求体的上限
+const MAX_SIGNED_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// 防重放校验
+///
+/// 受保护的路由要求客户端用各自的密钥对 `方法、路径、时间戳、nonce、请求体摘要` 做HMAC-SHA256签名，
+/// 时间戳超出允许偏差或nonce已使用过的请求会被拒绝
+#[derive(Clone)]
+pub struct ReplayGuard {
+    redis: Option<ConnectionManager>,
+}
+
+impl ReplayGuard {
+    /// 创建防重放校验，未启用时不连接Redis
+    pub async fn new(config: &ReplayConfig) -> Self {
+        if !config.enabled {
+            return Self { redis: None };
+        }
+
+        let redis = match redis::Client::open(config.redis_url.as_str()) {
+            Ok(client) => match ConnectionManager::new(client).await {
+                Ok(conn) => Some(conn),
+                Err(e) => {
+                    error!("防重放校验连接Redis失败: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                error!("防重放校验Redis地址无效: {}", e);
+                None
+            }
+        };
+        info!("防重放校验已启用，受保护路由数: {}", config.routes.len());
+
+        Self { redis }
+    }
+
+    /// 记录nonce，nonce已使用过时返回false
+    async fn remember_nonce(
+        &self,
+        client_id: &str,
+        nonce: &str,
+        ttl_secs: i64,
+    ) -> Result<bool, redis::RedisError> {
+        let Some(redis) = &self.redis else {
+            return Err(redis::RedisError::from((
+                redis::ErrorKind::IoError,
+                "Redis连接不可用",
+            )));
+        };
+
+        let key = format!("{}:{}:{}", NONCE_KEY_PREFIX, client_id, nonce);
+        let result: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs.max(1))
+            .query_async(&mut redis.clone())
+            .await?;
+
+        Ok(result.is_some())
+    }
+}
+
+/// 防重放中间件
+pub async fn replay_guard(
+    State(guard): State<ReplayGuard>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let config = {
+        let config = CONFIG.read().await;
+        if !config
+            .replay
+            .is_protected(request.method().as_str(), request.uri().path())
+        {
+            return next.run(request).await;
+        }
+        config.replay.clone()
+    };
+
+    let (parts, body) = request.into_parts();
+    let headers = SignedHeaders::from_headers(&parts.headers);
+    let Some(headers) = headers else {
+        return error_response("缺少请求签名", StatusCode::UNAUTHORIZED);
+    };
+
+    // 校验时间戳
+    if (Utc::now().timestamp() - headers.timestamp).abs() > config.max_skew_secs {
+        return error_response("请求已过期", StatusCode::UNAUTHORIZED);
+    }
+
+    let Some(secret) = config.clients.get(&headers.client_id) else {
+        warn!("未知的签名客户端: {}", headers.client_id);
+        return error_response("无效的请求签名", StatusCode::UNAUTHORIZED);
+    };
+
+    let body = match axum::body::to_bytes(body, MAX_SIGNED_BODY_SIZE).await {
+        Ok(body) => body,
+        Err(e) => {
+            return error_response(&format!("读取请求体失败: {}", e), StatusCode::BAD_REQUEST)
+        }
+    };
+
+    // 校验签名
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or_else(|| parts.uri.path());
+    let payload = signing_payload(
+        parts.method.as_str(),
+        path_and_query,
+        headers.timestamp,
+        &headers.nonce,
+        &body,
+    );
+    if !verify_signature(secret, &payload, &headers.signature) {
+        warn!("请求签名校验失败，客户端: {}, 路径: {}", headers.client_id, parts.uri.path());
+        return error_response("无效的请求签名", StatusCode::UNAUTHORIZED);
+    }
+
+    // 签名通过后再记录nonce，防止伪造请求占用nonce
+    match guard
+        .remember_nonce(&headers.client_id, &headers.nonce, config.max_skew_secs * 2)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            warn!("检测到重放请求，客户端: {}, nonce: {}", headers.client_id, headers.nonce);
+            return error_response("重复的请求", StatusCode::CONFLICT);
+        }
+        Err(e) => {
+            error!("记录请求nonce失败: {}", e);
+            return error_response("服务暂时不可用", StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+/// 签名相关请求头
+struct SignedHeaders {
+    client_id: String,
+    timestamp: i64,
+    nonce: String,
+    signature: String,
+}
+
+impl SignedHeaders {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let get = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        let nonce = get(NONCE_HEADER).filter(|n| (8..=64).contains(&n.len()))?;
+        Some(Self {
+            client_id: get(CLIENT_ID_HEADER)?,
+            timestamp: get(TIMESTAMP_HEADER)?.parse().ok()?,
+            nonce,
+            signature: get(SIGNATURE_HEADER)?,
+        })
+    }
+}
+
+/// 构造待签名内容
+pub fn signing_payload(
+    method: &str,
+    path_and_query: &str,
+    timestamp: i64,
+    nonce: &str,
+    body: &Bytes,
+) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}",
+        method.to_uppercase(),
+        path_and_query,
+        timestamp,
+        nonce,
+        hex::encode(Sha256::digest(body))
+    )
+}
+
+/// 计算十六进制签名
+pub fn sign(secret: &str, payload: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC可以接受任意长度的密钥");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 常量时间校验签名
+fn verify_signature(secret: &str, payload: &str, signature: &str) -> bool {
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC可以接受任意长度的密钥");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature() {
+        let body = Bytes::from_static(b"{\"email\":\"a@example.com\"}");
+        let payload = signing_payload("post", "/api/users/forgetPassword", 1700000000, "n0nce-123", &body);
+        let signature = sign("client-secret", &payload);
+
+        assert!(verify_signature("client-secret", &payload, &signature));
+        assert!(!verify_signature("other-secret", &payload, &signature));
+
+        // 请求体被篡改后签名失效
+        let tampered = signing_payload(
+            "POST",
+            "/api/users/forgetPassword",
+            1700000000,
+            "n0nce-123",
+            &Bytes::from_static(b"{\"email\":\"b@example.com\"}"),
+        );
+        assert!(!verify_signature("client-secret", &tampered, &signature));
+    }
+
+    #[test]
+    fn test_is_protected() {
+        let config = ReplayConfig {
+            enabled: true,
+            ..Default::default()
+        };
+
+        assert!(config.is_protected("POST", "/api/users/forgetPassword"));
+        assert!(!config.is_protected("GET", "/api/users/forgetPassword"));
+        assert!(!config.is_protected("POST", "/api/users/register"));
+    }
+}
@@ -0,0 +1,42 @@
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use http_body_util::Limited;
+use tracing::warn;
+
+use crate::config::CONFIG;
+use crate::proxy::services::common::error_response;
+
+/// 请求超时和请求体大小限制中间件
+///
+/// 每个请求按当前配置取超时和请求体上限，路由规则可以覆盖全局配置，配置热更新后立即生效。
+/// Content-Length 超过上限时直接返回413，未声明长度的请求体在读取超过上限时失败；超时返回408
+pub async fn request_limits(request: Request<Body>, next: Next) -> Response {
+    let (timeout, max_body_bytes) = CONFIG
+        .read()
+        .await
+        .http
+        .limits(request.method().as_str(), request.uri().path());
+
+    let content_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if content_length.map_or(false, |length| length > max_body_bytes) {
+        return error_response("请求体过大", StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let path = request.uri().path().to_string();
+    let request = request.map(|body| Body::new(Limited::new(body, max_body_bytes)));
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!("请求超时: {}，超时时间: {:?}", path, timeout);
+            error_response("请求超时", StatusCode::REQUEST_TIMEOUT)
+        }
+    }
+}
@@ -1,12 +1,24 @@
 use std::task::{Context, Poll};
 use std::time::Instant;
 
-use axum::http;
+use axum::body::Body;
+use axum::http::{self, header};
 use futures::future::BoxFuture;
+use serde_json::Value;
 use tower::{Layer, Service};
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
-/// 请求路径日志中间件
+use crate::auth::jwt::UserInfo;
+use crate::config::request_log_config::RequestLogConfig;
+use crate::config::CONFIG;
+
+/// 脱敏后的替换值
+const REDACTED: &str = "***";
+
+/// 请求日志中间件
+///
+/// 请求完成后输出一条结构化访问日志，包含方法、路由模板、状态码、耗时、用户ID和请求ID。
+/// 查询参数和请求体（需开启 `request_log.log_payloads`）按配置的字段名脱敏后记录
 #[derive(Clone)]
 pub struct RequestLoggerLayer;
 
@@ -23,11 +35,10 @@ pub struct RequestLogger<S> {
     inner: S,
 }
 
-impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for RequestLogger<S>
+impl<S, ResBody> Service<http::Request<Body>> for RequestLogger<S>
 where
-    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>> + Clone + Send + 'static,
+    S: Service<http::Request<Body>, Response = http::Response<ResBody>> + Clone + Send + 'static,
     S::Future: Send + 'static,
-    ReqBody: Send + 'static,
     ResBody: Send + 'static,
 {
     type Response = S::Response;
@@ -38,49 +49,58 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
-        // 克隆服务
-        let mut inner = self.inner.clone();
-
-        // 获取请求的信息 - 确保拥有所有数据而不是借用
-        let method = req.method().clone();
-        let path = req.uri().path().to_string(); // 使用to_string确保拥有数据
-        
-        // 提取请求标识符 - 确保拥有数据
-        let request_id = req
-            .headers()
-            .get("x-request-id")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("-")
-            .to_string(); // 确保完全拥有
-        
-        let start_time = Instant::now();
-
-        // 记录请求开始
-        info!(
-            method = %method,
-            path = %path,
-            request_id = %request_id,
-            "收到HTTP请求"
-        );
-
-        // 在获取所有所需信息后，调用内部服务
-        let future = inner.call(req);
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        // 使用已就绪的服务处理本次请求，克隆的服务留给下次请求
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
 
-        // 包装future实现日志记录
         Box::pin(async move {
-            // 直接await内部future
-            match future.await {
+            let start_time = Instant::now();
+            let config = CONFIG.read().await.request_log.clone();
+
+            let method = req.method().clone();
+            let route = route_template(req.uri().path());
+            let query = req
+                .uri()
+                .query()
+                .map(|query| redact_query(query, &config))
+                .unwrap_or_default();
+            let request_id = req
+                .headers()
+                .get("x-request-id")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("-")
+                .to_string();
+
+            debug!(
+                method = %method,
+                route = %route,
+                request_id = %request_id,
+                "收到HTTP请求"
+            );
+            let req = if config.log_payloads {
+                log_payload(req, &config, &request_id).await
+            } else {
+                req
+            };
+
+            match inner.call(req).await {
                 Ok(response) => {
-                    // 记录请求成功完成
-                    let duration = start_time.elapsed();
                     let status = response.status().as_u16();
+                    // 认证中间件把用户信息放入响应扩展，未认证的请求记为 -
+                    let user_id = response
+                        .extensions()
+                        .get::<UserInfo>()
+                        .map(|user| user.user_id.to_string())
+                        .unwrap_or_else(|| "-".to_string());
 
                     info!(
                         method = %method,
-                        path = %path,
-                        status = %status,
-                        duration_ms = %duration.as_millis(),
+                        route = %route,
+                        query = %query,
+                        status = status,
+                        latency_ms = start_time.elapsed().as_millis() as u64,
+                        user_id = %user_id,
                         request_id = %request_id,
                         "HTTP请求处理完成"
                     );
@@ -89,12 +109,11 @@ where
                 }
                 Err(err) => {
                     // 记录请求失败，不使用Debug格式打印错误
-                    let duration = start_time.elapsed();
-
                     warn!(
                         method = %method,
-                        path = %path,
-                        duration_ms = %duration.as_millis(),
+                        route = %route,
+                        query = %query,
+                        latency_ms = start_time.elapsed().as_millis() as u64,
                         request_id = %request_id,
                         "HTTP请求处理失败"
                     );
@@ -104,4 +123,160 @@ where
             }
         })
     }
-}
\ No newline at end of file
+}
+
+/// 在debug级别记录脱敏后的JSON请求体
+///
+/// 只读取声明了长度且不超过上限的请求体，读取后重新放回请求
+async fn log_payload(
+    req: http::Request<Body>,
+    config: &RequestLogConfig,
+    request_id: &str,
+) -> http::Request<Body> {
+    let is_json = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let Some(length) = length.filter(|length| is_json && *length <= config.max_payload_bytes)
+    else {
+        return req;
+    };
+
+    let (parts, body) = req.into_parts();
+    let bytes = match axum::body::to_bytes(body, length).await {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            // 读取失败的请求体已被消费，交给后端返回读取错误
+            warn!(request_id = %request_id, "读取请求体失败: {}", err);
+            return http::Request::from_parts(parts, Body::empty());
+        }
+    };
+    if let Ok(mut payload) = serde_json::from_slice::<Value>(&bytes) {
+        redact_json(&mut payload, config);
+        debug!(request_id = %request_id, payload = %payload, "HTTP请求体");
+    }
+    http::Request::from_parts(parts, Body::from(bytes))
+}
+
+/// 将JSON中需要脱敏的字段值替换为 ***，递归处理嵌套的对象和数组
+pub fn redact_json(value: &mut Value, config: &RequestLogConfig) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if config.is_sensitive(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value, config);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redact_json(value, config);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 将查询参数中需要脱敏的参数值替换为 ***
+pub fn redact_query(query: &str, config: &RequestLogConfig) -> String {
+    url::form_urlencoded::parse(query.as_bytes())
+        .map(|(key, value)| {
+            if config.is_sensitive(&key) {
+                format!("{}={}", key, REDACTED)
+            } else {
+                format!("{}={}", key, value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// 将路径中的ID替换为 {id}，得到用于日志聚合的路由模板，如 `/api/groups/{id}/members`
+fn route_template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if is_identifier(segment) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 数字ID、UUID，或者包含数字的16位以上的标识符
+fn is_identifier(segment: &str) -> bool {
+    let bytes = segment.as_bytes();
+    if bytes.is_empty() {
+        return false;
+    }
+    if bytes.iter().all(u8::is_ascii_digit) {
+        return true;
+    }
+    bytes.len() >= 16
+        && bytes.iter().any(u8::is_ascii_digit)
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || *b == b'-' || *b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_json() {
+        let config = RequestLogConfig::default();
+        let mut payload = json!({
+            "username": "alice",
+            "newPassword": "secret-1",
+            "sms_code": "123456",
+            "devices": [{ "deviceId": "d1", "pushToken": "t1" }],
+        });
+        redact_json(&mut payload, &config);
+        assert_eq!(
+            payload,
+            json!({
+                "username": "alice",
+                "newPassword": "***",
+                "sms_code": "***",
+                "devices": [{ "deviceId": "d1", "pushToken": "***" }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_redact_query() {
+        let config = RequestLogConfig::default();
+        assert_eq!(
+            redact_query("token=abc&page=2", &config),
+            "token=***&page=2"
+        );
+    }
+
+    #[test]
+    fn test_route_template() {
+        assert_eq!(
+            route_template("/api/groups/1234/members"),
+            "/api/groups/{id}/members"
+        );
+        assert_eq!(
+            route_template("/api/users/3f2a9c1e-8b7d-4e6f-9a0b-1c2d3e4f5a6b/profile"),
+            "/api/users/{id}/profile"
+        );
+        assert_eq!(
+            route_template("/api/users/me/devices"),
+            "/api/users/me/devices"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::Response;
+use axum::Json;
+use common::config::{AppConfig, MaintenanceConfig, RedisConfig};
+use common::grpc::maintenance;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, warn};
+
+use crate::auth::jwt::UserInfo;
+use crate::proxy::services::common::{error_response, success_response};
+
+/// 只读维护模式的管理接口
+///
+/// 开关写入Redis，各服务按配置的间隔刷新；配置文件中列出的服务始终处于维护模式，不能通过接口关闭
+pub struct MaintenanceAdmin {
+    config: MaintenanceConfig,
+    redis: Option<RedisConfig>,
+}
+
+impl MaintenanceAdmin {
+    pub fn new(config: &AppConfig) -> Arc<Self> {
+        Arc::new(Self {
+            config: config.maintenance.clone(),
+            redis: Some(config.redis.clone()),
+        })
+    }
+
+    /// 系统配置不可用时拒绝全部请求的实例
+    pub fn disabled() -> Arc<Self> {
+        Arc::new(Self {
+            config: MaintenanceConfig::default(),
+            redis: None,
+        })
+    }
+
+    /// 校验操作人是配置的管理员，返回可用的Redis配置
+    fn authorize(&self, user_info: Option<Extension<UserInfo>>) -> Result<&RedisConfig, Response> {
+        let Some(redis) = &self.redis else {
+            return Err(error_response(
+                "维护模式管理不可用",
+                StatusCode::SERVICE_UNAVAILABLE,
+            ));
+        };
+        let Some(Extension(user_info)) = user_info else {
+            return Err(error_response("未授权访问", StatusCode::UNAUTHORIZED));
+        };
+        let user_id = user_info.user_id.to_string();
+        if !self.config.admin_user_ids.contains(&user_id) {
+            warn!("非管理员用户 {} 试图管理维护模式", user_id);
+            return Err(error_response("无权管理维护模式", StatusCode::FORBIDDEN));
+        }
+        Ok(redis)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetMaintenanceRequest {
+    /// 服务名称，如 user-service
+    pub service: String,
+    /// 是否进入只读维护模式
+    pub read_only: bool,
+}
+
+/// 查询处于只读维护模式的服务
+pub async fn get_maintenance(
+    Extension(admin): Extension<Arc<MaintenanceAdmin>>,
+    user_info: Option<Extension<UserInfo>>,
+) -> Response {
+    let redis = match admin.authorize(user_info) {
+        Ok(redis) => redis,
+        Err(response) => return response,
+    };
+    match maintenance::read_only_overrides(redis).await {
+        Ok(overrides) => success_response(
+            json!({
+                "configured": admin.config.read_only_services,
+                "overrides": overrides,
+            }),
+            StatusCode::OK,
+        ),
+        Err(e) => {
+            error!("查询维护开关失败: {}", e);
+            error_response("查询维护开关失败", StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// 开启或关闭服务的只读维护模式
+pub async fn set_maintenance(
+    Extension(admin): Extension<Arc<MaintenanceAdmin>>,
+    user_info: Option<Extension<UserInfo>>,
+    Json(request): Json<SetMaintenanceRequest>,
+) -> Response {
+    let operator = user_info
+        .as_ref()
+        .map(|Extension(info)| info.user_id.to_string())
+        .unwrap_or_default();
+    let redis = match admin.authorize(user_info) {
+        Ok(redis) => redis,
+        Err(response) => return response,
+    };
+    if request.service.trim().is_empty() {
+        return error_response("服务名称不能为空", StatusCode::BAD_REQUEST);
+    }
+    if !request.read_only && admin.config.read_only_services.contains(&request.service) {
+        return error_response(
+            "该服务按配置处于维护模式，需要修改配置后关闭",
+            StatusCode::CONFLICT,
+        );
+    }
+
+    if let Err(e) = maintenance::set_read_only(redis, &request.service, request.read_only).await {
+        error!("设置维护开关失败: {}", e);
+        return error_response("设置维护开关失败", StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    warn!(
+        "用户 {} {}服务 {} 的只读维护模式，{}秒内生效",
+        operator,
+        if request.read_only {
+            "开启"
+        } else {
+            "关闭"
+        },
+        request.service,
+        admin.config.refresh_secs
+    );
+    success_response(
+        json!({
+            "service": request.service,
+            "readOnly": request.read_only,
+        }),
+        StatusCode::OK,
+    )
+}
@@ -0,0 +1,134 @@
+pub mod maintenance;
+
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use cache::Cache;
+use common::config::{AppConfig, OpsMetricsConfig};
+use common::ops_metrics::{ConsumerSample, OpsSnapshot};
+use common::time_sync::now_millis;
+use futures::stream::{self, Stream};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::auth::jwt::UserInfo;
+use crate::proxy::services::common::error_response;
+
+/// 运维看板实时指标推送
+///
+/// 后台任务按采样间隔汇总在线人数和各消息服务实例上报的消费速率、积压，
+/// 通过SSE推送给已订阅的运维看板；没有订阅者时不采样
+pub struct OpsMetricsHub {
+    config: OpsMetricsConfig,
+    snapshots: watch::Sender<OpsSnapshot>,
+}
+
+impl OpsMetricsHub {
+    fn new(config: OpsMetricsConfig) -> Arc<Self> {
+        let (snapshots, _) = watch::channel(OpsSnapshot::default());
+        Arc::new(Self { config, snapshots })
+    }
+
+    /// 拒绝全部订阅的实例
+    pub fn disabled() -> Arc<Self> {
+        Self::new(OpsMetricsConfig {
+            enabled: false,
+            ..Default::default()
+        })
+    }
+
+    /// 创建并启动采样任务，未启用时不采样
+    pub fn start(config: &AppConfig) -> Arc<Self> {
+        let hub = Self::new(config.ops_metrics.clone());
+        if !hub.config.enabled {
+            return hub;
+        }
+
+        let cache = cache::cache(config);
+        let sampler = hub.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(sampler.interval());
+            loop {
+                interval.tick().await;
+                if sampler.snapshots.receiver_count() == 0 {
+                    continue;
+                }
+                let snapshot = sampler.sample(cache.as_ref()).await;
+                sampler.snapshots.send_replace(snapshot);
+            }
+        });
+        info!("运维看板实时指标推送已启用");
+        hub
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval_secs.max(1))
+    }
+
+    /// 采样一次平台指标，Redis不可用时在线人数为空
+    async fn sample(&self, cache: &dyn Cache) -> OpsSnapshot {
+        let online_count = match cache.online_count().await {
+            Ok(count) => Some(count),
+            Err(e) => {
+                warn!("查询在线人数失败: {:?}", e);
+                None
+            }
+        };
+        let samples = match cache.get_ops_samples().await {
+            Ok(samples) => samples
+                .iter()
+                .filter_map(|sample| serde_json::from_str::<ConsumerSample>(sample).ok())
+                .collect(),
+            Err(e) => {
+                warn!("查询消费指标失败: {:?}", e);
+                Vec::new()
+            }
+        };
+
+        // 超过三个采样间隔未上报的消息服务实例视为已下线
+        let max_age_ms = self.interval().as_millis() as i64 * 3;
+        OpsSnapshot::aggregate(now_millis(), online_count, &samples, max_age_ms)
+    }
+
+    /// 订阅指标推送，没有订阅者时不采样，订阅后从下一次采样开始推送
+    fn subscribe(&self) -> impl Stream<Item = Result<Event, Infallible>> {
+        let receiver = self.snapshots.subscribe();
+        stream::unfold(receiver, |mut receiver| async move {
+            receiver.changed().await.ok()?;
+            let snapshot = receiver.borrow_and_update().clone();
+            let event = Event::default()
+                .event("metrics")
+                .json_data(&snapshot)
+                .unwrap_or_default();
+            Some((Ok(event), receiver))
+        })
+    }
+}
+
+/// 运维看板指标推送接口（SSE），只有配置的管理员可以订阅
+pub async fn metrics_stream(
+    Extension(hub): Extension<Arc<OpsMetricsHub>>,
+    user_info: Option<Extension<UserInfo>>,
+) -> Response {
+    if !hub.config.enabled {
+        return error_response("运维指标推送未启用", StatusCode::NOT_FOUND);
+    }
+    let Some(Extension(user_info)) = user_info else {
+        return error_response("未授权访问", StatusCode::UNAUTHORIZED);
+    };
+    let user_id = user_info.user_id.to_string();
+    if !hub.config.admin_user_ids.contains(&user_id) {
+        warn!("非管理员用户 {} 试图订阅运维指标", user_id);
+        return error_response("无权访问运维指标", StatusCode::FORBIDDEN);
+    }
+
+    info!("用户 {} 订阅运维指标推送", user_id);
+    Sse::new(hub.subscribe())
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
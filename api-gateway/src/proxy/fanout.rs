@@ -0,0 +1,160 @@
+use futures::future::join_all;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::{sleep, timeout_at, Instant};
+use tracing::{debug, warn};
+
+/// 默认的聚合请求总截止时间预算
+pub const DEFAULT_FANOUT_BUDGET: Duration = Duration::from_millis(800);
+
+/// 默认的对冲延迟，主请求超过该时间未返回时发起对冲请求
+pub const DEFAULT_HEDGE_DELAY: Duration = Duration::from_millis(150);
+
+/// 截止时间预算
+///
+/// 同一个网关请求内的多个上游gRPC调用共享一个总截止时间，
+/// 超出预算的调用会被直接丢弃（future被drop即取消）。
+#[derive(Debug, Clone, Copy)]
+pub struct DeadlineBudget {
+    deadline: Instant,
+}
+
+impl Default for DeadlineBudget {
+    fn default() -> Self {
+        Self::new(DEFAULT_FANOUT_BUDGET)
+    }
+}
+
+impl DeadlineBudget {
+    /// 创建新的截止时间预算
+    pub fn new(total: Duration) -> Self {
+        Self {
+            deadline: Instant::now() + total,
+        }
+    }
+
+    /// 剩余预算
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    /// 预算是否已耗尽
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// 在预算内执行单个调用，超时返回错误
+    pub async fn run<T, Fut>(&self, fut: Fut) -> Result<T, anyhow::Error>
+    where
+        Fut: Future<Output = Result<T, anyhow::Error>>,
+    {
+        match timeout_at(self.deadline, fut).await {
+            Ok(res) => res,
+            Err(_) => {
+                warn!("上游调用超出截止时间预算，已取消");
+                Err(anyhow::anyhow!("上游调用超出截止时间预算"))
+            }
+        }
+    }
+
+    /// 并发执行一组同类调用，所有调用共享同一预算
+    ///
+    /// 返回结果顺序与输入一致，超出预算的调用对应位置为错误。
+    pub async fn join_all<T, Fut, I>(&self, futs: I) -> Vec<Result<T, anyhow::Error>>
+    where
+        I: IntoIterator<Item = Fut>,
+        Fut: Future<Output = Result<T, anyhow::Error>>,
+    {
+        join_all(futs.into_iter().map(|fut| self.run(fut))).await
+    }
+
+    /// 对冲请求，仅用于幂等的读操作
+    ///
+    /// 主请求在 `hedge_delay` 内未返回时再发起一次相同请求，取先成功的结果，
+    /// 另一个请求随之被取消。整体仍受预算约束。
+    pub async fn hedged<T, F, Fut>(&self, hedge_delay: Duration, make: F) -> Result<T, anyhow::Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, anyhow::Error>>,
+    {
+        let work = async {
+            let primary = make();
+            tokio::pin!(primary);
+
+            tokio::select! {
+                res = &mut primary => return res,
+                _ = sleep(hedge_delay) => {}
+            }
+
+            debug!("主请求超过对冲延迟 {:?}，发起对冲请求", hedge_delay);
+            let backup = make();
+            tokio::pin!(backup);
+
+            tokio::select! {
+                res = &mut primary => match res {
+                    Ok(v) => Ok(v),
+                    Err(e) => {
+                        debug!("主请求失败，等待对冲请求: {}", e);
+                        backup.await
+                    }
+                },
+                res = &mut backup => match res {
+                    Ok(v) => Ok(v),
+                    Err(e) => {
+                        debug!("对冲请求失败，等待主请求: {}", e);
+                        primary.await
+                    }
+                },
+            }
+        };
+
+        self.run(work).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_join_all_drops_laggards() {
+        let budget = DeadlineBudget::new(Duration::from_millis(50));
+        let results = budget
+            .join_all(vec![
+                Box::pin(async { Ok::<_, anyhow::Error>(1) })
+                    as std::pin::Pin<Box<dyn Future<Output = _> + Send>>,
+                Box::pin(async {
+                    sleep(Duration::from_millis(500)).await;
+                    Ok(2)
+                }),
+            ])
+            .await;
+
+        assert_eq!(results[0].as_ref().unwrap(), &1);
+        assert!(results[1].is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hedged_uses_faster_attempt() {
+        let budget = DeadlineBudget::new(Duration::from_millis(300));
+        let attempts = AtomicUsize::new(0);
+
+        let res = budget
+            .hedged(Duration::from_millis(20), || {
+                let n = attempts.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    // 第一次调用很慢，对冲请求很快返回
+                    if n == 0 {
+                        sleep(Duration::from_millis(1000)).await;
+                    }
+                    Ok::<_, anyhow::Error>(n)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res, 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,19 +1,49 @@
 use axum::{
-    body::Body,
-    http::{Method, Request, Response, StatusCode},
+    body::{Body, Bytes},
+    http::{header, HeaderValue, Method, Request, Response, StatusCode},
 };
 use futures::future::BoxFuture;
 use serde_json::Value;
+use std::sync::Arc;
 use tonic::transport::Channel;
-use tracing::{debug, error};
-use common::grpc_client::{FriendServiceGrpcClient, GroupServiceGrpcClient, UserServiceGrpcClient};
-use common::service_registry::ServiceRegistry;
+use tracing::{debug, error, warn};
+use common::grpc_client::{
+    FriendServiceGrpcClient, GroupServiceGrpcClient, JobServiceGrpcClient,
+    MessageRequestGrpcClient, RetentionServiceGrpcClient, UserServiceGrpcClient, DraftGrpcClient,
+    TransactionGrpcClient, AccessLogGrpcClient, StorageGrpcClient, BackfillGrpcClient,
+};
+use common::grpc::maintenance::maintenance_retry_after;
+use common::grpc::subject::with_subject;
+use common::grpc::tenant::with_tenant;
+use common::service_registry::{ServiceRegister, ServiceRegistry};
 
+use crate::auth::jwt::UserInfo;
+use crate::proxy::ownership::enforce_subject;
 use crate::proxy::services::{
-    UserServiceHandler, FriendServiceHandler, GroupServiceHandler,
-    common::error_response
+    UserServiceHandler, FriendServiceHandler, GroupServiceHandler, JobServiceHandler,
+    RetentionServiceHandler, MessageRequestServiceHandler, DraftServiceHandler, TransactionServiceHandler,
+    AccessLogServiceHandler, HistoryServiceHandler,
+    common::{error_response, get_optional_string}
 };
 
+/// 处理函数失败时的响应，后端服务处于只读维护模式时返回503并提示重试时间
+fn handler_error(context: &str, err: anyhow::Error) -> Response<Body> {
+    let retry_after = err
+        .downcast_ref::<tonic::Status>()
+        .and_then(maintenance_retry_after);
+    if let Some(retry_after) = retry_after {
+        warn!("{}，服务维护中: {}", context, err);
+        let mut response =
+            error_response("服务维护中，暂时无法修改数据，请稍后重试", StatusCode::SERVICE_UNAVAILABLE);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        return response;
+    }
+    error!("{}: {}", context, err);
+    error_response(&format!("{}: {}", context, err), StatusCode::INTERNAL_SERVER_ERROR)
+}
+
 /// gRPC客户端工厂接口
 pub trait GrpcClientFactory: Send + Sync {
     /// 转发gRPC请求
@@ -81,33 +111,62 @@ impl BaseGrpcClient {
 /// 通用gRPC客户端工厂
 pub struct GrpcClientFactoryImpl {
     // 服务注册表
-    service_registry: ServiceRegistry,
+    service_registry: Arc<dyn ServiceRegister>,
     // 各服务处理器
     user_service: UserServiceHandler,
     friend_service: FriendServiceHandler,
     group_service: GroupServiceHandler,
+    job_service: JobServiceHandler,
+    retention_service: RetentionServiceHandler,
+    message_request_service: MessageRequestServiceHandler,
+    draft_service: DraftServiceHandler,
+    transaction_service: TransactionServiceHandler,
+    access_log_service: AccessLogServiceHandler,
+    history_service: HistoryServiceHandler,
 }
 
 impl GrpcClientFactoryImpl {
     /// 创建新的通用gRPC客户端工厂
     pub fn new() -> Self {
-        let service_registry = ServiceRegistry::from_env();
+        let service_registry = ServiceRegistry::from_env().shared();
 
         // 创建各服务客户端
         let user_client = UserServiceGrpcClient::from_env();
         let friend_client = FriendServiceGrpcClient::from_env();
         let group_client = GroupServiceGrpcClient::from_env();
+        let job_client = JobServiceGrpcClient::from_env();
+        let retention_client = RetentionServiceGrpcClient::from_env();
+        let message_request_client = MessageRequestGrpcClient::from_env();
+        let draft_client = DraftGrpcClient::from_env();
+        let transaction_client = TransactionGrpcClient::from_env();
+        let access_log_client = AccessLogGrpcClient::from_env();
+        let storage_client = StorageGrpcClient::from_env();
+        let backfill_client = BackfillGrpcClient::from_env();
 
         // 创建各服务处理器
-        let user_service = UserServiceHandler::new(user_client);
+        let user_service = UserServiceHandler::new(user_client, storage_client);
         let friend_service = FriendServiceHandler::new(friend_client);
         let group_service = GroupServiceHandler::new(group_client);
+        let job_service = JobServiceHandler::new(job_client);
+        let retention_service = RetentionServiceHandler::new(retention_client);
+        let message_request_service = MessageRequestServiceHandler::new(message_request_client);
+        let draft_service = DraftServiceHandler::new(draft_client);
+        let transaction_service = TransactionServiceHandler::new(transaction_client);
+        let access_log_service = AccessLogServiceHandler::new(access_log_client);
+        let history_service = HistoryServiceHandler::new(backfill_client);
 
         Self {
             service_registry,
             user_service,
             friend_service,
             group_service,
+            job_service,
+            retention_service,
+            message_request_service,
+            draft_service,
+            transaction_service,
+            access_log_service,
+            history_service,
         }
     }
 
@@ -124,6 +183,13 @@ impl GrpcClientFactoryImpl {
             "users" => "user".to_string(),
             "friends" => "friend".to_string(),
             "groups" => "group".to_string(),
+            "jobs" => "job".to_string(),
+            "retention" => "retention".to_string(),
+            "message-requests" => "message_request".to_string(),
+            "drafts" => "draft".to_string(),
+            "transactions" => "transaction".to_string(),
+            "access-logs" => "access_log".to_string(),
+            "history" => "backfill".to_string(),
             _ => service_name.clone(),
         };
 
@@ -142,16 +208,14 @@ impl GrpcClientFactoryImpl {
             .map_err(|e| anyhow::anyhow!("读取请求体失败: {}", e))?;
 
         // 解析JSON请求体或URL参数
-        let body: Value = match serde_json::from_slice(&body_bytes) {
-            Ok(json) => json,
-            Err(_) => {
-                // 尝试从URL参数获取
+        let body: Value = match parse_json_body(body_bytes) {
+            Some(json) => json,
+            None => {
+                // 尝试从URL参数获取，参数值需要URL解码（如中文搜索关键词）
                 let mut map = serde_json::map::Map::new();
                 if let Some(query_str) = query {
-                    for param in query_str.split('&') {
-                        if let Some((key, value)) = param.split_once('=') {
-                            map.insert(key.to_string(), Value::String(value.to_string()));
-                        }
+                    for (key, value) in url::form_urlencoded::parse(query_str.as_bytes()) {
+                        map.insert(key.into_owned(), Value::String(value.into_owned()));
                     }
                 }
                 Value::Object(map)
@@ -160,6 +224,43 @@ impl GrpcClientFactoryImpl {
 
         Ok((method, path, body))
     }
+
+    /// 根据服务类型调用对应的处理方法
+    async fn dispatch(&self, method: &Method, path: &str, body: Value) -> Response<Body> {
+        // 解析服务类型
+        let (service_name, _, _) = self.parse_path(path);
+
+        match service_name.as_str() {
+            "users" => self.user_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理用户服务请求失败", err)),
+            "friends" => self.friend_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理好友服务请求失败", err)),
+            "groups" => self.group_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理群组服务请求失败", err)),
+            "jobs" => self.job_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理异步任务请求失败", err)),
+            "retention" => self.retention_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理消息保留策略请求失败", err)),
+            "message-requests" => self.message_request_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理消息请求失败", err)),
+            "drafts" => self.draft_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理会话草稿请求失败", err)),
+            "transactions" => self.transaction_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理事务消息请求失败", err)),
+            "access-logs" => self.access_log_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理访问日志请求失败", err)),
+            "history" => self.history_service.handle_request(method, path, body).await
+                .unwrap_or_else(|err| handler_error("处理历史消息请求失败", err)),
+            // 将来可以添加其他服务的处理分支
+            _ => {
+                error!("不支持的服务类型: {}", service_name);
+                error_response(
+                    &format!("服务 {} 的gRPC转发尚未实现", service_name),
+                    StatusCode::NOT_IMPLEMENTED
+                )
+            }
+        }
+    }
 }
 
 impl GrpcClientFactory for GrpcClientFactoryImpl {
@@ -173,8 +274,11 @@ impl GrpcClientFactory for GrpcClientFactoryImpl {
         Box::pin(async move {
             debug!("收到gRPC转发请求，目标: {}", target_url);
 
+            // 认证中间件写入的用户信息，据此校验和绑定请求的操作人
+            let user_info = req.extensions().get::<UserInfo>().cloned();
+
             // 提取请求信息
-            let (method, path, body) = match Self::extract_request_body(req).await {
+            let (method, path, mut body) = match Self::extract_request_body(req).await {
                 Ok(data) => data,
                 Err(err) => {
                     error!("请求解析失败: {}", err);
@@ -182,35 +286,23 @@ impl GrpcClientFactory for GrpcClientFactoryImpl {
                 }
             };
 
-            // 解析服务类型
-            let (service_name, _, _) = self_clone.parse_path(&path);
-
-            // 根据服务类型调用对应的处理方法
-            match service_name.as_str() {
-                "users" => self_clone.user_service.handle_request(&method, &path, body).await
-                    .unwrap_or_else(|err| {
-                        error!("处理用户服务请求失败: {}", err);
-                        error_response(&format!("处理用户服务请求失败: {}", err), StatusCode::INTERNAL_SERVER_ERROR)
-                    }),
-                "friends" => self_clone.friend_service.handle_request(&method, &path, body).await
-                    .unwrap_or_else(|err| {
-                        error!("处理好友服务请求失败: {}", err);
-                        error_response(&format!("处理好友服务请求失败: {}", err), StatusCode::INTERNAL_SERVER_ERROR)
-                    }),
-                "groups" => self_clone.group_service.handle_request(&method, &path, body).await
-                    .unwrap_or_else(|err| {
-                        error!("处理群组服务请求失败: {}", err);
-                        error_response(&format!("处理群组服务请求失败: {}", err), StatusCode::INTERNAL_SERVER_ERROR)
-                    }),
-                // 将来可以添加其他服务的处理分支
-                _ => {
-                    error!("不支持的服务类型: {}", service_name);
-                    error_response(
-                        &format!("服务 {} 的gRPC转发尚未实现", service_name),
-                        StatusCode::NOT_IMPLEMENTED
-                    )
-                }
+            if let Err(response) = enforce_subject(&path, user_info.as_ref(), &mut body) {
+                return response;
             }
+
+            // 后端服务按租户路由数据库，未登录的请求（如注册、找回密码）使用请求中的租户ID
+            let tenant_id = match &user_info {
+                Some(info) => Some(info.tenant_id.to_string()),
+                None => get_optional_string(&body, "tenantId", Some("tenant_id")),
+            };
+
+            // 后端服务据转发的用户ID再次校验操作人
+            let subject = user_info.map(|info| info.user_id.to_string());
+            with_tenant(
+                tenant_id,
+                with_subject(subject, self_clone.dispatch(&method, &path, body)),
+            )
+            .await
         })
     }
 
@@ -220,7 +312,7 @@ impl GrpcClientFactory for GrpcClientFactoryImpl {
 
         Box::pin(async move {
             // 简单的健康检查：尝试连接用户服务
-            match service_registry.discover_service("user-service").await {
+            match service_registry.discover("user-service").await {
                 Ok(_) => true,
                 Err(_) => false,
             }
@@ -236,10 +328,31 @@ impl Clone for GrpcClientFactoryImpl {
             user_service: self.user_service.clone(),
             friend_service: self.friend_service.clone(),
             group_service: self.group_service.clone(),
+            job_service: self.job_service.clone(),
+            retention_service: self.retention_service.clone(),
+            message_request_service: self.message_request_service.clone(),
+            draft_service: self.draft_service.clone(),
+            transaction_service: self.transaction_service.clone(),
+            access_log_service: self.access_log_service.clone(),
+            history_service: self.history_service.clone(),
         }
     }
 }
 
+/// 解析JSON请求体，启用 simd-json 特性时使用SIMD解析
+#[cfg(feature = "simd-json")]
+fn parse_json_body(body: Bytes) -> Option<Value> {
+    // simd-json 原地解析，请求体缓冲区没有其他引用时直接复用，不会复制
+    let mut buf = Vec::from(body);
+    simd_json::serde::from_slice(&mut buf).ok()
+}
+
+/// 解析JSON请求体
+#[cfg(not(feature = "simd-json"))]
+fn parse_json_body(body: Bytes) -> Option<Value> {
+    serde_json::from_slice(&body).ok()
+}
+
 /// 创建gRPC通道
 pub async fn create_grpc_channel(target_url: &str) -> Result<Channel, tonic::transport::Error> {
     let endpoint = tonic::transport::Endpoint::new(target_url.to_string())?
@@ -1,5 +1,7 @@
+pub mod fanout;
 pub mod grpc_client;
 pub mod http_client;
+pub mod ownership;
 pub mod service_proxy;
 pub mod utils;
 pub mod services;
@@ -8,3 +10,4 @@ pub mod services;
 pub use grpc_client::GrpcClientFactoryImpl;
 pub use grpc_client::GrpcClientFactory;
 pub use service_proxy::ServiceProxy;
+pub use fanout::DeadlineBudget;
@@ -0,0 +1,188 @@
+use axum::{
+    body::Body,
+    http::{Response, StatusCode},
+};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::auth::jwt::UserInfo;
+use crate::proxy::services::common::error_response;
+
+/// 需要校验操作人的路由：(服务, 方法, 操作人字段及其别名)
+///
+/// 这些请求体中的操作人必须是令牌中的当前用户，方法为 `*` 时匹配该服务的所有请求
+const OWNED_ROUTES: &[(&str, &str, &str, &str)] = &[
+    ("users", "updateUser", "userId", "user_id"),
+    ("users", "updateConfig", "userId", "user_id"),
+    ("users", "getConfig", "userId", "user_id"),
+    ("users", "getUsersByIds", "viewerId", "viewer_id"),
+    ("users", "search", "viewerId", "viewer_id"),
+    ("users", "card", "viewerId", "viewer_id"),
+    ("users", "profile", "viewerId", "viewer_id"),
+    ("users", "invite", "senderId", "sender_id"),
+    ("friends", "sendRequest", "userId", "user_id"),
+    ("friends", "acceptRequest", "userId", "user_id"),
+    ("friends", "rejectRequest", "userId", "user_id"),
+    ("friends", "delete", "userId", "user_id"),
+    ("friends", "getGroupFriends", "userId", "user_id"),
+    ("groups", "create", "ownerId", "owner_id"),
+    ("groups", "update", "userId", "user_id"),
+    ("groups", "delete", "userId", "user_id"),
+    ("groups", "addMember", "addedById", "added_by_id"),
+    ("groups", "removeMember", "removedById", "removed_by_id"),
+    ("groups", "updateMemberRole", "updatedById", "updated_by_id"),
+    ("groups", "media", "userId", "user_id"),
+    ("groups", "polls", "userId", "user_id"),
+    ("groups", "getStats", "userId", "user_id"),
+    ("jobs", "*", "userId", "user_id"),
+    ("retention", "*", "userId", "user_id"),
+    ("message-requests", "*", "userId", "user_id"),
+    ("drafts", "*", "userId", "user_id"),
+    ("transactions", "*", "userId", "user_id"),
+    ("access-logs", "*", "operatorId", "operator_id"),
+    ("history", "*", "userId", "user_id"),
+];
+
+/// 校验并绑定请求的操作人
+///
+/// - `/api/{service}/me` 路由的用户ID只取自令牌，覆盖客户端传入的userId
+/// - [`OWNED_ROUTES`] 中的路由若传入的操作人与令牌不一致返回403，未传入时填充为当前用户
+/// - 服务名之后的任一路径段与方法名相同即匹配，既覆盖 `/api/{service}/{id}/{resource}`
+///   形式的子资源路由，也避免在方法名后追加路径段绕过校验
+pub fn enforce_subject(
+    path: &str,
+    user_info: Option<&UserInfo>,
+    body: &mut Value,
+) -> Result<(), Response<Body>> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let service = parts.get(2).copied().unwrap_or_default();
+    let segments = parts.get(3..).unwrap_or_default();
+
+    let (field, alias, strict) = if segments.first() == Some(&"me") {
+        ("userId", "user_id", false)
+    } else {
+        match OWNED_ROUTES.iter().find(|(s, m, _, _)| {
+            *s == service && (*m == "*" || segments.iter().any(|segment| segment == m))
+        }) {
+            Some((_, _, field, alias)) => (*field, *alias, true),
+            None => return Ok(()),
+        }
+    };
+
+    let user_info =
+        user_info.ok_or_else(|| error_response("未授权访问", StatusCode::UNAUTHORIZED))?;
+    let subject = user_info.user_id.to_string();
+
+    if !body.is_object() {
+        *body = Value::Object(serde_json::Map::new());
+    }
+    let Some(map) = body.as_object_mut() else {
+        return Ok(());
+    };
+
+    if strict {
+        let claimed = [field, alias]
+            .iter()
+            .filter_map(|key| map.get(*key))
+            .find_map(|value| match value {
+                Value::String(s) if !s.is_empty() => Some(s.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            });
+        if let Some(claimed) = claimed.filter(|claimed| *claimed != subject) {
+            warn!("用户 {} 试图以用户 {} 的身份访问 {}", subject, claimed, path);
+            return Err(error_response("无权以其他用户身份操作", StatusCode::FORBIDDEN));
+        }
+    }
+
+    map.remove(alias);
+    map.insert(field.to_string(), Value::String(subject));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user(user_id: i64) -> UserInfo {
+        UserInfo {
+            user_id,
+            username: "alice".to_string(),
+            tenant_id: 1,
+            tenant_name: "default".to_string(),
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_enforce_subject() {
+        let alice = user(1001);
+
+        // 未传入操作人时填充为当前用户
+        let mut body = json!({"friendId": "1002"});
+        assert!(enforce_subject("/api/friends/sendRequest", Some(&alice), &mut body).is_ok());
+        assert_eq!(body["userId"], "1001");
+
+        // 冒充其他用户被拒绝
+        let mut body = json!({"addedById": "1002", "userId": "1003"});
+        let resp = enforce_subject("/api/groups/addMember", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let mut body = json!({"groupId": "g1", "userId": "1002", "name": "x"});
+        let resp = enforce_subject("/api/groups/update", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let mut body = json!({"groupId": "g1", "name": "x"});
+        assert!(enforce_subject("/api/groups/update", Some(&alice), &mut body).is_ok());
+        assert_eq!(body["userId"], "1001");
+
+        // 未认证的请求被拒绝
+        let mut body = json!({"userId": "1001"});
+        let resp = enforce_subject("/api/users/updateUser", None, &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // /me 路由直接覆盖客户端传入的userId
+        let mut body = json!({"user_id": "1002"});
+        assert!(enforce_subject("/api/users/me", Some(&alice), &mut body).is_ok());
+        assert_eq!(body, json!({"userId": "1001"}));
+
+        // 子资源路由按资源名匹配
+        let mut body = json!({"userId": "1002"});
+        let resp = enforce_subject("/api/groups/g1/media", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let mut body = json!({});
+        assert!(enforce_subject("/api/groups/g1/media", Some(&alice), &mut body).is_ok());
+        assert_eq!(body["userId"], "1001");
+        let mut body = json!({});
+        let resp = enforce_subject("/api/users/1002/profile", None, &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert!(enforce_subject("/api/users/1002/profile", Some(&alice), &mut body).is_ok());
+        assert_eq!(body["viewerId"], "1001");
+
+        // 异步任务只能以当前用户创建和查询
+        let mut body = json!({"userId": "1002", "kind": "export_profile"});
+        let resp = enforce_subject("/api/jobs", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let mut body = json!({});
+        assert!(enforce_subject("/api/jobs/j1", Some(&alice), &mut body).is_ok());
+        assert_eq!(body["userId"], "1001");
+
+        // 方法名后追加路径段仍按方法名匹配
+        let mut body = json!({"addedById": "1002"});
+        let resp = enforce_subject("/api/groups/addMember/x", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let mut body = json!({"userId": "1002"});
+        let resp =
+            enforce_subject("/api/groups/g1/media/extra", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // 历史消息只能拉取自己的会话
+        let mut body = json!({"userId": "1002"});
+        let resp =
+            enforce_subject("/api/history/conversations/c1", Some(&alice), &mut body).unwrap_err();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // 只读接口不受影响
+        let mut body = json!({"userId": "1002"});
+        assert!(enforce_subject("/api/users/getUser", None, &mut body).is_ok());
+    }
+}
@@ -2,6 +2,7 @@ use crate::auth::jwt::UserInfo;
 use crate::config::routes_config::ServiceType;
 use crate::config::CONFIG;
 use crate::proxy::grpc_client::GrpcClientFactory;
+use common::region::prefer_region;
 use axum::{
     body::Body,
     http::{Request, Response, StatusCode},
@@ -23,6 +24,8 @@ pub struct ServiceDiscovery {
     consul_client: Client,
     // Consul URL
     consul_url: String,
+    // 本网关所在的区域，优先转发到同区域的服务实例
+    region: Option<String>,
 }
 
 impl ServiceDiscovery {
@@ -35,6 +38,9 @@ impl ServiceDiscovery {
                 .build()
                 .unwrap_or_default(),
             consul_url: consul_url.to_string(),
+            region: std::env::var("SERVICE_REGION")
+                .ok()
+                .filter(|region| !region.is_empty()),
         }
     }
 
@@ -58,7 +64,7 @@ impl ServiceDiscovery {
                 if response.status().is_success() {
                     match response.json::<Vec<serde_json::Value>>().await {
                         Ok(services) => {
-                            let mut addresses = Vec::new();
+                            let mut instances = Vec::new();
 
                             for service in services {
                                 if let (Some(address), Some(port)) = (
@@ -79,10 +85,22 @@ impl ServiceDiscovery {
                                         format!("https://{}:{}", address, port)
                                     };
 
-                                    addresses.push(addr);
+                                    let tags = service
+                                        .get("ServiceTags")
+                                        .and_then(|t| t.as_array())
+                                        .map(|tags| {
+                                            tags.iter()
+                                                .filter_map(|tag| tag.as_str().map(String::from))
+                                                .collect()
+                                        })
+                                        .unwrap_or_default();
+                                    instances.push((addr, tags));
                                 }
                             }
 
+                            // 优先使用同区域的实例，同区域没有实例时跨区域转发
+                            let addresses = prefer_region(instances, self.region.as_deref());
+
                             if addresses.is_empty() {
                                 return Err(format!("无法找到服务: {}", service_name));
                             }
@@ -183,6 +201,13 @@ impl ServiceProxy {
                     | ServiceType::Friend
                     | ServiceType::Group
                     | ServiceType::Chat
+                    | ServiceType::Job
+                    | ServiceType::Retention
+                    | ServiceType::MessageRequest
+                    | ServiceType::Draft
+                    | ServiceType::Transaction
+                    | ServiceType::AccessLog
+                    | ServiceType::History
                     | ServiceType::GrpcService(_) => {
                         // 转发gRPC请求
                         self.forward_grpc_request(req, &service_url).await
@@ -212,6 +237,20 @@ impl ServiceProxy {
             ServiceType::Friend => "friend-service".to_string(),
             ServiceType::Group => "group-service".to_string(),
             ServiceType::Chat => "chat-service".to_string(),
+            // 异步任务目前由用户服务承载
+            ServiceType::Job => "user-service".to_string(),
+            // 消息保留策略目前由用户服务承载
+            ServiceType::Retention => "user-service".to_string(),
+            // 消息请求由消息服务承载
+            ServiceType::MessageRequest => "msg-server".to_string(),
+            // 会话草稿由消息服务承载
+            ServiceType::Draft => "msg-server".to_string(),
+            // 事务消息由消息服务承载
+            ServiceType::Transaction => "msg-server".to_string(),
+            // 访问日志目前由用户服务承载
+            ServiceType::AccessLog => "user-service".to_string(),
+            // 历史消息由消息服务承载
+            ServiceType::History => "msg-server".to_string(),
             ServiceType::Static => "static-service".to_string(),
             ServiceType::HttpService(name) => name.clone(),
             ServiceType::GrpcService(name) => name.clone(),
@@ -0,0 +1,126 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use chrono::{DateTime, Utc};
+use common::grpc_client::AccessLogGrpcClient;
+use common::proto::access_log::{AccessLog, ListAccessLogsRequest};
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{
+    datetime_to_timestamp, extract_string_param, get_i64_param, get_optional_string,
+    success_response, timestamp_to_rfc3339,
+};
+
+/// 访问日志服务处理器
+#[derive(Clone)]
+pub struct AccessLogServiceHandler {
+    client: AccessLogGrpcClient,
+}
+
+impl AccessLogServiceHandler {
+    /// 创建新的访问日志服务处理器
+    pub fn new(client: AccessLogGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理访问日志请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理访问日志请求: {} {}", method, path);
+
+        // 路径格式: /api/access-logs
+        let parts: Vec<&str> = path.split('/').collect();
+        let resource = parts.get(3).copied().filter(|s| !s.is_empty());
+
+        match (method, resource) {
+            // 租户管理员查询访问日志
+            (&Method::GET, None) => {
+                let operator_id = extract_string_param(&body, "operatorId", Some("operator_id"))?;
+                let tenant_id = extract_string_param(&body, "tenantId", Some("tenant_id"))?;
+                let from = parse_time_param(&body, "from")?;
+                let to = parse_time_param(&body, "to")?;
+
+                let request = ListAccessLogsRequest {
+                    operator_id,
+                    tenant_id,
+                    user_id: get_optional_string(&body, "userId", Some("user_id"))
+                        .unwrap_or_default(),
+                    from: Some(datetime_to_timestamp(from)),
+                    to: Some(datetime_to_timestamp(to)),
+                    path_prefix: get_optional_string(&body, "pathPrefix", Some("path_prefix"))
+                        .unwrap_or_default(),
+                    status: get_i64_param(&body, "status", 0) as i32,
+                    ip: get_optional_string(&body, "ip", None).unwrap_or_default(),
+                    page: get_i64_param(&body, "page", 1),
+                    page_size: get_i64_param(&body, "pageSize", 50),
+                };
+
+                let response = self.client.list_access_logs(request).await?;
+                let logs: Vec<Value> = response
+                    .logs
+                    .iter()
+                    .map(|log| self.convert_log_to_json(log))
+                    .collect();
+
+                Ok(success_response(
+                    json!({
+                        "logs": logs,
+                        "total": response.total,
+                    }),
+                    StatusCode::OK,
+                ))
+            }
+
+            _ => {
+                error!("访问日志服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!(
+                    "访问日志服务不支持的方法: {} {}",
+                    method,
+                    path
+                ))
+            }
+        }
+    }
+
+    /// 将访问日志转换为JSON
+    fn convert_log_to_json(&self, log: &AccessLog) -> Value {
+        json!({
+            "id": log.id,
+            "userId": log.user_id,
+            "tenantId": log.tenant_id,
+            "method": log.method,
+            "path": log.path,
+            "status": log.status,
+            "latencyMs": log.latency_ms,
+            "ip": log.ip,
+            "userAgent": log.user_agent,
+            "requestId": log.request_id,
+            "accessedAt": timestamp_to_rfc3339(&log.accessed_at),
+        })
+    }
+}
+
+/// 解析时间参数，支持RFC3339字符串或毫秒时间戳
+fn parse_time_param(body: &Value, name: &str) -> Result<DateTime<Utc>, anyhow::Error> {
+    let value = body
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("参数 {} 缺失或格式错误", name))?;
+    let millis = value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.parse::<i64>().ok()));
+
+    let time = match millis {
+        Some(millis) => DateTime::from_timestamp_millis(millis),
+        None => value
+            .as_str()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc)),
+    };
+    time.ok_or_else(|| anyhow::anyhow!("参数 {} 缺失或格式错误", name))
+}
@@ -1,22 +1,38 @@
 use axum::{
-    body::Body,
-    http::StatusCode,
+    body::{Body, Bytes},
+    http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
 use chrono::{DateTime, TimeZone, Utc};
 use prost_types::Timestamp;
-use serde_json::{json, Value};
+use serde::Serialize;
+use serde_json::Value;
+
+/// 流式列表响应每个数据块包含的条目数
+const STREAM_CHUNK_ITEMS: usize = 64;
+
+/// 统一响应格式，直接序列化到响应体，不经过中间的 `serde_json::Value`
+#[derive(Serialize)]
+struct ApiResponse<'a, T> {
+    code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+    success: bool,
+}
 
 /// 通用响应生成辅助函数 - 成功响应
 pub fn success_response<T: serde::Serialize>(data: T, status_code: StatusCode) -> axum::response::Response<Body> {
     (
         status_code,
-        Json(json!({
-            "code": status_code.as_u16(),
-            "data": data,
-            "success": true
-        })),
+        Json(ApiResponse {
+            code: status_code.as_u16(),
+            data: Some(data),
+            message: None,
+            success: true,
+        }),
     ).into_response()
 }
 
@@ -24,12 +40,12 @@ pub fn success_response<T: serde::Serialize>(data: T, status_code: StatusCode) -
 pub fn success_with_message<T: serde::Serialize>(data: T, message: &str, status_code: StatusCode) -> axum::response::Response<Body> {
     (
         status_code,
-        Json(json!({
-            "code": status_code.as_u16(),
-            "data": data,
-            "message": message,
-            "success": true
-        })),
+        Json(ApiResponse {
+            code: status_code.as_u16(),
+            data: Some(data),
+            message: Some(message),
+            success: true,
+        }),
     ).into_response()
 }
 
@@ -37,11 +53,70 @@ pub fn success_with_message<T: serde::Serialize>(data: T, message: &str, status_
 pub fn error_response(message: &str, status_code: StatusCode) -> axum::response::Response<Body> {
     (
         status_code,
-        Json(json!({
-            "code": status_code.as_u16(),
-            "message": message,
-            "success": false
-        })),
+        Json(ApiResponse::<()> {
+            code: status_code.as_u16(),
+            data: None,
+            message: Some(message),
+            success: false,
+        }),
+    ).into_response()
+}
+
+/// 通用响应生成辅助函数 - 带数据的错误响应，data中携带客户端处理错误所需的信息
+pub fn error_response_with_data<T: serde::Serialize>(data: T, message: &str, status_code: StatusCode) -> axum::response::Response<Body> {
+    (
+        status_code,
+        Json(ApiResponse {
+            code: status_code.as_u16(),
+            data: Some(data),
+            message: Some(message),
+            success: false,
+        }),
+    ).into_response()
+}
+
+/// 通用响应生成辅助函数 - 流式列表响应
+///
+/// 响应格式与 `success_response` 相同，列表按块逐条序列化后直接写入响应体，
+/// 大列表不需要先拼出完整的JSON
+pub fn stream_list_response<T, F>(items: Vec<T>, status_code: StatusCode, write_item: F) -> axum::response::Response<Body>
+where
+    T: Send + 'static,
+    F: Fn(&T, &mut Vec<u8>) -> serde_json::Result<()> + Send + 'static,
+{
+    let total = items.len();
+    let mut items = items.into_iter();
+    let mut head = Some(format!("{{\"code\":{},\"data\":[", status_code.as_u16()));
+    let mut written = 0;
+    let mut finished = false;
+
+    let chunks = std::iter::from_fn(move || {
+        if finished {
+            return None;
+        }
+        let mut buf = head.take().map(String::into_bytes).unwrap_or_default();
+        for item in items.by_ref().take(STREAM_CHUNK_ITEMS) {
+            if written > 0 {
+                buf.push(b',');
+            }
+            if let Err(e) = write_item(&item, &mut buf) {
+                // 序列化失败时中断响应，客户端收到不完整的JSON
+                finished = true;
+                return Some(Err(std::io::Error::other(e)));
+            }
+            written += 1;
+        }
+        if written == total {
+            buf.extend_from_slice(b"],\"success\":true}");
+            finished = true;
+        }
+        Some(Ok(Bytes::from(buf)))
+    });
+
+    (
+        status_code,
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from_stream(futures::stream::iter(chunks)),
     ).into_response()
 }
 
@@ -67,10 +142,10 @@ pub fn get_optional_string(body: &Value, param_name: &str, alt_name: Option<&str
         })
 }
 
-/// 参数提取辅助函数 - 从JSON中提取i64整数参数
+/// 参数提取辅助函数 - 从JSON中提取i64整数参数，兼容URL参数中的数字字符串
 pub fn get_i64_param(body: &Value, param_name: &str, default: i64) -> i64 {
     body.get(param_name)
-        .and_then(|v| v.as_i64())
+        .and_then(|v| v.as_i64().or_else(|| v.as_str().and_then(|s| s.parse().ok())))
         .unwrap_or(default)
 }
 
@@ -110,4 +185,34 @@ pub fn format_timestamp(ts: Option<Timestamp>) -> String {
     } else {
         "".to_string()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: axum::response::Response<Body>) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_list_response() {
+        let items: Vec<i32> = (0..150).collect();
+        let streamed = stream_list_response(items.clone(), StatusCode::OK, |item, buf| {
+            serde_json::to_writer(buf, item)
+        });
+        assert_eq!(
+            body_json(streamed).await,
+            body_json(success_response(items, StatusCode::OK)).await
+        );
+
+        let empty = stream_list_response(Vec::<i32>::new(), StatusCode::OK, |item, buf| {
+            serde_json::to_writer(buf, item)
+        });
+        assert_eq!(
+            body_json(empty).await,
+            serde_json::json!({ "code": 200, "data": [], "success": true })
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use common::grpc_client::DraftGrpcClient;
+use common::proto::draft::Draft;
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{extract_string_param, get_i64_param, get_optional_string, success_response};
+
+/// 会话草稿服务处理器
+#[derive(Clone)]
+pub struct DraftServiceHandler {
+    client: DraftGrpcClient,
+}
+
+impl DraftServiceHandler {
+    /// 创建新的会话草稿服务处理器
+    pub fn new(client: DraftGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理会话草稿相关请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理会话草稿请求: {} {}", method, path);
+
+        let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+        // 路径格式: /api/drafts[/{conversationId}]
+        let conversation_id = path
+            .split('/')
+            .nth(3)
+            .filter(|s| !s.is_empty())
+            .unwrap_or_default();
+
+        match (method, conversation_id.is_empty()) {
+            // 查询草稿，指定会话时只返回该会话的草稿
+            (&Method::GET, _) => {
+                let response = self.client.get_drafts(&user_id, conversation_id).await?;
+                let drafts: Vec<Value> = response
+                    .drafts
+                    .iter()
+                    .map(|draft| self.convert_draft_to_json(draft))
+                    .collect();
+
+                Ok(success_response(drafts, StatusCode::OK))
+            }
+
+            // 保存草稿，文本为空时清除
+            (&Method::PUT, false) => {
+                let text = get_optional_string(&body, "text", None).unwrap_or_default();
+                let platform = get_i64_param(&body, "platform", 0) as i32;
+                let response = self
+                    .client
+                    .set_draft(&user_id, conversation_id, &text, platform)
+                    .await?;
+                let draft = response
+                    .draft
+                    .map(|draft| self.convert_draft_to_json(&draft));
+
+                Ok(success_response(draft, StatusCode::OK))
+            }
+
+            // 清除草稿
+            (&Method::DELETE, false) => {
+                let platform = get_i64_param(&body, "platform", 0) as i32;
+                self.client
+                    .clear_draft(&user_id, conversation_id, platform)
+                    .await?;
+
+                Ok(success_response(json!({}), StatusCode::OK))
+            }
+
+            _ => {
+                error!("会话草稿服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!(
+                    "会话草稿服务不支持的方法: {} {}",
+                    method,
+                    path
+                ))
+            }
+        }
+    }
+
+    /// 将草稿转换为JSON
+    fn convert_draft_to_json(&self, draft: &Draft) -> Value {
+        json!({
+            "conversationId": draft.conversation_id,
+            "text": draft.text,
+            "updatedAt": draft.updated_at,
+            "platform": draft.platform,
+        })
+    }
+}
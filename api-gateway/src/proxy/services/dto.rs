@@ -0,0 +1,470 @@
+//! 服务处理器的请求参数和响应数据
+//!
+//! 请求参数直接从合并后的请求体借用字符串，不再逐个字段复制；
+//! 响应数据借用gRPC响应中的字段，序列化时直接写入响应体，不经过中间的 `serde_json::Value`。
+
+use common::proto;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::common::{format_timestamp, timestamp_to_rfc3339};
+
+/// 从请求体解析类型化的请求参数，字符串字段借用请求体
+pub fn parse_params<'a, T: Deserialize<'a>>(body: &'a Value) -> Result<T, anyhow::Error> {
+    T::deserialize(body).map_err(|e| anyhow::anyhow!("参数缺失或格式错误: {}", e))
+}
+
+/// 整数参数，兼容URL参数中的数字字符串
+fn lenient_i64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<'a> {
+        Number(i64),
+        String(&'a str),
+    }
+
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(n) => Ok(n),
+        NumberOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+fn default_media_page_size() -> i64 {
+    20
+}
+
+/// 按用户查询的请求参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserParams<'a> {
+    #[serde(alias = "user_id")]
+    pub user_id: &'a str,
+}
+
+/// 按群组查询的请求参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupParams<'a> {
+    #[serde(alias = "group_id")]
+    pub group_id: &'a str,
+}
+
+/// 好友列表的请求参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FriendListParams<'a> {
+    #[serde(alias = "user_id")]
+    pub user_id: &'a str,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub page: i64,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub page_size: i64,
+    #[serde(default)]
+    pub sort_by: &'a str,
+}
+
+/// 分组好友列表的请求参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupFriendsParams<'a> {
+    #[serde(alias = "user_id")]
+    pub user_id: &'a str,
+    /// 为空表示未分组的好友
+    #[serde(default, alias = "group_id")]
+    pub group_id: &'a str,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub page: i64,
+    #[serde(default, deserialize_with = "lenient_i64")]
+    pub page_size: i64,
+    #[serde(default)]
+    pub cursor: &'a str,
+    #[serde(default)]
+    pub sort_by: &'a str,
+}
+
+/// 群媒体库的请求参数
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMediaParams<'a> {
+    #[serde(alias = "user_id")]
+    pub user_id: &'a str,
+    #[serde(default, rename = "type", alias = "mediaType")]
+    pub media_type: Option<&'a str>,
+    #[serde(default, alias = "sender_id")]
+    pub sender_id: Option<&'a str>,
+    #[serde(default)]
+    pub keyword: Option<&'a str>,
+    #[serde(default = "default_page", deserialize_with = "lenient_i64")]
+    pub page: i64,
+    #[serde(default = "default_media_page_size", deserialize_with = "lenient_i64")]
+    pub page_size: i64,
+}
+
+/// 群成员角色的文字说明
+fn role_text(role: i32) -> &'static str {
+    match role {
+        0 => "MEMBER",
+        1 => "ADMIN",
+        2 => "OWNER",
+        _ => "UNKNOWN",
+    }
+}
+
+/// 好友关系状态的文字说明
+pub fn friendship_status_text(status: i32) -> &'static str {
+    match status {
+        0 => "PENDING",
+        1 => "ACCEPTED",
+        2 => "REJECTED",
+        3 => "BLOCKED",
+        _ => "UNKNOWN",
+    }
+}
+
+/// 用户
+#[derive(Serialize)]
+pub struct UserDto<'a> {
+    id: &'a str,
+    username: &'a str,
+    email: &'a str,
+    nickname: Option<&'a str>,
+    #[serde(rename = "avatarUrl")]
+    avatar_url: Option<&'a str>,
+    #[serde(rename = "createdAt")]
+    created_at: String,
+    #[serde(rename = "updatedAt")]
+    updated_at: String,
+    phone: &'a str,
+    address: Option<&'a str>,
+    head_image: Option<&'a str>,
+    head_image_thumb: Option<&'a str>,
+    sex: Option<i32>,
+    user_stat: i32,
+    tenant_id: &'a str,
+    last_login_time: String,
+    user_idx: Option<&'a str>,
+    #[serde(rename = "lastActiveAt")]
+    last_active_at: Option<String>,
+}
+
+impl<'a> From<&'a proto::user::User> for UserDto<'a> {
+    fn from(user: &'a proto::user::User) -> Self {
+        Self {
+            id: &user.id,
+            username: &user.username,
+            email: &user.email,
+            nickname: user.nickname.as_deref(),
+            avatar_url: user.avatar_url.as_deref(),
+            created_at: format_timestamp(user.created_at.clone()),
+            updated_at: format_timestamp(user.updated_at.clone()),
+            phone: &user.phone,
+            address: user.address.as_deref(),
+            head_image: user.head_image.as_deref(),
+            head_image_thumb: user.head_image_thumb.as_deref(),
+            sex: user.sex,
+            user_stat: user.user_stat,
+            tenant_id: &user.tenant_id,
+            last_login_time: format_timestamp(user.last_login_time.clone()),
+            user_idx: user.user_idx.as_deref(),
+            last_active_at: user
+                .last_active_at
+                .clone()
+                .map(|t| format_timestamp(Some(t))),
+        }
+    }
+}
+
+/// 用户搜索结果
+#[derive(Serialize)]
+pub struct UserSearchDto<'a> {
+    pub users: Vec<UserDto<'a>>,
+    pub total: i32,
+}
+
+/// 好友关系
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FriendshipDto<'a> {
+    id: &'a str,
+    user_id: &'a str,
+    friend_id: &'a str,
+    status: i32,
+    status_text: &'static str,
+    created_at: String,
+    updated_at: String,
+    message: &'a str,
+    reject_reason: Option<&'a str>,
+}
+
+impl<'a> From<&'a proto::friend::Friendship> for FriendshipDto<'a> {
+    fn from(friendship: &'a proto::friend::Friendship) -> Self {
+        Self {
+            id: &friendship.id,
+            user_id: &friendship.user_id,
+            friend_id: &friendship.friend_id,
+            status: friendship.status,
+            status_text: friendship_status_text(friendship.status),
+            created_at: timestamp_to_rfc3339(&friendship.created_at),
+            updated_at: timestamp_to_rfc3339(&friendship.updated_at),
+            message: &friendship.message,
+            reject_reason: friendship.reject_reason.as_deref(),
+        }
+    }
+}
+
+/// 好友
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FriendDto<'a> {
+    id: &'a str,
+    username: &'a str,
+    nickname: Option<&'a str>,
+    avatar_url: Option<&'a str>,
+    friendship_created_at: String,
+}
+
+impl<'a> From<&'a proto::friend::Friend> for FriendDto<'a> {
+    fn from(friend: &'a proto::friend::Friend) -> Self {
+        Self {
+            id: &friend.id,
+            username: &friend.username,
+            nickname: friend.nickname.as_deref(),
+            avatar_url: friend.avatar_url.as_deref(),
+            friendship_created_at: timestamp_to_rfc3339(&friend.friendship_created_at),
+        }
+    }
+}
+
+/// 分组内的好友
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupFriendDto<'a> {
+    #[serde(flatten)]
+    friend: FriendDto<'a>,
+    online: bool,
+}
+
+/// 分组好友列表
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupFriendsDto<'a> {
+    friends: Vec<GroupFriendDto<'a>>,
+    /// 下一页的游标，为空表示没有更多
+    next_cursor: &'a str,
+}
+
+impl<'a> From<&'a proto::friend::GetGroupFriendsResponse> for GroupFriendsDto<'a> {
+    fn from(response: &'a proto::friend::GetGroupFriendsResponse) -> Self {
+        Self {
+            friends: response
+                .friends
+                .iter()
+                .filter_map(|item| {
+                    item.friend.as_ref().map(|friend| GroupFriendDto {
+                        friend: FriendDto::from(friend),
+                        online: item.online,
+                    })
+                })
+                .collect(),
+            next_cursor: &response.next_cursor,
+        }
+    }
+}
+
+/// 群组
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupDto<'a> {
+    id: &'a str,
+    name: &'a str,
+    description: &'a str,
+    avatar_url: &'a str,
+    owner_id: &'a str,
+    member_count: i32,
+    created_at: String,
+    updated_at: String,
+}
+
+impl<'a> From<&'a proto::group::Group> for GroupDto<'a> {
+    fn from(group: &'a proto::group::Group) -> Self {
+        Self {
+            id: &group.id,
+            name: &group.name,
+            description: &group.description,
+            avatar_url: &group.avatar_url,
+            owner_id: &group.owner_id,
+            member_count: group.member_count,
+            created_at: timestamp_to_rfc3339(&group.created_at),
+            updated_at: timestamp_to_rfc3339(&group.updated_at),
+        }
+    }
+}
+
+/// 群组成员
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberDto<'a> {
+    id: &'a str,
+    group_id: &'a str,
+    user_id: &'a str,
+    username: &'a str,
+    nickname: Option<&'a str>,
+    avatar_url: Option<&'a str>,
+    role: i32,
+    role_text: &'static str,
+    joined_at: String,
+}
+
+impl<'a> From<&'a proto::group::Member> for MemberDto<'a> {
+    fn from(member: &'a proto::group::Member) -> Self {
+        Self {
+            id: &member.id,
+            group_id: &member.group_id,
+            user_id: &member.user_id,
+            username: &member.username,
+            nickname: member.nickname.as_deref(),
+            avatar_url: member.avatar_url.as_deref(),
+            role: member.role,
+            role_text: role_text(member.role),
+            joined_at: timestamp_to_rfc3339(&member.joined_at),
+        }
+    }
+}
+
+/// 群组详情：群组信息和成员列表
+#[derive(Serialize)]
+pub struct GroupDetailDto<'a> {
+    pub group: GroupDto<'a>,
+    pub members: Vec<MemberDto<'a>>,
+}
+
+/// 用户加入的群组
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserGroupDto<'a> {
+    id: &'a str,
+    name: &'a str,
+    avatar_url: &'a str,
+    member_count: i32,
+    role: i32,
+    role_text: &'static str,
+    joined_at: String,
+}
+
+impl<'a> From<&'a proto::group::UserGroup> for UserGroupDto<'a> {
+    fn from(user_group: &'a proto::group::UserGroup) -> Self {
+        Self {
+            id: &user_group.id,
+            name: &user_group.name,
+            avatar_url: &user_group.avatar_url,
+            member_count: user_group.member_count,
+            role: user_group.role,
+            role_text: role_text(user_group.role),
+            joined_at: timestamp_to_rfc3339(&user_group.joined_at),
+        }
+    }
+}
+
+/// 群媒体
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMediaDto<'a> {
+    id: &'a str,
+    group_id: &'a str,
+    msg_id: &'a str,
+    sender_id: &'a str,
+    #[serde(rename = "type")]
+    media_type: &'a str,
+    object_key: &'a str,
+    url: &'a str,
+    file_name: &'a str,
+    mime_type: &'a str,
+    size: i64,
+    thumbnail_url: Option<&'a str>,
+    sent_at: String,
+}
+
+impl<'a> From<&'a proto::group::GroupMedia> for GroupMediaDto<'a> {
+    fn from(media: &'a proto::group::GroupMedia) -> Self {
+        Self {
+            id: &media.id,
+            group_id: &media.group_id,
+            msg_id: &media.msg_id,
+            sender_id: &media.sender_id,
+            media_type: &media.media_type,
+            object_key: &media.object_key,
+            url: &media.url,
+            file_name: &media.file_name,
+            mime_type: &media.mime_type,
+            size: media.size,
+            thumbnail_url: media.thumbnail_url.as_deref(),
+            sent_at: timestamp_to_rfc3339(&media.sent_at),
+        }
+    }
+}
+
+/// 群媒体库分页
+#[derive(Serialize)]
+pub struct GroupMediaPageDto<'a> {
+    pub items: Vec<GroupMediaDto<'a>>,
+    pub total: i64,
+}
+
+/// 群组每日统计
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupStatsDto<'a> {
+    date: &'a str,
+    message_count: i64,
+    active_members: i64,
+    joins: i64,
+    leaves: i64,
+}
+
+impl<'a> From<&'a proto::group::GroupDailyStats> for GroupStatsDto<'a> {
+    fn from(stats: &'a proto::group::GroupDailyStats) -> Self {
+        Self {
+            date: &stats.date,
+            message_count: stats.message_count,
+            active_members: stats.active_members,
+            joins: stats.joins,
+            leaves: stats.leaves,
+        }
+    }
+}
+
+/// 群组统计：当日实时数据和历史每日数据
+#[derive(Serialize)]
+pub struct GroupStatsReportDto<'a> {
+    pub today: Option<GroupStatsDto<'a>>,
+    pub history: Vec<GroupStatsDto<'a>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_params() {
+        let body = json!({ "user_id": "u1", "page": "2", "pageSize": 50 });
+        let params: FriendListParams = parse_params(&body).unwrap();
+        assert_eq!(params.user_id, "u1");
+        assert_eq!(params.page, 2);
+        assert_eq!(params.page_size, 50);
+        assert_eq!(params.sort_by, "");
+
+        let body = json!({ "userId": "u1", "mediaType": "image" });
+        let params: GroupMediaParams = parse_params(&body).unwrap();
+        assert_eq!(params.media_type, Some("image"));
+        assert_eq!(params.page, 1);
+        assert_eq!(params.page_size, 20);
+
+        assert!(parse_params::<UserParams>(&json!({})).is_err());
+        assert!(parse_params::<FriendListParams>(&json!({ "userId": "u1", "page": "x" })).is_err());
+    }
+}
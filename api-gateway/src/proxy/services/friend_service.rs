@@ -3,13 +3,17 @@ use axum::{
     http::{Method, Response, StatusCode},
 };
 use common::grpc_client::FriendServiceGrpcClient;
-use common::proto;
 use serde_json::{json, Value};
 use tracing::{error, debug};
 
 use super::common::{
-    success_response, extract_string_param, timestamp_to_rfc3339
+    success_response, stream_list_response, extract_string_param
 };
+use super::dto::{
+    friendship_status_text, parse_params, FriendDto, FriendListParams, FriendshipDto,
+    GroupFriendsDto, GroupFriendsParams, UserParams,
+};
+use crate::proxy::fanout::{DeadlineBudget, DEFAULT_HEDGE_DELAY};
 
 /// 好友服务处理器
 #[derive(Clone)]
@@ -45,7 +49,7 @@ impl FriendServiceHandler {
                 let response = self.client.send_friend_request(&user_id, &friend_id,&message).await?;
                 let friendship = response.friendship.ok_or_else(|| anyhow::anyhow!("好友关系数据为空"))?;
 
-                Ok(success_response(self.convert_friendship_to_json(&friendship), StatusCode::OK))
+                Ok(success_response(FriendshipDto::from(&friendship), StatusCode::OK))
             }
 
             // 接受好友请求
@@ -56,7 +60,7 @@ impl FriendServiceHandler {
                 let response = self.client.accept_friend_request(&user_id, &friend_id).await?;
                 let friendship = response.friendship.ok_or_else(|| anyhow::anyhow!("好友关系数据为空"))?;
 
-                Ok(success_response(self.convert_friendship_to_json(&friendship), StatusCode::OK))
+                Ok(success_response(FriendshipDto::from(&friendship), StatusCode::OK))
             }
 
             // 拒绝好友请求
@@ -68,38 +72,60 @@ impl FriendServiceHandler {
                 let response = self.client.reject_friend_request(&user_id, &friend_id,&reason).await?;
                 let friendship = response.friendship.ok_or_else(|| anyhow::anyhow!("好友关系数据为空"))?;
 
-                Ok(success_response(self.convert_friendship_to_json(&friendship), StatusCode::OK))
+                Ok(success_response(FriendshipDto::from(&friendship), StatusCode::OK))
             }
 
             // 获取好友列表
             (&Method::GET, "getList") => {
-                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
-
-                // 提取分页和排序参数
-                let page = body.get("page").and_then(|v| v.as_i64()).unwrap_or(0);
-                let page_size = body.get("pageSize").and_then(|v| v.as_i64()).unwrap_or(0);
-                let sort_by = body.get("sortBy").and_then(|v| v.as_str()).unwrap_or("");
-
-                let response = self.client.get_friend_list_with_params(
-                    &user_id,
-                    page,
-                    page_size,
-                    sort_by
-                ).await?;
-
-                let friends = response.friends.iter().map(|f| self.convert_friend_to_json(f)).collect::<Vec<_>>();
+                // 用户ID、分页和排序参数
+                let params: FriendListParams = parse_params(&body)?;
+
+                // 只读请求，使用对冲请求降低长尾延迟
+                let response = DeadlineBudget::default()
+                    .hedged(DEFAULT_HEDGE_DELAY, || {
+                        self.client.get_friend_list_with_params(
+                            params.user_id,
+                            params.page,
+                            params.page_size,
+                            params.sort_by,
+                        )
+                    })
+                    .await?;
+
+                Ok(stream_list_response(response.friends, StatusCode::OK, |friend, buf| {
+                    serde_json::to_writer(buf, &FriendDto::from(friend))
+                }))
+            }
 
-                Ok(success_response(friends, StatusCode::OK))
+            // 获取分组内的好友，支持页码或游标分页、排序和在线状态
+            (&Method::GET, "getGroupFriends") => {
+                let params: GroupFriendsParams = parse_params(&body)?;
+
+                let response = DeadlineBudget::default()
+                    .hedged(DEFAULT_HEDGE_DELAY, || {
+                        self.client.get_group_friends(
+                            params.user_id,
+                            params.group_id,
+                            params.page,
+                            params.page_size,
+                            params.cursor,
+                            params.sort_by,
+                        )
+                    })
+                    .await?;
+
+                Ok(success_response(GroupFriendsDto::from(&response), StatusCode::OK))
             }
 
             // 获取好友请求列表
             (&Method::GET, "getRequests") => {
-                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+                let params: UserParams = parse_params(&body)?;
 
-                let response = self.client.get_friend_requests(&user_id).await?;
-                let requests = response.requests.iter().map(|r| self.convert_friendship_to_json(r)).collect::<Vec<_>>();
+                let response = self.client.get_friend_requests(params.user_id).await?;
 
-                Ok(success_response(requests, StatusCode::OK))
+                Ok(stream_list_response(response.requests, StatusCode::OK, |request, buf| {
+                    serde_json::to_writer(buf, &FriendshipDto::from(request))
+                }))
             }
 
             // 删除好友
@@ -117,20 +143,14 @@ impl FriendServiceHandler {
                 let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
                 let friend_id = extract_string_param(&body, "friendId", Some("friend_id"))?;
 
-                let response = self.client.check_friendship(&user_id, &friend_id).await?;
-
-                let status_text = match response.status {
-                    0 => "PENDING",
-                    1 => "ACCEPTED",
-                    2 => "REJECTED",
-                    3 => "BLOCKED",
-                    _ => "UNKNOWN"
-                };
+                let response = DeadlineBudget::default()
+                    .hedged(DEFAULT_HEDGE_DELAY, || self.client.check_friendship(&user_id, &friend_id))
+                    .await?;
 
                 Ok(success_response(
                     json!({
                         "status": response.status,
-                        "statusText": status_text
+                        "statusText": friendship_status_text(response.status)
                     }),
                     StatusCode::OK
                 ))
@@ -143,38 +163,4 @@ impl FriendServiceHandler {
             }
         }
     }
-
-    /// 将好友关系消息转换为JSON
-    fn convert_friendship_to_json(&self, friendship: &proto::friend::Friendship) -> Value {
-        let status_text = match friendship.status {
-            0 => "PENDING",
-            1 => "ACCEPTED",
-            2 => "REJECTED",
-            3 => "BLOCKED",
-            _ => "UNKNOWN"
-        };
-
-        json!({
-            "id": friendship.id,
-            "userId": friendship.user_id,
-            "friendId": friendship.friend_id,
-            "status": friendship.status,
-            "statusText": status_text,
-            "createdAt": timestamp_to_rfc3339(&friendship.created_at),
-            "updatedAt": timestamp_to_rfc3339(&friendship.updated_at),
-            "message": friendship.message,
-            "rejectReason": friendship.reject_reason,
-        })
-    }
-
-    /// 将好友消息转换为JSON
-    fn convert_friend_to_json(&self, friend: &proto::friend::Friend) -> Value {
-        json!({
-            "id": friend.id,
-            "username": friend.username,
-            "nickname": friend.nickname,
-            "avatarUrl": friend.avatar_url,
-            "friendshipCreatedAt": timestamp_to_rfc3339(&friend.friendship_created_at),
-        })
-    }
-} 
\ No newline at end of file
+}
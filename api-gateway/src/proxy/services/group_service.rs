@@ -8,9 +8,14 @@ use serde_json::{json, Value};
 use tracing::{error, debug};
 
 use super::common::{
-    success_response, extract_string_param, get_optional_string, 
+    success_response, stream_list_response, extract_string_param, get_optional_string,
     get_i64_param, timestamp_to_rfc3339
 };
+use super::dto::{
+    parse_params, GroupDetailDto, GroupDto, GroupMediaDto, GroupMediaPageDto, GroupMediaParams,
+    GroupParams, GroupStatsDto, GroupStatsReportDto, MemberDto, UserGroupDto, UserParams,
+};
+use crate::proxy::fanout::DeadlineBudget;
 
 /// 群组服务处理器
 #[derive(Clone)]
@@ -36,6 +41,16 @@ impl GroupServiceHandler {
         // 从路径提取方法名 - 格式: /api/groups/[method]
         let method_name = path.split('/').nth(3).unwrap_or("unknown");
 
+        // 群媒体库 - 格式: /api/groups/{id}/media
+        if path.split('/').nth(4) == Some("media") {
+            return self.list_media(method, method_name, &body).await;
+        }
+
+        // 群投票 - 格式: /api/groups/{id}/polls[/{pollId}[/{action}]]
+        if path.split('/').nth(4) == Some("polls") {
+            return self.handle_polls(method, path, &body).await;
+        }
+
         match (method, method_name) {
             // 创建群组
             (&Method::POST, "create") => {
@@ -60,22 +75,52 @@ impl GroupServiceHandler {
 
                 let group = response.group.ok_or_else(|| anyhow::anyhow!("群组数据为空"))?;
 
-                Ok(success_response(self.convert_group_to_json(&group), StatusCode::OK))
+                Ok(success_response(GroupDto::from(&group), StatusCode::OK))
             }
 
             // 获取群组信息
             (&Method::GET, "getInfo") | (&Method::GET, "get") => {
-                let group_id = extract_string_param(&body, "groupId", Some("group_id"))?;
+                let params: GroupParams = parse_params(&body)?;
 
-                let response = self.client.get_group(&group_id).await?;
+                let response = self.client.get_group(params.group_id).await?;
                 let group = response.group.ok_or_else(|| anyhow::anyhow!("群组数据为空"))?;
 
-                Ok(success_response(self.convert_group_to_json(&group), StatusCode::OK))
+                Ok(success_response(GroupDto::from(&group), StatusCode::OK))
+            }
+
+            // 获取群组详情（群组信息 + 成员列表，并发获取）
+            (&Method::GET, "getDetail") => {
+                let params: GroupParams = parse_params(&body)?;
+
+                let budget = DeadlineBudget::default();
+                let (group_res, members_res) = tokio::join!(
+                    budget.run(self.client.get_group(params.group_id)),
+                    budget.run(self.client.get_members(params.group_id)),
+                );
+
+                let group = group_res?.group.ok_or_else(|| anyhow::anyhow!("群组数据为空"))?;
+                // 成员列表超时或失败时降级为空列表，群组信息仍然返回
+                let members = match members_res {
+                    Ok(resp) => resp.members,
+                    Err(e) => {
+                        error!("获取群组成员失败，降级返回: {}", e);
+                        Vec::new()
+                    }
+                };
+
+                Ok(success_response(
+                    GroupDetailDto {
+                        group: GroupDto::from(&group),
+                        members: members.iter().map(MemberDto::from).collect(),
+                    },
+                    StatusCode::OK
+                ))
             }
 
             // 更新群组信息
             (&Method::PUT, "update") => {
                 let group_id = extract_string_param(&body, "groupId", Some("group_id"))?;
+                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
                 
                 let name = get_optional_string(&body, "name", None);
                 let description = get_optional_string(&body, "description", None);
@@ -83,6 +128,7 @@ impl GroupServiceHandler {
 
                 let response = self.client.update_group(
                     &group_id,
+                    &user_id,
                     name,
                     description,
                     avatar_url
@@ -90,7 +136,7 @@ impl GroupServiceHandler {
                 
                 let group = response.group.ok_or_else(|| anyhow::anyhow!("群组数据为空"))?;
 
-                Ok(success_response(self.convert_group_to_json(&group), StatusCode::OK))
+                Ok(success_response(GroupDto::from(&group), StatusCode::OK))
             }
 
             // 删除群组
@@ -123,7 +169,7 @@ impl GroupServiceHandler {
                 let response = self.client.add_member(&group_id, &user_id, &added_by_id, role).await?;
                 let member = response.member.ok_or_else(|| anyhow::anyhow!("成员数据为空"))?;
 
-                Ok(success_response(self.convert_member_to_json(&member), StatusCode::OK))
+                Ok(success_response(MemberDto::from(&member), StatusCode::OK))
             }
 
             // 移除成员
@@ -157,27 +203,29 @@ impl GroupServiceHandler {
                 let response = self.client.update_member_role(&group_id, &user_id, &updated_by_id, role).await?;
                 let member = response.member.ok_or_else(|| anyhow::anyhow!("成员数据为空"))?;
 
-                Ok(success_response(self.convert_member_to_json(&member), StatusCode::OK))
+                Ok(success_response(MemberDto::from(&member), StatusCode::OK))
             }
 
             // 获取群组成员列表
             (&Method::GET, "getMembers") => {
-                let group_id = extract_string_param(&body, "groupId", Some("group_id"))?;
+                let params: GroupParams = parse_params(&body)?;
 
-                let response = self.client.get_members(&group_id).await?;
-                let members = response.members.iter().map(|m| self.convert_member_to_json(m)).collect::<Vec<_>>();
+                let response = self.client.get_members(params.group_id).await?;
 
-                Ok(success_response(members, StatusCode::OK))
+                Ok(stream_list_response(response.members, StatusCode::OK, |member, buf| {
+                    serde_json::to_writer(buf, &MemberDto::from(member))
+                }))
             }
 
             // 获取用户加入的群组列表
             (&Method::GET, "getUserGroups") => {
-                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+                let params: UserParams = parse_params(&body)?;
 
-                let response = self.client.get_user_groups(&user_id).await?;
-                let groups = response.groups.iter().map(|g| self.convert_user_group_to_json(g)).collect::<Vec<_>>();
+                let response = self.client.get_user_groups(params.user_id).await?;
 
-                Ok(success_response(groups, StatusCode::OK))
+                Ok(stream_list_response(response.groups, StatusCode::OK, |group, buf| {
+                    serde_json::to_writer(buf, &UserGroupDto::from(group))
+                }))
             }
 
             // 检查用户是否在群组中
@@ -208,6 +256,22 @@ impl GroupServiceHandler {
                 ))
             }
 
+            // 获取群组统计（仅群主/管理员）
+            (&Method::GET, "getStats") => {
+                let group_id = extract_string_param(&body, "groupId", Some("group_id"))?;
+                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+                let days = get_i64_param(&body, "days", 7) as i32;
+
+                let response = self.client.get_group_stats(&group_id, &user_id, days).await?;
+                Ok(success_response(
+                    GroupStatsReportDto {
+                        today: response.today.as_ref().map(GroupStatsDto::from),
+                        history: response.history.iter().map(GroupStatsDto::from).collect(),
+                    },
+                    StatusCode::OK
+                ))
+            }
+
             // 其他未实现的方法
             _ => {
                 error!("群组服务不支持的方法: {} {}", method, method_name);
@@ -216,59 +280,138 @@ impl GroupServiceHandler {
         }
     }
 
-    /// 将群组消息转换为JSON
-    fn convert_group_to_json(&self, group: &proto::group::Group) -> Value {
-        json!({
-            "id": group.id,
-            "name": group.name,
-            "description": group.description,
-            "avatarUrl": group.avatar_url,
-            "ownerId": group.owner_id,
-            "memberCount": group.member_count,
-            "createdAt": timestamp_to_rfc3339(&group.created_at),
-            "updatedAt": timestamp_to_rfc3339(&group.updated_at),
-        })
-    }
+    /// 分页浏览、搜索群媒体库
+    async fn list_media(
+        &self,
+        method: &Method,
+        group_id: &str,
+        body: &Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        if method != Method::GET {
+            return Err(anyhow::anyhow!("群媒体库不支持的方法: {}", method));
+        }
 
-    /// 将群组成员消息转换为JSON
-    fn convert_member_to_json(&self, member: &proto::group::Member) -> Value {
-        let role_text = match member.role {
-            0 => "MEMBER",
-            1 => "ADMIN",
-            2 => "OWNER",
-            _ => "UNKNOWN"
+        let params: GroupMediaParams = parse_params(body)?;
+        let request = proto::group::ListGroupMediaRequest {
+            group_id: group_id.to_string(),
+            user_id: params.user_id.to_string(),
+            media_type: params.media_type.unwrap_or_default().to_string(),
+            sender_id: params.sender_id.unwrap_or_default().to_string(),
+            keyword: params.keyword.unwrap_or_default().to_string(),
+            page: params.page as i32,
+            page_size: params.page_size as i32,
         };
 
-        json!({
-            "id": member.id,
-            "groupId": member.group_id,
-            "userId": member.user_id,
-            "username": member.username,
-            "nickname": member.nickname,
-            "avatarUrl": member.avatar_url,
-            "role": member.role,
-            "roleText": role_text,
-            "joinedAt": timestamp_to_rfc3339(&member.joined_at),
-        })
+        let response = self.client.list_group_media(request).await?;
+        Ok(success_response(
+            GroupMediaPageDto {
+                items: response.items.iter().map(GroupMediaDto::from).collect(),
+                total: response.total,
+            },
+            StatusCode::OK
+        ))
     }
 
-    /// 将用户群组消息转换为JSON
-    fn convert_user_group_to_json(&self, user_group: &proto::group::UserGroup) -> Value {
-        let role_text = match user_group.role {
-            0 => "MEMBER",
-            1 => "ADMIN",
-            2 => "OWNER",
-            _ => "UNKNOWN"
+    /// 发起、投票、查询和结束群投票
+    async fn handle_polls(
+        &self,
+        method: &Method,
+        path: &str,
+        body: &Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        let parts: Vec<&str> = path.split('/').collect();
+        let group_id = parts.get(3).copied().unwrap_or_default();
+        let poll_id = parts.get(5).copied().filter(|s| !s.is_empty());
+        let action = parts.get(6).copied().filter(|s| !s.is_empty());
+        let user_id = extract_string_param(body, "userId", Some("user_id"))?;
+
+        let response = match (method, poll_id, action) {
+            // 发起投票，deadline为截止时间的Unix秒数，不传表示手动结束
+            (&Method::POST, None, None) => {
+                let options = body
+                    .get("options")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| anyhow::anyhow!("缺少必要参数: options"))?
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect();
+                let deadline = get_i64_param(body, "deadline", 0);
+                let request = proto::group::CreatePollRequest {
+                    group_id: group_id.to_string(),
+                    user_id,
+                    question: extract_string_param(body, "question", None)?,
+                    options,
+                    multi_choice: body
+                        .get("multiChoice")
+                        .or_else(|| body.get("multi_choice"))
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false),
+                    deadline: (deadline > 0).then(|| prost_types::Timestamp {
+                        seconds: deadline,
+                        nanos: 0,
+                    }),
+                };
+                self.client.create_poll(request).await?
+            }
+
+            // 查询投票和统计结果
+            (&Method::GET, Some(poll_id), None) => {
+                self.client.get_poll_results(group_id, poll_id, &user_id).await?
+            }
+
+            // 投票，optionIndexes为空表示撤回投票
+            (&Method::POST, Some(poll_id), Some("vote")) => {
+                let option_indexes = body
+                    .get("optionIndexes")
+                    .or_else(|| body.get("option_indexes"))
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| anyhow::anyhow!("缺少必要参数: optionIndexes"))?
+                    .iter()
+                    .filter_map(|v| v.as_i64().map(|i| i as i32))
+                    .collect();
+                let request = proto::group::VotePollRequest {
+                    group_id: group_id.to_string(),
+                    poll_id: poll_id.to_string(),
+                    user_id,
+                    option_indexes,
+                };
+                self.client.vote_poll(request).await?
+            }
+
+            // 结束投票
+            (&Method::POST, Some(poll_id), Some("close")) => {
+                self.client.close_poll(group_id, poll_id, &user_id).await?
+            }
+
+            _ => {
+                error!("群投票不支持的方法: {} {}", method, path);
+                return Err(anyhow::anyhow!("群投票不支持的方法: {} {}", method, path));
+            }
         };
 
+        let poll = response.poll.ok_or_else(|| anyhow::anyhow!("投票数据为空"))?;
+        Ok(success_response(self.convert_poll_to_json(&poll), StatusCode::OK))
+    }
+
+    /// 将群投票转换为JSON
+    fn convert_poll_to_json(&self, poll: &proto::group::Poll) -> Value {
         json!({
-            "id": user_group.id,
-            "name": user_group.name,
-            "avatarUrl": user_group.avatar_url,
-            "memberCount": user_group.member_count,
-            "role": user_group.role,
-            "roleText": role_text,
-            "joinedAt": timestamp_to_rfc3339(&user_group.joined_at),
+            "id": poll.id,
+            "groupId": poll.group_id,
+            "creatorId": poll.creator_id,
+            "question": poll.question,
+            "options": poll.options.iter().map(|option| json!({
+                "index": option.index,
+                "content": option.content,
+                "votes": option.votes,
+            })).collect::<Vec<_>>(),
+            "multiChoice": poll.multi_choice,
+            "deadline": poll.deadline.as_ref().map(|_| timestamp_to_rfc3339(&poll.deadline)),
+            "closed": poll.closed,
+            "closedAt": poll.closed_at.as_ref().map(|_| timestamp_to_rfc3339(&poll.closed_at)),
+            "voterCount": poll.voter_count,
+            "myChoices": poll.my_choices,
+            "createdAt": timestamp_to_rfc3339(&poll.created_at),
         })
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,178 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use common::grpc_client::BackfillGrpcClient;
+use common::message::Msg;
+use common::proto::backfill::{GetConversationRangeRequest, HistoryChunk, StreamHistoryRequest};
+use prost::Message;
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{extract_string_param, get_i64_param, get_optional_string, success_response};
+
+/// 历史消息服务处理器
+#[derive(Clone)]
+pub struct HistoryServiceHandler {
+    client: BackfillGrpcClient,
+}
+
+impl HistoryServiceHandler {
+    /// 创建新的历史消息服务处理器
+    pub fn new(client: BackfillGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理历史消息相关请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理历史消息请求: {} {}", method, path);
+
+        let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+        // 路径格式: /api/history/{resource}[/{id}]
+        let parts: Vec<&str> = path.split('/').collect();
+        let resource = parts.get(3).copied().unwrap_or_default();
+        let id = parts.get(4).copied().filter(|s| !s.is_empty());
+
+        match (method, resource, id) {
+            // 新设备回填历史消息，每次请求返回一批，客户端用返回的 resumeToken 继续下一批
+            (&Method::GET, "backfill", None) => {
+                let device_id = extract_string_param(&body, "deviceId", Some("device_id"))?;
+                let mut stream = self
+                    .client
+                    .stream_history(StreamHistoryRequest {
+                        user_id,
+                        device_id,
+                        resume_token: get_optional_string(&body, "resumeToken", None)
+                            .unwrap_or_default(),
+                        restart: get_bool_param(&body, "restart"),
+                        chunk_size: get_i64_param(&body, "chunkSize", 0) as i32,
+                    })
+                    .await?;
+                // 只取第一批，之后服务端已推送到缓冲区的批次由下次请求的 resumeToken 重新读取
+                let chunk = stream
+                    .message()
+                    .await?
+                    .map(|chunk| self.convert_chunk_to_json(&chunk));
+
+                Ok(success_response(chunk, StatusCode::OK))
+            }
+
+            // 取消回填，进度保留
+            (&Method::POST, "backfill", Some("cancel")) => {
+                let device_id = extract_string_param(&body, "deviceId", Some("device_id"))?;
+                self.client.cancel_backfill(&user_id, &device_id).await?;
+
+                Ok(success_response(json!({}), StatusCode::OK))
+            }
+
+            // 查询各会话的已读位置
+            (&Method::GET, "read-cursors", None) => {
+                let response = self.client.get_read_cursors(&user_id).await?;
+                let cursors: Vec<Value> = response
+                    .cursors
+                    .iter()
+                    .map(|cursor| {
+                        json!({
+                            "conversationId": cursor.conversation_id,
+                            "readSeq": cursor.read_seq,
+                        })
+                    })
+                    .collect();
+
+                Ok(success_response(cursors, StatusCode::OK))
+            }
+
+            // 按会话序号拉取会话中的消息
+            (&Method::GET, "conversations", Some(conversation_id)) => {
+                let response = self
+                    .client
+                    .get_conversation_range(GetConversationRangeRequest {
+                        user_id,
+                        conversation_id: conversation_id.to_string(),
+                        is_group: get_bool_param(&body, "isGroup"),
+                        after_conv_seq: get_i64_param(&body, "afterConvSeq", 0),
+                        until_conv_seq: get_i64_param(&body, "untilConvSeq", 0),
+                        limit: get_i64_param(&body, "limit", 0) as i32,
+                    })
+                    .await?;
+                let messages: Vec<Value> = response
+                    .messages
+                    .iter()
+                    .filter_map(|bytes| self.convert_msg_to_json(bytes))
+                    .collect();
+
+                Ok(success_response(
+                    json!({ "messages": messages, "hasMore": response.has_more }),
+                    StatusCode::OK,
+                ))
+            }
+
+            _ => {
+                error!("历史消息服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!(
+                    "历史消息服务不支持的方法: {} {}",
+                    method,
+                    path
+                ))
+            }
+        }
+    }
+
+    /// 将回填的一批消息转换为JSON
+    fn convert_chunk_to_json(&self, chunk: &HistoryChunk) -> Value {
+        let messages: Vec<Value> = chunk
+            .messages
+            .iter()
+            .filter_map(|bytes| self.convert_msg_to_json(bytes))
+            .collect();
+        json!({
+            "conversationId": chunk.conversation_id,
+            "isGroup": chunk.is_group,
+            "messages": messages,
+            "resumeToken": chunk.resume_token,
+            "remainingConversations": chunk.remaining_conversations,
+            "done": chunk.done,
+            "readSeq": chunk.read_seq,
+        })
+    }
+
+    /// 将prost编码的消息转换为JSON
+    fn convert_msg_to_json(&self, bytes: &[u8]) -> Option<Value> {
+        let msg = Msg::decode(bytes)
+            .map_err(|e| error!("解码历史消息失败: {}", e))
+            .ok()?;
+
+        Some(json!({
+            "serverId": msg.server_id,
+            "localId": msg.local_id,
+            "sendId": msg.send_id,
+            "receiverId": msg.receiver_id,
+            "groupId": msg.group_id,
+            "sendTime": msg.send_time,
+            "seq": msg.seq,
+            "convSeq": msg.conv_seq,
+            "msgType": msg.msg_type,
+            "contentType": msg.content_type,
+            "content": String::from_utf8_lossy(&msg.content),
+            "nickname": msg.nickname,
+            "avatar": msg.avatar,
+            "extensions": msg.extensions,
+        }))
+    }
+}
+
+/// 布尔参数，兼容URL参数中的 "true"/"false"
+fn get_bool_param(body: &Value, param_name: &str) -> bool {
+    body.get(param_name)
+        .and_then(|v| {
+            v.as_bool()
+                .or_else(|| v.as_str().and_then(|s| s.parse().ok()))
+        })
+        .unwrap_or(false)
+}
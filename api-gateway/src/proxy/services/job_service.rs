@@ -0,0 +1,89 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use common::grpc_client::JobServiceGrpcClient;
+use common::proto;
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{extract_string_param, success_response, timestamp_to_rfc3339};
+
+/// 异步任务服务处理器
+#[derive(Clone)]
+pub struct JobServiceHandler {
+    client: JobServiceGrpcClient,
+}
+
+impl JobServiceHandler {
+    /// 创建新的异步任务服务处理器
+    pub fn new(client: JobServiceGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理异步任务请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理异步任务请求: {} {}", method, path);
+
+        // 路径格式: POST /api/jobs 创建任务，GET /api/jobs/{id} 查询任务
+        let job_id = path.split('/').nth(3).filter(|s| !s.is_empty());
+
+        match (method, job_id) {
+            // 创建任务
+            (&Method::POST, None) => {
+                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+                let kind = extract_string_param(&body, "kind", None)?;
+                let params = body.get("params").map(|v| v.to_string()).unwrap_or_default();
+
+                let response = self.client.create_job(&user_id, &kind, &params).await?;
+                let job = response.job.ok_or_else(|| anyhow::anyhow!("任务数据为空"))?;
+
+                Ok(success_response(self.convert_job_to_json(&job), StatusCode::ACCEPTED))
+            }
+
+            // 查询任务状态
+            (&Method::GET, Some(job_id)) => {
+                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+                let response = self.client.get_job(job_id, &user_id).await?;
+                let job = response.job.ok_or_else(|| anyhow::anyhow!("任务数据为空"))?;
+
+                Ok(success_response(self.convert_job_to_json(&job), StatusCode::OK))
+            }
+
+            _ => {
+                error!("异步任务服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!("异步任务服务不支持的方法: {} {}", method, path))
+            }
+        }
+    }
+
+    /// 将任务消息转换为JSON
+    fn convert_job_to_json(&self, job: &proto::job::Job) -> Value {
+        let status_text = match job.status {
+            0 => "PENDING",
+            1 => "RUNNING",
+            2 => "SUCCEEDED",
+            3 => "FAILED",
+            _ => "UNKNOWN",
+        };
+
+        json!({
+            "id": job.id,
+            "kind": job.kind,
+            "status": job.status,
+            "statusText": status_text,
+            "progress": job.progress,
+            "resultUrl": job.result_url,
+            "resultExpiresAt": timestamp_to_rfc3339(&job.result_expires_at),
+            "error": job.error,
+            "createdAt": timestamp_to_rfc3339(&job.created_at),
+            "updatedAt": timestamp_to_rfc3339(&job.updated_at),
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use common::grpc_client::MessageRequestGrpcClient;
+use common::message::Msg;
+use common::proto::message_request::MessageRequestThread;
+use prost::Message;
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{extract_string_param, get_i64_param, success_response};
+
+/// 消息请求服务处理器
+#[derive(Clone)]
+pub struct MessageRequestServiceHandler {
+    client: MessageRequestGrpcClient,
+}
+
+impl MessageRequestServiceHandler {
+    /// 创建新的消息请求服务处理器
+    pub fn new(client: MessageRequestGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理消息请求相关请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理消息请求: {} {}", method, path);
+
+        let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+        // 路径格式: /api/message-requests[/{senderId}[/{action}]]
+        let parts: Vec<&str> = path.split('/').collect();
+        let sender_id = parts.get(3).copied().filter(|s| !s.is_empty());
+        let action = parts.get(4).copied().filter(|s| !s.is_empty());
+
+        match (method, sender_id, action) {
+            // 查询待处理的消息请求
+            (&Method::GET, None, None) => {
+                let limit = get_i64_param(&body, "limit", 0);
+                let response = self
+                    .client
+                    .list_message_requests(&user_id, limit as i32)
+                    .await?;
+                let threads: Vec<Value> = response
+                    .threads
+                    .iter()
+                    .map(|thread| self.convert_thread_to_json(thread))
+                    .collect();
+
+                Ok(success_response(threads, StatusCode::OK))
+            }
+
+            // 预览某个发送者的消息
+            (&Method::GET, Some(sender_id), None) => {
+                let response = self.client.get_message_request(&user_id, sender_id).await?;
+                let messages: Vec<Value> = response
+                    .messages
+                    .iter()
+                    .filter_map(|bytes| self.convert_msg_to_json(bytes))
+                    .collect();
+
+                Ok(success_response(messages, StatusCode::OK))
+            }
+
+            // 接受消息请求
+            (&Method::POST, Some(sender_id), Some("accept")) => {
+                let response = self
+                    .client
+                    .accept_message_request(&user_id, sender_id)
+                    .await?;
+
+                Ok(success_response(
+                    json!({ "delivered": response.affected }),
+                    StatusCode::OK,
+                ))
+            }
+
+            // 拒绝消息请求
+            (&Method::POST, Some(sender_id), Some("decline")) => {
+                let response = self
+                    .client
+                    .decline_message_request(&user_id, sender_id)
+                    .await?;
+
+                Ok(success_response(
+                    json!({ "deleted": response.affected }),
+                    StatusCode::OK,
+                ))
+            }
+
+            _ => {
+                error!("消息请求服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!(
+                    "消息请求服务不支持的方法: {} {}",
+                    method,
+                    path
+                ))
+            }
+        }
+    }
+
+    /// 将消息请求转换为JSON
+    fn convert_thread_to_json(&self, thread: &MessageRequestThread) -> Value {
+        json!({
+            "senderId": thread.sender_id,
+            "messageCount": thread.message_count,
+            "lastSendTime": thread.last_send_time,
+            "lastMessage": self.convert_msg_to_json(&thread.last_message),
+        })
+    }
+
+    /// 将prost编码的消息转换为JSON
+    fn convert_msg_to_json(&self, bytes: &[u8]) -> Option<Value> {
+        let msg = Msg::decode(bytes)
+            .map_err(|e| error!("解码消息请求中的消息失败: {}", e))
+            .ok()?;
+
+        Some(json!({
+            "serverId": msg.server_id,
+            "sendId": msg.send_id,
+            "receiverId": msg.receiver_id,
+            "sendTime": msg.send_time,
+            "contentType": msg.content_type,
+            "content": String::from_utf8_lossy(&msg.content),
+            "nickname": msg.nickname,
+            "avatar": msg.avatar,
+            "extensions": msg.extensions,
+        }))
+    }
+}
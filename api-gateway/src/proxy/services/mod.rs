@@ -2,9 +2,24 @@
 pub mod user_service;
 pub mod friend_service;
 pub mod group_service;
+pub mod job_service;
+pub mod retention_service;
+pub mod message_request_service;
+pub mod draft_service;
+pub mod transaction_service;
+pub mod access_log_service;
+pub mod history_service;
 pub mod common;
+pub mod dto;
 
 // 重新导出所有服务，方便外部直接使用
 pub use user_service::UserServiceHandler;
 pub use friend_service::FriendServiceHandler;
-pub use group_service::GroupServiceHandler; 
\ No newline at end of file
+pub use group_service::GroupServiceHandler;
+pub use job_service::JobServiceHandler;
+pub use retention_service::RetentionServiceHandler;
+pub use message_request_service::MessageRequestServiceHandler;
+pub use draft_service::DraftServiceHandler;
+pub use transaction_service::TransactionServiceHandler;
+pub use access_log_service::AccessLogServiceHandler;
+pub use history_service::HistoryServiceHandler; 
\ No newline at end of file
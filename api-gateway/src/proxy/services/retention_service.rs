@@ -0,0 +1,201 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use common::grpc_client::RetentionServiceGrpcClient;
+use common::proto::retention::{
+    LegalHold, LegalHoldTarget, PlaceLegalHoldRequest, RetentionAudit, RetentionPolicy,
+    SetRetentionPolicyRequest,
+};
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{extract_string_param, get_i64_param, success_response, timestamp_to_rfc3339};
+
+/// 消息保留策略服务处理器
+#[derive(Clone)]
+pub struct RetentionServiceHandler {
+    client: RetentionServiceGrpcClient,
+}
+
+impl RetentionServiceHandler {
+    /// 创建新的消息保留策略服务处理器
+    pub fn new(client: RetentionServiceGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理消息保留策略请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理消息保留策略请求: {} {}", method, path);
+
+        let operator_id = extract_string_param(&body, "userId", Some("user_id"))?;
+        let tenant_id = extract_string_param(&body, "tenantId", Some("tenant_id"))?;
+
+        // 路径格式: /api/retention/{resource}[/{id}]
+        let parts: Vec<&str> = path.split('/').collect();
+        let resource = parts.get(3).copied().unwrap_or_default();
+        let id = parts.get(4).copied().filter(|s| !s.is_empty());
+
+        match (method, resource, id) {
+            // 查询保留策略
+            (&Method::GET, "policy", None) => {
+                let response = self
+                    .client
+                    .get_retention_policy(&operator_id, &tenant_id)
+                    .await?;
+                let policy = response.policy.ok_or_else(|| anyhow::anyhow!("保留策略数据为空"))?;
+
+                Ok(success_response(self.convert_policy_to_json(&policy), StatusCode::OK))
+            }
+
+            // 设置保留策略
+            (&Method::PUT, "policy", None) => {
+                let retain_days = body
+                    .get("retainDays")
+                    .or_else(|| body.get("retain_days"))
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| anyhow::anyhow!("参数 retainDays 缺失或格式错误"))?;
+
+                let response = self
+                    .client
+                    .set_retention_policy(SetRetentionPolicyRequest {
+                        operator_id,
+                        tenant_id,
+                        retain_days: retain_days as i32,
+                    })
+                    .await?;
+                let policy = response.policy.ok_or_else(|| anyhow::anyhow!("保留策略数据为空"))?;
+
+                Ok(success_response(self.convert_policy_to_json(&policy), StatusCode::OK))
+            }
+
+            // 查询生效中的法律保全
+            (&Method::GET, "holds", None) => {
+                let response = self.client.list_legal_holds(&operator_id, &tenant_id).await?;
+                let holds: Vec<Value> = response
+                    .holds
+                    .iter()
+                    .map(|hold| self.convert_hold_to_json(hold))
+                    .collect();
+
+                Ok(success_response(holds, StatusCode::OK))
+            }
+
+            // 设置法律保全
+            (&Method::POST, "holds", None) => {
+                let target_type = match extract_string_param(&body, "targetType", Some("target_type"))?
+                    .to_uppercase()
+                    .as_str()
+                {
+                    "USER" => LegalHoldTarget::User,
+                    "CONVERSATION" => LegalHoldTarget::Conversation,
+                    other => return Err(anyhow::anyhow!("无效的保全对象类型: {}", other)),
+                };
+                let target_id = extract_string_param(&body, "targetId", Some("target_id"))?;
+                let reason = extract_string_param(&body, "reason", None).unwrap_or_default();
+
+                let response = self
+                    .client
+                    .place_legal_hold(PlaceLegalHoldRequest {
+                        operator_id,
+                        tenant_id,
+                        target_type: target_type as i32,
+                        target_id,
+                        reason,
+                    })
+                    .await?;
+                let hold = response.hold.ok_or_else(|| anyhow::anyhow!("法律保全数据为空"))?;
+
+                Ok(success_response(self.convert_hold_to_json(&hold), StatusCode::CREATED))
+            }
+
+            // 解除法律保全
+            (&Method::DELETE, "holds", Some(hold_id)) => {
+                let response = self
+                    .client
+                    .release_legal_hold(&operator_id, &tenant_id, hold_id)
+                    .await?;
+                let hold = response.hold.ok_or_else(|| anyhow::anyhow!("法律保全数据为空"))?;
+
+                Ok(success_response(self.convert_hold_to_json(&hold), StatusCode::OK))
+            }
+
+            // 分页查询审计记录
+            (&Method::GET, "audits", None) => {
+                let page = get_i64_param(&body, "page", 1);
+                let page_size = get_i64_param(&body, "pageSize", 20);
+
+                let response = self
+                    .client
+                    .list_retention_audits(&operator_id, &tenant_id, page, page_size)
+                    .await?;
+                let audits: Vec<Value> = response
+                    .audits
+                    .iter()
+                    .map(|audit| self.convert_audit_to_json(audit))
+                    .collect();
+
+                Ok(success_response(
+                    json!({
+                        "audits": audits,
+                        "total": response.total,
+                    }),
+                    StatusCode::OK,
+                ))
+            }
+
+            _ => {
+                error!("消息保留策略服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!("消息保留策略服务不支持的方法: {} {}", method, path))
+            }
+        }
+    }
+
+    /// 将保留策略转换为JSON
+    fn convert_policy_to_json(&self, policy: &RetentionPolicy) -> Value {
+        json!({
+            "tenantId": policy.tenant_id,
+            "retainDays": policy.retain_days,
+            "updatedBy": policy.updated_by,
+            "updatedAt": timestamp_to_rfc3339(&policy.updated_at),
+        })
+    }
+
+    /// 将法律保全转换为JSON
+    fn convert_hold_to_json(&self, hold: &LegalHold) -> Value {
+        let target_type = match LegalHoldTarget::try_from(hold.target_type) {
+            Ok(LegalHoldTarget::Conversation) => "CONVERSATION",
+            _ => "USER",
+        };
+
+        json!({
+            "id": hold.id,
+            "tenantId": hold.tenant_id,
+            "targetType": target_type,
+            "targetId": hold.target_id,
+            "reason": hold.reason,
+            "createdBy": hold.created_by,
+            "createdAt": timestamp_to_rfc3339(&hold.created_at),
+            "releasedAt": hold.released_at.as_ref().map(|_| timestamp_to_rfc3339(&hold.released_at)),
+        })
+    }
+
+    /// 将审计记录转换为JSON
+    fn convert_audit_to_json(&self, audit: &RetentionAudit) -> Value {
+        let detail: Value = serde_json::from_str(&audit.detail).unwrap_or(Value::Null);
+
+        json!({
+            "id": audit.id,
+            "action": audit.action,
+            "operatorId": audit.operator_id,
+            "detail": detail,
+            "affectedRows": audit.affected_rows,
+            "createdAt": timestamp_to_rfc3339(&audit.created_at),
+        })
+    }
+}
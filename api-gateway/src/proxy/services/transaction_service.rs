@@ -0,0 +1,117 @@
+use axum::{
+    body::Body,
+    http::{Method, Response, StatusCode},
+};
+use common::grpc_client::TransactionGrpcClient;
+use common::proto::transaction::{Transaction, TransactionClaim, TransactionStatus};
+use serde_json::{json, Value};
+use tracing::{debug, error};
+
+use super::common::{extract_string_param, success_response};
+
+/// 事务消息服务处理器
+#[derive(Clone)]
+pub struct TransactionServiceHandler {
+    client: TransactionGrpcClient,
+}
+
+impl TransactionServiceHandler {
+    /// 创建新的事务消息服务处理器
+    pub fn new(client: TransactionGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 处理事务消息相关请求
+    pub async fn handle_request(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        debug!("处理事务消息请求: {} {}", method, path);
+
+        let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+        // 路径格式: /api/transactions/{transactionId}[/claim]
+        let parts: Vec<&str> = path.split('/').collect();
+        let transaction_id = parts
+            .get(3)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("缺少事务ID"))?;
+        let action = parts.get(4).copied().unwrap_or_default();
+
+        match (method, action) {
+            // 查询事务状态和领取记录
+            (&Method::GET, "") => {
+                let response = self
+                    .client
+                    .get_transaction(transaction_id, &user_id)
+                    .await?;
+                let transaction = response
+                    .transaction
+                    .map(|transaction| self.convert_transaction_to_json(&transaction));
+
+                Ok(success_response(transaction, StatusCode::OK))
+            }
+
+            // 领取一份，重复领取返回第一次的结果
+            (&Method::POST, "claim") => {
+                let response = self
+                    .client
+                    .claim_transaction(transaction_id, &user_id)
+                    .await?;
+                let claim = response
+                    .claim
+                    .map(|claim| self.convert_claim_to_json(&claim));
+
+                Ok(success_response(
+                    json!({ "claim": claim, "duplicate": response.duplicate }),
+                    StatusCode::OK,
+                ))
+            }
+
+            _ => {
+                error!("事务消息服务不支持的方法: {} {}", method, path);
+                Err(anyhow::anyhow!(
+                    "事务消息服务不支持的方法: {} {}",
+                    method,
+                    path
+                ))
+            }
+        }
+    }
+
+    /// 将事务消息转换为JSON
+    fn convert_transaction_to_json(&self, transaction: &Transaction) -> Value {
+        let status = TransactionStatus::try_from(transaction.status)
+            .map(|status| status.as_str_name().to_lowercase())
+            .unwrap_or_default();
+        json!({
+            "id": transaction.id,
+            "provider": transaction.provider,
+            "senderId": transaction.sender_id,
+            "receiverId": transaction.receiver_id,
+            "msgType": transaction.msg_type,
+            "status": status,
+            "totalShares": transaction.total_shares,
+            "remainingShares": transaction.remaining_shares,
+            "expiresAt": transaction.expires_at,
+            "createdAt": transaction.created_at,
+            "claims": transaction
+                .claims
+                .iter()
+                .map(|claim| self.convert_claim_to_json(claim))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    /// 将领取记录转换为JSON，回执原样返回给客户端
+    fn convert_claim_to_json(&self, claim: &TransactionClaim) -> Value {
+        json!({
+            "claimerId": claim.claimer_id,
+            "shareIndex": claim.share_index,
+            "receipt": serde_json::from_str::<Value>(&claim.receipt).unwrap_or(Value::Null),
+            "claimedAt": claim.claimed_at,
+        })
+    }
+}
@@ -2,23 +2,25 @@ use axum::{
     body::Body,
     http::{Method, Response, StatusCode},
 };
-use common::grpc_client::UserServiceGrpcClient;
+use common::grpc_client::{StorageGrpcClient, UserServiceGrpcClient};
 use common::proto;
 use serde_json::{json, Value};
 use tracing::{error, debug};
 
-use super::common::{success_response, success_with_message, error_response, extract_string_param, get_optional_string, timestamp_to_rfc3339, format_timestamp};
+use super::common::{success_response, success_with_message, error_response, stream_list_response, extract_string_param, get_i64_param, get_optional_string, timestamp_to_rfc3339};
+use super::dto::{parse_params, UserDto, UserParams, UserSearchDto};
 
 /// 用户服务处理器
 #[derive(Clone)]
 pub struct UserServiceHandler {
     client: UserServiceGrpcClient,
+    storage: StorageGrpcClient,
 }
 
 impl UserServiceHandler {
     /// 创建新的用户服务处理器
-    pub fn new(client: UserServiceGrpcClient) -> Self {
-        Self { client }
+    pub fn new(client: UserServiceGrpcClient, storage: StorageGrpcClient) -> Self {
+        Self { client, storage }
     }
 
     /// 处理用户服务请求
@@ -33,15 +35,29 @@ impl UserServiceHandler {
         // 从路径提取方法名 - 格式: /api/users/[method]
         let method_name = path.split('/').nth(3).unwrap_or("unknown");
 
+        // 当前用户路由 - 格式: /api/users/me[/config|/devices|/storage]，userId已由网关从令牌写入
+        if method_name == "me" {
+            return self.handle_me(method, path, body).await;
+        }
+
+        // 公开资料 - 格式: /api/users/{id}/profile，viewerId已由网关从令牌写入
+        if path.split('/').nth(4) == Some("profile") {
+            if *method != Method::GET {
+                return Err(anyhow::anyhow!("未实现的方法: {} {}", method, path));
+            }
+            let viewer_id = extract_string_param(&body, "viewerId", Some("viewer_id"))?;
+            return self.get_public_profile(&viewer_id, method_name).await;
+        }
+
         match (method, method_name) {
             // 用户查询
             (&Method::GET, "getUserById") | (&Method::GET, "getUser") => {
-                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+                let params: UserParams = parse_params(&body)?;
 
-                let response = self.client.get_user(&user_id).await?;
+                let response = self.client.get_user(params.user_id).await?;
                 let user = response.user.ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
 
-                Ok(success_response(self.convert_user_to_json(&user), StatusCode::OK))
+                Ok(success_response(UserDto::from(&user), StatusCode::OK))
             }
 
             // 用户名查询
@@ -51,7 +67,7 @@ impl UserServiceHandler {
                 let response = self.client.get_user_by_username(&username).await?;
                 let user = response.user.ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
 
-                Ok(success_response(self.convert_user_to_json(&user), StatusCode::OK))
+                Ok(success_response(UserDto::from(&user), StatusCode::OK))
             }
 
             // 创建用户
@@ -80,47 +96,14 @@ impl UserServiceHandler {
                 let user = response.user.ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
 
                 Ok(success_with_message(
-                    self.convert_user_to_json(&user),
+                    UserDto::from(&user),
                     "用户创建成功",
                     StatusCode::CREATED
                 ))
             }
 
             // 更新用户
-            (&Method::PUT, "updateUser") | (&Method::PATCH, "updateUser") => {
-                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
-
-                let nickname = get_optional_string(&body, "nickname", None);
-                let email = get_optional_string(&body, "email", None);
-                let avatar_url = get_optional_string(&body, "avatarUrl", Some("avatar_url"));
-                let password = get_optional_string(&body, "password", None);
-                let address = get_optional_string(&body, "address", None);
-                let head_image = get_optional_string(&body, "head_image", None);
-                let head_image_thumb = get_optional_string(&body, "head_image_thumb", None);
-                let sex = get_optional_string(&body, "sex", None)
-                    .and_then(|s| s.parse::<i32>().ok());
-
-                let request = proto::user::UpdateUserRequest {
-                    user_id,
-                    nickname,
-                    email,
-                    avatar_url,
-                    password,
-                    address,
-                    head_image,
-                    head_image_thumb,
-                    sex,
-                };
-
-                let response = self.client.update_user(request).await?;
-                let user = response.user.ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
-
-                Ok(success_with_message(
-                    self.convert_user_to_json(&user),
-                    "用户更新成功",
-                    StatusCode::OK
-                ))
-            }
+            (&Method::PUT, "updateUser") | (&Method::PATCH, "updateUser") => self.update_user(&body).await,
 
             // 用户账号密码注册
             (&Method::POST, "registerByUsername") => {
@@ -163,7 +146,7 @@ impl UserServiceHandler {
                             .user
                             .ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
                         Ok(success_with_message(
-                            self.convert_user_to_json(&user),
+                            UserDto::from(&user),
                             "用户注册成功",
                             StatusCode::CREATED
                         ))
@@ -216,7 +199,7 @@ impl UserServiceHandler {
                             .user
                             .ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
                         Ok(success_with_message(
-                            self.convert_user_to_json(&user),
+                            UserDto::from(&user),
                             "用户注册成功",
                             StatusCode::CREATED
                         ))
@@ -269,7 +252,7 @@ impl UserServiceHandler {
                             .user
                             .ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
                         Ok(success_with_message(
-                            self.convert_user_to_json(&user),
+                            UserDto::from(&user),
                             "密码更新成功",
                             StatusCode::OK
                         ))
@@ -281,6 +264,129 @@ impl UserServiceHandler {
                 }
             }
 
+            // 搜索用户，viewerId已由网关从令牌写入
+            (&Method::GET, "search") => {
+                let viewer_id = extract_string_param(&body, "viewerId", Some("viewer_id"))?;
+                let query = extract_string_param(&body, "query", None)?;
+                let page = get_i64_param(&body, "page", 1) as i32;
+                let page_size = get_i64_param(&body, "pageSize", 10) as i32;
+
+                let response = self.client.search_users(&viewer_id, &query, page, page_size).await?;
+                Ok(success_response(
+                    UserSearchDto {
+                        users: response.users.iter().map(UserDto::from).collect(),
+                        total: response.total,
+                    },
+                    StatusCode::OK,
+                ))
+            }
+
+            // 注册前检查用户名、手机号是否可用，网关已做限流和响应时间混淆
+            (&Method::GET, "availability") => {
+                let username = get_optional_string(&body, "username", None).unwrap_or_default();
+                let phone = get_optional_string(&body, "phone", None).unwrap_or_default();
+                if username.trim().is_empty() && phone.trim().is_empty() {
+                    return Ok(error_response("用户名或者手机号不能为空", StatusCode::BAD_REQUEST));
+                }
+
+                let response = self.client.check_availability(&username, &phone).await?;
+                Ok(success_response(
+                    json!({
+                        "usernameAvailable": response.username_available,
+                        "phoneAvailable": response.phone_available,
+                    }),
+                    StatusCode::OK,
+                ))
+            }
+
+            // 摘要邮件中的退订链接，无需登录，令牌由用户服务校验
+            (&Method::GET, "unsubscribeDigest") | (&Method::POST, "unsubscribeDigest") => {
+                let token = get_optional_string(&body, "token", None).unwrap_or_default();
+                if token.is_empty() {
+                    return Ok(error_response("退订链接无效", StatusCode::BAD_REQUEST));
+                }
+
+                match self.client.unsubscribe_email_digest(&token).await {
+                    Ok(response) => Ok(success_with_message(
+                        json!({ "userId": response.user_id }),
+                        "已退订离线摘要邮件",
+                        StatusCode::OK,
+                    )),
+                    Err(err) => match err.downcast_ref::<tonic::Status>() {
+                        Some(status) if status.code() == tonic::Code::InvalidArgument => {
+                            Ok(error_response(status.message(), StatusCode::BAD_REQUEST))
+                        }
+                        _ => Err(err),
+                    },
+                }
+            }
+
+            // 向手机号发送邀请消息，对方未注册时注册后投递
+            (&Method::POST, "invite") => {
+                let sender_id = extract_string_param(&body, "senderId", Some("sender_id"))?;
+                let phone = extract_string_param(&body, "phone", None)?;
+                let content = extract_string_param(&body, "content", None)?;
+
+                let response = self.client.send_phone_invite(&sender_id, &phone, &content).await?;
+                Ok(success_response(
+                    json!({
+                        "inviteId": response.invite_id,
+                        "delivered": response.delivered,
+                    }),
+                    StatusCode::OK,
+                ))
+            }
+
+            // 点击名片查看名片用户资料，viewerId已由网关从令牌写入
+            (&Method::GET, "card") => {
+                let viewer_id = extract_string_param(&body, "viewerId", Some("viewer_id"))?;
+                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+                let card = self.client.resolve_contact_card(&viewer_id, &user_id).await?;
+                Ok(success_response(
+                    json!({
+                        "userId": card.user_id,
+                        "username": card.username,
+                        "nickname": card.nickname,
+                        "avatarUrl": card.avatar_url,
+                        "isFriend": card.is_friend,
+                        "lastActiveAt": timestamp_to_rfc3339(&card.last_active_at),
+                    }),
+                    StatusCode::OK,
+                ))
+            }
+
+            // 批量获取用户，viewerId已由网关从令牌写入
+            (&Method::POST, "getUsersByIds") => {
+                let viewer_id = extract_string_param(&body, "viewerId", Some("viewer_id"))?;
+                let user_ids = body
+                    .get("userIds")
+                    .or_else(|| body.get("user_ids"))
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| anyhow::anyhow!("缺少必要参数: userIds"))?
+                    .iter()
+                    .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                    .collect::<Vec<_>>();
+
+                let response = self.client.get_users_by_ids(&viewer_id, user_ids).await?;
+                Ok(stream_list_response(response.users, StatusCode::OK, |user, buf| {
+                    serde_json::to_writer(buf, &UserDto::from(user))
+                }))
+            }
+
+            // 获取用户配置，userId已由网关从令牌写入
+            (&Method::GET, "getConfig") => {
+                let user_id = extract_string_param(&body, "userId", Some("user_id"))?;
+
+                let response = self.client.get_user_config(&user_id).await?;
+                let config = response.config.ok_or_else(|| anyhow::anyhow!("用户配置为空"))?;
+
+                Ok(success_response(self.convert_config_to_json(&config), StatusCode::OK))
+            }
+
+            // 更新用户配置
+            (&Method::PUT, "updateConfig") => self.update_config(&body).await,
+
             // 其他未知方法
             _ => {
                 error!("未知的用户服务方法: {}", method_name);
@@ -289,25 +395,337 @@ impl UserServiceHandler {
         }
     }
 
-    /// 将用户消息转换为JSON
-    fn convert_user_to_json(&self, user: &proto::user::User) -> Value {
+    /// 获取公开资料，查看者无权查看的字段不返回
+    async fn get_public_profile(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        let profile = self.client.get_public_profile(viewer_id, user_id).await?;
+
+        let mut data = json!({
+            "userId": profile.user_id,
+            "username": profile.username,
+            "nickname": profile.nickname,
+            "avatarUrl": profile.avatar_url,
+            "headImage": profile.head_image,
+            "headImageThumb": profile.head_image_thumb,
+            "isFriend": profile.is_friend,
+        });
+        let optional_fields = [
+            ("email", profile.email.map(Value::from)),
+            ("phone", profile.phone.map(Value::from)),
+            ("sex", profile.sex.map(Value::from)),
+            ("address", profile.address.map(Value::from)),
+            (
+                "lastActiveAt",
+                Some(timestamp_to_rfc3339(&profile.last_active_at))
+                    .filter(|at| !at.is_empty())
+                    .map(Value::from),
+            ),
+        ];
+        for (name, value) in optional_fields {
+            if let Some(value) = value {
+                data[name] = value;
+            }
+        }
+
+        Ok(success_response(data, StatusCode::OK))
+    }
+
+    /// 更新用户资料
+    async fn update_user(&self, body: &Value) -> Result<Response<Body>, anyhow::Error> {
+        let user_id = extract_string_param(body, "userId", Some("user_id"))?;
+
+        let nickname = get_optional_string(body, "nickname", None);
+        let email = get_optional_string(body, "email", None);
+        let avatar_url = get_optional_string(body, "avatarUrl", Some("avatar_url"));
+        let password = get_optional_string(body, "password", None);
+        let address = get_optional_string(body, "address", None);
+        let head_image = get_optional_string(body, "head_image", None);
+        let head_image_thumb = get_optional_string(body, "head_image_thumb", None);
+        let sex = get_optional_string(body, "sex", None)
+            .and_then(|s| s.parse::<i32>().ok());
+
+        let request = proto::user::UpdateUserRequest {
+            user_id,
+            nickname,
+            email,
+            avatar_url,
+            password,
+            address,
+            head_image,
+            head_image_thumb,
+            sex,
+        };
+
+        let response = self.client.update_user(request).await?;
+        let user = response.user.ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
+
+        Ok(success_with_message(
+            UserDto::from(&user),
+            "用户更新成功",
+            StatusCode::OK
+        ))
+    }
+
+    /// 更新用户配置
+    async fn update_config(&self, body: &Value) -> Result<Response<Body>, anyhow::Error> {
+        let user_id = extract_string_param(body, "userId", Some("user_id"))?;
+
+        // 可见范围: everyone / friends / nobody
+        let last_seen_visibility = match get_optional_string(body, "lastSeenVisibility", Some("last_seen_visibility")) {
+            Some(value) => match proto::user::LastSeenVisibility::from_str_name(&value.to_uppercase()) {
+                Some(visibility) => Some(visibility as i32),
+                None => {
+                    return Ok(error_response("无效的最后活跃时间可见范围", StatusCode::BAD_REQUEST));
+                }
+            },
+            None => None,
+        };
+
+        // 公开资料字段的可见范围，取值同上
+        let mut field_visibilities = [None; 4];
+        let fields = [
+            ("emailVisibility", "email_visibility"),
+            ("phoneVisibility", "phone_visibility"),
+            ("sexVisibility", "sex_visibility"),
+            ("addressVisibility", "address_visibility"),
+        ];
+        for (visibility, (name, alias)) in field_visibilities.iter_mut().zip(fields) {
+            if let Some(value) = get_optional_string(body, name, Some(alias)) {
+                match proto::user::LastSeenVisibility::from_str_name(&value.to_uppercase()) {
+                    Some(v) => *visibility = Some(v as i32),
+                    None => return Ok(error_response("无效的资料可见范围", StatusCode::BAD_REQUEST)),
+                }
+            }
+        }
+        let [email_visibility, phone_visibility, sex_visibility, address_visibility] = field_visibilities;
+
+        // 离线摘要邮件频率: off / hourly / daily
+        let email_digest = match get_optional_string(body, "emailDigest", Some("email_digest")) {
+            Some(value) => match proto::user::EmailDigest::from_str_name(&value.to_uppercase()) {
+                Some(digest) => Some(digest as i32),
+                None => {
+                    return Ok(error_response("无效的摘要邮件频率", StatusCode::BAD_REQUEST));
+                }
+            },
+            None => None,
+        };
+
+        let get_bool = |name: &str, alias: &str| {
+            body.get(name)
+                .or_else(|| body.get(alias))
+                .and_then(|v| v.as_bool())
+        };
+
+        let request = proto::user::UpdateUserConfigRequest {
+            user_id,
+            last_seen_visibility,
+            search_opt_out: get_bool("searchOptOut", "search_opt_out"),
+            allow_id_search: get_bool("allowIdSearch", "allow_id_search"),
+            allow_phone_search: get_bool("allowPhoneSearch", "allow_phone_search"),
+            allow_card_share: get_bool("allowCardShare", "allow_card_share"),
+            email_visibility,
+            phone_visibility,
+            sex_visibility,
+            address_visibility,
+            email_digest,
+        };
+
+        let response = self.client.update_user_config(request).await?;
+        let config = response.config.ok_or_else(|| anyhow::anyhow!("用户配置为空"))?;
+
+        Ok(success_response(self.convert_config_to_json(&config), StatusCode::OK))
+    }
+
+    /// 处理当前用户请求
+    async fn handle_me(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Value,
+    ) -> Result<Response<Body>, anyhow::Error> {
+        let resource = path.split('/').nth(4).filter(|s| !s.is_empty());
+        let user_id = extract_string_param(&body, "userId", None)?;
+
+        match (method, resource) {
+            // 当前用户资料
+            (&Method::GET, None) => {
+                let response = self.client.get_user(&user_id).await?;
+                let user = response.user.ok_or_else(|| anyhow::anyhow!("用户数据为空"))?;
+
+                Ok(success_response(UserDto::from(&user), StatusCode::OK))
+            }
+
+            // 当前用户配置
+            (&Method::GET, Some("config")) => {
+                let response = self.client.get_user_config(&user_id).await?;
+                let config = response.config.ok_or_else(|| anyhow::anyhow!("用户配置为空"))?;
+
+                Ok(success_response(self.convert_config_to_json(&config), StatusCode::OK))
+            }
+
+            // 当前用户登录过的设备
+            (&Method::GET, Some("devices")) => {
+                let response = self.client.list_user_devices(&user_id).await?;
+                let devices = response
+                    .devices
+                    .iter()
+                    .map(|device| {
+                        json!({
+                            "deviceId": device.device_id,
+                            "deviceName": device.device_name,
+                            "lastIp": device.last_ip,
+                            "lastLoginAt": timestamp_to_rfc3339(&device.last_login_at),
+                            "trusted": device.trusted,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(success_response(devices, StatusCode::OK))
+            }
+
+            // 当前用户附件存储用量
+            (&Method::GET, Some("storage")) => {
+                let usage = self.storage.get_storage_usage(&user_id).await?;
+                Ok(success_response(self.convert_storage_usage_to_json(&usage), StatusCode::OK))
+            }
+
+            // 确认附件上传完成，未确认的预占在上传地址过期后释放
+            (&Method::POST, Some("storage"))
+                if path.split('/').nth(5) == Some("uploads") && path.split('/').nth(6) == Some("complete") =>
+            {
+                let object_key = extract_string_param(&body, "objectKey", Some("object_key"))?;
+                match self.storage.complete_upload(&user_id, &object_key).await {
+                    Ok(response) => Ok(success_response(
+                        json!({
+                            "objectKey": response.object_key,
+                            "usage": response.usage.as_ref().map(|usage| self.convert_storage_usage_to_json(usage)),
+                        }),
+                        StatusCode::OK,
+                    )),
+                    Err(err) => match err.downcast_ref::<tonic::Status>() {
+                        Some(status) if status.code() == tonic::Code::NotFound => {
+                            Ok(error_response(status.message(), StatusCode::NOT_FOUND))
+                        }
+                        _ => Err(err),
+                    },
+                }
+            }
+
+            // 申请附件上传地址，超出存储配额时返回507
+            (&Method::POST, Some("storage")) if path.split('/').nth(5) == Some("uploads") => {
+                let file_name = get_optional_string(&body, "fileName", Some("file_name")).unwrap_or_default();
+                let mime_type = extract_string_param(&body, "mimeType", Some("mime_type"))?;
+                let size = get_i64_param(&body, "size", 0);
+                if size <= 0 {
+                    return Ok(error_response("附件大小无效", StatusCode::BAD_REQUEST));
+                }
+
+                let request = proto::storage::CreateUploadUrlRequest {
+                    user_id,
+                    file_name,
+                    mime_type,
+                    size,
+                };
+                match self.storage.create_upload_url(request).await {
+                    Ok(response) => Ok(success_response(
+                        json!({
+                            "objectKey": response.object_key,
+                            "uploadUrl": response.upload_url,
+                            "expiresAt": timestamp_to_rfc3339(&response.expires_at),
+                            "usage": response.usage.as_ref().map(|usage| self.convert_storage_usage_to_json(usage)),
+                        }),
+                        StatusCode::OK,
+                    )),
+                    Err(err) => match err.downcast_ref::<tonic::Status>() {
+                        Some(status) if status.code() == tonic::Code::ResourceExhausted => {
+                            Ok(error_response(status.message(), StatusCode::INSUFFICIENT_STORAGE))
+                        }
+                        Some(status) if status.code() == tonic::Code::InvalidArgument => {
+                            Ok(error_response(status.message(), StatusCode::BAD_REQUEST))
+                        }
+                        _ => Err(err),
+                    },
+                }
+            }
+
+            // 修改当前用户资料
+            (&Method::PUT, None) | (&Method::PATCH, None) => self.update_user(&body).await,
+
+            // 修改当前用户配置
+            (&Method::PUT, Some("config")) => self.update_config(&body).await,
+
+            // 当前用户的消息搜索排除设置
+            (&Method::GET, Some("search-exclusions")) => {
+                let response = self.client.get_search_exclusions(&user_id).await?;
+                Ok(success_response(self.convert_search_exclusions_to_json(&response), StatusCode::OK))
+            }
+
+            // 设置会话是否排除在消息搜索之外
+            (&Method::PUT, Some("search-exclusions")) => {
+                let conversation_id = extract_string_param(&body, "conversationId", Some("conversation_id"))?;
+                let opt_out = body
+                    .get("optOut")
+                    .or_else(|| body.get("opt_out"))
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+                let response = self
+                    .client
+                    .set_conversation_search_opt_out(&user_id, &conversation_id, opt_out)
+                    .await?;
+                Ok(success_response(self.convert_search_exclusions_to_json(&response), StatusCode::OK))
+            }
+
+            _ => {
+                error!("未知的当前用户方法: {} {}", method, path);
+                Err(anyhow::anyhow!("未实现的方法: {} {}", method, path))
+            }
+        }
+    }
+
+    /// 将附件存储用量转换为JSON
+    fn convert_storage_usage_to_json(&self, usage: &proto::storage::StorageUsage) -> Value {
+        json!({
+            "userId": usage.user_id,
+            "usedBytes": usage.used_bytes,
+            "quotaBytes": usage.quota_bytes,
+            "attachmentCount": usage.attachment_count,
+        })
+    }
+
+    /// 将消息搜索排除设置转换为JSON
+    fn convert_search_exclusions_to_json(&self, exclusions: &proto::user::SearchExclusionsResponse) -> Value {
+        json!({
+            "userId": exclusions.user_id,
+            "searchOptOut": exclusions.search_opt_out,
+            "conversationIds": exclusions.conversation_ids,
+        })
+    }
+
+    /// 将用户配置转换为JSON
+    fn convert_config_to_json(&self, config: &proto::user::UserConfig) -> Value {
+        let visibility = |value: i32| {
+            proto::user::LastSeenVisibility::try_from(value)
+                .map(|v| v.as_str_name().to_lowercase())
+                .unwrap_or_default()
+        };
+
         json!({
-            "id": user.id,
-            "username": user.username,
-            "email": user.email,
-            "nickname": user.nickname,
-            "avatarUrl": user.avatar_url,
-            "createdAt": format_timestamp(user.created_at.clone()),
-            "updatedAt": format_timestamp(user.updated_at.clone()),
-            "phone" : user.phone,
-            "address" : user.address,
-            "head_image" : user.head_image,
-            "head_image_thumb" : user.head_image_thumb,
-            "sex" : user.sex,
-            "user_stat" : user.user_stat,
-            "tenant_id" : user.tenant_id,
-            "last_login_time" : format_timestamp(user.last_login_time.clone()),
-            "user_idx" : user.user_idx,
+            "userId": config.user_id,
+            "lastSeenVisibility": visibility(config.last_seen_visibility),
+            "searchOptOut": config.search_opt_out,
+            "allowIdSearch": config.allow_id_search,
+            "allowPhoneSearch": config.allow_phone_search,
+            "allowCardShare": config.allow_card_share,
+            "emailVisibility": visibility(config.email_visibility),
+            "phoneVisibility": visibility(config.phone_visibility),
+            "sexVisibility": visibility(config.sex_visibility),
+            "addressVisibility": visibility(config.address_visibility),
+            "emailDigest": proto::user::EmailDigest::try_from(config.email_digest)
+                .map(|v| v.as_str_name().to_lowercase())
+                .unwrap_or_default(),
         })
     }
 } 
\ No newline at end of file
@@ -1,24 +1,30 @@
 use crate::auth::middleware::auth_middleware;
 use crate::config::CONFIG;
+use crate::middleware::{coalesce_get, RequestCoalescer};
 use crate::proxy::service_proxy::ServiceProxy;
 use crate::{auth::controller, UserServiceGrpcClient};
 use crate::api_doc::api_docs;
+use crate::ops::maintenance::{self, MaintenanceAdmin};
+use crate::ops::{self, OpsMetricsHub};
 use axum::body::Body;
+use axum::extract::Query;
 use axum::http::{Request, StatusCode};
 use axum::middleware;
 use axum::response::IntoResponse;
 use axum::routing::{any, get, post};
 use axum::Json;
 use axum::Router;
-use common::grpc_client::GrpcServiceClient;
+use common::grpc_client::{AuthServiceGrpcClient, GrpcServiceClient};
+use common::time_sync::{now_millis, TimeSyncRequest, TimeSyncResponse};
 use serde_json::json;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
 /// 路由构建器
 pub struct RouterBuilder {
     service_proxy: Arc<ServiceProxy>,
     user_client: Arc<UserServiceGrpcClient>,
+    auth_client: Arc<AuthServiceGrpcClient>,
     router: Router,
 }
 
@@ -28,6 +34,9 @@ impl RouterBuilder {
         // 创建用户服务客户端
         let service_client = GrpcServiceClient::from_env("user-service");
         let user_client = Arc::new(UserServiceGrpcClient::new(service_client));
+
+        // 创建令牌服务客户端，用于登出时吊销令牌
+        let auth_client = Arc::new(AuthServiceGrpcClient::from_env());
         
         // 创建基础路由器
         let router = Router::new();
@@ -35,6 +44,7 @@ impl RouterBuilder {
         Self {
             service_proxy,
             user_client,
+            auth_client,
             router,
         }
     }
@@ -54,6 +64,9 @@ impl RouterBuilder {
         // 添加API文档路由
         router = Self::add_api_docs_routes(router);
 
+        // 转发路由共享同一个GET请求合并器，合并在认证之后进行，按用户区分请求者
+        let coalescer = RequestCoalescer::default();
+
         // 遍历路由配置，添加到路由器中
         for route in &routes_config.routes {
             let path = route.path_prefix.clone();
@@ -71,6 +84,11 @@ impl RouterBuilder {
                 }
             });
 
+            let handler = handler.layer(middleware::from_fn_with_state(
+                coalescer.clone(),
+                coalesce_get,
+            ));
+
             // 根据是否需要认证添加中间件
             let route_path = path.clone();
             if require_auth {
@@ -93,9 +111,35 @@ impl RouterBuilder {
             }
         }
 
+        // 添加运维看板实时指标推送和维护模式管理，需要系统配置中的Redis连接
+        let (ops_hub, maintenance_admin) = match common::config::AppConfig::new() {
+            Ok(app_config) => (
+                OpsMetricsHub::start(&app_config),
+                MaintenanceAdmin::new(&app_config),
+            ),
+            Err(e) => {
+                warn!("读取系统配置失败，运维指标推送和维护模式管理不可用: {}", e);
+                (OpsMetricsHub::disabled(), MaintenanceAdmin::disabled())
+            }
+        };
+        router = router.route(
+            "/api/admin/metrics/stream",
+            get(ops::metrics_stream)
+                .layer(middleware::from_fn(auth_middleware))
+                .layer(axum::Extension(ops_hub)),
+        );
+        router = router.route(
+            "/api/admin/maintenance",
+            get(maintenance::get_maintenance)
+                .put(maintenance::set_maintenance)
+                .layer(middleware::from_fn(auth_middleware))
+                .layer(axum::Extension(maintenance_admin)),
+        );
+
         // 添加健康检查和指标端点
         router = router
             .route("/health", get(health_check))
+            .route("/api/time", get(server_time))
             .route(
                 &config.metrics_endpoint,
                 get(crate::metrics::get_metrics_handler),
@@ -104,6 +148,7 @@ impl RouterBuilder {
         // 最后添加全局中间件
         let user_client = self.user_client.clone();
         router = router.layer(axum::Extension(user_client));
+        router = router.layer(axum::Extension(self.auth_client.clone()));
         
         Ok(router)
     }
@@ -118,10 +163,22 @@ impl RouterBuilder {
                 "/api/user/login",
                 post(controller::login),
             )
+            .route(
+                "/api/user/login/verify",
+                post(controller::verify_login),
+            )
             .route(
                 "/api/user/refresh",
                 post(controller::refresh_token),
             )
+            .route(
+                "/api/user/logout",
+                post(controller::logout),
+            )
+            .route(
+                "/api/user/userinfo",
+                get(controller::userinfo).layer(middleware::from_fn(auth_middleware)),
+            )
     }
 
     /// 添加API文档相关路由
@@ -146,3 +203,9 @@ async fn health_check() -> impl IntoResponse {
         }
     })))
 }
+
+/// 对时接口，返回服务端收发时间（UTC毫秒），客户端结合本地收发时间估算时钟偏差和往返耗时
+async fn server_time(Query(request): Query<TimeSyncRequest>) -> impl IntoResponse {
+    let received_at = now_millis();
+    (StatusCode::OK, Json(TimeSyncResponse::new(&request, received_at)))
+}
@@ -8,12 +8,76 @@ use std::fmt::Debug;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use futures::FutureExt;
 use common::message::GroupMemSeq;
 
 use common::config::AppConfig;
+use common::dependency_health::{DependencyHealth, HealthWatcher};
 use common::error::Error;
+use common::seq_fallback::SeqKind;
 
+#[cfg(any(test, feature = "test-util"))]
+mod memory;
 mod redis;
+mod seq;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use memory::MemoryCache;
+pub use seq::SeqAllocator;
+
+/// 群组单日统计数据
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupDailyStats {
+    /// 群组ID
+    pub group_id: String,
+    /// 日期，格式 yyyyMMdd（UTC）
+    pub date: String,
+    /// 当日消息数
+    pub message_count: i64,
+    /// 当日发言成员数（基于HyperLogLog，近似值）
+    pub active_members: i64,
+    /// 当日加入人数
+    pub joins: i64,
+    /// 当日退出/被移除人数
+    pub leaves: i64,
+}
+
+/// 租户下单个用户一个小时内的消息用量
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageUsage {
+    /// 租户ID，未归属租户时为空
+    pub tenant_id: String,
+    /// 发送者ID
+    pub user_id: String,
+    /// 消息数
+    pub messages: i64,
+    /// 消息内容字节数
+    pub bytes: i64,
+}
+
+/// 登录二次验证挑战
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoginChallenge {
+    /// 用户ID
+    pub user_id: String,
+    /// 验证码
+    pub code: String,
+    /// 已尝试次数
+    pub attempts: i64,
+}
+
+/// 图片衍生数据（缩略图、blurhash）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageDerivatives {
+    /// 缩略图的OSS对象键，发送消息时签名为下载地址
+    pub thumbnail_key: String,
+    /// blurhash占位符
+    pub blurhash: String,
+    /// 原图宽度
+    pub width: u32,
+    /// 原图高度
+    pub height: u32,
+}
 
 /// 缓存特征
 /// 
@@ -49,9 +113,32 @@ pub trait Cache: Sync + Send + Debug {
     /// 增加用户的发送序列号
     async fn incr_send_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error>;
 
+    /// 增加会话序列号，返回当前序列号、最大序列号和是否预留了新的一段
+    ///
+    /// 会话的序列号不存在（新会话或Redis数据丢失）时，`watermark` 为None返回None，
+    /// 由调用方查出水位后再次调用，从水位之后继续分配
+    async fn incr_conversation_seq(
+        &self,
+        conversation: &str,
+        watermark: Option<i64>,
+    ) -> Result<Option<(i64, i64, bool)>, Error>;
+
     /// 增加群组成员序列号
     async fn incr_group_seq(&self, members: Vec<String>) -> Result<Vec<GroupMemSeq>, Error>;
 
+    /// 把用户的当前和最大序列号提高到指定值，已经更大时不变
+    /// 用于Redis恢复后同步降级期间由Postgres分配的序列号
+    async fn raise_seq(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Result<(), Error>;
+
+    /// 分批扫描用户的最大序列号，用于初始化降级分配的水位
+    /// 返回下一次扫描的游标（0表示扫描结束）和本批的用户ID、最大序列号
+    async fn scan_max_seqs(
+        &self,
+        kind: SeqKind,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, i64)>), Error>;
+
     /// 查询群组成员ID
     async fn query_group_members_id(&self, group_id: &str) -> Result<Vec<String>, Error>;
 
@@ -78,6 +165,9 @@ pub trait Cache: Sync + Send + Debug {
     /// 删除群组所有成员
     async fn del_group_members(&self, group_id: &str) -> Result<(), Error>;
 
+    /// 查询群组成员版本号，成员每次变化时递增，没有记录时为0
+    async fn group_members_version(&self, group_id: &str) -> Result<i64, Error>;
+
     /// 保存注册验证码
     async fn save_register_code(&self, email: &str, code: &str) -> Result<(), Error>;
 
@@ -95,6 +185,188 @@ pub trait Cache: Sync + Send + Debug {
 
     /// 在线用户计数
     async fn online_count(&self) -> Result<i64, Error>;
+
+    /// 批量查询用户是否在线，结果与 `user_ids` 一一对应
+    async fn online_status(&self, user_ids: &[String]) -> Result<Vec<bool>, Error>;
+
+    /// 保存登录二次验证挑战，过期时间与注册验证码一致
+    async fn save_login_challenge(
+        &self,
+        challenge_id: &str,
+        user_id: &str,
+        code: &str,
+    ) -> Result<(), Error>;
+
+    /// 获取登录二次验证挑战
+    async fn get_login_challenge(&self, challenge_id: &str) -> Result<Option<LoginChallenge>, Error>;
+
+    /// 累加登录二次验证的尝试次数，返回累加后的次数
+    async fn incr_login_challenge_attempts(&self, challenge_id: &str) -> Result<i64, Error>;
+
+    /// 删除登录二次验证挑战
+    async fn del_login_challenge(&self, challenge_id: &str) -> Result<(), Error>;
+
+    /// 吊销单个令牌，`ttl` 为令牌剩余有效期（秒）
+    async fn revoke_token(&self, token_id: &str, ttl: i64) -> Result<(), Error>;
+
+    /// 吊销用户在 `before` 之前签发的所有令牌
+    async fn revoke_user_tokens(&self, user_id: &str, before: i64, ttl: i64) -> Result<(), Error>;
+
+    /// 检查令牌是否已被吊销
+    async fn is_token_revoked(
+        &self,
+        token_id: &str,
+        user_id: &str,
+        issued_at: i64,
+    ) -> Result<bool, Error>;
+
+    /// 记录用户最后活跃时间（秒级时间戳），同一用户的多次写入只保留最新值
+    async fn record_user_active(&self, user_id: &str, timestamp: i64) -> Result<(), Error>;
+
+    /// 取出最多 `count` 条待落库的用户最后活跃时间
+    async fn drain_user_active(&self, count: usize) -> Result<Vec<(String, i64)>, Error>;
+
+    /// 记录一条群消息，累加当日消息数并记录发言成员
+    async fn incr_group_msg_stats(
+        &self,
+        group_id: &str,
+        sender_id: &str,
+        date: &str,
+    ) -> Result<(), Error>;
+
+    /// 累加群组当日加入/退出人数
+    async fn incr_group_churn(
+        &self,
+        group_id: &str,
+        joins: i64,
+        leaves: i64,
+        date: &str,
+    ) -> Result<(), Error>;
+
+    /// 查询群组某日的统计数据
+    async fn get_group_daily_stats(
+        &self,
+        group_id: &str,
+        date: &str,
+    ) -> Result<GroupDailyStats, Error>;
+
+    /// 查询某日有统计数据变更的群组ID，供每日汇总使用
+    async fn group_stats_dirty_groups(&self, date: &str) -> Result<Vec<String>, Error>;
+
+    /// 累加某小时的消息用量，`hour` 格式为 yyyyMMddHH（UTC）
+    async fn incr_message_usage(&self, hour: &str, usage: &[MessageUsage]) -> Result<(), Error>;
+
+    /// 查询某小时的消息用量
+    async fn get_message_usage(&self, hour: &str) -> Result<Vec<MessageUsage>, Error>;
+
+    /// 有待汇总消息用量的小时
+    async fn message_usage_hours(&self) -> Result<Vec<String>, Error>;
+
+    /// 汇总完成后将某小时移出待汇总集合，用量本身保留到过期，之后再写入的用量会重新加入集合
+    async fn finish_message_usage_hour(&self, hour: &str) -> Result<(), Error>;
+
+    /// 将附件标记为已拦截（扫描发现威胁），`reason` 为拦截原因
+    async fn block_attachment(&self, object_key: &str, reason: &str) -> Result<(), Error>;
+
+    /// 查询附件的拦截原因，未拦截时返回None
+    async fn get_attachment_block(&self, object_key: &str) -> Result<Option<String>, Error>;
+
+    /// 保存图片的缩略图等衍生数据
+    async fn save_image_derivatives(
+        &self,
+        object_key: &str,
+        derivatives: &ImageDerivatives,
+    ) -> Result<(), Error>;
+
+    /// 查询图片的衍生数据，尚未生成时返回None
+    async fn get_image_derivatives(&self, object_key: &str)
+        -> Result<Option<ImageDerivatives>, Error>;
+
+    /// 保存链接预览（JSON），抓取失败时保存空字符串以避免重复抓取
+    async fn save_link_preview(&self, url: &str, preview: &str, ttl: i64) -> Result<(), Error>;
+
+    /// 查询链接预览，未缓存时返回None
+    async fn get_link_preview(&self, url: &str) -> Result<Option<String>, Error>;
+
+    /// 保存设备的历史消息回填进度，断线或取消后可从该进度继续
+    async fn save_backfill_cursor(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        cursor: &str,
+    ) -> Result<(), Error>;
+
+    /// 查询设备的历史消息回填进度，未开始或已完成时返回None
+    async fn get_backfill_cursor(&self, user_id: &str, device_id: &str)
+        -> Result<Option<String>, Error>;
+
+    /// 删除设备的历史消息回填进度
+    async fn delete_backfill_cursor(&self, user_id: &str, device_id: &str) -> Result<(), Error>;
+
+    /// 标记设备的历史消息回填已被取消，正在推送的回填任务会在下一批前停止
+    async fn cancel_backfill(&self, user_id: &str, device_id: &str) -> Result<(), Error>;
+
+    /// 读取并清除取消标记，返回是否已被取消
+    async fn take_backfill_cancel(&self, user_id: &str, device_id: &str) -> Result<bool, Error>;
+
+    /// 保存用户接收盒重建已完成到的接收序号，中断后从该序号之后继续
+    async fn save_rebuild_progress(&self, user_id: &str, seq: i64) -> Result<(), Error>;
+
+    /// 查询用户接收盒重建进度，未开始或已完成时返回None
+    async fn get_rebuild_progress(&self, user_id: &str) -> Result<Option<i64>, Error>;
+
+    /// 删除用户接收盒重建进度
+    async fn delete_rebuild_progress(&self, user_id: &str) -> Result<(), Error>;
+
+    /// 保存用户的会话草稿，并标记该用户的草稿待持久化
+    async fn save_draft(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        draft: &str,
+        ttl: i64,
+    ) -> Result<(), Error>;
+
+    /// 查询用户的全部会话草稿，返回 (会话ID, 草稿) 列表
+    async fn get_drafts(&self, user_id: &str) -> Result<Vec<(String, String)>, Error>;
+
+    /// 删除用户的会话草稿，并标记该用户的草稿待持久化
+    async fn del_draft(&self, user_id: &str, conversation_id: &str) -> Result<(), Error>;
+
+    /// 取出草稿有变更、待持久化的用户
+    async fn drain_dirty_drafts(&self, count: usize) -> Result<Vec<String>, Error>;
+
+    /// 推进用户在会话中的已读位置，只有新位置大于当前位置时才更新，返回是否已更新
+    async fn advance_read_cursor(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        read_seq: i64,
+    ) -> Result<bool, Error>;
+
+    /// 查询用户在会话中的已读位置，没有记录时返回0
+    async fn get_read_cursor(&self, user_id: &str, conversation_id: &str) -> Result<i64, Error>;
+
+    /// 查询用户全部会话的已读位置，返回 (会话ID, 已读位置) 列表
+    async fn get_read_cursors(&self, user_id: &str) -> Result<Vec<(String, i64)>, Error>;
+
+    /// 记录查看者在当前窗口内查看过的用户资料，返回窗口内查看过的不同用户数，
+    /// 窗口从第一次查看开始计时，`window` 为窗口长度（秒）
+    async fn record_profile_view(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+        window: i64,
+    ) -> Result<i64, Error>;
+
+    /// 保存消息服务实例的消费采样（JSON），全部实例停止上报 `ttl` 秒后自动清除
+    async fn save_ops_sample(&self, instance_id: &str, sample: &str, ttl: i64) -> Result<(), Error>;
+
+    /// 查询各消息服务实例最近一次的消费采样，包括已停止上报的实例
+    async fn get_ops_samples(&self) -> Result<Vec<String>, Error>;
+
+    /// 探测Redis是否可用，连接已断开时重新连接
+    async fn ping(&self) -> Result<(), Error>;
 }
 
 /// 根据配置创建缓存实例
@@ -107,3 +379,38 @@ pub trait Cache: Sync + Send + Debug {
 pub fn cache(config: &AppConfig) -> Arc<dyn Cache> {
     Arc::new(redis::RedisCache::from_config(config))
 }
+
+/// 启动Redis健康探测，返回共享的健康状态
+///
+/// 未启用降级时不探测，始终视为健康
+///
+/// # 参数
+/// * `config` - 应用配置
+/// * `cache` - 用于探测的缓存实例
+pub fn redis_health(config: &AppConfig, cache: Arc<dyn Cache>) -> DependencyHealth {
+    if !config.degradation.enabled {
+        return DependencyHealth::always_healthy("redis");
+    }
+    let (watcher, health) = HealthWatcher::new(
+        "redis",
+        &config.degradation,
+        Box::new(move || {
+            let cache = cache.clone();
+            async move { cache.ping().await }.boxed()
+        }),
+    );
+    watcher.start();
+    health
+}
+
+/// 将启用键前缀之前写入的旧键迁移到配置的命名空间下
+///
+/// # 参数
+/// * `config` - 应用配置
+///
+/// # 返回
+/// * 成功迁移的键数量
+pub async fn migrate_key_prefix(config: &AppConfig) -> Result<u64, Error> {
+    let client = ::redis::Client::open(config.redis.url())?;
+    redis::migrate::migrate_legacy_keys(&client, &config.redis.key_prefix()).await
+}
@@ -0,0 +1,878 @@
+/**
+ * 内存缓存实现
+ *
+ * 供下游服务的单元测试使用，不依赖Redis。行为与Redis实现保持一致：
+ * 序列号按步长分段分配，群成员变化时递增版本号，已读位置只前进不后退。
+ * 不模拟键的过期时间，需要过期效果的测试可以直接调用对应的删除方法。
+ */
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use common::error::Error;
+use common::message::GroupMemSeq;
+use common::seq_fallback::SeqKind;
+
+use crate::{Cache, GroupDailyStats, ImageDerivatives, LoginChallenge, MessageUsage};
+
+/// 默认序列号步长，与Redis实现一致
+const DEFAULT_SEQ_STEP: i64 = 5000;
+
+/// 当前序列号和最大序列号
+#[derive(Debug, Clone, Copy, Default)]
+struct SeqPair {
+    cur: i64,
+    max: i64,
+}
+
+impl SeqPair {
+    /// 与Redis的序列号脚本一致：当前序列号加一，超过最大序列号时最大序列号增加一个步长
+    fn increase(&mut self, step: i64) -> (i64, i64, bool) {
+        self.cur += 1;
+        if self.max == 0 {
+            self.max = step;
+        }
+        let mut updated = false;
+        if self.cur > self.max {
+            self.max += step;
+            updated = true;
+        }
+        (self.cur, self.max, updated)
+    }
+
+    fn raise(&mut self, seq: i64) {
+        self.cur = self.cur.max(seq);
+        self.max = self.max.max(seq);
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    seq_loaded: bool,
+    seqs: HashMap<String, SeqPair>,
+    send_seqs: HashMap<String, SeqPair>,
+    conv_seqs: HashMap<String, SeqPair>,
+    group_members: HashMap<String, HashSet<String>>,
+    group_versions: HashMap<String, i64>,
+    register_codes: HashMap<String, String>,
+    online: HashSet<String>,
+    login_challenges: HashMap<String, LoginChallenge>,
+    revoked_tokens: HashSet<String>,
+    user_tokens_revoked_before: HashMap<String, i64>,
+    user_active: HashMap<String, i64>,
+    group_stats: HashMap<(String, String), GroupDailyStats>,
+    group_active: HashMap<(String, String), HashSet<String>>,
+    group_stats_dirty: HashMap<String, HashSet<String>>,
+    message_usage: BTreeMap<String, HashMap<(String, String), MessageUsage>>,
+    message_usage_hours: HashSet<String>,
+    blocked_attachments: HashMap<String, String>,
+    image_derivatives: HashMap<String, ImageDerivatives>,
+    link_previews: HashMap<String, String>,
+    backfill_cursors: HashMap<(String, String), String>,
+    backfill_cancels: HashSet<(String, String)>,
+    rebuild_progress: HashMap<String, i64>,
+    drafts: HashMap<String, BTreeMap<String, String>>,
+    dirty_drafts: HashSet<String>,
+    read_cursors: HashMap<String, BTreeMap<String, i64>>,
+    profile_views: HashMap<String, HashSet<String>>,
+    ops_samples: BTreeMap<String, String>,
+}
+
+impl State {
+    fn bump_group_version(&mut self, group_id: &str) {
+        *self.group_versions.entry(group_id.to_string()).or_default() += 1;
+    }
+}
+
+/// 内存缓存，所有数据保存在进程内
+#[derive(Debug)]
+pub struct MemoryCache {
+    state: Mutex<State>,
+    seq_step: i64,
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryCache {
+    /// 创建空的内存缓存
+    pub fn new() -> Self {
+        Self::with_seq_step(DEFAULT_SEQ_STEP)
+    }
+
+    /// 使用指定的序列号步长，便于测试序列号落库的时机
+    pub fn with_seq_step(seq_step: i64) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            seq_step: seq_step.max(1),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn check_seq_loaded(&self) -> Result<bool, Error> {
+        Ok(!self.state().seq_loaded)
+    }
+
+    async fn set_seq_loaded(&self) -> Result<(), Error> {
+        self.state().seq_loaded = true;
+        Ok(())
+    }
+
+    async fn set_seq(&self, max_seq: &[(String, i64, i64)]) -> Result<(), Error> {
+        let mut state = self.state();
+        for (user_id, send_max_seq, rec_max_seq) in max_seq {
+            state.send_seqs.insert(
+                user_id.clone(),
+                SeqPair {
+                    cur: *send_max_seq,
+                    max: *send_max_seq,
+                },
+            );
+            state.seqs.insert(
+                user_id.clone(),
+                SeqPair {
+                    cur: *rec_max_seq,
+                    max: *rec_max_seq,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    async fn set_send_seq(&self, max_seq: &[(String, i64)]) -> Result<(), Error> {
+        let mut state = self.state();
+        for (user_id, max_seq) in max_seq {
+            state.send_seqs.insert(
+                user_id.clone(),
+                SeqPair {
+                    cur: *max_seq,
+                    max: *max_seq,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    async fn get_seq(&self, user_id: &str) -> Result<i64, Error> {
+        Ok(self.state().seqs.get(user_id).map_or(0, |seq| seq.cur))
+    }
+
+    async fn get_cur_seq(&self, user_id: &str) -> Result<(i64, i64), Error> {
+        let state = self.state();
+        Ok((
+            state.seqs.get(user_id).map_or(0, |seq| seq.cur),
+            state.send_seqs.get(user_id).map_or(0, |seq| seq.cur),
+        ))
+    }
+
+    async fn get_send_seq(&self, user_id: &str) -> Result<(i64, i64), Error> {
+        let seq = self
+            .state()
+            .send_seqs
+            .get(user_id)
+            .copied()
+            .unwrap_or_default();
+        Ok((seq.cur, seq.max))
+    }
+
+    async fn increase_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error> {
+        Ok(self
+            .state()
+            .seqs
+            .entry(user_id.to_string())
+            .or_default()
+            .increase(self.seq_step))
+    }
+
+    async fn incr_send_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error> {
+        Ok(self
+            .state()
+            .send_seqs
+            .entry(user_id.to_string())
+            .or_default()
+            .increase(self.seq_step))
+    }
+
+    async fn incr_conversation_seq(
+        &self,
+        conversation: &str,
+        watermark: Option<i64>,
+    ) -> Result<Option<(i64, i64, bool)>, Error> {
+        let mut state = self.state();
+        if watermark.is_none() && !state.conv_seqs.contains_key(conversation) {
+            return Ok(None);
+        }
+        let watermark = watermark.unwrap_or_default();
+        let seq = state
+            .conv_seqs
+            .entry(conversation.to_string())
+            .or_insert(SeqPair {
+                cur: watermark,
+                max: watermark,
+            });
+        // 与Redis脚本一致：从水位开始，第一次分配即预留新的一段
+        seq.cur += 1;
+        let mut updated = false;
+        if seq.cur > seq.max {
+            seq.max += self.seq_step;
+            updated = true;
+        }
+        Ok(Some((seq.cur, seq.max, updated)))
+    }
+
+    async fn incr_group_seq(&self, members: Vec<String>) -> Result<Vec<GroupMemSeq>, Error> {
+        let mut state = self.state();
+        Ok(members
+            .into_iter()
+            .map(|member| {
+                let (cur_seq, max_seq, updated) = state
+                    .seqs
+                    .entry(member.clone())
+                    .or_default()
+                    .increase(self.seq_step);
+                GroupMemSeq::new(member, cur_seq, max_seq, updated)
+            })
+            .collect())
+    }
+
+    async fn raise_seq(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Result<(), Error> {
+        let mut state = self.state();
+        let target = match kind {
+            SeqKind::Receive => &mut state.seqs,
+            SeqKind::Send => &mut state.send_seqs,
+        };
+        for (user_id, seq) in seqs {
+            target.entry(user_id.clone()).or_default().raise(*seq);
+        }
+        Ok(())
+    }
+
+    async fn scan_max_seqs(
+        &self,
+        kind: SeqKind,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, i64)>), Error> {
+        let state = self.state();
+        let seqs = match kind {
+            SeqKind::Receive => &state.seqs,
+            SeqKind::Send => &state.send_seqs,
+        };
+        let mut users: Vec<(&String, &SeqPair)> = seqs.iter().collect();
+        users.sort_by(|a, b| a.0.cmp(b.0));
+
+        let start = cursor as usize;
+        let end = (start + count.max(1)).min(users.len());
+        let batch = users
+            .get(start..end)
+            .unwrap_or_default()
+            .iter()
+            .map(|(user_id, seq)| ((*user_id).clone(), seq.max))
+            .collect();
+        let next = if end >= users.len() { 0 } else { end as u64 };
+        Ok((next, batch))
+    }
+
+    async fn query_group_members_id(&self, group_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state()
+            .group_members
+            .get(group_id)
+            .map(|members| members.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn save_group_members_id(
+        &self,
+        group_id: &str,
+        members_id: Vec<String>,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        state
+            .group_members
+            .entry(group_id.to_string())
+            .or_default()
+            .extend(members_id);
+        state.bump_group_version(group_id);
+        Ok(())
+    }
+
+    async fn add_group_member_id(&self, member_id: &str, group_id: &str) -> Result<(), Error> {
+        let mut state = self.state();
+        state
+            .group_members
+            .entry(group_id.to_string())
+            .or_default()
+            .insert(member_id.to_string());
+        state.bump_group_version(group_id);
+        Ok(())
+    }
+
+    async fn remove_group_member_id(&self, group_id: &str, member_id: &str) -> Result<(), Error> {
+        self.remove_group_member_batch(group_id, &[member_id]).await
+    }
+
+    async fn remove_group_member_batch(
+        &self,
+        group_id: &str,
+        member_id: &[&str],
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        if let Some(members) = state.group_members.get_mut(group_id) {
+            for id in member_id {
+                members.remove(*id);
+            }
+        }
+        state.bump_group_version(group_id);
+        Ok(())
+    }
+
+    async fn del_group_members(&self, group_id: &str) -> Result<(), Error> {
+        let mut state = self.state();
+        state.group_members.remove(group_id);
+        state.bump_group_version(group_id);
+        Ok(())
+    }
+
+    async fn group_members_version(&self, group_id: &str) -> Result<i64, Error> {
+        Ok(self
+            .state()
+            .group_versions
+            .get(group_id)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn save_register_code(&self, email: &str, code: &str) -> Result<(), Error> {
+        self.state()
+            .register_codes
+            .insert(email.to_string(), code.to_string());
+        Ok(())
+    }
+
+    async fn get_register_code(&self, email: &str) -> Result<Option<String>, Error> {
+        Ok(self.state().register_codes.get(email).cloned())
+    }
+
+    async fn del_register_code(&self, email: &str) -> Result<(), Error> {
+        self.state().register_codes.remove(email);
+        Ok(())
+    }
+
+    async fn user_login(&self, user_id: &str) -> Result<(), Error> {
+        self.state().online.insert(user_id.to_string());
+        Ok(())
+    }
+
+    async fn user_logout(&self, user_id: &str) -> Result<(), Error> {
+        self.state().online.remove(user_id);
+        Ok(())
+    }
+
+    async fn online_count(&self) -> Result<i64, Error> {
+        Ok(self.state().online.len() as i64)
+    }
+
+    async fn online_status(&self, user_ids: &[String]) -> Result<Vec<bool>, Error> {
+        let state = self.state();
+        Ok(user_ids
+            .iter()
+            .map(|user_id| state.online.contains(user_id))
+            .collect())
+    }
+
+    async fn save_login_challenge(
+        &self,
+        challenge_id: &str,
+        user_id: &str,
+        code: &str,
+    ) -> Result<(), Error> {
+        self.state().login_challenges.insert(
+            challenge_id.to_string(),
+            LoginChallenge {
+                user_id: user_id.to_string(),
+                code: code.to_string(),
+                attempts: 0,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_login_challenge(
+        &self,
+        challenge_id: &str,
+    ) -> Result<Option<LoginChallenge>, Error> {
+        Ok(self.state().login_challenges.get(challenge_id).cloned())
+    }
+
+    async fn incr_login_challenge_attempts(&self, challenge_id: &str) -> Result<i64, Error> {
+        let mut state = self.state();
+        let challenge = state
+            .login_challenges
+            .entry(challenge_id.to_string())
+            .or_default();
+        challenge.attempts += 1;
+        Ok(challenge.attempts)
+    }
+
+    async fn del_login_challenge(&self, challenge_id: &str) -> Result<(), Error> {
+        self.state().login_challenges.remove(challenge_id);
+        Ok(())
+    }
+
+    async fn revoke_token(&self, token_id: &str, _ttl: i64) -> Result<(), Error> {
+        self.state().revoked_tokens.insert(token_id.to_string());
+        Ok(())
+    }
+
+    async fn revoke_user_tokens(&self, user_id: &str, before: i64, _ttl: i64) -> Result<(), Error> {
+        self.state()
+            .user_tokens_revoked_before
+            .insert(user_id.to_string(), before);
+        Ok(())
+    }
+
+    async fn is_token_revoked(
+        &self,
+        token_id: &str,
+        user_id: &str,
+        issued_at: i64,
+    ) -> Result<bool, Error> {
+        let state = self.state();
+        Ok(state.revoked_tokens.contains(token_id)
+            || state
+                .user_tokens_revoked_before
+                .get(user_id)
+                .is_some_and(|before| issued_at < *before))
+    }
+
+    async fn record_user_active(&self, user_id: &str, timestamp: i64) -> Result<(), Error> {
+        let mut state = self.state();
+        let entry = state
+            .user_active
+            .entry(user_id.to_string())
+            .or_insert(timestamp);
+        *entry = (*entry).max(timestamp);
+        Ok(())
+    }
+
+    async fn drain_user_active(&self, count: usize) -> Result<Vec<(String, i64)>, Error> {
+        let mut state = self.state();
+        // 与ZPOPMIN一致，按时间从早到晚取出
+        let mut active: Vec<(String, i64)> = state
+            .user_active
+            .iter()
+            .map(|(user_id, timestamp)| (user_id.clone(), *timestamp))
+            .collect();
+        active.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        active.truncate(count);
+        for (user_id, _) in &active {
+            state.user_active.remove(user_id);
+        }
+        Ok(active)
+    }
+
+    async fn incr_group_msg_stats(
+        &self,
+        group_id: &str,
+        sender_id: &str,
+        date: &str,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        let key = (group_id.to_string(), date.to_string());
+        state
+            .group_stats
+            .entry(key.clone())
+            .or_default()
+            .message_count += 1;
+        state
+            .group_active
+            .entry(key)
+            .or_default()
+            .insert(sender_id.to_string());
+        state
+            .group_stats_dirty
+            .entry(date.to_string())
+            .or_default()
+            .insert(group_id.to_string());
+        Ok(())
+    }
+
+    async fn incr_group_churn(
+        &self,
+        group_id: &str,
+        joins: i64,
+        leaves: i64,
+        date: &str,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        let stats = state
+            .group_stats
+            .entry((group_id.to_string(), date.to_string()))
+            .or_default();
+        stats.joins += joins;
+        stats.leaves += leaves;
+        state
+            .group_stats_dirty
+            .entry(date.to_string())
+            .or_default()
+            .insert(group_id.to_string());
+        Ok(())
+    }
+
+    async fn get_group_daily_stats(
+        &self,
+        group_id: &str,
+        date: &str,
+    ) -> Result<GroupDailyStats, Error> {
+        let state = self.state();
+        let key = (group_id.to_string(), date.to_string());
+        let stats = state.group_stats.get(&key).cloned().unwrap_or_default();
+        Ok(GroupDailyStats {
+            group_id: group_id.to_string(),
+            date: date.to_string(),
+            active_members: state.group_active.get(&key).map_or(0, |m| m.len() as i64),
+            ..stats
+        })
+    }
+
+    async fn group_stats_dirty_groups(&self, date: &str) -> Result<Vec<String>, Error> {
+        Ok(self
+            .state()
+            .group_stats_dirty
+            .get(date)
+            .map(|groups| groups.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn incr_message_usage(&self, hour: &str, usage: &[MessageUsage]) -> Result<(), Error> {
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let mut state = self.state();
+        let hour_usage = state.message_usage.entry(hour.to_string()).or_default();
+        for item in usage {
+            let entry = hour_usage
+                .entry((item.tenant_id.clone(), item.user_id.clone()))
+                .or_insert_with(|| MessageUsage {
+                    tenant_id: item.tenant_id.clone(),
+                    user_id: item.user_id.clone(),
+                    ..Default::default()
+                });
+            entry.messages += item.messages;
+            entry.bytes += item.bytes;
+        }
+        state.message_usage_hours.insert(hour.to_string());
+        Ok(())
+    }
+
+    async fn get_message_usage(&self, hour: &str) -> Result<Vec<MessageUsage>, Error> {
+        Ok(self
+            .state()
+            .message_usage
+            .get(hour)
+            .map(|usage| usage.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn message_usage_hours(&self) -> Result<Vec<String>, Error> {
+        Ok(self.state().message_usage_hours.iter().cloned().collect())
+    }
+
+    async fn finish_message_usage_hour(&self, hour: &str) -> Result<(), Error> {
+        self.state().message_usage_hours.remove(hour);
+        Ok(())
+    }
+
+    async fn block_attachment(&self, object_key: &str, reason: &str) -> Result<(), Error> {
+        self.state()
+            .blocked_attachments
+            .insert(object_key.to_string(), reason.to_string());
+        Ok(())
+    }
+
+    async fn get_attachment_block(&self, object_key: &str) -> Result<Option<String>, Error> {
+        Ok(self.state().blocked_attachments.get(object_key).cloned())
+    }
+
+    async fn save_image_derivatives(
+        &self,
+        object_key: &str,
+        derivatives: &ImageDerivatives,
+    ) -> Result<(), Error> {
+        self.state()
+            .image_derivatives
+            .insert(object_key.to_string(), derivatives.clone());
+        Ok(())
+    }
+
+    async fn get_image_derivatives(
+        &self,
+        object_key: &str,
+    ) -> Result<Option<ImageDerivatives>, Error> {
+        Ok(self.state().image_derivatives.get(object_key).cloned())
+    }
+
+    async fn save_link_preview(&self, url: &str, preview: &str, _ttl: i64) -> Result<(), Error> {
+        self.state()
+            .link_previews
+            .insert(url.to_string(), preview.to_string());
+        Ok(())
+    }
+
+    async fn get_link_preview(&self, url: &str) -> Result<Option<String>, Error> {
+        Ok(self.state().link_previews.get(url).cloned())
+    }
+
+    async fn save_backfill_cursor(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        cursor: &str,
+    ) -> Result<(), Error> {
+        self.state().backfill_cursors.insert(
+            (user_id.to_string(), device_id.to_string()),
+            cursor.to_string(),
+        );
+        Ok(())
+    }
+
+    async fn get_backfill_cursor(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<String>, Error> {
+        Ok(self
+            .state()
+            .backfill_cursors
+            .get(&(user_id.to_string(), device_id.to_string()))
+            .cloned())
+    }
+
+    async fn delete_backfill_cursor(&self, user_id: &str, device_id: &str) -> Result<(), Error> {
+        self.state()
+            .backfill_cursors
+            .remove(&(user_id.to_string(), device_id.to_string()));
+        Ok(())
+    }
+
+    async fn cancel_backfill(&self, user_id: &str, device_id: &str) -> Result<(), Error> {
+        self.state()
+            .backfill_cancels
+            .insert((user_id.to_string(), device_id.to_string()));
+        Ok(())
+    }
+
+    async fn take_backfill_cancel(&self, user_id: &str, device_id: &str) -> Result<bool, Error> {
+        Ok(self
+            .state()
+            .backfill_cancels
+            .remove(&(user_id.to_string(), device_id.to_string())))
+    }
+
+    async fn save_rebuild_progress(&self, user_id: &str, seq: i64) -> Result<(), Error> {
+        self.state()
+            .rebuild_progress
+            .insert(user_id.to_string(), seq);
+        Ok(())
+    }
+
+    async fn get_rebuild_progress(&self, user_id: &str) -> Result<Option<i64>, Error> {
+        Ok(self.state().rebuild_progress.get(user_id).copied())
+    }
+
+    async fn delete_rebuild_progress(&self, user_id: &str) -> Result<(), Error> {
+        self.state().rebuild_progress.remove(user_id);
+        Ok(())
+    }
+
+    async fn save_draft(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        draft: &str,
+        _ttl: i64,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        state
+            .drafts
+            .entry(user_id.to_string())
+            .or_default()
+            .insert(conversation_id.to_string(), draft.to_string());
+        state.dirty_drafts.insert(user_id.to_string());
+        Ok(())
+    }
+
+    async fn get_drafts(&self, user_id: &str) -> Result<Vec<(String, String)>, Error> {
+        Ok(self
+            .state()
+            .drafts
+            .get(user_id)
+            .map(|drafts| drafts.clone().into_iter().collect())
+            .unwrap_or_default())
+    }
+
+    async fn del_draft(&self, user_id: &str, conversation_id: &str) -> Result<(), Error> {
+        let mut state = self.state();
+        if let Some(drafts) = state.drafts.get_mut(user_id) {
+            drafts.remove(conversation_id);
+        }
+        state.dirty_drafts.insert(user_id.to_string());
+        Ok(())
+    }
+
+    async fn drain_dirty_drafts(&self, count: usize) -> Result<Vec<String>, Error> {
+        let mut state = self.state();
+        let users: Vec<String> = state.dirty_drafts.iter().take(count).cloned().collect();
+        for user_id in &users {
+            state.dirty_drafts.remove(user_id);
+        }
+        Ok(users)
+    }
+
+    async fn advance_read_cursor(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        read_seq: i64,
+    ) -> Result<bool, Error> {
+        let mut state = self.state();
+        let cursor = state
+            .read_cursors
+            .entry(user_id.to_string())
+            .or_default()
+            .entry(conversation_id.to_string())
+            .or_default();
+        if read_seq > *cursor {
+            *cursor = read_seq;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    async fn get_read_cursor(&self, user_id: &str, conversation_id: &str) -> Result<i64, Error> {
+        Ok(self
+            .state()
+            .read_cursors
+            .get(user_id)
+            .and_then(|cursors| cursors.get(conversation_id))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn get_read_cursors(&self, user_id: &str) -> Result<Vec<(String, i64)>, Error> {
+        Ok(self
+            .state()
+            .read_cursors
+            .get(user_id)
+            .map(|cursors| cursors.clone().into_iter().collect())
+            .unwrap_or_default())
+    }
+
+    async fn record_profile_view(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+        _window: i64,
+    ) -> Result<i64, Error> {
+        let mut state = self.state();
+        let views = state
+            .profile_views
+            .entry(viewer_id.to_string())
+            .or_default();
+        views.insert(user_id.to_string());
+        Ok(views.len() as i64)
+    }
+
+    async fn save_ops_sample(
+        &self,
+        instance_id: &str,
+        sample: &str,
+        _ttl: i64,
+    ) -> Result<(), Error> {
+        self.state()
+            .ops_samples
+            .insert(instance_id.to_string(), sample.to_string());
+        Ok(())
+    }
+
+    async fn get_ops_samples(&self) -> Result<Vec<String>, Error> {
+        Ok(self.state().ops_samples.values().cloned().collect())
+    }
+
+    async fn ping(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_seq_matches_redis_script() {
+        let cache = MemoryCache::with_seq_step(2);
+        assert_eq!(cache.increase_seq("alice").await.unwrap(), (1, 2, false));
+        assert_eq!(cache.increase_seq("alice").await.unwrap(), (2, 2, false));
+        // 超过当前段的最大序列号时分配下一段，调用方据此落库
+        assert_eq!(cache.increase_seq("alice").await.unwrap(), (3, 4, true));
+
+        let seqs = cache
+            .incr_group_seq(vec!["alice".to_string(), "bob".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(seqs[0].cur_seq, 4);
+        assert_eq!(seqs[1].cur_seq, 1);
+
+        cache
+            .raise_seq(SeqKind::Receive, &[("bob".to_string(), 10)])
+            .await
+            .unwrap();
+        assert_eq!(cache.get_seq("bob").await.unwrap(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_conversation_seq_starts_from_watermark() {
+        let cache = MemoryCache::with_seq_step(2);
+        // 序列号不存在且没有水位时不分配，由调用方查询水位
+        assert_eq!(
+            cache.incr_conversation_seq("group:g1", None).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            cache
+                .incr_conversation_seq("group:g1", Some(4))
+                .await
+                .unwrap(),
+            Some((5, 6, true))
+        );
+        assert_eq!(
+            cache.incr_conversation_seq("group:g1", None).await.unwrap(),
+            Some((6, 6, false))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_group_members_version() {
+        let cache = MemoryCache::new();
+        cache
+            .save_group_members_id("g1", vec!["alice".to_string(), "bob".to_string()])
+            .await
+            .unwrap();
+        cache.remove_group_member_id("g1", "bob").await.unwrap();
+        assert_eq!(
+            cache.query_group_members_id("g1").await.unwrap(),
+            vec!["alice"]
+        );
+        assert_eq!(cache.group_members_version("g1").await.unwrap(), 2);
+    }
+}
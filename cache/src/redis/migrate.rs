@@ -0,0 +1,88 @@
+/**
+ * Redis键前缀迁移
+ *
+ * 启用键前缀之前写入的数据都是全局键，本模块负责把这些旧键
+ * 重命名到当前命名空间下，迁移可重复执行。
+ */
+use common::error::Error;
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncCommands, Client};
+use tracing::{info, warn};
+
+use super::{GROUP_MEMBERS_ID_PREFIX, IS_LOADED, REGISTER_CODE_KEY, USER_ONLINE_SET};
+
+/// 每次SCAN的数量
+const SCAN_COUNT: usize = 500;
+
+/// 将旧的无前缀键迁移到指定前缀下
+///
+/// 使用 RENAMENX，目标键已存在时跳过，不会覆盖新命名空间中的数据
+///
+/// # 参数
+/// * `client` - Redis客户端
+/// * `prefix` - 规范化后的键前缀（以 ':' 结尾）
+///
+/// # 返回
+/// * 成功迁移的键数量
+pub async fn migrate_legacy_keys(client: &Client, prefix: &str) -> Result<u64, Error> {
+    if prefix.is_empty() {
+        info!("未配置键前缀，无需迁移");
+        return Ok(0);
+    }
+
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    let mut migrated = 0;
+
+    // 固定键
+    for key in [IS_LOADED, REGISTER_CODE_KEY, USER_ONLINE_SET] {
+        migrated += rename_key(&mut conn, key, prefix).await?;
+    }
+
+    // 按模式匹配的键
+    let group_pattern = format!("{}:*", GROUP_MEMBERS_ID_PREFIX);
+    for pattern in ["seq:*", "send_seq:*", group_pattern.as_str()] {
+        let keys = scan_keys(&mut conn, pattern).await?;
+        for key in keys {
+            migrated += rename_key(&mut conn, &key, prefix).await?;
+        }
+    }
+
+    info!("Redis键前缀迁移完成，共迁移 {} 个键到 {}", migrated, prefix);
+    Ok(migrated)
+}
+
+async fn scan_keys(conn: &mut MultiplexedConnection, pattern: &str) -> Result<Vec<String>, Error> {
+    let mut cursor: u64 = 0;
+    let mut keys = Vec::new();
+    loop {
+        let (next, batch): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(pattern)
+            .arg("COUNT")
+            .arg(SCAN_COUNT)
+            .query_async(conn)
+            .await?;
+        keys.extend(batch);
+        if next == 0 {
+            break;
+        }
+        cursor = next;
+    }
+    Ok(keys)
+}
+
+async fn rename_key(conn: &mut MultiplexedConnection, key: &str, prefix: &str) -> Result<u64, Error> {
+    let exists: bool = conn.exists(key).await?;
+    if !exists {
+        return Ok(0);
+    }
+
+    let target = format!("{}{}", prefix, key);
+    let renamed: bool = conn.rename_nx(key, &target).await?;
+    if !renamed {
+        warn!("目标键 {} 已存在，跳过迁移 {}", target, key);
+        return Ok(0);
+    }
+    Ok(1)
+}
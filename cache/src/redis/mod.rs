@@ -10,20 +10,27 @@
  * 该实现采用异步编程模式，通过连接池和信号量机制提高并发性能，
  * 同时使用Lua脚本进行原子操作，确保数据一致性。
  */
-use crate::Cache;
+use crate::{Cache, GroupDailyStats, ImageDerivatives, LoginChallenge, MessageUsage};
 use async_trait::async_trait;
 use common::config::AppConfig;
 use common::error::Error;
 use common::message::GroupMemSeq;
+use common::seq_fallback::SeqKind;
 use redis::aio::MultiplexedConnection;
 use redis::{AsyncCommands, Client, RedisError};
+use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
 use std::sync::Arc;
 use tokio::sync::{Mutex, Semaphore};
 
+pub mod migrate;
+
 /// 群组成员ID前缀
 const GROUP_MEMBERS_ID_PREFIX: &str = "group_members_id";
 
+/// 群组成员版本号前缀，成员变化时递增，用于失效进程内的成员缓存
+const GROUP_MEMBERS_VERSION_PREFIX: &str = "group_members_version";
+
 /// 注册验证码的键
 const REGISTER_CODE_KEY: &str = "register_code";
 
@@ -34,7 +41,7 @@ const REGISTER_CODE_EXPIRE: i64 = 300;
 const USER_ONLINE_SET: &str = "user_online_set";
 
 /// 默认序列号步长
-const DEFAULT_SEQ_STEP: i32 = 5000;
+pub(crate) const DEFAULT_SEQ_STEP: i32 = 5000;
 
 /// 执行Lua脚本的命令
 const EVALSHA: &str = "EVALSHA";
@@ -51,9 +58,139 @@ const IS_LOADED: &str = "seq_need_load";
 /// 序列号不需要加载的值
 const SEQ_NO_NEED_LOAD: &str = "false";
 
+/// 已吊销令牌前缀
+const REVOKED_TOKEN_PREFIX: &str = "revoked_token";
+
+/// 用户令牌整体吊销时间前缀
+const USER_TOKENS_REVOKED_BEFORE_PREFIX: &str = "user_tokens_revoked_before";
+
+/// 登录二次验证挑战前缀
+const LOGIN_CHALLENGE_PREFIX: &str = "login_challenge";
+
+/// 待落库的用户最后活跃时间（有序集合，分数为时间戳）
+const USER_LAST_ACTIVE_SET: &str = "user_last_active";
+
+/// 已拦截附件前缀
+const BLOCKED_ATTACHMENT_PREFIX: &str = "blocked_attachment";
+
+/// 图片衍生数据前缀
+const IMAGE_DERIVATIVES_PREFIX: &str = "image_derivatives";
+
+/// 图片衍生数据保留时间（秒），过期后新消息不再附带缩略图，客户端回退为加载原图
+const IMAGE_DERIVATIVES_EXPIRE: i64 = 30 * 24 * 3600;
+
+/// 链接预览前缀
+const LINK_PREVIEW_PREFIX: &str = "link_preview";
+
+/// 历史消息回填进度前缀
+const BACKFILL_CURSOR_PREFIX: &str = "backfill_cursor";
+
+/// 历史消息回填进度保留时间（秒），超过后新设备需要重新回填
+const BACKFILL_CURSOR_EXPIRE: i64 = 7 * 24 * 3600;
+
+/// 历史消息回填取消标记前缀
+const BACKFILL_CANCEL_PREFIX: &str = "backfill_cancel";
+
+/// 回填取消标记的有效时间（秒）
+const BACKFILL_CANCEL_EXPIRE: i64 = 600;
+
+/// 接收盒重建进度前缀
+const REBUILD_PROGRESS_PREFIX: &str = "rebuild_progress";
+
+/// 接收盒重建进度保留时间（秒）
+const REBUILD_PROGRESS_EXPIRE: i64 = 7 * 24 * 3600;
+
+/// 会话草稿前缀，每个用户一个哈希表，字段为会话ID
+const DRAFT_PREFIX: &str = "conversation_draft";
+
+/// 草稿有变更、待持久化的用户集合
+const DRAFT_DIRTY_SET: &str = "conversation_draft_dirty";
+
+/// 会话已读位置前缀，每个用户一个哈希表，字段为会话ID
+const READ_CURSOR_PREFIX: &str = "read_cursor";
+
+/// 只在新位置更大时更新已读位置的Lua脚本，多端乱序上报时已读位置不会回退
+const READ_CURSOR_SCRIPT: &str = r#"
+local cur = tonumber(redis.call('HGET', KEYS[1], ARGV[1]) or '0')
+if tonumber(ARGV[2]) > cur then
+    redis.call('HSET', KEYS[1], ARGV[1], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+/// 把序列号提高到指定值的Lua脚本，KEYS与ARGV一一对应，已经更大时不变
+const RAISE_SEQ_SCRIPT: &str = r#"
+for i, key in ipairs(KEYS) do
+    local seq = tonumber(ARGV[i])
+    if seq > tonumber(redis.call('HGET', key, 'cur_seq') or '0') then
+        redis.call('HSET', key, 'cur_seq', seq)
+    end
+    if seq > tonumber(redis.call('HGET', key, 'max_seq') or '0') then
+        redis.call('HSET', key, 'max_seq', seq)
+    end
+end
+return 0
+"#;
+
+/// 增加会话序列号的Lua脚本，KEYS[1]为会话序列号的键，ARGV[1]为步长，ARGV[2]为水位
+///
+/// 与单序列号脚本相同，但序列号不存在时不从0开始：水位小于0时返回 {0, 0, 0}，
+/// 否则先把当前和最大序列号设为水位再分配
+const CONVERSATION_SEQ_SCRIPT: &str = r#"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    if tonumber(ARGV[2]) < 0 then
+        return {0, 0, 0}
+    end
+    redis.call('HSET', KEYS[1], 'cur_seq', ARGV[2], 'max_seq', ARGV[2])
+end
+local cur_seq = redis.call('HINCRBY', KEYS[1], 'cur_seq', 1)
+local max_seq = tonumber(redis.call('HGET', KEYS[1], 'max_seq'))
+local updated = 0
+if cur_seq > max_seq then
+    max_seq = max_seq + tonumber(ARGV[1])
+    redis.call('HSET', KEYS[1], 'max_seq', max_seq)
+    updated = 1
+end
+return {cur_seq, max_seq, updated}
+"#;
+
+/// 查看者在当前窗口内查看过的用户资料集合前缀
+const PROFILE_VIEWS_PREFIX: &str = "profile_views";
+
+/// 消息服务实例消费采样的哈希表，字段为实例ID
+const OPS_SAMPLES_KEY: &str = "ops_consumer_samples";
+
 /// 默认最大连接数
 const DEFAULT_MAX_CONNECTIONS: usize = 20;
 
+/// 群组每日统计计数器前缀
+const GROUP_STATS_PREFIX: &str = "group_stats";
+
+/// 群组每日发言成员（HyperLogLog）前缀
+const GROUP_ACTIVE_PREFIX: &str = "group_active";
+
+/// 当日有统计变更的群组集合前缀
+const GROUP_STATS_DIRTY_PREFIX: &str = "group_stats_dirty";
+
+/// 群组统计数据在Redis中的保留时间（秒），汇总到数据库后自然过期
+const GROUP_STATS_EXPIRE: i64 = 3 * 24 * 3600;
+
+/// 每小时消息用量前缀
+const MESSAGE_USAGE_PREFIX: &str = "message_usage";
+
+/// 有待汇总消息用量的小时集合
+const MESSAGE_USAGE_HOURS_KEY: &str = "message_usage_hours";
+
+/// 消息用量在Redis中的保留时间（秒），汇总到数据库后自然过期
+const MESSAGE_USAGE_EXPIRE: i64 = 3 * 24 * 3600;
+
+/// 消息用量字段：消息数
+const USAGE_FIELD_MESSAGES: &str = "m";
+
+/// 消息用量字段：字节数
+const USAGE_FIELD_BYTES: &str = "b";
+
 /// Redis缓存实现
 pub struct RedisCache {
     /// Redis客户端
@@ -70,6 +207,8 @@ pub struct RedisCache {
     group_seq_exe_sha: String,
     /// 最大连接数
     max_connections: usize,
+    /// 键前缀（命名空间），为空表示不加前缀
+    key_prefix: String,
 }
 
 /// 为RedisCache实现Debug特征
@@ -82,6 +221,7 @@ impl Debug for RedisCache {
             .field("single_seq_exe_sha", &self.single_seq_exe_sha)
             .field("group_seq_exe_sha", &self.group_seq_exe_sha)
             .field("max_connections", &self.max_connections)
+            .field("key_prefix", &self.key_prefix)
             .finish()
     }
 }
@@ -121,6 +261,7 @@ impl RedisCache {
             single_seq_exe_sha,
             group_seq_exe_sha,
             max_connections,
+            key_prefix: String::new(),
         }
     }
 
@@ -169,9 +310,26 @@ impl RedisCache {
             single_seq_exe_sha,
             group_seq_exe_sha,
             max_connections,
+            key_prefix: config.redis.key_prefix(),
         }
     }
 
+    /// 为键加上命名空间前缀
+    ///
+    /// # 参数
+    /// * `key` - 原始键
+    ///
+    /// # 返回
+    /// * 带前缀的完整键
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.key_prefix, key)
+    }
+
+    /// 群组成员版本号的键
+    fn group_members_version_key(&self, group_id: &str) -> String {
+        self.key(&format!("{}:{}", GROUP_MEMBERS_VERSION_PREFIX, group_id))
+    }
+
     /// 加载单序列号生成的Lua脚本
     ///
     /// 该脚本用于原子方式增加序列号并在需要时更新最大序列号
@@ -215,10 +373,11 @@ impl RedisCache {
     async fn group_script_load(conn: &mut MultiplexedConnection) -> Result<String, RedisError> {
         let script = r#"
         local seq_step = tonumber(ARGV[1])
+        local prefix = ARGV[2]
         local result = {}
 
-        for i=2,#ARGV do
-            local key = "seq:" .. ARGV[i]
+        for i=3,#ARGV do
+            local key = prefix .. "seq:" .. ARGV[i]
             local cur_seq = redis.call('HINCRBY', key, 'cur_seq', 1)
             local max_seq = redis.call('HGET', key, 'max_seq')
             local updated = 0
@@ -277,7 +436,7 @@ impl Cache for RedisCache {
         let mut conn = self.get_connection().await?;
 
         // redis.get::<K,U>() K是键，U是返回值类型
-        let need_load = conn.get::<_, Option<String>>(IS_LOADED).await;
+        let need_load = conn.get::<_, Option<String>>(self.key(IS_LOADED)).await;
         match need_load {
             Ok(Some(value)) if value == SEQ_NO_NEED_LOAD => Ok(false),
             _ => Ok(true),
@@ -289,7 +448,7 @@ impl Cache for RedisCache {
     /// 设置一个标志，表明序列号已经成功从持久存储加载到缓存
     async fn set_seq_loaded(&self) -> Result<(), Error> {
         let mut conn = self.get_connection().await?;
-        conn.set(IS_LOADED, SEQ_NO_NEED_LOAD).await?;
+        conn.set(self.key(IS_LOADED), SEQ_NO_NEED_LOAD).await?;
         Ok(())
     }
 
@@ -303,10 +462,10 @@ impl Cache for RedisCache {
         let mut conn = self.get_connection().await?;
         let mut pipe = redis::pipe();
         for (user_id, send_max_seq, rec_max_seq) in max_seq {
-            let key = format!("send_seq:{}", user_id);
+            let key = self.key(&format!("send_seq:{}", user_id));
             pipe.hset(&key, CUR_SEQ_KEY, send_max_seq);
             pipe.hset(&key, MAX_SEQ_KEY, send_max_seq);
-            let key = format!("seq:{}", user_id);
+            let key = self.key(&format!("seq:{}", user_id));
             pipe.hset(&key, CUR_SEQ_KEY, rec_max_seq);
             pipe.hset(&key, MAX_SEQ_KEY, rec_max_seq);
         }
@@ -324,7 +483,7 @@ impl Cache for RedisCache {
         let mut conn = self.get_connection().await?;
         let mut pipe = redis::pipe();
         for (user_id, max_seq) in max_seq {
-            let key = format!("send_seq:{}", user_id);
+            let key = self.key(&format!("send_seq:{}", user_id));
             pipe.hset(&key, CUR_SEQ_KEY, max_seq);
             pipe.hset(&key, MAX_SEQ_KEY, max_seq);
         }
@@ -341,7 +500,7 @@ impl Cache for RedisCache {
     /// * 用户的当前接收序列号
     async fn get_seq(&self, user_id: &str) -> Result<i64, Error> {
         // 生成键
-        let key = format!("seq:{}", user_id);
+        let key = self.key(&format!("seq:{}", user_id));
 
         let mut conn = self.get_connection().await?;
         let seq: i64 = conn.hget(&key, CUR_SEQ_KEY).await.unwrap_or_default();
@@ -357,8 +516,8 @@ impl Cache for RedisCache {
     /// * 包含接收序列号和发送序列号的元组
     async fn get_cur_seq(&self, user_id: &str) -> Result<(i64, i64), Error> {
         // 生成键
-        let key1 = format!("seq:{}", user_id);
-        let key2 = format!("send_seq:{}", user_id);
+        let key1 = self.key(&format!("seq:{}", user_id));
+        let key2 = self.key(&format!("send_seq:{}", user_id));
 
         let mut conn = self.get_connection().await?;
         // 使用管道一次性获取两个值，减少网络往返
@@ -384,7 +543,7 @@ impl Cache for RedisCache {
     /// * 包含当前发送序列号和最大发送序列号的元组
     async fn get_send_seq(&self, user_id: &str) -> Result<(i64, i64), Error> {
         // 生成键
-        let key = format!("send_seq:{}", user_id);
+        let key = self.key(&format!("send_seq:{}", user_id));
 
         let mut conn = self.get_connection().await?;
         // 使用管道一次性获取两个值，减少网络往返
@@ -416,7 +575,7 @@ impl Cache for RedisCache {
     /// * 包含当前序列号、最大序列号和是否更新的元组
     async fn increase_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error> {
         // 生成键
-        let key = format!("seq:{}", user_id);
+        let key = self.key(&format!("seq:{}", user_id));
 
         let mut conn = self.get_connection().await?;
         // 增加序列号
@@ -441,7 +600,7 @@ impl Cache for RedisCache {
     /// * 包含当前序列号、最大序列号和是否更新的元组
     async fn incr_send_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error> {
         // 生成键
-        let key = format!("send_seq:{}", user_id);
+        let key = self.key(&format!("send_seq:{}", user_id));
 
         let mut conn = self.get_connection().await?;
         // 增加序列号
@@ -455,6 +614,32 @@ impl Cache for RedisCache {
         Ok(seq)
     }
 
+    /// 增加会话序列号
+    ///
+    /// # 参数
+    /// * `conversation` - 会话序号键
+    /// * `watermark` - 序列号不存在时的起始水位，None表示序列号不存在时不分配
+    async fn incr_conversation_seq(
+        &self,
+        conversation: &str,
+        watermark: Option<i64>,
+    ) -> Result<Option<(i64, i64, bool)>, Error> {
+        let key = self.key(&format!("conv_seq:{}", conversation));
+
+        let mut conn = self.get_connection().await?;
+        let (cur_seq, max_seq, updated): (i64, i64, i64) =
+            redis::Script::new(CONVERSATION_SEQ_SCRIPT)
+                .key(&key)
+                .arg(self.seq_step)
+                .arg(watermark.unwrap_or(-1))
+                .invoke_async(&mut conn)
+                .await?;
+        if cur_seq == 0 {
+            return Ok(None);
+        }
+        Ok(Some((cur_seq, max_seq, updated == 1)))
+    }
+
     /// 增加群组成员序列号
     ///
     /// 一次性为多个群组成员增加序列号
@@ -468,7 +653,10 @@ impl Cache for RedisCache {
         let mut conn = self.get_connection().await?;
 
         let mut cmd = redis::cmd(EVALSHA);
-        cmd.arg(&self.group_seq_exe_sha).arg(0).arg(self.seq_step);
+        cmd.arg(&self.group_seq_exe_sha)
+            .arg(0)
+            .arg(self.seq_step)
+            .arg(&self.key_prefix);
 
         for member in members.iter() {
             cmd.arg(member);
@@ -499,6 +687,75 @@ impl Cache for RedisCache {
         Ok(seq)
     }
 
+    /// 提高用户的序列号
+    ///
+    /// # 参数
+    /// * `kind` - 接收或发送序列号
+    /// * `seqs` - 用户ID和序列号
+    async fn raise_seq(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Result<(), Error> {
+        if seqs.is_empty() {
+            return Ok(());
+        }
+        let prefix = match kind {
+            SeqKind::Receive => "seq",
+            SeqKind::Send => "send_seq",
+        };
+        let mut script = redis::Script::new(RAISE_SEQ_SCRIPT).prepare_invoke();
+        for (user_id, seq) in seqs {
+            script
+                .key(self.key(&format!("{}:{}", prefix, user_id)))
+                .arg(seq);
+        }
+        let mut conn = self.get_connection().await?;
+        let _: i64 = script.invoke_async(&mut conn).await?;
+        Ok(())
+    }
+
+    /// 扫描用户的最大序列号
+    ///
+    /// # 参数
+    /// * `kind` - 接收或发送序列号
+    /// * `cursor` - SCAN游标，首次为0
+    /// * `count` - 每批扫描的键数（近似值）
+    async fn scan_max_seqs(
+        &self,
+        kind: SeqKind,
+        cursor: u64,
+        count: usize,
+    ) -> Result<(u64, Vec<(String, i64)>), Error> {
+        let prefix = self.key(match kind {
+            SeqKind::Receive => "seq:",
+            SeqKind::Send => "send_seq:",
+        });
+        let mut conn = self.get_connection().await?;
+        let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+            .arg(cursor)
+            .arg("MATCH")
+            .arg(format!("{}*", prefix))
+            .arg("COUNT")
+            .arg(count)
+            .query_async(&mut conn)
+            .await?;
+        if keys.is_empty() {
+            return Ok((next, Vec::new()));
+        }
+
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.hget(key, MAX_SEQ_KEY);
+        }
+        let values: Vec<Option<i64>> = pipe.query_async(&mut conn).await?;
+        let seqs = keys
+            .into_iter()
+            .zip(values)
+            .filter_map(|(key, max_seq)| {
+                let user_id = key.strip_prefix(&prefix)?.to_string();
+                max_seq.map(|max_seq| (user_id, max_seq))
+            })
+            .collect();
+        Ok((next, seqs))
+    }
+
     /// 查询群组成员ID列表
     ///
     /// Redis中的群组成员信息以集合形式存储，键为 group_members_id:group_id
@@ -510,7 +767,7 @@ impl Cache for RedisCache {
     /// * 该群组的成员ID列表
     async fn query_group_members_id(&self, group_id: &str) -> Result<Vec<String>, Error> {
         // 生成键
-        let key = format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id);
+        let key = self.key(&format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id));
         // 从Redis查询值
         let mut conn = self.get_connection().await?;
 
@@ -530,13 +787,14 @@ impl Cache for RedisCache {
         group_id: &str,
         members_id: Vec<String>,
     ) -> Result<(), Error> {
-        let key = format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id);
+        let key = self.key(&format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id));
         let mut conn = self.get_connection().await?;
         // 通过Redis管道为群组添加每个成员
         let mut pipe = redis::pipe();
         for member in members_id {
             pipe.sadd(&key, &member);
         }
+        pipe.incr(self.group_members_version_key(group_id), 1);
         pipe.query_async(&mut conn).await?;
         Ok(())
     }
@@ -547,9 +805,13 @@ impl Cache for RedisCache {
     /// * `member_id` - 成员ID
     /// * `group_id` - 群组ID
     async fn add_group_member_id(&self, member_id: &str, group_id: &str) -> Result<(), Error> {
-        let key = format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id);
+        let key = self.key(&format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id));
         let mut conn = self.get_connection().await?;
-        conn.sadd(&key, member_id).await?;
+        redis::pipe()
+            .sadd(&key, member_id)
+            .incr(self.group_members_version_key(group_id), 1)
+            .query_async(&mut conn)
+            .await?;
         Ok(())
     }
 
@@ -559,9 +821,13 @@ impl Cache for RedisCache {
     /// * `group_id` - 群组ID
     /// * `member_id` - 要移除的成员ID
     async fn remove_group_member_id(&self, group_id: &str, member_id: &str) -> Result<(), Error> {
-        let key = format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id);
+        let key = self.key(&format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id));
         let mut conn = self.get_connection().await?;
-        conn.srem(&key, member_id).await?;
+        redis::pipe()
+            .srem(&key, member_id)
+            .incr(self.group_members_version_key(group_id), 1)
+            .query_async(&mut conn)
+            .await?;
         Ok(())
     }
 
@@ -575,9 +841,13 @@ impl Cache for RedisCache {
         group_id: &str,
         member_id: &[&str],
     ) -> Result<(), Error> {
-        let key = format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id);
+        let key = self.key(&format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id));
         let mut conn = self.get_connection().await?;
-        conn.srem(&key, member_id).await?;
+        redis::pipe()
+            .srem(&key, member_id)
+            .incr(self.group_members_version_key(group_id), 1)
+            .query_async(&mut conn)
+            .await?;
         Ok(())
     }
 
@@ -586,12 +856,26 @@ impl Cache for RedisCache {
     /// # 参数
     /// * `group_id` - 群组ID
     async fn del_group_members(&self, group_id: &str) -> Result<(), Error> {
-        let key = format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id);
+        let key = self.key(&format!("{}:{}", GROUP_MEMBERS_ID_PREFIX, group_id));
         let mut conn = self.get_connection().await?;
-        conn.del(&key).await?;
+        redis::pipe()
+            .del(&key)
+            .incr(self.group_members_version_key(group_id), 1)
+            .query_async(&mut conn)
+            .await?;
         Ok(())
     }
 
+    /// 查询群组成员版本号
+    ///
+    /// # 参数
+    /// * `group_id` - 群组ID
+    async fn group_members_version(&self, group_id: &str) -> Result<i64, Error> {
+        let mut conn = self.get_connection().await?;
+        let version: Option<i64> = conn.get(self.group_members_version_key(group_id)).await?;
+        Ok(version.unwrap_or_default())
+    }
+
     /// 保存用户注册验证码
     ///
     /// 将验证码与邮箱关联并设置5分钟过期时间
@@ -604,8 +888,9 @@ impl Cache for RedisCache {
         let mut conn = self.get_connection().await?;
         // 使用管道执行两个命令
         let mut pipe = redis::pipe();
-        pipe.hset(REGISTER_CODE_KEY, email, code)
-            .expire(REGISTER_CODE_KEY, REGISTER_CODE_EXPIRE)
+        let key = self.key(REGISTER_CODE_KEY);
+        pipe.hset(&key, email, code)
+            .expire(&key, REGISTER_CODE_EXPIRE)
             .query_async(&mut conn)
             .await?;
         Ok(())
@@ -620,7 +905,7 @@ impl Cache for RedisCache {
     /// * 对应的验证码，如果不存在则返回None
     async fn get_register_code(&self, email: &str) -> Result<Option<String>, Error> {
         let mut conn = self.get_connection().await?;
-        let result = conn.hget(REGISTER_CODE_KEY, email).await?;
+        let result = conn.hget(self.key(REGISTER_CODE_KEY), email).await?;
         Ok(result)
     }
 
@@ -632,7 +917,7 @@ impl Cache for RedisCache {
     /// * `email` - 用户邮箱
     async fn del_register_code(&self, email: &str) -> Result<(), Error> {
         let mut conn = self.get_connection().await?;
-        conn.hdel(REGISTER_CODE_KEY, email).await?;
+        conn.hdel(self.key(REGISTER_CODE_KEY), email).await?;
         Ok(())
     }
 
@@ -644,7 +929,7 @@ impl Cache for RedisCache {
     /// * `user_id` - 用户ID
     async fn user_login(&self, user_id: &str) -> Result<(), Error> {
         let mut conn = self.get_connection().await?;
-        conn.sadd(USER_ONLINE_SET, user_id).await?;
+        conn.sadd(self.key(USER_ONLINE_SET), user_id).await?;
         Ok(())
     }
 
@@ -656,7 +941,7 @@ impl Cache for RedisCache {
     /// * `user_id` - 用户ID
     async fn user_logout(&self, user_id: &str) -> Result<(), Error> {
         let mut conn = self.get_connection().await?;
-        conn.srem(USER_ONLINE_SET, user_id).await?;
+        conn.srem(self.key(USER_ONLINE_SET), user_id).await?;
         Ok(())
     }
 
@@ -666,9 +951,722 @@ impl Cache for RedisCache {
     /// * 当前在线用户数量
     async fn online_count(&self) -> Result<i64, Error> {
         let mut conn = self.get_connection().await?;
-        let result: i64 = conn.scard(USER_ONLINE_SET).await?;
+        let result: i64 = conn.scard(self.key(USER_ONLINE_SET)).await?;
+        Ok(result)
+    }
+
+    /// 批量查询用户是否在线
+    ///
+    /// # 参数
+    /// * `user_ids` - 用户ID列表
+    ///
+    /// # 返回
+    /// * 与 `user_ids` 一一对应的在线状态
+    async fn online_status(&self, user_ids: &[String]) -> Result<Vec<bool>, Error> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.get_connection().await?;
+        let result: Vec<bool> = conn.smismember(self.key(USER_ONLINE_SET), user_ids).await?;
+        Ok(result)
+    }
+
+    /// 保存登录二次验证挑战
+    ///
+    /// # 参数
+    /// * `challenge_id` - 挑战ID
+    /// * `user_id` - 用户ID
+    /// * `code` - 验证码
+    async fn save_login_challenge(
+        &self,
+        challenge_id: &str,
+        user_id: &str,
+        code: &str,
+    ) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", LOGIN_CHALLENGE_PREFIX, challenge_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .hset(&key, "user_id", user_id)
+            .hset(&key, "code", code)
+            .hset(&key, "attempts", 0)
+            .expire(&key, REGISTER_CODE_EXPIRE)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 获取登录二次验证挑战
+    ///
+    /// # 返回
+    /// * 挑战不存在或已过期时返回None
+    async fn get_login_challenge(&self, challenge_id: &str) -> Result<Option<LoginChallenge>, Error> {
+        let key = self.key(&format!("{}:{}", LOGIN_CHALLENGE_PREFIX, challenge_id));
+        let mut conn = self.get_connection().await?;
+        let (user_id, code, attempts): (Option<String>, Option<String>, Option<i64>) = redis::cmd("HMGET")
+            .arg(&key)
+            .arg("user_id")
+            .arg("code")
+            .arg("attempts")
+            .query_async(&mut conn)
+            .await?;
+
+        match (user_id, code) {
+            (Some(user_id), Some(code)) => Ok(Some(LoginChallenge {
+                user_id,
+                code,
+                attempts: attempts.unwrap_or_default(),
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// 累加登录二次验证的尝试次数
+    async fn incr_login_challenge_attempts(&self, challenge_id: &str) -> Result<i64, Error> {
+        let key = self.key(&format!("{}:{}", LOGIN_CHALLENGE_PREFIX, challenge_id));
+        let mut conn = self.get_connection().await?;
+        let attempts: i64 = conn.hincr(&key, "attempts", 1).await?;
+        Ok(attempts)
+    }
+
+    /// 删除登录二次验证挑战
+    async fn del_login_challenge(&self, challenge_id: &str) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", LOGIN_CHALLENGE_PREFIX, challenge_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.del(&key).await?;
+        Ok(())
+    }
+
+    /// 吊销单个令牌
+    ///
+    /// # 参数
+    /// * `token_id` - 令牌标识（JWT签名段）
+    /// * `ttl` - 令牌剩余有效期（秒），过期后记录自动清除
+    async fn revoke_token(&self, token_id: &str, ttl: i64) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", REVOKED_TOKEN_PREFIX, token_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set_ex(&key, 1, ttl.max(1) as u64).await?;
+        Ok(())
+    }
+
+    /// 吊销用户此前签发的所有令牌
+    ///
+    /// # 参数
+    /// * `user_id` - 用户ID
+    /// * `before` - 签发时间早于该时间戳的令牌全部失效
+    /// * `ttl` - 记录保留时间（秒），应不短于令牌的最长有效期
+    async fn revoke_user_tokens(&self, user_id: &str, before: i64, ttl: i64) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", USER_TOKENS_REVOKED_BEFORE_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set_ex(&key, before, ttl.max(1) as u64).await?;
+        Ok(())
+    }
+
+    /// 检查令牌是否已被吊销
+    ///
+    /// # 参数
+    /// * `token_id` - 令牌标识（JWT签名段）
+    /// * `user_id` - 令牌所属用户
+    /// * `issued_at` - 令牌签发时间
+    async fn is_token_revoked(
+        &self,
+        token_id: &str,
+        user_id: &str,
+        issued_at: i64,
+    ) -> Result<bool, Error> {
+        let token_key = self.key(&format!("{}:{}", REVOKED_TOKEN_PREFIX, token_id));
+        let user_key = self.key(&format!("{}:{}", USER_TOKENS_REVOKED_BEFORE_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let (revoked, before): (bool, Option<i64>) = redis::pipe()
+            .exists(&token_key)
+            .get(&user_key)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(revoked || before.is_some_and(|before| issued_at < before))
+    }
+
+    /// 记录用户最后活跃时间
+    ///
+    /// 写入有序集合，使用 ZADD GT 保证同一用户只保留最新的时间戳，由用户服务定期批量落库
+    ///
+    /// # 参数
+    /// * `user_id` - 用户ID
+    /// * `timestamp` - 活跃时间（秒级时间戳）
+    async fn record_user_active(&self, user_id: &str, timestamp: i64) -> Result<(), Error> {
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::cmd("ZADD")
+            .arg(self.key(USER_LAST_ACTIVE_SET))
+            .arg("GT")
+            .arg(timestamp)
+            .arg(user_id)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 取出待落库的用户最后活跃时间
+    ///
+    /// # 参数
+    /// * `count` - 单次最多取出的条数
+    ///
+    /// # 返回
+    /// * (用户ID, 活跃时间) 列表，取出后从集合中移除
+    async fn drain_user_active(&self, count: usize) -> Result<Vec<(String, i64)>, Error> {
+        let mut conn = self.get_connection().await?;
+        let result: Vec<(String, i64)> = conn
+            .zpopmin(self.key(USER_LAST_ACTIVE_SET), count as isize)
+            .await?;
+        Ok(result)
+    }
+
+    /// 记录群消息统计
+    ///
+    /// 累加当日消息数，把发送者加入当日发言成员HyperLogLog，并标记群组当日有变更
+    ///
+    /// # 参数
+    /// * `group_id` - 群组ID
+    /// * `sender_id` - 发送者ID
+    /// * `date` - 日期（yyyyMMdd）
+    async fn incr_group_msg_stats(
+        &self,
+        group_id: &str,
+        sender_id: &str,
+        date: &str,
+    ) -> Result<(), Error> {
+        let stats_key = self.key(&format!("{}:{}:{}", GROUP_STATS_PREFIX, group_id, date));
+        let active_key = self.key(&format!("{}:{}:{}", GROUP_ACTIVE_PREFIX, group_id, date));
+        let dirty_key = self.key(&format!("{}:{}", GROUP_STATS_DIRTY_PREFIX, date));
+
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .hincr(&stats_key, "msg_count", 1)
+            .expire(&stats_key, GROUP_STATS_EXPIRE)
+            .pfadd(&active_key, sender_id)
+            .expire(&active_key, GROUP_STATS_EXPIRE)
+            .sadd(&dirty_key, group_id)
+            .expire(&dirty_key, GROUP_STATS_EXPIRE)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 累加群组成员变动统计
+    ///
+    /// # 参数
+    /// * `group_id` - 群组ID
+    /// * `joins` - 加入人数
+    /// * `leaves` - 退出人数
+    /// * `date` - 日期（yyyyMMdd）
+    async fn incr_group_churn(
+        &self,
+        group_id: &str,
+        joins: i64,
+        leaves: i64,
+        date: &str,
+    ) -> Result<(), Error> {
+        let stats_key = self.key(&format!("{}:{}:{}", GROUP_STATS_PREFIX, group_id, date));
+        let dirty_key = self.key(&format!("{}:{}", GROUP_STATS_DIRTY_PREFIX, date));
+
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .hincr(&stats_key, "joins", joins)
+            .hincr(&stats_key, "leaves", leaves)
+            .expire(&stats_key, GROUP_STATS_EXPIRE)
+            .sadd(&dirty_key, group_id)
+            .expire(&dirty_key, GROUP_STATS_EXPIRE)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询群组某日统计
+    ///
+    /// # 参数
+    /// * `group_id` - 群组ID
+    /// * `date` - 日期（yyyyMMdd）
+    ///
+    /// # 返回
+    /// * 当日统计数据，不存在时各项为0
+    async fn get_group_daily_stats(
+        &self,
+        group_id: &str,
+        date: &str,
+    ) -> Result<GroupDailyStats, Error> {
+        let stats_key = self.key(&format!("{}:{}:{}", GROUP_STATS_PREFIX, group_id, date));
+        let active_key = self.key(&format!("{}:{}:{}", GROUP_ACTIVE_PREFIX, group_id, date));
+
+        let mut conn = self.get_connection().await?;
+        let (message_count, joins, leaves, active_members): (
+            Option<i64>,
+            Option<i64>,
+            Option<i64>,
+            i64,
+        ) = redis::pipe()
+            .hget(&stats_key, "msg_count")
+            .hget(&stats_key, "joins")
+            .hget(&stats_key, "leaves")
+            .pfcount(&active_key)
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(GroupDailyStats {
+            group_id: group_id.to_string(),
+            date: date.to_string(),
+            message_count: message_count.unwrap_or_default(),
+            active_members,
+            joins: joins.unwrap_or_default(),
+            leaves: leaves.unwrap_or_default(),
+        })
+    }
+
+    /// 查询某日有统计变更的群组
+    ///
+    /// # 参数
+    /// * `date` - 日期（yyyyMMdd）
+    async fn group_stats_dirty_groups(&self, date: &str) -> Result<Vec<String>, Error> {
+        let dirty_key = self.key(&format!("{}:{}", GROUP_STATS_DIRTY_PREFIX, date));
+        let mut conn = self.get_connection().await?;
+        let result: Vec<String> = conn.smembers(&dirty_key).await?;
+        Ok(result)
+    }
+
+    /// 累加某小时的消息用量
+    ///
+    /// 每小时一个哈希，字段为 `m|租户ID|用户ID` 和 `b|租户ID|用户ID`，并记录到待汇总小时集合
+    ///
+    /// # 参数
+    /// * `hour` - 小时（yyyyMMddHH）
+    /// * `usage` - 各用户的增量
+    async fn incr_message_usage(&self, hour: &str, usage: &[MessageUsage]) -> Result<(), Error> {
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let usage_key = self.key(&format!("{}:{}", MESSAGE_USAGE_PREFIX, hour));
+        let hours_key = self.key(MESSAGE_USAGE_HOURS_KEY);
+
+        let mut pipe = redis::pipe();
+        for item in usage {
+            let field = format!("{}|{}", item.tenant_id, item.user_id);
+            pipe.hincr(&usage_key, format!("{}|{}", USAGE_FIELD_MESSAGES, field), item.messages)
+                .hincr(&usage_key, format!("{}|{}", USAGE_FIELD_BYTES, field), item.bytes);
+        }
+        pipe.expire(&usage_key, MESSAGE_USAGE_EXPIRE)
+            .sadd(&hours_key, hour);
+
+        let mut conn = self.get_connection().await?;
+        let _: () = pipe.query_async(&mut conn).await?;
+        Ok(())
+    }
+
+    /// 查询某小时的消息用量
+    ///
+    /// # 参数
+    /// * `hour` - 小时（yyyyMMddHH）
+    async fn get_message_usage(&self, hour: &str) -> Result<Vec<MessageUsage>, Error> {
+        let usage_key = self.key(&format!("{}:{}", MESSAGE_USAGE_PREFIX, hour));
+        let mut conn = self.get_connection().await?;
+        let fields: HashMap<String, i64> = conn.hgetall(&usage_key).await?;
+
+        let mut usage: HashMap<(String, String), MessageUsage> = HashMap::new();
+        for (field, value) in fields {
+            let mut parts = field.splitn(3, '|');
+            let (Some(kind), Some(tenant_id), Some(user_id)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            let entry = usage
+                .entry((tenant_id.to_string(), user_id.to_string()))
+                .or_insert_with(|| MessageUsage {
+                    tenant_id: tenant_id.to_string(),
+                    user_id: user_id.to_string(),
+                    ..Default::default()
+                });
+            match kind {
+                USAGE_FIELD_MESSAGES => entry.messages = value,
+                USAGE_FIELD_BYTES => entry.bytes = value,
+                _ => {}
+            }
+        }
+        Ok(usage.into_values().collect())
+    }
+
+    /// 有待汇总消息用量的小时
+    async fn message_usage_hours(&self) -> Result<Vec<String>, Error> {
+        let hours_key = self.key(MESSAGE_USAGE_HOURS_KEY);
+        let mut conn = self.get_connection().await?;
+        let result: Vec<String> = conn.smembers(&hours_key).await?;
+        Ok(result)
+    }
+
+    /// 将某小时移出待汇总集合
+    ///
+    /// 用量哈希保留到过期，汇总以覆盖方式写入数据库，迟到的写入重新加入集合后再次汇总即可
+    ///
+    /// # 参数
+    /// * `hour` - 小时（yyyyMMddHH）
+    async fn finish_message_usage_hour(&self, hour: &str) -> Result<(), Error> {
+        let hours_key = self.key(MESSAGE_USAGE_HOURS_KEY);
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.srem(&hours_key, hour).await?;
+        Ok(())
+    }
+
+    /// 将附件标记为已拦截
+    ///
+    /// # 参数
+    /// * `object_key` - 附件的OSS对象键
+    /// * `reason` - 拦截原因（扫描服务返回的威胁名称）
+    async fn block_attachment(&self, object_key: &str, reason: &str) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", BLOCKED_ATTACHMENT_PREFIX, object_key));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set(&key, reason).await?;
+        Ok(())
+    }
+
+    /// 查询附件的拦截原因
+    async fn get_attachment_block(&self, object_key: &str) -> Result<Option<String>, Error> {
+        let key = self.key(&format!("{}:{}", BLOCKED_ATTACHMENT_PREFIX, object_key));
+        let mut conn = self.get_connection().await?;
+        let result: Option<String> = conn.get(&key).await?;
+        Ok(result)
+    }
+
+    /// 保存图片衍生数据
+    ///
+    /// # 参数
+    /// * `object_key` - 原图的OSS对象键
+    /// * `derivatives` - 缩略图对象键、blurhash和原图尺寸
+    async fn save_image_derivatives(
+        &self,
+        object_key: &str,
+        derivatives: &ImageDerivatives,
+    ) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", IMAGE_DERIVATIVES_PREFIX, object_key));
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .hset(&key, "thumbnail_key", &derivatives.thumbnail_key)
+            .hset(&key, "blurhash", &derivatives.blurhash)
+            .hset(&key, "width", derivatives.width)
+            .hset(&key, "height", derivatives.height)
+            .expire(&key, IMAGE_DERIVATIVES_EXPIRE)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询图片衍生数据
+    async fn get_image_derivatives(
+        &self,
+        object_key: &str,
+    ) -> Result<Option<ImageDerivatives>, Error> {
+        let key = self.key(&format!("{}:{}", IMAGE_DERIVATIVES_PREFIX, object_key));
+        let mut conn = self.get_connection().await?;
+        let (thumbnail_key, blurhash, width, height): (
+            Option<String>,
+            Option<String>,
+            Option<u32>,
+            Option<u32>,
+        ) = redis::cmd("HMGET")
+            .arg(&key)
+            .arg("thumbnail_key")
+            .arg("blurhash")
+            .arg("width")
+            .arg("height")
+            .query_async(&mut conn)
+            .await?;
+
+        Ok(thumbnail_key.map(|thumbnail_key| ImageDerivatives {
+            thumbnail_key,
+            blurhash: blurhash.unwrap_or_default(),
+            width: width.unwrap_or_default(),
+            height: height.unwrap_or_default(),
+        }))
+    }
+
+    /// 保存链接预览
+    ///
+    /// # 参数
+    /// * `url` - 链接地址
+    /// * `preview` - 预览数据（JSON），空字符串表示该链接无可用预览
+    /// * `ttl` - 缓存时间（秒）
+    async fn save_link_preview(&self, url: &str, preview: &str, ttl: i64) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", LINK_PREVIEW_PREFIX, url));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set_ex(&key, preview, ttl.max(1) as u64).await?;
+        Ok(())
+    }
+
+    /// 查询链接预览
+    async fn get_link_preview(&self, url: &str) -> Result<Option<String>, Error> {
+        let key = self.key(&format!("{}:{}", LINK_PREVIEW_PREFIX, url));
+        let mut conn = self.get_connection().await?;
+        let result: Option<String> = conn.get(&key).await?;
+        Ok(result)
+    }
+
+    /// 保存历史消息回填进度
+    ///
+    /// # 参数
+    /// * `user_id` - 用户ID
+    /// * `device_id` - 设备ID
+    /// * `cursor` - 序列化后的回填进度
+    async fn save_backfill_cursor(
+        &self,
+        user_id: &str,
+        device_id: &str,
+        cursor: &str,
+    ) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}:{}", BACKFILL_CURSOR_PREFIX, user_id, device_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn
+            .set_ex(&key, cursor, BACKFILL_CURSOR_EXPIRE as u64)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询历史消息回填进度
+    async fn get_backfill_cursor(
+        &self,
+        user_id: &str,
+        device_id: &str,
+    ) -> Result<Option<String>, Error> {
+        let key = self.key(&format!("{}:{}:{}", BACKFILL_CURSOR_PREFIX, user_id, device_id));
+        let mut conn = self.get_connection().await?;
+        let result: Option<String> = conn.get(&key).await?;
+        Ok(result)
+    }
+
+    /// 删除历史消息回填进度
+    async fn delete_backfill_cursor(&self, user_id: &str, device_id: &str) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}:{}", BACKFILL_CURSOR_PREFIX, user_id, device_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.del(&key).await?;
+        Ok(())
+    }
+
+    /// 标记历史消息回填已取消
+    async fn cancel_backfill(&self, user_id: &str, device_id: &str) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}:{}", BACKFILL_CANCEL_PREFIX, user_id, device_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.set_ex(&key, 1, BACKFILL_CANCEL_EXPIRE as u64).await?;
+        Ok(())
+    }
+
+    /// 读取并清除回填取消标记
+    async fn take_backfill_cancel(&self, user_id: &str, device_id: &str) -> Result<bool, Error> {
+        let key = self.key(&format!("{}:{}:{}", BACKFILL_CANCEL_PREFIX, user_id, device_id));
+        let mut conn = self.get_connection().await?;
+        let deleted: i64 = conn.del(&key).await?;
+        Ok(deleted > 0)
+    }
+
+    /// 保存接收盒重建进度
+    async fn save_rebuild_progress(&self, user_id: &str, seq: i64) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", REBUILD_PROGRESS_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn
+            .set_ex(&key, seq, REBUILD_PROGRESS_EXPIRE as u64)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询接收盒重建进度
+    async fn get_rebuild_progress(&self, user_id: &str) -> Result<Option<i64>, Error> {
+        let key = self.key(&format!("{}:{}", REBUILD_PROGRESS_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let result: Option<i64> = conn.get(&key).await?;
         Ok(result)
     }
+
+    /// 删除接收盒重建进度
+    async fn delete_rebuild_progress(&self, user_id: &str) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", REBUILD_PROGRESS_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = conn.del(&key).await?;
+        Ok(())
+    }
+
+    /// 保存会话草稿
+    ///
+    /// 每次写入刷新用户草稿哈希表的过期时间，长期不编辑的草稿由Redis自动清理，
+    /// 持久化的副本在过期后用于恢复
+    ///
+    /// # 参数
+    /// * `user_id` - 用户ID
+    /// * `conversation_id` - 会话ID
+    /// * `draft` - 序列化后的草稿
+    /// * `ttl` - 过期时间（秒）
+    async fn save_draft(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        draft: &str,
+        ttl: i64,
+    ) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", DRAFT_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .hset(&key, conversation_id, draft)
+            .ignore()
+            .expire(&key, ttl)
+            .ignore()
+            .sadd(self.key(DRAFT_DIRTY_SET), user_id)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询用户的全部会话草稿
+    async fn get_drafts(&self, user_id: &str) -> Result<Vec<(String, String)>, Error> {
+        let key = self.key(&format!("{}:{}", DRAFT_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let result: Vec<(String, String)> = conn.hgetall(&key).await?;
+        Ok(result)
+    }
+
+    /// 删除会话草稿
+    async fn del_draft(&self, user_id: &str, conversation_id: &str) -> Result<(), Error> {
+        let key = self.key(&format!("{}:{}", DRAFT_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .hdel(&key, conversation_id)
+            .ignore()
+            .sadd(self.key(DRAFT_DIRTY_SET), user_id)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 取出待持久化草稿的用户
+    ///
+    /// # 参数
+    /// * `count` - 单次最多取出的用户数
+    async fn drain_dirty_drafts(&self, count: usize) -> Result<Vec<String>, Error> {
+        let mut conn = self.get_connection().await?;
+        let result: Vec<String> = redis::cmd("SPOP")
+            .arg(self.key(DRAFT_DIRTY_SET))
+            .arg(count)
+            .query_async(&mut conn)
+            .await?;
+        Ok(result)
+    }
+
+    /// 推进会话已读位置
+    ///
+    /// # 参数
+    /// * `user_id` - 用户ID
+    /// * `conversation_id` - 会话ID，单聊为对方用户ID，群聊为群组ID
+    /// * `read_seq` - 已读到的消息序号
+    async fn advance_read_cursor(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        read_seq: i64,
+    ) -> Result<bool, Error> {
+        let key = self.key(&format!("{}:{}", READ_CURSOR_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let updated: i64 = redis::Script::new(READ_CURSOR_SCRIPT)
+            .key(&key)
+            .arg(conversation_id)
+            .arg(read_seq)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(updated == 1)
+    }
+
+    /// 查询会话已读位置
+    async fn get_read_cursor(&self, user_id: &str, conversation_id: &str) -> Result<i64, Error> {
+        let key = self.key(&format!("{}:{}", READ_CURSOR_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let result: Option<i64> = conn.hget(&key, conversation_id).await?;
+        Ok(result.unwrap_or_default())
+    }
+
+    /// 查询用户全部会话的已读位置
+    async fn get_read_cursors(&self, user_id: &str) -> Result<Vec<(String, i64)>, Error> {
+        let key = self.key(&format!("{}:{}", READ_CURSOR_PREFIX, user_id));
+        let mut conn = self.get_connection().await?;
+        let result: Vec<(String, i64)> = conn.hgetall(&key).await?;
+        Ok(result)
+    }
+
+    /// 记录查看者查看过的用户资料
+    ///
+    /// # 参数
+    /// * `viewer_id` - 查看者ID
+    /// * `user_id` - 被查看的用户ID
+    /// * `window` - 统计窗口（秒）
+    ///
+    /// # 返回
+    /// * 窗口内查看过的不同用户数，重复查看同一用户不会增加
+    async fn record_profile_view(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+        window: i64,
+    ) -> Result<i64, Error> {
+        let key = self.key(&format!("{}:{}", PROFILE_VIEWS_PREFIX, viewer_id));
+        let mut conn = self.get_connection().await?;
+        let (_, count, ttl): (i64, i64, i64) = redis::pipe()
+            .sadd(&key, user_id)
+            .scard(&key)
+            .ttl(&key)
+            .query_async(&mut conn)
+            .await?;
+        // 只在窗口开始时设置过期时间，持续查看不会延长窗口
+        if ttl < 0 {
+            let _: () = conn.expire(&key, window.max(1)).await?;
+        }
+        Ok(count)
+    }
+
+    /// 保存消息服务实例的消费采样
+    ///
+    /// # 参数
+    /// * `instance_id` - 消息服务实例ID
+    /// * `sample` - 序列化后的采样
+    /// * `ttl` - 哈希表的保留时间（秒），每次上报时刷新
+    async fn save_ops_sample(&self, instance_id: &str, sample: &str, ttl: i64) -> Result<(), Error> {
+        let key = self.key(OPS_SAMPLES_KEY);
+        let mut conn = self.get_connection().await?;
+        let _: () = redis::pipe()
+            .hset(&key, instance_id, sample)
+            .expire(&key, ttl.max(1))
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 查询各消息服务实例最近一次的消费采样
+    async fn get_ops_samples(&self) -> Result<Vec<String>, Error> {
+        let key = self.key(OPS_SAMPLES_KEY);
+        let mut conn = self.get_connection().await?;
+        let result: Vec<String> = conn.hvals(&key).await?;
+        Ok(result)
+    }
+
+    /// 探测Redis是否可用
+    ///
+    /// 多路复用连接断开后不会自动重连，探测失败时重新建立连接并重新加载Lua脚本，
+    /// Redis重启后脚本缓存会清空，脚本内容不变时SHA也不变
+    async fn ping(&self) -> Result<(), Error> {
+        let mut conn = self.get_connection().await?;
+        let pong: Result<String, RedisError> = redis::cmd("PING").query_async(&mut conn).await;
+        if pong.is_ok() {
+            return Ok(());
+        }
+
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        Self::single_script_load(&mut conn).await?;
+        Self::group_script_load(&mut conn).await?;
+        *self.connection_manager.lock().await = conn;
+        Ok(())
+    }
 }
 
 /// 测试模块
@@ -0,0 +1,628 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::config::{AppConfig, DegradationConfig};
+use common::conversation_seq::ConversationSeqStore;
+use common::dependency_health::DependencyHealth;
+use common::error::Error;
+use common::message::GroupMemSeq;
+use common::seq_fallback::{PendingSeq, SeqFallback, SeqFallbackStore, SeqKind};
+use tracing::{error, info, warn};
+
+use crate::redis::DEFAULT_SEQ_STEP;
+use crate::Cache;
+
+/// 恢复后每批同步回Redis的序号数
+const RESYNC_BATCH_SIZE: i64 = 500;
+
+/// 初始化水位时每批扫描的键数
+const SEED_BATCH_SIZE: usize = 1000;
+
+/// Redis新预留的一段与降级分配的序号重复时，提高序号后重新分配的次数
+const CONFLICT_RETRIES: usize = 3;
+
+/// 消息序号分配
+///
+/// 正常情况下由Redis分配，Redis预留新一段序号时把上限记为Postgres中的水位；
+/// 降级期间从水位之后由Postgres分配。是否降级记录在Postgres中由所有实例共用：Redis不可用的实例
+/// 持续续期，到期后各实例先把降级期间分配的序号同步回Redis，再切回Redis分配。
+/// 各实例切换有先后，Redis新预留的一段与降级分配的序号重复时，记录水位时会发现，提高序号后重新分配
+#[derive(Debug, Clone)]
+pub struct SeqAllocator {
+    cache: Arc<dyn Cache>,
+    fallback: Option<Arc<dyn SeqFallbackStore>>,
+    conversations: Option<ConversationSeqStore>,
+    redis: DependencyHealth,
+    degraded: Arc<AtomicBool>,
+    /// 有水位记录失败，需要重新扫描Redis初始化水位
+    reseed: Arc<AtomicBool>,
+    /// 同步降级状态的间隔
+    interval: Duration,
+    /// Redis不可用时降级状态的续期时长
+    lease: Duration,
+}
+
+impl SeqAllocator {
+    /// 未启用降级时只使用Redis，也不记录水位
+    pub fn new(
+        config: &AppConfig,
+        cache: Arc<dyn Cache>,
+        redis: DependencyHealth,
+    ) -> Result<Self, Error> {
+        let fallback: Option<Arc<dyn SeqFallbackStore>> = if config.degradation.enabled {
+            let seq_step = match config.redis.seq_step {
+                0 => DEFAULT_SEQ_STEP,
+                seq_step => seq_step,
+            };
+            Some(Arc::new(SeqFallback::new(config, seq_step as i64)?))
+        } else {
+            None
+        };
+        // 多区域部署时群聊消息由各区域分别处理，无法保证会话序号唯一，不分配
+        let conversations = match (config.conversation_seq.enabled, config.region.enabled) {
+            (true, true) => {
+                warn!("多区域部署不支持会话序号，已忽略 conversation_seq.enabled");
+                None
+            }
+            (true, false) => Some(ConversationSeqStore::new(config)?),
+            _ => None,
+        };
+        Ok(Self {
+            conversations,
+            ..Self::with_fallback(cache, fallback, redis, &config.degradation)
+        })
+    }
+
+    fn with_fallback(
+        cache: Arc<dyn Cache>,
+        fallback: Option<Arc<dyn SeqFallbackStore>>,
+        redis: DependencyHealth,
+        config: &DegradationConfig,
+    ) -> Self {
+        let interval = Duration::from_millis(config.probe_interval_ms.max(100));
+        Self {
+            cache,
+            fallback,
+            conversations: None,
+            degraded: Arc::new(AtomicBool::new(redis.is_degraded())),
+            redis,
+            reseed: Arc::new(AtomicBool::new(false)),
+            interval,
+            lease: interval * (config.recovery_threshold.max(1) + 1),
+        }
+    }
+
+    /// 是否正在由Postgres分配序号
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::Acquire)
+    }
+
+    /// 启动后台任务，跟随Redis健康状态和所有实例共用的降级状态切换分配方式
+    pub fn start(&self) -> tokio::task::JoinHandle<()> {
+        let allocator = self.clone();
+        tokio::spawn(async move {
+            let mut state = allocator.redis.subscribe();
+            let mut interval = tokio::time::interval(allocator.interval);
+            loop {
+                tokio::select! {
+                    changed = state.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                    _ = interval.tick() => {}
+                }
+                allocator.sync_mode().await;
+            }
+        })
+    }
+
+    /// 分配接收序号，返回当前序号、最大序号和是否预留了新的一段
+    pub async fn increase_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error> {
+        self.incr_single(SeqKind::Receive, user_id).await
+    }
+
+    /// 分配发送序号，返回当前序号、最大序号和是否预留了新的一段
+    pub async fn incr_send_seq(&self, user_id: &str) -> Result<(i64, i64, bool), Error> {
+        self.incr_single(SeqKind::Send, user_id).await
+    }
+
+    /// 分配会话序号，未启用会话序号或正在降级时不分配，返回None
+    ///
+    /// 降级期间不在Postgres中分配会话序号，这段时间的消息没有会话序号，客户端按接收序号同步
+    pub async fn incr_conversation_seq(&self, conversation: &str) -> Result<Option<i64>, Error> {
+        let Some(store) = &self.conversations else {
+            return Ok(None);
+        };
+        if self.is_degraded() {
+            return Ok(None);
+        }
+
+        let allocated = match self.cache.incr_conversation_seq(conversation, None).await? {
+            Some(allocated) => allocated,
+            None => {
+                // 新会话或Redis数据丢失，从Postgres中的水位之后继续；读不到水位时本条消息不分配会话序号，
+                // 避免与已分配的序号重复
+                let watermark = match store.watermark(conversation).await {
+                    Ok(watermark) => watermark,
+                    Err(e) => {
+                        warn!("读取会话 {} 的序号水位失败: {:?}", conversation, e);
+                        return Ok(None);
+                    }
+                };
+                self.cache
+                    .incr_conversation_seq(conversation, Some(watermark))
+                    .await?
+                    .ok_or_else(|| {
+                        Error::Internal(format!("会话 {} 的序号分配失败", conversation))
+                    })?
+            }
+        };
+        let (cur_seq, max_seq, updated) = allocated;
+        // 水位记录失败不影响本次分配，下一段预留时再记录；期间Redis数据丢失可能从较低的水位重新分配
+        if updated {
+            if let Err(e) = store.record_watermark(conversation, max_seq).await {
+                warn!("记录会话 {} 的序号水位失败: {:?}", conversation, e);
+            }
+        }
+        Ok(Some(cur_seq))
+    }
+
+    /// 为群成员分配接收序号
+    ///
+    /// 降级期间分配的序号已写入Postgres，返回的成员序号不需要再保存最大序号
+    pub async fn incr_group_seq(&self, members: Vec<String>) -> Result<Vec<GroupMemSeq>, Error> {
+        if let Some(seqs) = self.allocate_degraded(SeqKind::Receive, &members).await? {
+            return Ok(seqs
+                .into_iter()
+                .map(|(user_id, seq)| GroupMemSeq::new(user_id, seq, seq, false))
+                .collect());
+        }
+
+        let mut seqs = self.cache.incr_group_seq(members).await?;
+        let mut reserved = reserved_segments(&seqs);
+        for _ in 0..CONFLICT_RETRIES {
+            let conflicts = self.record_watermarks(SeqKind::Receive, &reserved).await;
+            if conflicts.is_empty() {
+                return Ok(seqs);
+            }
+            // 只为与降级分配重复的成员重新分配
+            self.absorb(SeqKind::Receive, &conflicts).await?;
+            let retried = self
+                .cache
+                .incr_group_seq(conflicts.into_iter().map(|(user_id, _)| user_id).collect())
+                .await?;
+            reserved = reserved_segments(&retried);
+            for seq in retried {
+                if let Some(slot) = seqs.iter_mut().find(|s| s.mem_id == seq.mem_id) {
+                    *slot = seq;
+                }
+            }
+        }
+        Err(Error::Internal(
+            "群成员的序号与降级期间分配的序号重复".to_string(),
+        ))
+    }
+
+    async fn incr_single(&self, kind: SeqKind, user_id: &str) -> Result<(i64, i64, bool), Error> {
+        if let Some(seqs) = self.allocate_degraded(kind, &[user_id.to_string()]).await? {
+            let seq = seqs
+                .into_iter()
+                .next()
+                .map(|(_, seq)| seq)
+                .ok_or_else(|| Error::Internal(format!("用户 {} 的序号分配失败", user_id)))?;
+            return Ok((seq, seq, false));
+        }
+
+        for _ in 0..CONFLICT_RETRIES {
+            let (cur_seq, max_seq, updated) = match kind {
+                SeqKind::Receive => self.cache.increase_seq(user_id).await?,
+                SeqKind::Send => self.cache.incr_send_seq(user_id).await?,
+            };
+            if !updated {
+                return Ok((cur_seq, max_seq, updated));
+            }
+            let conflicts = self
+                .record_watermarks(kind, &[(user_id.to_string(), max_seq)])
+                .await;
+            if conflicts.is_empty() {
+                return Ok((cur_seq, max_seq, updated));
+            }
+            self.absorb(kind, &conflicts).await?;
+        }
+        Err(Error::Internal(format!(
+            "用户 {} 的序号与降级期间分配的序号重复",
+            user_id
+        )))
+    }
+
+    // 正在降级时由Postgres分配；全局不在降级模式或水位未初始化时返回None，改由Redis分配
+    async fn allocate_degraded(
+        &self,
+        kind: SeqKind,
+        user_ids: &[String],
+    ) -> Result<Option<Vec<(String, i64)>>, Error> {
+        match &self.fallback {
+            Some(fallback) if self.is_degraded() => fallback.allocate(kind, user_ids).await,
+            _ => Ok(None),
+        }
+    }
+
+    // 返回降级期间分配过的用户，这些用户刚预留的一段可能与降级分配的序号重复。
+    // 记录失败不影响本次分配，之后重新扫描Redis初始化水位
+    async fn record_watermarks(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Vec<(String, i64)> {
+        let Some(fallback) = &self.fallback else {
+            return Vec::new();
+        };
+        match fallback.record_watermarks(kind, seqs).await {
+            Ok(conflicts) => conflicts,
+            Err(e) => {
+                if !self.reseed.swap(true, Ordering::AcqRel) {
+                    warn!("记录序号水位失败，稍后重新初始化水位: {:?}", e);
+                }
+                Vec::new()
+            }
+        }
+    }
+
+    // 把Redis中的序号提高到降级分配过的序号之后，这些用户已同步，清除待同步标记
+    async fn absorb(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Result<(), Error> {
+        self.cache.raise_seq(kind, seqs).await?;
+        if let Some(fallback) = &self.fallback {
+            if let Err(e) = fallback.clear_pending(kind, seqs).await {
+                warn!("清除已同步序号的标记失败: {:?}", e);
+            }
+        }
+        Ok(())
+    }
+
+    // Redis不可用时续期全局降级状态；Redis可用时跟随全局状态，全局退出降级后同步完成才切回Redis
+    async fn sync_mode(&self) {
+        let Some(fallback) = &self.fallback else {
+            self.set_degraded(self.redis.is_degraded());
+            return;
+        };
+
+        if self.redis.is_degraded() {
+            self.set_degraded(true);
+            if let Err(e) = fallback.renew_degraded(self.lease).await {
+                warn!("延长序号降级状态失败: {:?}", e);
+            }
+            return;
+        }
+
+        let mode = match fallback.mode().await {
+            Ok(mode) => mode,
+            Err(e) => {
+                warn!("查询序号降级状态失败: {:?}", e);
+                return;
+            }
+        };
+        if !mode.seeded || self.reseed.load(Ordering::Acquire) {
+            if let Err(e) = self.seed(fallback.as_ref()).await {
+                self.reseed.store(true, Ordering::Release);
+                warn!("初始化序号水位失败: {:?}", e);
+            }
+        }
+        if mode.degraded {
+            self.set_degraded(true);
+            return;
+        }
+
+        match self.resync(fallback.as_ref()).await {
+            Ok(count) => {
+                if count > 0 {
+                    info!("已同步 {} 个降级期间分配的序号", count);
+                }
+                self.set_degraded(false);
+            }
+            Err(e) => error!("同步降级期间分配的序号失败: {:?}", e),
+        }
+    }
+
+    fn set_degraded(&self, degraded: bool) {
+        if self.degraded.swap(degraded, Ordering::AcqRel) == degraded {
+            return;
+        }
+        if degraded {
+            warn!("序号进入降级模式");
+        } else {
+            info!("序号退出降级模式，切回Redis分配");
+        }
+    }
+
+    // 用Redis中的最大序号初始化水位，Redis中已有但从未记录过水位的用户也不会被降级分配重复
+    async fn seed(&self, fallback: &dyn SeqFallbackStore) -> Result<(), Error> {
+        self.reseed.store(false, Ordering::Release);
+        for kind in [SeqKind::Receive, SeqKind::Send] {
+            let mut cursor = 0;
+            loop {
+                let (next, seqs) = self
+                    .cache
+                    .scan_max_seqs(kind, cursor, SEED_BATCH_SIZE)
+                    .await?;
+                fallback.record_watermarks(kind, &seqs).await?;
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+        fallback.set_seeded().await?;
+        info!("已用Redis中的最大序号初始化序号水位");
+        Ok(())
+    }
+
+    // 把降级期间分配的序号同步回Redis，返回同步的条数
+    async fn resync(&self, fallback: &dyn SeqFallbackStore) -> Result<usize, Error> {
+        let mut total = 0;
+        loop {
+            let pending = fallback.take_pending(RESYNC_BATCH_SIZE).await?;
+            if pending.is_empty() {
+                return Ok(total);
+            }
+            if let Err(e) = self.raise(&pending).await {
+                fallback.restore_pending(&pending).await?;
+                return Err(e);
+            }
+            total += pending.len();
+            if (pending.len() as i64) < RESYNC_BATCH_SIZE {
+                return Ok(total);
+            }
+        }
+    }
+
+    async fn raise(&self, pending: &[PendingSeq]) -> Result<(), Error> {
+        for kind in [SeqKind::Receive, SeqKind::Send] {
+            let seqs: Vec<(String, i64)> = pending
+                .iter()
+                .filter(|p| p.kind == kind)
+                .map(|p| (p.user_id.clone(), p.seq))
+                .collect();
+            self.cache.raise_seq(kind, &seqs).await?;
+        }
+        Ok(())
+    }
+}
+
+// 预留了新一段的成员和新的上限
+fn reserved_segments(seqs: &[GroupMemSeq]) -> Vec<(String, i64)> {
+    seqs.iter()
+        .filter(|seq| seq.need_update)
+        .map(|seq| (seq.mem_id.clone(), seq.max_seq))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use common::seq_fallback::SeqMode;
+
+    use super::*;
+    use crate::MemoryCache;
+
+    const SEQ_STEP: i64 = 2;
+
+    /// 内存中的水位表，行为与Postgres实现一致
+    #[derive(Debug, Default)]
+    struct MemoryFallback {
+        rows: Mutex<HashMap<(SeqKind, String), (i64, bool)>>,
+        mode: Mutex<SeqMode>,
+        fail_record: AtomicBool,
+    }
+
+    impl MemoryFallback {
+        fn watermark(&self, kind: SeqKind, user_id: &str) -> Option<i64> {
+            self.rows
+                .lock()
+                .unwrap()
+                .get(&(kind, user_id.to_string()))
+                .map(|(seq, _)| *seq)
+        }
+
+        fn set_mode(&self, degraded: bool, seeded: bool) {
+            *self.mode.lock().unwrap() = SeqMode { degraded, seeded };
+        }
+    }
+
+    #[async_trait]
+    impl SeqFallbackStore for MemoryFallback {
+        async fn record_watermarks(
+            &self,
+            kind: SeqKind,
+            seqs: &[(String, i64)],
+        ) -> common::Result<Vec<(String, i64)>> {
+            if self.fail_record.load(Ordering::Acquire) {
+                return Err(Error::Internal("relation does not exist".to_string()));
+            }
+            let mut rows = self.rows.lock().unwrap();
+            let mut conflicts = Vec::new();
+            for (user_id, seq) in seqs {
+                let row = rows.entry((kind, user_id.clone())).or_insert((*seq, false));
+                row.0 = row.0.max(*seq);
+                if row.1 {
+                    conflicts.push((user_id.clone(), row.0));
+                }
+            }
+            Ok(conflicts)
+        }
+
+        async fn allocate(
+            &self,
+            kind: SeqKind,
+            user_ids: &[String],
+        ) -> common::Result<Option<Vec<(String, i64)>>> {
+            let mode = *self.mode.lock().unwrap();
+            if !mode.degraded || !mode.seeded {
+                return Ok(None);
+            }
+            let mut rows = self.rows.lock().unwrap();
+            Ok(Some(
+                user_ids
+                    .iter()
+                    .map(|user_id| {
+                        let row = rows
+                            .entry((kind, user_id.clone()))
+                            .and_modify(|row| row.0 += 1)
+                            .or_insert((SEQ_STEP + 1, true));
+                        row.1 = true;
+                        (user_id.clone(), row.0)
+                    })
+                    .collect(),
+            ))
+        }
+
+        async fn take_pending(&self, limit: i64) -> common::Result<Vec<PendingSeq>> {
+            let mut rows = self.rows.lock().unwrap();
+            Ok(rows
+                .iter_mut()
+                .filter(|(_, (_, dirty))| *dirty)
+                .take(limit as usize)
+                .map(|((kind, user_id), row)| {
+                    row.1 = false;
+                    PendingSeq {
+                        user_id: user_id.clone(),
+                        kind: *kind,
+                        seq: row.0,
+                    }
+                })
+                .collect())
+        }
+
+        async fn restore_pending(&self, pending: &[PendingSeq]) -> common::Result<()> {
+            let mut rows = self.rows.lock().unwrap();
+            for p in pending {
+                if let Some(row) = rows.get_mut(&(p.kind, p.user_id.clone())) {
+                    row.1 = true;
+                }
+            }
+            Ok(())
+        }
+
+        async fn clear_pending(&self, kind: SeqKind, seqs: &[(String, i64)]) -> common::Result<()> {
+            let mut rows = self.rows.lock().unwrap();
+            for (user_id, seq) in seqs {
+                if let Some(row) = rows.get_mut(&(kind, user_id.clone())) {
+                    if row.0 <= *seq {
+                        row.1 = false;
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        async fn mode(&self) -> common::Result<SeqMode> {
+            Ok(*self.mode.lock().unwrap())
+        }
+
+        async fn renew_degraded(&self, _lease: Duration) -> common::Result<()> {
+            self.mode.lock().unwrap().degraded = true;
+            Ok(())
+        }
+
+        async fn set_seeded(&self) -> common::Result<()> {
+            self.mode.lock().unwrap().seeded = true;
+            Ok(())
+        }
+    }
+
+    fn allocator(cache: Arc<MemoryCache>, fallback: Arc<MemoryFallback>) -> SeqAllocator {
+        SeqAllocator::with_fallback(
+            cache,
+            Some(fallback),
+            DependencyHealth::always_healthy("redis"),
+            &DegradationConfig::default(),
+        )
+    }
+
+    async fn receive_seqs(allocator: &SeqAllocator, user_id: &str, count: usize) -> Vec<i64> {
+        let mut seqs = Vec::new();
+        for _ in 0..count {
+            seqs.push(allocator.increase_seq(user_id).await.unwrap().0);
+        }
+        seqs
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_does_not_fail_allocation() {
+        let fallback = Arc::new(MemoryFallback::default());
+        fallback.fail_record.store(true, Ordering::Release);
+        let allocator = allocator(Arc::new(MemoryCache::with_seq_step(SEQ_STEP)), fallback);
+
+        assert_eq!(receive_seqs(&allocator, "u1", 5).await, vec![1, 2, 3, 4, 5]);
+        assert!(allocator.reseed.load(Ordering::Acquire));
+    }
+
+    #[tokio::test]
+    async fn test_degraded_allocation_requires_shared_mode() {
+        let cache = Arc::new(MemoryCache::with_seq_step(SEQ_STEP));
+        let fallback = Arc::new(MemoryFallback::default());
+        let allocator = allocator(cache, fallback.clone());
+        allocator.degraded.store(true, Ordering::Release);
+
+        // 水位未初始化时不降级分配
+        fallback.set_mode(true, false);
+        assert_eq!(receive_seqs(&allocator, "u1", 1).await, vec![1]);
+
+        // 全局不在降级模式时也不降级分配
+        fallback.set_mode(false, true);
+        assert_eq!(receive_seqs(&allocator, "u1", 1).await, vec![2]);
+        assert_eq!(fallback.watermark(SeqKind::Receive, "u1"), None);
+    }
+
+    #[tokio::test]
+    async fn test_seed_and_resync() {
+        let cache = Arc::new(MemoryCache::with_seq_step(SEQ_STEP));
+        let fallback = Arc::new(MemoryFallback::default());
+        let allocator = allocator(cache, fallback.clone());
+
+        // 第一段不会报告，由初始化扫描记录水位
+        assert_eq!(receive_seqs(&allocator, "u1", 1).await, vec![1]);
+        assert_eq!(fallback.watermark(SeqKind::Receive, "u1"), None);
+        allocator.sync_mode().await;
+        assert_eq!(fallback.watermark(SeqKind::Receive, "u1"), Some(2));
+        assert!(fallback.mode.lock().unwrap().seeded);
+
+        // 其他实例进入降级后跟随切换，从水位之后分配
+        fallback.set_mode(true, true);
+        allocator.sync_mode().await;
+        assert!(allocator.is_degraded());
+        assert_eq!(receive_seqs(&allocator, "u1", 2).await, vec![3, 4]);
+
+        // 全局退出降级后先同步回Redis再切回
+        fallback.set_mode(false, true);
+        allocator.sync_mode().await;
+        assert!(!allocator.is_degraded());
+        assert_eq!(receive_seqs(&allocator, "u1", 1).await, vec![5]);
+    }
+
+    #[tokio::test]
+    async fn test_reserved_segment_skips_degraded_seqs() {
+        let cache = Arc::new(MemoryCache::with_seq_step(SEQ_STEP));
+        let fallback = Arc::new(MemoryFallback::default());
+        fallback.set_mode(true, true);
+
+        // 两个实例共用Redis和水位，A已切换为降级分配，B还在使用Redis
+        let a = allocator(cache.clone(), fallback.clone());
+        a.degraded.store(true, Ordering::Release);
+        let b = allocator(cache, fallback.clone());
+
+        let mut seqs = receive_seqs(&b, "u1", 1).await;
+        seqs.extend(receive_seqs(&a, "u1", 2).await);
+        seqs.extend(receive_seqs(&b, "u1", 2).await);
+        assert_eq!(seqs, vec![1, 3, 4, 2, 5]);
+
+        // 群聊消息同样如此：B在A降级分配之后预留的一段重新分配，没有冲突的成员不受影响
+        let members = vec!["u1".to_string(), "u2".to_string()];
+        let mut group = Vec::new();
+        for allocator in [&a, &b, &b] {
+            let seqs = allocator.incr_group_seq(members.clone()).await.unwrap();
+            group.push(seqs.iter().map(|seq| seq.cur_seq).collect::<Vec<_>>());
+        }
+        assert_eq!(group, vec![vec![7, 3], vec![6, 1], vec![9, 2]]);
+    }
+}
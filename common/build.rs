@@ -18,6 +18,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "private_message.proto",
         "group_message.proto",
         "message_gateway.proto",
+        "job.proto",
+        "auth.proto",
+        "retention.proto",
+        "backfill.proto",
+        "push_stream.proto",
+        "message_request.proto",
+        "draft.proto",
+        "transaction.proto",
+        "access_log.proto",
+        "mailbox.proto",
+        "storage.proto",
+        "msg_store.proto",
     ];
 
     // 编译所有proto文件并生成文件描述符集
@@ -0,0 +1,244 @@
+//! 消息附件描述
+//!
+//! 图片、视频、语音、文件消息的 content 为 JSON 格式的附件描述，
+//! 文件本身已由客户端上传到OSS。消息服务在写入Kafka之前校验描述的合法性。
+
+use crate::config::AttachmentConfig;
+use crate::message::ContentType;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// 附件描述
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentDescriptor {
+    /// OSS对象键
+    pub object_key: String,
+    /// 下载地址
+    pub url: String,
+    /// 原始文件名
+    #[serde(default)]
+    pub file_name: String,
+    /// MIME类型
+    pub mime_type: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 图片/视频宽度
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// 图片/视频高度
+    #[serde(default)]
+    pub height: Option<u32>,
+    /// 音视频时长（秒）
+    #[serde(default)]
+    pub duration: Option<u32>,
+    /// 文件SHA-256摘要，供扫描服务去重
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// 缩略图地址，由服务端在缩略图生成后填充
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    /// 图片的blurhash占位符
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blurhash: Option<String>,
+}
+
+/// 文件上传完成事件，用户服务确认附件上传完成时写入 `kafka.upload_topic`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadCompletedEvent {
+    /// OSS对象键
+    pub object_key: String,
+    /// MIME类型
+    pub mime_type: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 上传者ID
+    pub uploader_id: String,
+}
+
+impl AttachmentDescriptor {
+    /// 解析并校验消息内容中的附件描述
+    ///
+    /// # 返回
+    /// * 非附件类消息返回 `Ok(None)`
+    pub fn from_msg_content(
+        content_type: i32,
+        content: &[u8],
+        config: &AttachmentConfig,
+    ) -> Result<Option<Self>> {
+        let content_type = match ContentType::try_from(content_type) {
+            Ok(
+                ct @ (ContentType::Image | ContentType::Video | ContentType::Audio | ContentType::File),
+            ) => ct,
+            _ => return Ok(None),
+        };
+
+        let descriptor: Self = serde_json::from_slice(content)
+            .map_err(|e| Error::BadRequest(format!("附件描述格式错误: {}", e)))?;
+        descriptor.validate(content_type, config)?;
+        Ok(Some(descriptor))
+    }
+
+    /// 按内容类型校验大小、MIME类型和尺寸
+    pub fn validate(&self, content_type: ContentType, config: &AttachmentConfig) -> Result<()> {
+        if self.object_key.is_empty() || self.object_key.contains("..") {
+            return Err(Error::BadRequest("附件对象键无效".to_string()));
+        }
+        if self.url.is_empty() {
+            return Err(Error::BadRequest("附件地址不能为空".to_string()));
+        }
+
+        let mime = self.mime_type.to_lowercase();
+        let (category, max_size) = match content_type {
+            ContentType::Image => (Some("image/"), config.max_image_size),
+            ContentType::Video => (Some("video/"), config.max_video_size),
+            ContentType::Audio => (Some("audio/"), config.max_audio_size),
+            _ => (None, config.max_file_size),
+        };
+
+        if let Some(prefix) = category {
+            if !mime.starts_with(prefix) {
+                return Err(Error::BadRequest(format!(
+                    "附件类型 {} 与消息类型不匹配",
+                    self.mime_type
+                )));
+            }
+        }
+        if !mime_allowed(&mime, &config.allowed_mime_types) {
+            return Err(Error::BadRequest(format!(
+                "不允许的附件类型: {}",
+                self.mime_type
+            )));
+        }
+
+        if self.size == 0 || self.size > max_size {
+            return Err(Error::BadRequest(format!(
+                "附件大小超出限制: {} > {}",
+                self.size, max_size
+            )));
+        }
+
+        match content_type {
+            // 图片必须携带尺寸，客户端据此预留占位
+            ContentType::Image => match (self.width, self.height) {
+                (Some(w), Some(h)) => check_dimension(w, h, config.max_dimension)?,
+                _ => return Err(Error::BadRequest("图片附件缺少尺寸".to_string())),
+            },
+            ContentType::Video => {
+                if let (Some(w), Some(h)) = (self.width, self.height) {
+                    check_dimension(w, h, config.max_dimension)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// 申请上传地址时按MIME类型校验文件类型和大小，大小上限与对应消息类型的附件一致
+pub fn validate_upload(mime_type: &str, size: u64, config: &AttachmentConfig) -> Result<()> {
+    let mime = mime_type.to_lowercase();
+    if !mime_allowed(&mime, &config.allowed_mime_types) {
+        return Err(Error::BadRequest(format!("不允许的附件类型: {}", mime_type)));
+    }
+    let max_size = match mime.split_once('/').map(|(top, _)| top) {
+        Some("image") => config.max_image_size,
+        Some("video") => config.max_video_size,
+        Some("audio") => config.max_audio_size,
+        _ => config.max_file_size,
+    };
+    if size == 0 || size > max_size {
+        return Err(Error::BadRequest(format!(
+            "附件大小超出限制: {} > {}",
+            size, max_size
+        )));
+    }
+    Ok(())
+}
+
+fn check_dimension(width: u32, height: u32, max: u32) -> Result<()> {
+    if width == 0 || height == 0 || width > max || height > max {
+        return Err(Error::BadRequest(format!(
+            "附件尺寸无效: {}x{}",
+            width, height
+        )));
+    }
+    Ok(())
+}
+
+/// MIME类型是否在允许列表中，`image/*` 匹配所有图片类型
+fn mime_allowed(mime: &str, allowed: &[String]) -> bool {
+    allowed.iter().any(|pattern| {
+        let pattern = pattern.to_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some(prefix) => mime
+                .split_once('/')
+                .is_some_and(|(top, _)| top == prefix),
+            None => pattern == mime,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> AttachmentDescriptor {
+        AttachmentDescriptor {
+            object_key: "chat/2025/05/a.png".to_string(),
+            url: "https://oss.example.com/chat/2025/05/a.png".to_string(),
+            file_name: "a.png".to_string(),
+            mime_type: "image/png".to_string(),
+            size: 1024,
+            width: Some(800),
+            height: Some(600),
+            duration: None,
+            sha256: None,
+            thumbnail_url: None,
+            blurhash: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_attachment() {
+        let config = AttachmentConfig::default();
+        assert!(image().validate(ContentType::Image, &config).is_ok());
+
+        // MIME类型与消息类型不匹配
+        assert!(image().validate(ContentType::Video, &config).is_err());
+
+        let mut no_size = image();
+        no_size.width = None;
+        assert!(no_size.validate(ContentType::Image, &config).is_err());
+
+        let mut too_large = image();
+        too_large.size = config.max_image_size + 1;
+        assert!(too_large.validate(ContentType::Image, &config).is_err());
+
+        let mut exe = image();
+        exe.mime_type = "application/x-msdownload".to_string();
+        assert!(exe.validate(ContentType::File, &config).is_err());
+
+        // 文本消息不做附件校验
+        let parsed = AttachmentDescriptor::from_msg_content(
+            ContentType::Text as i32,
+            b"hello",
+            &config,
+        )
+        .unwrap();
+        assert!(parsed.is_none());
+    }
+
+    #[test]
+    fn test_validate_upload() {
+        let config = AttachmentConfig::default();
+        assert!(validate_upload("image/png", 1024, &config).is_ok());
+        assert!(validate_upload("image/png", config.max_image_size + 1, &config).is_err());
+        // 普通文件使用文件的大小上限
+        assert!(validate_upload("application/pdf", config.max_image_size + 1, &config).is_ok());
+        assert!(validate_upload("application/x-msdownload", 1024, &config).is_err());
+        assert!(validate_upload("text/plain", 0, &config).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! 客户端版本
+//!
+//! 网关和消息网关按 [`ClientVersionConfig`] 中各平台的最低版本判断客户端是否需要强制升级，
+//! 并按平台、版本统计客户端分布
+
+use std::fmt;
+
+use crate::config::ClientVersionConfig;
+
+/// 版本、平台标签的最大长度，避免指标标签无限增长
+const MAX_LABEL_LEN: usize = 32;
+
+/// 客户端版本号，只比较主、次、修订版本，预发布和构建后缀忽略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ClientVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ClientVersion {
+    /// 解析 `2`、`2.1`、`v2.1.3`、`2.1.3-beta+5` 形式的版本号，缺少的部分按0处理
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        let core = version.split(['-', '+']).next()?;
+
+        let mut parts = core.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        let patch = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// 版本检查结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionCheck {
+    /// 允许访问
+    Allowed,
+    /// 需要升级到不低于最低版本
+    UpgradeRequired { min_version: String },
+}
+
+impl ClientVersionConfig {
+    /// 检查平台上的客户端版本，未配置最低版本的平台不限制
+    pub fn check(&self, platform: &str, version: Option<&str>) -> VersionCheck {
+        if !self.enabled {
+            return VersionCheck::Allowed;
+        }
+        let Some(min_version) = self.min_versions.get(&platform.to_ascii_lowercase()) else {
+            return VersionCheck::Allowed;
+        };
+        // 最低版本配置有误时不限制，避免误拦全部客户端
+        let Some(min) = ClientVersion::parse(min_version) else {
+            return VersionCheck::Allowed;
+        };
+
+        let outdated = match version.and_then(ClientVersion::parse) {
+            Some(version) => version < min,
+            None => self.reject_unknown,
+        };
+        if outdated {
+            VersionCheck::UpgradeRequired {
+                min_version: min.to_string(),
+            }
+        } else {
+            VersionCheck::Allowed
+        }
+    }
+
+    /// HTTP请求的路径是否不检查版本
+    pub fn is_exempt(&self, path: &str) -> bool {
+        self.exempt_paths
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+    }
+}
+
+/// 用作指标标签的值，缺失或包含异常字符时为 unknown
+pub fn label(value: Option<&str>) -> String {
+    value
+        .map(str::trim)
+        .filter(|v| {
+            !v.is_empty()
+                && v.len() <= MAX_LABEL_LEN
+                && v.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+        })
+        .map_or_else(|| "unknown".to_string(), str::to_ascii_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let v = |major, minor, patch| ClientVersion {
+            major,
+            minor,
+            patch,
+        };
+        assert_eq!(ClientVersion::parse("2.1.3"), Some(v(2, 1, 3)));
+        assert_eq!(ClientVersion::parse("v2.1"), Some(v(2, 1, 0)));
+        assert_eq!(ClientVersion::parse("2.1.3-beta+5"), Some(v(2, 1, 3)));
+        assert_eq!(ClientVersion::parse("2.1.3.4"), None);
+        assert_eq!(ClientVersion::parse("latest"), None);
+        assert!(v(2, 10, 0) > v(2, 9, 9));
+    }
+
+    #[test]
+    fn test_check_version() {
+        let mut config = ClientVersionConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config
+            .min_versions
+            .insert("mobile".to_string(), "2.3.0".to_string());
+
+        let upgrade = VersionCheck::UpgradeRequired {
+            min_version: "2.3.0".to_string(),
+        };
+        assert_eq!(config.check("Mobile", Some("2.2.9")), upgrade);
+        assert_eq!(config.check("mobile", Some("2.3.0")), VersionCheck::Allowed);
+        assert_eq!(
+            config.check("desktop", Some("1.0.0")),
+            VersionCheck::Allowed
+        );
+        assert_eq!(config.check("mobile", None), VersionCheck::Allowed);
+
+        config.reject_unknown = true;
+        assert_eq!(config.check("mobile", Some("dev")), upgrade);
+
+        config.enabled = false;
+        assert_eq!(config.check("mobile", Some("1.0.0")), VersionCheck::Allowed);
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(label(None), "unknown");
+        assert_eq!(label(Some(" 2.3.1-Beta ")), "2.3.1-beta");
+        assert_eq!(label(Some("2.3 <script>")), "unknown");
+    }
+}
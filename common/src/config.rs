@@ -1,6 +1,7 @@
 use config::{Config, ConfigError, File, FileFormat};
 use dotenv::dotenv;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::path::Path;
 use std::sync::{Arc, RwLock};
 use std::thread;
@@ -24,6 +25,42 @@ pub struct MongodbConfig {
     pub password: Option<String>,
     pub database: String,
     pub clean: MongodbCleanConfig,
+    #[serde(default)]
+    pub pseudonym: PseudonymConfig, // 接收盒ID化名配置
+}
+
+/// 接收盒ID化名配置
+///
+/// 启用后消息盒子中的用户ID和群组ID以HMAC化名保存，密钥轮换时把新密钥设为
+/// active_key，旧密钥保留到旧数据过期后再删除
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PseudonymConfig {
+    pub enabled: bool,
+    /// 写入时使用的密钥ID
+    pub active_key: String,
+    pub keys: Vec<PseudonymKeyConfig>,
+}
+
+/// 化名密钥，secret_env 优先，用于读取KMS或密钥管理系统注入的环境变量
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PseudonymKeyConfig {
+    pub id: String,
+    pub secret: Option<String>,
+    pub secret_env: Option<String>,
+}
+
+impl MongodbConfig {
+    pub fn url(&self) -> String {
+        match (&self.user, &self.password) {
+            (Some(user), Some(password)) if !user.is_empty() => format!(
+                "mongodb://{}:{}@{}:{}",
+                user, password, self.host, self.port
+            ),
+            _ => format!("mongodb://{}:{}", self.host, self.port),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -60,12 +97,26 @@ pub struct RedisConfig {
     pub max_connections: Option<usize>,
     pub pool_timeout_ms: Option<u64>,
     pub connection_timeout_ms: Option<u64>,
+    /// 键前缀（命名空间），多个环境共用同一个Redis时用于隔离，如 "staging"
+    pub key_prefix: Option<String>,
+    /// 启动时将启用键前缀之前写入的无前缀旧键迁移到 `key_prefix` 下；
+    /// 只应在首次启用前缀时开启，多个环境共用Redis时开启会把其他环境的键迁移过来
+    #[serde(default)]
+    pub migrate_legacy_keys: bool,
 }
 
 impl RedisConfig {
     pub fn url(&self) -> String {
         format!("redis://{}:{}", self.host, self.port)
     }
+
+    /// 规范化后的键前缀，未配置时为空字符串，否则以 ':' 结尾
+    pub fn key_prefix(&self) -> String {
+        match self.key_prefix.as_deref().map(|p| p.trim_end_matches(':')) {
+            Some(prefix) if !prefix.is_empty() => format!("{}:", prefix),
+            _ => String::new(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -74,6 +125,49 @@ pub struct KafkaProducerConfig {
     pub acks: String,
     pub max_retry: u32,
     pub retry_interval: u64,
+    #[serde(default)]
+    pub payload_format: KafkaPayloadFormat, // 消息编码格式
+    #[serde(default)]
+    pub spill: KafkaSpillConfig, // Kafka不可用时的本地暂存配置
+}
+
+/// Kafka不可用时的本地暂存配置
+///
+/// 代理短暂不可用时消息先写入本地文件，代理恢复后按顺序补发，客户端收到降级接收的响应
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KafkaSpillConfig {
+    pub enabled: bool,
+    /// 暂存文件所在目录
+    pub dir: String,
+    /// 最多暂存的消息数，超过后发送直接失败
+    pub max_messages: usize,
+    /// 补发检查间隔（毫秒）
+    pub replay_interval_ms: u64,
+}
+
+impl Default for KafkaSpillConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "./data/kafka-spill".to_string(),
+            max_messages: 10_000,
+            replay_interval_ms: 1_000,
+        }
+    }
+}
+
+/// 写入Kafka的消息编码格式
+///
+/// 消费端按消息头识别格式，没有格式头的消息按JSON解析。
+/// 默认与旧版本一致写入JSON，旧版本消费者只能解析JSON，滚动升级期间不能切换；
+/// 待消费端全部升级后再显式配置为protobuf
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KafkaPayloadFormat {
+    Protobuf,
+    #[default]
+    Json,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -90,6 +184,46 @@ pub struct KafkaConfig {
     pub connect_timeout: u64,
     pub producer: KafkaProducerConfig,
     pub consumer: KafkaConsumerConfig,
+    #[serde(default = "default_upload_topic")]
+    pub upload_topic: String,  // 文件上传完成事件主题
+    #[serde(default)]
+    pub tenant_topics: TenantTopicsConfig, // 大租户独立的消息主题
+}
+
+/// 大租户独立的消息主题配置
+///
+/// 配置的租户消息写入按模板生成的独立主题，消费端为每个主题使用独立的消费者，
+/// 单个租户的消息积压不会影响其他租户；未配置的租户仍使用默认主题
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TenantTopicsConfig {
+    pub enabled: bool,
+    /// 主题名模板，`{tenant}` 替换为租户ID
+    pub template: String,
+    pub tenants: Vec<TenantTopicConfig>,
+}
+
+impl Default for TenantTopicsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "im-msg-{tenant}".to_string(),
+            tenants: Vec::new(),
+        }
+    }
+}
+
+/// 使用独立主题的租户
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TenantTopicConfig {
+    pub id: String,
+    /// 每秒最多消费的消息数，未配置时不限制
+    #[serde(default)]
+    pub max_msgs_per_sec: Option<u32>,
+}
+
+fn default_upload_topic() -> String {
+    "rustIM-upload".to_string()
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -137,6 +271,155 @@ pub struct WebsocketConfig {
     pub port: u16,
     pub name: String,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub limits: ConnectionLimitConfig, // 连接数限制
+    #[serde(default)]
+    pub outbound: OutboundQueueConfig, // 下行发送队列
+    #[serde(default)]
+    pub topology: TopologyConfig, // 网关拓扑变更通知
+    #[serde(default)]
+    pub tls: WsTlsConfig, // 不经过负载均衡直接提供wss时的TLS配置
+}
+
+/// WebSocket服务的TLS配置
+///
+/// 证书来自PEM文件，或配置acme后通过ACME（如Let's Encrypt）自动申请和续期
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WsTlsConfig {
+    pub enabled: bool,
+    /// PEM格式的证书链文件路径
+    pub cert_path: String,
+    /// PEM格式的私钥文件路径
+    pub key_path: String,
+    /// ALPN协议列表，WebSocket握手使用HTTP/1.1
+    pub alpn: Vec<String>,
+    /// 明文端口，配置后在该端口将ws/http请求重定向到wss/https
+    pub redirect_port: Option<u16>,
+    /// 通过ACME自动申请证书，配置后忽略证书文件路径
+    pub acme: Option<WsAcmeConfig>,
+}
+
+impl Default for WsTlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: String::new(),
+            key_path: String::new(),
+            alpn: vec!["http/1.1".to_string()],
+            redirect_port: None,
+            acme: None,
+        }
+    }
+}
+
+/// ACME自动申请证书配置，使用TLS-ALPN-01验证，证书缓存在本地目录
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WsAcmeConfig {
+    /// 申请证书的域名
+    pub domains: Vec<String>,
+    /// 联系邮箱
+    #[serde(default)]
+    pub contacts: Vec<String>,
+    /// 证书和账户的缓存目录
+    pub cache_dir: String,
+    /// 使用Let's Encrypt正式环境，关闭时使用测试环境
+    #[serde(default)]
+    pub production: bool,
+}
+
+/// 网关拓扑变更通知配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TopologyConfig {
+    /// 是否向客户端推送拓扑变更
+    pub enabled: bool,
+    /// 从Consul刷新网关实例列表的间隔（秒）
+    pub refresh_interval_secs: u64,
+    /// 下线前通知客户端后等待其迁移的时间（秒）
+    pub drain_grace_secs: u64,
+}
+
+impl Default for TopologyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            refresh_interval_secs: 30,
+            drain_grace_secs: 10,
+        }
+    }
+}
+
+/// 发送队列满时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowConsumerPolicy {
+    /// 丢弃最早的非关键消息，没有可丢弃的消息时断开连接
+    DropOldest,
+    /// 直接断开连接，由客户端重连后按序号补齐
+    Disconnect,
+}
+
+/// WebSocket下行发送队列配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OutboundQueueConfig {
+    /// 每个连接的发送队列容量
+    pub capacity: usize,
+    /// 队列满时的处理策略
+    pub policy: SlowConsumerPolicy,
+}
+
+impl Default for OutboundQueueConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 256,
+            policy: SlowConsumerPolicy::DropOldest,
+        }
+    }
+}
+
+/// WebSocket连接数限制配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConnectionLimitConfig {
+    /// 单个网关实例的最大连接数，0表示不限制
+    pub max_connections: usize,
+    /// 单个IP的最大连接数，0表示不限制
+    pub max_per_ip: usize,
+    /// 达到全局上限时是否返回排队响应，提示客户端稍后重试
+    pub waiting_room: bool,
+    /// 排队响应中建议客户端的重试间隔（秒）
+    pub retry_after_secs: u64,
+    /// 是否信任X-Forwarded-For中的客户端IP，网关部署在反向代理之后时开启
+    pub trust_forwarded_for: bool,
+    /// 可信代理的地址，取X-Forwarded-For中从右向左第一个不在此列表中的地址；为空时取最右侧的地址
+    pub trusted_proxies: Vec<IpAddr>,
+    /// 每秒接入的新连接数，0表示不限制；网关重启后大量客户端同时重连时平滑接入
+    pub accept_rate: u64,
+    /// 接入令牌桶容量，允许的瞬时突发连接数
+    pub accept_burst: u64,
+    /// 为携带续传序号且令牌有效的重连客户端保留的令牌数，普通客户端不能使用；需小于 accept_burst
+    pub priority_reserve: u64,
+    /// 限流响应在重试间隔上附加的随机抖动上限（秒），打散客户端的重试时间
+    pub retry_jitter_secs: u64,
+}
+
+impl Default for ConnectionLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 0,
+            max_per_ip: 0,
+            waiting_room: false,
+            retry_after_secs: 5,
+            trust_forwarded_for: false,
+            trusted_proxies: Vec::new(),
+            accept_rate: 0,
+            accept_burst: 200,
+            priority_reserve: 50,
+            retry_jitter_secs: 10,
+        }
+    }
 }
 
 impl WebsocketConfig {
@@ -150,6 +433,16 @@ impl WebsocketConfig {
         url(https, &self.host, self.port)
     }
 
+    /// 客户端连接使用的协议，网关自行终止TLS时为wss
+    #[inline]
+    pub fn client_protocol(&self) -> &str {
+        if self.tls.enabled {
+            "wss"
+        } else {
+            &self.protocol
+        }
+    }
+
     #[inline]
     pub fn ws_url(&self, secure: bool) -> String {
         if secure {
@@ -272,6 +565,1110 @@ pub struct AppConfig {
     pub jwt: JwtConfig,
     pub oss: OssConfig,
     pub mail: MailConfig,
+    #[serde(default)]
+    pub push: PushConfig,  // 离线推送配置
+    #[serde(default)]
+    pub attachment: AttachmentConfig,  // 消息附件校验配置
+    #[serde(default)]
+    pub backfill: BackfillConfig,  // 新设备历史消息回填配置
+    #[serde(default)]
+    pub receive_box_rebuild: ReceiveBoxRebuildConfig,  // 接收盒重建配置
+    #[serde(default)]
+    pub invite: InviteConfig,  // 手机号邀请配置
+    #[serde(default)]
+    pub signaling: SignalingConfig,  // 信令消息投递期限配置
+    #[serde(default)]
+    pub message_limits: MessageLimitsConfig,  // 消息写入Kafka前的校验配置
+    #[serde(default)]
+    pub region: RegionConfig,  // 多区域部署配置
+    #[serde(default)]
+    pub group_member_cache: GroupMemberCacheConfig,  // 消息服务进程内的群成员缓存配置
+    #[serde(default)]
+    pub grpc_server: GrpcServerConfig,  // 业务gRPC服务的连接管理和下线配置
+    #[serde(default)]
+    pub runtime: RuntimeConfig,  // 各服务的异步运行时参数
+    #[serde(default)]
+    pub usage: UsageConfig,  // 计费用的消息用量统计配置
+    #[serde(default)]
+    pub message_requests: MessageRequestConfig,  // 陌生人消息请求配置
+    #[serde(default)]
+    pub schema_migrations: SchemaMigrationConfig,  // 表结构迁移的双写/双读开关
+    #[serde(default)]
+    pub drafts: DraftConfig,  // 会话草稿多端同步配置
+    #[serde(default)]
+    pub transactions: TransactionConfig,  // 红包、礼物等事务消息配置
+    #[serde(default)]
+    pub access_logs: AccessLogConfig,  // 用户级API访问日志配置
+    #[serde(default)]
+    pub data_residency: DataResidencyConfig,  // 按租户路由数据库的数据驻留配置
+    #[serde(default)]
+    pub degradation: DegradationConfig,  // Redis不可用时的降级配置
+    #[serde(default)]
+    pub client_version: ClientVersionConfig,  // 客户端最低版本和强制升级配置
+    #[serde(default)]
+    pub profile: ProfileConfig,  // 公开资料查看频率限制
+    #[serde(default)]
+    pub ops_metrics: OpsMetricsConfig,  // 运维看板实时指标推送配置
+    #[serde(default)]
+    pub consumer_alert: ConsumerAlertConfig,  // 消息消费错误率告警
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,  // 数据库迁移期间的只读维护模式
+    #[serde(default)]
+    pub email_digest: EmailDigestConfig,  // 离线用户的未读摘要邮件
+    #[serde(default)]
+    pub content_policy: ContentPolicyConfig,  // 好友请求、群名称和群公告的内容策略
+    #[serde(default)]
+    pub receipts: ReceiptConfig,  // 批量已读确认和已读回执转发配置
+    #[serde(default)]
+    pub storage_quota: StorageQuotaConfig,  // 用户附件存储配额
+    #[serde(default)]
+    pub conversation_seq: ConversationSeqConfig,  // 按会话分配的消息序号
+}
+
+/// 只读维护模式配置
+///
+/// 处于维护模式的服务拒绝写操作，读操作照常处理，用于数据库迁移期间；
+/// 除配置外，管理员也可以通过网关的管理接口临时开启，开关保存在Redis中，各服务定期刷新
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// 处于只读维护模式的服务名称，如 user-service
+    pub read_only_services: Vec<String>,
+    /// 建议客户端重试的间隔（秒），网关写入Retry-After响应头
+    pub retry_after_secs: u64,
+    /// 各服务从Redis刷新维护开关的间隔（秒）
+    pub refresh_secs: u64,
+    /// 可以通过管理接口开关维护模式的用户ID
+    pub admin_user_ids: Vec<String>,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            read_only_services: Vec::new(),
+            retry_after_secs: 60,
+            refresh_secs: 5,
+            admin_user_ids: Vec::new(),
+        }
+    }
+}
+
+/// 离线摘要邮件配置
+///
+/// 用户离线超过阈值后，定时任务按用户选择的频率（每小时/每天，默认不发送，需用户主动开启）发送未读私聊消息和待处理好友请求的摘要邮件，
+/// 邮件中附带退订链接，令牌使用 `unsubscribe_secret` 签名
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct EmailDigestConfig {
+    /// 是否启用
+    pub enabled: bool,
+    /// 用户离线超过该时长（小时）才发送摘要
+    pub offline_threshold_hours: i64,
+    /// 检查待发送摘要的间隔（秒）
+    pub scan_interval_secs: u64,
+    /// 每次查询的用户数
+    pub batch_size: i64,
+    /// 退订令牌的签名密钥，为空时不发送摘要
+    pub unsubscribe_secret: String,
+    /// 退订链接地址，令牌以 `token` 查询参数附加在后面
+    pub unsubscribe_url: String,
+    /// 邮件发送服务，未配置时只记录日志
+    pub email: Option<EmailConfig>,
+}
+
+impl Default for EmailDigestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            offline_threshold_hours: 24,
+            scan_interval_secs: 300,
+            batch_size: 200,
+            unsubscribe_secret: String::new(),
+            unsubscribe_url: String::new(),
+            email: None,
+        }
+    }
+}
+
+/// 已读回执配置
+///
+/// 客户端以批量确认帧一次上报多条消息已读，网关转换为一条已读消息，消息服务一次更新接收盒和已读位置；
+/// 单聊的已读回执按发送者和读者合并，每个间隔只向发送者转发一次已读到的最大发送序号
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReceiptConfig {
+    /// 单个批量确认帧最多包含的序号数
+    pub max_ack_seqs: usize,
+    /// 是否向消息发送者转发单聊的已读回执
+    pub forward_to_sender: bool,
+    /// 合并转发已读回执的间隔（毫秒）
+    pub flush_interval_ms: u64,
+}
+
+impl Default for ReceiptConfig {
+    fn default() -> Self {
+        Self {
+            max_ack_seqs: 500,
+            forward_to_sender: true,
+            flush_interval_ms: 1000,
+        }
+    }
+}
+
+/// 用户附件存储配额配置
+///
+/// 客户端申请签名上传地址时按文件大小预占配额，超过配额时拒绝；消息撤回或过期清理后释放
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StorageQuotaConfig {
+    /// 是否限制存储配额，关闭时只统计用量；
+    /// 开启前需要先补齐已有附件的用量，否则历史附件不计入配额
+    pub enabled: bool,
+    /// 每个用户的默认配额（字节）
+    pub default_quota_bytes: i64,
+    /// 按租户ID覆盖的配额（字节），0表示不限制
+    pub tenant_quota_bytes: std::collections::HashMap<String, i64>,
+    /// 签名上传地址的有效期（秒）
+    pub upload_url_ttl_secs: u64,
+    /// 上传地址过期后等待客户端确认上传完成的时间（秒），超时未确认的预占被释放
+    pub commit_grace_secs: u64,
+    /// 附件对象键的前缀
+    pub key_prefix: String,
+}
+
+impl StorageQuotaConfig {
+    /// 租户生效的配额，未限制时返回0
+    pub fn quota_for(&self, tenant_id: Option<&str>) -> i64 {
+        if !self.enabled {
+            return 0;
+        }
+        tenant_id
+            .and_then(|tenant_id| self.tenant_quota_bytes.get(tenant_id))
+            .copied()
+            .unwrap_or(self.default_quota_bytes)
+            .max(0)
+    }
+
+    /// 预占的有效期（秒）：上传地址有效期加上确认等待时间
+    pub fn reservation_ttl_secs(&self) -> i64 {
+        (self.upload_url_ttl_secs.max(1) + self.commit_grace_secs) as i64
+    }
+}
+
+impl Default for StorageQuotaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            default_quota_bytes: 1024 * 1024 * 1024,
+            tenant_quota_bytes: std::collections::HashMap::new(),
+            upload_url_ttl_secs: 900,
+            commit_grace_secs: 600,
+            key_prefix: "chat".to_string(),
+        }
+    }
+}
+
+/// 会话序号配置
+///
+/// 启用后消息服务在用户接收序号之外，为每条单聊、群聊消息按会话再分配一个连续的序号（conv_seq），
+/// 客户端据此按会话检测和补齐缺失的消息。未启用或未能分配时 conv_seq 为0，客户端应回退到按接收序号同步
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConversationSeqConfig {
+    /// 是否分配会话序号
+    pub enabled: bool,
+}
+
+/// 用户输入文本的内容策略配置
+///
+/// default 为全部租户的默认策略，tenants 中按租户ID覆盖单个字段的策略，
+/// 未覆盖的字段使用默认策略
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ContentPolicyConfig {
+    pub default: ContentPolicies,
+    pub tenants: std::collections::HashMap<String, TenantContentPolicies>,
+}
+
+impl ContentPolicyConfig {
+    /// 指定租户生效的内容策略
+    pub fn for_tenant(&self, tenant_id: Option<&str>) -> ContentPolicies {
+        let Some(policies) = tenant_id.and_then(|tenant_id| self.tenants.get(tenant_id)) else {
+            return self.default.clone();
+        };
+        ContentPolicies {
+            friend_request: policies
+                .friend_request
+                .clone()
+                .unwrap_or_else(|| self.default.friend_request.clone()),
+            group_name: policies
+                .group_name
+                .clone()
+                .unwrap_or_else(|| self.default.group_name.clone()),
+            group_announcement: policies
+                .group_announcement
+                .clone()
+                .unwrap_or_else(|| self.default.group_announcement.clone()),
+        }
+    }
+}
+
+/// 各字段的内容策略
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ContentPolicies {
+    /// 好友请求附言
+    pub friend_request: TextPolicy,
+    /// 群名称
+    pub group_name: TextPolicy,
+    /// 群公告
+    pub group_announcement: TextPolicy,
+}
+
+impl Default for ContentPolicies {
+    fn default() -> Self {
+        Self {
+            friend_request: TextPolicy {
+                max_chars: 255,
+                ..Default::default()
+            },
+            group_name: TextPolicy {
+                min_chars: 1,
+                max_chars: 100,
+                ..Default::default()
+            },
+            group_announcement: TextPolicy {
+                max_chars: 2000,
+                allow_newlines: true,
+                mask_sensitive_words: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// 租户覆盖的内容策略，未配置的字段使用默认策略
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TenantContentPolicies {
+    pub friend_request: Option<TextPolicy>,
+    pub group_name: Option<TextPolicy>,
+    pub group_announcement: Option<TextPolicy>,
+}
+
+/// 单个文本字段的内容策略
+///
+/// 长度按字符数计算，首尾空白去除后再校验；控制字符始终不允许，换行符由 allow_newlines 控制
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TextPolicy {
+    /// 最少字符数
+    pub min_chars: usize,
+    /// 最多字符数
+    pub max_chars: usize,
+    /// 是否允许换行
+    pub allow_newlines: bool,
+    /// 不允许出现的字符
+    pub disallowed_chars: String,
+    /// 敏感词，不区分大小写
+    pub sensitive_words: Vec<String>,
+    /// 包含敏感词时替换为*号，关闭时直接拒绝
+    pub mask_sensitive_words: bool,
+}
+
+impl Default for TextPolicy {
+    fn default() -> Self {
+        Self {
+            min_chars: 0,
+            max_chars: 255,
+            allow_newlines: false,
+            disallowed_chars: String::new(),
+            sensitive_words: Vec::new(),
+            mask_sensitive_words: false,
+        }
+    }
+}
+
+/// 邮件发送服务配置（邮件网关HTTP接口）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EmailConfig {
+    pub endpoint: String,
+    pub timeout_ms: u64,
+    /// 发件人地址
+    pub from: String,
+}
+
+/// 计费用的消息用量统计配置
+///
+/// 消息服务按小时、租户和发送者累加聊天消息的条数和字节数，定期写入Redis；
+/// 用户服务在每小时结束后汇总到数据库，供导出给计费系统
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UsageConfig {
+    pub enabled: bool,
+    /// 消息服务将内存中的用量写入Redis的间隔（秒）
+    pub flush_interval_secs: u64,
+    /// 小时结束后延迟汇总的时间（秒），应大于写入间隔
+    pub rollup_delay_secs: u64,
+    /// 单次导出的最大时间跨度（小时）
+    pub max_export_hours: i64,
+}
+
+impl Default for UsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            flush_interval_secs: 10,
+            rollup_delay_secs: 300,
+            max_export_hours: 24 * 31,
+        }
+    }
+}
+
+/// 运维看板实时指标配置
+///
+/// 消息服务定期把消费速率和Kafka消费积压写入Redis，网关按同样的间隔汇总在线人数后推送给运维看板
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OpsMetricsConfig {
+    pub enabled: bool,
+    /// 采样间隔（秒）
+    pub interval_secs: u64,
+    /// 可以订阅指标推送的用户ID
+    pub admin_user_ids: Vec<String>,
+}
+
+impl Default for OpsMetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 5,
+            admin_user_ids: Vec::new(),
+        }
+    }
+}
+
+/// 消息消费错误率告警配置
+///
+/// 消息服务按统计窗口计算解析、存储和推送三类错误的错误率，连续多个窗口超过阈值时
+/// 向Webhook（兼容Slack的 `{"text": ...}` 格式）发送告警，恢复后发送恢复通知
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ConsumerAlertConfig {
+    pub enabled: bool,
+    /// 告警Webhook地址
+    pub webhook_url: String,
+    /// 调用Webhook的超时时间（毫秒）
+    pub timeout_ms: u64,
+    /// 统计窗口（秒）
+    pub window_secs: u64,
+    /// 错误率阈值，0到1之间
+    pub error_rate_threshold: f64,
+    /// 窗口内错误数少于该值时不计为超过阈值，避免低流量时个别错误触发告警
+    pub min_errors: u64,
+    /// 连续超过阈值多少个窗口后告警
+    pub trigger_windows: u32,
+    /// 连续低于阈值多少个窗口后发送恢复通知
+    pub recovery_windows: u32,
+    /// 告警持续期间重复提醒的间隔（秒），期间不重复发送
+    pub repeat_interval_secs: u64,
+}
+
+impl Default for ConsumerAlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            webhook_url: String::new(),
+            timeout_ms: 3000,
+            window_secs: 60,
+            error_rate_threshold: 0.05,
+            min_errors: 10,
+            trigger_windows: 3,
+            recovery_windows: 2,
+            repeat_interval_secs: 3600,
+        }
+    }
+}
+
+/// 陌生人消息请求配置
+///
+/// 启用后，发送者与接收者既不是好友也没有共同群组时，单聊消息进入接收者的消息请求，
+/// 接收者接受后才投递到收件箱
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MessageRequestConfig {
+    pub enabled: bool,
+    /// 联系人关系在消息服务进程内的缓存时间（秒）
+    pub contact_cache_secs: u64,
+    /// 每个发送者的消息请求最多暂存的消息数，超出后丢弃新消息
+    pub max_messages_per_sender: u64,
+    /// 查询消息请求列表的默认条数
+    pub list_limit: i64,
+}
+
+impl Default for MessageRequestConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            contact_cache_secs: 300,
+            max_messages_per_sender: 50,
+            list_limit: 50,
+        }
+    }
+}
+
+/// 会话草稿多端同步配置
+///
+/// 草稿保存在Redis中并推送给用户的其他在线设备，消息服务定期将有变更的草稿持久化，
+/// Redis中的草稿过期后从持久化的副本恢复
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DraftConfig {
+    /// Redis中草稿的过期时间（秒），每次编辑后重新计时
+    pub ttl_secs: i64,
+    /// 持久化有变更草稿的间隔（秒）
+    pub flush_interval_secs: u64,
+    /// 草稿文本的最大字节数
+    pub max_text_bytes: usize,
+}
+
+impl Default for DraftConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 7 * 24 * 3600,
+            flush_interval_secs: 30,
+            max_text_bytes: 16384,
+        }
+    }
+}
+
+/// 事务消息配置
+///
+/// 红包、礼物等消息投递前由外部交易服务确认，领取和到期退还同样回调外部交易服务，
+/// IM服务只记录份数和领取记录，不处理金额。未配置外部交易服务时拒绝事务消息
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TransactionConfig {
+    /// 外部交易服务，按名称配置，客户端发送时指定
+    pub providers: std::collections::HashMap<String, TransactionProviderConfig>,
+    /// 单条消息最多可领取的份数
+    pub max_shares: u32,
+    /// 客户端未指定时的有效期（秒）
+    pub default_expire_secs: i64,
+    /// 最长有效期（秒）
+    pub max_expire_secs: i64,
+    /// 检查到期事务的间隔（秒）
+    pub refund_interval_secs: u64,
+    /// 到期后等待进行中的领取完成再退还的时间（秒）
+    pub refund_grace_secs: i64,
+}
+
+impl Default for TransactionConfig {
+    fn default() -> Self {
+        Self {
+            providers: Default::default(),
+            max_shares: 100,
+            default_expire_secs: 24 * 3600,
+            max_expire_secs: 24 * 3600,
+            refund_interval_secs: 60,
+            refund_grace_secs: 60,
+        }
+    }
+}
+
+/// 外部交易服务配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransactionProviderConfig {
+    /// 回调地址前缀，依次调用 {endpoint}/authorize、/claim、/refund
+    pub endpoint: String,
+    pub timeout_ms: u64,
+}
+
+/// 用户级API访问日志配置
+///
+/// 网关异步批量写入已认证请求的访问日志，用户服务按天分区保存，超过保留天数的分区整体删除
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AccessLogConfig {
+    /// 保留天数
+    pub retention_days: i64,
+    /// 提前创建的分区天数
+    pub precreate_days: i64,
+    /// 单次查询的最大时间跨度（天）
+    pub max_query_days: i64,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: 30,
+            precreate_days: 3,
+            max_query_days: 7,
+        }
+    }
+}
+
+/// Redis降级配置
+///
+/// 后台探测Redis，连续失败达到阈值后进入降级模式：序号改由Postgres分配，群成员改为查询群组服务，
+/// 在线状态相关功能暂停；连续成功达到阈值后先把降级期间分配的序号同步回Redis，再退出降级模式
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DegradationConfig {
+    /// 是否启用降级，关闭时Redis不可用的请求直接失败；启用前需要先执行降级序号的DDL
+    pub enabled: bool,
+    /// 探测间隔（毫秒）
+    pub probe_interval_ms: u64,
+    /// 单次探测的超时时间（毫秒）
+    pub probe_timeout_ms: u64,
+    /// 连续失败多少次后进入降级模式
+    pub failure_threshold: u32,
+    /// 连续成功多少次后恢复
+    pub recovery_threshold: u32,
+}
+
+impl Default for DegradationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            probe_interval_ms: 1000,
+            probe_timeout_ms: 500,
+            failure_threshold: 3,
+            recovery_threshold: 5,
+        }
+    }
+}
+
+/// 客户端版本配置
+///
+/// 客户端通过请求头（WebSocket也可以用查询参数）携带版本，低于所在平台最低版本的请求
+/// 被要求强制升级：HTTP返回426，WebSocket以升级关闭码断开
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClientVersionConfig {
+    /// 是否启用强制升级，关闭时只统计版本分布
+    pub enabled: bool,
+    /// 客户端版本请求头
+    pub version_header: String,
+    /// 客户端平台请求头，WebSocket连接使用路径中的平台
+    pub platform_header: String,
+    /// 各平台的最低版本，键为平台名称（小写），未配置的平台不限制
+    pub min_versions: std::collections::HashMap<String, String>,
+    /// 未携带版本或版本无法解析的客户端是否按需要升级处理
+    pub reject_unknown: bool,
+    /// 升级地址，在升级响应中返回
+    pub upgrade_url: Option<String>,
+    /// HTTP请求中不检查版本的路径前缀，如健康检查、指标和对时接口
+    pub exempt_paths: Vec<String>,
+}
+
+impl Default for ClientVersionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            version_header: "X-Client-Version".to_string(),
+            platform_header: "X-Client-Platform".to_string(),
+            min_versions: std::collections::HashMap::new(),
+            reject_unknown: false,
+            upgrade_url: None,
+            exempt_paths: vec![
+                "/health".to_string(),
+                "/metrics".to_string(),
+                "/api/time".to_string(),
+                "/swagger-ui".to_string(),
+                "/api-doc".to_string(),
+            ],
+        }
+    }
+}
+
+/// 数据驻留配置
+///
+/// 指定租户的Postgres、MongoDB数据存放到指定的数据库集群，其余租户使用 `database` 中的默认集群。
+/// 租户到集群的映射来自本配置和默认Postgres中的控制表 tenant_db_route，控制表优先；
+/// 调整已有数据的租户映射前需要先迁移数据
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DataResidencyConfig {
+    /// 数据库集群，键为集群名称
+    pub targets: std::collections::HashMap<String, DbTargetConfig>,
+    /// 租户ID到集群名称的映射
+    pub tenants: std::collections::HashMap<String, String>,
+    /// 是否从控制表加载映射
+    pub control_table: bool,
+    /// 控制表刷新间隔（秒）
+    pub refresh_secs: u64,
+}
+
+impl Default for DataResidencyConfig {
+    fn default() -> Self {
+        Self {
+            targets: std::collections::HashMap::new(),
+            tenants: std::collections::HashMap::new(),
+            control_table: false,
+            refresh_secs: 60,
+        }
+    }
+}
+
+/// 数据库集群的连接配置，未配置的数据库使用默认集群
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DbTargetConfig {
+    /// Postgres连接地址
+    pub postgres_url: Option<String>,
+    /// Postgres连接池大小
+    pub max_connections: u32,
+    /// MongoDB连接地址
+    pub mongodb_url: Option<String>,
+    /// MongoDB数据库名，未配置时与默认集群相同
+    pub mongodb_database: Option<String>,
+}
+
+impl Default for DbTargetConfig {
+    fn default() -> Self {
+        Self {
+            postgres_url: None,
+            max_connections: 10,
+            mongodb_url: None,
+            mongodb_database: None,
+        }
+    }
+}
+
+/// 表结构迁移配置
+///
+/// 按迁移名称（如 users_uuid）配置各阶段开关，未配置的迁移全部关闭，只读写旧结构。
+/// 典型的上线顺序：执行DDL → 开启 dual_write 并回填 → 开启 verify 观察 → 开启 read_new，
+/// 回退时按相反顺序关闭，旧结构始终保持写入，随时可以切回
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SchemaMigrationConfig {
+    pub migrations: std::collections::HashMap<String, MigrationToggle>,
+}
+
+impl SchemaMigrationConfig {
+    /// 指定迁移的开关
+    pub fn toggle(&self, name: &str) -> MigrationToggle {
+        self.migrations.get(name).cloned().unwrap_or_default()
+    }
+}
+
+/// 单个表结构迁移的阶段开关
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MigrationToggle {
+    /// 写入旧结构后同步写入新结构
+    pub dual_write: bool,
+    /// 读取新结构，关闭时读取旧结构
+    pub read_new: bool,
+    /// 同时读取新旧结构并比较，不一致时记录日志，返回 read_new 选择的结果
+    pub verify: bool,
+}
+
+/// 各服务的异步运行时参数
+///
+/// services 中按服务名（如 msg-server、msg-gateway）覆盖 default 中的参数，
+/// 未配置的参数使用tokio的默认值
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    pub default: RuntimeTuning,
+    pub services: std::collections::HashMap<String, RuntimeTuning>,
+}
+
+impl RuntimeConfig {
+    /// 指定服务生效的运行时参数
+    pub fn for_service(&self, service: &str) -> RuntimeTuning {
+        let Some(tuning) = self.services.get(service) else {
+            return self.default.clone();
+        };
+        RuntimeTuning {
+            worker_threads: tuning.worker_threads.or(self.default.worker_threads),
+            max_blocking_threads: tuning
+                .max_blocking_threads
+                .or(self.default.max_blocking_threads),
+            max_push_tasks: tuning.max_push_tasks.or(self.default.max_push_tasks),
+        }
+    }
+}
+
+/// 单个服务的运行时参数
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RuntimeTuning {
+    /// 工作线程数，默认为CPU核数
+    pub worker_threads: Option<usize>,
+    /// 阻塞任务线程池的最大线程数，默认512
+    pub max_blocking_threads: Option<usize>,
+    /// 同时进行的消息推送任务数上限，只对消息服务生效，默认不限制
+    pub max_push_tasks: Option<usize>,
+}
+
+/// 业务gRPC服务的连接管理和下线配置
+///
+/// 连接达到最长存活时间后服务端发送GOAWAY，客户端在新连接上重新均衡到各实例；
+/// 下线时先从注册中心注销，等待客户端刷新实例列表后再对全部连接发送GOAWAY，
+/// 处理中的请求在超时前完成，被拒绝的请求由客户端重试到其他实例
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GrpcServerConfig {
+    /// 连接的最长存活时间（秒），0表示不限制
+    pub max_connection_age_secs: u64,
+    /// 连接到期发送GOAWAY后等待处理中请求完成的时间（秒）
+    pub max_connection_age_grace_secs: u64,
+    /// 注销服务后继续接受请求的时间（秒），应不小于客户端刷新实例列表的间隔
+    pub drain_delay_secs: u64,
+    /// 发送GOAWAY后等待全部连接关闭的最长时间（秒）
+    pub drain_timeout_secs: u64,
+    /// HTTP/2保活PING的间隔（秒），0表示不发送
+    pub keepalive_interval_secs: u64,
+    /// 保活PING的超时时间（秒）
+    pub keepalive_timeout_secs: u64,
+}
+
+impl Default for GrpcServerConfig {
+    fn default() -> Self {
+        Self {
+            max_connection_age_secs: 300,
+            max_connection_age_grace_secs: 30,
+            drain_delay_secs: 5,
+            drain_timeout_secs: 30,
+            keepalive_interval_secs: 30,
+            keepalive_timeout_secs: 10,
+        }
+    }
+}
+
+/// 多区域部署配置
+///
+/// 每个用户按ID哈希归属到一个区域，归属区域负责分配该用户的消息序号、保存消息盒子和推送，
+/// 各区域之间通过复制消息主题保持一致
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegionConfig {
+    pub enabled: bool,
+    /// 本部署所在的区域，需与环境变量 SERVICE_REGION 一致
+    pub name: String,
+    /// 全部区域，调整列表会改变用户的归属区域，只能追加并在迁移后生效
+    pub regions: Vec<String>,
+    /// 需要从中复制消息的其他区域
+    pub peers: Vec<RegionPeerConfig>,
+    /// 跨区域复制的消费组
+    pub replication_group: String,
+}
+
+impl RegionConfig {
+    /// 启用多区域部署时返回本区域名称
+    pub fn local(&self) -> Option<&str> {
+        self.enabled.then_some(self.name.as_str())
+    }
+}
+
+impl Default for RegionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            name: "default".to_string(),
+            regions: Vec::new(),
+            peers: Vec::new(),
+            replication_group: "chat-replication".to_string(),
+        }
+    }
+}
+
+/// 对端区域的Kafka地址和消息主题
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RegionPeerConfig {
+    pub name: String,
+    pub hosts: Vec<String>,
+    pub topic: String,
+}
+
+/// 消息写入Kafka前的校验配置
+///
+/// 生产端拒绝格式错误或超限的消息，避免异常消息阻塞消费端
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MessageLimitsConfig {
+    pub enabled: bool,
+    /// 文本消息内容的最大字节数
+    pub max_text_bytes: usize,
+    /// 附件消息中附件描述的最大字节数
+    pub max_attachment_bytes: usize,
+    /// 其他消息（信令、系统通知等）内容的最大字节数
+    pub max_content_bytes: usize,
+    /// 发送者和接收者ID的最大长度
+    pub max_id_len: usize,
+    /// 是否校验单聊接收者存在、群聊发送者在群内
+    pub check_receiver: bool,
+    /// 已确认存在的接收者的缓存时间（秒）
+    pub receiver_cache_secs: u64,
+    /// 批量发送单次最多的消息数
+    pub max_batch_messages: usize,
+    /// 批量发送时同时校验的消息数，校验时会查询用户服务和缓存
+    pub batch_concurrency: usize,
+    /// 消息扩展字段的最大条数
+    pub max_extension_entries: usize,
+    /// 扩展字段键的最大长度
+    pub max_extension_key_len: usize,
+    /// 扩展字段全部键和值的最大字节数
+    pub max_extensions_bytes: usize,
+}
+
+impl Default for MessageLimitsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_text_bytes: 16 * 1024,
+            max_attachment_bytes: 8 * 1024,
+            max_content_bytes: 64 * 1024,
+            max_id_len: 64,
+            check_receiver: true,
+            receiver_cache_secs: 300,
+            max_batch_messages: 500,
+            batch_concurrency: 16,
+            max_extension_entries: 16,
+            max_extension_key_len: 64,
+            max_extensions_bytes: 4 * 1024,
+        }
+    }
+}
+
+/// 消息服务进程内的群成员缓存配置
+///
+/// 热点群每条消息都要从Redis读取全部成员，进程内缓存在有效期内直接使用，
+/// 超过有效期后只比较Redis中的成员版本号，版本未变化时继续使用
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GroupMemberCacheConfig {
+    pub enabled: bool,
+    /// 最多缓存的群数
+    pub max_groups: u64,
+    /// 不检查版本号直接使用的时间（毫秒）
+    pub fresh_ms: u64,
+    /// 缓存条目的最长保留时间（秒）
+    pub ttl_secs: u64,
+}
+
+impl Default for GroupMemberCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_groups: 10_000,
+            fresh_ms: 1_000,
+            ttl_secs: 300,
+        }
+    }
+}
+
+/// 信令消息投递期限配置
+///
+/// 通话信令和正在输入提示过期后没有意义，超过期限的消息在消费和推送时直接丢弃
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SignalingConfig {
+    pub enabled: bool,
+    /// 通话邀请、应答、SDP和候选地址的有效期（毫秒）
+    pub call_ttl_ms: i64,
+    /// 正在输入提示的有效期（毫秒）
+    pub typing_ttl_ms: i64,
+}
+
+impl Default for SignalingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            call_ttl_ms: 30_000,
+            typing_ttl_ms: 5_000,
+        }
+    }
+}
+
+/// 手机号邀请配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct InviteConfig {
+    /// 邀请的有效天数，过期后对方注册也不再投递
+    pub expire_days: i64,
+    /// 每个用户24小时内最多发出的邀请数，防止滥发短信
+    pub max_per_sender_daily: i64,
+    /// 邀请消息内容的最大长度
+    pub max_content_len: usize,
+    /// 短信中附带的下载地址
+    pub download_url: String,
+    /// 短信发送服务，未配置时只记录日志
+    pub sms: Option<SmsConfig>,
+}
+
+impl Default for InviteConfig {
+    fn default() -> Self {
+        Self {
+            expire_days: 30,
+            max_per_sender_daily: 20,
+            max_content_len: 500,
+            download_url: String::new(),
+            sms: None,
+        }
+    }
+}
+
+/// 公开资料配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// 每个用户在一个窗口内最多查看的不同用户资料数，防止批量抓取，0表示不限制
+    pub max_viewed_per_window: i64,
+    /// 统计窗口（秒）
+    pub window_secs: i64,
+}
+
+impl Default for ProfileConfig {
+    fn default() -> Self {
+        Self {
+            max_viewed_per_window: 200,
+            window_secs: 3600,
+        }
+    }
+}
+
+/// 短信发送服务配置（短信网关HTTP接口）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SmsConfig {
+    pub endpoint: String,
+    pub timeout_ms: u64,
+    /// 邀请短信的模板ID
+    pub invite_template: String,
+    /// 登录二次验证码短信的模板ID，模板参数为 `code`
+    #[serde(default = "default_login_sms_template")]
+    pub login_template: String,
+}
+
+fn default_login_sms_template() -> String {
+    "IM_LOGIN_CODE".to_string()
+}
+
+/// 新设备历史消息回填配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BackfillConfig {
+    /// 每批默认消息条数
+    pub chunk_size: i64,
+    /// 客户端可请求的每批最大消息条数
+    pub max_chunk_size: i64,
+    /// 两批之间的最小间隔（毫秒），用于限制对MongoDB的压力
+    pub chunk_interval_ms: u64,
+    /// 单个服务实例同时进行的回填数量上限
+    pub max_concurrent: usize,
+    /// 最多回填的会话数，按最近活跃排序
+    pub max_conversations: i64,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 100,
+            max_chunk_size: 500,
+            chunk_interval_ms: 200,
+            max_concurrent: 32,
+            max_conversations: 500,
+        }
+    }
+}
+
+/// 接收盒重建配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ReceiveBoxRebuildConfig {
+    /// 每批从Postgres读取并写入接收盒的消息条数
+    pub batch_size: i64,
+    /// 两批之间的最小间隔（毫秒），用于限制对Postgres和MongoDB的压力
+    pub batch_interval_ms: u64,
+    /// 单个服务实例同时进行的重建数量上限
+    pub max_concurrent: usize,
+    /// 可以通过网关发起重建的用户ID，内部服务直接调用不受限制
+    pub admin_user_ids: Vec<String>,
+}
+
+impl Default for ReceiveBoxRebuildConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            batch_interval_ms: 200,
+            max_concurrent: 2,
+            admin_user_ids: Vec::new(),
+        }
+    }
+}
+
+/// 消息附件校验配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AttachmentConfig {
+    /// 图片大小上限（字节）
+    pub max_image_size: u64,
+    /// 视频大小上限（字节）
+    pub max_video_size: u64,
+    /// 语音大小上限（字节）
+    pub max_audio_size: u64,
+    /// 普通文件大小上限（字节）
+    pub max_file_size: u64,
+    /// 图片/视频单边最大像素
+    pub max_dimension: u32,
+    /// 允许的MIME类型，支持 image/* 形式的通配
+    pub allowed_mime_types: Vec<String>,
+    /// 病毒扫描服务，未配置时不扫描
+    pub scanner: Option<ScannerConfig>,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_image_size: 20 * 1024 * 1024,
+            max_video_size: 200 * 1024 * 1024,
+            max_audio_size: 20 * 1024 * 1024,
+            max_file_size: 100 * 1024 * 1024,
+            max_dimension: 10000,
+            allowed_mime_types: vec![
+                "image/*".to_string(),
+                "video/*".to_string(),
+                "audio/*".to_string(),
+                "application/pdf".to_string(),
+                "application/zip".to_string(),
+                "text/plain".to_string(),
+            ],
+            scanner: None,
+        }
+    }
+}
+
+/// 附件病毒扫描服务配置（ClamAV REST 网关或第三方扫描API）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScannerConfig {
+    pub endpoint: String,
+    pub timeout_ms: u64,
+}
+
+/// 离线推送配置
+///
+/// 接收者不在线时按模板生成推送文案，通过HTTP推送网关（APNs/FCM等厂商通道的转发服务）发送，
+/// 未配置 `endpoint` 时不发送离线推送
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PushConfig {
+    /// 推送网关地址，为空时不发送离线推送
+    pub endpoint: Option<String>,
+    /// 调用推送网关的超时时间（毫秒），为0时使用5秒
+    #[serde(default)]
+    pub timeout_ms: u64,
+    /// 接收者未设置语言时使用的默认语言，如 zh-CN
+    pub default_locale: Option<String>,
+    /// 租户自定义推送模板：租户ID -> 语言 -> 模板键 -> 模板
+    #[serde(default)]
+    pub tenant_templates: std::collections::HashMap<
+        String,
+        std::collections::HashMap<String, std::collections::HashMap<String, String>>,
+    >,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -541,4 +1938,33 @@ mod tests {
         assert_eq!(config.database.postgres.user, "kelisi");
         assert_eq!(config.database.postgres.password, "123456");
     }
+
+    #[test]
+    fn test_runtime_for_service() {
+        let mut config = RuntimeConfig {
+            default: RuntimeTuning {
+                worker_threads: Some(4),
+                max_blocking_threads: Some(64),
+                max_push_tasks: None,
+            },
+            ..Default::default()
+        };
+        config.services.insert(
+            "msg-server".to_string(),
+            RuntimeTuning {
+                worker_threads: Some(16),
+                max_push_tasks: Some(2000),
+                ..Default::default()
+            },
+        );
+
+        let tuning = config.for_service("msg-server");
+        assert_eq!(tuning.worker_threads, Some(16));
+        assert_eq!(tuning.max_blocking_threads, Some(64));
+        assert_eq!(tuning.max_push_tasks, Some(2000));
+
+        let tuning = config.for_service("msg-gateway");
+        assert_eq!(tuning.worker_threads, Some(4));
+        assert_eq!(tuning.max_push_tasks, None);
+    }
 }
@@ -0,0 +1,69 @@
+//! 名片消息
+//!
+//! 名片消息的 content 为 JSON 格式的名片，客户端只需要填写名片用户ID。
+//! 消息服务在写入Kafka之前按发送者的权限查询名片用户，写入昵称和头像的快照，
+//! 接收者点击名片时再按自己的权限解析最新资料。
+
+use crate::message::ContentType;
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// 名片
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactCard {
+    /// 名片用户ID
+    pub user_id: String,
+    /// 发送时的昵称快照，由服务端填充
+    #[serde(default)]
+    pub nickname: String,
+    /// 发送时的头像快照，由服务端填充
+    #[serde(default)]
+    pub avatar: String,
+}
+
+impl ContactCard {
+    /// 解析消息内容中的名片
+    ///
+    /// # 返回
+    /// * 非名片消息返回 `Ok(None)`
+    pub fn from_msg_content(content_type: i32, content: &[u8]) -> Result<Option<Self>> {
+        if content_type != ContentType::ContactCard as i32 {
+            return Ok(None);
+        }
+
+        let card: Self = serde_json::from_slice(content)
+            .map_err(|e| Error::BadRequest(format!("名片格式错误: {}", e)))?;
+        if card.user_id.trim().is_empty() {
+            return Err(Error::BadRequest("名片用户ID不能为空".to_string()));
+        }
+        Ok(Some(card))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_msg_content() {
+        let text = ContactCard::from_msg_content(ContentType::Text as i32, b"hello");
+        assert_eq!(text.unwrap(), None);
+
+        let card = ContactCard::from_msg_content(
+            ContentType::ContactCard as i32,
+            br#"{"userId":"u1","nickname":"fake"}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(card.user_id, "u1");
+        assert_eq!(card.avatar, "");
+
+        assert!(ContactCard::from_msg_content(ContentType::ContactCard as i32, b"{}").is_err());
+        assert!(ContactCard::from_msg_content(
+            ContentType::ContactCard as i32,
+            br#"{"userId":" "}"#
+        )
+        .is_err());
+    }
+}
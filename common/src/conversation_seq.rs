@@ -0,0 +1,105 @@
+//! 会话序号
+//!
+//! 接收序号按用户分配，同一会话的消息在接收序号上并不连续，客户端无法按会话判断是否缺消息。
+//! 启用会话序号后，消息服务为每条聊天消息按会话再分配一个连续的序号：单聊两端共用一个序列，
+//! 群聊全体成员共用一个序列。序号由Redis分配，每预留一段序号时把上限记为Postgres中的水位，
+//! Redis数据丢失后从水位之后继续分配，保证不会与已分配的序号重复
+
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+use crate::config::AppConfig;
+use crate::message::Msg;
+use crate::Result;
+
+/// 消息所属会话的序号键，单聊按双方ID排序，群聊为群组ID；无法确定会话时返回None
+pub fn conversation_key(msg: &Msg) -> Option<String> {
+    if !msg.group_id.is_empty() {
+        return Some(format!("group:{}", msg.group_id));
+    }
+    if msg.send_id.is_empty() || msg.receiver_id.is_empty() {
+        return None;
+    }
+    let (first, second) = if msg.send_id <= msg.receiver_id {
+        (&msg.send_id, &msg.receiver_id)
+    } else {
+        (&msg.receiver_id, &msg.send_id)
+    };
+    Some(format!("single:{}:{}", first, second))
+}
+
+/// Postgres中的会话序号水位
+#[derive(Debug, Clone)]
+pub struct ConversationSeqStore {
+    pool: PgPool,
+}
+
+impl ConversationSeqStore {
+    /// 水位存放在默认集群，连接在首次使用时建立
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_lazy(&config.database.url())?;
+        Ok(Self { pool })
+    }
+
+    /// 会话已预留的序号上限，没有记录时返回0
+    pub async fn watermark(&self, conversation: &str) -> Result<i64> {
+        let seq: Option<i64> = sqlx::query_scalar(
+            "SELECT max_seq FROM conversation_seq_watermark WHERE conversation_id = $1",
+        )
+        .bind(conversation)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(seq.unwrap_or(0))
+    }
+
+    /// 记录Redis预留的序号上限，只会提高水位
+    pub async fn record_watermark(&self, conversation: &str, max_seq: i64) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO conversation_seq_watermark (conversation_id, max_seq)
+            VALUES ($1, $2)
+            ON CONFLICT (conversation_id) DO UPDATE
+            SET max_seq = GREATEST(conversation_seq_watermark.max_seq, EXCLUDED.max_seq),
+                updated_at = now()
+            "#,
+        )
+        .bind(conversation)
+        .bind(max_seq)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_key() {
+        let msg = Msg {
+            send_id: "u2".to_string(),
+            receiver_id: "u1".to_string(),
+            ..Default::default()
+        };
+        let reply = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "u2".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(conversation_key(&msg), Some("single:u1:u2".to_string()));
+        assert_eq!(conversation_key(&msg), conversation_key(&reply));
+
+        let group = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "u3".to_string(),
+            group_id: "g1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(conversation_key(&group), Some("group:g1".to_string()));
+
+        assert_eq!(conversation_key(&Msg::default()), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! 依赖健康状态
+//!
+//! 后台任务定期探测外部依赖，按连续失败、连续成功的次数切换健康状态，
+//! 使用方通过 [`DependencyHealth`] 读取当前状态或订阅状态变化，据此进入或退出降级模式
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::DegradationConfig;
+use crate::Result;
+
+/// 依赖探测函数
+pub type Probe = Box<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// 依赖的健康状态，可以克隆后在多个组件间共享
+#[derive(Debug, Clone)]
+pub struct DependencyHealth {
+    name: &'static str,
+    state: watch::Receiver<bool>,
+}
+
+impl DependencyHealth {
+    /// 始终健康的状态，未启用降级时使用
+    pub fn always_healthy(name: &'static str) -> Self {
+        let (_, state) = watch::channel(true);
+        Self { name, state }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        *self.state.borrow()
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        !self.is_healthy()
+    }
+
+    /// 订阅健康状态的变化
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.state.clone()
+    }
+}
+
+/// 按连续探测结果判断健康状态
+#[derive(Debug)]
+struct HealthTracker {
+    healthy: bool,
+    failures: u32,
+    successes: u32,
+    failure_threshold: u32,
+    recovery_threshold: u32,
+}
+
+impl HealthTracker {
+    fn new(config: &DegradationConfig) -> Self {
+        Self {
+            healthy: true,
+            failures: 0,
+            successes: 0,
+            failure_threshold: config.failure_threshold.max(1),
+            recovery_threshold: config.recovery_threshold.max(1),
+        }
+    }
+
+    /// 记录一次探测结果，状态发生变化时返回新的状态
+    fn observe(&mut self, ok: bool) -> Option<bool> {
+        if ok {
+            self.failures = 0;
+            self.successes += 1;
+        } else {
+            self.successes = 0;
+            self.failures += 1;
+        }
+
+        let healthy = if self.healthy {
+            self.failures < self.failure_threshold
+        } else {
+            self.successes >= self.recovery_threshold
+        };
+        if healthy == self.healthy {
+            return None;
+        }
+        self.healthy = healthy;
+        Some(healthy)
+    }
+}
+
+/// 依赖健康探测任务
+pub struct HealthWatcher {
+    name: &'static str,
+    probe: Probe,
+    tracker: HealthTracker,
+    interval: Duration,
+    timeout: Duration,
+    state: watch::Sender<bool>,
+}
+
+impl HealthWatcher {
+    /// 创建探测任务，返回任务和共享的健康状态，初始状态为健康
+    pub fn new(
+        name: &'static str,
+        config: &DegradationConfig,
+        probe: Probe,
+    ) -> (Self, DependencyHealth) {
+        let (state, receiver) = watch::channel(true);
+        let watcher = Self {
+            name,
+            probe,
+            tracker: HealthTracker::new(config),
+            interval: Duration::from_millis(config.probe_interval_ms.max(100)),
+            timeout: Duration::from_millis(config.probe_timeout_ms.max(1)),
+            state,
+        };
+        let health = DependencyHealth {
+            name,
+            state: receiver,
+        };
+        (watcher, health)
+    }
+
+    /// 启动后台探测任务
+    pub fn start(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                let ok = match tokio::time::timeout(self.timeout, (self.probe)()).await {
+                    Ok(Ok(())) => true,
+                    Ok(Err(e)) => {
+                        warn!("探测 {} 失败: {}", self.name, e);
+                        false
+                    }
+                    Err(_) => {
+                        warn!("探测 {} 超时", self.name);
+                        false
+                    }
+                };
+                match self.tracker.observe(ok) {
+                    Some(true) => info!("{} 已恢复，退出降级模式", self.name),
+                    Some(false) => warn!("{} 不可用，进入降级模式", self.name),
+                    None => continue,
+                }
+                self.state.send_replace(self.tracker.healthy);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_tracker_thresholds() {
+        let config = DegradationConfig {
+            failure_threshold: 2,
+            recovery_threshold: 3,
+            ..Default::default()
+        };
+        let mut tracker = HealthTracker::new(&config);
+
+        // 偶发失败不进入降级
+        assert_eq!(tracker.observe(false), None);
+        assert_eq!(tracker.observe(true), None);
+        assert_eq!(tracker.observe(false), None);
+        assert_eq!(tracker.observe(false), Some(false));
+        assert_eq!(tracker.observe(false), None);
+
+        // 恢复需要连续成功
+        assert_eq!(tracker.observe(true), None);
+        assert_eq!(tracker.observe(true), None);
+        assert_eq!(tracker.observe(false), None);
+        assert_eq!(tracker.observe(true), None);
+        assert_eq!(tracker.observe(true), None);
+        assert_eq!(tracker.observe(true), Some(true));
+        assert!(tracker.healthy);
+    }
+}
@@ -38,6 +38,9 @@ pub enum Error {
     #[error("请求无效: {0}")]
     BadRequest(String),
 
+    #[error("请求过于频繁: {0}")]
+    TooManyRequests(String),
+
     #[error("数据库错误: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -93,6 +96,7 @@ impl From<Error> for tonic::Status {
             Error::Authentication(msg) => tonic::Status::unauthenticated(msg),
             Error::Authorization(msg) => tonic::Status::permission_denied(msg),
             Error::BadRequest(msg) => tonic::Status::invalid_argument(msg),
+            Error::TooManyRequests(msg) => tonic::Status::resource_exhausted(msg),
             _ => tonic::Status::internal(error.to_string()),
         }
     }
@@ -116,6 +120,7 @@ impl From<Error> for axum::http::StatusCode {
             Error::Authentication(_) => StatusCode::UNAUTHORIZED,
             Error::Authorization(_) => StatusCode::FORBIDDEN,
             Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -1,10 +1,32 @@
-use std::future::Future;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::task::{Context, Poll};
-use tonic::{Request, Status};
-use tracing::info;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use futures::{FutureExt, Stream};
+use hyper::body::{Bytes, HttpBody, SizeHint};
+use hyper::http::{HeaderMap, Request as HttpRequest, Response as HttpResponse};
+use tonic::body::BoxBody;
+use tonic::{Code, Request, Status};
+use tower::{Layer, Service};
+use tracing::field::Empty;
+use tracing::{debug, info, info_span, warn, Instrument, Span};
+
+use super::service_auth::ServiceClaims;
+
+/// 链路追踪ID所在的元数据键
+pub const TRACE_ID_METADATA_KEY: &str = "x-trace-id";
+
+/// 未能识别的调用方、服务和方法的标签
+const UNKNOWN_LABEL: &str = "unknown";
 
 /// 用于记录gRPC请求的拦截器
+///
+/// 识别调用方服务并写入 [`LoggingLayer`] 创建的请求span和指标标签。
+/// 只认服务间令牌中校验过的签发方，调用方自报的元数据不可信，未认证的调用方记为unknown，
+/// 避免指标标签被任意写入
 #[derive(Debug, Clone, Default)]
 pub struct LoggingInterceptor {}
 
@@ -16,104 +38,383 @@ impl LoggingInterceptor {
 
 impl tonic::service::Interceptor for LoggingInterceptor {
     fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
-        // 获取请求的路径，通过metadata中的:path字段
-        let path = request
-            .metadata()
-            .get(":path")
-            .map(|v| v.to_str().unwrap_or("/unknown"))
-            .unwrap_or("/unknown");
-        
         // 从请求元数据中提取trace_id，如果存在
         let trace_id = request
             .metadata()
-            .get("x-trace-id")
-            .map(|v| v.to_str().unwrap_or("unknown"))
+            .get(TRACE_ID_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
             .unwrap_or("none");
-        
-        // 提取调用方信息
-        let caller = request
-            .metadata()
-            .get("caller")
-            .map(|v| v.to_str().unwrap_or("unknown"))
-            .unwrap_or("unknown");
-        
-        // 记录请求信息
-        info!(path = %path, trace_id = %trace_id, caller = %caller, "收到gRPC请求");
-        
+
+        // 提取校验过的调用方信息
+        let peer = request
+            .extensions()
+            .get::<ServiceClaims>()
+            .map(|claims| claims.iss.as_str())
+            .unwrap_or(UNKNOWN_LABEL);
+
+        Span::current().record("peer", peer);
+        if let Some(slot) = request.extensions().get::<PeerSlot>() {
+            let _ = slot.0.set(peer.to_string());
+        }
+
+        // 记录请求信息，完成时由LoggingLayer记录结果和耗时
+        debug!(trace_id = %trace_id, peer = %peer, "收到gRPC请求");
+
         Ok(request)
     }
 }
 
-/// 创建一个服务层拦截器，用于记录每个gRPC请求
+/// 调用方服务名，由中间件放入请求扩展，拦截器识别调用方后写入
+#[derive(Debug, Clone, Default)]
+struct PeerSlot(Arc<OnceLock<String>>);
+
+impl PeerSlot {
+    fn get(&self) -> String {
+        self.0
+            .get()
+            .cloned()
+            .unwrap_or_else(|| UNKNOWN_LABEL.to_string())
+    }
+}
+
+/// 服务端中间件：为每个gRPC请求创建span，并按方法记录请求数、耗时、状态码和收发字节数
+///
+/// 状态码以响应头中的 `grpc-status` 为准，正常响应的状态码在响应体结束后的trailers中，
+/// 因此指标在响应体发送完毕或被丢弃时才记录。耗时为收到请求到返回响应头的时间，
+/// 流式方法即为建立流的耗时。返回Unimplemented的请求（未注册的服务或方法）的服务和方法
+/// 都记为unknown，避免任意路径撑大指标标签
+///
+/// 记录的指标：
+/// - `im_grpc_requests_total{service, method, code, peer}`
+/// - `im_grpc_request_duration_seconds{service, method, code}`
+/// - `im_grpc_request_bytes{service, method}` / `im_grpc_response_bytes{service, method}`
+#[derive(Debug, Clone, Default)]
 pub struct LoggingLayer;
 
-impl<S> tower::layer::Layer<S> for LoggingLayer
-where
-    S: tower::Service<tonic::Request<()>> + Send + 'static,
-    S::Future: Send + 'static,
-    S::Error: Into<tonic::Status>,
-    S::Response: Send + 'static,
-{
+impl<S> Layer<S> for LoggingLayer {
     type Service = LoggingService<S>;
 
-    fn layer(&self, service: S) -> Self::Service {
-        LoggingService {
-            inner: service,
-        }
+    fn layer(&self, inner: S) -> Self::Service {
+        LoggingService { inner }
     }
 }
 
-/// 包装原始服务的日志服务
+#[derive(Debug, Clone)]
 pub struct LoggingService<S> {
     inner: S,
 }
 
-impl<S> tower::Service<tonic::Request<()>> for LoggingService<S>
+impl<S> LoggingService<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Service<HttpRequest<hyper::Body>> for LoggingService<S>
 where
-    S: tower::Service<tonic::Request<()>> + Send,
+    S: Service<HttpRequest<hyper::Body>, Response = HttpResponse<BoxBody>>,
     S::Future: Send + 'static,
-    S::Error: Into<tonic::Status>,
-    S::Response: Send + 'static,
 {
     type Response = S::Response;
-    type Error = tonic::Status;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx).map_err(Into::into)
+        self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: tonic::Request<()>) -> Self::Future {
-        // 获取请求的路径，通过metadata中的:path字段
-        let path = req
-            .metadata()
-            .get(":path")
-            .map(|v| v.to_str().unwrap_or("/unknown"))
-            .unwrap_or("/unknown")
-            .to_string();
-        
-        let future = self.inner.call(req);
-        
-        // 包装原始future，增加日志功能
-        Box::pin(async move {
-            // 等待原始future完成
-            match future.await.map_err(Into::into) {
-                Ok(response) => {
-                    // 记录成功响应
-                    info!(path = %path, "gRPC请求处理成功");
-                    Ok(response)
-                }
-                Err(status) => {
-                    // 记录错误响应
-                    info!(
-                        path = %path, 
-                        code = %status.code() as i32, 
-                        message = %status.message(), 
-                        "gRPC请求处理失败"
-                    );
-                    Err(status)
-                }
+    fn call(&mut self, request: HttpRequest<hyper::Body>) -> Self::Future {
+        let (service, method) = split_path(request.uri().path());
+        let trace_id = request
+            .headers()
+            .get(TRACE_ID_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("none");
+        let span = info_span!(
+            "grpc_request",
+            service = %service,
+            method = %method,
+            trace_id = %trace_id,
+            peer = Empty,
+            code = Empty,
+        );
+        let call = CallMetrics {
+            service,
+            method,
+            peer: PeerSlot::default(),
+            request_bytes: Arc::new(AtomicUsize::new(0)),
+            started: Instant::now(),
+            span: span.clone(),
+        };
+
+        // 请求体按读取的字节数计数，与响应一起记录
+        let (mut parts, body) = request.into_parts();
+        parts.extensions.insert(call.peer.clone());
+        let body = hyper::Body::wrap_stream(CountedRequest {
+            body,
+            bytes: call.request_bytes.clone(),
+        });
+
+        // 拦截器在内层服务的call中同步执行，需要在span内调用才能写入调用方
+        let future = {
+            let _entered = span.enter();
+            self.inner.call(HttpRequest::from_parts(parts, body))
+        };
+
+        Box::pin(
+            future
+                .map(move |result| {
+                    result.map(|response| {
+                        let elapsed = call.started.elapsed();
+                        let (parts, body) = response.into_parts();
+                        let code = grpc_code(&parts.headers);
+                        let body = BoxBody::new(ObservedBody {
+                            inner: body,
+                            bytes: 0,
+                            code,
+                            elapsed,
+                            call: Some(call),
+                        });
+                        HttpResponse::from_parts(parts, body)
+                    })
+                })
+                .instrument(span),
+        )
+    }
+}
+
+/// 单次调用的指标标签和计时
+#[derive(Debug, Clone)]
+struct CallMetrics {
+    service: String,
+    method: String,
+    peer: PeerSlot,
+    // 已读取的请求体字节数
+    request_bytes: Arc<AtomicUsize>,
+    started: Instant,
+    span: Span,
+}
+
+impl CallMetrics {
+    fn finish(&self, code: Code, elapsed: Duration, response_bytes: usize) {
+        let code_label = format!("{:?}", code);
+        let peer = self.peer.get();
+        self.span.record("code", code_label.as_str());
+
+        let (service, method) = metric_labels(&self.service, &self.method, code);
+        metrics::counter!(
+            "im_grpc_requests_total",
+            "service" => service.clone(),
+            "method" => method.clone(),
+            "code" => code_label.clone(),
+            "peer" => peer.clone()
+        )
+        .increment(1);
+        metrics::histogram!(
+            "im_grpc_request_duration_seconds",
+            "service" => service.clone(),
+            "method" => method.clone(),
+            "code" => code_label.clone()
+        )
+        .record(elapsed.as_secs_f64());
+        metrics::histogram!(
+            "im_grpc_request_bytes",
+            "service" => service.clone(),
+            "method" => method.clone()
+        )
+        .record(self.request_bytes.load(Ordering::Relaxed) as f64);
+        metrics::histogram!(
+            "im_grpc_response_bytes",
+            "service" => service,
+            "method" => method
+        )
+        .record(response_bytes as f64);
+
+        let _entered = self.span.enter();
+        let elapsed_ms = elapsed.as_millis() as u64;
+        match code {
+            Code::Ok => info!(peer = %peer, elapsed_ms, "gRPC请求处理成功"),
+            Code::Unknown | Code::Internal | Code::Unavailable | Code::DataLoss => {
+                warn!(peer = %peer, code = %code_label, elapsed_ms, "gRPC请求处理失败")
             }
-        })
+            _ => info!(peer = %peer, code = %code_label, elapsed_ms, "gRPC请求处理失败"),
+        }
     }
-} 
\ No newline at end of file
+}
+
+/// 计数的请求体
+struct CountedRequest {
+    body: hyper::Body,
+    bytes: Arc<AtomicUsize>,
+}
+
+impl Stream for CountedRequest {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let polled = Pin::new(&mut self.body).poll_data(cx);
+        if let Poll::Ready(Some(Ok(data))) = &polled {
+            self.bytes.fetch_add(data.len(), Ordering::Relaxed);
+        }
+        polled
+    }
+}
+
+/// 计数的响应体，结束时从trailers中取状态码并记录指标
+struct ObservedBody {
+    inner: BoxBody,
+    bytes: usize,
+    // 响应头中的状态码，正常响应为None，需要等trailers
+    code: Option<Code>,
+    elapsed: Duration,
+    call: Option<CallMetrics>,
+}
+
+impl ObservedBody {
+    fn finish(&mut self, code: Code) {
+        if let Some(call) = self.call.take() {
+            call.finish(code, self.elapsed, self.bytes);
+        }
+    }
+}
+
+impl HttpBody for ObservedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_data(cx);
+        match &polled {
+            Poll::Ready(Some(Ok(data))) => self.bytes += data.len(),
+            Poll::Ready(Some(Err(status))) => {
+                let code = status.code();
+                self.finish(code);
+            }
+            _ => {}
+        }
+        polled
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        let polled = Pin::new(&mut self.inner).poll_trailers(cx);
+        if let Poll::Ready(Ok(trailers)) = &polled {
+            let code = trailers
+                .as_ref()
+                .and_then(grpc_code)
+                .or(self.code)
+                .unwrap_or(Code::Ok);
+            self.finish(code);
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for ObservedBody {
+    fn drop(&mut self) {
+        // 没有读到trailers就结束的响应：仅有响应头的错误响应，或者客户端提前断开
+        let code = self.code.unwrap_or(Code::Cancelled);
+        self.finish(code);
+    }
+}
+
+/// 拆分gRPC请求路径 `/包名.服务名/方法名`，无法识别时服务和方法都记为unknown，避免指标标签无限增长
+fn split_path(path: &str) -> (String, String) {
+    match path.trim_start_matches('/').split_once('/') {
+        Some((service, method))
+            if !service.is_empty() && !method.is_empty() && !method.contains('/') =>
+        {
+            (service.to_string(), method.to_string())
+        }
+        _ => (UNKNOWN_LABEL.to_string(), UNKNOWN_LABEL.to_string()),
+    }
+}
+
+/// 指标使用的服务和方法标签，未注册的服务或方法（返回Unimplemented）记为unknown
+fn metric_labels(service: &str, method: &str, code: Code) -> (String, String) {
+    if code == Code::Unimplemented {
+        (UNKNOWN_LABEL.to_string(), UNKNOWN_LABEL.to_string())
+    } else {
+        (service.to_string(), method.to_string())
+    }
+}
+
+/// 从响应头或trailers中读取gRPC状态码
+fn grpc_code(headers: &HeaderMap) -> Option<Code> {
+    headers
+        .get("grpc-status")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i32>().ok())
+        .map(Code::from_i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::service::Interceptor;
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            split_path("/user.UserService/GetUserById"),
+            ("user.UserService".to_string(), "GetUserById".to_string())
+        );
+        assert_eq!(
+            split_path("/grpc.health.v1.Health/Check"),
+            ("grpc.health.v1.Health".to_string(), "Check".to_string())
+        );
+        assert_eq!(
+            split_path("/"),
+            ("unknown".to_string(), "unknown".to_string())
+        );
+        assert_eq!(
+            split_path("/a/b/c"),
+            ("unknown".to_string(), "unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn test_metric_labels() {
+        assert_eq!(
+            metric_labels("user.UserService", "GetUserById", Code::NotFound),
+            ("user.UserService".to_string(), "GetUserById".to_string())
+        );
+        assert_eq!(
+            metric_labels("user.UserService", "NoSuchMethod", Code::Unimplemented),
+            ("unknown".to_string(), "unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn test_peer_requires_verified_claims() {
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert("caller", "api-gateway".parse().unwrap());
+        let slot = PeerSlot::default();
+        request.extensions_mut().insert(slot.clone());
+        LoggingInterceptor::new().call(request).unwrap();
+        assert_eq!(slot.get(), "unknown");
+    }
+
+    #[test]
+    fn test_grpc_code() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(grpc_code(&headers), None);
+        headers.insert("grpc-status", "5".parse().unwrap());
+        assert_eq!(grpc_code(&headers), Some(Code::NotFound));
+    }
+}
@@ -0,0 +1,303 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use hyper::http::{Request as HttpRequest, Response as HttpResponse};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+use tonic::body::BoxBody;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+use crate::config::{MaintenanceConfig, RedisConfig};
+use crate::Result;
+
+/// 维护开关的Redis键，哈希字段为服务名称
+const MAINTENANCE_KEY: &str = "maintenance:read_only";
+
+/// 标识维护错误的gRPC元数据键，值为建议的重试间隔（秒）
+pub const MAINTENANCE_METADATA_KEY: &str = "x-maintenance-retry-after";
+
+/// 维护期间拒绝的写方法，格式为 `包名.服务名/方法名`
+///
+/// 未列出的方法一律放行，新增写接口时需要同步加入；登录相关的密码校验、风险评估
+/// 和二次验证不在其中，维护期间用户仍然可以登录
+const MUTATING_METHODS: &[&str] = &[
+    "user.UserService/CreateUser",
+    "user.UserService/UpdateUser",
+    "user.UserService/registerByUsername",
+    "user.UserService/registerByPhone",
+    "user.UserService/forgetPassword",
+    "user.UserService/UpdateUserConfig",
+    "user.UserService/SendPhoneInvite",
+    "user.UserService/UnsubscribeEmailDigest",
+    "auth.AuthService/RevokeToken",
+    "job.JobService/CreateJob",
+    "access_log.AccessLogService/RecordAccessLogs",
+    "retention.RetentionService/SetRetentionPolicy",
+    "retention.RetentionService/PlaceLegalHold",
+    "retention.RetentionService/ReleaseLegalHold",
+    "storage.StorageService/CreateUploadUrl",
+    "storage.StorageService/CompleteUpload",
+    "storage.StorageService/ReleaseAttachments",
+    "friend.FriendService/SendFriendRequest",
+    "friend.FriendService/AcceptFriendRequest",
+    "friend.FriendService/RejectFriendRequest",
+    "friend.FriendService/DeleteFriend",
+    "group.GroupService/CreateGroup",
+    "group.GroupService/UpdateGroup",
+    "group.GroupService/DeleteGroup",
+    "group.GroupService/AddMember",
+    "group.GroupService/RemoveMember",
+    "group.GroupService/UpdateMemberRole",
+    "group.GroupService/IndexGroupMedia",
+    "group.GroupService/RemoveGroupMedia",
+    "group.GroupService/CreatePoll",
+    "group.GroupService/VotePoll",
+    "group.GroupService/ClosePoll",
+    "group_message.GroupMessageService/SendMessage",
+    "group_message.GroupMessageService/DeleteMessage",
+    "private_message.PrivateMessageService/SendMessage",
+    "private_message.PrivateMessageService/MarkAsRead",
+    "private_message.PrivateMessageService/DeleteMessage",
+    "message.ChatService/SendMsg",
+    "message.ChatService/SendBatch",
+    "msg_store.MsgStoreService/DeleteMessage",
+    "msg_store.MsgStoreService/MarkRead",
+    "draft.DraftService/SetDraft",
+    "draft.DraftService/ClearDraft",
+    "message_request.MessageRequestService/AcceptMessageRequest",
+    "message_request.MessageRequestService/DeclineMessageRequest",
+    "transaction.TransactionService/ClaimTransaction",
+    "mailbox.MailboxRepairService/RebuildReceiveBox",
+];
+
+/// 服务的只读维护模式
+///
+/// 配置中列出的服务始终处于维护模式；管理员通过网关开启的维护开关保存在Redis中，
+/// 后台任务定期刷新，Redis不可用时保持上一次的状态
+#[derive(Debug, Clone)]
+pub struct MaintenanceMode {
+    service: String,
+    configured: bool,
+    overridden: Arc<AtomicBool>,
+    retry_after_secs: u64,
+    refresh_secs: u64,
+}
+
+impl MaintenanceMode {
+    pub fn new(service: &str, config: &MaintenanceConfig) -> Self {
+        Self {
+            service: service.to_string(),
+            configured: config.read_only_services.iter().any(|s| s == service),
+            overridden: Arc::new(AtomicBool::new(false)),
+            retry_after_secs: config.retry_after_secs,
+            refresh_secs: config.refresh_secs,
+        }
+    }
+
+    /// 启动后台任务，定期从Redis刷新管理员设置的维护开关
+    pub fn start(&self, redis: &RedisConfig) {
+        if self.configured {
+            warn!("服务 {} 按配置处于只读维护模式", self.service);
+        }
+        let client = match redis::Client::open(redis.url()) {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("维护开关Redis地址无效，只使用配置中的维护模式: {}", e);
+                return;
+            }
+        };
+        let key = maintenance_key(redis);
+        let mode = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(mode.refresh_secs.max(1)));
+            let mut conn: Option<MultiplexedConnection> = None;
+            loop {
+                interval.tick().await;
+                if conn.is_none() {
+                    conn = client.get_multiplexed_async_connection().await.ok();
+                }
+                let Some(c) = conn.as_mut() else {
+                    continue;
+                };
+                let result: redis::RedisResult<bool> = c.hexists(&key, &mode.service).await;
+                match result {
+                    Ok(read_only) => mode.set_overridden(read_only),
+                    Err(e) => {
+                        warn!("刷新维护开关失败: {}", e);
+                        conn = None;
+                    }
+                }
+            }
+        });
+    }
+
+    fn set_overridden(&self, read_only: bool) {
+        if self.overridden.swap(read_only, Ordering::Relaxed) != read_only {
+            if read_only {
+                warn!("服务 {} 进入只读维护模式", self.service);
+            } else {
+                info!("服务 {} 退出只读维护模式", self.service);
+            }
+        }
+    }
+
+    /// 是否处于只读维护模式
+    pub fn is_read_only(&self) -> bool {
+        self.configured || self.overridden.load(Ordering::Relaxed)
+    }
+
+    /// 拒绝写操作的维护错误，元数据中带有建议的重试间隔
+    fn rejection(&self) -> Status {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            MAINTENANCE_METADATA_KEY,
+            MetadataValue::from(self.retry_after_secs),
+        );
+        Status::with_metadata(
+            Code::Unavailable,
+            format!("MAINTENANCE: 服务 {} 维护中，暂不支持写操作", self.service),
+            metadata,
+        )
+    }
+}
+
+fn maintenance_key(redis: &RedisConfig) -> String {
+    format!("{}{}", redis.key_prefix(), MAINTENANCE_KEY)
+}
+
+/// 开启或关闭服务的维护开关，供管理接口使用
+pub async fn set_read_only(redis: &RedisConfig, service: &str, read_only: bool) -> Result<()> {
+    let client = redis::Client::open(redis.url())?;
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    let key = maintenance_key(redis);
+    if read_only {
+        let _: () = conn.hset(&key, service, 1).await?;
+    } else {
+        let _: () = conn.hdel(&key, service).await?;
+    }
+    Ok(())
+}
+
+/// 管理员开启了维护开关的服务
+pub async fn read_only_overrides(redis: &RedisConfig) -> Result<Vec<String>> {
+    let client = redis::Client::open(redis.url())?;
+    let mut conn = client.get_multiplexed_async_connection().await?;
+    Ok(conn.hkeys(maintenance_key(redis)).await?)
+}
+
+/// 维护错误建议的重试间隔（秒），不是维护错误时返回None
+pub fn maintenance_retry_after(status: &Status) -> Option<u64> {
+    if status.code() != Code::Unavailable {
+        return None;
+    }
+    status
+        .metadata()
+        .get(MAINTENANCE_METADATA_KEY)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// 维护期间是否放行该方法：只拒绝明确列出的写方法
+fn allowed_in_maintenance(path: &str) -> bool {
+    let method = path.trim_start_matches('/');
+    !MUTATING_METHODS.contains(&method)
+}
+
+/// 服务端中间件：只读维护期间拒绝写操作
+#[derive(Debug, Clone)]
+pub struct MaintenanceLayer {
+    mode: MaintenanceMode,
+}
+
+impl MaintenanceLayer {
+    pub fn new(mode: MaintenanceMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl<S> Layer<S> for MaintenanceLayer {
+    type Service = ReadOnlyGuard<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReadOnlyGuard::new(inner, self.mode.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReadOnlyGuard<S> {
+    inner: S,
+    mode: MaintenanceMode,
+}
+
+impl<S> ReadOnlyGuard<S> {
+    pub fn new(inner: S, mode: MaintenanceMode) -> Self {
+        Self { inner, mode }
+    }
+}
+
+impl<S, B> Service<HttpRequest<B>> for ReadOnlyGuard<S>
+where
+    S: Service<HttpRequest<B>, Response = HttpResponse<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, std::result::Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest<B>) -> Self::Future {
+        if self.mode.is_read_only() && !allowed_in_maintenance(request.uri().path()) {
+            let response = self.mode.rejection().to_http();
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowed_in_maintenance() {
+        assert!(allowed_in_maintenance("/user.UserService/GetUserById"));
+        assert!(allowed_in_maintenance("/group.GroupService/ListGroupMedia"));
+        assert!(allowed_in_maintenance("/grpc.health.v1.Health/Check"));
+        assert!(allowed_in_maintenance("/user.UserService/VerifyPassword"));
+        assert!(allowed_in_maintenance("/user.UserService/EvaluateLogin"));
+        assert!(allowed_in_maintenance(
+            "/user.UserService/VerifyLoginChallenge"
+        ));
+        assert!(!allowed_in_maintenance("/user.UserService/registerByPhone"));
+        assert!(!allowed_in_maintenance("/user.UserService/UpdateUser"));
+        assert!(!allowed_in_maintenance("/message.ChatService/SendMsg"));
+        assert!(!allowed_in_maintenance(
+            "/friend.FriendService/AcceptFriendRequest"
+        ));
+    }
+
+    #[test]
+    fn test_maintenance_retry_after() {
+        let config = MaintenanceConfig {
+            read_only_services: vec!["user-service".to_string()],
+            retry_after_secs: 120,
+            ..Default::default()
+        };
+        let mode = MaintenanceMode::new("user-service", &config);
+        assert!(mode.is_read_only());
+        assert_eq!(maintenance_retry_after(&mode.rejection()), Some(120));
+        assert_eq!(maintenance_retry_after(&Status::unavailable("下线")), None);
+        assert!(!MaintenanceMode::new("group-service", &config).is_read_only());
+    }
+}
@@ -1,3 +1,8 @@
 pub mod interceptor;
+pub mod maintenance;
+pub mod server;
+pub mod service_auth;
+pub mod subject;
+pub mod tenant;
 
-pub use interceptor::*; 
\ No newline at end of file
+pub use interceptor::*;
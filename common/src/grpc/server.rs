@@ -0,0 +1,146 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use hyper::server::conn::Http;
+use rand::Rng;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tonic::transport::server::{Connected, Routes};
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+use super::interceptor::LoggingService;
+use super::maintenance::{MaintenanceMode, ReadOnlyGuard};
+use super::tenant::TenantScope;
+use crate::config::GrpcServerConfig;
+
+/// 运行gRPC服务，收到关闭信号后排空连接再返回
+///
+/// tonic自带的服务端不支持限制连接存活时间，这里自行接受连接并交给hyper处理：
+/// 连接存活超过配置的时间（加上最多10%的随机抖动，避免连接同时到期）后发送GOAWAY，
+/// 等待处理中的请求完成，客户端在新连接上重新均衡到各实例。
+/// 收到关闭信号时服务已从注册中心注销，先继续处理请求一段时间，
+/// 再停止接受连接并对全部连接发送GOAWAY，超时仍未关闭的连接强制断开。
+/// 服务处于只读维护模式时拒绝写操作，每个请求都经过 [`LoggingService`] 记录指标和span
+pub async fn serve_with_drain<F>(
+    routes: Routes,
+    addr: SocketAddr,
+    config: &GrpcServerConfig,
+    maintenance: MaintenanceMode,
+    signal: F,
+) -> std::io::Result<()>
+where
+    F: Future<Output = ()>,
+{
+    let listener = TcpListener::bind(addr).await?;
+
+    let mut http = Http::new();
+    http.http2_only(true);
+    if config.keepalive_interval_secs > 0 {
+        http.http2_keep_alive_interval(Duration::from_secs(config.keepalive_interval_secs))
+            .http2_keep_alive_timeout(Duration::from_secs(config.keepalive_timeout_secs));
+    }
+
+    let max_age = (config.max_connection_age_secs > 0)
+        .then(|| Duration::from_secs(config.max_connection_age_secs));
+    let age_grace = Duration::from_secs(config.max_connection_age_grace_secs);
+    let drain_timeout = Duration::from_secs(config.drain_timeout_secs);
+
+    let drain_delay = Duration::from_secs(config.drain_delay_secs);
+    // 注销后客户端刷新实例列表前仍可能发来请求，收到信号后继续正常处理一段时间
+    let signal = async {
+        signal.await;
+        info!("gRPC服务开始下线，{}秒后通知全部连接", drain_delay.as_secs());
+        tokio::time::sleep(drain_delay).await;
+    };
+
+    let (drain_tx, drain_rx) = watch::channel(false);
+    let mut connections = JoinSet::new();
+    tokio::pin!(signal);
+
+    loop {
+        tokio::select! {
+            _ = &mut signal => break,
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            accepted = listener.accept() => {
+                let (stream, remote) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("接受gRPC连接失败: {}", e);
+                        continue;
+                    }
+                };
+                let _ = stream.set_nodelay(true);
+
+                // 与tonic一致，在请求中附带连接信息，供拦截器读取客户端地址
+                let connect_info = stream.connect_info();
+                let routes = routes.clone();
+                let maintenance = maintenance.clone();
+                // 请求在其所属租户的上下文中处理，数据库按租户路由，最外层记录方法级指标和span
+                let service = hyper::service::service_fn(move |mut request| {
+                    request.extensions_mut().insert(connect_info.clone());
+                    LoggingService::new(ReadOnlyGuard::new(
+                        TenantScope::new(routes.clone()),
+                        maintenance.clone(),
+                    ))
+                    .oneshot(request)
+                });
+                let connection = http.serve_connection(stream, service);
+                let expire = max_age.map(jitter);
+                let mut drain = drain_rx.clone();
+
+                connections.spawn(async move {
+                    tokio::pin!(connection);
+                    let grace = tokio::select! {
+                        result = connection.as_mut() => {
+                            if let Err(e) = result {
+                                debug!("gRPC连接 {} 异常关闭: {}", remote, e);
+                            }
+                            return;
+                        }
+                        _ = expired(expire) => {
+                            debug!("gRPC连接 {} 达到最长存活时间，发送GOAWAY", remote);
+                            age_grace
+                        }
+                        _ = drain.changed() => drain_timeout,
+                    };
+
+                    connection.as_mut().graceful_shutdown();
+                    if tokio::time::timeout(grace, connection).await.is_err() {
+                        debug!("gRPC连接 {} 未在宽限时间内关闭，强制断开", remote);
+                    }
+                });
+            }
+        }
+    }
+
+    drop(listener);
+    info!("gRPC服务停止接受连接，通知 {} 个连接", connections.len());
+    let _ = drain_tx.send(true);
+    let drained = tokio::time::timeout(drain_timeout, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        warn!("仍有 {} 个gRPC连接未在下线时间内关闭，强制断开", connections.len());
+        connections.shutdown().await;
+    }
+    info!("gRPC服务连接已排空");
+    Ok(())
+}
+
+/// 在连接最长存活时间上增加最多10%的随机抖动
+fn jitter(age: Duration) -> Duration {
+    let spread = age.as_millis() as u64 / 10;
+    age + Duration::from_millis(rand::rng().random_range(0..=spread))
+}
+
+/// 连接到期时完成，未限制存活时间时永不完成
+async fn expired(expire: Option<Duration>) {
+    match expire {
+        Some(expire) => tokio::time::sleep(expire).await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,304 @@
+use std::env;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::Utc;
+use hyper::http::HeaderMap;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+use tracing::{debug, error};
+
+use crate::error::Error;
+
+use super::subject::{attach_subject, current_subject, SUBJECT_METADATA_KEY};
+use super::tenant::{attach_tenant, current_tenant};
+
+/// 服务间令牌所在的元数据键
+pub const SERVICE_TOKEN_METADATA_KEY: &str = "x-service-token";
+
+/// 服务间令牌的受众，防止与用户令牌混用
+const SERVICE_TOKEN_AUDIENCE: &str = "chrisim-internal";
+
+/// 服务间令牌默认有效期（秒）
+const DEFAULT_TOKEN_TTL_SECS: i64 = 60;
+
+/// 客户端服务间认证拦截器类型
+pub type OutboundInterceptor = fn(Request<()>) -> Result<Request<()>, Status>;
+
+/// 携带服务间令牌的客户端通道，用于需要长期持有的客户端
+pub type AuthorizedChannel<T = Channel> = InterceptedService<T, OutboundInterceptor>;
+
+/// 服务间令牌声明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceClaims {
+    /// 调用方服务名
+    pub iss: String,
+    pub aud: String,
+    pub iat: i64,
+    pub exp: i64,
+    /// 网关转发的已认证用户ID，被调方据此校验操作人
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    /// 请求所属租户ID，被调方据此路由数据库
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+}
+
+/// 服务间认证
+///
+/// 调用方用共享密钥签发短期令牌，被调方校验签名、受众和有效期。
+/// 转发的用户ID和租户ID签入令牌，被调方只信任令牌中的值，不信任请求元数据。
+/// 未配置 `SERVICE_AUTH_SECRET` 时不签发令牌，被调方拒绝所有调用
+pub struct ServiceAuth {
+    secret: Option<String>,
+    service_name: String,
+    ttl_secs: i64,
+    // 缓存的令牌及其过期时间，过半有效期后重新签发
+    cached: Mutex<Option<(String, i64)>>,
+}
+
+impl ServiceAuth {
+    /// 从环境变量创建
+    pub fn from_env() -> Self {
+        let secret = env::var("SERVICE_AUTH_SECRET").ok().filter(|s| !s.is_empty());
+        if secret.is_none() {
+            error!("未配置 SERVICE_AUTH_SECRET，服务间调用将被拒绝");
+        }
+
+        Self {
+            secret,
+            service_name: env::var("SERVICE_NAME").unwrap_or_else(|_| "unknown".to_string()),
+            ttl_secs: env::var("SERVICE_AUTH_TTL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_TOKEN_TTL_SECS),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// 全局实例
+    pub fn global() -> &'static ServiceAuth {
+        static INSTANCE: OnceLock<ServiceAuth> = OnceLock::new();
+        INSTANCE.get_or_init(ServiceAuth::from_env)
+    }
+
+    /// 是否启用服务间认证
+    pub fn enabled(&self) -> bool {
+        self.secret.is_some()
+    }
+
+    /// 提供RPC服务前检查是否配置了密钥，未配置时拒绝启动
+    pub fn ensure_enabled(&self) -> Result<(), Error> {
+        if self.enabled() {
+            Ok(())
+        } else {
+            Err(Error::Authentication(
+                "未配置 SERVICE_AUTH_SECRET，拒绝启动RPC服务".to_string(),
+            ))
+        }
+    }
+
+    /// 获取当前可用的服务间令牌，未启用时返回None
+    pub fn token(&self) -> Result<Option<String>, Status> {
+        let Some(secret) = &self.secret else {
+            return Ok(None);
+        };
+
+        let now = Utc::now().timestamp();
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((token, exp)) = cached.as_ref() {
+            if exp - now > self.ttl_secs / 2 {
+                return Ok(Some(token.clone()));
+            }
+        }
+
+        let claims = self.claims(now, None, None);
+        let token = Self::sign(secret, &claims)?;
+        *cached = Some((token.clone(), claims.exp));
+        Ok(Some(token))
+    }
+
+    /// 签发带转发用户ID和租户ID的令牌，两者都没有时复用缓存的服务间令牌
+    pub fn scoped_token(
+        &self,
+        subject: Option<String>,
+        tenant: Option<String>,
+    ) -> Result<Option<String>, Status> {
+        if subject.is_none() && tenant.is_none() {
+            return self.token();
+        }
+        let Some(secret) = &self.secret else {
+            return Ok(None);
+        };
+
+        let claims = self.claims(Utc::now().timestamp(), subject, tenant);
+        Self::sign(secret, &claims).map(Some)
+    }
+
+    fn claims(&self, now: i64, sub: Option<String>, tenant: Option<String>) -> ServiceClaims {
+        ServiceClaims {
+            iss: self.service_name.clone(),
+            aud: SERVICE_TOKEN_AUDIENCE.to_string(),
+            iat: now,
+            exp: now + self.ttl_secs,
+            sub,
+            tenant,
+        }
+    }
+
+    fn sign(secret: &str, claims: &ServiceClaims) -> Result<String, Status> {
+        encode(
+            &Header::default(),
+            claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .map_err(|e| Status::internal(format!("签发服务间令牌失败: {}", e)))
+    }
+
+    /// 校验服务间令牌，返回调用方声明
+    pub fn verify(&self, token: &str) -> Result<ServiceClaims, Status> {
+        let Some(secret) = &self.secret else {
+            return Err(Status::unauthenticated("服务间调用认证未启用"));
+        };
+
+        let mut validation = Validation::default();
+        validation.set_audience(&[SERVICE_TOKEN_AUDIENCE]);
+        validation.leeway = 5;
+
+        decode::<ServiceClaims>(
+            token,
+            &DecodingKey::from_secret(secret.as_bytes()),
+            &validation,
+        )
+        .map(|data| data.claims)
+        .map_err(|e| Status::unauthenticated(format!("无效的服务间令牌: {}", e)))
+    }
+}
+
+/// 客户端拦截器：写入签入了网关转发的用户ID和当前租户ID的服务间令牌
+///
+/// 用户ID和租户ID同时写入请求元数据，仅用于日志排查
+pub fn authorize_outbound(request: Request<()>) -> Result<Request<()>, Status> {
+    let mut request = attach_tenant(attach_subject(request)?)?;
+    let token = ServiceAuth::global().scoped_token(current_subject(), current_tenant())?;
+    if let Some(token) = token {
+        let value = token
+            .parse()
+            .map_err(|_| Status::internal("无效的服务间令牌"))?;
+        request.metadata_mut().insert(SERVICE_TOKEN_METADATA_KEY, value);
+    }
+    Ok(request)
+}
+
+/// 为客户端通道加上服务间认证
+pub fn authorized<T>(inner: T) -> AuthorizedChannel<T> {
+    InterceptedService::new(inner, authorize_outbound as OutboundInterceptor)
+}
+
+/// 从请求头中读取并校验服务间令牌，缺少或无效时返回None
+pub fn verified_claims(headers: &HeaderMap) -> Option<ServiceClaims> {
+    let token = headers
+        .get(SERVICE_TOKEN_METADATA_KEY)
+        .and_then(|v| v.to_str().ok())?;
+    ServiceAuth::global().verify(token).ok()
+}
+
+/// 服务端拦截器：校验服务间令牌后交给内层拦截器处理，未配置密钥时拒绝所有调用
+///
+/// 请求元数据中的转发用户ID替换为令牌中签入的值，[`check_subject`](super::subject::check_subject)
+/// 据此校验操作人
+#[derive(Clone)]
+pub struct ServiceAuthInterceptor<I> {
+    inner: I,
+}
+
+impl<I> ServiceAuthInterceptor<I> {
+    pub fn new(inner: I) -> Self {
+        Self { inner }
+    }
+}
+
+impl<I: tonic::service::Interceptor> tonic::service::Interceptor for ServiceAuthInterceptor<I> {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let auth = ServiceAuth::global();
+        let token = request
+            .metadata()
+            .get(SERVICE_TOKEN_METADATA_KEY)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| Status::unauthenticated("缺少服务间令牌"))?;
+        let claims = auth.verify(token)?;
+        debug!("服务间调用认证通过，调用方: {}", claims.iss);
+
+        // 调用方自行写入的转发用户ID不可信
+        let metadata = request.metadata_mut();
+        metadata.remove(SUBJECT_METADATA_KEY);
+        if let Some(subject) = &claims.sub {
+            let value = subject
+                .parse()
+                .map_err(|_| Status::unauthenticated("无效的认证用户ID"))?;
+            metadata.insert(SUBJECT_METADATA_KEY, value);
+        }
+
+        // 内层拦截器据此识别调用方服务
+        request.extensions_mut().insert(claims);
+
+        self.inner.call(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(secret: &str) -> ServiceAuth {
+        ServiceAuth {
+            secret: Some(secret.to_string()),
+            service_name: "api-gateway".to_string(),
+            ttl_secs: DEFAULT_TOKEN_TTL_SECS,
+            cached: Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn test_service_token() {
+        let gateway = auth("shared-secret");
+        let token = gateway.token().unwrap().unwrap();
+
+        // 有效期过半前复用同一令牌
+        assert_eq!(gateway.token().unwrap().unwrap(), token);
+
+        let claims = auth("shared-secret").verify(&token).unwrap();
+        assert_eq!(claims.iss, "api-gateway");
+
+        // 转发的用户ID和租户ID签入令牌
+        let scoped = gateway
+            .scoped_token(Some("1001".to_string()), Some("7".to_string()))
+            .unwrap()
+            .unwrap();
+        let claims = auth("shared-secret").verify(&scoped).unwrap();
+        assert_eq!(claims.sub.as_deref(), Some("1001"));
+        assert_eq!(claims.tenant.as_deref(), Some("7"));
+        assert_eq!(gateway.scoped_token(None, None).unwrap().unwrap(), token);
+
+        // 密钥不一致时校验失败
+        let err = auth("other-secret").verify(&token).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[test]
+    fn test_verify_without_secret_fails_closed() {
+        let token = auth("shared-secret").token().unwrap().unwrap();
+
+        let unconfigured = ServiceAuth {
+            secret: None,
+            service_name: "msg-server".to_string(),
+            ttl_secs: DEFAULT_TOKEN_TTL_SECS,
+            cached: Mutex::new(None),
+        };
+        assert!(unconfigured.ensure_enabled().is_err());
+        let err = unconfigured.verify(&token).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+    }
+}
@@ -0,0 +1,80 @@
+use std::future::Future;
+
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Status};
+use tracing::warn;
+
+/// 网关转发的已认证用户ID所在的元数据键
+pub const SUBJECT_METADATA_KEY: &str = "x-auth-subject";
+
+tokio::task_local! {
+    static FORWARDED_SUBJECT: String;
+}
+
+/// 在当前已认证用户的上下文中执行请求
+///
+/// 期间通过 [`attach_subject`] 拦截器发出的gRPC请求都会携带该用户ID
+pub async fn with_subject<F: Future>(subject: Option<String>, fut: F) -> F::Output {
+    match subject {
+        Some(subject) => FORWARDED_SUBJECT.scope(subject, fut).await,
+        None => fut.await,
+    }
+}
+
+/// 当前上下文中的已认证用户ID，不在用户上下文中时返回None
+pub fn current_subject() -> Option<String> {
+    FORWARDED_SUBJECT.try_with(|subject| subject.clone()).ok()
+}
+
+/// 客户端拦截器：将当前上下文中的已认证用户ID写入请求元数据
+pub fn attach_subject(mut request: Request<()>) -> Result<Request<()>, Status> {
+    if let Some(subject) = current_subject() {
+        let value = subject
+            .parse()
+            .map_err(|_| Status::internal("无效的认证用户ID"))?;
+        request.metadata_mut().insert(SUBJECT_METADATA_KEY, value);
+    }
+    Ok(request)
+}
+
+/// 请求携带的网关转发用户ID，内部服务之间的调用返回None
+pub fn forwarded_subject(metadata: &MetadataMap) -> Option<&str> {
+    metadata
+        .get(SUBJECT_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// 服务端纵深防御检查：请求携带了网关转发的用户ID时，必须与被操作的用户一致
+///
+/// 该元数据由服务间认证拦截器按令牌中签入的值重写，未携带的请求来自内部服务之间的调用，不做限制
+pub fn check_subject(metadata: &MetadataMap, acting_user_id: &str) -> Result<(), Status> {
+    let subject = match metadata.get(SUBJECT_METADATA_KEY) {
+        Some(value) => value
+            .to_str()
+            .map_err(|_| Status::unauthenticated("无效的认证用户ID"))?,
+        None => return Ok(()),
+    };
+
+    if subject != acting_user_id {
+        warn!("认证用户 {} 试图以用户 {} 的身份操作", subject, acting_user_id);
+        return Err(Status::permission_denied("无权以其他用户身份操作"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_subject() {
+        let mut metadata = MetadataMap::new();
+        assert!(check_subject(&metadata, "1001").is_ok());
+
+        metadata.insert(SUBJECT_METADATA_KEY, "1001".parse().unwrap());
+        assert!(check_subject(&metadata, "1001").is_ok());
+
+        let err = check_subject(&metadata, "1002").unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,95 @@
+use std::future::Future;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use hyper::http::{HeaderMap, Request as HttpRequest};
+use tonic::{Request, Status};
+use tower::{Layer, Service};
+
+use super::service_auth::verified_claims;
+
+/// 当前请求所属租户ID所在的元数据键
+pub const TENANT_METADATA_KEY: &str = "x-tenant-id";
+
+tokio::task_local! {
+    static CURRENT_TENANT: String;
+}
+
+/// 在租户的上下文中执行请求
+///
+/// 期间数据库按该租户路由，通过 [`attach_tenant`] 拦截器发出的gRPC请求都会携带租户ID
+pub async fn with_tenant<F: Future>(tenant_id: Option<String>, fut: F) -> F::Output {
+    match tenant_id.filter(|id| !id.is_empty()) {
+        Some(tenant_id) => CURRENT_TENANT.scope(tenant_id, fut).await,
+        None => fut.await,
+    }
+}
+
+/// 当前上下文中的租户ID，不在租户上下文中时返回None
+pub fn current_tenant() -> Option<String> {
+    CURRENT_TENANT.try_with(|tenant_id| tenant_id.clone()).ok()
+}
+
+/// 客户端拦截器：将当前上下文中的租户ID写入请求元数据
+pub fn attach_tenant(mut request: Request<()>) -> Result<Request<()>, Status> {
+    if let Some(tenant_id) = current_tenant() {
+        let value = tenant_id
+            .parse()
+            .map_err(|_| Status::internal("无效的租户ID"))?;
+        request.metadata_mut().insert(TENANT_METADATA_KEY, value);
+    }
+    Ok(request)
+}
+
+/// 从请求头的服务间令牌中读取签入的租户ID
+///
+/// 请求元数据中的租户ID由调用方自行写入，不可信，只使用校验通过的令牌中的值
+pub fn tenant_from_headers(headers: &HeaderMap) -> Option<String> {
+    verified_claims(headers).and_then(|claims| claims.tenant)
+}
+
+/// 服务端中间件：在请求令牌签入的租户上下文中处理请求
+///
+/// 拦截器无法包住处理函数的执行过程，因此以tower中间件的形式挂在gRPC服务上；
+/// 令牌缺少或无效的请求随后会被服务间认证拦截器拒绝
+#[derive(Debug, Clone, Default)]
+pub struct TenantScopeLayer;
+
+impl<S> Layer<S> for TenantScopeLayer {
+    type Service = TenantScope<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TenantScope { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TenantScope<S> {
+    inner: S,
+}
+
+impl<S> TenantScope<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, B> Service<HttpRequest<B>> for TenantScope<S>
+where
+    S: Service<HttpRequest<B>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<S::Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: HttpRequest<B>) -> Self::Future {
+        let tenant_id = tenant_from_headers(request.headers());
+        let future = self.inner.call(request);
+        Box::pin(with_tenant(tenant_id, future))
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::access_log::access_log_service_client::AccessLogServiceClient;
+use crate::proto::access_log::{
+    AccessLog, ListAccessLogsRequest, ListAccessLogsResponse, RecordAccessLogsRequest,
+    RecordAccessLogsResponse,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 访问日志服务gRPC客户端
+#[derive(Clone)]
+pub struct AccessLogGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl AccessLogGrpcClient {
+    /// 创建新的访问日志服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 访问日志服务目前由用户服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("user-service");
+        Self::new(service_client)
+    }
+
+    /// 批量写入访问日志
+    pub async fn record_access_logs(
+        &self,
+        logs: Vec<AccessLog>,
+    ) -> Result<RecordAccessLogsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = AccessLogServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(RecordAccessLogsRequest { logs });
+
+        let response = client.record_access_logs(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询租户内的访问日志
+    pub async fn list_access_logs(
+        &self,
+        request: ListAccessLogsRequest,
+    ) -> Result<ListAccessLogsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = AccessLogServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.list_access_logs(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tonic::Request;
+
+use crate::proto::auth::auth_service_client::AuthServiceClient;
+use crate::proto::auth::{
+    IntrospectTokenRequest, IntrospectTokenResponse, RevokeTokenRequest, RevokeTokenResponse,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 自省结果的本地缓存时间，令牌被吊销后最多延迟该时间生效
+const INTROSPECT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// 本地缓存的最大条目数，超出后清空
+const INTROSPECT_CACHE_CAPACITY: usize = 10_000;
+
+/// 令牌服务gRPC客户端
+///
+/// 有效的自省结果会在本地短暂缓存，避免每次握手都访问用户服务
+#[derive(Clone)]
+pub struct AuthServiceGrpcClient {
+    service_client: GrpcServiceClient,
+    cache: Arc<Mutex<HashMap<String, (Instant, IntrospectTokenResponse)>>>,
+}
+
+impl AuthServiceGrpcClient {
+    /// 创建新的令牌服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self {
+            service_client,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 令牌服务目前由用户服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("user-service");
+        Self::new(service_client)
+    }
+
+    /// 令牌自省
+    pub async fn introspect(&self, token: &str) -> Result<IntrospectTokenResponse> {
+        if let Some(cached) = self.cached(token) {
+            return Ok(cached);
+        }
+
+        let channel = self.service_client.get_channel().await?;
+        let mut client = AuthServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(IntrospectTokenRequest {
+            token: token.to_string(),
+        });
+
+        let response = client.introspect_token(request).await?.into_inner();
+        if response.active {
+            self.store(token, &response);
+        }
+        Ok(response)
+    }
+
+    /// 吊销令牌，同时清除本地缓存
+    pub async fn revoke(&self, token: &str, all_sessions: bool) -> Result<RevokeTokenResponse> {
+        self.cache.lock().unwrap().remove(token);
+
+        let channel = self.service_client.get_channel().await?;
+        let mut client = AuthServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(RevokeTokenRequest {
+            token: token.to_string(),
+            all_sessions,
+        });
+
+        let response = client.revoke_token(request).await?;
+        Ok(response.into_inner())
+    }
+
+    fn cached(&self, token: &str) -> Option<IntrospectTokenResponse> {
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(token) {
+            Some((expires, response)) if *expires > Instant::now() => Some(response.clone()),
+            Some(_) => {
+                cache.remove(token);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn store(&self, token: &str, response: &IntrospectTokenResponse) {
+        // 缓存时间不超过令牌本身的剩余有效期
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        let remaining = Duration::from_secs((response.expires_at - now).max(0) as u64);
+        let ttl = INTROSPECT_CACHE_TTL.min(remaining);
+        if ttl.is_zero() {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= INTROSPECT_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(token.to_string(), (Instant::now() + ttl, response.clone()));
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use tonic::{Request, Streaming};
+
+use crate::proto::backfill::history_backfill_service_client::HistoryBackfillServiceClient;
+use crate::proto::backfill::{
+    CancelBackfillRequest, GetConversationRangeRequest, GetConversationRangeResponse,
+    GetReadCursorsRequest, GetReadCursorsResponse, HistoryChunk, StreamHistoryRequest,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 历史消息服务gRPC客户端
+#[derive(Clone)]
+pub struct BackfillGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl BackfillGrpcClient {
+    /// 创建新的历史消息服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 历史消息服务由消息服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("msg-server");
+        Self::new(service_client)
+    }
+
+    /// 开始或继续回填，返回服务端分批推送的历史消息流
+    pub async fn stream_history(
+        &self,
+        request: StreamHistoryRequest,
+    ) -> Result<Streaming<HistoryChunk>> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client =
+            HistoryBackfillServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.stream_history(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 取消回填，进度保留以便之后继续
+    pub async fn cancel_backfill(&self, user_id: &str, device_id: &str) -> Result<()> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client =
+            HistoryBackfillServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(CancelBackfillRequest {
+            user_id: user_id.to_string(),
+            device_id: device_id.to_string(),
+        });
+
+        client.cancel_backfill(request).await?;
+        Ok(())
+    }
+
+    /// 查询用户各会话的已读位置
+    pub async fn get_read_cursors(&self, user_id: &str) -> Result<GetReadCursorsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client =
+            HistoryBackfillServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetReadCursorsRequest {
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_read_cursors(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 按会话序号拉取会话中的消息
+    pub async fn get_conversation_range(
+        &self,
+        request: GetConversationRangeRequest,
+    ) -> Result<GetConversationRangeResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client =
+            HistoryBackfillServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.get_conversation_range(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -6,15 +6,16 @@ use tokio::sync::Mutex;
 use tonic::transport::{Channel, Endpoint};
 use tracing::{error, info, debug};
 
-use crate::service_registry::ServiceRegistry;
+use crate::grpc_client::retry::RetryChannel;
+use crate::service_registry::{ServiceRegister, ServiceRegistry};
 
 /// gRPC服务客户端，用于调用其他微服务的gRPC接口
 #[derive(Clone, Debug)]
 pub struct GrpcServiceClient {
-    service_registry: ServiceRegistry,
+    service_registry: Arc<dyn ServiceRegister>,
     service_name: String,
-    // 缓存已发现的服务Channel
-    channels: Arc<Mutex<Vec<Channel>>>,
+    // 缓存已发现的服务Channel，同时记录实例地址
+    channels: Arc<Mutex<Vec<(String, Channel)>>>,
     // 配置参数
     connection_timeout: Duration,
     request_timeout: Duration,
@@ -24,7 +25,7 @@ pub struct GrpcServiceClient {
 impl GrpcServiceClient {
     /// 创建新的gRPC服务客户端
     pub fn new(
-        service_registry: ServiceRegistry,
+        service_registry: Arc<dyn ServiceRegister>,
         service_name: &str,
         connection_timeout: Duration,
         request_timeout: Duration,
@@ -41,7 +42,7 @@ impl GrpcServiceClient {
     }
 
     /// 使用默认设置创建新的gRPC服务客户端
-    pub fn with_defaults(service_registry: ServiceRegistry, service_name: &str) -> Self {
+    pub fn with_defaults(service_registry: Arc<dyn ServiceRegister>, service_name: &str) -> Self {
         Self::new(
             service_registry,
             service_name,
@@ -53,7 +54,7 @@ impl GrpcServiceClient {
 
     /// 从环境变量创建服务客户端
     pub fn from_env(service_name: &str) -> Self {
-        let service_registry = ServiceRegistry::from_env();
+        let service_registry = ServiceRegistry::from_env().shared();
         Self::with_defaults(service_registry, service_name)
     }
 
@@ -61,10 +62,7 @@ impl GrpcServiceClient {
     pub async fn refresh_channels(&self) -> Result<()> {
         debug!("开始刷新服务通道: {}", self.service_name);
         // 从Consul获取服务实例
-        let service_urls = self
-            .service_registry
-            .discover_service(&self.service_name)
-            .await?;
+        let service_urls = self.service_registry.discover(&self.service_name).await?;
 
         if service_urls.is_empty() {
             return Err(anyhow::anyhow!(
@@ -86,7 +84,7 @@ impl GrpcServiceClient {
 
             match self.create_channel(&grpc_url).await {
                 Ok(channel) => {
-                    new_channels.push(channel);
+                    new_channels.push((grpc_url, channel));
                 }
                 Err(err) => {
                     error!("无法连接到gRPC服务 {}: {}", grpc_url, err);
@@ -138,8 +136,14 @@ impl GrpcServiceClient {
         Ok(channel)
     }
 
-    /// 获取通道（带负载均衡）
-    pub async fn get_channel(&self) -> Result<Channel> {
+    /// 获取通道（带负载均衡），实例下线拒绝请求时自动重试到其他实例
+    pub async fn get_channel(&self) -> Result<RetryChannel> {
+        let (endpoint, channel) = self.pick_channel().await?;
+        Ok(RetryChannel::new(self.clone(), endpoint, channel))
+    }
+
+    /// 随机选择一个实例的通道
+    pub(crate) async fn pick_channel(&self) -> Result<(String, Channel)> {
         // 检查缓存是否为空
         {
             let channels = self.channels.lock().await;
@@ -162,6 +166,18 @@ impl GrpcServiceClient {
         Ok(channels[index].clone())
     }
 
+    /// 移除正在下线的实例，全部移除后下次获取通道时重新从Consul发现
+    pub(crate) async fn evict_channel(&self, endpoint: &str) {
+        let mut channels = self.channels.lock().await;
+        channels.retain(|(url, _)| url != endpoint);
+        info!(
+            "{} 服务实例 {} 已拒绝请求，暂时移除，剩余 {} 个实例",
+            self.service_name,
+            endpoint,
+            channels.len()
+        );
+    }
+
     /// 启动一个后台任务定期刷新服务实例列表
     pub fn start_refresh_task(client: Arc<Self>) {
         let refresh_interval = std::env::var("SERVICE_REFRESH_INTERVAL")
@@ -188,18 +204,18 @@ impl GrpcServiceClient {
 /// gRPC服务客户端工厂，用于创建各种服务的gRPC客户端
 #[derive(Clone)]
 pub struct GrpcClientFactory {
-    service_registry: ServiceRegistry,
+    service_registry: Arc<dyn ServiceRegister>,
 }
 
 impl GrpcClientFactory {
     /// 创建新的gRPC客户端工厂
-    pub fn new(service_registry: ServiceRegistry) -> Self {
+    pub fn new(service_registry: Arc<dyn ServiceRegister>) -> Self {
         Self { service_registry }
     }
 
     /// 从环境变量创建gRPC客户端工厂
     pub fn from_env() -> Self {
-        let service_registry = ServiceRegistry::from_env();
+        let service_registry = ServiceRegistry::from_env().shared();
         Self::new(service_registry)
     }
 
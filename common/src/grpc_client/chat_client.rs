@@ -0,0 +1,46 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::message::chat_service_client::ChatServiceClient;
+use crate::message::{Msg, MsgResponse, SendBatchRequest, SendMsgRequest};
+
+use crate::grpc_client::GrpcServiceClient;
+
+/// 消息服务gRPC客户端，用于服务端主动下发消息
+#[derive(Clone)]
+pub struct ChatServiceGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl ChatServiceGrpcClient {
+    /// 创建新的消息服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("msg-server");
+        Self::new(service_client)
+    }
+
+    /// 发送消息，消息经消息队列投递到接收者的在线会话
+    pub async fn send_msg(&self, request: SendMsgRequest) -> Result<MsgResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = ChatServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.send_msg(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+    /// 批量发送同一发送者的消息，返回与消息一一对应的结果
+    pub async fn send_batch(&self, messages: Vec<Msg>) -> Result<Vec<MsgResponse>> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = ChatServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client
+            .send_batch(Request::new(SendBatchRequest { messages }))
+            .await?;
+        Ok(response.into_inner().results)
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::draft::draft_service_client::DraftServiceClient;
+use crate::proto::draft::{
+    ClearDraftRequest, ClearDraftResponse, DraftResponse, GetDraftsRequest, GetDraftsResponse,
+    SetDraftRequest,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 会话草稿服务gRPC客户端
+#[derive(Clone)]
+pub struct DraftGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl DraftGrpcClient {
+    /// 创建新的会话草稿服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 会话草稿服务由消息服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("msg-server");
+        Self::new(service_client)
+    }
+
+    /// 保存会话草稿
+    pub async fn set_draft(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        text: &str,
+        platform: i32,
+    ) -> Result<DraftResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = DraftServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(SetDraftRequest {
+            user_id: user_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            text: text.to_string(),
+            platform,
+        });
+
+        let response = client.set_draft(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询会话草稿，会话ID为空时返回全部草稿
+    pub async fn get_drafts(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+    ) -> Result<GetDraftsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = DraftServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetDraftsRequest {
+            user_id: user_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+        });
+
+        let response = client.get_drafts(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 清除会话草稿
+    pub async fn clear_draft(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        platform: i32,
+    ) -> Result<ClearDraftResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = DraftServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ClearDraftRequest {
+            user_id: user_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            platform,
+        });
+
+        let response = client.clear_draft(request).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -5,10 +5,11 @@ use crate::proto::friend::friend_service_client::FriendServiceClient;
 use crate::proto::friend::{
     AcceptFriendRequestRequest, CheckFriendshipRequest, CheckFriendshipResponse, DeleteFriendRequest,
     DeleteFriendResponse, FriendshipResponse, GetFriendListRequest, GetFriendListResponse,
-    GetFriendRequestsRequest, GetFriendRequestsResponse, RejectFriendRequestRequest,
-    SendFriendRequestRequest,
+    GetFriendRequestsRequest, GetFriendRequestsResponse, GetGroupFriendsRequest,
+    GetGroupFriendsResponse, RejectFriendRequestRequest, SendFriendRequestRequest,
 };
 
+use crate::grpc::service_auth::authorize_outbound;
 use crate::grpc_client::GrpcServiceClient;
 
 /// 好友服务gRPC客户端
@@ -37,7 +38,7 @@ impl FriendServiceGrpcClient {
         message: &str,
     ) -> Result<FriendshipResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(SendFriendRequestRequest {
             user_id: user_id.to_string(),
@@ -56,7 +57,7 @@ impl FriendServiceGrpcClient {
         friend_id: &str,
     ) -> Result<FriendshipResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(AcceptFriendRequestRequest {
             user_id: user_id.to_string(),
@@ -75,7 +76,7 @@ impl FriendServiceGrpcClient {
         reason: &str,
     ) -> Result<FriendshipResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(RejectFriendRequestRequest {
             user_id: user_id.to_string(),
@@ -101,7 +102,7 @@ impl FriendServiceGrpcClient {
         sort_by: &str,
     ) -> Result<GetFriendListResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetFriendListRequest {
             user_id: user_id.to_string(),
@@ -117,7 +118,7 @@ impl FriendServiceGrpcClient {
     /// 获取好友请求列表
     pub async fn get_friend_requests(&self, user_id: &str) -> Result<GetFriendRequestsResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetFriendRequestsRequest {
             user_id: user_id.to_string(),
@@ -130,7 +131,7 @@ impl FriendServiceGrpcClient {
     /// 删除好友
     pub async fn delete_friend(&self, user_id: &str, friend_id: &str) -> Result<DeleteFriendResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(DeleteFriendRequest {
             user_id: user_id.to_string(),
@@ -148,7 +149,7 @@ impl FriendServiceGrpcClient {
         friend_id: &str,
     ) -> Result<CheckFriendshipResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = FriendServiceClient::new(channel);
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(CheckFriendshipRequest {
             user_id: user_id.to_string(),
@@ -158,4 +159,30 @@ impl FriendServiceGrpcClient {
         let response = client.check_friendship(request).await?;
         Ok(response.into_inner())
     }
+
+    /// 获取分组内的好友，传了游标时按游标分页，否则按页码分页
+    pub async fn get_group_friends(
+        &self,
+        user_id: &str,
+        group_id: &str,
+        page: i64,
+        page_size: i64,
+        cursor: &str,
+        sort_by: &str,
+    ) -> Result<GetGroupFriendsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = FriendServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetGroupFriendsRequest {
+            user_id: user_id.to_string(),
+            group_id: group_id.to_string(),
+            page,
+            page_size,
+            cursor: cursor.to_string(),
+            sort_by: sort_by.to_string(),
+        });
+
+        let response = client.get_group_friends(request).await?;
+        Ok(response.into_inner())
+    }
 } 
\ No newline at end of file
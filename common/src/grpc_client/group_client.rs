@@ -4,11 +4,15 @@ use tonic::Request;
 use crate::proto::group::group_service_client::GroupServiceClient;
 use crate::proto::group::{
     AddMemberRequest, CheckMembershipRequest, CheckMembershipResponse, CreateGroupRequest,
-    DeleteGroupRequest, DeleteGroupResponse, GetGroupRequest, GetMembersRequest, GetMembersResponse,
-    GetUserGroupsRequest, GetUserGroupsResponse, GroupResponse, MemberResponse, MemberRole,
-    RemoveMemberRequest, RemoveMemberResponse, UpdateGroupRequest, UpdateMemberRoleRequest,
+    DeleteGroupRequest, DeleteGroupResponse, GetGroupRequest, GetGroupStatsRequest,
+    GetGroupStatsResponse, GetMembersRequest, GetMembersResponse,
+    GetUserGroupsRequest, GetUserGroupsResponse, GroupMedia, GroupResponse, IndexGroupMediaRequest,
+    ListGroupMediaRequest, ListGroupMediaResponse, MemberResponse, MemberRole,
+    RemoveGroupMediaRequest, RemoveMemberRequest, RemoveMemberResponse, UpdateGroupRequest, UpdateMemberRoleRequest,
+    ClosePollRequest, CreatePollRequest, GetPollResultsRequest, PollResponse, VotePollRequest,
 };
 
+use crate::grpc::service_auth::authorize_outbound;
 use crate::grpc_client::GrpcServiceClient;
 
 /// 群组服务gRPC客户端
@@ -38,7 +42,7 @@ impl GroupServiceGrpcClient {
         avatar_url: &str,
     ) -> Result<GroupResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(CreateGroupRequest {
             name: name.to_string(),
@@ -54,7 +58,7 @@ impl GroupServiceGrpcClient {
     /// 获取群组信息
     pub async fn get_group(&self, group_id: &str) -> Result<GroupResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetGroupRequest {
             group_id: group_id.to_string(),
@@ -68,18 +72,20 @@ impl GroupServiceGrpcClient {
     pub async fn update_group(
         &self,
         group_id: &str,
+        user_id: &str,
         name: Option<String>,
         description: Option<String>,
         avatar_url: Option<String>,
     ) -> Result<GroupResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(UpdateGroupRequest {
             group_id: group_id.to_string(),
             name,
             description,
             avatar_url,
+            user_id: user_id.to_string(),
         });
 
         let response = client.update_group(request).await?;
@@ -89,7 +95,7 @@ impl GroupServiceGrpcClient {
     /// 删除群组
     pub async fn delete_group(&self, group_id: &str, user_id: &str) -> Result<DeleteGroupResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(DeleteGroupRequest {
             group_id: group_id.to_string(),
@@ -109,7 +115,7 @@ impl GroupServiceGrpcClient {
         role: MemberRole,
     ) -> Result<MemberResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(AddMemberRequest {
             group_id: group_id.to_string(),
@@ -130,7 +136,7 @@ impl GroupServiceGrpcClient {
         removed_by_id: &str,
     ) -> Result<RemoveMemberResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(RemoveMemberRequest {
             group_id: group_id.to_string(),
@@ -151,7 +157,7 @@ impl GroupServiceGrpcClient {
         role: MemberRole,
     ) -> Result<MemberResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(UpdateMemberRoleRequest {
             group_id: group_id.to_string(),
@@ -167,7 +173,7 @@ impl GroupServiceGrpcClient {
     /// 获取群组成员列表
     pub async fn get_members(&self, group_id: &str) -> Result<GetMembersResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetMembersRequest {
             group_id: group_id.to_string(),
@@ -180,7 +186,7 @@ impl GroupServiceGrpcClient {
     /// 获取用户加入的群组列表
     pub async fn get_user_groups(&self, user_id: &str) -> Result<GetUserGroupsResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetUserGroupsRequest {
             user_id: user_id.to_string(),
@@ -197,7 +203,7 @@ impl GroupServiceGrpcClient {
         user_id: &str,
     ) -> Result<CheckMembershipResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = GroupServiceClient::new(channel);
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(CheckMembershipRequest {
             group_id: group_id.to_string(),
@@ -207,4 +213,119 @@ impl GroupServiceGrpcClient {
         let response = client.check_membership(request).await?;
         Ok(response.into_inner())
     }
-} 
\ No newline at end of file
+
+    /// 获取群组统计数据
+    pub async fn get_group_stats(
+        &self,
+        group_id: &str,
+        user_id: &str,
+        days: i32,
+    ) -> Result<GetGroupStatsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetGroupStatsRequest {
+            group_id: group_id.to_string(),
+            user_id: user_id.to_string(),
+            days,
+        });
+
+        let response = client.get_group_stats(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 索引群聊中发送的附件
+    pub async fn index_group_media(&self, media: GroupMedia) -> Result<()> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(IndexGroupMediaRequest { media: Some(media) });
+
+        client.index_group_media(request).await?;
+        Ok(())
+    }
+
+    /// 分页浏览、搜索群媒体
+    pub async fn list_group_media(
+        &self,
+        request: ListGroupMediaRequest,
+    ) -> Result<ListGroupMediaResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.list_group_media(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 移除原消息对应的群媒体，返回移除的条数
+    pub async fn remove_group_media(&self, group_id: &str, msg_id: &str) -> Result<i64> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(RemoveGroupMediaRequest {
+            group_id: group_id.to_string(),
+            msg_id: msg_id.to_string(),
+        });
+
+        let response = client.remove_group_media(request).await?;
+        Ok(response.into_inner().removed)
+    }
+
+    /// 发起群投票
+    pub async fn create_poll(&self, request: CreatePollRequest) -> Result<PollResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.create_poll(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 投票，选项为空表示撤回投票
+    pub async fn vote_poll(&self, request: VotePollRequest) -> Result<PollResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.vote_poll(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询群投票和统计结果
+    pub async fn get_poll_results(
+        &self,
+        group_id: &str,
+        poll_id: &str,
+        user_id: &str,
+    ) -> Result<PollResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetPollResultsRequest {
+            group_id: group_id.to_string(),
+            poll_id: poll_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_poll_results(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 结束群投票
+    pub async fn close_poll(
+        &self,
+        group_id: &str,
+        poll_id: &str,
+        user_id: &str,
+    ) -> Result<PollResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = GroupServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ClosePollRequest {
+            group_id: group_id.to_string(),
+            poll_id: poll_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.close_poll(request).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::job::job_service_client::JobServiceClient;
+use crate::proto::job::{CreateJobRequest, GetJobRequest, JobResponse};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 异步任务服务gRPC客户端
+#[derive(Clone)]
+pub struct JobServiceGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl JobServiceGrpcClient {
+    /// 创建新的异步任务服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 异步任务服务目前由用户服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("user-service");
+        Self::new(service_client)
+    }
+
+    /// 创建异步任务
+    pub async fn create_job(&self, user_id: &str, kind: &str, params: &str) -> Result<JobResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = JobServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(CreateJobRequest {
+            user_id: user_id.to_string(),
+            kind: kind.to_string(),
+            params: params.to_string(),
+        });
+
+        let response = client.create_job(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询任务状态
+    pub async fn get_job(&self, job_id: &str, user_id: &str) -> Result<JobResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = JobServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetJobRequest {
+            job_id: job_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_job(request).await?;
+        Ok(response.into_inner())
+    }
+}
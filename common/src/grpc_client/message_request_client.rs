@@ -0,0 +1,104 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::message_request::message_request_service_client::MessageRequestServiceClient;
+use crate::proto::message_request::{
+    GetMessageRequestRequest, GetMessageRequestResponse, ListMessageRequestsRequest,
+    ListMessageRequestsResponse, MessageRequestActionRequest, MessageRequestActionResponse,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 消息请求服务gRPC客户端
+#[derive(Clone)]
+pub struct MessageRequestGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl MessageRequestGrpcClient {
+    /// 创建新的消息请求服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 消息请求服务由消息服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("msg-server");
+        Self::new(service_client)
+    }
+
+    /// 查询待处理的消息请求
+    pub async fn list_message_requests(
+        &self,
+        user_id: &str,
+        limit: i32,
+    ) -> Result<ListMessageRequestsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = MessageRequestServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ListMessageRequestsRequest {
+            user_id: user_id.to_string(),
+            limit,
+        });
+
+        let response = client.list_message_requests(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询某个发送者的消息请求中的消息
+    pub async fn get_message_request(
+        &self,
+        user_id: &str,
+        sender_id: &str,
+    ) -> Result<GetMessageRequestResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = MessageRequestServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetMessageRequestRequest {
+            user_id: user_id.to_string(),
+            sender_id: sender_id.to_string(),
+        });
+
+        let response = client.get_message_request(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 接受消息请求
+    pub async fn accept_message_request(
+        &self,
+        user_id: &str,
+        sender_id: &str,
+    ) -> Result<MessageRequestActionResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = MessageRequestServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(MessageRequestActionRequest {
+            user_id: user_id.to_string(),
+            sender_id: sender_id.to_string(),
+        });
+
+        let response = client.accept_message_request(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 拒绝消息请求
+    pub async fn decline_message_request(
+        &self,
+        user_id: &str,
+        sender_id: &str,
+    ) -> Result<MessageRequestActionResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = MessageRequestServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(MessageRequestActionRequest {
+            user_id: user_id.to_string(),
+            sender_id: sender_id.to_string(),
+        });
+
+        let response = client.decline_message_request(request).await?;
+        Ok(response.into_inner())
+    }
+}
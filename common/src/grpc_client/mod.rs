@@ -1,15 +1,33 @@
 pub mod user_client;
 pub mod friend_client;
 pub mod group_client;
+pub mod job_client;
+pub mod chat_client;
+pub mod auth_client;
+pub mod retention_client;
+pub mod message_request_client;
+pub mod draft_client;
+pub mod transaction_client;
+pub mod access_log_client;
+pub mod storage_client;
+pub mod backfill_client;
 
 pub use user_client::UserServiceGrpcClient;
 pub use friend_client::FriendServiceGrpcClient;
 pub use group_client::GroupServiceGrpcClient;
+pub use job_client::JobServiceGrpcClient;
+pub use chat_client::ChatServiceGrpcClient;
+pub use auth_client::AuthServiceGrpcClient;
+pub use retention_client::RetentionServiceGrpcClient;
+pub use message_request_client::MessageRequestGrpcClient;
+pub use draft_client::DraftGrpcClient;
+pub use transaction_client::TransactionGrpcClient;
+pub use access_log_client::AccessLogGrpcClient;
+pub use storage_client::StorageGrpcClient;
+pub use backfill_client::BackfillGrpcClient;
 
 mod base;
+mod retry;
 
 pub use base::{GrpcClientFactory, GrpcServiceClient};
-
-// 后续可以继续添加其他服务客户端模块
-// pub mod auth_client;
-// pub use auth_client::AuthServiceGrpcClient;
+pub use retry::RetryChannel;
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::retention::retention_service_client::RetentionServiceClient;
+use crate::proto::retention::{
+    GetRetentionPolicyRequest, LegalHoldResponse, ListLegalHoldsRequest, ListLegalHoldsResponse,
+    ListRetentionAuditsRequest, ListRetentionAuditsResponse, PlaceLegalHoldRequest,
+    ReleaseLegalHoldRequest, RetentionPolicyResponse, SetRetentionPolicyRequest,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 消息保留策略服务gRPC客户端
+#[derive(Clone)]
+pub struct RetentionServiceGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl RetentionServiceGrpcClient {
+    /// 创建新的消息保留策略服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 消息保留策略服务目前由用户服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("user-service");
+        Self::new(service_client)
+    }
+
+    /// 设置租户保留策略
+    pub async fn set_retention_policy(
+        &self,
+        request: SetRetentionPolicyRequest,
+    ) -> Result<RetentionPolicyResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = RetentionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.set_retention_policy(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询租户保留策略
+    pub async fn get_retention_policy(
+        &self,
+        operator_id: &str,
+        tenant_id: &str,
+    ) -> Result<RetentionPolicyResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = RetentionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetRetentionPolicyRequest {
+            operator_id: operator_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+        });
+
+        let response = client.get_retention_policy(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 设置法律保全
+    pub async fn place_legal_hold(&self, request: PlaceLegalHoldRequest) -> Result<LegalHoldResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = RetentionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.place_legal_hold(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 解除法律保全
+    pub async fn release_legal_hold(
+        &self,
+        operator_id: &str,
+        tenant_id: &str,
+        hold_id: &str,
+    ) -> Result<LegalHoldResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = RetentionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ReleaseLegalHoldRequest {
+            operator_id: operator_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            hold_id: hold_id.to_string(),
+        });
+
+        let response = client.release_legal_hold(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询生效中的法律保全
+    pub async fn list_legal_holds(
+        &self,
+        operator_id: &str,
+        tenant_id: &str,
+    ) -> Result<ListLegalHoldsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = RetentionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ListLegalHoldsRequest {
+            operator_id: operator_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+        });
+
+        let response = client.list_legal_holds(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 分页查询审计记录
+    pub async fn list_retention_audits(
+        &self,
+        operator_id: &str,
+        tenant_id: &str,
+        page: i64,
+        page_size: i64,
+    ) -> Result<ListRetentionAuditsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = RetentionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ListRetentionAuditsRequest {
+            operator_id: operator_id.to_string(),
+            tenant_id: tenant_id.to_string(),
+            page,
+            page_size,
+        });
+
+        let response = client.list_retention_audits(request).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -0,0 +1,118 @@
+use std::error::Error as StdError;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use hyper::body::Bytes;
+use tonic::body::BoxBody;
+use tonic::codegen::http::{Request, Response};
+use tonic::transport::Channel;
+use tower::{Service, ServiceExt};
+use tracing::warn;
+
+use crate::grpc_client::GrpcServiceClient;
+
+/// 请求被拒绝后最多重试的次数
+const MAX_REFUSED_RETRIES: usize = 2;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// 自动重试被下线实例拒绝的请求的通道
+///
+/// 服务端下线或连接到期时发送GOAWAY，之后在该连接上发出的请求不会被处理，
+/// 这类请求以及连接被拒绝的请求可以安全地重发：移除该实例后换一个实例重试。
+/// 请求体缓存在内存中以便重发，只适用于一元调用
+#[derive(Clone)]
+pub struct RetryChannel {
+    client: GrpcServiceClient,
+    endpoint: String,
+    channel: Channel,
+}
+
+impl RetryChannel {
+    pub(crate) fn new(client: GrpcServiceClient, endpoint: String, channel: Channel) -> Self {
+        Self {
+            client,
+            endpoint,
+            channel,
+        }
+    }
+}
+
+impl Service<Request<BoxBody>> for RetryChannel {
+    type Response = Response<hyper::Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.channel.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+        // 使用已就绪的通道发送第一次请求
+        let clone = self.channel.clone();
+        let mut channel = std::mem::replace(&mut self.channel, clone);
+        let mut endpoint = self.endpoint.clone();
+        let client = self.client.clone();
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let body = hyper::body::to_bytes(body).await?;
+
+            let mut retries = 0;
+            loop {
+                let request = rebuild(&parts, body.clone());
+                match channel.call(request).await {
+                    Err(err) if retries < MAX_REFUSED_RETRIES && is_refused(&err) => {
+                        retries += 1;
+                        warn!(
+                            "请求 {} 被实例 {} 拒绝，第 {} 次重试: {}",
+                            parts.uri.path(),
+                            endpoint,
+                            retries,
+                            err
+                        );
+                        client.evict_channel(&endpoint).await;
+                        let (next_endpoint, next_channel) =
+                            client.pick_channel().await.map_err(BoxError::from)?;
+                        endpoint = next_endpoint;
+                        channel = next_channel;
+                        channel.ready().await?;
+                    }
+                    result => return result.map_err(Into::into),
+                }
+            }
+        })
+    }
+}
+
+/// 用缓存的请求体重新构造请求
+fn rebuild(parts: &tonic::codegen::http::request::Parts, body: Bytes) -> Request<BoxBody> {
+    let mut request = Request::new(tonic::body::boxed(hyper::Body::from(body)));
+    *request.method_mut() = parts.method.clone();
+    *request.uri_mut() = parts.uri.clone();
+    *request.version_mut() = parts.version;
+    *request.headers_mut() = parts.headers.clone();
+    request
+}
+
+/// 请求是否确定没有被服务端处理：被GOAWAY拒绝、连接关闭前未发出或连接被拒绝
+fn is_refused(err: &(dyn StdError + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            return err.reason() == Some(h2::Reason::REFUSED_STREAM)
+                || (err.is_go_away() && err.reason() == Some(h2::Reason::NO_ERROR));
+        }
+        if let Some(err) = err.downcast_ref::<hyper::Error>() {
+            if err.is_canceled() {
+                return true;
+            }
+        }
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            return err.kind() == std::io::ErrorKind::ConnectionRefused;
+        }
+        source = err.source();
+    }
+    false
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::storage::storage_service_client::StorageServiceClient;
+use crate::proto::storage::{
+    CompleteUploadRequest, CompleteUploadResponse, CreateUploadUrlRequest,
+    CreateUploadUrlResponse, GetStorageUsageRequest, ReleaseAttachmentsRequest,
+    ReleaseAttachmentsResponse, StorageUsage,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 附件存储配额服务gRPC客户端
+#[derive(Clone)]
+pub struct StorageGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl StorageGrpcClient {
+    /// 创建新的附件存储配额服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 附件存储配额服务目前由用户服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("user-service");
+        Self::new(service_client)
+    }
+
+    /// 申请附件的签名上传地址
+    pub async fn create_upload_url(
+        &self,
+        request: CreateUploadUrlRequest,
+    ) -> Result<CreateUploadUrlResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = StorageServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.create_upload_url(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 确认附件已上传完成
+    pub async fn complete_upload(
+        &self,
+        user_id: &str,
+        object_key: &str,
+    ) -> Result<CompleteUploadResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = StorageServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(CompleteUploadRequest {
+            user_id: user_id.to_string(),
+            object_key: object_key.to_string(),
+        });
+
+        let response = client.complete_upload(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询用户的附件存储用量
+    pub async fn get_storage_usage(&self, user_id: &str) -> Result<StorageUsage> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = StorageServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetStorageUsageRequest {
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_storage_usage(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 释放附件占用的存储空间并删除文件
+    pub async fn release_attachments(
+        &self,
+        object_keys: Vec<String>,
+    ) -> Result<ReleaseAttachmentsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = StorageServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ReleaseAttachmentsRequest { object_keys });
+
+        let response = client.release_attachments(request).await?;
+        Ok(response.into_inner())
+    }
+}
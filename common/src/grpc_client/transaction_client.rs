@@ -0,0 +1,67 @@
+use anyhow::Result;
+use tonic::Request;
+
+use crate::proto::transaction::transaction_service_client::TransactionServiceClient;
+use crate::proto::transaction::{
+    ClaimTransactionRequest, ClaimTransactionResponse, GetTransactionRequest, TransactionResponse,
+};
+
+use crate::grpc::service_auth::authorize_outbound;
+use crate::grpc_client::GrpcServiceClient;
+
+/// 事务消息服务gRPC客户端
+#[derive(Clone)]
+pub struct TransactionGrpcClient {
+    service_client: GrpcServiceClient,
+}
+
+impl TransactionGrpcClient {
+    /// 创建新的事务消息服务客户端
+    pub fn new(service_client: GrpcServiceClient) -> Self {
+        Self { service_client }
+    }
+
+    /// 从环境变量创建客户端
+    ///
+    /// 事务消息服务由消息服务承载
+    pub fn from_env() -> Self {
+        let service_client = GrpcServiceClient::from_env("msg-server");
+        Self::new(service_client)
+    }
+
+    /// 查询事务消息的状态和领取记录
+    pub async fn get_transaction(
+        &self,
+        transaction_id: &str,
+        user_id: &str,
+    ) -> Result<TransactionResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = TransactionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetTransactionRequest {
+            transaction_id: transaction_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_transaction(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 领取一份
+    pub async fn claim_transaction(
+        &self,
+        transaction_id: &str,
+        user_id: &str,
+    ) -> Result<ClaimTransactionResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = TransactionServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ClaimTransactionRequest {
+            transaction_id: transaction_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.claim_transaction(request).await?;
+        Ok(response.into_inner())
+    }
+}
@@ -4,9 +4,15 @@ use tonic::Request;
 use crate::proto::user::user_service_client::UserServiceClient;
 use crate::proto::user::{
     CreateUserRequest, GetUserByIdRequest, GetUserByUsernameRequest, UpdateUserRequest,
-    UserResponse, ForgetPasswordRequest, RegisterRequest, VerifyPasswordRequest, VerifyPasswordResponse, SearchUsersRequest, SearchUsersResponse
+    UserResponse, ForgetPasswordRequest, RegisterRequest, VerifyPasswordRequest, VerifyPasswordResponse, SearchUsersRequest, SearchUsersResponse,
+    GetUsersByIdsRequest, GetUsersByIdsResponse, GetUserConfigRequest, UpdateUserConfigRequest, UserConfigResponse,
+    EvaluateLoginRequest, EvaluateLoginResponse, VerifyLoginChallengeRequest, ListUserDevicesRequest, ListUserDevicesResponse,
+    CheckAvailabilityRequest, CheckAvailabilityResponse, SendPhoneInviteRequest, SendPhoneInviteResponse, ResolveContactCardRequest, ContactCardResponse,
+    GetPublicProfileRequest, PublicProfileResponse, UnsubscribeEmailDigestRequest, UnsubscribeEmailDigestResponse,
+    SetConversationSearchOptOutRequest, GetSearchExclusionsRequest, SearchExclusionsResponse
 };
 
+use crate::grpc::service_auth::authorize_outbound;
 use crate::grpc_client::GrpcServiceClient;
 
 /// 用户服务gRPC客户端
@@ -30,7 +36,7 @@ impl UserServiceGrpcClient {
     /// 获取用户
     pub async fn get_user(&self, user_id: &str) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetUserByIdRequest {
             user_id: user_id.to_string(),
@@ -43,7 +49,7 @@ impl UserServiceGrpcClient {
     /// 按用户名获取用户
     pub async fn get_user_by_username(&self, username: &str) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(GetUserByUsernameRequest {
             username: username.to_string(),
@@ -56,7 +62,7 @@ impl UserServiceGrpcClient {
     /// 创建用户
     pub async fn create_user(&self, request: CreateUserRequest) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let response = client.create_user(Request::new(request)).await?;
         Ok(response.into_inner())
@@ -65,7 +71,7 @@ impl UserServiceGrpcClient {
     /// 更新用户
     pub async fn update_user(&self, request: UpdateUserRequest) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let response = client.update_user(Request::new(request)).await?;
         Ok(response.into_inner())
@@ -74,31 +80,76 @@ impl UserServiceGrpcClient {
     /// 验证用户密码
     pub async fn verify_password(&self, request: VerifyPasswordRequest) -> Result<VerifyPasswordResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let response = client.verify_password(Request::new(request)).await?;
         Ok(response.into_inner())
     }
 
     /// 搜索用户
-    pub async fn search_users(&self, query: &str, page: i32, page_size: i32) -> Result<SearchUsersResponse> {
+    pub async fn search_users(
+        &self,
+        viewer_id: &str,
+        query: &str,
+        page: i32,
+        page_size: i32,
+    ) -> Result<SearchUsersResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let request = Request::new(SearchUsersRequest {
             query: query.to_string(),
             page,
             page_size,
+            viewer_id: viewer_id.to_string(),
         });
 
         let response = client.search_users(request).await?;
         Ok(response.into_inner())
     }
 
+    /// 注册前检查用户名、手机号是否可用
+    pub async fn check_availability(
+        &self,
+        username: &str,
+        phone: &str,
+    ) -> Result<CheckAvailabilityResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(CheckAvailabilityRequest {
+            username: username.to_string(),
+            phone: phone.to_string(),
+        });
+
+        let response = client.check_availability(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 向手机号发送邀请消息
+    pub async fn send_phone_invite(
+        &self,
+        sender_id: &str,
+        phone: &str,
+        content: &str,
+    ) -> Result<SendPhoneInviteResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(SendPhoneInviteRequest {
+            sender_id: sender_id.to_string(),
+            phone: phone.to_string(),
+            content: content.to_string(),
+        });
+
+        let response = client.send_phone_invite(request).await?;
+        Ok(response.into_inner())
+    }
+
     /// 用户账号密码注册
     pub async fn register_by_username(&self, request: RegisterRequest) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let response = client.register_by_username(Request::new(request)).await?;
         Ok(response.into_inner())
@@ -107,7 +158,7 @@ impl UserServiceGrpcClient {
     /// 用户手机号注册
     pub async fn register_by_phone(&self, request: RegisterRequest) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let response = client.register_by_phone(Request::new(request)).await?;
         Ok(response.into_inner())
@@ -116,9 +167,162 @@ impl UserServiceGrpcClient {
     /// 忘记密码
     pub async fn forget_password(&self, request: ForgetPasswordRequest) -> Result<UserResponse> {
         let channel = self.service_client.get_channel().await?;
-        let mut client = UserServiceClient::new(channel);
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
 
         let response = client.forget_password(Request::new(request)).await?;
         Ok(response.into_inner())
     }
+
+    /// 批量获取用户，最后活跃时间按查看者的权限返回
+    pub async fn get_users_by_ids(&self, viewer_id: &str, user_ids: Vec<String>) -> Result<GetUsersByIdsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetUsersByIdsRequest {
+            viewer_id: viewer_id.to_string(),
+            user_ids,
+        });
+
+        let response = client.get_users_by_ids(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 获取用户配置
+    pub async fn get_user_config(&self, user_id: &str) -> Result<UserConfigResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetUserConfigRequest {
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_user_config(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 更新用户配置
+    pub async fn update_user_config(&self, request: UpdateUserConfigRequest) -> Result<UserConfigResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.update_user_config(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 登录风险评估
+    pub async fn evaluate_login(&self, request: EvaluateLoginRequest) -> Result<EvaluateLoginResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let response = client.evaluate_login(Request::new(request)).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 校验登录二次验证码
+    pub async fn verify_login_challenge(&self, login_id: &str, code: &str) -> Result<UserResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(VerifyLoginChallengeRequest {
+            login_id: login_id.to_string(),
+            code: code.to_string(),
+        });
+
+        let response = client.verify_login_challenge(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 查询用户登录过的设备
+    pub async fn list_user_devices(&self, user_id: &str) -> Result<ListUserDevicesResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ListUserDevicesRequest {
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.list_user_devices(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 解析名片，查看者无权查看时返回 PermissionDenied
+    pub async fn resolve_contact_card(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+    ) -> Result<ContactCardResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(ResolveContactCardRequest {
+            viewer_id: viewer_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.resolve_contact_card(request).await?;
+        Ok(response.into_inner())
+    }
+    /// 获取查看者可见的公开资料，用户不存在或对查看者不可见时返回 NotFound
+    pub async fn get_public_profile(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+    ) -> Result<PublicProfileResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetPublicProfileRequest {
+            viewer_id: viewer_id.to_string(),
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_public_profile(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 通过摘要邮件中的退订令牌关闭离线摘要邮件
+    pub async fn unsubscribe_email_digest(&self, token: &str) -> Result<UnsubscribeEmailDigestResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(UnsubscribeEmailDigestRequest {
+            token: token.to_string(),
+        });
+
+        let response = client.unsubscribe_email_digest(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 设置会话是否排除在消息搜索之外
+    pub async fn set_conversation_search_opt_out(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        opt_out: bool,
+    ) -> Result<SearchExclusionsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(SetConversationSearchOptOutRequest {
+            user_id: user_id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            opt_out,
+        });
+
+        let response = client.set_conversation_search_opt_out(request).await?;
+        Ok(response.into_inner())
+    }
+
+    /// 获取用户的消息搜索排除设置
+    pub async fn get_search_exclusions(&self, user_id: &str) -> Result<SearchExclusionsResponse> {
+        let channel = self.service_client.get_channel().await?;
+        let mut client = UserServiceClient::with_interceptor(channel, authorize_outbound);
+
+        let request = Request::new(GetSearchExclusionsRequest {
+            user_id: user_id.to_string(),
+        });
+
+        let response = client.get_search_exclusions(request).await?;
+        Ok(response.into_inner())
+    }
 }
@@ -0,0 +1,222 @@
+//! 缓存失效总线
+//!
+//! 多实例部署时，各实例的本地内存缓存（用户资料、路由、群成员等）通过 Redis
+//! pub/sub 广播失效事件。每个缓存键维护一个单调递增的版本号，订阅方只处理
+//! 比已见版本更新的事件，因此重复投递或重连后的重放都是安全的。
+
+use crate::config::RedisConfig;
+use crate::{Error, Result};
+use futures::StreamExt;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+/// 失效事件广播频道
+const INVALIDATION_CHANNEL: &str = "cache_invalidation";
+
+/// 失效事件版本号键前缀
+const INVALIDATION_VERSION_PREFIX: &str = "cache_invalidation:version";
+
+/// 本地事件广播缓冲区大小
+const LOCAL_BUFFER_SIZE: usize = 1024;
+
+/// 版本表的最大条目数，超出后清空（清空只会导致少量重复失效，不影响正确性）
+const MAX_TRACKED_VERSIONS: usize = 100_000;
+
+/// 订阅断开后的重连间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(3);
+
+/// 缓存失效事件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InvalidationEvent {
+    /// 用户资料变更
+    UserProfile { user_id: String },
+    /// 群组成员变更
+    GroupMembers { group_id: String },
+    /// 网关路由配置变更
+    Route { route_id: String },
+}
+
+impl InvalidationEvent {
+    /// 事件对应的缓存键，版本号按该键独立递增
+    pub fn cache_key(&self) -> String {
+        match self {
+            InvalidationEvent::UserProfile { user_id } => format!("user_profile:{}", user_id),
+            InvalidationEvent::GroupMembers { group_id } => format!("group_members:{}", group_id),
+            InvalidationEvent::Route { route_id } => format!("route:{}", route_id),
+        }
+    }
+}
+
+/// 在频道上传输的失效消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidationMessage {
+    /// 发布者实例ID
+    pub source: String,
+    /// 该缓存键的版本号
+    pub version: u64,
+    /// 失效事件
+    pub event: InvalidationEvent,
+}
+
+/// 缓存失效总线
+#[derive(Clone)]
+pub struct InvalidationBus {
+    client: redis::Client,
+    channel: String,
+    version_prefix: String,
+    instance_id: String,
+    sender: broadcast::Sender<InvalidationEvent>,
+    versions: Arc<Mutex<HashMap<String, u64>>>,
+}
+
+impl InvalidationBus {
+    /// 根据Redis配置创建失效总线，频道与版本键都带有配置的键前缀
+    pub fn new(config: &RedisConfig) -> Result<Self> {
+        let client = redis::Client::open(config.url())?;
+        let prefix = config.key_prefix();
+        let (sender, _) = broadcast::channel(LOCAL_BUFFER_SIZE);
+
+        Ok(Self {
+            client,
+            channel: format!("{}{}", prefix, INVALIDATION_CHANNEL),
+            version_prefix: format!("{}{}", prefix, INVALIDATION_VERSION_PREFIX),
+            instance_id: uuid::Uuid::new_v4().to_string(),
+            sender,
+            versions: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// 发布失效事件，返回该事件分配到的版本号
+    pub async fn publish(&self, event: InvalidationEvent) -> Result<u64> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+
+        let version_key = format!("{}:{}", self.version_prefix, event.cache_key());
+        let version: u64 = conn.incr(&version_key, 1).await?;
+
+        let message = InvalidationMessage {
+            source: self.instance_id.clone(),
+            version,
+            event,
+        };
+        let payload = serde_json::to_string(&message)?;
+        let _: () = conn.publish(&self.channel, payload).await?;
+
+        debug!("发布缓存失效事件: {:?}", message);
+        Ok(version)
+    }
+
+    /// 订阅本地失效事件，缓存在收到事件后移除对应条目
+    pub fn subscribe(&self) -> broadcast::Receiver<InvalidationEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 启动后台订阅任务，断线后自动重连
+    pub fn start(&self) -> tokio::task::JoinHandle<()> {
+        let bus = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = bus.run().await {
+                    error!("缓存失效订阅中断: {}", e);
+                }
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+            }
+        })
+    }
+
+    async fn run(&self) -> Result<()> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(&self.channel).await?;
+        info!("已订阅缓存失效频道: {}", self.channel);
+
+        let mut stream = pubsub.on_message();
+        while let Some(msg) = stream.next().await {
+            let payload: String = match msg.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("无法读取缓存失效消息: {}", e);
+                    continue;
+                }
+            };
+
+            match serde_json::from_str::<InvalidationMessage>(&payload) {
+                Ok(message) => self.dispatch(message)?,
+                Err(e) => warn!("无法解析缓存失效消息: {}", e),
+            }
+        }
+
+        Err(Error::Internal("缓存失效订阅流已关闭".to_string()))
+    }
+
+    /// 按版本号去重后分发给本地订阅者
+    fn dispatch(&self, message: InvalidationMessage) -> Result<()> {
+        if !self.accept(&message.event.cache_key(), message.version) {
+            debug!("忽略过期的缓存失效事件: {:?}", message);
+            return Ok(());
+        }
+
+        // 没有本地订阅者时发送会失败，这种情况可以忽略
+        if self.sender.receiver_count() > 0 {
+            self.sender
+                .send(message.event)
+                .map_err(|e| Error::BroadCastError(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    /// 版本号大于已见版本时接受事件并记录
+    fn accept(&self, cache_key: &str, version: u64) -> bool {
+        let mut versions = self.versions.lock().unwrap();
+        if versions.len() >= MAX_TRACKED_VERSIONS {
+            versions.clear();
+        }
+
+        let seen = versions.entry(cache_key.to_string()).or_insert(0);
+        if version <= *seen {
+            return false;
+        }
+        *seen = version;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> RedisConfig {
+        RedisConfig {
+            host: "127.0.0.1".to_string(),
+            port: 6379,
+            seq_step: 0,
+            max_connections: None,
+            pool_timeout_ms: None,
+            connection_timeout_ms: None,
+            key_prefix: Some("test".to_string()),
+            migrate_legacy_keys: false,
+        }
+    }
+
+    #[test]
+    fn test_replayed_versions_are_ignored() {
+        let bus = InvalidationBus::new(&test_config()).unwrap();
+        let key = InvalidationEvent::UserProfile {
+            user_id: "1".to_string(),
+        }
+        .cache_key();
+
+        assert!(bus.accept(&key, 1));
+        assert!(bus.accept(&key, 3));
+        // 重放或乱序到达的旧版本
+        assert!(!bus.accept(&key, 3));
+        assert!(!bus.accept(&key, 2));
+        // 不同键的版本互不影响
+        assert!(bus.accept("group_members:1", 1));
+        assert_eq!(bus.channel, "test:cache_invalidation");
+    }
+}
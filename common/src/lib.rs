@@ -1,14 +1,32 @@
+pub mod attachment;
+pub mod client_version;
 pub mod config;
+pub mod contact_card;
+pub mod conversation_seq;
+pub mod dependency_health;
 pub mod error;
 pub mod grpc;
 pub mod grpc_client;
+pub mod invalidation;
 pub mod logging;
 pub mod message;
 pub mod models;
+pub mod ops_metrics;
 pub mod proto;
+pub mod pseudonym;
+pub mod region;
+pub mod runtime;
+pub mod schema_migration;
+pub mod seq_fallback;
 pub mod service_registry;
+pub mod tenant_db;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod time_sync;
+pub mod transaction;
 pub mod types;
 pub mod utils;
+pub mod validation;
 
 pub use error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -19,9 +19,11 @@ pub struct Msg {
     pub local_id: ::prost::alloc::string::String,
     #[prost(string, tag = "4")]
     pub server_id: ::prost::alloc::string::String,
-    /// timestamp
+    /// time the client created the message, client local clock, millis
     #[prost(int64, tag = "5")]
     pub create_time: i64,
+    /// time the server accepted the message, UTC millis,
+    /// all server side timestamps use `time_sync::now_millis`
     #[prost(int64, tag = "6")]
     pub send_time: i64,
     /// receiver sequence
@@ -57,6 +59,24 @@ pub struct Msg {
     /// / send sequence
     #[prost(int64, tag = "20")]
     pub send_seq: i64,
+    /// tenant of the sender, filled by the gateway from the connection's token,
+    /// never read from or written to clients
+    #[serde(skip)]
+    #[prost(string, tag = "21")]
+    pub tenant_id: ::prost::alloc::string::String,
+    /// app specific metadata (e.g. order id), opaque to the server,
+    /// persisted and delivered as is, size limited by `message_limits`
+    #[serde(default)]
+    #[prost(map = "string, string", tag = "22")]
+    pub extensions: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// per-conversation sequence, allocated by msg-server when
+    /// `conversation_seq.enabled`, 0 when not allocated
+    #[serde(default)]
+    #[prost(int64, tag = "23")]
+    pub conv_seq: i64,
 }
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -586,6 +606,20 @@ pub struct SendMsgRequest {
     #[prost(message, optional, tag = "1")]
     pub message: ::core::option::Option<Msg>,
 }
+/// 批量发送消息请求，供机器人和系统通知使用，全部消息的发送者和租户必须相同
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SendBatchRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: ::prost::alloc::vec::Vec<Msg>,
+}
+/// 批量发送消息响应，结果与请求中的消息一一对应，未能投递的消息在err中说明原因
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SendBatchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub results: ::prost::alloc::vec::Vec<MsgResponse>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SendGroupMsgRequest {
@@ -593,10 +627,17 @@ pub struct SendGroupMsgRequest {
     pub message: ::core::option::Option<Msg>,
     #[prost(message, repeated, tag = "2")]
     pub members: ::prost::alloc::vec::Vec<GroupMemSeq>,
+    /// 重试推送时为 true，网关不再转发给发送者的其他设备
+    #[prost(bool, tag = "3")]
+    pub skip_sender: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct SendMsgResponse {}
+pub struct SendMsgResponse {
+    /// 群聊推送时由本网关成功送达的成员ID
+    #[prost(string, repeated, tag = "1")]
+    pub delivered: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
 #[derive(serde::Serialize, serde::Deserialize)]
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -609,6 +650,10 @@ pub struct MsgResponse {
     pub send_time: i64,
     #[prost(string, tag = "4")]
     pub err: ::prost::alloc::string::String,
+    /// Kafka不可用时消息已暂存到服务端本地，代理恢复后补发
+    #[serde(default)]
+    #[prost(bool, tag = "5")]
+    pub degraded: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -754,6 +799,10 @@ pub enum ContentType {
     VideoCall = 7,
     AudioCall = 8,
     Error = 9,
+    ContactCard = 10,
+    Poll = 11,
+    Transactional = 12,
+    Recall = 13,
 }
 impl ContentType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -772,6 +821,10 @@ impl ContentType {
             ContentType::VideoCall => "VideoCall",
             ContentType::AudioCall => "AudioCall",
             ContentType::Error => "Error",
+            ContentType::ContactCard => "ContactCard",
+            ContentType::Poll => "Poll",
+            ContentType::Transactional => "Transactional",
+            ContentType::Recall => "Recall",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -787,6 +840,10 @@ impl ContentType {
             "VideoCall" => Some(Self::VideoCall),
             "AudioCall" => Some(Self::AudioCall),
             "Error" => Some(Self::Error),
+            "ContactCard" => Some(Self::ContactCard),
+            "Poll" => Some(Self::Poll),
+            "Transactional" => Some(Self::Transactional),
+            "Recall" => Some(Self::Recall),
             _ => None,
         }
     }
@@ -864,6 +921,8 @@ pub enum MsgType {
     Notification = 25,
     Service = 26,
     FriendshipReceived = 27,
+    /// / typing indicator, not persisted and expires quickly
+    Typing = 28,
 }
 impl MsgType {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -900,6 +959,7 @@ impl MsgType {
             MsgType::Notification => "MsgTypeNotification",
             MsgType::Service => "MsgTypeService",
             MsgType::FriendshipReceived => "MsgTypeFriendshipReceived",
+            MsgType::Typing => "MsgTypeTyping",
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -933,6 +993,7 @@ impl MsgType {
             "MsgTypeNotification" => Some(Self::Notification),
             "MsgTypeService" => Some(Self::Service),
             "MsgTypeFriendshipReceived" => Some(Self::FriendshipReceived),
+            "MsgTypeTyping" => Some(Self::Typing),
             _ => None,
         }
     }
@@ -1249,6 +1310,23 @@ pub mod chat_service_client {
                 .insert(GrpcMethod::new("message.ChatService", "SendMsg"));
             self.inner.unary(req, path, codec).await
         }
+        pub async fn send_batch(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SendBatchRequest>,
+        ) -> std::result::Result<tonic::Response<super::SendBatchResponse>, tonic::Status> {
+            self.inner.ready().await.map_err(|e| {
+                tonic::Status::new(
+                    tonic::Code::Unknown,
+                    format!("Service was not ready: {}", e.into()),
+                )
+            })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/message.ChatService/SendBatch");
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("message.ChatService", "SendBatch"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -1520,6 +1598,11 @@ pub mod chat_service_server {
             &self,
             request: tonic::Request<super::SendMsgRequest>,
         ) -> std::result::Result<tonic::Response<super::MsgResponse>, tonic::Status>;
+        /// 批量发送消息，逐条返回结果
+        async fn send_batch(
+            &self,
+            request: tonic::Request<super::SendBatchRequest>,
+        ) -> std::result::Result<tonic::Response<super::SendBatchResponse>, tonic::Status>;
     }
     /// / chat service, receive message then generate message id and send message to
     /// / mq; response operation result;
@@ -1638,6 +1721,45 @@ pub mod chat_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/message.ChatService/SendBatch" => {
+                    #[allow(non_camel_case_types)]
+                    struct SendBatchSvc<T: ChatService>(pub Arc<T>);
+                    impl<T: ChatService> tonic::server::UnaryService<super::SendBatchRequest> for SendBatchSvc<T> {
+                        type Response = super::SendBatchResponse;
+                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SendBatchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut =
+                                async move { <T as ChatService>::send_batch(&inner, request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SendBatchSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => Box::pin(async move {
                     Ok(http::Response::builder()
                         .status(200)
@@ -0,0 +1,101 @@
+//! 运维看板实时指标
+//!
+//! 每个消息服务实例按 [`OpsMetricsConfig`](crate::config::OpsMetricsConfig) 的间隔上报
+//! [`ConsumerSample`]，网关汇总各实例的采样和在线人数得到 [`OpsSnapshot`] 推送给运维看板
+
+use serde::{Deserialize, Serialize};
+
+/// 单个消息服务实例的消费采样
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsumerSample {
+    /// 实例ID
+    pub instance_id: String,
+    /// 采样时间（UTC毫秒）
+    pub sampled_at: i64,
+    /// 采样间隔内每秒处理的消息数
+    pub msgs_per_sec: f64,
+    /// 已分配分区的消费积压（消息数），无法获取水位时为空
+    pub consumer_lag: Option<i64>,
+}
+
+/// 推送给运维看板的平台指标
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpsSnapshot {
+    /// 采样时间（UTC毫秒）
+    pub timestamp: i64,
+    /// 在线人数，Redis不可用时为空
+    pub online_count: Option<i64>,
+    /// 全部消息服务实例每秒处理的消息数
+    pub msgs_per_sec: f64,
+    /// 全部消息服务实例的消费积压，没有实例上报积压时为空
+    pub consumer_lag: Option<i64>,
+    /// 仍在上报的消息服务实例数
+    pub consumers: usize,
+}
+
+impl OpsSnapshot {
+    /// 汇总消息服务实例的采样，超过 `max_age_ms` 未上报的实例视为已下线
+    pub fn aggregate(
+        timestamp: i64,
+        online_count: Option<i64>,
+        samples: &[ConsumerSample],
+        max_age_ms: i64,
+    ) -> Self {
+        let live: Vec<&ConsumerSample> = samples
+            .iter()
+            .filter(|sample| timestamp - sample.sampled_at <= max_age_ms)
+            .collect();
+
+        let msgs_per_sec = live.iter().map(|sample| sample.msgs_per_sec).sum::<f64>();
+        let consumer_lag = live
+            .iter()
+            .filter_map(|sample| sample.consumer_lag)
+            .reduce(|a, b| a + b);
+
+        Self {
+            timestamp,
+            online_count,
+            // 保留两位小数，避免看板显示浮点误差
+            msgs_per_sec: (msgs_per_sec * 100.0).round() / 100.0,
+            consumer_lag,
+            consumers: live.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(
+        instance_id: &str,
+        sampled_at: i64,
+        msgs_per_sec: f64,
+        lag: Option<i64>,
+    ) -> ConsumerSample {
+        ConsumerSample {
+            instance_id: instance_id.to_string(),
+            sampled_at,
+            msgs_per_sec,
+            consumer_lag: lag,
+        }
+    }
+
+    #[test]
+    fn test_aggregate_skips_stale_instances() {
+        let samples = vec![
+            sample("a", 10_000, 12.5, Some(30)),
+            sample("b", 9_000, 7.333, None),
+            sample("c", 1_000, 100.0, Some(500)),
+        ];
+        let snapshot = OpsSnapshot::aggregate(11_000, Some(42), &samples, 5_000);
+
+        assert_eq!(snapshot.consumers, 2);
+        assert_eq!(snapshot.msgs_per_sec, 19.83);
+        assert_eq!(snapshot.consumer_lag, Some(30));
+        assert_eq!(snapshot.online_count, Some(42));
+
+        let snapshot = OpsSnapshot::aggregate(11_000, None, &samples[1..2], 5_000);
+        assert_eq!(snapshot.consumer_lag, None);
+    }
+}
@@ -41,3 +41,93 @@ pub mod message_gateway {
     // 生成用于反射的文件描述符集
     pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("message_gateway_descriptor");
 }
+
+pub mod job {
+    tonic::include_proto!("job");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("job_descriptor");
+}
+
+pub mod auth {
+    tonic::include_proto!("auth");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("auth_descriptor");
+}
+
+pub mod retention {
+    tonic::include_proto!("retention");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("retention_descriptor");
+}
+
+pub mod backfill {
+    tonic::include_proto!("backfill");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("backfill_descriptor");
+}
+
+pub mod push_stream {
+    tonic::include_proto!("push_stream");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("push_stream_descriptor");
+}
+
+pub mod message_request {
+    tonic::include_proto!("message_request");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("message_request_descriptor");
+}
+
+pub mod draft {
+    tonic::include_proto!("draft");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("draft_descriptor");
+}
+
+pub mod transaction {
+    tonic::include_proto!("transaction");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("transaction_descriptor");
+}
+
+pub mod access_log {
+    tonic::include_proto!("access_log");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("access_log_descriptor");
+}
+
+pub mod mailbox {
+    tonic::include_proto!("mailbox");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("mailbox_descriptor");
+}
+
+pub mod storage {
+    tonic::include_proto!("storage");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("storage_descriptor");
+}
+
+pub mod msg_store {
+    tonic::include_proto!("msg_store");
+
+    // 生成用于反射的文件描述符集
+    pub const FILE_DESCRIPTOR_SET: &[u8] =
+        tonic::include_file_descriptor_set!("msg_store_descriptor");
+}
@@ -0,0 +1,201 @@
+use hmac::{Hmac, Mac};
+use mongodb::bson::Document;
+use sha2::Sha256;
+
+use crate::config::PseudonymConfig;
+use crate::message::{GroupMemSeq, Msg};
+use crate::{Error, Result};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 接收盒文档中以化名保存的ID字段
+pub const PSEUDONYM_FIELDS: [&str; 3] = ["send_id", "receiver_id", "group_id"];
+
+/// 化名保留的HMAC字节数
+const DIGEST_LEN: usize = 16;
+
+/// 密钥的最短字节数
+const MIN_SECRET_LEN: usize = 32;
+
+/// 接收盒ID化名
+///
+/// 用HMAC-SHA256把用户ID和群组ID换成不可逆的化名作为MongoDB的查询键，接收盒数据泄露时
+/// 无法直接对应到用户身份；化名带密钥ID前缀，密钥轮换期间用全部密钥的化名查询，
+/// 旧密钥写入的数据仍然可读。未启用时化名就是原ID
+#[derive(Clone, Default)]
+pub struct IdPseudonymizer {
+    // 写入使用的密钥排在第一位
+    keys: Vec<(String, Vec<u8>)>,
+}
+
+impl IdPseudonymizer {
+    pub fn from_config(config: &PseudonymConfig) -> Result<Self> {
+        if !config.enabled {
+            return Ok(Self::default());
+        }
+
+        let mut keys = Vec::with_capacity(config.keys.len());
+        for key in &config.keys {
+            if key.id.is_empty() || key.id.contains('.') {
+                return Err(Error::Internal(format!("化名密钥ID {:?} 无效", key.id)));
+            }
+            let secret = match (&key.secret_env, &key.secret) {
+                (Some(env), _) => std::env::var(env).map_err(|_| {
+                    Error::Internal(format!("化名密钥 {} 的环境变量 {} 未设置", key.id, env))
+                })?,
+                (None, Some(secret)) => secret.clone(),
+                (None, None) => {
+                    return Err(Error::Internal(format!("化名密钥 {} 未配置", key.id)));
+                }
+            };
+            if secret.len() < MIN_SECRET_LEN {
+                return Err(Error::Internal(format!(
+                    "化名密钥 {} 长度不足 {} 字节",
+                    key.id, MIN_SECRET_LEN
+                )));
+            }
+            keys.push((key.id.clone(), secret.into_bytes()));
+        }
+
+        let active = keys
+            .iter()
+            .position(|(id, _)| *id == config.active_key)
+            .ok_or_else(|| Error::Internal(format!("化名密钥 {} 不存在", config.active_key)))?;
+        keys.swap(0, active);
+        Ok(Self { keys })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// 写入时使用的化名，空ID保持为空
+    pub fn pseudonymize(&self, id: &str) -> String {
+        match self.keys.first() {
+            Some((key_id, secret)) if !id.is_empty() => digest(key_id, secret, id),
+            _ => id.to_string(),
+        }
+    }
+
+    /// 查询时使用的化名，包含轮换期间的全部密钥
+    pub fn candidates(&self, id: &str) -> Vec<String> {
+        if self.keys.is_empty() || id.is_empty() {
+            return vec![id.to_string()];
+        }
+        self.keys
+            .iter()
+            .map(|(key_id, secret)| digest(key_id, secret, id))
+            .collect()
+    }
+
+    /// 将接收盒文档中的ID字段替换为化名，写入MongoDB前调用
+    pub fn pseudonymize_document(&self, document: &mut Document) {
+        if !self.is_enabled() {
+            return;
+        }
+        for field in PSEUDONYM_FIELDS {
+            if let Some(pseudonym) = document.get_str(field).ok().map(|id| self.pseudonymize(id)) {
+                document.insert(field, pseudonym);
+            }
+        }
+    }
+
+    /// 将消息中的ID字段替换为化名，通过消息盒子仓库写入前调用
+    pub fn pseudonymize_msg(&self, msg: &mut Msg) {
+        if !self.is_enabled() {
+            return;
+        }
+        msg.send_id = self.pseudonymize(&msg.send_id);
+        msg.receiver_id = self.pseudonymize(&msg.receiver_id);
+        msg.group_id = self.pseudonymize(&msg.group_id);
+    }
+
+    /// 将群成员ID替换为化名，群聊消息按成员写入消息盒子前调用
+    pub fn pseudonymize_members(&self, members: &mut [GroupMemSeq]) {
+        if !self.is_enabled() {
+            return;
+        }
+        for member in members {
+            member.mem_id = self.pseudonymize(&member.mem_id);
+        }
+    }
+}
+
+/// 化名格式：`密钥ID.HMAC前16字节的十六进制`
+fn digest(key_id: &str, secret: &[u8], id: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC接受任意长度的密钥");
+    mac.update(id.as_bytes());
+    let bytes = mac.finalize().into_bytes();
+    format!("{}.{}", key_id, hex::encode(&bytes[..DIGEST_LEN]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PseudonymKeyConfig;
+    use mongodb::bson::doc;
+
+    fn key(id: &str) -> PseudonymKeyConfig {
+        PseudonymKeyConfig {
+            id: id.to_string(),
+            secret: Some(format!("{}-0123456789abcdef0123456789abcdef", id)),
+            secret_env: None,
+        }
+    }
+
+    #[test]
+    fn test_pseudonymize() {
+        let disabled = IdPseudonymizer::default();
+        assert_eq!(disabled.pseudonymize("u1"), "u1");
+        assert_eq!(disabled.candidates("u1"), vec!["u1".to_string()]);
+
+        let config = PseudonymConfig {
+            enabled: true,
+            active_key: "k2".to_string(),
+            keys: vec![key("k1"), key("k2")],
+        };
+        let pseudonymizer = IdPseudonymizer::from_config(&config).unwrap();
+        let pseudonym = pseudonymizer.pseudonymize("u1");
+        assert!(pseudonym.starts_with("k2."));
+        assert_eq!(pseudonym.len(), 3 + DIGEST_LEN * 2);
+        assert_eq!(pseudonym, pseudonymizer.pseudonymize("u1"));
+        assert_ne!(pseudonym, pseudonymizer.pseudonymize("u2"));
+        assert_eq!(pseudonymizer.pseudonymize(""), "");
+
+        // 轮换期间旧密钥的化名仍在查询范围内
+        let candidates = pseudonymizer.candidates("u1");
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0], pseudonym);
+        assert!(candidates[1].starts_with("k1."));
+
+        let mut document = doc! { "send_id": "u1", "receiver_id": "u2", "group_id": "", "seq": 1 };
+        pseudonymizer.pseudonymize_document(&mut document);
+        assert_eq!(document.get_str("send_id").unwrap(), pseudonym);
+        assert_eq!(document.get_str("group_id").unwrap(), "");
+        assert_eq!(document.get_i32("seq").unwrap(), 1);
+
+        let mut msg = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "g1".to_string(),
+            group_id: "g1".to_string(),
+            ..Default::default()
+        };
+        pseudonymizer.pseudonymize_msg(&mut msg);
+        assert_eq!(msg.send_id, pseudonym);
+        assert_eq!(msg.group_id, pseudonymizer.pseudonymize("g1"));
+        let mut members = vec![GroupMemSeq {
+            mem_id: "u1".to_string(),
+            ..Default::default()
+        }];
+        pseudonymizer.pseudonymize_members(&mut members);
+        assert_eq!(members[0].mem_id, pseudonym);
+
+        // 密钥过短或当前密钥不存在时拒绝启动
+        let mut invalid = config.clone();
+        invalid.keys[0].secret = Some("short".to_string());
+        assert!(IdPseudonymizer::from_config(&invalid).is_err());
+        let mut invalid = config;
+        invalid.active_key = "k3".to_string();
+        assert!(IdPseudonymizer::from_config(&invalid).is_err());
+    }
+}
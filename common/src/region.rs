@@ -0,0 +1,118 @@
+use crate::config::RegionConfig;
+
+/// 服务注册标签中区域的前缀，如 `region:cn-east`
+pub const REGION_TAG_PREFIX: &str = "region:";
+
+/// 区域的服务注册标签
+pub fn region_tag(region: &str) -> String {
+    format!("{}{}", REGION_TAG_PREFIX, region)
+}
+
+/// 从服务注册标签中读取区域
+pub fn tag_region(tags: &[String]) -> Option<&str> {
+    tags.iter().find_map(|tag| tag.strip_prefix(REGION_TAG_PREFIX))
+}
+
+/// 优先选择同区域的服务实例，同区域没有实例时使用全部实例
+pub fn prefer_region<T>(instances: Vec<(T, Vec<String>)>, region: Option<&str>) -> Vec<T> {
+    let Some(region) = region else {
+        return instances.into_iter().map(|(instance, _)| instance).collect();
+    };
+    let (local, others): (Vec<_>, Vec<_>) = instances
+        .into_iter()
+        .partition(|(_, tags)| tag_region(tags) == Some(region));
+    if local.is_empty() {
+        others.into_iter().map(|(instance, _)| instance).collect()
+    } else {
+        local.into_iter().map(|(instance, _)| instance).collect()
+    }
+}
+
+/// 用户的归属区域
+///
+/// 用户ID按FNV-1a哈希到配置的区域列表，各区域计算结果一致；
+/// 只有归属区域为用户分配消息序号，避免多个区域同时分配产生冲突。未启用多区域时所有用户都属于本区域
+#[derive(Debug, Clone)]
+pub struct HomeRegions {
+    config: RegionConfig,
+}
+
+impl HomeRegions {
+    pub fn new(config: RegionConfig) -> Self {
+        Self { config }
+    }
+
+    /// 本区域名称
+    pub fn local(&self) -> &str {
+        &self.config.name
+    }
+
+    /// 用户的归属区域
+    pub fn home_region(&self, user_id: &str) -> &str {
+        if !self.config.enabled || self.config.regions.is_empty() {
+            return &self.config.name;
+        }
+        let index = fnv1a(user_id.as_bytes()) % self.config.regions.len() as u64;
+        &self.config.regions[index as usize]
+    }
+
+    /// 用户是否归属本区域
+    pub fn is_local(&self, user_id: &str) -> bool {
+        self.home_region(user_id) == self.config.name
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_home_regions() {
+        let disabled = HomeRegions::new(RegionConfig::default());
+        assert!(disabled.is_local("u1"));
+
+        let config = RegionConfig {
+            enabled: true,
+            name: "cn-east".to_string(),
+            regions: vec!["cn-east".to_string(), "eu-west".to_string()],
+            ..Default::default()
+        };
+        let east = HomeRegions::new(config.clone());
+        let west = HomeRegions::new(RegionConfig {
+            name: "eu-west".to_string(),
+            ..config
+        });
+
+        // 两个区域对同一用户的归属判断一致，且每个用户只归属一个区域
+        let ids: Vec<String> = (0..100).map(|i| format!("user-{}", i)).collect();
+        for id in &ids {
+            assert_eq!(east.home_region(id), west.home_region(id));
+            assert_ne!(east.is_local(id), west.is_local(id));
+        }
+        assert!(ids.iter().any(|id| east.is_local(id)));
+        assert!(ids.iter().any(|id| west.is_local(id)));
+    }
+
+    #[test]
+    fn test_prefer_region() {
+        assert_eq!(tag_region(&[region_tag("eu-west")]), Some("eu-west"));
+        assert_eq!(tag_region(&["api".to_string()]), None);
+
+        let instances = || {
+            vec![
+                ("a", vec![region_tag("cn-east")]),
+                ("b", vec![region_tag("eu-west")]),
+                ("c", vec![]),
+            ]
+        };
+        assert_eq!(prefer_region(instances(), Some("eu-west")), vec!["b"]);
+        assert_eq!(prefer_region(instances(), Some("us-west")), vec!["a", "b", "c"]);
+        assert_eq!(prefer_region(instances(), None), vec!["a", "b", "c"]);
+    }
+}
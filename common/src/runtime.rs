@@ -0,0 +1,16 @@
+use tokio::runtime::{Builder, Runtime};
+
+use crate::config::RuntimeTuning;
+
+/// 按配置创建多线程运行时，替代 #[tokio::main]
+pub fn build(service: &str, tuning: &RuntimeTuning) -> std::io::Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all().thread_name(service);
+    if let Some(worker_threads) = tuning.worker_threads.filter(|threads| *threads > 0) {
+        builder.worker_threads(worker_threads);
+    }
+    if let Some(max_blocking_threads) = tuning.max_blocking_threads.filter(|threads| *threads > 0) {
+        builder.max_blocking_threads(max_blocking_threads);
+    }
+    builder.build()
+}
@@ -0,0 +1,297 @@
+//! Redis不可用时的序号分配
+//!
+//! 正常情况下序号由Redis分配，每预留一段序号时把预留的上限记为水位；降级期间从水位之后
+//! 在Postgres中逐个分配，保证不会与Redis已分配的序号重复。降级期间分配过的用户标记为待同步，
+//! Redis恢复后把这些用户的序号同步回Redis，再切回Redis分配
+//!
+//! 是否处于降级模式记录在Postgres中，所有实例共用：Redis不可用的实例持续续期，
+//! 到期后各实例一起切回Redis。水位初始化完成前不允许降级分配
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+use crate::config::AppConfig;
+use crate::Result;
+
+/// 序号类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SeqKind {
+    /// 接收序号
+    Receive,
+    /// 发送序号
+    Send,
+}
+
+impl SeqKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeqKind::Receive => "receive",
+            SeqKind::Send => "send",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "receive" => Some(SeqKind::Receive),
+            "send" => Some(SeqKind::Send),
+            _ => None,
+        }
+    }
+}
+
+/// 降级期间分配过序号、待同步回Redis的用户
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSeq {
+    pub user_id: String,
+    pub kind: SeqKind,
+    pub seq: i64,
+}
+
+/// 所有实例共用的降级状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeqMode {
+    /// 是否有实例的Redis不可用，需要由Postgres分配序号
+    pub degraded: bool,
+    /// 是否已用Redis中的最大序号初始化水位
+    pub seeded: bool,
+}
+
+/// 序号水位和降级分配的存储
+#[async_trait]
+pub trait SeqFallbackStore: Send + Sync + Debug {
+    /// 记录Redis预留的序号上限，只会提高水位
+    ///
+    /// 返回降级期间分配过、尚未同步回Redis的用户和水位，这些用户刚预留的一段可能与降级分配的序号重复
+    async fn record_watermarks(
+        &self,
+        kind: SeqKind,
+        seqs: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>>;
+
+    /// 为每个用户分配下一个序号，返回用户ID和分配的序号；不在降级模式或水位未初始化时返回None
+    async fn allocate(
+        &self,
+        kind: SeqKind,
+        user_ids: &[String],
+    ) -> Result<Option<Vec<(String, i64)>>>;
+
+    /// 取出一批待同步回Redis的序号并清除标记，并发执行时各实例取到不同的用户
+    async fn take_pending(&self, limit: i64) -> Result<Vec<PendingSeq>>;
+
+    /// 同步失败时重新标记为待同步
+    async fn restore_pending(&self, pending: &[PendingSeq]) -> Result<()>;
+
+    /// 清除已同步回Redis的待同步标记，之后又分配过的保留
+    async fn clear_pending(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Result<()>;
+
+    /// 查询所有实例共用的降级状态
+    async fn mode(&self) -> Result<SeqMode>;
+
+    /// 进入或延长降级模式，到期前没有续期时自动退出
+    async fn renew_degraded(&self, lease: Duration) -> Result<()>;
+
+    /// 标记水位已初始化
+    async fn set_seeded(&self) -> Result<()>;
+}
+
+/// Postgres中的序号水位和降级分配
+#[derive(Debug, Clone)]
+pub struct SeqFallback {
+    pool: PgPool,
+    /// 没有水位的用户从第一段之后分配：Redis预留第一段时不报告，不会记录水位
+    first_seq: i64,
+}
+
+impl SeqFallback {
+    /// 序号按用户存放在默认集群，连接在首次使用时建立
+    pub fn new(config: &AppConfig, seq_step: i64) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_lazy(&config.database.url())?;
+        Ok(Self {
+            pool,
+            first_seq: seq_step + 1,
+        })
+    }
+}
+
+#[async_trait]
+impl SeqFallbackStore for SeqFallback {
+    async fn record_watermarks(
+        &self,
+        kind: SeqKind,
+        seqs: &[(String, i64)],
+    ) -> Result<Vec<(String, i64)>> {
+        if seqs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let user_ids: Vec<&str> = seqs.iter().map(|(user_id, _)| user_id.as_str()).collect();
+        let values: Vec<i64> = seqs.iter().map(|(_, seq)| *seq).collect();
+
+        let rows: Vec<(String, i64, bool)> = sqlx::query_as(
+            r#"
+            INSERT INTO message_seq_fallback (user_id, kind, seq)
+            SELECT user_id, $3, seq FROM UNNEST($1::varchar[], $2::bigint[]) AS t(user_id, seq)
+            ON CONFLICT (user_id, kind) DO UPDATE
+            SET seq = GREATEST(message_seq_fallback.seq, EXCLUDED.seq), updated_at = now()
+            RETURNING user_id, seq, dirty
+            "#,
+        )
+        .bind(&user_ids)
+        .bind(&values)
+        .bind(kind.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter(|(_, _, dirty)| *dirty)
+            .map(|(user_id, seq, _)| (user_id, seq))
+            .collect())
+    }
+
+    async fn allocate(
+        &self,
+        kind: SeqKind,
+        user_ids: &[String],
+    ) -> Result<Option<Vec<(String, i64)>>> {
+        if user_ids.is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+
+        // 共享锁住降级状态，退出降级的更新会等进行中的分配完成
+        let seqs: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            WITH mode AS (
+                SELECT id FROM message_seq_mode
+                WHERE id = 1 AND seeded AND degraded_until > now()
+                FOR SHARE
+            )
+            INSERT INTO message_seq_fallback (user_id, kind, seq, dirty)
+            SELECT DISTINCT user_id, $2, $3, TRUE FROM UNNEST($1::varchar[]) AS t(user_id), mode
+            ON CONFLICT (user_id, kind) DO UPDATE
+            SET seq = message_seq_fallback.seq + 1, dirty = TRUE, updated_at = now()
+            RETURNING user_id, seq
+            "#,
+        )
+        .bind(user_ids)
+        .bind(kind.as_str())
+        .bind(self.first_seq)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if seqs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(seqs))
+    }
+
+    async fn take_pending(&self, limit: i64) -> Result<Vec<PendingSeq>> {
+        let rows: Vec<(String, String, i64)> = sqlx::query_as(
+            r#"
+            UPDATE message_seq_fallback SET dirty = FALSE
+            WHERE (user_id, kind) IN (
+                SELECT user_id, kind FROM message_seq_fallback
+                WHERE dirty
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING user_id, kind, seq
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(user_id, kind, seq)| {
+                SeqKind::parse(&kind).map(|kind| PendingSeq { user_id, kind, seq })
+            })
+            .collect())
+    }
+
+    async fn restore_pending(&self, pending: &[PendingSeq]) -> Result<()> {
+        for kind in [SeqKind::Receive, SeqKind::Send] {
+            let user_ids: Vec<&str> = pending
+                .iter()
+                .filter(|p| p.kind == kind)
+                .map(|p| p.user_id.as_str())
+                .collect();
+            if user_ids.is_empty() {
+                continue;
+            }
+            sqlx::query(
+                "UPDATE message_seq_fallback SET dirty = TRUE WHERE kind = $1 AND user_id = ANY($2)",
+            )
+            .bind(kind.as_str())
+            .bind(&user_ids)
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn clear_pending(&self, kind: SeqKind, seqs: &[(String, i64)]) -> Result<()> {
+        if seqs.is_empty() {
+            return Ok(());
+        }
+        let user_ids: Vec<&str> = seqs.iter().map(|(user_id, _)| user_id.as_str()).collect();
+        let values: Vec<i64> = seqs.iter().map(|(_, seq)| *seq).collect();
+
+        sqlx::query(
+            r#"
+            UPDATE message_seq_fallback f SET dirty = FALSE
+            FROM UNNEST($2::varchar[], $3::bigint[]) AS t(user_id, seq)
+            WHERE f.kind = $1 AND f.user_id = t.user_id AND f.seq <= t.seq AND f.dirty
+            "#,
+        )
+        .bind(kind.as_str())
+        .bind(&user_ids)
+        .bind(&values)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mode(&self) -> Result<SeqMode> {
+        let mode: Option<(bool, bool)> = sqlx::query_as(
+            "SELECT degraded_until > now(), seeded FROM message_seq_mode WHERE id = 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(mode
+            .map(|(degraded, seeded)| SeqMode { degraded, seeded })
+            .unwrap_or_default())
+    }
+
+    async fn renew_degraded(&self, lease: Duration) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE message_seq_mode
+            SET degraded_until = GREATEST(degraded_until, now() + make_interval(secs => $1)),
+                updated_at = now()
+            WHERE id = 1
+            "#,
+        )
+        .bind(lease.as_secs_f64())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn set_seeded(&self) -> Result<()> {
+        sqlx::query("UPDATE message_seq_mode SET seeded = TRUE, updated_at = now() WHERE id = 1")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,10 +1,13 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tracing::{info, debug, error};
 
+use crate::region::{prefer_region, region_tag};
+
 /// 服务节点信息
 #[derive(Debug, Serialize, Deserialize)]
 struct ConsulNode {
@@ -30,6 +33,8 @@ struct ConsulService {
     Address: String,
     #[serde(rename = "Port")]
     Port: u32,
+    #[serde(rename = "Tags", default)]
+    Tags: Vec<String>,
 }
 
 /// Consul健康检查信息
@@ -42,12 +47,38 @@ struct ConsulCheck {
 #[derive(Debug, Serialize, Deserialize)]
 struct ConsulHealthResponse(Vec<ConsulServiceWithHealth>);
 
+/// 服务注册与发现接口
+///
+/// 生产环境使用基于Consul的 [`ServiceRegistry`]，单元测试可以使用
+/// `test-util` 特性提供的内存实现。服务和客户端以 `Arc<dyn ServiceRegister>` 持有
+#[async_trait]
+pub trait ServiceRegister: Send + Sync + std::fmt::Debug {
+    /// 注册服务，返回服务实例ID
+    async fn register(
+        &self,
+        service_name: &str,
+        host: &str,
+        port: u32,
+        tags: Vec<String>,
+        health_check_path: &str,
+        health_check_interval: &str,
+    ) -> Result<String>;
+
+    /// 注销本实例注册的服务
+    async fn deregister(&self) -> Result<()>;
+
+    /// 发现服务的健康实例地址
+    async fn discover(&self, service_name: &str) -> Result<Vec<String>>;
+}
+
 /// 服务注册管理器
 #[derive(Clone, Debug)]
 pub struct ServiceRegistry {
     http_client: Client,
     consul_url: String,
     service_id: Arc<RwLock<Option<String>>>,
+    // 本实例所在的区域，注册时写入标签，发现服务时优先选择同区域的实例
+    region: Option<String>,
 }
 
 impl ServiceRegistry {
@@ -62,14 +93,29 @@ impl ServiceRegistry {
             http_client,
             consul_url: consul_url.to_string(),
             service_id: Arc::new(RwLock::new(None)),
+            region: None,
         }
     }
 
-    /// 从环境变量创建服务注册管理器
+    /// 从环境变量创建服务注册管理器，SERVICE_REGION 为本实例所在的区域
     pub fn from_env() -> Self {
         let consul_url =
             std::env::var("CONSUL_URL").unwrap_or_else(|_| "http://localhost:8500".to_string());
-        Self::new(&consul_url)
+        let region = std::env::var("SERVICE_REGION").ok();
+        Self::new(&consul_url).with_region(region.as_deref())
+    }
+
+    /// 转换为共享的服务注册接口
+    pub fn shared(self) -> Arc<dyn ServiceRegister> {
+        Arc::new(self)
+    }
+
+    /// 设置本实例所在的区域，为空时保持不变
+    pub fn with_region(mut self, region: Option<&str>) -> Self {
+        if let Some(region) = region.filter(|region| !region.is_empty()) {
+            self.region = Some(region.to_string());
+        }
+        self
     }
 
     /// 注册服务到Consul
@@ -78,10 +124,14 @@ impl ServiceRegistry {
         service_name: &str,
         host: &str,
         port: u32,
-        tags: Vec<String>,
+        mut tags: Vec<String>,
         health_check_path: &str,
         health_check_interval: &str,
     ) -> Result<String> {
+        if let Some(region) = &self.region {
+            tags.push(region_tag(region));
+        }
+
         // 生成唯一服务ID
         let service_id = format!("{}-{}-{}", service_name, host, port);
 
@@ -176,7 +226,7 @@ impl ServiceRegistry {
         Ok(())
     }
 
-    /// 发现服务实例
+    /// 发现服务实例，设置了区域时优先返回同区域的实例
     pub async fn discover_service(&self, service_name: &str) -> Result<Vec<String>> {
         let url = format!("{}/v1/health/service/{}", self.consul_url, service_name);
 
@@ -213,7 +263,7 @@ impl ServiceRegistry {
             }
         };
 
-        let service_urls = services.0.into_iter()
+        let instances = services.0.into_iter()
             .map(|health_entry| {
                 let svc = health_entry.Service;
                 let host = if svc.Address.is_empty() {
@@ -221,10 +271,41 @@ impl ServiceRegistry {
                 } else {
                     svc.Address
                 };
-                format!("http://{}:{}", host, svc.Port)
+                (format!("http://{}:{}", host, svc.Port), svc.Tags)
             })
             .collect();
 
-        Ok(service_urls)
+        Ok(prefer_region(instances, self.region.as_deref()))
+    }
+}
+
+#[async_trait]
+impl ServiceRegister for ServiceRegistry {
+    async fn register(
+        &self,
+        service_name: &str,
+        host: &str,
+        port: u32,
+        tags: Vec<String>,
+        health_check_path: &str,
+        health_check_interval: &str,
+    ) -> Result<String> {
+        self.register_service(
+            service_name,
+            host,
+            port,
+            tags,
+            health_check_path,
+            health_check_interval,
+        )
+        .await
+    }
+
+    async fn deregister(&self) -> Result<()> {
+        self.deregister_service().await
+    }
+
+    async fn discover(&self, service_name: &str) -> Result<Vec<String>> {
+        self.discover_service(service_name).await
     }
 }
@@ -0,0 +1,321 @@
+//! 单元测试辅助工具
+//!
+//! 通过 `test-util` 特性启用，提供消息和用户的测试数据构建器，以及内存版的服务注册实现，
+//! 下游服务测试处理逻辑时不需要启动Consul等基础设施。缓存的内存实现位于 `cache` 的同名特性中，
+//! 推送、消息接收盒和消息存储的内存实现位于 `msg-server` 的同名特性中
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::message::{ContentType, Msg, MsgType, PlatformType};
+use crate::proto::user::User;
+use crate::service_registry::ServiceRegister;
+use crate::time_sync::now_millis;
+
+/// 测试消息构建器，默认构建一条单聊文本消息
+///
+/// ```ignore
+/// let msg = MsgBuilder::single("alice", "bob").text("你好").seq(3).build();
+/// let group_msg = MsgBuilder::group("alice", "group-1").text("大家好").build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MsgBuilder {
+    msg: Msg,
+}
+
+impl MsgBuilder {
+    /// 单聊消息
+    pub fn single(send_id: &str, receiver_id: &str) -> Self {
+        Self::new(send_id, MsgType::SingleMsg).receiver(receiver_id)
+    }
+
+    /// 群聊消息
+    pub fn group(send_id: &str, group_id: &str) -> Self {
+        Self::new(send_id, MsgType::GroupMsg).group_id(group_id)
+    }
+
+    fn new(send_id: &str, msg_type: MsgType) -> Self {
+        let now = now_millis();
+        Self {
+            msg: Msg {
+                send_id: send_id.to_string(),
+                local_id: uuid::Uuid::new_v4().to_string(),
+                server_id: uuid::Uuid::new_v4().to_string(),
+                create_time: now,
+                send_time: now,
+                msg_type: msg_type as i32,
+                content_type: ContentType::Text as i32,
+                platform: PlatformType::Mobile as i32,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn receiver(mut self, receiver_id: &str) -> Self {
+        self.msg.receiver_id = receiver_id.to_string();
+        self
+    }
+
+    pub fn group_id(mut self, group_id: &str) -> Self {
+        self.msg.group_id = group_id.to_string();
+        self
+    }
+
+    pub fn msg_type(mut self, msg_type: MsgType) -> Self {
+        self.msg.msg_type = msg_type as i32;
+        self
+    }
+
+    /// 文本内容，同时把内容类型设为文本
+    pub fn text(mut self, text: &str) -> Self {
+        self.msg.content_type = ContentType::Text as i32;
+        self.msg.content = text.as_bytes().to_vec();
+        self
+    }
+
+    pub fn content(mut self, content_type: ContentType, content: Vec<u8>) -> Self {
+        self.msg.content_type = content_type as i32;
+        self.msg.content = content;
+        self
+    }
+
+    pub fn local_id(mut self, local_id: &str) -> Self {
+        self.msg.local_id = local_id.to_string();
+        self
+    }
+
+    pub fn server_id(mut self, server_id: &str) -> Self {
+        self.msg.server_id = server_id.to_string();
+        self
+    }
+
+    pub fn seq(mut self, seq: i64) -> Self {
+        self.msg.seq = seq;
+        self
+    }
+
+    pub fn send_seq(mut self, send_seq: i64) -> Self {
+        self.msg.send_seq = send_seq;
+        self
+    }
+
+    pub fn conv_seq(mut self, conv_seq: i64) -> Self {
+        self.msg.conv_seq = conv_seq;
+        self
+    }
+
+    pub fn send_time(mut self, send_time: i64) -> Self {
+        self.msg.create_time = send_time;
+        self.msg.send_time = send_time;
+        self
+    }
+
+    pub fn platform(mut self, platform: PlatformType) -> Self {
+        self.msg.platform = platform as i32;
+        self
+    }
+
+    pub fn tenant(mut self, tenant_id: &str) -> Self {
+        self.msg.tenant_id = tenant_id.to_string();
+        self
+    }
+
+    pub fn related(mut self, related_msg_id: &str) -> Self {
+        self.msg.related_msg_id = Some(related_msg_id.to_string());
+        self
+    }
+
+    pub fn extension(mut self, key: &str, value: &str) -> Self {
+        self.msg
+            .extensions
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn build(self) -> Msg {
+        self.msg
+    }
+}
+
+/// 测试用户构建器，默认用户名、昵称和邮箱都由用户ID生成
+#[derive(Debug, Clone)]
+pub struct UserBuilder {
+    user: User,
+}
+
+impl UserBuilder {
+    pub fn new(id: &str) -> Self {
+        Self {
+            user: User {
+                id: id.to_string(),
+                username: id.to_string(),
+                email: format!("{}@example.com", id),
+                nickname: Some(id.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn username(mut self, username: &str) -> Self {
+        self.user.username = username.to_string();
+        self
+    }
+
+    pub fn nickname(mut self, nickname: &str) -> Self {
+        self.user.nickname = Some(nickname.to_string());
+        self
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.user.email = email.to_string();
+        self
+    }
+
+    pub fn phone(mut self, phone: &str) -> Self {
+        self.user.phone = phone.to_string();
+        self
+    }
+
+    pub fn avatar(mut self, avatar_url: &str) -> Self {
+        self.user.avatar_url = Some(avatar_url.to_string());
+        self
+    }
+
+    pub fn tenant(mut self, tenant_id: &str) -> Self {
+        self.user.tenant_id = tenant_id.to_string();
+        self
+    }
+
+    pub fn status(mut self, user_stat: i32) -> Self {
+        self.user.user_stat = user_stat;
+        self
+    }
+
+    pub fn build(self) -> User {
+        self.user
+    }
+}
+
+/// 内存版服务注册，记录注册的实例，发现服务时按注册顺序返回实例地址
+#[derive(Debug, Clone, Default)]
+pub struct MemoryRegistry {
+    // 服务名 -> (实例ID, 实例地址)
+    services: Arc<Mutex<HashMap<String, Vec<(String, String)>>>>,
+    registered: Arc<Mutex<Vec<String>>>,
+}
+
+impl MemoryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 预先添加其他服务的实例地址，模拟已经在运行的服务
+    pub fn with_instance(self, service_name: &str, addr: &str) -> Self {
+        self.services
+            .lock()
+            .unwrap()
+            .entry(service_name.to_string())
+            .or_default()
+            .push((format!("{}-{}", service_name, addr), addr.to_string()));
+        self
+    }
+
+    /// 本实例当前注册的实例ID
+    pub fn registered(&self) -> Vec<String> {
+        self.registered.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl ServiceRegister for MemoryRegistry {
+    async fn register(
+        &self,
+        service_name: &str,
+        host: &str,
+        port: u32,
+        _tags: Vec<String>,
+        _health_check_path: &str,
+        _health_check_interval: &str,
+    ) -> Result<String> {
+        let service_id = format!("{}-{}-{}", service_name, host, port);
+        self.services
+            .lock()
+            .unwrap()
+            .entry(service_name.to_string())
+            .or_default()
+            .push((service_id.clone(), format!("http://{}:{}", host, port)));
+        self.registered.lock().unwrap().push(service_id.clone());
+        Ok(service_id)
+    }
+
+    async fn deregister(&self) -> Result<()> {
+        let registered = std::mem::take(&mut *self.registered.lock().unwrap());
+        if registered.is_empty() {
+            return Err(anyhow!("服务未注册"));
+        }
+        for instances in self.services.lock().unwrap().values_mut() {
+            instances.retain(|(id, _)| !registered.contains(id));
+        }
+        Ok(())
+    }
+
+    async fn discover(&self, service_name: &str) -> Result<Vec<String>> {
+        Ok(self
+            .services
+            .lock()
+            .unwrap()
+            .get(service_name)
+            .map(|instances| instances.iter().map(|(_, addr)| addr.clone()).collect())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc_client::GrpcServiceClient;
+
+    #[test]
+    fn test_msg_builder() {
+        let msg = MsgBuilder::group("alice", "group-1")
+            .text("大家好")
+            .seq(3)
+            .tenant("tenant-a")
+            .build();
+        assert_eq!(msg.msg_type, MsgType::GroupMsg as i32);
+        assert_eq!(msg.group_id, "group-1");
+        assert_eq!(msg.content, "大家好".as_bytes());
+        assert_eq!(msg.seq, 3);
+        assert!(!msg.server_id.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_registry() {
+        let registry = MemoryRegistry::new().with_instance("user-service", "http://10.0.0.1:50001");
+        registry
+            .register("user-service", "127.0.0.1", 50002, vec![], "/health", "10s")
+            .await
+            .unwrap();
+        assert_eq!(registry.discover("user-service").await.unwrap().len(), 2);
+
+        registry.deregister().await.unwrap();
+        assert_eq!(
+            registry.discover("user-service").await.unwrap(),
+            vec!["http://10.0.0.1:50001"]
+        );
+        assert!(registry.discover("group-service").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_discovers_through_injected_registry() {
+        let client =
+            GrpcServiceClient::with_defaults(Arc::new(MemoryRegistry::new()), "user-service");
+        let err = client.refresh_channels().await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("没有发现可用的 user-service 服务实例"));
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+/// 当前UTC时间（毫秒）
+///
+/// 服务端写入消息和对时响应的时间统一使用该函数，保证单位和时区一致
+pub fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// 对时请求，客户端发送时携带本地发送时间
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeSyncRequest {
+    /// 客户端发送请求的本地时间（毫秒）
+    #[serde(default)]
+    pub client_time: Option<i64>,
+}
+
+/// 对时响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeSyncResponse {
+    /// 原样返回客户端发送请求的时间
+    pub client_time: Option<i64>,
+    /// 服务端收到请求的时间（UTC毫秒）
+    pub server_receive_time: i64,
+    /// 服务端发送响应的时间（UTC毫秒）
+    pub server_send_time: i64,
+}
+
+impl TimeSyncResponse {
+    /// 根据收到请求的时间构造响应，发送时间取当前时间
+    pub fn new(request: &TimeSyncRequest, server_receive_time: i64) -> Self {
+        Self {
+            client_time: request.client_time,
+            server_receive_time,
+            server_send_time: now_millis(),
+        }
+    }
+
+    /// 结合客户端收到响应的本地时间得到一次对时采样，请求未携带发送时间时返回None
+    pub fn sample(&self, client_receive_time: i64) -> Option<ClockSample> {
+        Some(ClockSample {
+            client_send: self.client_time?,
+            server_receive: self.server_receive_time,
+            server_send: self.server_send_time,
+            client_receive: client_receive_time,
+        })
+    }
+}
+
+/// 一次对时的四个时间点（毫秒），客户端时间为本地时间，服务端时间为UTC时间
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSample {
+    pub client_send: i64,
+    pub server_receive: i64,
+    pub server_send: i64,
+    pub client_receive: i64,
+}
+
+impl ClockSample {
+    /// 网络往返耗时，不包含服务端处理时间
+    pub fn round_trip(&self) -> i64 {
+        ((self.client_receive - self.client_send) - (self.server_send - self.server_receive)).max(0)
+    }
+
+    /// 服务端时间相对客户端本地时间的偏差，客户端时间加上偏差即为服务端时间
+    ///
+    /// 假设上下行耗时相同，误差不超过往返耗时的一半
+    pub fn offset(&self) -> i64 {
+        ((self.server_receive - self.client_send) + (self.server_send - self.client_receive)) / 2
+    }
+}
+
+/// 从多次采样中取往返耗时最短的一次作为偏差估计，往返越短误差越小
+pub fn estimate_offset(samples: &[ClockSample]) -> Option<i64> {
+    samples
+        .iter()
+        .min_by_key(|sample| sample.round_trip())
+        .map(ClockSample::offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_sample() {
+        // 客户端时钟比服务端慢1000毫秒，单程耗时50毫秒，服务端处理10毫秒
+        let sample = ClockSample {
+            client_send: 0,
+            server_receive: 1050,
+            server_send: 1060,
+            client_receive: 110,
+        };
+        assert_eq!(sample.round_trip(), 100);
+        assert_eq!(sample.offset(), 1000);
+
+        let slow = ClockSample {
+            client_send: 0,
+            server_receive: 1300,
+            server_send: 1300,
+            client_receive: 400,
+        };
+        assert_eq!(estimate_offset(&[slow, sample]), Some(1000));
+        assert_eq!(estimate_offset(&[]), None);
+
+        let response = TimeSyncResponse {
+            client_time: Some(0),
+            server_receive_time: 1050,
+            server_send_time: 1060,
+        };
+        assert_eq!(response.sample(110), Some(sample));
+    }
+}
@@ -0,0 +1,162 @@
+//! 事务消息描述
+//!
+//! 红包、礼物等消息的 content 为 JSON 格式的事务描述：客户端填写外部交易服务名称、份数，
+//! 以及交给外部交易服务的参数（如金额、祝福语）。消息服务在外部交易服务确认后
+//! 写入事务ID和到期时间再投递，接收者凭事务ID领取。
+
+use crate::config::TransactionConfig;
+use crate::message::{ContentType, MsgType};
+use crate::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// 事务描述
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionalContent {
+    /// 外部交易服务名称
+    pub provider: String,
+    /// 可领取的份数
+    pub shares: u32,
+    /// 有效期（秒），不填使用默认值
+    #[serde(default)]
+    pub expire_secs: Option<i64>,
+    /// 交给外部交易服务的参数，IM服务不解析
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    /// 事务ID，由服务端填充
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_id: Option<String>,
+    /// 到期时间（毫秒），由服务端填充
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+impl TransactionalContent {
+    /// 解析并校验消息内容中的事务描述
+    ///
+    /// # 返回
+    /// * 非事务消息返回 `Ok(None)`
+    pub fn from_msg_content(
+        msg_type: i32,
+        content_type: i32,
+        content: &[u8],
+        config: &TransactionConfig,
+    ) -> Result<Option<Self>> {
+        if content_type != ContentType::Transactional as i32 {
+            return Ok(None);
+        }
+
+        let content: Self = serde_json::from_slice(content)
+            .map_err(|e| Error::BadRequest(format!("事务消息格式错误: {}", e)))?;
+        content.validate(msg_type, config)?;
+        Ok(Some(content))
+    }
+
+    /// 校验外部交易服务、份数和有效期
+    pub fn validate(&self, msg_type: i32, config: &TransactionConfig) -> Result<()> {
+        // 已投递的事务消息不能被转发，否则接收者会看到另一个会话的事务
+        if self.transaction_id.is_some() {
+            return Err(Error::BadRequest("事务消息不能转发".to_string()));
+        }
+        if !config.providers.contains_key(&self.provider) {
+            return Err(Error::BadRequest(format!(
+                "未配置的外部交易服务: {}",
+                self.provider
+            )));
+        }
+
+        if msg_type == MsgType::SingleMsg as i32 {
+            if self.shares != 1 {
+                return Err(Error::BadRequest("单聊事务消息只能有一份".to_string()));
+            }
+        } else if msg_type != MsgType::GroupMsg as i32 {
+            return Err(Error::BadRequest(
+                "事务消息只能在单聊或群聊中发送".to_string(),
+            ));
+        }
+        if self.shares == 0 || self.shares > config.max_shares {
+            return Err(Error::BadRequest(format!(
+                "份数需要在 1 到 {} 之间",
+                config.max_shares
+            )));
+        }
+
+        if let Some(expire_secs) = self.expire_secs {
+            if expire_secs <= 0 || expire_secs > config.max_expire_secs {
+                return Err(Error::BadRequest(format!(
+                    "有效期需要在 1 到 {} 秒之间",
+                    config.max_expire_secs
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 有效期（秒）
+    pub fn expire_secs(&self, config: &TransactionConfig) -> i64 {
+        self.expire_secs.unwrap_or(config.default_expire_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TransactionProviderConfig;
+
+    fn config() -> TransactionConfig {
+        let mut config = TransactionConfig::default();
+        config.providers.insert(
+            "redpacket".to_string(),
+            TransactionProviderConfig {
+                endpoint: "http://localhost:9600/im".to_string(),
+                timeout_ms: 1000,
+            },
+        );
+        config
+    }
+
+    fn parse(msg_type: MsgType, content: &str) -> Result<Option<TransactionalContent>> {
+        TransactionalContent::from_msg_content(
+            msg_type as i32,
+            ContentType::Transactional as i32,
+            content.as_bytes(),
+            &config(),
+        )
+    }
+
+    #[test]
+    fn test_from_msg_content() {
+        let content = parse(
+            MsgType::GroupMsg,
+            r#"{"provider":"redpacket","shares":5,"payload":{"amount":1000}}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(content.shares, 5);
+        assert_eq!(content.expire_secs(&config()), 24 * 3600);
+
+        // 单聊只能有一份
+        assert!(parse(MsgType::SingleMsg, r#"{"provider":"redpacket","shares":2}"#).is_err());
+        // 未配置的外部交易服务
+        assert!(parse(MsgType::GroupMsg, r#"{"provider":"other","shares":1}"#).is_err());
+        // 转发已投递的事务消息
+        assert!(parse(
+            MsgType::GroupMsg,
+            r#"{"provider":"redpacket","shares":1,"transactionId":"t1"}"#
+        )
+        .is_err());
+        assert!(parse(
+            MsgType::GroupMsg,
+            r#"{"provider":"redpacket","shares":1,"expireSecs":999999}"#
+        )
+        .is_err());
+
+        let text = TransactionalContent::from_msg_content(
+            MsgType::SingleMsg as i32,
+            ContentType::Text as i32,
+            b"hello",
+            &config(),
+        );
+        assert_eq!(text.unwrap(), None);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::message::{
-    GetDbMessagesRequest, GetDbMsgRequest, GroupMemSeq, Msg, MsgResponse, MsgType,
-    SaveGroupMsgRequest, SaveMessageRequest, SendMsgRequest, UserAndGroupId,
+    ContentType, GetDbMessagesRequest, GetDbMsgRequest, GroupMemSeq, Msg, MsgRead, MsgResponse,
+    MsgType, SaveGroupMsgRequest, SaveMessageRequest, SendMsgRequest, UserAndGroupId,
 };
+use crate::time_sync::now_millis;
 use crate::Error;
-use mongodb::bson::Document;
+use mongodb::bson::spec::BinarySubtype;
+use mongodb::bson::{doc, Binary, Bson, Document};
+use std::collections::HashMap;
 use tonic::Status;
 
 impl From<Status> for MsgResponse {
@@ -13,10 +16,21 @@ impl From<Status> for MsgResponse {
             server_id: String::new(),
             send_time: 0,
             err: status.message().to_string(),
+            degraded: false,
         }
     }
 }
 
+impl MsgResponse {
+    /// 降级接收时回执消息的内容，客户端据此提示消息可能延迟送达
+    pub const ACCEPTED_DEGRADED: &'static str = "accepted-degraded";
+}
+
+impl MsgRead {
+    /// 单聊已读消息的扩展字段，值为读者已读到的对方消息的最大发送序号，消息服务据此向对方转发已读回执
+    pub const READ_SEND_SEQ: &'static str = "read_send_seq";
+}
+
 /// maybe there is the performance issue
 impl TryFrom<Document> for Msg {
     type Error = Error;
@@ -35,6 +49,7 @@ impl TryFrom<Document> for Msg {
             receiver_id: value.get_str("receiver_id").unwrap_or_default().to_string(),
             seq: value.get_i64("seq").unwrap_or_default(),
             send_seq: value.get_i64("send_seq").unwrap_or_default(),
+            conv_seq: value.get_i64("conv_seq").unwrap_or_default(),
             msg_type: value.get_i32("msg_type").unwrap_or_default(),
             is_read: value.get_bool("is_read").unwrap_or_default(),
             group_id: value.get_str("group_id").unwrap_or_default().to_string(),
@@ -44,17 +59,98 @@ impl TryFrom<Document> for Msg {
             related_msg_id: value
                 .get_str("related_msg_id")
                 .map_or(None, |v| Some(v.to_string())),
+            tenant_id: value.get_str("tenant_id").unwrap_or_default().to_string(),
+            extensions: value.get_document("extensions").map_or(HashMap::new(), |v| {
+                v.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect()
+            }),
         })
     }
 }
 
+/// 与 `TryFrom<Document>` 对应，字段名与接收盒中的文档一致
+impl From<&Msg> for Document {
+    fn from(msg: &Msg) -> Self {
+        let extensions: Document = msg
+            .extensions
+            .iter()
+            .map(|(k, v)| (k.clone(), Bson::String(v.clone())))
+            .collect();
+        let mut document = doc! {
+            "local_id": &msg.local_id,
+            "server_id": &msg.server_id,
+            "create_time": msg.create_time,
+            "send_time": msg.send_time,
+            "content_type": msg.content_type,
+            "content": Binary {
+                subtype: BinarySubtype::Generic,
+                bytes: msg.content.clone(),
+            },
+            "send_id": &msg.send_id,
+            "receiver_id": &msg.receiver_id,
+            "seq": msg.seq,
+            "send_seq": msg.send_seq,
+            "conv_seq": msg.conv_seq,
+            "msg_type": msg.msg_type,
+            "is_read": msg.is_read,
+            "group_id": &msg.group_id,
+            "platform": msg.platform,
+            "avatar": &msg.avatar,
+            "nickname": &msg.nickname,
+            "tenant_id": &msg.tenant_id,
+            "extensions": extensions,
+        };
+        if let Some(related_msg_id) = &msg.related_msg_id {
+            document.insert("related_msg_id", related_msg_id);
+        }
+        document
+    }
+}
+
 impl SendMsgRequest {
+    /// 系统通知消息（如安全提醒），由服务端发往用户的所有在线会话
+    pub fn new_with_notification(receiver_id: String, content: Vec<u8>) -> Self {
+        Self {
+            message: Some(Msg {
+                receiver_id,
+                send_time: now_millis(),
+                content,
+                msg_type: MsgType::Notification as i32,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 手机号邀请消息，对方注册后以单聊文本消息投递，local_id使用邀请ID便于客户端去重
+    pub fn new_with_invite(
+        send_id: String,
+        receiver_id: String,
+        invite_id: String,
+        content: Vec<u8>,
+        create_time: i64,
+    ) -> Self {
+        Self {
+            message: Some(Msg {
+                send_id,
+                receiver_id,
+                local_id: invite_id,
+                create_time,
+                send_time: now_millis(),
+                content,
+                msg_type: MsgType::SingleMsg as i32,
+                content_type: ContentType::Text as i32,
+                ..Default::default()
+            }),
+        }
+    }
+
     pub fn new_with_friend_del(send_id: String, receiver_id: String) -> Self {
         Self {
             message: Some(Msg {
                 send_id,
                 receiver_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 msg_type: MsgType::FriendDelete as i32,
                 ..Default::default()
             }),
@@ -72,7 +168,7 @@ impl SendMsgRequest {
                 send_seq,
                 send_id,
                 receiver_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 content: fs,
                 msg_type: MsgType::FriendApplyReq as i32,
                 ..Default::default()
@@ -87,7 +183,7 @@ impl SendMsgRequest {
                 receiver_id,
                 content: fs,
                 msg_type: MsgType::FriendApplyResp as i32,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 ..Default::default()
             }),
         }
@@ -106,7 +202,7 @@ impl SendMsgRequest {
                 send_id,
                 group_id: receiver_id.clone(),
                 receiver_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 msg_type: msg_type as i32,
                 send_seq,
                 ..Default::default()
@@ -125,7 +221,7 @@ impl SendMsgRequest {
                 send_id,
                 group_id: receiver_id.clone(),
                 receiver_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 msg_type: MsgType::GroupInvitation as i32,
                 content: invitation,
                 send_seq,
@@ -145,7 +241,7 @@ impl SendMsgRequest {
                 send_id,
                 group_id: receiver_id.clone(),
                 receiver_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 msg_type: MsgType::GroupInviteNew as i32,
                 content: invitation,
                 send_seq,
@@ -165,7 +261,7 @@ impl SendMsgRequest {
                 send_id,
                 receiver_id: group_id.clone(),
                 group_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 msg_type: MsgType::GroupRemoveMember as i32,
                 content: invitation,
                 send_seq,
@@ -185,7 +281,7 @@ impl SendMsgRequest {
                 send_id,
                 group_id: receiver_id.clone(),
                 receiver_id,
-                send_time: chrono::Utc::now().timestamp_millis(),
+                send_time: now_millis(),
                 msg_type: MsgType::GroupUpdate as i32,
                 content: msg,
                 send_seq,
@@ -0,0 +1,212 @@
+//! 用户输入文本的内容策略
+//!
+//! 好友请求附言、群名称和群公告按 [`ContentPolicyConfig`] 中租户生效的策略统一校验长度、
+//! 允许的字符和敏感词，校验失败返回 [`Error::BadRequest`]
+
+use crate::config::{ContentPolicyConfig, TextPolicy};
+use crate::Error;
+
+/// 需要校验的文本字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    /// 好友请求附言
+    FriendRequest,
+    /// 群名称
+    GroupName,
+    /// 群公告
+    GroupAnnouncement,
+}
+
+impl TextField {
+    fn label(self) -> &'static str {
+        match self {
+            TextField::FriendRequest => "好友请求附言",
+            TextField::GroupName => "群名称",
+            TextField::GroupAnnouncement => "群公告",
+        }
+    }
+}
+
+/// 内容策略
+#[derive(Debug, Clone)]
+pub struct ContentPolicy {
+    config: ContentPolicyConfig,
+}
+
+impl ContentPolicy {
+    pub fn new(config: &ContentPolicyConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    /// 按租户的策略校验文本，返回去除首尾空白、敏感词替换后的文本
+    pub fn check(
+        &self,
+        tenant_id: Option<&str>,
+        field: TextField,
+        text: &str,
+    ) -> Result<String, Error> {
+        let policies = self.config.for_tenant(tenant_id);
+        let policy = match field {
+            TextField::FriendRequest => &policies.friend_request,
+            TextField::GroupName => &policies.group_name,
+            TextField::GroupAnnouncement => &policies.group_announcement,
+        };
+        check_text(policy, field, text)
+    }
+}
+
+fn check_text(policy: &TextPolicy, field: TextField, text: &str) -> Result<String, Error> {
+    let text = text.trim();
+    let label = field.label();
+
+    let length = text.chars().count();
+    if length < policy.min_chars {
+        return Err(Error::BadRequest(if policy.min_chars == 1 {
+            format!("{}不能为空", label)
+        } else {
+            format!("{}不能少于{}个字符", label, policy.min_chars)
+        }));
+    }
+    if length > policy.max_chars {
+        return Err(Error::BadRequest(format!(
+            "{}长度不能超过{}个字符，当前长度: {}",
+            label, policy.max_chars, length
+        )));
+    }
+
+    for c in text.chars() {
+        let newline = c == '\n' || c == '\r';
+        if (newline && !policy.allow_newlines) || (!newline && c.is_control()) {
+            return Err(Error::BadRequest(format!("{}包含不允许的字符", label)));
+        }
+        if policy.disallowed_chars.contains(c) {
+            return Err(Error::BadRequest(format!("{}不能包含字符: {}", label, c)));
+        }
+    }
+
+    let sensitive = sensitive_ranges(text, &policy.sensitive_words);
+    if sensitive.is_empty() {
+        return Ok(text.to_string());
+    }
+    if !policy.mask_sensitive_words {
+        return Err(Error::BadRequest(format!("{}包含敏感词", label)));
+    }
+    Ok(text
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if sensitive
+                .iter()
+                .any(|(start, end)| (*start..*end).contains(&i))
+            {
+                '*'
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+/// 查找文本中全部敏感词的位置（按字符计算的起止下标），不区分大小写
+fn sensitive_ranges(text: &str, words: &[String]) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().map(fold_case).collect();
+    let mut ranges = Vec::new();
+    for word in words {
+        let word: Vec<char> = word.trim().chars().map(fold_case).collect();
+        if word.is_empty() || word.len() > chars.len() {
+            continue;
+        }
+        for start in 0..=chars.len() - word.len() {
+            if chars[start..start + word.len()] == word[..] {
+                ranges.push((start, start + word.len()));
+            }
+        }
+    }
+    ranges
+}
+
+/// 逐字符转小写，保持字符数不变以便按下标替换
+fn fold_case(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TenantContentPolicies;
+
+    #[test]
+    fn test_length_and_chars() {
+        let policy = ContentPolicy::new(&ContentPolicyConfig::default());
+        assert_eq!(
+            policy
+                .check(None, TextField::GroupName, "  技术交流群 ")
+                .unwrap(),
+            "技术交流群"
+        );
+        assert!(policy.check(None, TextField::GroupName, "   ").is_err());
+        assert!(policy
+            .check(None, TextField::FriendRequest, &"好".repeat(256))
+            .is_err());
+        assert!(policy
+            .check(None, TextField::FriendRequest, &"好".repeat(255))
+            .is_ok());
+        // 群名称不允许换行，群公告允许
+        assert!(policy.check(None, TextField::GroupName, "a\nb").is_err());
+        assert!(policy
+            .check(None, TextField::GroupAnnouncement, "第一行\n第二行")
+            .is_ok());
+        assert!(policy
+            .check(None, TextField::GroupAnnouncement, "a\u{0}b")
+            .is_err());
+    }
+
+    #[test]
+    fn test_sensitive_words_and_tenant_override() {
+        let mut config = ContentPolicyConfig::default();
+        config.default.friend_request.sensitive_words = vec!["加微信".to_string()];
+        config.default.group_announcement.sensitive_words = vec!["SPAM".to_string()];
+        config.default.group_announcement.mask_sensitive_words = true;
+        config.tenants.insert(
+            "tenant-a".to_string(),
+            TenantContentPolicies {
+                friend_request: Some(TextPolicy {
+                    max_chars: 10,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        let policy = ContentPolicy::new(&config);
+
+        assert!(policy
+            .check(None, TextField::FriendRequest, "你好，加微信聊")
+            .is_err());
+        assert_eq!(
+            policy
+                .check(None, TextField::GroupAnnouncement, "no spam, Spam!")
+                .unwrap(),
+            "no ****, ****!"
+        );
+
+        // 租户覆盖的策略整条替换默认策略，未覆盖的字段使用默认策略
+        assert!(policy
+            .check(Some("tenant-a"), TextField::FriendRequest, "你好，加微信聊")
+            .is_ok());
+        assert!(policy
+            .check(Some("tenant-a"), TextField::FriendRequest, &"好".repeat(11))
+            .is_err());
+        assert_eq!(
+            policy
+                .check(Some("tenant-a"), TextField::GroupAnnouncement, "spam")
+                .unwrap(),
+            "****"
+        );
+    }
+}
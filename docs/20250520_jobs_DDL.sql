@@ -0,0 +1,32 @@
+-- 异步任务表（导出、导入、归档取回等长耗时操作）
+CREATE TABLE async_jobs
+(
+    id                VARCHAR(36) PRIMARY KEY,                        -- 任务ID
+    user_id           VARCHAR(36)  NOT NULL,                          -- 创建者用户ID
+    kind              VARCHAR(32)  NOT NULL,                          -- 任务类型
+    status            SMALLINT     NOT NULL DEFAULT 0,                -- 状态: 0-排队 1-执行中 2-完成 3-失败
+    progress          INT          NOT NULL DEFAULT 0,                -- 进度 0-100
+    params            TEXT         NOT NULL DEFAULT '{}',             -- 任务参数(JSON)
+    result_key        VARCHAR(255),                                   -- 结果文件在对象存储中的key
+    error             VARCHAR(512),                                   -- 失败原因
+    attempts          INT          NOT NULL DEFAULT 0,                -- 已执行次数
+    created_at        TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at        TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT check_job_status CHECK (status IN (0, 1, 2, 3))
+);
+
+-- 工作进程按状态+创建时间拉取待执行任务
+CREATE INDEX idx_async_jobs_status_created ON async_jobs (status, created_at);
+-- 按用户统计进行中的任务（用于限流）
+CREATE INDEX idx_async_jobs_user_kind ON async_jobs (user_id, kind, status);
+
+CREATE TRIGGER update_async_jobs_modtime
+    BEFORE UPDATE
+    ON async_jobs
+    FOR EACH ROW
+    EXECUTE FUNCTION update_modified_column();
+
+COMMENT ON TABLE async_jobs IS '异步任务表';
+COMMENT ON COLUMN async_jobs.kind IS '任务类型: export_messages, import_contacts, archive_retrieval';
+COMMENT ON COLUMN async_jobs.status IS '任务状态: 0-排队 1-执行中 2-完成 3-失败';
+COMMENT ON COLUMN async_jobs.result_key IS '结果文件key，查询时生成签名URL返回';
@@ -0,0 +1,15 @@
+-- 群组每日统计表（由群组服务每日从Redis汇总写入）
+CREATE TABLE group_daily_stats
+(
+    group_id       VARCHAR(36) NOT NULL,               -- 群组ID
+    stat_date      VARCHAR(8)  NOT NULL,               -- 统计日期 yyyyMMdd (UTC)
+    message_count  BIGINT      NOT NULL DEFAULT 0,     -- 当日消息数
+    active_members BIGINT      NOT NULL DEFAULT 0,     -- 当日发言成员数(近似值)
+    joins          BIGINT      NOT NULL DEFAULT 0,     -- 当日加入人数
+    leaves         BIGINT      NOT NULL DEFAULT 0,     -- 当日退出人数
+    created_at     TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT pk_group_daily_stats PRIMARY KEY (group_id, stat_date)
+);
+
+COMMENT ON TABLE group_daily_stats IS '群组每日统计';
+COMMENT ON COLUMN group_daily_stats.active_members IS '基于HyperLogLog统计的发言成员数，近似值';
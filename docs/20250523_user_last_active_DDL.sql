@@ -0,0 +1,24 @@
+-- 用户最后活跃时间（由网关心跳写入Redis，用户服务定期批量落库）
+ALTER TABLE users
+    ADD COLUMN last_active_at timestamp(6);
+
+COMMENT ON COLUMN "public"."users"."last_active_at" IS '最后活跃时间';
+
+-- 用户配置表
+CREATE TABLE user_config
+(
+    user_id              VARCHAR(36) PRIMARY KEY,                       -- 用户ID
+    last_seen_visibility SMALLINT    NOT NULL DEFAULT 0,                -- 最后活跃时间可见范围: 0-所有人 1-仅好友 2-所有人不可见
+    created_at           TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at           TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT check_last_seen_visibility CHECK (last_seen_visibility IN (0, 1, 2))
+);
+
+CREATE TRIGGER update_user_config_modtime
+    BEFORE UPDATE
+    ON user_config
+    FOR EACH ROW
+    EXECUTE FUNCTION update_modified_column();
+
+COMMENT ON TABLE user_config IS '用户配置表';
+COMMENT ON COLUMN user_config.last_seen_visibility IS '最后活跃时间可见范围: 0-所有人 1-仅好友 2-所有人不可见';
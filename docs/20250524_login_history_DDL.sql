@@ -0,0 +1,19 @@
+-- 登录历史表，用于异地登录/新设备识别
+CREATE TABLE login_history
+(
+    id          VARCHAR(36) PRIMARY KEY,                       -- 登录记录ID
+    user_id     VARCHAR(36)  NOT NULL,                         -- 用户ID
+    device_id   VARCHAR(128) NOT NULL DEFAULT '',              -- 设备标识
+    device_name VARCHAR(128) NOT NULL DEFAULT '',              -- 设备名称
+    ip          VARCHAR(64)  NOT NULL DEFAULT '',              -- 登录IP
+    ip_network  VARCHAR(64)  NOT NULL DEFAULT '',              -- IP所属网段，用于粗粒度地域比对
+    user_agent  VARCHAR(512) NOT NULL DEFAULT '',              -- 客户端UA
+    risk_score  INT          NOT NULL DEFAULT 0,               -- 风险评分
+    trusted     BOOLEAN      NOT NULL DEFAULT FALSE,           -- 是否为可信登录（低风险或已通过二次验证）
+    created_at  TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_login_history_user_time ON login_history (user_id, created_at DESC);
+
+COMMENT ON TABLE login_history IS '登录历史表';
+COMMENT ON COLUMN login_history.trusted IS '是否为可信登录，只有可信登录会作为后续风险比对的基线';
@@ -0,0 +1,64 @@
+-- 租户管理员表（可管理租户的消息保留策略与法律保全）
+CREATE TABLE tenant_admins
+(
+    tenant_id  VARCHAR(20)  NOT NULL,                                 -- 租户ID
+    user_id    VARCHAR(36)  NOT NULL,                                 -- 管理员用户ID
+    created_at TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT tenant_admins_pkey PRIMARY KEY (tenant_id, user_id)
+);
+
+COMMENT ON TABLE tenant_admins IS '租户管理员表';
+
+-- 消息保留策略表（每个租户一条）
+CREATE TABLE retention_policies
+(
+    tenant_id   VARCHAR(20)  PRIMARY KEY,                             -- 租户ID
+    retain_days INT          NOT NULL DEFAULT 0,                      -- 保留天数，0 表示永久保留
+    updated_by  VARCHAR(36)  NOT NULL,                                -- 最后修改人
+    created_at  TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at  TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT check_retain_days CHECK (retain_days >= 0)
+);
+
+CREATE TRIGGER update_retention_policies_modtime
+    BEFORE UPDATE
+    ON retention_policies
+    FOR EACH ROW
+    EXECUTE FUNCTION update_modified_column();
+
+COMMENT ON TABLE retention_policies IS '租户消息保留策略表';
+
+-- 法律保全表，生效中的保全对象的消息不会被清理
+CREATE TABLE legal_holds
+(
+    id          VARCHAR(36)  PRIMARY KEY,                             -- 保全ID
+    tenant_id   VARCHAR(20)  NOT NULL,                                -- 租户ID
+    target_type SMALLINT     NOT NULL,                                -- 对象类型: 0-用户 1-会话(群组)
+    target_id   VARCHAR(36)  NOT NULL,                                -- 用户ID或群组ID
+    reason      VARCHAR(255) NOT NULL DEFAULT '',                     -- 保全原因
+    created_by  VARCHAR(36)  NOT NULL,                                -- 操作人
+    created_at  TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    released_at TIMESTAMP(6),                                         -- 解除时间，为空表示生效中
+    CONSTRAINT check_hold_target_type CHECK (target_type IN (0, 1))
+);
+
+-- 清理任务按租户+对象查询生效中的保全
+CREATE INDEX idx_legal_holds_active ON legal_holds (tenant_id, target_type, target_id) WHERE released_at IS NULL;
+
+COMMENT ON TABLE legal_holds IS '法律保全表';
+
+-- 保留策略审计表（策略变更、保全变更、清理记录）
+CREATE TABLE retention_audits
+(
+    id            BIGSERIAL    PRIMARY KEY,
+    tenant_id     VARCHAR(20)  NOT NULL,                              -- 租户ID
+    action        VARCHAR(20)  NOT NULL,                              -- SET_POLICY, PLACE_HOLD, RELEASE_HOLD, PURGE
+    operator_id   VARCHAR(36)  NOT NULL,                              -- 操作人，清理任务为 system
+    detail        TEXT         NOT NULL DEFAULT '{}',                 -- 详情(JSON)
+    affected_rows BIGINT       NOT NULL DEFAULT 0,                    -- 影响行数
+    created_at    TIMESTAMP(6) NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_retention_audits_tenant_created ON retention_audits (tenant_id, created_at DESC);
+
+COMMENT ON TABLE retention_audits IS '消息保留策略审计表';
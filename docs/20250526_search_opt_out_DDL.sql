@@ -0,0 +1,5 @@
+-- 消息搜索隐私：用户可选择不让自己发送的消息进入搜索索引
+ALTER TABLE user_config
+    ADD COLUMN search_opt_out BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN user_config.search_opt_out IS '不允许消息被搜索索引';
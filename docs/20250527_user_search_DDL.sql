@@ -0,0 +1,28 @@
+-- 用户搜索：隐私设置、昵称拼音与三元组索引
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- 是否允许被用户名/邮箱、手机号搜索到
+ALTER TABLE user_config
+    ADD COLUMN allow_id_search    BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN allow_phone_search BOOLEAN NOT NULL DEFAULT TRUE;
+
+COMMENT ON COLUMN user_config.allow_id_search IS '允许通过用户名/邮箱搜索到自己';
+COMMENT ON COLUMN user_config.allow_phone_search IS '允许通过手机号搜索到自己';
+
+-- 昵称拼音检索键：全拼 + 空格 + 首字母，由用户服务写入，历史数据在服务启动时补全
+ALTER TABLE users
+    ADD COLUMN nickname_pinyin VARCHAR(512);
+
+COMMENT ON COLUMN "public"."users"."nickname_pinyin" IS '昵称拼音检索键';
+
+-- 昵称、拼音模糊匹配与相似度排序
+CREATE INDEX idx_users_nickname_trgm ON users USING gin (nickname gin_trgm_ops);
+CREATE INDEX idx_users_nickname_pinyin_trgm ON users USING gin (nickname_pinyin gin_trgm_ops);
+
+-- 用户名、邮箱、手机号精确匹配
+CREATE INDEX idx_users_lower_username ON users (lower(username));
+CREATE INDEX idx_users_lower_email ON users (lower(email));
+CREATE INDEX idx_users_phone ON users (phone);
+
+-- 排除拉黑关系
+CREATE INDEX idx_friend_relation_blocked ON friend_relation (user_id, friend_id) WHERE status = 2;
@@ -0,0 +1,18 @@
+-- 手机号邀请表，发给未注册手机号的消息先保存在这里，对方注册后自动投递
+CREATE TABLE phone_invites
+(
+    id           VARCHAR(36) PRIMARY KEY,                      -- 邀请ID
+    sender_id    VARCHAR(36)   NOT NULL,                       -- 发送人ID
+    phone        VARCHAR(32)   NOT NULL,                       -- 被邀请的手机号
+    content      VARCHAR(1024) NOT NULL DEFAULT '',            -- 邀请消息内容
+    sms_sent     BOOLEAN       NOT NULL DEFAULT FALSE,         -- 邀请短信是否发送成功
+    created_at   TIMESTAMP     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at   TIMESTAMP     NOT NULL,                       -- 过期时间，过期后不再投递
+    delivered_at TIMESTAMP                                     -- 投递时间，未投递为空
+);
+
+CREATE INDEX idx_phone_invites_pending ON phone_invites (phone) WHERE delivered_at IS NULL;
+CREATE INDEX idx_phone_invites_sender_time ON phone_invites (sender_id, created_at DESC);
+
+COMMENT ON TABLE phone_invites IS '手机号邀请表';
+COMMENT ON COLUMN phone_invites.delivered_at IS '对方注册后投递邀请消息的时间，未投递为空';
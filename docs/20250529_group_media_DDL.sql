@@ -0,0 +1,24 @@
+-- 群媒体库表，索引群聊中发送的图片、视频、语音和文件（由消息服务在消费群消息时写入）
+CREATE TABLE group_media
+(
+    id            VARCHAR(36) PRIMARY KEY,                   -- 条目ID
+    group_id      VARCHAR(36)   NOT NULL,                    -- 群组ID
+    msg_id        VARCHAR(64)   NOT NULL,                    -- 原消息的server_id
+    sender_id     VARCHAR(36)   NOT NULL,                    -- 发送者ID
+    media_type    VARCHAR(16)   NOT NULL,                    -- 媒体类型 image/video/audio/file
+    object_key    VARCHAR(512)  NOT NULL,                    -- OSS对象键
+    url           VARCHAR(1024) NOT NULL,                    -- 下载地址
+    file_name     VARCHAR(256)  NOT NULL DEFAULT '',         -- 原始文件名
+    mime_type     VARCHAR(128)  NOT NULL DEFAULT '',         -- MIME类型
+    size          BIGINT        NOT NULL DEFAULT 0,          -- 文件大小（字节）
+    thumbnail_url VARCHAR(1024),                             -- 缩略图地址
+    sent_at       TIMESTAMP     NOT NULL,                    -- 消息发送时间
+    created_at    TIMESTAMP     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT uk_group_media_msg UNIQUE (group_id, msg_id)
+);
+
+CREATE INDEX idx_group_media_group_time ON group_media (group_id, sent_at DESC);
+CREATE INDEX idx_group_media_group_type_time ON group_media (group_id, media_type, sent_at DESC);
+
+COMMENT ON TABLE group_media IS '群媒体库';
+COMMENT ON COLUMN group_media.msg_id IS '原消息的server_id，原消息撤回或附件被拦截时按此删除';
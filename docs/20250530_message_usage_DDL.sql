@@ -0,0 +1,17 @@
+-- 每小时消息用量表（由用户服务每小时从Redis汇总写入，供计费系统导出）
+CREATE TABLE message_usage_hourly
+(
+    stat_hour  VARCHAR(10) NOT NULL,                 -- 统计小时 yyyyMMddHH (UTC)
+    tenant_id  VARCHAR(36) NOT NULL DEFAULT '',      -- 租户ID，未归属租户时为空
+    user_id    VARCHAR(36) NOT NULL,                 -- 发送者ID
+    messages   BIGINT      NOT NULL DEFAULT 0,       -- 消息数
+    bytes      BIGINT      NOT NULL DEFAULT 0,       -- 消息内容字节数
+    created_at TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT pk_message_usage_hourly PRIMARY KEY (stat_hour, tenant_id, user_id)
+);
+
+-- 按租户导出一段时间的用量
+CREATE INDEX idx_message_usage_tenant_hour ON message_usage_hourly (tenant_id, stat_hour);
+
+COMMENT ON TABLE message_usage_hourly IS '每小时消息用量';
+COMMENT ON COLUMN message_usage_hourly.messages IS '单聊和群聊消息数，Kafka重复投递时可能略多于实际值';
@@ -0,0 +1,29 @@
+-- 用户ID迁移为UUID类型：新增UUID列，迁移期间由服务双写（见配置 schema_migrations）
+-- 执行顺序：
+--   1. 执行本文件的 ALTER TABLE / CREATE INDEX
+--   2. 开启对应迁移的 dual_write，之后执行下方的回填语句（可重复执行）
+--   3. 开启 verify，观察服务日志中的“校验不一致”，确认后开启 read_new
+-- 回退时按相反顺序关闭开关，旧的字符串ID列始终保持写入
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS id_uuid UUID;
+CREATE UNIQUE INDEX CONCURRENTLY IF NOT EXISTS idx_users_id_uuid ON users (id_uuid);
+
+ALTER TABLE friendships ADD COLUMN IF NOT EXISTS user_uuid UUID;
+ALTER TABLE friendships ADD COLUMN IF NOT EXISTS friend_uuid UUID;
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_friendships_user_uuid ON friendships (user_uuid, friend_uuid);
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_friendships_friend_uuid ON friendships (friend_uuid, user_uuid);
+
+ALTER TABLE group_members ADD COLUMN IF NOT EXISTS group_uuid UUID;
+ALTER TABLE group_members ADD COLUMN IF NOT EXISTS user_uuid UUID;
+CREATE INDEX CONCURRENTLY IF NOT EXISTS idx_group_members_group_uuid ON group_members (group_uuid, user_uuid);
+
+-- 回填：开启双写后执行，数据量大时可按ID范围分批执行
+UPDATE users SET id_uuid = id::uuid WHERE id_uuid IS NULL;
+UPDATE friendships SET user_uuid = user_id::uuid, friend_uuid = friend_id::uuid WHERE user_uuid IS NULL OR friend_uuid IS NULL;
+UPDATE group_members SET group_uuid = group_id::uuid, user_uuid = user_id::uuid WHERE group_uuid IS NULL OR user_uuid IS NULL;
+
+COMMENT ON COLUMN users.id_uuid IS '用户ID（UUID类型），迁移完成后替代 id';
+COMMENT ON COLUMN friendships.user_uuid IS '用户ID（UUID类型），迁移完成后替代 user_id';
+COMMENT ON COLUMN friendships.friend_uuid IS '好友ID（UUID类型），迁移完成后替代 friend_id';
+COMMENT ON COLUMN group_members.group_uuid IS '群组ID（UUID类型），迁移完成后替代 group_id';
+COMMENT ON COLUMN group_members.user_uuid IS '用户ID（UUID类型），迁移完成后替代 user_id';
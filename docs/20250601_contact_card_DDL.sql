@@ -0,0 +1,5 @@
+-- 名片消息：是否允许非好友查看分享的名片
+ALTER TABLE user_config
+    ADD COLUMN allow_card_share BOOLEAN NOT NULL DEFAULT TRUE;
+
+COMMENT ON COLUMN user_config.allow_card_share IS '允许非好友查看分享的名片';
@@ -0,0 +1,32 @@
+-- 群投票表（由群组服务写入，到期的投票由群组服务后台任务结束）
+CREATE TABLE group_poll
+(
+    id           VARCHAR(36) PRIMARY KEY,              -- 投票ID
+    group_id     VARCHAR(36)  NOT NULL,                -- 群组ID
+    creator_id   VARCHAR(36)  NOT NULL,                -- 发起人ID
+    question     VARCHAR(1024) NOT NULL,               -- 投票问题
+    options      TEXT[]       NOT NULL,                -- 选项，按数组下标（从0开始）编号
+    multi_choice BOOLEAN      NOT NULL DEFAULT FALSE,  -- 是否允许多选
+    deadline     TIMESTAMP,                            -- 截止时间 (UTC)，为空时需手动结束
+    closed_at    TIMESTAMP,                            -- 结束时间 (UTC)
+    closed_by    VARCHAR(36),                          -- 手动结束的操作人，到期结束时为空
+    created_at   TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_group_poll_group_time ON group_poll (group_id, created_at DESC);
+-- 后台任务查找到期未结束的投票
+CREATE INDEX idx_group_poll_open_deadline ON group_poll (deadline) WHERE closed_at IS NULL;
+
+COMMENT ON TABLE group_poll IS '群投票';
+
+-- 群投票记录，多选时每个选项一行
+CREATE TABLE group_poll_vote
+(
+    poll_id      VARCHAR(36) NOT NULL REFERENCES group_poll (id) ON DELETE CASCADE,
+    user_id      VARCHAR(36) NOT NULL,                 -- 投票人ID
+    option_index INT         NOT NULL,                 -- 选项编号
+    voted_at     TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT pk_group_poll_vote PRIMARY KEY (poll_id, user_id, option_index)
+);
+
+COMMENT ON TABLE group_poll_vote IS '群投票记录，统计结果由服务端按选项聚合';
@@ -0,0 +1,28 @@
+-- 用户级API访问日志（网关批量写入，按天分区，用于滥用调查）
+-- 分区由用户服务提前创建，超过保留天数的分区整体删除，分区名为 api_access_log_pYYYYMMDD
+CREATE TABLE api_access_log
+(
+    id          BIGSERIAL,
+    user_id     VARCHAR(36)  NOT NULL,                                -- 用户ID
+    tenant_id   VARCHAR(20)  NOT NULL DEFAULT '',                     -- 租户ID
+    method      VARCHAR(10)  NOT NULL,                                -- 请求方法
+    path        VARCHAR(512) NOT NULL,                                -- 请求路径，不含查询参数
+    status      SMALLINT     NOT NULL,                                -- HTTP状态码
+    latency_ms  INT          NOT NULL DEFAULT 0,                      -- 耗时（毫秒）
+    ip          VARCHAR(45)  NOT NULL DEFAULT '',                     -- 客户端IP
+    user_agent  VARCHAR(256) NOT NULL DEFAULT '',
+    request_id  VARCHAR(64)  NOT NULL DEFAULT '',
+    accessed_at TIMESTAMPTZ  NOT NULL,                                -- 访问时间
+    CONSTRAINT pk_api_access_log PRIMARY KEY (accessed_at, id)
+) PARTITION BY RANGE (accessed_at);
+
+-- 按用户、按IP查询一段时间的访问记录
+CREATE INDEX idx_api_access_log_tenant_user ON api_access_log (tenant_id, user_id, accessed_at);
+CREATE INDEX idx_api_access_log_tenant_ip ON api_access_log (tenant_id, ip, accessed_at);
+
+COMMENT ON TABLE api_access_log IS '用户级API访问日志';
+COMMENT ON COLUMN api_access_log.path IS '请求路径，包含路径中的资源ID，超长时截断';
+
+-- 初始分区（之后由用户服务每小时检查并创建）
+CREATE TABLE IF NOT EXISTS api_access_log_p20250603 PARTITION OF api_access_log
+    FOR VALUES FROM ('2025-06-03 00:00:00+00') TO ('2025-06-04 00:00:00+00');
@@ -0,0 +1,10 @@
+-- 租户数据库集群映射表（只建在默认集群，启用 data_residency.control_table 时各服务定期加载）
+-- 映射优先于配置文件中的 data_residency.tenants，target 必须是配置中已有的集群名称
+CREATE TABLE tenant_db_route
+(
+    tenant_id  VARCHAR(64) PRIMARY KEY,                      -- 租户ID
+    target     VARCHAR(64) NOT NULL,                         -- 数据库集群名称
+    updated_at TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 迁移已有租户的数据后再写入映射，映射生效前写入的数据仍在默认集群
@@ -0,0 +1,28 @@
+-- 消息序号水位表（Redis降级期间由Postgres分配序号，见配置 degradation）
+-- Redis每预留一段序号时把上限写入 seq，降级期间从 seq 之后逐个分配，不会与Redis分配的序号重复
+CREATE TABLE message_seq_fallback
+(
+    user_id    VARCHAR(36) NOT NULL,                          -- 用户ID
+    kind       VARCHAR(8)  NOT NULL,                          -- 序号类型：receive 接收序号 / send 发送序号
+    seq        BIGINT      NOT NULL,                          -- 已预留或已分配的最大序号
+    dirty      BOOLEAN     NOT NULL DEFAULT FALSE,            -- 降级期间分配过，Redis恢复后需要同步回Redis
+    updated_at TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, kind)
+);
+
+-- Redis恢复后按批取出待同步的序号
+CREATE INDEX idx_message_seq_fallback_dirty ON message_seq_fallback (user_id, kind) WHERE dirty;
+
+-- 所有实例共用的降级状态，只有一行
+-- Redis不可用的实例持续延长 degraded_until，到期后各实例一起切回Redis分配；
+-- 上线时Redis中已预留的序号没有水位，由服务扫描Redis中的最大序号初始化本表后才把 seeded 置为 TRUE，
+-- 初始化完成前不允许降级分配
+CREATE TABLE message_seq_mode
+(
+    id             SMALLINT  PRIMARY KEY CHECK (id = 1),
+    degraded_until TIMESTAMP NOT NULL DEFAULT '-infinity',
+    seeded         BOOLEAN   NOT NULL DEFAULT FALSE,
+    updated_at     TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO message_seq_mode (id) VALUES (1);
@@ -0,0 +1,11 @@
+-- 公开资料的字段级隐私设置，取值与 last_seen_visibility 一致：0-所有人可见 1-仅好友可见 2-所有人不可见
+ALTER TABLE user_config
+    ADD COLUMN email_visibility SMALLINT NOT NULL DEFAULT 2,
+    ADD COLUMN phone_visibility SMALLINT NOT NULL DEFAULT 2,
+    ADD COLUMN sex_visibility SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN address_visibility SMALLINT NOT NULL DEFAULT 1;
+
+COMMENT ON COLUMN user_config.email_visibility IS '公开资料中邮箱的可见范围';
+COMMENT ON COLUMN user_config.phone_visibility IS '公开资料中手机号的可见范围';
+COMMENT ON COLUMN user_config.sex_visibility IS '公开资料中性别的可见范围';
+COMMENT ON COLUMN user_config.address_visibility IS '公开资料中地址的可见范围';
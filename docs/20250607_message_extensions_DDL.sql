@@ -0,0 +1,44 @@
+-- 消息历史保存完整的消息信封：消息服务按服务端ID把单聊消息写入 private_messages、群聊消息写入 group_messages，
+-- 接收盒重建和消息查询从这两张表读取
+
+-- 内容类型与消息的 ContentType 一致（TEXT、IMAGE、CONTACTCARD 等），不再限定为原有的五种
+ALTER TABLE private_messages DROP CONSTRAINT IF EXISTS check_content_type;
+ALTER TABLE private_messages ALTER COLUMN content_type TYPE VARCHAR(20);
+ALTER TABLE group_messages DROP CONSTRAINT IF EXISTS check_content_type;
+ALTER TABLE group_messages ALTER COLUMN content_type TYPE VARCHAR(20);
+
+-- 消息信封中的其余字段；content 保存文本形式，不是有效UTF-8的内容另存原始字节到 raw_content
+ALTER TABLE private_messages
+    ADD COLUMN raw_content    BYTEA,
+    ADD COLUMN local_id       TEXT        NOT NULL DEFAULT '',
+    ADD COLUMN create_time    BIGINT      NOT NULL DEFAULT 0,
+    ADD COLUMN seq            BIGINT      NOT NULL DEFAULT 0,
+    ADD COLUMN send_seq       BIGINT      NOT NULL DEFAULT 0,
+    ADD COLUMN msg_type       INT         NOT NULL DEFAULT 0,
+    ADD COLUMN platform       INT         NOT NULL DEFAULT 0,
+    ADD COLUMN avatar         TEXT        NOT NULL DEFAULT '',
+    ADD COLUMN nickname       TEXT        NOT NULL DEFAULT '',
+    ADD COLUMN related_msg_id VARCHAR(36),
+    ADD COLUMN tenant_id      VARCHAR(64) NOT NULL DEFAULT '';
+
+ALTER TABLE group_messages
+    ADD COLUMN raw_content    BYTEA,
+    ADD COLUMN local_id       TEXT        NOT NULL DEFAULT '',
+    ADD COLUMN create_time    BIGINT      NOT NULL DEFAULT 0,
+    ADD COLUMN send_seq       BIGINT      NOT NULL DEFAULT 0,
+    ADD COLUMN msg_type       INT         NOT NULL DEFAULT 0,
+    ADD COLUMN platform       INT         NOT NULL DEFAULT 0,
+    ADD COLUMN avatar         TEXT        NOT NULL DEFAULT '',
+    ADD COLUMN nickname       TEXT        NOT NULL DEFAULT '',
+    ADD COLUMN related_msg_id VARCHAR(36),
+    ADD COLUMN tenant_id      VARCHAR(64) NOT NULL DEFAULT '';
+
+-- 消息扩展字段：业务自定义的字符串键值对，服务端不解析，原样保存和投递
+ALTER TABLE private_messages
+    ADD COLUMN extensions JSONB NOT NULL DEFAULT '{}'::jsonb;
+
+ALTER TABLE group_messages
+    ADD COLUMN extensions JSONB NOT NULL DEFAULT '{}'::jsonb;
+
+COMMENT ON COLUMN private_messages.extensions IS '消息扩展字段（字符串键值对）';
+COMMENT ON COLUMN group_messages.extensions IS '消息扩展字段（字符串键值对）';
@@ -0,0 +1,17 @@
+-- 离线摘要邮件：用户选择的发送频率和每个用户上一次发送摘要的时间
+ALTER TABLE user_config
+    ADD COLUMN email_digest SMALLINT NOT NULL DEFAULT 0;
+
+COMMENT ON COLUMN user_config.email_digest IS '离线摘要邮件的发送频率: 0-不发送 1-每小时 2-每天';
+
+CREATE TABLE email_digest_log
+(
+    user_id VARCHAR(36) PRIMARY KEY,          -- 用户ID
+    sent_at TIMESTAMP   NOT NULL              -- 上一次发送摘要的时间（UTC）
+);
+
+COMMENT ON TABLE email_digest_log IS '离线摘要邮件发送记录';
+
+-- 查询离线期间的未读私聊消息和待处理好友请求
+CREATE INDEX IF NOT EXISTS idx_private_messages_receiver_unread ON private_messages (receiver_id, sent_at) WHERE NOT is_read AND NOT is_deleted;
+CREATE INDEX IF NOT EXISTS idx_users_last_active_at ON users (last_active_at);
@@ -0,0 +1,2 @@
+-- 接收盒重建按接收者和接收序号分批读取单聊消息历史
+CREATE INDEX IF NOT EXISTS idx_private_messages_receiver_seq ON private_messages (receiver_id, seq);
@@ -0,0 +1,27 @@
+-- 用户附件存储配额：申请签名上传地址时登记附件并预占配额，客户端上传完成后确认；
+-- 到期未确认的预占由后台任务释放，已确认的附件在消息撤回或过期清理后释放
+CREATE TABLE user_storage_usage
+(
+    user_id          VARCHAR(36) PRIMARY KEY,                          -- 用户ID
+    used_bytes       BIGINT      NOT NULL DEFAULT 0,                   -- 已占用的字节数
+    attachment_count BIGINT      NOT NULL DEFAULT 0,                   -- 附件数
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+COMMENT ON TABLE user_storage_usage IS '用户附件存储用量';
+
+CREATE TABLE user_attachments
+(
+    object_key VARCHAR(255) PRIMARY KEY,                                -- OSS对象键
+    user_id    VARCHAR(36)  NOT NULL,                                   -- 上传者ID
+    size       BIGINT       NOT NULL,                                   -- 申请上传时声明的大小（字节）
+    mime_type  VARCHAR(128) NOT NULL DEFAULT '',
+    committed  BOOLEAN      NOT NULL DEFAULT FALSE,                     -- 客户端是否已确认上传完成
+    reserved_until TIMESTAMPTZ,                                         -- 未确认时预占的到期时间
+    created_at TIMESTAMPTZ  NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_user_attachments_user_id ON user_attachments (user_id);
+CREATE INDEX idx_user_attachments_reserved_until ON user_attachments (reserved_until) WHERE NOT committed;
+
+COMMENT ON TABLE user_attachments IS '已登记的用户附件，释放时删除';
@@ -0,0 +1,20 @@
+-- 会话序号（见配置 conversation_seq）：在接收序号之外按会话分配的连续序号，未分配时为0
+ALTER TABLE private_messages ADD COLUMN IF NOT EXISTS conv_seq BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE group_messages ADD COLUMN IF NOT EXISTS conv_seq BIGINT NOT NULL DEFAULT 0;
+
+-- 按会话序号补齐缺失的消息，只索引已分配会话序号的消息
+CREATE INDEX IF NOT EXISTS idx_group_messages_conv_seq ON group_messages (group_id, conv_seq) WHERE conv_seq > 0;
+CREATE INDEX IF NOT EXISTS idx_private_messages_conv_seq ON private_messages (sender_id, receiver_id, conv_seq) WHERE conv_seq > 0;
+
+-- 会话序号水位表，Redis每预留一段会话序号时把上限写入 max_seq，
+-- Redis中的会话序号丢失后从 max_seq 之后继续分配，不会与已分配的序号重复
+CREATE TABLE conversation_seq_watermark
+(
+    conversation_id VARCHAR(128) PRIMARY KEY,                     -- 会话序号键：single:较小的用户ID:较大的用户ID / group:群组ID
+    max_seq         BIGINT      NOT NULL,                         -- 已预留的最大会话序号
+    updated_at      TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 接收盒（MongoDB msg_box 集合）需要同样的索引，供按会话序号拉取消息使用：
+-- db.msg_box.createIndex({ receiver_id: 1, group_id: 1, conv_seq: 1 }, { partialFilterExpression: { conv_seq: { $gt: 0 } } })
+-- db.msg_box.createIndex({ send_id: 1, receiver_id: 1, conv_seq: 1 }, { partialFilterExpression: { conv_seq: { $gt: 0 } } })
@@ -0,0 +1,10 @@
+-- 消息搜索隐私：用户可按会话排除消息，被排除会话的消息不进入该用户的搜索索引
+CREATE TABLE conversation_search_opt_out
+(
+    user_id         VARCHAR(36) NOT NULL,                          -- 设置排除的用户ID
+    conversation_id VARCHAR(36) NOT NULL,                          -- 单聊为对方用户ID，群聊为群组ID
+    created_at      TIMESTAMP   NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, conversation_id)
+);
+
+COMMENT ON TABLE conversation_search_opt_out IS '不进入搜索索引的会话';
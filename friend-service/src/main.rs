@@ -3,13 +3,19 @@ use axum::{routing::get, Router};
 use axum_server;
 use clap::Parser;
 use common::config::AppConfig;
+use common::grpc::maintenance::MaintenanceMode;
+use common::grpc::server::serve_with_drain;
+use common::grpc::service_auth::{ServiceAuth, ServiceAuthInterceptor};
 use common::grpc::LoggingInterceptor;
-use common::service_registry::ServiceRegistry;
-use sqlx::postgres::PgPoolOptions;
+use common::schema_migration::SchemaMigration;
+use common::service_registry::{ServiceRegister, ServiceRegistry};
+use common::tenant_db::PgRouter;
+use common::validation::ContentPolicy;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::signal;
 use tokio::sync::oneshot;
-use tonic::transport::Server;
+use tonic::transport::server::Routes;
 use tonic_reflection::server::Builder as ReflectionBuilder;
 use tracing::{error, info, warn};
 
@@ -18,6 +24,7 @@ mod repository;
 mod service;
 
 use common::proto::friend::friend_service_server::FriendServiceServer;
+use repository::friendship_repository::FRIENDSHIPS_UUID_MIGRATION;
 use service::friend_service::FriendServiceImpl;
 // 导入好友服务proto文件描述符，用于gRPC反射
 const FILE_DESCRIPTOR_SET: &[u8] = common::proto::friend::FILE_DESCRIPTOR_SET;
@@ -30,14 +37,19 @@ struct Args {
     config: String,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     // 初始化命令行参数
     let args = Args::parse();
 
     // 加载配置
     let config = AppConfig::from_file(Some(&args.config))?;
 
+    // 按配置的线程数创建运行时
+    common::runtime::build("friend-service", &config.runtime.for_service("friend-service"))?
+        .block_on(run(config))
+}
+
+async fn run(config: AppConfig) -> Result<()> {
     // 初始化日志和链路追踪
     // 根据配置判断是否启用链路追踪
     if config.telemetry.enabled {
@@ -52,20 +64,19 @@ async fn main() -> Result<()> {
 
     info!("正在启动好友服务...");
 
+    // 未配置服务间认证密钥时拒绝启动，避免RPC端口对外无认证暴露
+    ServiceAuth::global().ensure_enabled()?;
+
     // 使用已加载的配置
     let host = &config.server.host;
     let port = 50004; // 指定好友服务端口
     let addr = format!("{}:{}", host, port).parse::<SocketAddr>()?;
 
-    // 初始化数据库连接池
-    let db_pool = match PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&config.database.url())
-        .await
-    {
-        Ok(pool) => {
+    // 初始化数据库连接池，配置了数据驻留的租户使用各自的数据库集群
+    let db = match PgRouter::connect(&config, 10).await {
+        Ok(db) => {
             info!("数据库连接成功");
-            pool
+            db
         }
         Err(err) => {
             error!("数据库连接失败: {}", err);
@@ -73,8 +84,13 @@ async fn main() -> Result<()> {
         }
     };
 
-    // 初始化好友服务
-    let friend_service = FriendServiceImpl::new(db_pool);
+    // 初始化好友服务，在线状态从Redis中的在线用户集合查询
+    let friend_service = FriendServiceImpl::new(
+        db,
+        SchemaMigration::new(FRIENDSHIPS_UUID_MIGRATION, &config.schema_migrations),
+        cache::cache(&config),
+        ContentPolicy::new(&config.content_policy),
+    );
 
     // 创建HTTP服务器用于健康检查
     let health_port = port + 1;
@@ -82,9 +98,11 @@ async fn main() -> Result<()> {
     let health_service = start_health_service(host, health_port).await?;
 
     // 创建并注册到Consul
-    let service_registry = ServiceRegistry::from_env();
+    let service_registry = ServiceRegistry::from_env()
+        .with_region(config.region.local())
+        .shared();
     let service_id = service_registry
-        .register_service(
+        .register(
             "friend-service",
             host,
             port as u32, // 注册gRPC服务端口
@@ -105,23 +123,25 @@ async fn main() -> Result<()> {
         .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
         .build()?;
 
-    // 创建日志拦截器
+    // 创建日志拦截器，业务服务外层再校验服务间令牌
     let logging_interceptor = LoggingInterceptor::new();
 
     // 启动gRPC服务
     info!("好友服务启动，监听地址: {}", addr);
 
-    // 创建服务器并运行
-    let server = Server::builder()
-        .add_service(reflection_service) // 添加反射服务
+    // 创建服务器并运行，连接到期和下线时通知客户端
+    let routes = Routes::new(reflection_service) // 添加反射服务
         .add_service(FriendServiceServer::with_interceptor(
             friend_service, 
-            logging_interceptor
-        ))
-        .serve_with_shutdown(addr, async {
-            let _ = shutdown_rx.await;
-            info!("接收到关闭信号，gRPC服务准备关闭");
-        });
+            ServiceAuthInterceptor::new(logging_interceptor)
+        ));
+    // 数据库迁移期间可以切换到只读维护模式，拒绝写操作
+    let maintenance = MaintenanceMode::new("friend-service", &config.maintenance);
+    maintenance.start(&config.redis);
+    let server = serve_with_drain(routes, addr, &config.grpc_server, maintenance, async {
+        let _ = shutdown_rx.await;
+        info!("接收到关闭信号，gRPC服务准备关闭");
+    });
 
     tokio::select! {
         _ = server => {
@@ -177,7 +197,10 @@ async fn health_check() -> &'static str {
 }
 
 // 优雅关闭信号处理
-async fn shutdown_signal(tx: oneshot::Sender<()>, service_registry: ServiceRegistry) -> Result<()> {
+async fn shutdown_signal(
+    tx: oneshot::Sender<()>,
+    service_registry: Arc<dyn ServiceRegister>,
+) -> Result<()> {
     let ctrl_c = async {
         signal::ctrl_c().await.expect("无法安装Ctrl+C处理器");
     };
@@ -201,7 +224,7 @@ async fn shutdown_signal(tx: oneshot::Sender<()>, service_registry: ServiceRegis
     info!("接收到关闭信号，准备优雅关闭...");
 
     // 从Consul注销服务
-    match service_registry.deregister_service().await {
+    match service_registry.deregister().await {
         Ok(_) => info!("已从Consul注销服务"),
         Err(e) => error!("从Consul注销服务失败: {}", e),
     }
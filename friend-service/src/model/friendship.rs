@@ -70,3 +70,112 @@ impl Friend {
         }
     }
 }
+
+/// 分组好友的排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupFriendSort {
+    /// 按显示名称（备注、昵称、用户名）升序
+    NameAsc,
+    /// 按显示名称降序
+    NameDesc,
+    /// 按添加好友的时间升序
+    AddedAtAsc,
+    /// 按添加好友的时间降序
+    AddedAtDesc,
+}
+
+impl GroupFriendSort {
+    /// 解析排序参数，为空时按名称升序
+    pub fn parse(sort_by: &str) -> Option<Self> {
+        match sort_by {
+            "" | "name_asc" => Some(Self::NameAsc),
+            "name_desc" => Some(Self::NameDesc),
+            "added_at_asc" => Some(Self::AddedAtAsc),
+            "added_at_desc" => Some(Self::AddedAtDesc),
+            _ => None,
+        }
+    }
+
+    /// 排序字段的SQL表达式
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::NameAsc | Self::NameDesc => {
+                "COALESCE(NULLIF(fr.remark, ''), NULLIF(u.nickname, ''), u.username)"
+            }
+            Self::AddedAtAsc | Self::AddedAtDesc => "fr.create_time",
+        }
+    }
+
+    pub fn is_asc(&self) -> bool {
+        matches!(self, Self::NameAsc | Self::AddedAtAsc)
+    }
+}
+
+/// 分组好友列表的游标：上一页最后一位好友的排序值和好友ID，格式为 `好友ID:排序值`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupFriendCursor {
+    pub friend_id: String,
+    pub sort_value: String,
+}
+
+impl GroupFriendCursor {
+    pub fn encode(&self) -> String {
+        format!("{}:{}", self.friend_id, self.sort_value)
+    }
+
+    pub fn parse(cursor: &str) -> Option<Self> {
+        let (friend_id, sort_value) = cursor.split_once(':')?;
+        if friend_id.is_empty() {
+            return None;
+        }
+        Some(Self {
+            friend_id: friend_id.to_string(),
+            sort_value: sort_value.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_friend_sort_parse() {
+        assert_eq!(GroupFriendSort::parse(""), Some(GroupFriendSort::NameAsc));
+        assert_eq!(
+            GroupFriendSort::parse("name_desc"),
+            Some(GroupFriendSort::NameDesc)
+        );
+        assert_eq!(
+            GroupFriendSort::parse("added_at_asc"),
+            Some(GroupFriendSort::AddedAtAsc)
+        );
+        assert_eq!(
+            GroupFriendSort::parse("added_at_desc"),
+            Some(GroupFriendSort::AddedAtDesc)
+        );
+        assert_eq!(GroupFriendSort::parse("NAME_ASC"), None);
+        assert_eq!(GroupFriendSort::parse("id"), None);
+
+        assert!(GroupFriendSort::NameAsc.is_asc());
+        assert!(!GroupFriendSort::AddedAtDesc.is_asc());
+        assert_eq!(GroupFriendSort::AddedAtAsc.column(), "fr.create_time");
+    }
+
+    #[test]
+    fn test_group_friend_cursor_round_trip() {
+        // 排序值为时间时本身带有冒号，只按第一个冒号切分
+        let cursor = GroupFriendCursor {
+            friend_id: "5b4f7c1e-8f2a-4c55-9d7e-2f1a3b4c5d6e".to_string(),
+            sort_value: "2025-06-01 08:30:00+00".to_string(),
+        };
+        assert_eq!(GroupFriendCursor::parse(&cursor.encode()), Some(cursor));
+
+        let empty_value = GroupFriendCursor::parse("abc:").unwrap();
+        assert_eq!(empty_value.friend_id, "abc");
+        assert_eq!(empty_value.sort_value, "");
+
+        assert_eq!(GroupFriendCursor::parse("no-separator"), None);
+        assert_eq!(GroupFriendCursor::parse(":value"), None);
+    }
+}
@@ -1,18 +1,49 @@
 use anyhow::Result;
 use chrono::{TimeZone, Utc};
 use common::proto::friend::FriendshipStatus;
-use sqlx::{PgPool, Row, FromRow, types::chrono::NaiveDateTime};
+use common::schema_migration::SchemaMigration;
+use common::tenant_db::PgRouter;
+use sqlx::{Row, FromRow, types::chrono::NaiveDateTime};
 use uuid::Uuid;
 
-use crate::model::friendship::{Friend, Friendship};
+use crate::model::friendship::{Friend, Friendship, GroupFriendCursor, GroupFriendSort};
+
+/// 好友关系的用户ID迁移为UUID列的表结构迁移名称
+pub const FRIENDSHIPS_UUID_MIGRATION: &str = "friendships_uuid";
 
 pub struct FriendshipRepository {
-    pool: PgPool,
+    db: PgRouter,
+    migration: SchemaMigration,
 }
 
 impl FriendshipRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: PgRouter) -> Self {
+        Self {
+            db,
+            migration: SchemaMigration::disabled(FRIENDSHIPS_UUID_MIGRATION),
+        }
+    }
+
+    // 设置好友关系的表结构迁移开关
+    pub fn with_migration(mut self, migration: SchemaMigration) -> Self {
+        self.migration = migration;
+        self
+    }
+
+    // 迁移期间将好友关系的用户ID同步写入新的UUID列，迁移列在执行DDL之前不存在，使用运行时检查的查询
+    async fn sync_uuid_columns(&self, id: &str, op: &str) {
+        if !self.migration.dual_write() {
+            return;
+        }
+        if let Err(e) = sqlx::query(
+            "UPDATE friendships SET user_uuid = user_id::uuid, friend_uuid = friend_id::uuid WHERE id = $1",
+        )
+        .bind(id)
+        .execute(self.db.pool())
+        .await
+        {
+            self.migration.write_failed(op, e);
+        }
     }
 
     // 创建好友请求
@@ -42,8 +73,9 @@ impl FriendshipRepository {
             created_at_naive,
             updated_at_naive
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
+        self.sync_uuid_columns(&result.id, "create_friend_request").await;
 
         Ok(Friendship {
             id: Uuid::parse_str(&result.id).unwrap(),
@@ -67,7 +99,7 @@ impl FriendshipRepository {
         let now_naive = now.naive_utc();
 
         // 开始事务
-        let mut tx = self.pool.begin().await?;
+        let mut tx = self.db.pool().begin().await?;
 
         // 1. 更新friendships表中的状态为已接受
         let result = sqlx::query!(
@@ -155,7 +187,7 @@ impl FriendshipRepository {
             user_id.to_string(),
             friend_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(Friendship {
@@ -227,7 +259,7 @@ impl FriendshipRepository {
             .bind(user_id.to_string())
             .bind(page_size)
             .bind(offset)
-            .fetch_all(&self.pool)
+            .fetch_all(self.db.pool())
             .await?;
         
         // 将FriendRow转换为Friend
@@ -246,6 +278,87 @@ impl FriendshipRepository {
         Ok(friends)
     }
 
+    // 获取分组内的好友，返回好友和对应的排序值，游标为空时按偏移分页
+    pub async fn get_group_friends(
+        &self,
+        user_id: Uuid,
+        group_id: Option<&str>,
+        sort: GroupFriendSort,
+        cursor: Option<&GroupFriendCursor>,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(Friend, String)>> {
+        let column = sort.column();
+        let (order, cmp) = if sort.is_asc() { ("ASC", ">") } else { ("DESC", "<") };
+        // 时间排序的游标值是时间的文本形式，比较前转换回时间
+        let cast = match sort {
+            GroupFriendSort::AddedAtAsc | GroupFriendSort::AddedAtDesc => "::timestamp",
+            _ => "",
+        };
+        let after = if cursor.is_some() {
+            format!("AND ({}, fr.friend_id) {} ($5{}, $6)", column, cmp, cast)
+        } else {
+            String::new()
+        };
+
+        let query = format!(
+            r#"
+            SELECT 
+                u.id::text, 
+                u.username, 
+                u.nickname, 
+                u.avatar_url, 
+                fr.create_time as friendship_created_at, 
+                fr.remark,
+                ({column})::text as sort_value
+            FROM users u
+            JOIN friend_relation fr ON fr.friend_id = u.id 
+            WHERE fr.user_id = $1 AND fr.status = 1 AND fr.group_id IS NOT DISTINCT FROM $2
+            {after}
+            ORDER BY {column} {order}, fr.friend_id {order}
+            LIMIT $3 OFFSET $4
+            "#,
+        );
+
+        #[derive(sqlx::FromRow)]
+        struct GroupFriendRow {
+            id: String,
+            username: String,
+            nickname: Option<String>,
+            avatar_url: Option<String>,
+            friendship_created_at: NaiveDateTime,
+            remark: Option<String>,
+            sort_value: String,
+        }
+
+        let mut query = sqlx::query_as::<_, GroupFriendRow>(&query)
+            .bind(user_id.to_string())
+            .bind(group_id)
+            .bind(limit)
+            .bind(if cursor.is_some() { 0 } else { offset });
+        if let Some(cursor) = cursor {
+            query = query.bind(&cursor.sort_value).bind(&cursor.friend_id);
+        }
+        let rows = query.fetch_all(self.db.pool()).await?;
+
+        let friends = rows
+            .into_iter()
+            .map(|row| {
+                let friend = Friend {
+                    id: Uuid::parse_str(&row.id).unwrap(),
+                    username: row.username,
+                    nickname: row.nickname,
+                    avatar_url: row.avatar_url,
+                    friendship_created_at: Utc.from_utc_datetime(&row.friendship_created_at),
+                    remark: row.remark,
+                };
+                (friend, row.sort_value)
+            })
+            .collect();
+
+        Ok(friends)
+    }
+
     // 获取好友请求列表
     pub async fn get_friend_requests(&self, user_id: Uuid) -> Result<Vec<Friendship>> {
         let requests = sqlx::query!(
@@ -256,7 +369,7 @@ impl FriendshipRepository {
             "#,
             user_id.to_string(),
             user_id.to_string())
-        .fetch_all(&self.pool)
+        .fetch_all(self.db.pool())
         .await?;
 
         let result = requests
@@ -286,7 +399,7 @@ impl FriendshipRepository {
             user_id.to_string(),
             friend_id.to_string()
         )
-        .execute(&self.pool)
+        .execute(self.db.pool())
         .await?
         .rows_affected();
 
@@ -298,6 +411,21 @@ impl FriendshipRepository {
         &self,
         user_id: Uuid,
         friend_id: Uuid,
+    ) -> Result<Option<FriendshipStatus>> {
+        self.migration
+            .read(
+                "check_friendship",
+                self.check_friendship_legacy(user_id, friend_id),
+                self.check_friendship_uuid(user_id, friend_id),
+            )
+            .await
+    }
+
+    // 按旧的字符串ID列检查好友关系
+    async fn check_friendship_legacy(
+        &self,
+        user_id: Uuid,
+        friend_id: Uuid,
     ) -> Result<Option<FriendshipStatus>> {
         let result = sqlx::query!(
             r#"
@@ -308,19 +436,42 @@ impl FriendshipRepository {
             user_id.to_string(),
             friend_id.to_string()
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(self.db.pool())
         .await?;
 
-        Ok(result.map(|r| {
-            let status_code = r.status.parse::<i32>().unwrap_or(0);
-            match status_code {
-                0 => FriendshipStatus::Pending,
-                1 => FriendshipStatus::Accepted,
-                2 => FriendshipStatus::Rejected,
-                3 => FriendshipStatus::Blocked,
-                _ => FriendshipStatus::Pending,
-            }
-        }))
+        Ok(result.map(|r| Self::parse_status(&r.status)))
+    }
+
+    // 按新的UUID列检查好友关系
+    async fn check_friendship_uuid(
+        &self,
+        user_id: Uuid,
+        friend_id: Uuid,
+    ) -> Result<Option<FriendshipStatus>> {
+        let status: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT status
+            FROM friendships
+            WHERE (user_uuid = $1 AND friend_uuid = $2) OR (user_uuid = $2 AND friend_uuid = $1)
+            "#,
+        )
+        .bind(user_id)
+        .bind(friend_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(status.map(|status| Self::parse_status(&status)))
+    }
+
+    // 解析数据库中的好友关系状态
+    fn parse_status(status: &str) -> FriendshipStatus {
+        match status.parse::<i32>().unwrap_or(0) {
+            0 => FriendshipStatus::Pending,
+            1 => FriendshipStatus::Accepted,
+            2 => FriendshipStatus::Rejected,
+            3 => FriendshipStatus::Blocked,
+            _ => FriendshipStatus::Pending,
+        }
     }
 
     // 检查用户是否存在
@@ -335,7 +486,7 @@ impl FriendshipRepository {
             "#,
             user_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
         Ok(result.exists)
     }
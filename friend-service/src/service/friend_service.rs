@@ -1,25 +1,49 @@
+use std::sync::Arc;
+
+use cache::Cache;
+use common::grpc::subject::check_subject;
+use common::grpc::tenant::current_tenant;
+use common::schema_migration::SchemaMigration;
+use common::tenant_db::PgRouter;
+use common::validation::{ContentPolicy, TextField};
 use common::proto::friend::friend_service_server::FriendService;
 use common::proto::friend::{
     AcceptFriendRequestRequest, CheckFriendshipRequest, CheckFriendshipResponse,
     DeleteFriendRequest, DeleteFriendResponse, FriendshipResponse, GetFriendListRequest,
     GetFriendListResponse, GetFriendRequestsRequest, GetFriendRequestsResponse,
+    GetGroupFriendsRequest, GetGroupFriendsResponse, GroupFriend,
     RejectFriendRequestRequest, SendFriendRequestRequest,FriendshipStatus,
 };
-use sqlx::PgPool;
 use tonic::{Request, Response, Status};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use crate::model::friendship::{GroupFriendCursor, GroupFriendSort};
 use crate::repository::friendship_repository::FriendshipRepository;
 
+/// 分组好友列表的默认每页数量
+const GROUP_FRIENDS_PAGE_SIZE: i64 = 20;
+
+/// 分组好友列表的每页数量上限
+const GROUP_FRIENDS_MAX_PAGE_SIZE: i64 = 100;
+
 pub struct FriendServiceImpl {
     repository: FriendshipRepository,
+    cache: Arc<dyn Cache>,
+    content_policy: ContentPolicy,
 }
 
 impl FriendServiceImpl {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(
+        db: PgRouter,
+        migration: SchemaMigration,
+        cache: Arc<dyn Cache>,
+        content_policy: ContentPolicy,
+    ) -> Self {
         Self {
-            repository: FriendshipRepository::new(pool),
+            repository: FriendshipRepository::new(db).with_migration(migration),
+            cache,
+            content_policy,
         }
     }
 
@@ -47,6 +71,7 @@ impl FriendService for FriendServiceImpl {
         &self,
         request: Request<SendFriendRequestRequest>,
     ) -> Result<Response<FriendshipResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
 
         let user_id = req
@@ -59,13 +84,12 @@ impl FriendService for FriendServiceImpl {
             .parse::<Uuid>()
             .map_err(|e| Status::invalid_argument(format!("无效的好友ID: {}", e)))?;
 
-        let message = &req.message;
-        let msg_length = message.chars().count();
-        if msg_length > 255 {
-            return Err(Status::invalid_argument(
-                format!("消息长度不能超过255个字符，当前长度: {}", msg_length)
-            ));
-        }
+        // 按租户的内容策略校验附言，敏感词可能被替换
+        let message = self.content_policy.check(
+            current_tenant().as_deref(),
+            TextField::FriendRequest,
+            &req.message,
+        )?;
         
         // 检查用户和好友是否存在
         self.check_user_exists(user_id).await?;
@@ -105,7 +129,7 @@ impl FriendService for FriendServiceImpl {
         // 创建好友请求
         match self
             .repository
-            .create_friend_request(user_id, friend_id, message.to_string())
+            .create_friend_request(user_id, friend_id, message)
             .await
         {
             Ok(friendship) => {
@@ -126,6 +150,7 @@ impl FriendService for FriendServiceImpl {
         &self,
         request: Request<AcceptFriendRequestRequest>,
     ) -> Result<Response<FriendshipResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
 
         let user_id = req
@@ -179,6 +204,7 @@ impl FriendService for FriendServiceImpl {
         &self,
         request: Request<RejectFriendRequestRequest>,
     ) -> Result<Response<FriendshipResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
 
         let user_id = req
@@ -298,6 +324,7 @@ impl FriendService for FriendServiceImpl {
         &self,
         request: Request<DeleteFriendRequest>,
     ) -> Result<Response<DeleteFriendResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
 
         let user_id = req
@@ -346,4 +373,86 @@ impl FriendService for FriendServiceImpl {
             }
         }
     }
+
+    // 获取分组内的好友
+    async fn get_group_friends(
+        &self,
+        request: Request<GetGroupFriendsRequest>,
+    ) -> Result<Response<GetGroupFriendsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+
+        let user_id = req
+            .user_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的用户ID: {}", e)))?;
+        let sort = GroupFriendSort::parse(&req.sort_by)
+            .ok_or_else(|| Status::invalid_argument(format!("无效的排序方式: {}", req.sort_by)))?;
+        let cursor = if req.cursor.is_empty() {
+            None
+        } else {
+            Some(
+                GroupFriendCursor::parse(&req.cursor)
+                    .ok_or_else(|| Status::invalid_argument("无效的分页游标"))?,
+            )
+        };
+        let group_id = (!req.group_id.is_empty()).then_some(req.group_id.as_str());
+        let page = req.page.max(1);
+        let page_size = if req.page_size > 0 {
+            req.page_size.min(GROUP_FRIENDS_MAX_PAGE_SIZE)
+        } else {
+            GROUP_FRIENDS_PAGE_SIZE
+        };
+
+        // 多查一条判断是否还有下一页
+        let mut rows = self
+            .repository
+            .get_group_friends(
+                user_id,
+                group_id,
+                sort,
+                cursor.as_ref(),
+                (page - 1).saturating_mul(page_size),
+                page_size + 1,
+            )
+            .await
+            .map_err(|e| {
+                error!("获取分组好友失败: {}", e);
+                Status::internal("获取分组好友失败")
+            })?;
+        let has_more = rows.len() as i64 > page_size;
+        rows.truncate(page_size as usize);
+        let next_cursor = match rows.last() {
+            Some((friend, sort_value)) if has_more => GroupFriendCursor {
+                friend_id: friend.id.to_string(),
+                sort_value: sort_value.clone(),
+            }
+            .encode(),
+            _ => String::new(),
+        };
+
+        // 查询在线状态，Redis不可用时按离线返回
+        let friend_ids: Vec<String> = rows.iter().map(|(friend, _)| friend.id.to_string()).collect();
+        let online = match self.cache.online_status(&friend_ids).await {
+            Ok(online) => online,
+            Err(e) => {
+                warn!("查询好友在线状态失败: {}", e);
+                vec![false; friend_ids.len()]
+            }
+        };
+
+        let friends = rows
+            .iter()
+            .zip(online)
+            .map(|((friend, _), online)| GroupFriend {
+                friend: Some(friend.to_proto()),
+                online,
+            })
+            .collect();
+
+        Ok(Response::new(GetGroupFriendsResponse {
+            friends,
+            next_cursor,
+        }))
+    }
 }
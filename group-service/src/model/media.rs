@@ -0,0 +1,71 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// 群媒体库支持的媒体类型
+pub const MEDIA_TYPES: [&str; 4] = ["image", "video", "audio", "file"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMedia {
+    pub id: String,
+    pub group_id: String,
+    pub msg_id: String,
+    pub sender_id: String,
+    pub media_type: String,
+    pub object_key: String,
+    pub url: String,
+    pub file_name: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub thumbnail_url: Option<String>,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl GroupMedia {
+    pub fn from_proto(media: common::proto::group::GroupMedia) -> Self {
+        let sent_at = media
+            .sent_at
+            .and_then(|ts| Utc.timestamp_opt(ts.seconds, ts.nanos.max(0) as u32).single())
+            .unwrap_or_else(Utc::now);
+
+        Self {
+            id: media.id,
+            group_id: media.group_id,
+            msg_id: media.msg_id,
+            sender_id: media.sender_id,
+            media_type: media.media_type,
+            object_key: media.object_key,
+            url: media.url,
+            file_name: media.file_name,
+            mime_type: media.mime_type,
+            size: media.size,
+            thumbnail_url: media.thumbnail_url,
+            sent_at,
+        }
+    }
+
+    pub fn to_proto(&self) -> common::proto::group::GroupMedia {
+        common::proto::group::GroupMedia {
+            id: self.id.clone(),
+            group_id: self.group_id.clone(),
+            msg_id: self.msg_id.clone(),
+            sender_id: self.sender_id.clone(),
+            media_type: self.media_type.clone(),
+            object_key: self.object_key.clone(),
+            url: self.url.clone(),
+            file_name: self.file_name.clone(),
+            mime_type: self.mime_type.clone(),
+            size: self.size,
+            thumbnail_url: self.thumbnail_url.clone(),
+            sent_at: Some(prost_types::Timestamp::from(SystemTime::from(self.sent_at))),
+        }
+    }
+}
+
+/// 群媒体查询条件
+#[derive(Debug, Clone, Default)]
+pub struct MediaFilter {
+    pub media_type: Option<String>,
+    pub sender_id: Option<String>,
+    pub keyword: Option<String>,
+}
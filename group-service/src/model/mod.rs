@@ -1,2 +1,4 @@
 pub mod group;
 pub mod member;
+pub mod media;
+pub mod poll;
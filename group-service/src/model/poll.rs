@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::SystemTime;
+
+/// 群投票的选项数量范围
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 20;
+
+/// 群投票问题和选项的最大字符数
+pub const MAX_POLL_QUESTION_CHARS: usize = 256;
+pub const MAX_POLL_OPTION_CHARS: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Poll {
+    pub id: String,
+    pub group_id: String,
+    pub creator_id: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub multi_choice: bool,
+    pub deadline: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 群投票的统计结果，由数据库按选项聚合
+#[derive(Debug, Clone, Default)]
+pub struct PollTally {
+    // 按选项编号排列的得票数
+    pub votes: Vec<i64>,
+    pub voter_count: i64,
+    // 请求者选择的选项
+    pub my_choices: Vec<i32>,
+}
+
+impl Poll {
+    // 已手动结束或已过截止时间，过期未结束的投票由后台任务补发结束通知
+    pub fn is_closed(&self, now: DateTime<Utc>) -> bool {
+        self.closed_at.is_some() || self.deadline.is_some_and(|deadline| deadline <= now)
+    }
+
+    // 校验投票选择，返回去重排序后的选项编号
+    pub fn check_choices(&self, choices: &[i32]) -> Result<Vec<i32>, String> {
+        let mut choices = choices.to_vec();
+        choices.sort_unstable();
+        choices.dedup();
+
+        if choices
+            .iter()
+            .any(|&index| index < 0 || index as usize >= self.options.len())
+        {
+            return Err("无效的投票选项".to_string());
+        }
+        if !self.multi_choice && choices.len() > 1 {
+            return Err("该投票只能选择一个选项".to_string());
+        }
+        Ok(choices)
+    }
+
+    pub fn to_proto(&self, tally: &PollTally) -> common::proto::group::Poll {
+        common::proto::group::Poll {
+            id: self.id.clone(),
+            group_id: self.group_id.clone(),
+            creator_id: self.creator_id.clone(),
+            question: self.question.clone(),
+            options: self
+                .options
+                .iter()
+                .enumerate()
+                .map(|(index, content)| common::proto::group::PollOption {
+                    index: index as i32,
+                    content: content.clone(),
+                    votes: tally.votes.get(index).copied().unwrap_or_default(),
+                })
+                .collect(),
+            multi_choice: self.multi_choice,
+            deadline: self.deadline.map(to_timestamp),
+            closed: self.is_closed(Utc::now()),
+            closed_at: self.closed_at.map(to_timestamp),
+            voter_count: tally.voter_count,
+            my_choices: tally.my_choices.clone(),
+            created_at: Some(to_timestamp(self.created_at)),
+        }
+    }
+
+    // 投票通知消息的内容，结果中不包含个人的选择
+    pub fn event_content(&self, event: &str, tally: &PollTally) -> Value {
+        json!({
+            "event": event,
+            "pollId": self.id,
+            "question": self.question,
+            "options": self
+                .options
+                .iter()
+                .enumerate()
+                .map(|(index, content)| json!({
+                    "index": index,
+                    "content": content,
+                    "votes": tally.votes.get(index).copied().unwrap_or_default(),
+                }))
+                .collect::<Vec<_>>(),
+            "multiChoice": self.multi_choice,
+            "deadline": self.deadline.map(|deadline| deadline.timestamp_millis()),
+            "closed": self.is_closed(Utc::now()),
+            "voterCount": tally.voter_count,
+        })
+    }
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp::from(SystemTime::from(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(multi_choice: bool) -> Poll {
+        Poll {
+            id: "p1".to_string(),
+            group_id: "g1".to_string(),
+            creator_id: "u1".to_string(),
+            question: "周五聚餐去哪".to_string(),
+            options: vec!["火锅".to_string(), "烧烤".to_string(), "日料".to_string()],
+            multi_choice,
+            deadline: None,
+            closed_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_check_choices_single() {
+        let poll = poll(false);
+        assert_eq!(poll.check_choices(&[1]), Ok(vec![1]));
+        // 重复的选项去重后只算一个
+        assert_eq!(poll.check_choices(&[2, 2]), Ok(vec![2]));
+        // 空选择表示撤回投票
+        assert_eq!(poll.check_choices(&[]), Ok(vec![]));
+        assert!(poll.check_choices(&[0, 1]).is_err());
+    }
+
+    #[test]
+    fn test_check_choices_multi() {
+        let poll = poll(true);
+        assert_eq!(poll.check_choices(&[2, 0, 2]), Ok(vec![0, 2]));
+        assert!(poll.check_choices(&[0, 3]).is_err());
+        assert!(poll.check_choices(&[-1]).is_err());
+    }
+
+    #[test]
+    fn test_is_closed() {
+        let now = Utc::now();
+        let mut poll = poll(false);
+        assert!(!poll.is_closed(now));
+
+        poll.deadline = Some(now + chrono::Duration::minutes(1));
+        assert!(!poll.is_closed(now));
+        poll.deadline = Some(now);
+        assert!(poll.is_closed(now));
+
+        poll.deadline = None;
+        poll.closed_at = Some(now);
+        assert!(poll.is_closed(now));
+    }
+}
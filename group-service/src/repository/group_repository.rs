@@ -1,17 +1,17 @@
 use anyhow::Result;
 use chrono::{TimeZone, Utc};
-use sqlx::PgPool;
+use common::tenant_db::PgRouter;
 use uuid::Uuid;
 
 use crate::model::group::{Group, UserGroup};
 
 pub struct GroupRepository {
-    pool: PgPool,
+    db: PgRouter,
 }
 
 impl GroupRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
     }
 
     // 创建群组
@@ -42,7 +42,7 @@ impl GroupRepository {
             created_at_naive,
             updated_at_naive
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(Group {
@@ -66,7 +66,7 @@ impl GroupRepository {
             "#,
             group_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(Group {
@@ -108,7 +108,7 @@ impl GroupRepository {
             now_naive,
             group_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(Group {
@@ -137,7 +137,7 @@ impl GroupRepository {
             "#,
             group_id.to_string()
         )
-        .execute(&self.pool)
+        .execute(self.db.pool())
         .await?
         .rows_affected();
 
@@ -154,7 +154,7 @@ impl GroupRepository {
             "#,
             group_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(result.count.unwrap_or(0) as i32)
@@ -177,7 +177,7 @@ impl GroupRepository {
             "#,
             user_id.to_string()
         )
-        .fetch_all(&self.pool)
+        .fetch_all(self.db.pool())
         .await?;
 
         let result = groups
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use common::tenant_db::PgRouter;
+use sqlx::{Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::model::media::{GroupMedia, MediaFilter};
+
+pub struct MediaRepository {
+    db: PgRouter,
+}
+
+impl MediaRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    // 索引一条群媒体，同一消息重复索引时忽略（消费消息可能重试）
+    pub async fn insert_media(&self, media: &GroupMedia) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO group_media (id, group_id, msg_id, sender_id, media_type, object_key, url,
+                                     file_name, mime_type, size, thumbnail_url, sent_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            ON CONFLICT (group_id, msg_id) DO NOTHING
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(&media.group_id)
+        .bind(&media.msg_id)
+        .bind(&media.sender_id)
+        .bind(&media.media_type)
+        .bind(&media.object_key)
+        .bind(&media.url)
+        .bind(&media.file_name)
+        .bind(&media.mime_type)
+        .bind(media.size)
+        .bind(&media.thumbnail_url)
+        .bind(media.sent_at.naive_utc())
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    // 分页查询群媒体，按发送时间倒序，返回当前页和总数
+    pub async fn list_media(
+        &self,
+        group_id: &str,
+        filter: &MediaFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<GroupMedia>, i64)> {
+        let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM group_media");
+        push_conditions(&mut count_query, group_id, filter);
+        let total: i64 = count_query
+            .build()
+            .fetch_one(self.db.pool())
+            .await?
+            .get(0);
+
+        let mut query = QueryBuilder::new(
+            "SELECT id, group_id, msg_id, sender_id, media_type, object_key, url, file_name, \
+             mime_type, size, thumbnail_url, sent_at FROM group_media",
+        );
+        push_conditions(&mut query, group_id, filter);
+        query
+            .push(" ORDER BY sent_at DESC, id LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let rows = query.build().fetch_all(self.db.pool()).await?;
+        let items = rows
+            .into_iter()
+            .map(|row| GroupMedia {
+                id: row.get("id"),
+                group_id: row.get("group_id"),
+                msg_id: row.get("msg_id"),
+                sender_id: row.get("sender_id"),
+                media_type: row.get("media_type"),
+                object_key: row.get("object_key"),
+                url: row.get("url"),
+                file_name: row.get("file_name"),
+                mime_type: row.get("mime_type"),
+                size: row.get("size"),
+                thumbnail_url: row.get("thumbnail_url"),
+                sent_at: Utc.from_utc_datetime(&row.get::<NaiveDateTime, _>("sent_at")),
+            })
+            .collect();
+
+        Ok((items, total))
+    }
+
+    // 删除原消息对应的群媒体，返回删除的条数
+    pub async fn delete_by_msg(&self, group_id: &str, msg_id: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM group_media WHERE group_id = $1 AND msg_id = $2")
+            .bind(group_id)
+            .bind(msg_id)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // 删除群组的全部媒体索引，群组解散时调用
+    pub async fn delete_by_group(&self, group_id: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM group_media WHERE group_id = $1")
+            .bind(group_id)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// 拼接查询条件
+fn push_conditions(query: &mut QueryBuilder<'_, Postgres>, group_id: &str, filter: &MediaFilter) {
+    query.push(" WHERE group_id = ").push_bind(group_id.to_string());
+    if let Some(media_type) = &filter.media_type {
+        query.push(" AND media_type = ").push_bind(media_type.clone());
+    }
+    if let Some(sender_id) = &filter.sender_id {
+        query.push(" AND sender_id = ").push_bind(sender_id.clone());
+    }
+    if let Some(keyword) = &filter.keyword {
+        query
+            .push(" AND file_name ILIKE ")
+            .push_bind(format!("%{}%", escape_like(keyword)))
+            .push(" ESCAPE '\\'");
+    }
+}
+
+// 转义LIKE通配符，关键字按字面匹配
+fn escape_like(keyword: &str) -> String {
+    keyword
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
@@ -1,18 +1,49 @@
 use anyhow::Result;
 use chrono::{TimeZone, Utc};
 use common::proto::group::MemberRole;
-use sqlx::PgPool;
+use common::schema_migration::SchemaMigration;
+use common::tenant_db::PgRouter;
+use sqlx::Row;
 use uuid::Uuid;
 
 use crate::model::member::Member;
 
+/// 群成员的群组ID和用户ID迁移为UUID列的表结构迁移名称
+pub const GROUP_MEMBERS_UUID_MIGRATION: &str = "group_members_uuid";
+
 pub struct MemberRepository {
-    pool: PgPool,
+    db: PgRouter,
+    migration: SchemaMigration,
 }
 
 impl MemberRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: PgRouter) -> Self {
+        Self {
+            db,
+            migration: SchemaMigration::disabled(GROUP_MEMBERS_UUID_MIGRATION),
+        }
+    }
+
+    // 设置群成员的表结构迁移开关
+    pub fn with_migration(mut self, migration: SchemaMigration) -> Self {
+        self.migration = migration;
+        self
+    }
+
+    // 迁移期间将群成员的群组ID和用户ID同步写入新的UUID列，迁移列在执行DDL之前不存在，使用运行时检查的查询
+    async fn sync_uuid_columns(&self, id: &str, op: &str) {
+        if !self.migration.dual_write() {
+            return;
+        }
+        if let Err(e) = sqlx::query(
+            "UPDATE group_members SET group_uuid = group_id::uuid, user_uuid = user_id::uuid WHERE id = $1",
+        )
+        .bind(id)
+        .execute(self.db.pool())
+        .await
+        {
+            self.migration.write_failed(op, e);
+        }
     }
 
     // 添加群组成员
@@ -42,8 +73,9 @@ impl MemberRepository {
             member.role.to_string(),
             joined_at_naive
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
+        self.sync_uuid_columns(&result.id, "add_member").await;
 
         Ok(Member {
             id: Uuid::parse_str(&result.id).unwrap(),
@@ -84,7 +116,7 @@ impl MemberRepository {
             group_id.to_string(),
             user_id.to_string()
         )
-        .execute(&self.pool)
+        .execute(self.db.pool())
         .await?
         .rows_affected();
 
@@ -126,7 +158,7 @@ impl MemberRepository {
             group_id.to_string(),
             user_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(Member {
@@ -156,7 +188,7 @@ impl MemberRepository {
             group_id.to_string(),
             user_id.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await?;
 
         Ok(Member {
@@ -182,7 +214,7 @@ impl MemberRepository {
             group_id.to_string(),
             user_id.to_string()
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(self.db.pool())
         .await?;
 
         match result {
@@ -193,6 +225,17 @@ impl MemberRepository {
 
     // 获取群组成员列表
     pub async fn get_members(&self, group_id: Uuid) -> Result<Vec<Member>> {
+        self.migration
+            .read(
+                "get_members",
+                self.get_members_legacy(group_id),
+                self.get_members_uuid(group_id),
+            )
+            .await
+    }
+
+    // 按旧的字符串ID列获取群组成员
+    async fn get_members_legacy(&self, group_id: Uuid) -> Result<Vec<Member>> {
         // 在真实环境中，这需要从user-service获取用户信息
         let members = sqlx::query!(
             r#"
@@ -205,7 +248,7 @@ impl MemberRepository {
             "#,
             group_id.to_string()
         )
-        .fetch_all(&self.pool)
+        .fetch_all(self.db.pool())
         .await?;
 
         let result = members
@@ -225,11 +268,59 @@ impl MemberRepository {
         Ok(result)
     }
 
+    // 按新的UUID列获取群组成员，排序与旧查询一致
+    async fn get_members_uuid(&self, group_id: Uuid) -> Result<Vec<Member>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT m.id, m.group_id, m.user_id, m.role, m.joined_at,
+                   u.username, u.nickname, u.avatar_url
+            FROM group_members m
+            JOIN users u ON m.user_id = u.id
+            WHERE m.group_uuid = $1
+            ORDER BY m.role DESC, m.joined_at ASC
+            "#,
+        )
+        .bind(group_id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        rows.into_iter()
+            .map(|row| -> Result<Member> {
+                let role: String = row.try_get("role")?;
+                Ok(Member {
+                    id: Uuid::parse_str(row.try_get("id")?)?,
+                    group_id: Uuid::parse_str(row.try_get("group_id")?)?,
+                    user_id: Uuid::parse_str(row.try_get("user_id")?)?,
+                    username: row.try_get("username")?,
+                    nickname: row.try_get("nickname")?,
+                    avatar_url: row.try_get("avatar_url")?,
+                    role: role.parse::<i32>().unwrap_or(0),
+                    joined_at: Utc.from_utc_datetime(&row.try_get("joined_at")?),
+                })
+            })
+            .collect()
+    }
+
     // 检查用户是否是群组成员
     pub async fn check_membership(
         &self,
         group_id: Uuid,
         user_id: Uuid,
+    ) -> Result<(bool, Option<i32>)> {
+        self.migration
+            .read(
+                "check_membership",
+                self.check_membership_legacy(group_id, user_id),
+                self.check_membership_uuid(group_id, user_id),
+            )
+            .await
+    }
+
+    // 按旧的字符串ID列检查成员资格
+    async fn check_membership_legacy(
+        &self,
+        group_id: Uuid,
+        user_id: Uuid,
     ) -> Result<(bool, Option<i32>)> {
         let result = sqlx::query!(
             r#"
@@ -240,7 +331,7 @@ impl MemberRepository {
             group_id.to_string(),
             user_id.to_string()
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(self.db.pool())
         .await?;
 
         match result {
@@ -248,4 +339,28 @@ impl MemberRepository {
             None => Ok((false, None)),
         }
     }
+
+    // 按新的UUID列检查成员资格
+    async fn check_membership_uuid(
+        &self,
+        group_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(bool, Option<i32>)> {
+        let role: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT role
+            FROM group_members
+            WHERE group_uuid = $1 AND user_uuid = $2
+            "#,
+        )
+        .bind(group_id)
+        .bind(user_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(match role {
+            Some(role) => (true, Some(role.parse::<i32>().unwrap_or(0))),
+            None => (false, None),
+        })
+    }
 }
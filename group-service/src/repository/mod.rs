@@ -1,2 +1,5 @@
 pub mod group_repository;
 pub mod member_repository;
+pub mod stats_repository;
+pub mod media_repository;
+pub mod poll_repository;
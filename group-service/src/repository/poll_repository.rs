@@ -0,0 +1,229 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use common::tenant_db::PgRouter;
+use sqlx::postgres::PgRow;
+use sqlx::Row;
+
+use crate::model::poll::{Poll, PollTally};
+
+const POLL_COLUMNS: &str =
+    "id, group_id, creator_id, question, options, multi_choice, deadline, closed_at, created_at";
+
+pub struct PollRepository {
+    db: PgRouter,
+}
+
+impl PollRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    // 创建群投票
+    pub async fn create_poll(&self, poll: &Poll) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO group_poll (id, group_id, creator_id, question, options, multi_choice,
+                                    deadline, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(&poll.id)
+        .bind(&poll.group_id)
+        .bind(&poll.creator_id)
+        .bind(&poll.question)
+        .bind(&poll.options)
+        .bind(poll.multi_choice)
+        .bind(poll.deadline.map(|deadline| deadline.naive_utc()))
+        .bind(poll.created_at.naive_utc())
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    // 查询群投票
+    pub async fn get_poll(&self, poll_id: &str) -> Result<Option<Poll>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM group_poll WHERE id = $1",
+            POLL_COLUMNS
+        ))
+        .bind(poll_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(row.as_ref().map(poll_from_row))
+    }
+
+    // 覆盖用户的投票，投票已结束时返回false
+    //
+    // 锁定投票行后再检查状态，与结束投票互斥，结束后的统计结果不会再变化
+    pub async fn replace_votes(
+        &self,
+        poll_id: &str,
+        user_id: &str,
+        choices: &[i32],
+    ) -> Result<bool> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let open = sqlx::query(
+            r#"
+            SELECT 1 FROM group_poll
+            WHERE id = $1 AND closed_at IS NULL AND (deadline IS NULL OR deadline > NOW() AT TIME ZONE 'UTC')
+            FOR SHARE
+            "#,
+        )
+        .bind(poll_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+        if !open {
+            return Ok(false);
+        }
+
+        sqlx::query("DELETE FROM group_poll_vote WHERE poll_id = $1 AND user_id = $2")
+            .bind(poll_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        if !choices.is_empty() {
+            sqlx::query(
+                r#"
+                INSERT INTO group_poll_vote (poll_id, user_id, option_index)
+                SELECT $1, $2, UNNEST($3::int4[])
+                "#,
+            )
+            .bind(poll_id)
+            .bind(user_id)
+            .bind(choices)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    // 统计各选项的得票数、投票人数和请求者的选择
+    pub async fn tally(&self, poll: &Poll, user_id: Option<&str>) -> Result<PollTally> {
+        let rows = sqlx::query(
+            r#"
+            SELECT option_index, COUNT(*) AS votes
+            FROM group_poll_vote
+            WHERE poll_id = $1
+            GROUP BY option_index
+            "#,
+        )
+        .bind(&poll.id)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut votes = vec![0; poll.options.len()];
+        for row in rows {
+            let index: i32 = row.get("option_index");
+            if let Some(count) = votes.get_mut(index as usize) {
+                *count = row.get("votes");
+            }
+        }
+
+        let voter_count: i64 =
+            sqlx::query("SELECT COUNT(DISTINCT user_id) FROM group_poll_vote WHERE poll_id = $1")
+                .bind(&poll.id)
+                .fetch_one(self.db.pool())
+                .await?
+                .get(0);
+
+        let my_choices = match user_id {
+            Some(user_id) => sqlx::query(
+                r#"
+                SELECT option_index FROM group_poll_vote
+                WHERE poll_id = $1 AND user_id = $2
+                ORDER BY option_index
+                "#,
+            )
+            .bind(&poll.id)
+            .bind(user_id)
+            .fetch_all(self.db.pool())
+            .await?
+            .iter()
+            .map(|row| row.get("option_index"))
+            .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(PollTally {
+            votes,
+            voter_count,
+            my_choices,
+        })
+    }
+
+    // 结束投票，返回结束时间；已结束或已过截止时间的投票返回None，由后台任务统一结束
+    pub async fn close_poll(
+        &self,
+        poll_id: &str,
+        closed_by: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let row = sqlx::query(
+            r#"
+            UPDATE group_poll SET closed_at = NOW() AT TIME ZONE 'UTC', closed_by = $2
+            WHERE id = $1 AND closed_at IS NULL
+              AND (deadline IS NULL OR deadline > NOW() AT TIME ZONE 'UTC')
+            RETURNING closed_at
+            "#,
+        )
+        .bind(poll_id)
+        .bind(closed_by)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(row.map(|row| Utc.from_utc_datetime(&row.get::<NaiveDateTime, _>("closed_at"))))
+    }
+
+    // 结束已过截止时间的投票，结束时间记为截止时间，返回本次结束的投票
+    pub async fn close_expired(&self, limit: i64) -> Result<Vec<Poll>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            UPDATE group_poll SET closed_at = deadline
+            WHERE id IN (
+                SELECT id FROM group_poll
+                WHERE closed_at IS NULL AND deadline <= NOW() AT TIME ZONE 'UTC'
+                ORDER BY deadline
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {}
+            "#,
+            POLL_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(rows.iter().map(poll_from_row).collect())
+    }
+
+    // 删除群组的全部投票，投票记录随之级联删除，群组解散时调用
+    pub async fn delete_by_group(&self, group_id: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM group_poll WHERE group_id = $1")
+            .bind(group_id)
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn poll_from_row(row: &PgRow) -> Poll {
+    let utc = |time: NaiveDateTime| Utc.from_utc_datetime(&time);
+    Poll {
+        id: row.get("id"),
+        group_id: row.get("group_id"),
+        creator_id: row.get("creator_id"),
+        question: row.get("question"),
+        options: row.get("options"),
+        multi_choice: row.get("multi_choice"),
+        deadline: row.get::<Option<NaiveDateTime>, _>("deadline").map(utc),
+        closed_at: row.get::<Option<NaiveDateTime>, _>("closed_at").map(utc),
+        created_at: utc(row.get("created_at")),
+    }
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use cache::GroupDailyStats;
+use common::tenant_db::PgRouter;
+use sqlx::Row;
+
+pub struct StatsRepository {
+    db: PgRouter,
+}
+
+impl StatsRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    // 写入（或覆盖）群组单日统计，汇总任务可重复执行
+    // 统计数据中没有租户信息，只写入群组所在的数据库集群
+    pub async fn upsert_daily_stats(&self, stats: &GroupDailyStats) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO group_daily_stats (group_id, stat_date, message_count, active_members, joins, leaves)
+            SELECT $1, $2, $3, $4, $5, $6
+            WHERE EXISTS (SELECT 1 FROM groups WHERE id = $1)
+            ON CONFLICT (group_id, stat_date) DO UPDATE
+            SET message_count = EXCLUDED.message_count,
+                active_members = EXCLUDED.active_members,
+                joins = EXCLUDED.joins,
+                leaves = EXCLUDED.leaves
+            "#,
+        )
+        .bind(&stats.group_id)
+        .bind(&stats.date)
+        .bind(stats.message_count)
+        .bind(stats.active_members)
+        .bind(stats.joins)
+        .bind(stats.leaves)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    // 查询群组最近若干天的统计，按日期倒序
+    pub async fn get_recent_stats(&self, group_id: &str, days: i64) -> Result<Vec<GroupDailyStats>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT group_id, stat_date, message_count, active_members, joins, leaves
+            FROM group_daily_stats
+            WHERE group_id = $1
+            ORDER BY stat_date DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(group_id)
+        .bind(days)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| GroupDailyStats {
+                group_id: row.get("group_id"),
+                date: row.get("stat_date"),
+                message_count: row.get("message_count"),
+                active_members: row.get("active_members"),
+                joins: row.get("joins"),
+                leaves: row.get("leaves"),
+            })
+            .collect())
+    }
+}
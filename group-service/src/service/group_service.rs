@@ -1,31 +1,132 @@
+use common::grpc::subject::check_subject;
+use common::grpc::tenant::current_tenant;
+use common::schema_migration::SchemaMigration;
+use common::tenant_db::PgRouter;
+use common::validation::{ContentPolicy, TextField};
 use common::proto::group::group_service_server::GroupService;
 use common::proto::group::{
-    AddMemberRequest, CheckMembershipRequest, CheckMembershipResponse, CreateGroupRequest,
-    DeleteGroupRequest, DeleteGroupResponse, GetGroupRequest, GetMembersRequest,
-    GetMembersResponse, GetUserGroupsRequest, GetUserGroupsResponse, GroupResponse, MemberResponse,
-    MemberRole, RemoveMemberRequest, RemoveMemberResponse, UpdateGroupRequest,
-    UpdateMemberRoleRequest,
+    AddMemberRequest, CheckMembershipRequest, CheckMembershipResponse, ClosePollRequest,
+    CreateGroupRequest, CreatePollRequest,
+    DeleteGroupRequest, DeleteGroupResponse, GetGroupRequest, GetGroupStatsRequest,
+    GetGroupStatsResponse, GetMembersRequest, GetMembersResponse, GetPollResultsRequest,
+    GetUserGroupsRequest, GetUserGroupsResponse, GroupResponse, MemberResponse,
+    IndexGroupMediaRequest, IndexGroupMediaResponse, ListGroupMediaRequest, ListGroupMediaResponse,
+    MemberRole, PollResponse, RemoveGroupMediaRequest, RemoveGroupMediaResponse, RemoveMemberRequest,
+    RemoveMemberResponse, UpdateGroupRequest, UpdateMemberRoleRequest, VotePollRequest,
 };
-use sqlx::PgPool;
+use cache::{Cache, GroupDailyStats};
+use chrono::{TimeZone, Utc};
+use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use tracing::{error, info};
 use uuid::Uuid;
 
+use crate::model::media::{GroupMedia, MediaFilter, MEDIA_TYPES};
+use crate::model::poll::{
+    Poll, MAX_POLL_OPTIONS, MAX_POLL_OPTION_CHARS, MAX_POLL_QUESTION_CHARS, MIN_POLL_OPTIONS,
+};
 use crate::repository::group_repository::GroupRepository;
+use crate::repository::media_repository::MediaRepository;
 use crate::repository::member_repository::MemberRepository;
+use crate::repository::poll_repository::PollRepository;
+use crate::repository::stats_repository::StatsRepository;
+use crate::service::poll_events::{PollEvent, PollNotifier};
+
+// 群组统计默认/最大历史天数
+const DEFAULT_STATS_DAYS: i32 = 7;
+const MAX_STATS_DAYS: i32 = 30;
+
+// 群媒体库默认/最大分页大小
+const DEFAULT_MEDIA_PAGE_SIZE: i32 = 20;
+const MAX_MEDIA_PAGE_SIZE: i32 = 100;
 
 pub struct GroupServiceImpl {
     group_repository: GroupRepository,
     member_repository: MemberRepository,
+    stats_repository: StatsRepository,
+    media_repository: MediaRepository,
+    poll_repository: PollRepository,
+    poll_notifier: PollNotifier,
+    cache: Arc<dyn Cache>,
+    content_policy: ContentPolicy,
 }
 
 impl GroupServiceImpl {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(
+        db: PgRouter,
+        cache: Arc<dyn Cache>,
+        migration: SchemaMigration,
+        poll_notifier: PollNotifier,
+        content_policy: ContentPolicy,
+    ) -> Self {
         Self {
-            group_repository: GroupRepository::new(pool.clone()),
-            member_repository: MemberRepository::new(pool),
+            group_repository: GroupRepository::new(db.clone()),
+            member_repository: MemberRepository::new(db.clone()).with_migration(migration),
+            stats_repository: StatsRepository::new(db.clone()),
+            media_repository: MediaRepository::new(db.clone()),
+            poll_repository: PollRepository::new(db),
+            poll_notifier,
+            cache,
+            content_policy,
+        }
+    }
+
+    // 按租户的内容策略校验群名称或群公告，返回去除首尾空白、敏感词替换后的文本
+    fn check_text(&self, field: TextField, text: &str) -> Result<String, Status> {
+        Ok(self
+            .content_policy
+            .check(current_tenant().as_deref(), field, text)?)
+    }
+
+    // 查询请求者在群内的角色，不是群成员时拒绝
+    async fn require_member(&self, group_id: &str, user_id: &str) -> Result<i32, Status> {
+        let group_id = group_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的群组ID: {}", e)))?;
+        let user_id = user_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的用户ID: {}", e)))?;
+
+        self.member_repository
+            .get_member_role(group_id, user_id)
+            .await
+            .map_err(|_| Status::permission_denied("操作者不是群组成员"))
+    }
+
+    // 查询群内的投票
+    async fn load_poll(&self, group_id: &str, poll_id: &str) -> Result<Poll, Status> {
+        match self.poll_repository.get_poll(poll_id).await {
+            Ok(Some(poll)) if poll.group_id == group_id => Ok(poll),
+            Ok(_) => Err(Status::not_found("投票不存在")),
+            Err(e) => {
+                error!("查询投票失败: {}", e);
+                Err(Status::internal("查询投票失败"))
+            }
         }
     }
+
+    // 投票及请求者视角的统计结果
+    async fn poll_response(&self, poll: &Poll, user_id: &str) -> Result<Response<PollResponse>, Status> {
+        match self.poll_repository.tally(poll, Some(user_id)).await {
+            Ok(tally) => Ok(Response::new(PollResponse {
+                poll: Some(poll.to_proto(&tally)),
+            })),
+            Err(e) => {
+                error!("统计投票结果失败: {}", e);
+                Err(Status::internal("统计投票结果失败"))
+            }
+        }
+    }
+}
+
+fn stats_to_proto(stats: GroupDailyStats) -> common::proto::group::GroupDailyStats {
+    common::proto::group::GroupDailyStats {
+        date: stats.date,
+        message_count: stats.message_count,
+        active_members: stats.active_members,
+        joins: stats.joins,
+        leaves: stats.leaves,
+    }
 }
 
 #[tonic::async_trait]
@@ -35,6 +136,7 @@ impl GroupService for GroupServiceImpl {
         &self,
         request: Request<CreateGroupRequest>,
     ) -> Result<Response<GroupResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().owner_id)?;
         let req = request.into_inner();
 
         let owner_id = req
@@ -42,9 +144,13 @@ impl GroupService for GroupServiceImpl {
             .parse::<Uuid>()
             .map_err(|e| Status::invalid_argument(format!("无效的用户ID: {}", e)))?;
 
+        // 群描述即群公告
+        let name = self.check_text(TextField::GroupName, &req.name)?;
+        let description = self.check_text(TextField::GroupAnnouncement, &req.description)?;
+
         match self
             .group_repository
-            .create_group(req.name, req.description, req.avatar_url, owner_id)
+            .create_group(name, description, req.avatar_url, owner_id)
             .await
         {
             Ok(group) => {
@@ -117,6 +223,7 @@ impl GroupService for GroupServiceImpl {
         &self,
         request: Request<UpdateGroupRequest>,
     ) -> Result<Response<GroupResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
 
         let group_id = req
@@ -124,9 +231,30 @@ impl GroupService for GroupServiceImpl {
             .parse::<Uuid>()
             .map_err(|e| Status::invalid_argument(format!("无效的群组ID: {}", e)))?;
 
+        let user_id = req
+            .user_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的用户ID: {}", e)))?;
+
+        // 只有群主和管理员可以修改群名称、群公告和头像
+        match self.member_repository.get_member_role(group_id, user_id).await {
+            Ok(role) if role >= MemberRole::Admin as i32 => {}
+            Ok(_) => return Err(Status::permission_denied("只有群主或管理员可以更新群组信息")),
+            Err(_) => return Err(Status::permission_denied("操作者不是群组成员")),
+        }
+
+        let name = req
+            .name
+            .map(|name| self.check_text(TextField::GroupName, &name))
+            .transpose()?;
+        let description = req
+            .description
+            .map(|description| self.check_text(TextField::GroupAnnouncement, &description))
+            .transpose()?;
+
         match self
             .group_repository
-            .update_group(group_id, req.name, req.description, req.avatar_url)
+            .update_group(group_id, name, description, req.avatar_url)
             .await
         {
             Ok(group) => {
@@ -153,6 +281,7 @@ impl GroupService for GroupServiceImpl {
         &self,
         request: Request<DeleteGroupRequest>,
     ) -> Result<Response<DeleteGroupResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
 
         let group_id = req
@@ -169,6 +298,13 @@ impl GroupService for GroupServiceImpl {
             Ok(success) => {
                 if success {
                     info!("删除群组成功: {}", group_id);
+                    // 清理群媒体索引，失败不影响删除结果
+                    if let Err(e) = self.media_repository.delete_by_group(&group_id.to_string()).await {
+                        error!("清理群媒体索引失败: {}", e);
+                    }
+                    if let Err(e) = self.poll_repository.delete_by_group(&group_id.to_string()).await {
+                        error!("清理群投票失败: {}", e);
+                    }
                     Ok(Response::new(DeleteGroupResponse { success }))
                 } else {
                     Err(Status::not_found("群组不存在"))
@@ -190,6 +326,7 @@ impl GroupService for GroupServiceImpl {
         &self,
         request: Request<AddMemberRequest>,
     ) -> Result<Response<MemberResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().added_by_id)?;
         let req = request.into_inner();
 
         let group_id = req
@@ -271,6 +408,7 @@ impl GroupService for GroupServiceImpl {
         &self,
         request: Request<RemoveMemberRequest>,
     ) -> Result<Response<RemoveMemberResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().removed_by_id)?;
         let req = request.into_inner();
 
         let group_id = req
@@ -322,6 +460,7 @@ impl GroupService for GroupServiceImpl {
         &self,
         request: Request<UpdateMemberRoleRequest>,
     ) -> Result<Response<MemberResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().updated_by_id)?;
         let req = request.into_inner();
 
         let group_id = req
@@ -453,4 +592,306 @@ impl GroupService for GroupServiceImpl {
             }
         }
     }
+
+    // 获取群组统计数据
+    async fn get_group_stats(
+        &self,
+        request: Request<GetGroupStatsRequest>,
+    ) -> Result<Response<GetGroupStatsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+
+        let group_id = req
+            .group_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的群组ID: {}", e)))?;
+
+        let user_id = req
+            .user_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的用户ID: {}", e)))?;
+
+        // 只有群主和管理员可以查看统计
+        match self.member_repository.get_member_role(group_id, user_id).await {
+            Ok(role) if role >= MemberRole::Admin as i32 => {}
+            Ok(_) => return Err(Status::permission_denied("只有群主或管理员可以查看群组统计")),
+            Err(_) => return Err(Status::permission_denied("操作者不是群组成员")),
+        }
+
+        let days = match req.days {
+            d if d <= 0 => DEFAULT_STATS_DAYS,
+            d => d.min(MAX_STATS_DAYS),
+        };
+
+        let group_id = group_id.to_string();
+        let today = chrono::Utc::now().format("%Y%m%d").to_string();
+
+        let today_stats = match self.cache.get_group_daily_stats(&group_id, &today).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                error!("获取群组当日统计失败: {}", e);
+                return Err(Status::internal("获取群组统计失败"));
+            }
+        };
+
+        let history = match self
+            .stats_repository
+            .get_recent_stats(&group_id, days as i64)
+            .await
+        {
+            Ok(history) => history,
+            Err(e) => {
+                error!("获取群组历史统计失败: {}", e);
+                return Err(Status::internal("获取群组统计失败"));
+            }
+        };
+
+        Ok(Response::new(GetGroupStatsResponse {
+            today: Some(stats_to_proto(today_stats)),
+            history: history.into_iter().map(stats_to_proto).collect(),
+        }))
+    }
+
+    // 索引群聊中发送的附件
+    async fn index_group_media(
+        &self,
+        request: Request<IndexGroupMediaRequest>,
+    ) -> Result<Response<IndexGroupMediaResponse>, Status> {
+        let media = request
+            .into_inner()
+            .media
+            .ok_or_else(|| Status::invalid_argument("缺少群媒体信息"))?;
+        if media.group_id.is_empty() || media.msg_id.is_empty() {
+            return Err(Status::invalid_argument("群组ID和消息ID不能为空"));
+        }
+        if !MEDIA_TYPES.contains(&media.media_type.as_str()) {
+            return Err(Status::invalid_argument(format!("无效的媒体类型: {}", media.media_type)));
+        }
+
+        let media = GroupMedia::from_proto(media);
+        if let Err(e) = self.media_repository.insert_media(&media).await {
+            error!("索引群媒体失败: {}", e);
+            return Err(Status::internal("索引群媒体失败"));
+        }
+
+        Ok(Response::new(IndexGroupMediaResponse {}))
+    }
+
+    // 分页浏览、搜索群媒体（仅群成员）
+    async fn list_group_media(
+        &self,
+        request: Request<ListGroupMediaRequest>,
+    ) -> Result<Response<ListGroupMediaResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+
+        let group_id = req
+            .group_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的群组ID: {}", e)))?;
+
+        let user_id = req
+            .user_id
+            .parse::<Uuid>()
+            .map_err(|e| Status::invalid_argument(format!("无效的用户ID: {}", e)))?;
+
+        if self.member_repository.get_member_role(group_id, user_id).await.is_err() {
+            return Err(Status::permission_denied("操作者不是群组成员"));
+        }
+
+        let media_type = Some(req.media_type.trim().to_lowercase()).filter(|t| !t.is_empty());
+        if let Some(media_type) = &media_type {
+            if !MEDIA_TYPES.contains(&media_type.as_str()) {
+                return Err(Status::invalid_argument(format!("无效的媒体类型: {}", media_type)));
+            }
+        }
+        let filter = MediaFilter {
+            media_type,
+            sender_id: Some(req.sender_id.trim().to_string()).filter(|s| !s.is_empty()),
+            keyword: Some(req.keyword.trim().to_string()).filter(|k| !k.is_empty()),
+        };
+
+        let page = req.page.max(1);
+        let page_size = match req.page_size {
+            size if size <= 0 => DEFAULT_MEDIA_PAGE_SIZE,
+            size => size.min(MAX_MEDIA_PAGE_SIZE),
+        };
+        let offset = (page as i64 - 1) * page_size as i64;
+
+        match self
+            .media_repository
+            .list_media(&group_id.to_string(), &filter, page_size as i64, offset)
+            .await
+        {
+            Ok((items, total)) => Ok(Response::new(ListGroupMediaResponse {
+                items: items.iter().map(|m| m.to_proto()).collect(),
+                total,
+            })),
+            Err(e) => {
+                error!("查询群媒体失败: {}", e);
+                Err(Status::internal("查询群媒体失败"))
+            }
+        }
+    }
+
+    // 原消息撤回或附件被拦截时移除群媒体
+    async fn remove_group_media(
+        &self,
+        request: Request<RemoveGroupMediaRequest>,
+    ) -> Result<Response<RemoveGroupMediaResponse>, Status> {
+        let req = request.into_inner();
+
+        match self
+            .media_repository
+            .delete_by_msg(&req.group_id, &req.msg_id)
+            .await
+        {
+            Ok(removed) => {
+                if removed > 0 {
+                    info!("移除群媒体: 群组 {}，消息 {}", req.group_id, req.msg_id);
+                }
+                Ok(Response::new(RemoveGroupMediaResponse {
+                    removed: removed as i64,
+                }))
+            }
+            Err(e) => {
+                error!("移除群媒体失败: {}", e);
+                Err(Status::internal("移除群媒体失败"))
+            }
+        }
+    }
+
+    // 发起群投票
+    async fn create_poll(
+        &self,
+        request: Request<CreatePollRequest>,
+    ) -> Result<Response<PollResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        self.require_member(&req.group_id, &req.user_id).await?;
+
+        let question = req.question.trim().to_string();
+        if question.is_empty() || question.chars().count() > MAX_POLL_QUESTION_CHARS {
+            return Err(Status::invalid_argument(format!(
+                "投票问题不能为空且不能超过 {} 个字符",
+                MAX_POLL_QUESTION_CHARS
+            )));
+        }
+        let options: Vec<String> = req.options.iter().map(|o| o.trim().to_string()).collect();
+        if options.len() < MIN_POLL_OPTIONS || options.len() > MAX_POLL_OPTIONS {
+            return Err(Status::invalid_argument(format!(
+                "投票选项需要 {} 到 {} 个",
+                MIN_POLL_OPTIONS, MAX_POLL_OPTIONS
+            )));
+        }
+        if options
+            .iter()
+            .any(|o| o.is_empty() || o.chars().count() > MAX_POLL_OPTION_CHARS)
+        {
+            return Err(Status::invalid_argument(format!(
+                "投票选项不能为空且不能超过 {} 个字符",
+                MAX_POLL_OPTION_CHARS
+            )));
+        }
+
+        let now = Utc::now();
+        let deadline = req
+            .deadline
+            .and_then(|ts| Utc.timestamp_opt(ts.seconds, ts.nanos.max(0) as u32).single());
+        if deadline.is_some_and(|deadline| deadline <= now) {
+            return Err(Status::invalid_argument("截止时间必须晚于当前时间"));
+        }
+
+        let poll = Poll {
+            id: Uuid::new_v4().to_string(),
+            group_id: req.group_id,
+            creator_id: req.user_id,
+            question,
+            options,
+            multi_choice: req.multi_choice,
+            deadline,
+            closed_at: None,
+            created_at: now,
+        };
+        if let Err(e) = self.poll_repository.create_poll(&poll).await {
+            error!("创建群投票失败: {}", e);
+            return Err(Status::internal("创建群投票失败"));
+        }
+        info!("创建群投票成功: 群组 {}，投票 {}", poll.group_id, poll.id);
+
+        self.poll_notifier.notify(&poll, PollEvent::Created).await;
+        self.poll_response(&poll, &poll.creator_id).await
+    }
+
+    // 投票，重复投票时覆盖之前的选择
+    async fn vote_poll(
+        &self,
+        request: Request<VotePollRequest>,
+    ) -> Result<Response<PollResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        self.require_member(&req.group_id, &req.user_id).await?;
+
+        let poll = self.load_poll(&req.group_id, &req.poll_id).await?;
+        let choices = poll
+            .check_choices(&req.option_indexes)
+            .map_err(Status::invalid_argument)?;
+
+        match self
+            .poll_repository
+            .replace_votes(&poll.id, &req.user_id, &choices)
+            .await
+        {
+            Ok(true) => self.poll_response(&poll, &req.user_id).await,
+            Ok(false) => Err(Status::failed_precondition("投票已结束")),
+            Err(e) => {
+                error!("投票失败: {}", e);
+                Err(Status::internal("投票失败"))
+            }
+        }
+    }
+
+    // 查询投票和统计结果
+    async fn get_poll_results(
+        &self,
+        request: Request<GetPollResultsRequest>,
+    ) -> Result<Response<PollResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        self.require_member(&req.group_id, &req.user_id).await?;
+
+        let poll = self.load_poll(&req.group_id, &req.poll_id).await?;
+        self.poll_response(&poll, &req.user_id).await
+    }
+
+    // 结束投票（发起人或群主、管理员）
+    async fn close_poll(
+        &self,
+        request: Request<ClosePollRequest>,
+    ) -> Result<Response<PollResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        let role = self.require_member(&req.group_id, &req.user_id).await?;
+
+        let mut poll = self.load_poll(&req.group_id, &req.poll_id).await?;
+        if poll.creator_id != req.user_id && role < MemberRole::Admin as i32 {
+            return Err(Status::permission_denied("只有发起人或群主、管理员可以结束投票"));
+        }
+
+        match self.poll_repository.close_poll(&poll.id, &req.user_id).await {
+            Ok(Some(closed_at)) => {
+                poll.closed_at = Some(closed_at);
+                info!("结束群投票: 群组 {}，投票 {}", poll.group_id, poll.id);
+                self.poll_notifier.notify(&poll, PollEvent::Closed).await;
+            }
+            // 已结束的投票直接返回结果，到期未结束的由后台任务发送结束通知
+            Ok(None) => {}
+            Err(e) => {
+                error!("结束群投票失败: {}", e);
+                return Err(Status::internal("结束群投票失败"));
+            }
+        }
+
+        self.poll_response(&poll, &req.user_id).await
+    }
 }
@@ -1 +1,3 @@
 pub mod group_service;
+pub mod stats_rollup;
+pub mod poll_events;
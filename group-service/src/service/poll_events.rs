@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::grpc_client::ChatServiceGrpcClient;
+use common::message::{ContentType, Msg, MsgType, SendMsgRequest};
+use common::time_sync::now_millis;
+use tracing::{error, info, warn};
+
+use crate::model::poll::Poll;
+use crate::repository::poll_repository::PollRepository;
+
+/// 过期投票检查间隔
+const CLOSE_EXPIRED_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 单次结束的过期投票数量
+const CLOSE_EXPIRED_BATCH: i64 = 100;
+
+/// 投票的生命周期事件
+#[derive(Debug, Clone, Copy)]
+pub enum PollEvent {
+    Created,
+    Closed,
+}
+
+impl PollEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PollEvent::Created => "created",
+            PollEvent::Closed => "closed",
+        }
+    }
+}
+
+/// 投票通知
+///
+/// 发起和结束投票时以群的名义发送群消息通知群成员（发送者ID为群ID），消息内容为投票当时的统计结果，
+/// 不会以发起人或结束人的身份发出；投票过程中的得票变化不发消息，客户端打开投票时查询最新结果
+#[derive(Clone)]
+pub struct PollNotifier {
+    repository: Arc<PollRepository>,
+    chat_client: ChatServiceGrpcClient,
+}
+
+impl PollNotifier {
+    pub fn new(repository: Arc<PollRepository>, chat_client: ChatServiceGrpcClient) -> Self {
+        Self {
+            repository,
+            chat_client,
+        }
+    }
+
+    // 以群消息发送投票事件，失败只记录日志，不影响投票本身
+    pub async fn notify(&self, poll: &Poll, event: PollEvent) {
+        let tally = match self.repository.tally(poll, None).await {
+            Ok(tally) => tally,
+            Err(e) => {
+                error!("统计投票 {} 结果失败: {}", poll.id, e);
+                return;
+            }
+        };
+
+        let request = SendMsgRequest {
+            message: Some(Msg {
+                send_id: poll.group_id.clone(),
+                receiver_id: poll.group_id.clone(),
+                // 同一投票的同一事件使用固定的local_id，便于客户端去重
+                local_id: format!("poll:{}:{}", poll.id, event.as_str()),
+                create_time: now_millis(),
+                msg_type: MsgType::GroupMsg as i32,
+                content_type: ContentType::Poll as i32,
+                content: poll
+                    .event_content(event.as_str(), &tally)
+                    .to_string()
+                    .into_bytes(),
+                ..Default::default()
+            }),
+        };
+        if let Err(e) = self.chat_client.send_msg(request).await {
+            warn!("发送投票 {} 的 {} 通知失败: {}", poll.id, event.as_str(), e);
+        }
+    }
+
+    // 启动过期投票结束任务，到达截止时间的投票发送结束通知
+    pub fn start_closer(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLOSE_EXPIRED_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.close_expired().await;
+            }
+        })
+    }
+
+    async fn close_expired(&self) {
+        loop {
+            let polls = match self.repository.close_expired(CLOSE_EXPIRED_BATCH).await {
+                Ok(polls) => polls,
+                Err(e) => {
+                    error!("结束过期投票失败: {}", e);
+                    return;
+                }
+            };
+
+            for poll in &polls {
+                self.notify(poll, PollEvent::Closed).await;
+            }
+            if !polls.is_empty() {
+                info!("已结束 {} 个到期的群投票", polls.len());
+            }
+            if (polls.len() as i64) < CLOSE_EXPIRED_BATCH {
+                return;
+            }
+        }
+    }
+}
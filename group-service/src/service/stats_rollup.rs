@@ -0,0 +1,80 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use cache::Cache;
+use chrono::{Days, Utc};
+use tracing::{error, info};
+
+use crate::repository::stats_repository::StatsRepository;
+
+/// 每日汇总在UTC零点之后延迟执行的时间，等待消费者处理完前一天的尾部消息
+const ROLLUP_DELAY_AFTER_MIDNIGHT: Duration = Duration::from_secs(5 * 60);
+
+/// 群组统计每日汇总任务
+///
+/// 将Redis中前一天的群组计数器写入Postgres，Redis中的数据随后自然过期。
+/// 计数器中没有租户信息，每个群组都提交给全部数据库集群，只有群组所在的集群会写入
+pub struct StatsRollup {
+    cache: Arc<dyn Cache>,
+    repositories: Vec<StatsRepository>,
+}
+
+impl StatsRollup {
+    pub fn new(cache: Arc<dyn Cache>, repositories: Vec<StatsRepository>) -> Self {
+        Self {
+            cache,
+            repositories,
+        }
+    }
+
+    // 启动后台汇总任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            // 启动时补做一次前一天的汇总，覆盖服务在零点附近重启的情况
+            self.rollup_yesterday().await;
+
+            loop {
+                tokio::time::sleep(Self::until_next_run()).await;
+                self.rollup_yesterday().await;
+            }
+        })
+    }
+
+    async fn rollup_yesterday(&self) {
+        let date = match Utc::now().date_naive().checked_sub_days(Days::new(1)) {
+            Some(d) => d.format("%Y%m%d").to_string(),
+            None => return,
+        };
+
+        if let Err(e) = self.rollup(&date).await {
+            error!("群组统计汇总失败 {}: {}", date, e);
+        }
+    }
+
+    // 汇总指定日期的统计数据
+    pub async fn rollup(&self, date: &str) -> anyhow::Result<usize> {
+        let groups = self.cache.group_stats_dirty_groups(date).await?;
+
+        for group_id in &groups {
+            let stats = self.cache.get_group_daily_stats(group_id, date).await?;
+            for repository in &self.repositories {
+                repository.upsert_daily_stats(&stats).await?;
+            }
+        }
+
+        info!("群组统计汇总完成: {}，共 {} 个群组", date, groups.len());
+        Ok(groups.len())
+    }
+
+    fn until_next_run() -> Duration {
+        let now = Utc::now();
+        let next_midnight = now
+            .date_naive()
+            .succ_opt()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|dt| dt.and_utc())
+            .unwrap_or(now);
+
+        (next_midnight - now).to_std().unwrap_or_default() + ROLLUP_DELAY_AFTER_MIDNIGHT
+    }
+}
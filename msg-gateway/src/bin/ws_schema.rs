@@ -0,0 +1,11 @@
+//! 生成WebSocket协议描述文件
+//!
+//! `cargo run -p msg-gateway --bin ws_schema > docs/ws_schema.json`
+
+fn main() {
+    let schema = msg_gateway::schema::ws_schema();
+    println!(
+        "{}",
+        serde_json::to_string_pretty(schema).expect("协议描述可以序列化为JSON")
+    );
+}
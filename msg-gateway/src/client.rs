@@ -1,12 +1,14 @@
 use axum::body::Bytes;
 use axum::extract::ws::{Message, Utf8Bytes, WebSocket};
-use common::message::PlatformType;
+use common::message::{Msg, PlatformType};
 use futures::stream::SplitSink;
 use futures::SinkExt;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::RwLock;
 
+use crate::outbound::{OutboundQueue, PushOutcome};
+
 type ClientSender = Arc<RwLock<SplitSink<WebSocket, Message>>>;
 
 /// client
@@ -20,10 +22,22 @@ pub struct Client {
     pub platform_id: String,
     pub platform: PlatformType,
     pub notify_sender: Sender<()>,
+    // bounded outbound queue, drained by the connection's writer task
+    pub outbound: Arc<OutboundQueue>,
 }
 
 #[allow(dead_code)]
 impl Client {
+    /// enqueue a serialized message without waiting for the client to read it
+    pub fn push_msg(&self, content: Vec<u8>, msg: &Msg) -> PushOutcome {
+        let critical = !OutboundQueue::is_droppable(msg.msg_type);
+        // only messages received by this user carry its receive sequence,
+        // replies and echoes of its own messages do not take part in resuming
+        let seq = if msg.send_id != self.user_id { msg.seq } else { 0 };
+        self.outbound
+            .push(Message::Binary(Bytes::from(content)), seq, critical)
+    }
+
     pub async fn send_text(&self, msg: String) -> Result<(), axum::Error> {
         self.sender
             .write()
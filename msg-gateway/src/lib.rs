@@ -1,4 +1,12 @@
 mod client;
+mod limiter;
 mod manager;
+mod outbound;
+pub mod push_stream;
+pub mod receipt;
 pub mod rpc;
+pub mod schema;
+mod tls;
+mod topology;
+mod version;
 pub mod ws_server;
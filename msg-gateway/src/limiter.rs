@@ -0,0 +1,293 @@
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use dashmap::DashMap;
+use tracing::warn;
+
+use common::config::ConnectionLimitConfig;
+
+/// 连接被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// 达到实例的最大连接数
+    Global,
+    /// 达到单个IP的最大连接数
+    PerIp,
+    /// 新连接接入过快，被重连限流
+    Throttled,
+}
+
+/// WebSocket连接数限制
+///
+/// 在升级连接前占用名额，连接结束时由 [`ConnectionPermit`] 释放，
+/// 防止大量空闲连接耗尽网关资源
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    config: ConnectionLimitConfig,
+    // 当前连接总数
+    total: AtomicUsize,
+    // 每个IP的当前连接数
+    per_ip: DashMap<IpAddr, usize>,
+    // 因全局上限被拒绝的次数
+    rejected_global: AtomicU64,
+    // 因单IP上限被拒绝的次数
+    rejected_per_ip: AtomicU64,
+    // 新连接接入限流，未配置接入速率时为None
+    throttle: Option<TokenBucket>,
+    // 为优先接入保留的令牌数，小于令牌桶容量
+    reserve: f64,
+    // 因接入限流被拒绝的次数
+    rejected_throttled: AtomicU64,
+    // 使用保留令牌优先接入的次数
+    admitted_priority: AtomicU64,
+}
+
+impl ConnectionLimiter {
+    pub fn new(config: ConnectionLimitConfig) -> Self {
+        let burst = config.accept_burst.max(1);
+        let throttle = (config.accept_rate > 0)
+            .then(|| TokenBucket::new(config.accept_rate as f64, burst as f64));
+        // 保留的令牌占满令牌桶时普通客户端无法接入，至少给普通客户端留一个
+        let reserve = if config.accept_rate > 0 && config.priority_reserve >= burst {
+            warn!(
+                "priority_reserve {} 不小于 accept_burst {}，按 {} 处理",
+                config.priority_reserve,
+                burst,
+                burst - 1
+            );
+            burst - 1
+        } else {
+            config.priority_reserve
+        };
+        Self {
+            config,
+            reserve: reserve as f64,
+            total: AtomicUsize::new(0),
+            per_ip: DashMap::new(),
+            rejected_global: AtomicU64::new(0),
+            rejected_per_ip: AtomicU64::new(0),
+            throttle,
+            rejected_throttled: AtomicU64::new(0),
+            admitted_priority: AtomicU64::new(0),
+        }
+    }
+
+    /// 新连接接入限流
+    ///
+    /// 普通客户端只能使用保留数量以外的令牌；令牌不足时才等待 `priority`
+    /// 判断客户端能否优先接入，可以则使用保留的令牌
+    pub async fn admit<F>(&self, priority: F) -> Result<(), RejectReason>
+    where
+        F: Future<Output = bool>,
+    {
+        let Some(bucket) = &self.throttle else {
+            return Ok(());
+        };
+        if bucket.try_take(Instant::now(), self.reserve) {
+            return Ok(());
+        }
+        if self.reserve > 0.0 && priority.await && bucket.try_take(Instant::now(), 0.0) {
+            self.admitted_priority.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        self.rejected_throttled.fetch_add(1, Ordering::Relaxed);
+        Err(RejectReason::Throttled)
+    }
+
+    /// 建议客户端的重试间隔（秒），在配置的间隔上附加随机抖动，避免被拒绝的客户端同时重试
+    pub fn retry_after(&self) -> u64 {
+        let jitter = match self.config.retry_jitter_secs {
+            0 => 0,
+            max => rand::random_range(0..=max),
+        };
+        self.config.retry_after_secs + jitter
+    }
+
+    pub fn config(&self) -> &ConnectionLimitConfig {
+        &self.config
+    }
+
+    /// 为新连接占用名额
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit, RejectReason> {
+        // 先检查单IP上限，持有该IP的条目锁直到占用完成
+        let mut entry = self.per_ip.entry(ip).or_insert(0);
+        if self.config.max_per_ip > 0 && *entry >= self.config.max_per_ip {
+            self.rejected_per_ip.fetch_add(1, Ordering::Relaxed);
+            return Err(RejectReason::PerIp);
+        }
+
+        let max = self.config.max_connections;
+        let acquired = self
+            .total
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |total| {
+                (max == 0 || total < max).then_some(total + 1)
+            })
+            .is_ok();
+        if !acquired {
+            self.rejected_global.fetch_add(1, Ordering::Relaxed);
+            drop(entry);
+            self.per_ip.remove_if(&ip, |_, count| *count == 0);
+            return Err(RejectReason::Global);
+        }
+
+        *entry += 1;
+        Ok(ConnectionPermit {
+            limiter: self.clone(),
+            ip,
+        })
+    }
+
+    fn release(&self, ip: &IpAddr) {
+        self.total.fetch_sub(1, Ordering::AcqRel);
+        if let Some(mut entry) = self.per_ip.get_mut(ip) {
+            *entry = entry.saturating_sub(1);
+        }
+        self.per_ip.remove_if(ip, |_, count| *count == 0);
+    }
+
+    /// 当前连接总数
+    pub fn active(&self) -> usize {
+        self.total.load(Ordering::Acquire)
+    }
+
+    /// 以Prometheus文本格式输出连接指标
+    pub fn render_metrics(&self) -> String {
+        format!(
+            "# HELP ws_connections_active 当前WebSocket连接数\n\
+             # TYPE ws_connections_active gauge\n\
+             ws_connections_active {}\n\
+             # HELP ws_connections_rejected_total 被拒绝的WebSocket连接数\n\
+             # TYPE ws_connections_rejected_total counter\n\
+             ws_connections_rejected_total{{reason=\"global\"}} {}\n\
+             ws_connections_rejected_total{{reason=\"per_ip\"}} {}\n\
+             ws_connections_rejected_total{{reason=\"throttled\"}} {}\n\
+             # HELP ws_connections_priority_admitted_total 使用保留令牌优先接入的连接数\n\
+             # TYPE ws_connections_priority_admitted_total counter\n\
+             ws_connections_priority_admitted_total {}\n",
+            self.active(),
+            self.rejected_global.load(Ordering::Relaxed),
+            self.rejected_per_ip.load(Ordering::Relaxed),
+            self.rejected_throttled.load(Ordering::Relaxed),
+            self.admitted_priority.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// 令牌桶，按固定速率补充令牌，容量即允许的突发数量
+#[derive(Debug)]
+struct TokenBucket {
+    // 每秒补充的令牌数
+    rate: f64,
+    // 令牌桶容量
+    burst: f64,
+    // 当前令牌数和上次补充的时间
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// 取走一个令牌，取走后剩余令牌不能低于 `reserve`
+    fn try_take(&self, now: Instant, reserve: f64) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let (tokens, last) = &mut *state;
+        let elapsed = now.saturating_duration_since(*last).as_secs_f64();
+        *tokens = (*tokens + elapsed * self.rate).min(self.burst);
+        *last = now;
+        if *tokens - 1.0 < reserve {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+}
+
+/// 连接名额，释放时归还
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.limiter.release(&self.ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_connection_limits() {
+        let limiter = Arc::new(ConnectionLimiter::new(ConnectionLimitConfig {
+            max_connections: 3,
+            max_per_ip: 2,
+            ..Default::default()
+        }));
+        let a: IpAddr = "10.0.0.1".parse().unwrap();
+        let b: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let p1 = limiter.try_acquire(a).unwrap();
+        let _p2 = limiter.try_acquire(a).unwrap();
+        assert_eq!(limiter.try_acquire(a).unwrap_err(), RejectReason::PerIp);
+
+        let _p3 = limiter.try_acquire(b).unwrap();
+        assert_eq!(limiter.try_acquire(b).unwrap_err(), RejectReason::Global);
+
+        // 连接结束后归还名额
+        drop(p1);
+        assert_eq!(limiter.active(), 2);
+        assert!(limiter.try_acquire(a).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reserve_smaller_than_burst() {
+        let limiter = ConnectionLimiter::new(ConnectionLimitConfig {
+            accept_rate: 1,
+            accept_burst: 2,
+            priority_reserve: 5,
+            ..Default::default()
+        });
+
+        // 保留数不小于容量时给普通客户端留一个令牌
+        assert!(limiter.admit(async { false }).await.is_ok());
+        assert_eq!(
+            limiter.admit(async { false }).await,
+            Err(RejectReason::Throttled)
+        );
+        assert!(limiter.admit(async { true }).await.is_ok());
+    }
+
+    #[test]
+    fn test_token_bucket_reserve() {
+        let bucket = TokenBucket::new(10.0, 3.0);
+        let now = Instant::now();
+
+        // 保留1个令牌，普通客户端只能取走2个
+        assert!(bucket.try_take(now, 1.0));
+        assert!(bucket.try_take(now, 1.0));
+        assert!(!bucket.try_take(now, 1.0));
+        // 优先客户端可以使用保留的令牌
+        assert!(bucket.try_take(now, 0.0));
+        assert!(!bucket.try_take(now, 0.0));
+
+        // 按速率补充，不超过容量
+        assert!(bucket.try_take(now + Duration::from_millis(100), 0.0));
+        assert!(bucket.try_take(now + Duration::from_secs(10), 1.0));
+        assert!(bucket.try_take(now + Duration::from_secs(10), 1.0));
+        assert!(!bucket.try_take(now + Duration::from_secs(10), 1.0));
+    }
+}
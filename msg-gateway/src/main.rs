@@ -3,12 +3,17 @@ use tracing::{info, Level};
 use common::config::AppConfig;
 use msg_gateway::ws_server::WsServer;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     // 加载配置文件
     // 从指定路径读取配置，如果失败则panic
     let config = AppConfig::from_file(Some("./config/config.yaml")).unwrap();
-    
+
+    // 按配置的线程数创建运行时
+    common::runtime::build("msg-gateway", &config.runtime.for_service("msg-gateway"))?
+        .block_on(run(config))
+}
+
+async fn run(config: AppConfig) -> anyhow::Result<()> {
     // 初始化日志和链路追踪系统
     // 根据配置判断是否启用分布式链路追踪
     if config.telemetry.enabled {
@@ -26,7 +31,7 @@ async fn main() -> anyhow::Result<()> {
     
     // 启动WebSocket服务器
     // 这是消息网关的核心功能，负责管理客户端连接和消息转发
-    WsServer::start(config).await;
+    WsServer::start(config).await?;
     
     // 在程序结束前关闭链路追踪，确保所有数据都被发送
     // 这是一个优雅关闭的步骤，防止数据丢失
@@ -1,18 +1,21 @@
 use std::sync::Arc;
 
+use axum::body::Bytes;
+use axum::extract::ws::Message;
 use common::config::AppConfig;
 use dashmap::DashMap;
 use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
 
 use crate::client::Client;
-use cache::Cache;
+use crate::outbound::PushOutcome;
+use cache::{Cache, SeqAllocator};
+use common::dependency_health::DependencyHealth;
 use common::error::Error;
-use common::message::chat_service_client::ChatServiceClient;
+use common::grpc_client::{ChatServiceGrpcClient, GrpcServiceClient};
 use common::message::{
     ContentType, GroupMemSeq, Msg, MsgResponse, MsgType, PlatformType, SendMsgRequest,
 };
-use common::service_registry::LbWithServiceDiscovery;
 
 type UserID = String;
 /// client hub
@@ -24,39 +27,63 @@ pub struct Manager {
     tx: mpsc::Sender<Msg>,
     pub hub: Hub,
     pub cache: Arc<dyn Cache>,
-    pub chat_rpc: ChatServiceClient<LbWithServiceDiscovery>,
+    /// Redis健康状态，降级期间暂停在线状态相关功能
+    pub redis: DependencyHealth,
+    /// 发送序号分配，Redis降级期间由Postgres分配
+    pub seqs: SeqAllocator,
+    /// 消息服务客户端，调用时携带服务间令牌
+    pub chat_rpc: ChatServiceGrpcClient,
 }
 
 #[allow(dead_code)]
 impl Manager {
     pub async fn new(tx: mpsc::Sender<Msg>, config: &AppConfig) -> Self {
         let cache = cache::cache(config);
-        let chat_rpc = utils::get_rpc_client(config, config.rpc.chat.name.clone())
-            .await
-            .expect("chat rpc can't open");
+        let redis = cache::redis_health(config, cache.clone());
+        let seqs = SeqAllocator::new(config, cache.clone(), redis.clone())
+            .expect("序号分配初始化失败");
+        seqs.start();
+        let chat_rpc =
+            ChatServiceGrpcClient::new(GrpcServiceClient::from_env(&config.rpc.chat.name));
         Manager {
             tx,
             hub: Arc::new(DashMap::new()),
             cache,
+            redis,
+            seqs,
             chat_rpc,
         }
     }
 
-    pub async fn send_group(&self, obj_ids: Vec<GroupMemSeq>, mut msg: Msg) {
-        self.send_to_self(&msg.send_id, &msg).await;
+    /// send group message to the members connected to this gateway,
+    /// returns the ids of members that received the message
+    pub async fn send_group(
+        &self,
+        obj_ids: Vec<GroupMemSeq>,
+        mut msg: Msg,
+        skip_sender: bool,
+    ) -> Vec<String> {
+        // 重试推送时发送者的其他设备已经收到过
+        if !skip_sender {
+            self.send_to_self(&msg.send_id, &msg).await;
+        }
 
         // set send sequence to 0
         msg.send_seq = 0;
 
+        let mut delivered = Vec::new();
         for mem in obj_ids {
             if let Some(clients) = self.hub.get(&mem.mem_id) {
                 // Modify only the seq in the message and serialize it.
                 msg.seq = mem.cur_seq;
 
                 // Send message to all clients
-                self.send_msg_to_clients(&clients, &msg).await;
+                if self.send_msg_to_clients(&clients, &msg).await {
+                    delivered.push(mem.mem_id);
+                }
             }
         }
+        delivered
     }
 
     async fn send_to_self(&self, id: &str, msg: &Msg) {
@@ -75,8 +102,8 @@ impl Manager {
                         return;
                     }
                 };
-                if let Err(e) = sender.send_binary(content).await {
-                    error!("send to self error: {}", e)
+                if sender.push_msg(content, msg) == PushOutcome::Overflow {
+                    warn!("client {} is too slow, disconnecting", sender.platform_id);
                 }
             }
         }
@@ -89,45 +116,93 @@ impl Manager {
         self.send_to_self(&msg.send_id, msg).await;
     }
 
-    async fn send_msg_to_clients(&self, clients: &DashMap<PlatformType, Client>, msg: &Msg) {
+    /// send message to all platform clients of the user,
+    /// returns true if at least one client received it
+    async fn send_msg_to_clients(&self, clients: &DashMap<PlatformType, Client>, msg: &Msg) -> bool {
         match clients.len() {
-            0 => error!("no client found"),
+            0 => {
+                error!("no client found");
+                false
+            }
             1 => {
                 let content = match bincode::serialize(msg) {
                     Ok(res) => res,
                     Err(e) => {
                         error!("msg serialize error: {}", e);
-                        return;
+                        return false;
                     }
                 };
                 if let Some(client) = clients.iter().next() {
-                    if let Err(e) = client.value().send_binary(content).await {
-                        error!("send message error: {}", e);
-                    }
+                    return Self::push_to_client(client.value(), content, msg);
                 }
+                false
             }
             2 => {
                 let content = match bincode::serialize(msg) {
                     Ok(res) => res,
                     Err(e) => {
                         error!("msg serialize error: {}", e);
-                        return;
+                        return false;
                     }
                 };
+                let mut delivered = false;
                 let mut iter = clients.iter();
                 if let Some(first_client) = iter.next() {
-                    if let Err(e) = first_client.value().send_binary(content.clone()).await {
-                        error!("send message error: {}", e);
-                    }
+                    delivered |= Self::push_to_client(first_client.value(), content.clone(), msg);
                 }
                 if let Some(second_client) = iter.next() {
-                    if let Err(e) = second_client.value().send_binary(content).await {
-                        error!("send message error: {}", e);
-                    }
+                    delivered |= Self::push_to_client(second_client.value(), content, msg);
+                }
+                delivered
+            }
+            _ => {
+                warn!("Unexpected number of clients: {}", clients.len());
+                false
+            }
+        }
+    }
+
+    /// enqueue the message to the client's outbound queue,
+    /// returns false if the client was disconnected or the message was dropped
+    fn push_to_client(client: &Client, content: Vec<u8>, msg: &Msg) -> bool {
+        match client.push_msg(content, msg) {
+            PushOutcome::Queued => true,
+            PushOutcome::Dropped => {
+                warn!("client {} is too slow, message dropped", client.platform_id);
+                false
+            }
+            PushOutcome::Overflow => {
+                warn!("client {} is too slow, disconnecting", client.platform_id);
+                false
+            }
+            PushOutcome::Closed => {
+                error!("send message error: client {} closed", client.platform_id);
+                false
+            }
+        }
+    }
+
+    /// push a control message to every client connected to this gateway,
+    /// control messages are critical and never dropped from the outbound queue,
+    /// returns the number of clients that received it
+    pub fn notify_all(&self, msg: &Msg) -> usize {
+        let content = match bincode::serialize(msg) {
+            Ok(res) => res,
+            Err(e) => {
+                error!("msg serialize error: {}", e);
+                return 0;
+            }
+        };
+        let mut notified = 0;
+        for clients in self.hub.iter() {
+            for client in clients.iter() {
+                let message = Message::Binary(Bytes::from(content.clone()));
+                if client.outbound.push(message, 0, true) == PushOutcome::Queued {
+                    notified += 1;
                 }
             }
-            _ => warn!("Unexpected number of clients: {}", clients.len()),
         }
+        notified
     }
 
     // register client
@@ -171,7 +246,7 @@ impl Manager {
         //  we do not operate the database here about saving send sequence
         // we do that in the consumer module
         // even if the increment fails, it is not a problem
-        match self.cache.incr_send_seq(&message.send_id).await {
+        match self.seqs.incr_send_seq(&message.send_id).await {
             Ok((seq, _, _)) => message.send_seq = seq,
             Err(e) => {
                 self.create_error_message(message, e);
@@ -182,7 +257,11 @@ impl Manager {
         // send message through gRPC
         match self.send_rpc_message(message.clone()).await {
             Ok(response) => {
-                if response.err.is_empty() {
+                if response.degraded {
+                    // 服务端已暂存消息，告知客户端消息可能延迟送达
+                    warn!("message accepted in degraded mode");
+                    message.content = MsgResponse::ACCEPTED_DEGRADED.as_bytes().to_vec();
+                } else if response.err.is_empty() {
                     debug!("send message success");
                     message.content.clear();
                 } else {
@@ -201,14 +280,12 @@ impl Manager {
         }
     }
 
-    async fn send_rpc_message(&self, message: Msg) -> Result<MsgResponse, tonic::Status> {
-        let mut chat_rpc = self.chat_rpc.clone();
-        chat_rpc
+    async fn send_rpc_message(&self, message: Msg) -> anyhow::Result<MsgResponse> {
+        self.chat_rpc
             .send_msg(SendMsgRequest {
                 message: Some(message),
             })
             .await
-            .map(|res| res.into_inner())
     }
 
     fn create_error_message(&self, message: &mut Msg, error: impl ToString) {
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use axum::extract::ws::Message;
+use tokio::sync::Notify;
+
+use common::config::{OutboundQueueConfig, SlowConsumerPolicy};
+use common::message::MsgType;
+
+/// 入队结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// 已入队
+    Queued,
+    /// 队列已满，丢弃了一条非关键消息（最早的一条或本条）
+    Dropped,
+    /// 队列已满且无法丢弃，连接需要断开
+    Overflow,
+    /// 连接已关闭
+    Closed,
+}
+
+#[derive(Debug)]
+struct Entry {
+    message: Message,
+    seq: i64,
+    critical: bool,
+}
+
+/// 单个连接的有界下行发送队列
+///
+/// 消息先入队，再由连接的发送任务写入WebSocket，客户端读取过慢时不会阻塞推送方，
+/// 队列满时按配置丢弃非关键消息或断开连接
+#[derive(Debug)]
+pub struct OutboundQueue {
+    config: OutboundQueueConfig,
+    queue: Mutex<VecDeque<Entry>>,
+    notify: Notify,
+    // 因客户端过慢而关闭
+    overflowed: AtomicBool,
+    closed: AtomicBool,
+    // 已写入连接的最大消息序号，断开后客户端从这里继续
+    last_sent_seq: AtomicI64,
+    dropped: AtomicU64,
+}
+
+impl OutboundQueue {
+    pub fn new(config: OutboundQueueConfig) -> Self {
+        Self {
+            config,
+            queue: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            overflowed: AtomicBool::new(false),
+            closed: AtomicBool::new(false),
+            last_sent_seq: AtomicI64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// 是否为可以丢弃的非关键消息，这类消息丢失后客户端可以自行恢复
+    pub fn is_droppable(msg_type: i32) -> bool {
+        matches!(
+            MsgType::try_from(msg_type),
+            Ok(MsgType::Read | MsgType::Notification | MsgType::Service | MsgType::Typing)
+        )
+    }
+
+    /// 消息入队，seq为0表示不参与续传的消息
+    pub fn push(&self, message: Message, seq: i64, critical: bool) -> PushOutcome {
+        if self.closed.load(Ordering::Acquire) {
+            return PushOutcome::Closed;
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        let mut outcome = PushOutcome::Queued;
+        if queue.len() >= self.config.capacity.max(1) {
+            if self.config.policy == SlowConsumerPolicy::Disconnect {
+                drop(queue);
+                self.overflow();
+                return PushOutcome::Overflow;
+            }
+
+            // 优先丢弃最早的非关键消息，没有时丢弃本条非关键消息
+            if let Some(index) = queue.iter().position(|entry| !entry.critical) {
+                queue.remove(index);
+            } else if !critical {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return PushOutcome::Dropped;
+            } else {
+                drop(queue);
+                self.overflow();
+                return PushOutcome::Overflow;
+            }
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            outcome = PushOutcome::Dropped;
+        }
+
+        queue.push_back(Entry {
+            message,
+            seq,
+            critical,
+        });
+        drop(queue);
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// 取出下一条待发送的消息，队列关闭后返回None
+    pub async fn pop(&self) -> Option<Message> {
+        loop {
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            if let Some(entry) = self.queue.lock().unwrap().pop_front() {
+                if entry.seq > 0 {
+                    self.last_sent_seq.fetch_max(entry.seq, Ordering::AcqRel);
+                }
+                return Some(entry.message);
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    fn overflow(&self) {
+        self.overflowed.store(true, Ordering::Release);
+        self.close();
+    }
+
+    /// 关闭队列，唤醒发送任务
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.queue.lock().unwrap().clear();
+        self.notify.notify_one();
+    }
+
+    /// 是否因客户端过慢而关闭
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Acquire)
+    }
+
+    /// 已写入连接的最大消息序号
+    pub fn last_sent_seq(&self) -> i64 {
+        self.last_sent_seq.load(Ordering::Acquire)
+    }
+
+    /// 已丢弃的消息数
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(policy: SlowConsumerPolicy) -> OutboundQueue {
+        OutboundQueue::new(OutboundQueueConfig {
+            capacity: 2,
+            policy,
+        })
+    }
+
+    fn text(s: &str) -> Message {
+        Message::Text(s.into())
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let q = queue(SlowConsumerPolicy::DropOldest);
+        assert_eq!(q.push(text("read"), 0, false), PushOutcome::Queued);
+        assert_eq!(q.push(text("a"), 1, true), PushOutcome::Queued);
+
+        // 丢弃最早的非关键消息
+        assert_eq!(q.push(text("b"), 2, true), PushOutcome::Dropped);
+        // 全部为关键消息时，非关键消息直接丢弃，关键消息导致断开
+        assert_eq!(q.push(text("read"), 0, false), PushOutcome::Dropped);
+        assert_eq!(q.push(text("c"), 3, true), PushOutcome::Overflow);
+        assert!(q.is_overflowed());
+        assert!(q.pop().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_and_last_seq() {
+        let q = queue(SlowConsumerPolicy::Disconnect);
+        q.push(text("a"), 5, true);
+        q.push(text("b"), 6, true);
+        assert_eq!(q.pop().await, Some(text("a")));
+        assert_eq!(q.last_sent_seq(), 5);
+
+        q.push(text("c"), 7, true);
+        assert_eq!(q.push(text("read"), 0, false), PushOutcome::Overflow);
+        assert_eq!(q.last_sent_seq(), 5);
+    }
+}
@@ -0,0 +1,100 @@
+use prost::Message;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{async_trait, Request, Response, Status, Streaming};
+use tracing::{debug, warn};
+
+use crate::manager::Manager;
+use common::message::{SendGroupMsgRequest, SendMsgRequest};
+use common::proto::push_stream::push_stream_service_server::PushStreamService;
+use common::proto::push_stream::{PushAck, PushFrame};
+
+/// 建立推送流时发放给推送服务的初始额度，即允许的最大在途帧数
+const INITIAL_CREDITS: u32 = 256;
+
+/// 推送服务到本网关的长连接推送流
+///
+/// 按顺序处理推送帧，每处理完一帧在确认中归还一个额度；
+/// 网关处理变慢时额度回收随之变慢，推送服务自然降速
+pub struct PushStreamRpcService {
+    manager: Manager,
+}
+
+impl PushStreamRpcService {
+    pub fn new(manager: Manager) -> Self {
+        Self { manager }
+    }
+
+    /// 处理单个推送帧，返回群聊推送时送达的成员
+    async fn handle_frame(manager: &Manager, frame: &PushFrame) -> Result<Vec<String>, String> {
+        if frame.group {
+            let req = SendGroupMsgRequest::decode(frame.payload.as_slice())
+                .map_err(|e| format!("解码群聊推送失败: {}", e))?;
+            let msg = req.message.ok_or("message is empty")?;
+            Ok(manager.send_group(req.members, msg, req.skip_sender).await)
+        } else {
+            let req = SendMsgRequest::decode(frame.payload.as_slice())
+                .map_err(|e| format!("解码单聊推送失败: {}", e))?;
+            let msg = req.message.ok_or("message is empty")?;
+            manager.send_single_msg(&msg.receiver_id, &msg).await;
+            Ok(Vec::new())
+        }
+    }
+}
+
+#[async_trait]
+impl PushStreamService for PushStreamRpcService {
+    type PushStream = ReceiverStream<Result<PushAck, Status>>;
+
+    async fn push(
+        &self,
+        request: Request<Streaming<PushFrame>>,
+    ) -> Result<Response<Self::PushStream>, Status> {
+        let mut inbound = request.into_inner();
+        let (tx, rx) = mpsc::channel(INITIAL_CREDITS as usize + 1);
+
+        // 先发放初始额度
+        tx.send(Ok(PushAck {
+            id: 0,
+            credits: INITIAL_CREDITS,
+            ..Default::default()
+        }))
+        .await
+        .map_err(|_| Status::internal("推送流已关闭"))?;
+
+        let manager = self.manager.clone();
+        tokio::spawn(async move {
+            loop {
+                let frame = match inbound.message().await {
+                    Ok(Some(frame)) => frame,
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!("推送流接收失败: {}", e);
+                        break;
+                    }
+                };
+
+                let ack = match Self::handle_frame(&manager, &frame).await {
+                    Ok(delivered) => PushAck {
+                        id: frame.id,
+                        delivered,
+                        credits: 1,
+                        error: String::new(),
+                    },
+                    Err(error) => PushAck {
+                        id: frame.id,
+                        delivered: Vec::new(),
+                        credits: 1,
+                        error,
+                    },
+                };
+                if tx.send(Ok(ack)).await.is_err() {
+                    break;
+                }
+            }
+            debug!("推送流已关闭");
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
@@ -0,0 +1,206 @@
+//! 批量已读确认
+//!
+//! 协商了 [`RECEIPT_PROTOCOL`] 子协议的客户端可以用一个确认帧上报同一会话中多条消息已读，
+//! 网关转换为一条已读消息转发给消息服务，消息服务一次更新接收盒和已读位置，
+//! 单聊的已读回执合并后转发给对方。未协商子协议的旧客户端仍按条发送已读消息，
+//! 这些已读消息不能携带已读回执序号，读者必须是连接的用户
+
+use serde::Deserialize;
+
+use common::message::{Msg, MsgRead, MsgType};
+
+/// 支持批量已读确认的WebSocket子协议，客户端在握手时通过 Sec-WebSocket-Protocol 协商
+pub const RECEIPT_PROTOCOL: &str = "chrisim.receipts.v1";
+
+/// 批量确认帧：`{"type": "ack", "conversation_id": 会话ID, "group": 是否群聊, "seqs": [序号],
+/// "read_send_seq": 已读到的对方消息的最大发送序号}`
+#[derive(Debug, Deserialize)]
+struct AckFrame {
+    conversation_id: String,
+    #[serde(default)]
+    group: bool,
+    seqs: Vec<i64>,
+    #[serde(default)]
+    read_send_seq: Option<i64>,
+}
+
+/// 把批量确认帧转换为已读消息，不是确认帧时返回 None，确认帧格式错误时返回错误说明
+///
+/// 读者以连接的用户为准，序号去重排序后超过 max_seqs 个时拒绝
+pub fn ack_to_read(msg: &Msg, user_id: &str, max_seqs: usize) -> Option<Result<Msg, String>> {
+    if msg.msg_type != MsgType::Service as i32 {
+        return None;
+    }
+    let content = serde_json::from_slice::<serde_json::Value>(&msg.content).ok()?;
+    if content.get("type").and_then(|t| t.as_str()) != Some("ack") {
+        return None;
+    }
+    Some(build_read(content, msg, user_id, max_seqs))
+}
+
+/// 检查客户端直接发送的消息：去掉只能由确认帧生成的已读回执序号，已读消息的读者必须是连接的用户，
+/// 发送者改为连接的用户
+pub fn check_client_msg(msg: &mut Msg, user_id: &str) -> Result<(), String> {
+    msg.extensions.remove(MsgRead::READ_SEND_SEQ);
+    if msg.msg_type != MsgType::Read as i32 {
+        return Ok(());
+    }
+    let read: MsgRead =
+        bincode::deserialize(&msg.content).map_err(|e| format!("已读消息格式错误: {}", e))?;
+    if read.user_id != user_id {
+        return Err(format!("已读消息的读者 {} 不是连接的用户", read.user_id));
+    }
+    msg.send_id = user_id.to_string();
+    Ok(())
+}
+
+fn build_read(
+    content: serde_json::Value,
+    msg: &Msg,
+    user_id: &str,
+    max_seqs: usize,
+) -> Result<Msg, String> {
+    let mut frame: AckFrame =
+        serde_json::from_value(content).map_err(|e| format!("确认帧格式错误: {}", e))?;
+    if frame.conversation_id.is_empty() {
+        return Err("确认帧缺少会话ID".to_string());
+    }
+    frame.seqs.retain(|seq| *seq > 0);
+    frame.seqs.sort_unstable();
+    frame.seqs.dedup();
+    if frame.seqs.is_empty() {
+        return Err("确认帧没有有效的序号".to_string());
+    }
+    if frame.seqs.len() > max_seqs {
+        return Err(format!("确认帧最多包含 {} 个序号", max_seqs));
+    }
+
+    let read = MsgRead {
+        msg_seq: frame.seqs,
+        user_id: user_id.to_string(),
+    };
+    let content = bincode::serialize(&read).map_err(|e| format!("序列化已读消息失败: {}", e))?;
+    let mut read_msg = Msg {
+        send_id: user_id.to_string(),
+        local_id: msg.local_id.clone(),
+        create_time: msg.create_time,
+        msg_type: MsgType::Read as i32,
+        platform: msg.platform,
+        tenant_id: msg.tenant_id.clone(),
+        content,
+        ..Default::default()
+    };
+    // 群聊只更新已读，不转发已读回执
+    if frame.group {
+        read_msg.group_id = frame.conversation_id;
+    } else {
+        read_msg.receiver_id = frame.conversation_id;
+        if let Some(read_send_seq) = frame.read_send_seq.filter(|seq| *seq > 0) {
+            read_msg.extensions.insert(
+                MsgRead::READ_SEND_SEQ.to_string(),
+                read_send_seq.to_string(),
+            );
+        }
+    }
+    Ok(read_msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::message::ContentType;
+
+    fn ack(content: serde_json::Value) -> Msg {
+        Msg {
+            send_id: "alice".to_string(),
+            msg_type: MsgType::Service as i32,
+            content_type: ContentType::Text as i32,
+            content: content.to_string().into_bytes(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ack_to_read() {
+        let msg = ack(serde_json::json!({
+            "type": "ack",
+            "conversation_id": "bob",
+            "seqs": [7, 5, 7, 6, 0],
+            "read_send_seq": 42,
+        }));
+        let read = ack_to_read(&msg, "alice", 500).unwrap().unwrap();
+        assert_eq!(read.msg_type, MsgType::Read as i32);
+        assert_eq!(read.receiver_id, "bob");
+        assert_eq!(read.extensions[MsgRead::READ_SEND_SEQ], "42");
+        let data: MsgRead = bincode::deserialize(&read.content).unwrap();
+        assert_eq!(data.msg_seq, vec![5, 6, 7]);
+        assert_eq!(data.user_id, "alice");
+
+        // 群聊确认只更新已读，不携带回执序号
+        let msg = ack(serde_json::json!({
+            "type": "ack",
+            "conversation_id": "group-1",
+            "group": true,
+            "seqs": [1, 2],
+            "read_send_seq": 42,
+        }));
+        let read = ack_to_read(&msg, "alice", 500).unwrap().unwrap();
+        assert_eq!(read.group_id, "group-1");
+        assert!(read.receiver_id.is_empty());
+        assert!(read.extensions.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_ack() {
+        let msg = ack(serde_json::json!({
+            "type": "ack",
+            "conversation_id": "bob",
+            "seqs": [1, 2, 3],
+        }));
+        assert!(ack_to_read(&msg, "alice", 2).unwrap().is_err());
+
+        let msg = ack(serde_json::json!({ "type": "ack", "conversation_id": "bob", "seqs": [] }));
+        assert!(ack_to_read(&msg, "alice", 500).unwrap().is_err());
+
+        // 其他控制帧不处理
+        let msg = ack(serde_json::json!({ "type": "time_sync", "client_time": 1 }));
+        assert!(ack_to_read(&msg, "alice", 500).is_none());
+    }
+
+    #[test]
+    fn test_check_client_msg() {
+        let read = |reader: &str| {
+            let mut msg = Msg {
+                send_id: "alice".to_string(),
+                receiver_id: "bob".to_string(),
+                msg_type: MsgType::Read as i32,
+                content: bincode::serialize(&MsgRead {
+                    msg_seq: vec![1],
+                    user_id: reader.to_string(),
+                })
+                .unwrap(),
+                ..Default::default()
+            };
+            msg.extensions
+                .insert(MsgRead::READ_SEND_SEQ.to_string(), "42".to_string());
+            msg
+        };
+
+        // 按条发送的已读消息不能携带回执序号
+        let mut msg = read("alice");
+        msg.send_id.clear();
+        assert!(check_client_msg(&mut msg, "alice").is_ok());
+        assert!(msg.extensions.is_empty());
+        assert_eq!(msg.send_id, "alice");
+
+        // 不能替其他用户标记已读
+        assert!(check_client_msg(&mut read("carol"), "alice").is_err());
+        assert!(check_client_msg(&mut read("alice"), "carol").is_err());
+
+        let mut msg = ack(serde_json::json!({ "type": "typing" }));
+        msg.extensions
+            .insert(MsgRead::READ_SEND_SEQ.to_string(), "42".to_string());
+        assert!(check_client_msg(&mut msg, "alice").is_ok());
+        assert!(msg.extensions.is_empty());
+    }
+}
@@ -5,13 +5,16 @@ use tonic::{async_trait, Request, Response, Status};
 use tracing::{debug, info};
 
 use crate::manager::Manager;
+use crate::push_stream::PushStreamRpcService;
 use common::config::{AppConfig, Component};
 use common::error::Error;
-use common::grpc::LoggingInterceptor;
+use common::grpc::service_auth::{ServiceAuth, ServiceAuthInterceptor};
+use common::grpc::{LoggingInterceptor, LoggingLayer};
 use common::message::msg_service_server::MsgServiceServer;
 use common::message::{
     msg_service_server::MsgService, SendGroupMsgRequest, SendMsgRequest, SendMsgResponse,
 };
+use common::proto::push_stream::push_stream_service_server::PushStreamServiceServer;
 use common::service_registry::ServiceRegistry;
 use tonic_health::server::{Health, HealthServer};
 
@@ -25,11 +28,16 @@ impl MsgRpcService {
     }
 
     pub async fn start(manager: Manager, config: &AppConfig) -> Result<(), Error> {
+        // 未配置服务间认证密钥时拒绝启动，避免推送接口对外无认证暴露
+        ServiceAuth::global().ensure_enabled()?;
+
         // register service to service register center
         // 创建并注册到Consul
-        let service_registry = ServiceRegistry::from_env();
+        let service_registry = ServiceRegistry::from_env()
+            .with_region(config.region.local())
+            .shared();
         let service_id = service_registry
-            .register_service(
+            .register(
                 "msg-gateway",
                 &config.server.host,
                 config.server.port as u32, // 显式转换为u32类型
@@ -45,19 +53,26 @@ impl MsgRpcService {
         let health_service = HealthServer::new(Health::default());
         info!("<ws> rpc service health check started");
 
-        // 创建日志拦截器
-        let logging_interceptor = LoggingInterceptor::new();
+        // 创建日志拦截器，外层校验服务间令牌，只接受消息服务等内部服务的推送
+        let interceptor = ServiceAuthInterceptor::new(LoggingInterceptor::new());
 
+        let push_stream = PushStreamServiceServer::with_interceptor(
+            PushStreamRpcService::new(manager.clone()),
+            interceptor.clone(),
+        );
         let service = Self::new(manager);
-        let svc = MsgServiceServer::with_interceptor(service, logging_interceptor);
+        let svc = MsgServiceServer::with_interceptor(service, interceptor);
         info!(
             "<ws> rpc service started at {}",
             config.rpc.ws.rpc_server_url()
         );
 
+        // 记录方法级指标和span
         Server::builder()
+            .layer(LoggingLayer)
             .add_service(health_service)
             .add_service(svc)
+            .add_service(push_stream)
             .serve(config.rpc.ws.rpc_server_url().parse().unwrap())
             .await
             .unwrap();
@@ -77,7 +92,7 @@ impl MsgService for MsgRpcService {
             .message
             .ok_or(Status::invalid_argument("message is empty"))?;
         self.manager.broadcast(msg).await?;
-        let response = Response::new(SendMsgResponse {});
+        let response = Response::new(SendMsgResponse::default());
         Ok(response)
     }
 
@@ -93,7 +108,7 @@ impl MsgService for MsgRpcService {
             .ok_or(Status::invalid_argument("message is empty"))?;
         debug!("send message to user: {:?}", msg);
         self.manager.send_single_msg(&msg.receiver_id, &msg).await;
-        let response = Response::new(SendMsgResponse {});
+        let response = Response::new(SendMsgResponse::default());
         Ok(response)
     }
 
@@ -106,8 +121,8 @@ impl MsgService for MsgRpcService {
             .message
             .ok_or(Status::invalid_argument("message is empty"))?;
         let members = req.members;
-        self.manager.send_group(members, msg).await;
-        let response = Response::new(SendMsgResponse {});
+        let delivered = self.manager.send_group(members, msg, req.skip_sender).await;
+        let response = Response::new(SendMsgResponse { delivered });
         Ok(response)
     }
 }
@@ -0,0 +1,452 @@
+//! WebSocket协议描述
+//!
+//! 以JSON Schema的形式描述消息信封、控制帧、回执语义、关闭码和消息类型目录，由 `/ws/schema` 提供，
+//! 也可以通过 `cargo run -p msg-gateway --bin ws_schema` 在构建时生成文件。
+//! 信封字段和对时帧字段由对应的Rust类型序列化得到，枚举目录由proto生成的枚举得到，
+//! 类型变更后描述随之变化；以 `json!` 构造的控制帧字段在 [`CONTROL_FRAMES`] 中维护
+
+use std::sync::OnceLock;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use common::message::{ContentType, Msg, MsgResponse, MsgType, PlatformType};
+use common::time_sync::{TimeSyncRequest, TimeSyncResponse};
+
+use crate::receipt::RECEIPT_PROTOCOL;
+use crate::ws_server::{
+    KNOCK_OFF_CODE, SLOW_CONSUMER_CODE, UNAUTHORIZED_CODE, UPGRADE_REQUIRED_CODE,
+};
+
+/// 枚举取值的探测上限，proto枚举的取值都在该范围内
+const ENUM_PROBE_LIMIT: i32 = 256;
+
+/// 消息信封字段的说明
+const ENVELOPE_FIELDS: &[(&str, &str)] = &[
+    ("send_id", "发送者ID，客户端发送时必填"),
+    ("receiver_id", "接收者ID，群消息为群ID"),
+    (
+        "local_id",
+        "客户端生成的消息ID，回执原样返回，用于匹配发送结果",
+    ),
+    ("server_id", "服务端生成的消息ID，回执中返回"),
+    ("create_time", "客户端创建消息的本地时间（毫秒）"),
+    ("send_time", "服务端接收消息的UTC时间（毫秒）"),
+    ("seq", "接收者的消息序号，客户端据此发现缺失的消息"),
+    ("msg_type", "消息类型，取值见 MsgType"),
+    ("content_type", "内容类型，取值见 ContentType"),
+    (
+        "content",
+        "消息内容的字节数组，控制帧和通知为UTF-8编码的JSON，聊天消息为UTF-8文本",
+    ),
+    ("is_read", "是否已读"),
+    ("group_id", "群ID，单聊消息为空"),
+    ("platform", "发送者的平台，取值见 PlatformType"),
+    ("avatar", "发送者头像"),
+    ("nickname", "发送者昵称"),
+    (
+        "related_msg_id",
+        "关联的消息ID，如回复、撤回、消息请求通知对应的消息",
+    ),
+    ("send_seq", "发送者的发送序号，由网关分配"),
+    ("conv_seq", "会话序号，启用会话序号时由消息服务分配，未分配时为0"),
+    (
+        "extensions",
+        "业务自定义的扩展字段（字符串键值对），服务端不解析，原样保存和投递",
+    ),
+];
+
+/// 控制帧的传输方向
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    /// 客户端发往服务端
+    ClientToServer,
+    /// 服务端推送给客户端
+    ServerToClient,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::ClientToServer => "client_to_server",
+            Direction::ServerToClient => "server_to_client",
+        }
+    }
+}
+
+/// 以 `json!` 构造的控制帧，字段为 (名称, JSON类型, 说明)
+pub struct ControlFrame {
+    pub name: &'static str,
+    pub direction: Direction,
+    pub msg_type: MsgType,
+    pub description: &'static str,
+    pub fields: &'static [(&'static str, &'static str, &'static str)],
+}
+
+/// 控制帧目录，字段与构造控制帧的代码保持一致，对时帧由类型生成，不在此列出
+pub const CONTROL_FRAMES: &[ControlFrame] = &[
+    ControlFrame {
+        name: "draft",
+        direction: Direction::ClientToServer,
+        msg_type: MsgType::Service,
+        description: "同步会话草稿，文本为空时清除草稿，不返回回执",
+        fields: &[
+            ("conversation_id", "string", "会话ID"),
+            ("text", "string", "草稿内容"),
+        ],
+    },
+    ControlFrame {
+        name: "draft",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Service,
+        description: "草稿变更，推送给用户的全部在线设备，客户端按平台忽略自己发出的更新",
+        fields: &[
+            ("conversation_id", "string", "会话ID"),
+            ("text", "string", "草稿内容，为空表示已清除"),
+            ("updated_at", "integer", "更新时间（UTC毫秒）"),
+            ("platform", "integer", "发出更新的平台"),
+        ],
+    },
+    ControlFrame {
+        name: "resume",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Service,
+        description: "断线重连后需要补拉的序号区间",
+        fields: &[
+            ("from_seq", "integer", "起始序号（含）"),
+            ("to_seq", "integer", "结束序号（含）"),
+        ],
+    },
+    ControlFrame {
+        name: "topology",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Service,
+        description: "网关拓扑变更，客户端按需迁移到新的接入地址",
+        fields: &[
+            ("reason", "string", "变更原因"),
+            ("endpoints", "array", "可用的接入地址"),
+        ],
+    },
+    ControlFrame {
+        name: "read_sync",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Read,
+        description: "已读状态在同一用户的其他设备间同步",
+        fields: &[
+            ("conversation_id", "string", "会话ID"),
+            ("read_seq", "integer", "已读到的序号"),
+            ("platform", "integer", "产生已读的平台"),
+        ],
+    },
+    ControlFrame {
+        name: "ack",
+        direction: Direction::ClientToServer,
+        msg_type: MsgType::Service,
+        description: "批量已读确认，协商了批量已读确认子协议后使用，一次确认同一会话中的多条消息，\
+                      按一条已读消息返回回执",
+        fields: &[
+            ("conversation_id", "string", "会话ID，单聊为对方用户ID"),
+            ("group", "boolean", "是否群聊，默认为单聊"),
+            ("seqs", "array", "已读消息的序号"),
+            (
+                "read_send_seq",
+                "integer",
+                "单聊中已读到的对方消息的最大发送序号，用于向对方转发已读回执",
+            ),
+        ],
+    },
+    ControlFrame {
+        name: "read_receipt",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Read,
+        description: "单聊的已读回执，同一对方在合并间隔内的多次已读只推送一次",
+        fields: &[
+            ("conversation_id", "string", "读者的用户ID"),
+            ("read_send_seq", "integer", "对方已读到的本人消息的最大发送序号"),
+            ("read_time", "integer", "回执的发送时间（UTC毫秒）"),
+        ],
+    },
+    ControlFrame {
+        name: "message_request",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Notification,
+        description: "收到新的消息请求，related_msg_id 为对应的消息",
+        fields: &[("sender_id", "string", "发送者ID")],
+    },
+    ControlFrame {
+        name: "link_preview",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Notification,
+        description: "消息中链接的预览已生成",
+        fields: &[
+            ("serverId", "string", "消息的服务端ID"),
+            ("groupId", "string", "群ID，单聊为空"),
+            ("preview", "object", "链接预览"),
+        ],
+    },
+    ControlFrame {
+        name: "attachment_blocked",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Notification,
+        description: "消息附件未通过安全扫描，已被拦截",
+        fields: &[
+            ("serverId", "string", "消息的服务端ID"),
+            ("groupId", "string", "群ID，单聊为空"),
+            ("objectKey", "string", "附件的对象存储键"),
+            ("reason", "string", "拦截原因"),
+        ],
+    },
+    ControlFrame {
+        name: "security",
+        direction: Direction::ServerToClient,
+        msg_type: MsgType::Notification,
+        description: "账号安全提醒，如新设备登录",
+        fields: &[
+            ("event", "string", "安全事件"),
+            ("deviceName", "string", "设备名称"),
+            ("ip", "string", "登录IP"),
+            ("time", "integer", "事件时间（UTC毫秒）"),
+        ],
+    },
+];
+
+/// 协议描述，首次访问时生成
+pub fn ws_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(build_schema)
+}
+
+fn build_schema() -> Value {
+    let mut frames = vec![
+        typed_frame(
+            "time_sync",
+            Direction::ClientToServer,
+            "对时请求，服务端立即返回对时响应",
+            &TimeSyncRequest::default(),
+            &TimeSyncRequest {
+                client_time: Some(0),
+            },
+        ),
+        typed_frame(
+            "time_sync",
+            Direction::ServerToClient,
+            "对时响应，发送队列满时可能丢弃，客户端超时后重试",
+            &TimeSyncResponse::new(&TimeSyncRequest::default(), 0),
+            &TimeSyncResponse::new(
+                &TimeSyncRequest {
+                    client_time: Some(0),
+                },
+                0,
+            ),
+        ),
+    ];
+    frames.extend(CONTROL_FRAMES.iter().map(control_frame));
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "chrisIM WebSocket协议",
+        "version": env!("CARGO_PKG_VERSION"),
+        "encoding": {
+            "binary": "bincode序列化的Msg，服务端推送的消息都使用二进制帧",
+            "text": "JSON序列化的Msg，content为字节数组",
+            "heartbeat": "服务端定期发送Ping，客户端应回复Pong",
+        },
+        "$defs": {
+            "Msg": envelope_schema(),
+            "MsgType": enum_schema(|v| MsgType::try_from(v).ok().map(|t| t.as_str_name())),
+            "ContentType": enum_schema(|v| ContentType::try_from(v).ok().map(|t| t.as_str_name())),
+            "PlatformType": enum_schema(|v| PlatformType::try_from(v).ok().map(|t| t.as_str_name())),
+        },
+        "subprotocols": [
+            {
+                "name": RECEIPT_PROTOCOL,
+                "description": "批量已读确认，握手时通过 Sec-WebSocket-Protocol 协商，\
+                                服务端选中后客户端可以发送 ack 控制帧",
+            },
+        ],
+        "control_frames": frames,
+        "ack": ack_schema(),
+        "close_codes": close_codes(),
+    })
+}
+
+// 信封字段由 Msg 的序列化结果得到，不向客户端序列化的字段不会出现
+fn envelope_schema() -> Value {
+    let sample = Msg {
+        related_msg_id: Some(String::new()),
+        ..Default::default()
+    };
+    let mut schema = object_schema(&Msg::default(), &sample, ENVELOPE_FIELDS);
+    let properties = schema["properties"].as_object_mut().expect("对象属性");
+    for (field, def) in [
+        ("msg_type", "MsgType"),
+        ("content_type", "ContentType"),
+        ("platform", "PlatformType"),
+    ] {
+        if let Some(property) = properties.get_mut(field) {
+            property["$ref"] = json!(format!("#/$defs/{}", def));
+        }
+    }
+    schema["description"] = json!("消息信封，聊天消息、控制帧和回执都使用该结构");
+    schema
+}
+
+// 按序列化后的字段生成对象描述，sample 需要填写全部可选字段，
+// 默认值中为null的字段视为可选
+fn object_schema<T: Serialize>(default: &T, sample: &T, docs: &[(&str, &str)]) -> Value {
+    let default = serde_json::to_value(default).expect("协议类型可以序列化为JSON");
+    let sample = serde_json::to_value(sample).expect("协议类型可以序列化为JSON");
+    let mut properties = Map::new();
+    if let Value::Object(fields) = sample {
+        for (name, value) in fields {
+            let kind = json_type(&value);
+            let kind = if default.get(&name).map_or(false, Value::is_null) {
+                json!([kind, "null"])
+            } else {
+                json!(kind)
+            };
+            let mut property = json!({ "type": kind });
+            if let Some((_, doc)) = docs.iter().find(|(field, _)| *field == name) {
+                property["description"] = json!(doc);
+            }
+            properties.insert(name, property);
+        }
+    }
+    json!({ "type": "object", "properties": properties })
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn enum_schema(name: impl Fn(i32) -> Option<&'static str>) -> Value {
+    let values: Vec<Value> = (0..ENUM_PROBE_LIMIT)
+        .filter_map(|v| name(v).map(|name| json!({ "const": v, "title": name })))
+        .collect();
+    json!({ "type": "integer", "oneOf": values })
+}
+
+fn typed_frame<T: Serialize>(
+    name: &str,
+    direction: Direction,
+    description: &str,
+    default: &T,
+    sample: &T,
+) -> Value {
+    let mut content = object_schema(default, sample, &[]);
+    content["properties"]["type"] = json!({ "const": name });
+    json!({
+        "name": name,
+        "direction": direction.as_str(),
+        "msg_type": MsgType::Service as i32,
+        "description": description,
+        "content": content,
+    })
+}
+
+fn control_frame(frame: &ControlFrame) -> Value {
+    let mut properties = Map::new();
+    properties.insert("type".to_string(), json!({ "const": frame.name }));
+    for (name, kind, description) in frame.fields {
+        properties.insert(
+            name.to_string(),
+            json!({ "type": kind, "description": description }),
+        );
+    }
+    json!({
+        "name": frame.name,
+        "direction": frame.direction.as_str(),
+        "msg_type": frame.msg_type as i32,
+        "description": frame.description,
+        "content": { "type": "object", "properties": properties },
+    })
+}
+
+fn ack_schema() -> Value {
+    json!({
+        "msg_type": MsgType::MsgRecResp as i32,
+        "description": "客户端发送的每条消息都会收到一条回执，回执是原消息改写后的Msg，\
+                        local_id 原样返回，server_id 和 send_time 为服务端分配的值",
+        "outcomes": [
+            {
+                "name": "accepted",
+                "content": "",
+                "description": "发送成功，content为空",
+            },
+            {
+                "name": "accepted_degraded",
+                "content": MsgResponse::ACCEPTED_DEGRADED,
+                "description": "服务端降级暂存了消息，消息可能延迟送达，客户端不应重发",
+            },
+            {
+                "name": "rejected",
+                "content_type": ContentType::Error as i32,
+                "description": "发送失败，content为UTF-8编码的错误信息，客户端可以重试",
+            },
+        ],
+    })
+}
+
+fn close_codes() -> Value {
+    json!([
+        {
+            "code": KNOCK_OFF_CODE,
+            "description": "同一平台在其他设备登录，当前连接被踢下线",
+        },
+        {
+            "code": UNAUTHORIZED_CODE,
+            "description": "令牌无效，需要重新登录",
+        },
+        {
+            "code": SLOW_CONSUMER_CODE,
+            "description": "客户端读取过慢被断开，关闭原因为续传序号，重连时作为 resume_seq 携带",
+        },
+        {
+            "code": UPGRADE_REQUIRED_CODE,
+            "description": "客户端版本过低，关闭原因为所在平台的最低版本，升级前不应重连",
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_fields_documented() {
+        let schema = ws_schema();
+        let properties = schema["$defs"]["Msg"]["properties"].as_object().unwrap();
+
+        // 租户由网关填写，不属于客户端协议
+        assert!(!properties.contains_key("tenant_id"));
+        for (name, property) in properties {
+            assert!(
+                property.get("description").is_some(),
+                "字段 {} 缺少说明",
+                name
+            );
+        }
+        assert_eq!(properties["msg_type"]["$ref"], "#/$defs/MsgType");
+        assert_eq!(
+            properties["related_msg_id"]["type"],
+            json!(["string", "null"])
+        );
+    }
+
+    #[test]
+    fn test_msg_type_catalog() {
+        let values = ws_schema()["$defs"]["MsgType"]["oneOf"].as_array().unwrap();
+        let ack = values
+            .iter()
+            .find(|v| v["const"] == MsgType::MsgRecResp as i32)
+            .unwrap();
+        assert_eq!(ack["title"], MsgType::MsgRecResp.as_str_name());
+    }
+}
@@ -0,0 +1,242 @@
+use std::io;
+use std::net::SocketAddr;
+
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Router;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use common::config::WsTlsConfig;
+
+/// 启动WebSocket服务
+///
+/// 未启用TLS时提供明文ws，通常由前置的负载均衡终止TLS；
+/// 启用后网关自行终止TLS提供wss，证书来自PEM文件或ACME自动申请
+pub async fn serve(router: Router, addr: SocketAddr, config: &WsTlsConfig) -> io::Result<()> {
+    if !config.enabled {
+        let listener = TcpListener::bind(addr).await?;
+        info!("start websocket server on ws://{}", addr);
+        return axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await;
+    }
+
+    if let Some(port) = config.redirect_port {
+        tokio::spawn(redirect_plain(
+            SocketAddr::new(addr.ip(), port),
+            addr.port(),
+        ));
+    }
+    info!("start websocket server on wss://{}", addr);
+    serve_tls(router, addr, config).await
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(router: Router, addr: SocketAddr, config: &WsTlsConfig) -> io::Result<()> {
+    use std::sync::Arc;
+
+    use axum_server::tls_rustls::RustlsConfig;
+
+    // 同时启用多个加密库时需要指定默认实现，已指定时忽略
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+
+    if let Some(acme) = &config.acme {
+        return serve_acme(service, addr, acme, alpn_protocols(config)).await;
+    }
+
+    let mut server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(load_certs(&config.cert_path)?, load_key(&config.key_path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    server_config.alpn_protocols = alpn_protocols(config);
+
+    axum_server::bind_rustls(addr, RustlsConfig::from_config(Arc::new(server_config)))
+        .serve(service)
+        .await
+}
+
+#[cfg(not(feature = "tls"))]
+async fn serve_tls(_router: Router, _addr: SocketAddr, _config: &WsTlsConfig) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "未启用tls特性，无法提供wss，请使用 --features tls 构建",
+    ))
+}
+
+/// 读取PEM格式的证书链
+#[cfg(feature = "tls")]
+fn load_certs(path: &str) -> io::Result<Vec<rustls::pki_types::CertificateDer<'static>>> {
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("证书文件 {} 中没有证书", path),
+        ));
+    }
+    Ok(certs)
+}
+
+/// 读取PEM格式的私钥
+#[cfg(feature = "tls")]
+fn load_key(path: &str) -> io::Result<rustls::pki_types::PrivateKeyDer<'static>> {
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    rustls_pemfile::private_key(&mut reader)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("私钥文件 {} 中没有私钥", path),
+        )
+    })
+}
+
+#[cfg(feature = "tls")]
+fn alpn_protocols(config: &WsTlsConfig) -> Vec<Vec<u8>> {
+    config
+        .alpn
+        .iter()
+        .map(|protocol| protocol.as_bytes().to_vec())
+        .collect()
+}
+
+/// 通过ACME自动申请和续期证书，验证请求（TLS-ALPN-01）在同一端口上处理
+#[cfg(feature = "acme")]
+async fn serve_acme(
+    service: axum::extract::connect_info::IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    addr: SocketAddr,
+    acme: &common::config::WsAcmeConfig,
+    alpn: Vec<Vec<u8>>,
+) -> io::Result<()> {
+    use std::sync::Arc;
+
+    use futures::StreamExt;
+    use rustls_acme::caches::DirCache;
+    use rustls_acme::AcmeConfig;
+
+    let mut state = AcmeConfig::new(acme.domains.clone())
+        .contact(
+            acme.contacts
+                .iter()
+                .map(|contact| format!("mailto:{}", contact)),
+        )
+        .cache(DirCache::new(acme.cache_dir.clone()))
+        .directory_lets_encrypt(acme.production)
+        .state();
+
+    let mut server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_cert_resolver(state.resolver());
+    server_config.alpn_protocols = alpn;
+    let acceptor = state.axum_acceptor(Arc::new(server_config));
+
+    tokio::spawn(async move {
+        while let Some(event) = state.next().await {
+            match event {
+                Ok(event) => info!("ACME证书事件: {:?}", event),
+                Err(e) => error!("ACME证书申请失败: {:?}", e),
+            }
+        }
+    });
+
+    axum_server::bind(addr)
+        .acceptor(acceptor)
+        .serve(service)
+        .await
+}
+
+#[cfg(all(feature = "tls", not(feature = "acme")))]
+async fn serve_acme(
+    _service: axum::extract::connect_info::IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+    _addr: SocketAddr,
+    _acme: &common::config::WsAcmeConfig,
+    _alpn: Vec<Vec<u8>>,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "未启用acme特性，无法自动申请证书，请使用 --features acme 构建或配置证书文件",
+    ))
+}
+
+/// 在明文端口上将请求重定向到TLS端口
+async fn redirect_plain(addr: SocketAddr, tls_port: u16) {
+    let router = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        redirect_response(&headers, &uri, tls_port)
+    });
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("明文重定向端口 {} 监听失败: {}", addr, e);
+            return;
+        }
+    };
+    info!("redirect plain websocket connections on {}", addr);
+    if let Err(e) = axum::serve(listener, router).await {
+        error!("明文重定向服务异常退出: {}", e);
+    }
+}
+
+fn redirect_response(headers: &HeaderMap, uri: &Uri, tls_port: u16) -> Response {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok());
+    let websocket = headers
+        .get(header::UPGRADE)
+        .and_then(|upgrade| upgrade.to_str().ok())
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+
+    match redirect_location(host, path, websocket, tls_port) {
+        Some(location) => Redirect::permanent(&location).into_response(),
+        None => (StatusCode::BAD_REQUEST, "缺少Host请求头").into_response(),
+    }
+}
+
+/// 重定向的目标地址：WebSocket握手重定向到wss，其他请求重定向到https，保留路径和参数
+fn redirect_location(
+    host: Option<&str>,
+    path: &str,
+    websocket: bool,
+    tls_port: u16,
+) -> Option<String> {
+    let host = host.filter(|host| !host.is_empty())?;
+    // 去掉Host中的端口，IPv6地址形如 [::1]:80
+    let hostname = match host.rsplit_once(':') {
+        Some((hostname, port)) if !port.contains(']') => hostname,
+        _ => host,
+    };
+    let scheme = if websocket { "wss" } else { "https" };
+    Some(if tls_port == 443 {
+        format!("{}://{}{}", scheme, hostname, path)
+    } else {
+        format!("{}://{}:{}{}", scheme, hostname, tls_port, path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_location() {
+        assert_eq!(
+            redirect_location(Some("im.example.com:80"), "/ws/u/conn/p/1/t", true, 443),
+            Some("wss://im.example.com/ws/u/conn/p/1/t".to_string())
+        );
+        assert_eq!(
+            redirect_location(Some("im.example.com"), "/metrics?x=1", false, 50443),
+            Some("https://im.example.com:50443/metrics?x=1".to_string())
+        );
+        assert_eq!(
+            redirect_location(Some("[::1]:50001"), "/", true, 50000),
+            Some("wss://[::1]:50000/".to_string())
+        );
+        assert_eq!(
+            redirect_location(Some("[::1]"), "/", false, 443),
+            Some("https://[::1]/".to_string())
+        );
+        assert_eq!(redirect_location(None, "/", true, 443), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use common::config::{TopologyConfig, WebsocketConfig};
+use common::message::{ContentType, Msg, MsgType};
+use common::service_registry::{ServiceRegister, ServiceRegistry};
+use common::time_sync::now_millis;
+
+use crate::manager::Manager;
+
+/// 网关拓扑变化的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TopologyReason {
+    /// 网关实例增减
+    Changed,
+    /// 当前实例即将下线，客户端应迁移到其他实例
+    Draining,
+}
+
+impl TopologyReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TopologyReason::Changed => "changed",
+            TopologyReason::Draining => "draining",
+        }
+    }
+}
+
+/// 网关拓扑通知
+///
+/// 定期从Consul刷新健康的网关实例列表，实例变化或本实例下线时向已连接的客户端推送最新的接入地址，
+/// 客户端可以直接重连到健康的实例，不需要再调用REST接口获取接入地址
+#[derive(Clone)]
+pub struct TopologyNotifier {
+    config: TopologyConfig,
+    registry: Arc<dyn ServiceRegister>,
+    // Consul中网关WebSocket服务的名称
+    service_name: String,
+    // 客户端连接使用的协议，ws或wss
+    protocol: String,
+    // 本实例的接入地址
+    self_endpoint: String,
+    manager: Manager,
+}
+
+impl TopologyNotifier {
+    pub fn new(websocket: &WebsocketConfig, manager: Manager) -> Self {
+        Self {
+            config: websocket.topology.clone(),
+            registry: ServiceRegistry::from_env().shared(),
+            service_name: websocket.name.clone(),
+            protocol: websocket.client_protocol().to_string(),
+            self_endpoint: websocket.url(),
+            manager,
+        }
+    }
+
+    /// 替换发现网关实例使用的服务注册
+    pub fn with_registry(mut self, registry: Arc<dyn ServiceRegister>) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// 启动拓扑刷新任务
+    pub fn start(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            if !self.config.enabled {
+                return;
+            }
+
+            let mut ticker = tokio::time::interval(Duration::from_secs(
+                self.config.refresh_interval_secs.max(1),
+            ));
+            let mut known: Option<Vec<String>> = None;
+            loop {
+                ticker.tick().await;
+                let endpoints = match self.endpoints().await {
+                    Ok(endpoints) => endpoints,
+                    Err(e) => {
+                        warn!("刷新网关实例列表失败: {}", e);
+                        continue;
+                    }
+                };
+
+                // 首次获取只记录，之后实例列表变化时通知客户端
+                if known.as_ref().is_some_and(|known| *known != endpoints) {
+                    let notified = self.notify(TopologyReason::Changed, &endpoints);
+                    info!("网关实例列表已变化，通知客户端数: {}", notified);
+                }
+                known = Some(endpoints);
+            }
+        })
+    }
+
+    /// 本实例下线，通知客户端迁移到其他实例，并等待客户端重连
+    pub async fn drain(&self) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let endpoints = match self.endpoints().await {
+            Ok(endpoints) => endpoints
+                .into_iter()
+                .filter(|endpoint| *endpoint != self.self_endpoint)
+                .collect(),
+            Err(e) => {
+                // 拿不到列表时仍然通知下线，客户端回退到REST接口获取接入地址
+                warn!("获取网关实例列表失败: {}", e);
+                Vec::new()
+            }
+        };
+        let notified = self.notify(TopologyReason::Draining, &endpoints);
+        info!(
+            "网关即将下线，已通知客户端数: {}，等待 {} 秒",
+            notified, self.config.drain_grace_secs
+        );
+        tokio::time::sleep(Duration::from_secs(self.config.drain_grace_secs)).await;
+    }
+
+    /// 从Consul获取健康的网关接入地址，按字典序排列便于比较
+    async fn endpoints(&self) -> anyhow::Result<Vec<String>> {
+        let mut endpoints: Vec<String> = self
+            .registry
+            .discover(&self.service_name)
+            .await?
+            .into_iter()
+            .map(|url| to_ws_endpoint(&url, &self.protocol))
+            .collect();
+        endpoints.sort();
+        endpoints.dedup();
+        Ok(endpoints)
+    }
+
+    fn notify(&self, reason: TopologyReason, endpoints: &[String]) -> usize {
+        self.manager.notify_all(&topology_msg(reason, endpoints))
+    }
+}
+
+/// 将服务发现返回的地址转换为客户端使用的接入地址
+fn to_ws_endpoint(url: &str, protocol: &str) -> String {
+    let address = url.split_once("://").map_or(url, |(_, address)| address);
+    format!("{}://{}", protocol, address)
+}
+
+/// 构造拓扑变更控制消息
+fn topology_msg(reason: TopologyReason, endpoints: &[String]) -> Msg {
+    Msg {
+        send_time: now_millis(),
+        msg_type: MsgType::Service as i32,
+        content_type: ContentType::Text as i32,
+        content: serde_json::json!({
+            "type": "topology",
+            "reason": reason.as_str(),
+            "endpoints": endpoints,
+        })
+        .to_string()
+        .into_bytes(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topology_msg() {
+        assert_eq!(to_ws_endpoint("http://10.0.0.1:50000", "ws"), "ws://10.0.0.1:50000");
+        assert_eq!(to_ws_endpoint("10.0.0.1:50000", "wss"), "wss://10.0.0.1:50000");
+
+        let msg = topology_msg(TopologyReason::Draining, &["ws://10.0.0.2:50000".to_string()]);
+        assert_eq!(msg.msg_type, MsgType::Service as i32);
+        let content: serde_json::Value = serde_json::from_slice(&msg.content).unwrap();
+        assert_eq!(content["type"], "topology");
+        assert_eq!(content["reason"], "draining");
+        assert_eq!(content["endpoints"][0], "ws://10.0.0.2:50000");
+    }
+}
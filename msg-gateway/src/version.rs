@@ -0,0 +1,113 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use dashmap::DashMap;
+
+use common::client_version::VersionCheck;
+use common::config::ClientVersionConfig;
+
+/// 统计的版本组合上限，超过后新的组合计入 other，避免异常客户端撑大指标
+const MAX_SERIES: usize = 512;
+
+/// 客户端版本检查和版本分布统计
+#[derive(Debug)]
+pub struct VersionGate {
+    config: ClientVersionConfig,
+    // (平台, 版本, 检查结果) 到连接次数
+    counts: DashMap<(String, String, &'static str), AtomicU64>,
+}
+
+impl VersionGate {
+    pub fn new(config: ClientVersionConfig) -> Self {
+        Self {
+            config,
+            counts: DashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ClientVersionConfig {
+        &self.config
+    }
+
+    /// 检查连接的客户端版本并计入分布
+    pub fn check(&self, platform: &str, version: Option<&str>) -> VersionCheck {
+        let check = self.config.check(platform, version);
+        let result = match check {
+            VersionCheck::Allowed => "allowed",
+            VersionCheck::UpgradeRequired { .. } => "upgrade_required",
+        };
+        let platform = common::client_version::label(Some(platform));
+        let version = common::client_version::label(version);
+        self.record(platform, version, result);
+        check
+    }
+
+    fn record(&self, platform: String, version: String, result: &'static str) {
+        let key = (platform, version, result);
+        if let Some(count) = self.counts.get(&key) {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let key = if self.counts.len() < MAX_SERIES {
+            key
+        } else {
+            (key.0, "other".to_string(), result)
+        };
+        self.counts
+            .entry(key)
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 以Prometheus文本格式输出版本分布
+    pub fn render_metrics(&self) -> String {
+        let mut output = String::from(
+            "# HELP ws_client_versions_total 按平台、版本统计的WebSocket连接次数\n\
+             # TYPE ws_client_versions_total counter\n",
+        );
+        for entry in self.counts.iter() {
+            let (platform, version, result) = entry.key();
+            let _ = writeln!(
+                output,
+                "ws_client_versions_total{{platform=\"{}\",version=\"{}\",result=\"{}\"}} {}",
+                platform,
+                version,
+                result,
+                entry.value().load(Ordering::Relaxed)
+            );
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_distribution() {
+        let mut config = ClientVersionConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        config
+            .min_versions
+            .insert("mobile".to_string(), "2.0.0".to_string());
+        let gate = VersionGate::new(config);
+
+        assert_eq!(gate.check("mobile", Some("2.1.0")), VersionCheck::Allowed);
+        assert_eq!(gate.check("mobile", Some("2.1.0")), VersionCheck::Allowed);
+        assert!(matches!(
+            gate.check("mobile", Some("1.9.0")),
+            VersionCheck::UpgradeRequired { .. }
+        ));
+
+        let metrics = gate.render_metrics();
+        assert!(metrics.contains(
+            "ws_client_versions_total{platform=\"mobile\",version=\"2.1.0\",result=\"allowed\"} 2"
+        ));
+        assert!(metrics.contains(
+            "ws_client_versions_total{platform=\"mobile\",version=\"1.9.0\",result=\"upgrade_required\"} 1"
+        ));
+    }
+}
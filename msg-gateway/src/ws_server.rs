@@ -1,57 +1,84 @@
 use std::borrow::Cow;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use axum::extract::ws::CloseFrame;
-use axum::extract::{Path, State, WebSocketUpgrade};
-use axum::response::IntoResponse;
+use axum::extract::{ConnectInfo, Path, Query, State, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use axum::{
     extract::ws::{Message, WebSocket},
-    Router,
+    Json, Router,
 };
 use futures::{SinkExt, StreamExt};
-use jsonwebtoken::{decode, DecodingKey, Validation};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tokio::sync::{mpsc, RwLock};
 use tonic::transport::Channel;
 use tracing::{error, info, warn};
 
-use common::config::AppConfig;
+use common::client_version::VersionCheck;
+use common::config::{AppConfig, ConnectionLimitConfig, OutboundQueueConfig};
 use common::error::Error;
-use common::message::{Msg, PlatformType};
+use common::grpc::tenant::with_tenant;
+use common::grpc_client::{AuthServiceGrpcClient, DraftGrpcClient};
+use common::message::{ContentType, Msg, MsgType, PlatformType};
+use common::time_sync::{now_millis, TimeSyncRequest, TimeSyncResponse};
 
 use crate::client::Client;
+use crate::limiter::{ConnectionLimiter, ConnectionPermit, RejectReason};
 use crate::manager::Manager;
+use crate::outbound::OutboundQueue;
+use crate::receipt::{self, RECEIPT_PROTOCOL};
 use crate::rpc::MsgRpcService;
+use crate::schema;
+use crate::tls;
+use crate::version::VersionGate;
+use crate::topology::TopologyNotifier;
 
 // 心跳检测间隔时间，单位为秒
 // 用于定期向客户端发送ping消息，确认连接是否活跃
 pub const HEART_BEAT_INTERVAL: u64 = 30;
+// 最后活跃时间上报间隔，单位为秒
+// 同一连接在间隔内的多次心跳只上报一次，减少Redis写入
+pub const ACTIVE_REPORT_INTERVAL: u64 = 60;
 // 被踢下线的WebSocket关闭代码
 pub const KNOCK_OFF_CODE: u16 = 4001;
 // 未授权的WebSocket关闭代码
 pub const UNAUTHORIZED_CODE: u16 = 4002;
+// 客户端读取过慢被断开的WebSocket关闭代码，关闭原因中携带续传序号
+pub const SLOW_CONSUMER_CODE: u16 = 4003;
+// 客户端版本过低需要强制升级的WebSocket关闭代码，关闭原因中携带最低版本
+pub const UPGRADE_REQUIRED_CODE: u16 = 4004;
 
 /// WebSocket服务的应用状态
-/// 包含连接管理器和JWT密钥
+/// 包含连接管理器和令牌服务客户端
 #[derive(Clone)]
 pub struct AppState {
     // 连接管理器，负责管理所有客户端连接
     manager: Manager,
-    // JWT密钥，用于验证客户端token
-    jwt_secret: String,
+    // 令牌服务客户端，用于验证客户端token
+    auth_client: AuthServiceGrpcClient,
+    // 连接数限制
+    limiter: Arc<ConnectionLimiter>,
+    // 下行发送队列配置
+    outbound: OutboundQueueConfig,
+    // 会话草稿服务客户端，处理客户端的草稿同步请求
+    drafts: DraftGrpcClient,
+    // 客户端版本检查和版本分布统计
+    versions: Arc<VersionGate>,
+    // 单个批量已读确认帧最多包含的序号数
+    max_ack_seqs: usize,
 }
 
-/// JWT令牌的声明结构
-#[derive(Serialize, Deserialize)]
-pub struct Claims {
-    // 用户标识
-    pub sub: String,
-    // 过期时间
-    pub exp: u64,
-    // 颁发时间
-    pub iat: u64,
+/// 连接参数
+#[derive(Debug, Default, Deserialize)]
+pub struct ConnectParams {
+    // 重连时客户端已收到的最大消息序号
+    pub resume_seq: Option<i64>,
+    // 客户端版本，浏览器无法设置WebSocket请求头时使用
+    pub client_version: Option<String>,
 }
 
 /// WebSocket服务器实现
@@ -108,9 +135,19 @@ impl WsServer {
         Ok(description)
     }
 
+    /// 连接数指标，Prometheus文本格式
+    async fn metrics(State(state): State<AppState>) -> String {
+        state.limiter.render_metrics() + &state.versions.render_metrics()
+    }
+
+    /// WebSocket协议描述
+    async fn schema() -> Json<&'static serde_json::Value> {
+        Json(schema::ws_schema())
+    }
+
     /// 启动WebSocket服务器
-    /// 初始化管理器、设置路由并启动服务
-    pub async fn start(config: AppConfig) {
+    /// 初始化管理器、设置路由并启动服务，监听地址无法解析时返回错误
+    pub async fn start(config: AppConfig) -> Result<(), Error> {
         // 创建消息通道，用于Manager和客户端之间的通信
         let (tx, rx) = mpsc::channel(1024);
         // 初始化连接管理器
@@ -123,7 +160,12 @@ impl WsServer {
         // 创建应用状态
         let app_state = AppState {
             manager: hub.clone(),
-            jwt_secret: config.jwt.secret.clone(),
+            auth_client: AuthServiceGrpcClient::from_env(),
+            limiter: Arc::new(ConnectionLimiter::new(config.websocket.limits.clone())),
+            outbound: config.websocket.outbound.clone(),
+            drafts: DraftGrpcClient::from_env(),
+            versions: Arc::new(VersionGate::new(config.client_version.clone())),
+            max_ack_seqs: config.receipts.max_ack_seqs,
         };
 
         // 配置Axum路由
@@ -133,21 +175,27 @@ impl WsServer {
                 get(Self::websocket_handler),
             )
             .route("/test", get(Self::test))
+            .route("/metrics", get(Self::metrics))
+            .route("/ws/schema", get(Self::schema))
             .with_state(app_state);
-        // 构建监听地址
-        let addr = format!("{}:{}", config.websocket.host, config.websocket.port);
+        // 构建监听地址，主机可以是IP或域名
+        let addr = Self::listen_addr(&config.websocket.host, config.websocket.port).await?;
 
-        // 启动TCP监听器
-        let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
-        // 在独立任务中启动WebSocket服务器
+        // 在独立任务中启动WebSocket服务器，按配置提供ws或wss
+        let tls_config = config.websocket.tls.clone();
         let mut ws = tokio::spawn(async move {
-            info!("start websocket server on {}", addr);
-            axum::serve(listener, router).await.unwrap();
+            if let Err(e) = tls::serve(router, addr, &tls_config).await {
+                error!("WebSocket服务异常退出: {}", e);
+            }
         });
 
         // 向服务注册中心注册WebSocket服务
         Self::register_service(&config).await.unwrap();
 
+        // 网关实例变化时通知客户端新的接入地址
+        let topology = TopologyNotifier::new(&config.websocket, hub.clone());
+        let topology_task = topology.clone().start();
+
         // 克隆配置用于RPC服务
         let config = config.clone();
         // 在独立任务中启动RPC服务
@@ -156,42 +204,423 @@ impl WsServer {
             MsgRpcService::start(hub, &config).await.unwrap();
         });
         
-        // 等待任一任务完成，并中止另一个任务
+        // 等待任一任务完成或收到退出信号，并中止其他任务
         tokio::select! {
-            _ = (&mut ws) => ws.abort(),
-            _ = (&mut rpc) => rpc.abort(),
+            _ = (&mut ws) => rpc.abort(),
+            _ = (&mut rpc) => ws.abort(),
+            _ = tokio::signal::ctrl_c() => {
+                // 下线前通知客户端迁移到其他实例
+                topology.drain().await;
+                ws.abort();
+                rpc.abort();
+            }
         }
+        topology_task.abort();
+        Ok(())
+    }
+
+    /// 解析监听地址，域名取解析到的第一个地址
+    async fn listen_addr(host: &str, port: u16) -> Result<SocketAddr, Error> {
+        tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or_else(|| Error::Internal(format!("WebSocket监听地址 {} 无法解析", host)))
     }
 
-    /// 验证JWT令牌
-    /// 确保连接请求是授权的
-    fn verify_token(token: String, jwt_secret: &String) -> Result<(), Error> {
-        if let Err(err) = decode::<Claims>(
-            &token,
-            &DecodingKey::from_secret(jwt_secret.as_bytes()),
-            &Validation::default(),
-        ) {
+    /// 验证令牌
+    /// 通过令牌服务自省，确保令牌有效、未被吊销且属于连接的用户，返回用户所属的租户ID
+    async fn verify_token(
+        token: &str,
+        user_id: &str,
+        auth_client: &AuthServiceGrpcClient,
+    ) -> Result<String, Error> {
+        let response = auth_client
+            .introspect(token)
+            .await
+            .map_err(|e| Error::Internal(format!("introspect token error: {}", e)))?;
+
+        if !response.active {
             return Err(Error::Authentication(format!(
                 "verify token error: {}:{}",
-                err, "/ws"
+                response.reason, "/ws"
             )));
         }
-        Ok(())
+        if response.user_id != user_id {
+            return Err(Error::Authentication(format!(
+                "token user mismatch: {}:{}",
+                user_id, "/ws"
+            )));
+        }
+        // 没有租户的用户租户ID为0
+        if response.tenant_id == 0 {
+            return Ok(String::new());
+        }
+        Ok(response.tenant_id.to_string())
+    }
+
+    /// 上报用户最后活跃时间，失败只记录日志，不影响连接
+    /// 最后活跃时间经Redis汇总后落库，Redis降级期间不上报
+    async fn report_active(hub: &Manager, user_id: &str) {
+        if hub.redis.is_degraded() {
+            return;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        if let Err(e) = hub.cache.record_user_active(user_id, now).await {
+            warn!("上报用户最后活跃时间失败: {:?}", e);
+        }
+    }
+
+    /// 获取客户端IP
+    ///
+    /// 开启信任时从右向左取X-Forwarded-For中第一个不是可信代理的地址，左侧的地址由客户端填写，不能信任；
+    /// 配置了可信代理而直连的对端不是可信代理时，忽略X-Forwarded-For
+    fn client_ip(
+        headers: &HeaderMap,
+        remote: SocketAddr,
+        config: &ConnectionLimitConfig,
+    ) -> IpAddr {
+        let trusted = |ip: &IpAddr| config.trusted_proxies.contains(ip);
+        if !config.trust_forwarded_for
+            || (!config.trusted_proxies.is_empty() && !trusted(&remote.ip()))
+        {
+            return remote.ip();
+        }
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .collect();
+        for entry in forwarded.into_iter().rev() {
+            match entry.trim().parse::<IpAddr>() {
+                Ok(ip) if trusted(&ip) => continue,
+                Ok(ip) => return ip,
+                // 无法解析的地址左侧的内容都不可信
+                Err(_) => break,
+            }
+        }
+        remote.ip()
+    }
+
+    /// 携带续传序号的重连客户端先验证令牌，通过时返回租户ID，用于重连限流时优先接入
+    /// 只在普通令牌用尽时调用，不查询Redis，被限流的连接尝试不增加Redis的压力
+    async fn verify_reconnect(
+        state: &AppState,
+        token: &str,
+        user_id: &str,
+        resume_seq: Option<i64>,
+    ) -> Option<String> {
+        if !resume_seq.is_some_and(|seq| seq > 0) {
+            return None;
+        }
+        match Self::verify_token(token, user_id, &state.auth_client).await {
+            Ok(tenant_id) => Some(tenant_id),
+            Err(e) => {
+                warn!("重连客户端令牌验证失败，不优先接入: {:?}", e);
+                None
+            }
+        }
+    }
+
+    /// 连接被拒绝时的响应
+    /// 达到全局上限且开启排队时返回排队响应，告知客户端重试间隔
+    /// 被重连限流时返回429，重试间隔带随机抖动，打散客户端的重试时间
+    fn reject_response(reason: RejectReason, limiter: &ConnectionLimiter) -> Response {
+        let config = limiter.config();
+        match reason {
+            RejectReason::Throttled => {
+                let retry_after = limiter.retry_after();
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    axum::Json(serde_json::json!({
+                        "code": StatusCode::TOO_MANY_REQUESTS.as_u16(),
+                        "message": "连接过于频繁，请稍后重试",
+                        "retry_after": retry_after,
+                        "retry_jitter": config.retry_jitter_secs,
+                    })),
+                )
+                    .into_response()
+            }
+            RejectReason::PerIp => {
+                (StatusCode::TOO_MANY_REQUESTS, "too many connections from this ip").into_response()
+            }
+            RejectReason::Global if config.waiting_room => {
+                let retry_after = limiter.retry_after();
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(header::RETRY_AFTER, retry_after.to_string())],
+                    axum::Json(serde_json::json!({
+                        "code": StatusCode::SERVICE_UNAVAILABLE.as_u16(),
+                        "message": "服务繁忙，正在排队，请稍后重试",
+                        "retry_after": retry_after,
+                        "retry_jitter": config.retry_jitter_secs,
+                    })),
+                )
+                    .into_response()
+            }
+            RejectReason::Global => {
+                (StatusCode::SERVICE_UNAVAILABLE, "too many connections").into_response()
+            }
+        }
+    }
+
+    /// 客户端续传
+    /// 比较客户端已收到的序号和当前最大序号，存在缺口时通知客户端按区间拉取
+    /// Redis降级期间Redis中的序号不是最新的，不发送续传通知，客户端收到新消息时按序号缺口拉取
+    async fn resume(hub: &Manager, user_id: &str, resume_seq: i64, outbound: &OutboundQueue) {
+        if hub.redis.is_degraded() {
+            return;
+        }
+        let cur_seq = match hub.cache.get_seq(user_id).await {
+            Ok(seq) => seq,
+            Err(e) => {
+                warn!("获取用户消息序号失败: {:?}", e);
+                return;
+            }
+        };
+        if cur_seq <= resume_seq {
+            return;
+        }
+
+        let msg = Msg {
+            receiver_id: user_id.to_string(),
+            send_time: now_millis(),
+            msg_type: MsgType::Service as i32,
+            content_type: ContentType::Text as i32,
+            content: serde_json::json!({
+                "type": "resume",
+                "from_seq": resume_seq + 1,
+                "to_seq": cur_seq,
+            })
+            .to_string()
+            .into_bytes(),
+            ..Default::default()
+        };
+        match bincode::serialize(&msg) {
+            // 续传通知必须送达，按关键消息入队
+            Ok(content) => {
+                outbound.push(Message::Binary(content.into()), 0, true);
+            }
+            Err(e) => error!("msg serialize error: {}", e),
+        }
+        info!("用户 {} 续传消息，序号区间: {}-{}", user_id, resume_seq + 1, cur_seq);
+    }
+
+    /// 处理客户端发来的控制消息，返回true表示已处理，不再转发
+    ///
+    /// 目前支持：
+    /// - 对时请求：`{"type": "time_sync", "client_time": 客户端发送时间}`，
+    ///   直接在本连接上返回服务端收发时间，客户端据此估算时钟偏差
+    /// - 草稿同步：`{"type": "draft", "conversation_id": 会话ID, "text": 草稿}`，
+    ///   文本为空时清除草稿，由草稿服务保存并推送给用户的其他设备
+    fn handle_control(
+        msg: &Msg,
+        received_at: i64,
+        outbound: &OutboundQueue,
+        drafts: &DraftGrpcClient,
+        user_id: &str,
+        platform: PlatformType,
+    ) -> bool {
+        if msg.msg_type != MsgType::Service as i32 {
+            return false;
+        }
+        let Ok(content) = serde_json::from_slice::<serde_json::Value>(&msg.content) else {
+            return false;
+        };
+        match content.get("type").and_then(|t| t.as_str()) {
+            Some("time_sync") => {}
+            Some("draft") => {
+                Self::sync_draft(&content, drafts, user_id, &msg.tenant_id, platform);
+                return true;
+            }
+            _ => return false,
+        }
+
+        let request: TimeSyncRequest = serde_json::from_value(content).unwrap_or_default();
+        let response = TimeSyncResponse::new(&request, received_at);
+        let reply = Msg {
+            receiver_id: msg.send_id.clone(),
+            local_id: msg.local_id.clone(),
+            send_time: response.server_send_time,
+            msg_type: MsgType::Service as i32,
+            content_type: ContentType::Text as i32,
+            content: serde_json::json!({
+                "type": "time_sync",
+                "client_time": response.client_time,
+                "server_receive_time": response.server_receive_time,
+                "server_send_time": response.server_send_time,
+            })
+            .to_string()
+            .into_bytes(),
+            ..Default::default()
+        };
+        match bincode::serialize(&reply) {
+            // 对时响应对时效敏感，发送队列满时可以丢弃，客户端会重试
+            Ok(content) => {
+                outbound.push(Message::Binary(content.into()), 0, false);
+            }
+            Err(e) => error!("msg serialize error: {}", e),
+        }
+        true
+    }
+
+    /// 协商了批量确认子协议时把确认帧转换为一条已读消息，其他消息检查后原样返回；
+    /// 确认帧无效、未协商子协议或已读消息的读者不是连接的用户时丢弃
+    fn ack_frame(mut msg: Msg, user_id: &str, receipts: bool, max_ack_seqs: usize) -> Option<Msg> {
+        match receipt::ack_to_read(&msg, user_id, max_ack_seqs) {
+            None => match receipt::check_client_msg(&mut msg, user_id) {
+                Ok(()) => Some(msg),
+                Err(e) => {
+                    warn!("丢弃用户 {} 的无效已读消息: {}", user_id, e);
+                    None
+                }
+            },
+            Some(_) if !receipts => {
+                warn!("用户 {} 未协商批量确认子协议，丢弃确认帧", user_id);
+                None
+            }
+            Some(Ok(read)) => Some(read),
+            Some(Err(e)) => {
+                warn!("丢弃用户 {} 的无效确认帧: {}", user_id, e);
+                None
+            }
+        }
+    }
+
+    /// 转发草稿同步请求，草稿以连接的用户为准，不等待结果
+    fn sync_draft(
+        content: &serde_json::Value,
+        drafts: &DraftGrpcClient,
+        user_id: &str,
+        tenant_id: &str,
+        platform: PlatformType,
+    ) {
+        let conversation_id = content
+            .get("conversation_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+        if conversation_id.is_empty() {
+            warn!("草稿同步请求缺少会话ID，用户: {}", user_id);
+            return;
+        }
+        let text = content
+            .get("text")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let drafts = drafts.clone();
+        let user_id = user_id.to_string();
+        let tenant_id = Some(tenant_id.to_string());
+        // 在连接的租户上下文中请求，草稿写入该租户的数据库集群
+        tokio::spawn(with_tenant(tenant_id, async move {
+            let result = if text.is_empty() {
+                drafts
+                    .clear_draft(&user_id, &conversation_id, platform as i32)
+                    .await
+                    .map(|_| ())
+            } else {
+                drafts
+                    .set_draft(&user_id, &conversation_id, &text, platform as i32)
+                    .await
+                    .map(|_| ())
+            };
+            if let Err(e) = result {
+                warn!("同步用户 {} 的会话草稿失败: {:?}", user_id, e);
+            }
+        }));
     }
 
     /// WebSocket连接处理器
     /// 从URL路径中提取参数并处理连接升级
     pub async fn websocket_handler(
         Path((user_id, pointer_id, platform, token)): Path<(String, String, i32, String)>,
+        ConnectInfo(remote): ConnectInfo<SocketAddr>,
+        Query(params): Query<ConnectParams>,
+        headers: HeaderMap,
         ws: WebSocketUpgrade,
         State(state): State<AppState>,
-    ) -> impl IntoResponse {
+    ) -> Response {
         // 将平台类型转换为枚举值
         let platform = PlatformType::try_from(platform).unwrap_or_default();
-        // 处理WebSocket连接升级
-        ws.on_upgrade(move |socket| {
-            Self::websocket(user_id, pointer_id, token, platform, socket, state)
-        })
+
+        // 版本过低的客户端不占用连接名额，升级后以关闭码告知，浏览器客户端读不到握手响应的状态码
+        let version = headers
+            .get(state.versions.config().version_header.as_str())
+            .and_then(|v| v.to_str().ok())
+            .or(params.client_version.as_deref());
+        let platform_name = platform.as_str_name().to_ascii_lowercase();
+        if let VersionCheck::UpgradeRequired { min_version } =
+            state.versions.check(&platform_name, version)
+        {
+            warn!(
+                "客户端版本过低，要求升级，用户: {}, 平台: {}, 版本: {:?}, 最低版本: {}",
+                user_id, platform_name, version, min_version
+            );
+            return ws
+                .on_upgrade(move |socket| Self::close_upgrade_required(socket, min_version))
+                .into_response();
+        }
+
+        // 升级前占用连接名额，超限直接拒绝
+        let ip = Self::client_ip(&headers, remote, state.limiter.config());
+        // 重连限流，令牌不足时携带续传序号且令牌有效的重连客户端优先接入
+        // 优先接入前已经验证过令牌的，升级后不再重复验证
+        let mut verified = None;
+        if let Err(reason) = state
+            .limiter
+            .admit(async {
+                verified =
+                    Self::verify_reconnect(&state, &token, &user_id, params.resume_seq).await;
+                verified.is_some()
+            })
+            .await
+        {
+            warn!("拒绝WebSocket连接，IP: {}, 原因: {:?}", ip, reason);
+            return Self::reject_response(reason, &state.limiter);
+        }
+        let permit = match state.limiter.try_acquire(ip) {
+            Ok(permit) => permit,
+            Err(reason) => {
+                warn!("拒绝WebSocket连接，IP: {}, 原因: {:?}", ip, reason);
+                return Self::reject_response(reason, &state.limiter);
+            }
+        };
+
+        // 处理WebSocket连接升级，客户端提供批量已读确认子协议时选中，客户端据此判断能否发送确认帧
+        ws.protocols([RECEIPT_PROTOCOL])
+            .on_upgrade(move |socket| {
+                Self::websocket(
+                    user_id,
+                    pointer_id,
+                    token,
+                    platform,
+                    params.resume_seq,
+                    verified,
+                    socket,
+                    state,
+                    permit,
+                )
+            })
+            .into_response()
+    }
+
+    /// 以升级关闭码断开版本过低的客户端
+    async fn close_upgrade_required(mut ws: WebSocket, min_version: String) {
+        if let Err(e) = ws
+            .send(Message::Close(Some(CloseFrame {
+                code: UPGRADE_REQUIRED_CODE,
+                reason: Cow::Owned(min_version),
+            })))
+            .await
+        {
+            error!("发送升级关闭消息给客户端时出错: {}", e);
+        }
     }
 
     /// 处理WebSocket连接
@@ -201,38 +630,56 @@ impl WsServer {
         pointer_id: String,
         token: String,
         platform: PlatformType,
+        resume_seq: Option<i64>,
+        // 接入时已验证令牌得到的租户ID
+        verified_tenant: Option<String>,
         ws: WebSocket,
         app_state: AppState,
+        // 连接结束时释放名额
+        _permit: ConnectionPermit,
     ) {
         tracing::info!(
             "客户端 {} 已连接，用户ID: {}",
             user_id.clone(),
             pointer_id.clone()
         );
+        // 握手时选中了批量确认子协议的连接才接受确认帧
+        let receipts = ws
+            .protocol()
+            .is_some_and(|protocol| protocol == RECEIPT_PROTOCOL);
         // 将WebSocket分为发送和接收两部分
         let (mut ws_tx, mut ws_rx) = ws.split();
         
         // 验证令牌
-        if let Err(err) = Self::verify_token(token, &app_state.jwt_secret) {
-            warn!("验证令牌错误: {:?}", err);
-            // 如果验证失败，发送关闭消息
-            if let Err(e) = ws_tx
-                .send(Message::Close(Some(CloseFrame {
-                    code: UNAUTHORIZED_CODE,
-                    reason: Cow::Owned("未授权连接".to_string()),
-                })))
-                .await
-            {
-                error!("发送验证失败消息给客户端时出错: {}", e);
+        let verified = match verified_tenant {
+            Some(tenant_id) => Ok(tenant_id),
+            None => Self::verify_token(&token, &user_id, &app_state.auth_client).await,
+        };
+        let tenant_id = match verified {
+            Ok(tenant_id) => tenant_id,
+            Err(err) => {
+                warn!("验证令牌错误: {:?}", err);
+                // 如果验证失败，发送关闭消息
+                if let Err(e) = ws_tx
+                    .send(Message::Close(Some(CloseFrame {
+                        code: UNAUTHORIZED_CODE,
+                        reason: Cow::Owned("未授权连接".to_string()),
+                    })))
+                    .await
+                {
+                    error!("发送验证失败消息给客户端时出错: {}", e);
+                }
+                return;
             }
-            return;
-        }
+        };
         
         // 创建共享的发送通道
         let shared_tx = Arc::new(RwLock::new(ws_tx));
         // 创建通知通道，用于关闭连接
         let (notify_sender, mut notify_receiver) = tokio::sync::mpsc::channel(1);
         let mut hub = app_state.manager.clone();
+        // 下行发送队列，推送方只入队，由发送任务写入连接
+        let outbound = Arc::new(OutboundQueue::new(app_state.outbound.clone()));
         
         // 创建客户端对象
         let client = Client {
@@ -241,11 +688,50 @@ impl WsServer {
             sender: shared_tx.clone(),
             platform,
             notify_sender,
+            outbound: outbound.clone(),
         };
         
         // 向连接管理器注册客户端
         hub.register(user_id.clone(), client).await;
 
+        // 重连时按序号续传
+        if let Some(resume_seq) = resume_seq {
+            Self::resume(&hub, &user_id, resume_seq, &outbound).await;
+        }
+
+        // 从发送队列取消息写入连接的任务
+        let cloned_tx = shared_tx.clone();
+        let cloned_outbound = outbound.clone();
+        let mut send_task = tokio::spawn(async move {
+            while let Some(msg) = cloned_outbound.pop().await {
+                if let Err(e) = cloned_tx.write().await.send(msg).await {
+                    error!("send message error: {}", e);
+                    cloned_outbound.close();
+                    return;
+                }
+            }
+
+            // 客户端读取过慢，断开连接并告知续传序号
+            if cloned_outbound.is_overflowed() {
+                warn!(
+                    "客户端读取过慢，断开连接，已丢弃消息数: {}",
+                    cloned_outbound.dropped()
+                );
+                let reason = format!("slow consumer, resume_seq={}", cloned_outbound.last_sent_seq());
+                if let Err(e) = cloned_tx
+                    .write()
+                    .await
+                    .send(Message::Close(Some(CloseFrame {
+                        code: SLOW_CONSUMER_CODE,
+                        reason: Cow::Owned(reason),
+                    })))
+                    .await
+                {
+                    error!("send slow consumer close frame error: {}", e);
+                }
+            }
+        });
+
         // 发送心跳消息给客户端的任务
         let cloned_tx = shared_tx.clone();
         let mut ping_task = tokio::spawn(async move {
@@ -287,9 +773,25 @@ impl WsServer {
         // spawn a new task to receive message
         let cloned_hub = hub.clone();
         let shared_tx = shared_tx.clone();
+        let active_user_id = user_id.clone();
+        let control_outbound = outbound.clone();
+        let control_drafts = app_state.drafts.clone();
+        let max_ack_seqs = app_state.max_ack_seqs;
         // receive message from client
         let mut rec_task = tokio::spawn(async move {
+            let mut last_reported: Option<Instant> = None;
             while let Some(Ok(msg)) = ws_rx.next().await {
+                // 对时请求需要尽早记录收到的时间
+                let received_at = now_millis();
+
+                // 收到任何客户端帧都视为活跃，按上报间隔合并写入
+                if last_reported
+                    .map_or(true, |t| t.elapsed() >= Duration::from_secs(ACTIVE_REPORT_INTERVAL))
+                {
+                    last_reported = Some(Instant::now());
+                    Self::report_active(&cloned_hub, &active_user_id).await;
+                }
+
                 // 处理消息
                 match msg {
                     Message::Text(text) => {
@@ -298,8 +800,25 @@ impl WsServer {
                             error!("deserialize error: {:?}； source: {text}", result.err());
                             continue;
                         }
-
-                        if cloned_hub.broadcast(result.unwrap()).await.is_err() {
+                        let mut msg: Msg = result.unwrap();
+                        // 租户以令牌为准，消息服务按租户选择消息主题和数据库集群
+                        msg.tenant_id = tenant_id.clone();
+                        if Self::handle_control(
+                            &msg,
+                            received_at,
+                            &control_outbound,
+                            &control_drafts,
+                            &active_user_id,
+                            platform,
+                        ) {
+                            continue;
+                        }
+                        let Some(msg) =
+                            Self::ack_frame(msg, &active_user_id, receipts, max_ack_seqs)
+                        else {
+                            continue;
+                        };
+                        if cloned_hub.broadcast(msg).await.is_err() {
                             // if broadcast not available, close the connection
                             break;
                         }
@@ -330,7 +849,23 @@ impl WsServer {
                             error!("deserialize error: {:?}； source: {:?}", result.err(), b);
                             continue;
                         }
-                        let msg: Msg = result.unwrap();
+                        let mut msg: Msg = result.unwrap();
+                        msg.tenant_id = tenant_id.clone();
+                        if Self::handle_control(
+                            &msg,
+                            received_at,
+                            &control_outbound,
+                            &control_drafts,
+                            &active_user_id,
+                            platform,
+                        ) {
+                            continue;
+                        }
+                        let Some(msg) =
+                            Self::ack_frame(msg, &active_user_id, receipts, max_ack_seqs)
+                        else {
+                            continue;
+                        };
                         // todo need to judge the local id is empty by message type
                         // if msg.local_id.is_empty() {
                         //     warn!("receive empty message");
@@ -345,10 +880,12 @@ impl WsServer {
         });
         let mut need_unregister = true;
         tokio::select! {
-            _ = (&mut ping_task) => {rec_task.abort(); watch_task.abort();},
-            _ = (&mut watch_task) => {need_unregister = false; rec_task.abort(); ping_task.abort();},
-            _ = (&mut rec_task) => {ping_task.abort(); watch_task.abort();},
+            _ = (&mut ping_task) => {rec_task.abort(); watch_task.abort(); send_task.abort();},
+            _ = (&mut watch_task) => {need_unregister = false; rec_task.abort(); ping_task.abort(); send_task.abort();},
+            _ = (&mut rec_task) => {ping_task.abort(); watch_task.abort(); send_task.abort();},
+            _ = (&mut send_task) => {ping_task.abort(); watch_task.abort(); rec_task.abort();},
         }
+        outbound.close();
 
         // lost the connection, remove the client from hub
         if need_unregister {
@@ -357,3 +894,45 @@ impl WsServer {
         tracing::debug!("client thread exit {}", hub.hub.iter().count());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip() {
+        let remote: SocketAddr = "10.0.0.1:4000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "1.1.1.1, 2.2.2.2, 10.0.0.2".parse().unwrap(),
+        );
+        let mut config = ConnectionLimitConfig::default();
+
+        // 未开启信任时使用直连地址
+        assert_eq!(WsServer::client_ip(&headers, remote, &config), remote.ip());
+
+        // 客户端填写的最左侧地址不可信，取最右侧的地址
+        config.trust_forwarded_for = true;
+        let ip = WsServer::client_ip(&headers, remote, &config);
+        assert_eq!(ip, "10.0.0.2".parse::<IpAddr>().unwrap());
+
+        // 跳过可信代理
+        config.trusted_proxies = vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        let ip = WsServer::client_ip(&headers, remote, &config);
+        assert_eq!(ip, "2.2.2.2".parse::<IpAddr>().unwrap());
+
+        // 直连的对端不是可信代理时忽略X-Forwarded-For
+        let direct: SocketAddr = "3.3.3.3:4000".parse().unwrap();
+        assert_eq!(WsServer::client_ip(&headers, direct, &config), direct.ip());
+    }
+
+    #[tokio::test]
+    async fn test_listen_addr() {
+        let addr = WsServer::listen_addr("0.0.0.0", 50000).await.unwrap();
+        assert_eq!(addr, "0.0.0.0:50000".parse::<SocketAddr>().unwrap());
+
+        let addr = WsServer::listen_addr("localhost", 50000).await.unwrap();
+        assert!(addr.ip().is_loopback());
+    }
+}
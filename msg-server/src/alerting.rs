@@ -0,0 +1,343 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nanoid::nanoid;
+use serde_json::json;
+use tracing::{error, info, warn};
+
+use common::config::ConsumerAlertConfig;
+
+/// 消费错误类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// Kafka消息解码失败
+    Parse,
+    /// 写入Postgres或MongoDB失败
+    Storage,
+    /// 推送给在线用户失败
+    Push,
+}
+
+impl ErrorCategory {
+    const ALL: [ErrorCategory; 3] = [
+        ErrorCategory::Parse,
+        ErrorCategory::Storage,
+        ErrorCategory::Push,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCategory::Parse => "parse",
+            ErrorCategory::Storage => "storage",
+            ErrorCategory::Push => "push",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ErrorCategory::Parse => "消息解析",
+            ErrorCategory::Storage => "消息存储",
+            ErrorCategory::Push => "消息推送",
+        }
+    }
+}
+
+/// 统计窗口内的处理数和错误数
+#[derive(Debug, Default)]
+struct Counter {
+    total: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// 告警通知类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AlertKind {
+    /// 开始告警
+    Firing,
+    /// 告警持续，重复提醒
+    Repeat,
+    /// 已恢复
+    Resolved,
+}
+
+/// 单个错误类别的告警状态
+#[derive(Debug, Default)]
+struct AlertState {
+    firing: bool,
+    /// 连续超过阈值的窗口数
+    breached_windows: u32,
+    /// 连续低于阈值的窗口数
+    healthy_windows: u32,
+    /// 上一次发送告警的时间
+    last_sent: Option<Instant>,
+}
+
+impl AlertState {
+    /// 根据本窗口是否超过阈值更新状态，返回需要发送的通知；
+    /// 同一次告警只在开始、按间隔重复提醒和恢复时发送
+    fn evaluate(
+        &mut self,
+        breached: bool,
+        now: Instant,
+        config: &ConsumerAlertConfig,
+    ) -> Option<AlertKind> {
+        if breached {
+            self.breached_windows += 1;
+            self.healthy_windows = 0;
+        } else {
+            self.healthy_windows += 1;
+            self.breached_windows = 0;
+        }
+
+        if !self.firing {
+            if self.breached_windows >= config.trigger_windows.max(1) {
+                self.firing = true;
+                self.last_sent = Some(now);
+                return Some(AlertKind::Firing);
+            }
+            return None;
+        }
+
+        if self.healthy_windows >= config.recovery_windows.max(1) {
+            self.firing = false;
+            self.last_sent = None;
+            return Some(AlertKind::Resolved);
+        }
+        let repeat = Duration::from_secs(config.repeat_interval_secs);
+        if breached
+            && self
+                .last_sent
+                .map_or(true, |last_sent| now.duration_since(last_sent) >= repeat)
+        {
+            self.last_sent = Some(now);
+            return Some(AlertKind::Repeat);
+        }
+        None
+    }
+}
+
+/// 窗口内的错误率是否超过阈值
+fn is_breached(errors: u64, total: u64, config: &ConsumerAlertConfig) -> bool {
+    total > 0
+        && errors >= config.min_errors
+        && errors as f64 / total as f64 >= config.error_rate_threshold
+}
+
+/// 消息消费错误率告警
+///
+/// 消费循环记录每次解析、存储和推送的结果，后台任务按统计窗口计算各类错误的错误率，
+/// 持续超过阈值时向Webhook发送告警，恢复后发送恢复通知。告警状态保存在各实例内，
+/// 通知中附带实例ID，多个实例同时出错时各自告警
+#[derive(Clone)]
+pub struct ConsumerAlerts {
+    counters: Arc<[Counter; 3]>,
+    config: ConsumerAlertConfig,
+    instance_id: String,
+}
+
+impl ConsumerAlerts {
+    pub fn new(config: &ConsumerAlertConfig) -> Self {
+        Self {
+            counters: Arc::new(Default::default()),
+            config: config.clone(),
+            instance_id: format!("msg-server-{}", nanoid!(8)),
+        }
+    }
+
+    /// 记录一次处理结果
+    pub fn record(&self, category: ErrorCategory, ok: bool) {
+        self.record_many(category, 1, u64::from(!ok));
+    }
+
+    /// 记录一批处理结果，例如群聊推送按成员统计，`errors` 为其中失败的数量
+    pub fn record_many(&self, category: ErrorCategory, total: u64, errors: u64) {
+        let counter = &self.counters[category.index()];
+        counter.total.fetch_add(total, Ordering::Relaxed);
+        if errors > 0 {
+            counter.errors.fetch_add(errors, Ordering::Relaxed);
+            metrics::counter!("im_kafka_consume_errors_total", "category" => category.as_str())
+                .increment(errors);
+        }
+    }
+
+    /// 启动后台检查任务，未启用或未配置Webhook时只统计指标
+    pub fn start(&self) {
+        if !self.config.enabled {
+            return;
+        }
+        if self.config.webhook_url.is_empty() {
+            warn!("未配置消费错误告警的Webhook地址，不发送告警");
+            return;
+        }
+        let alerts = self.clone();
+        tokio::spawn(async move {
+            alerts.run().await;
+        });
+    }
+
+    async fn run(&self) {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(self.config.timeout_ms))
+            .build()
+            .expect("创建告警Webhook HTTP客户端失败");
+        let mut states: [AlertState; 3] = Default::default();
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.window_secs.max(1)));
+        // 第一次tick立即返回，跳过不完整的窗口
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            for category in ErrorCategory::ALL {
+                let counter = &self.counters[category.index()];
+                let total = counter.total.swap(0, Ordering::Relaxed);
+                let errors = counter.errors.swap(0, Ordering::Relaxed);
+                let breached = is_breached(errors, total, &self.config);
+                if let Some(kind) = states[category.index()].evaluate(breached, now, &self.config) {
+                    self.notify(&client, category, kind, errors, total).await;
+                }
+            }
+        }
+    }
+
+    /// 发送告警或恢复通知，发送失败只记录日志
+    async fn notify(
+        &self,
+        client: &reqwest::Client,
+        category: ErrorCategory,
+        kind: AlertKind,
+        errors: u64,
+        total: u64,
+    ) {
+        let rate = if total > 0 {
+            errors as f64 / total as f64
+        } else {
+            0.0
+        };
+        let window = format!(
+            "最近 {} 秒 {}/{}（{:.1}%）",
+            self.config.window_secs,
+            errors,
+            total,
+            rate * 100.0
+        );
+        let (status, text) = match kind {
+            AlertKind::Firing => (
+                "firing",
+                format!(
+                    "[告警] {} {}错误率持续超过 {:.1}%：{}",
+                    self.instance_id,
+                    category.label(),
+                    self.config.error_rate_threshold * 100.0,
+                    window
+                ),
+            ),
+            AlertKind::Repeat => (
+                "firing",
+                format!(
+                    "[告警] {} {}错误率仍然超过 {:.1}%：{}",
+                    self.instance_id,
+                    category.label(),
+                    self.config.error_rate_threshold * 100.0,
+                    window
+                ),
+            ),
+            AlertKind::Resolved => (
+                "resolved",
+                format!(
+                    "[恢复] {} {}错误率已恢复：{}",
+                    self.instance_id,
+                    category.label(),
+                    window
+                ),
+            ),
+        };
+        info!("发送消费错误告警: {}", text);
+
+        let payload = json!({
+            "text": text,
+            "status": status,
+            "category": category.as_str(),
+            "instance": self.instance_id,
+            "errors": errors,
+            "total": total,
+            "error_rate": rate,
+        });
+        if let Err(e) = client
+            .post(&self.config.webhook_url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+        {
+            error!("发送消费错误告警失败: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_breached() {
+        let config = ConsumerAlertConfig::default();
+        assert!(is_breached(10, 100, &config));
+        // 错误数不足时不计为超过阈值
+        assert!(!is_breached(5, 10, &config));
+        assert!(!is_breached(10, 1000, &config));
+        assert!(!is_breached(0, 0, &config));
+    }
+
+    #[test]
+    fn test_record_many() {
+        let alerts = ConsumerAlerts::new(&ConsumerAlertConfig::default());
+        alerts.record(ErrorCategory::Push, false);
+        alerts.record_many(ErrorCategory::Push, 10, 3);
+        alerts.record_many(ErrorCategory::Push, 5, 0);
+
+        let counter = &alerts.counters[ErrorCategory::Push.index()];
+        assert_eq!(counter.total.load(Ordering::Relaxed), 16);
+        assert_eq!(counter.errors.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn test_alert_dedup_and_recovery() {
+        let config = ConsumerAlertConfig::default();
+        let mut state = AlertState::default();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // 连续3个窗口超过阈值才告警
+        assert_eq!(state.evaluate(true, at(0), &config), None);
+        assert_eq!(state.evaluate(true, at(60), &config), None);
+        assert_eq!(state.evaluate(false, at(120), &config), None);
+        assert_eq!(state.evaluate(true, at(180), &config), None);
+        assert_eq!(state.evaluate(true, at(240), &config), None);
+        assert_eq!(
+            state.evaluate(true, at(300), &config),
+            Some(AlertKind::Firing)
+        );
+
+        // 告警期间不重复发送，超过重复提醒间隔后再提醒一次
+        assert_eq!(state.evaluate(true, at(360), &config), None);
+        assert_eq!(state.evaluate(false, at(420), &config), None);
+        assert_eq!(
+            state.evaluate(true, at(3900), &config),
+            Some(AlertKind::Repeat)
+        );
+
+        // 连续2个窗口低于阈值后恢复
+        assert_eq!(state.evaluate(false, at(3960), &config), None);
+        assert_eq!(
+            state.evaluate(false, at(4020), &config),
+            Some(AlertKind::Resolved)
+        );
+        assert_eq!(state.evaluate(false, at(4080), &config), None);
+    }
+}
@@ -0,0 +1,774 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::FindOptions;
+use mongodb::Collection;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, warn};
+
+use cache::Cache;
+use common::config::{AppConfig, BackfillConfig};
+use common::error::Error;
+use common::grpc::subject::check_subject;
+use common::grpc::tenant::{current_tenant, with_tenant};
+use common::grpc_client::{FriendServiceGrpcClient, GroupServiceGrpcClient};
+use common::message::{Msg, MsgType};
+use common::proto::backfill::history_backfill_service_server::HistoryBackfillService;
+use common::proto::backfill::{
+    CancelBackfillRequest, CancelBackfillResponse, GetConversationRangeRequest,
+    GetConversationRangeResponse, GetReadCursorsRequest, GetReadCursorsResponse, HistoryChunk,
+    ReadCursor, StreamHistoryRequest,
+};
+use common::pseudonym::IdPseudonymizer;
+use common::tenant_db::MongoRouter;
+
+/// 消息盒子集合名称
+const MSG_BOX_COLLECTION: &str = "msg_box";
+
+/// 回填会话
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conversation {
+    /// 单聊为对方用户ID，群聊为群组ID
+    pub id: String,
+    pub is_group: bool,
+}
+
+/// 历史消息来源
+#[async_trait]
+pub trait HistorySource: Send + Sync {
+    /// 用户的会话列表，按最近一条消息的时间从新到旧
+    async fn conversations(&self, user_id: &str, limit: i64) -> Result<Vec<Conversation>, Error>;
+
+    /// 会话中位于 `before` 之前的一批消息，按发送时间从新到旧
+    async fn messages(
+        &self,
+        user_id: &str,
+        conversation: &Conversation,
+        before: Option<&Position>,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error>;
+
+    /// 会话中会话序号位于 `(after, until]` 的消息，按会话序号从小到大；`until` 为None时不限制上界
+    async fn conversation_range(
+        &self,
+        user_id: &str,
+        conversation: &Conversation,
+        after: i64,
+        until: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error>;
+}
+
+/// 会话内的回填位置：已推送的最旧一条消息的发送时间和服务端ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub send_time: i64,
+    pub server_id: String,
+}
+
+/// 设备的回填进度，保存在Redis中
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct BackfillCursor {
+    /// 开始回填时确定的会话顺序
+    conversations: Vec<Conversation>,
+    /// 当前回填的会话下标
+    index: usize,
+    /// 当前会话已回填到的位置，None 表示尚未开始
+    before: Option<Position>,
+}
+
+impl BackfillCursor {
+    fn new(conversations: Vec<Conversation>) -> Self {
+        Self {
+            conversations,
+            index: 0,
+            before: None,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.index >= self.conversations.len()
+    }
+
+    /// 根据本批结果推进进度，不足一批说明当前会话已回填完
+    fn advance(&mut self, messages: &[Msg], chunk_size: i64) {
+        match messages.last() {
+            Some(last) if messages.len() as i64 >= chunk_size => {
+                self.before = Some(Position {
+                    send_time: last.send_time,
+                    server_id: last.server_id.clone(),
+                });
+            }
+            _ => {
+                self.index += 1;
+                self.before = None;
+            }
+        }
+    }
+
+    /// 客户端持有的继续令牌，格式为 `index` 或 `index:send_time:server_id`
+    fn token(&self) -> String {
+        match &self.before {
+            Some(position) => format!(
+                "{}:{}:{}",
+                self.index, position.send_time, position.server_id
+            ),
+            None => self.index.to_string(),
+        }
+    }
+
+    /// 按客户端令牌回退进度
+    ///
+    /// 服务端保存的进度可能比客户端实际处理的多一批（推送后客户端未来得及处理就断开），
+    /// 因此以客户端令牌为准，但不允许超过服务端进度
+    fn rewind(&mut self, token: &str) -> Result<(), Error> {
+        let invalid = || Error::BadRequest(format!("无效的回填令牌: {}", token));
+
+        let mut parts = token.splitn(3, ':');
+        let index: usize = parts
+            .next()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(invalid)?;
+        let before = match (parts.next(), parts.next()) {
+            (Some(send_time), Some(server_id)) => Some(Position {
+                send_time: send_time.parse().map_err(|_| invalid())?,
+                server_id: server_id.to_string(),
+            }),
+            (None, None) => None,
+            _ => return Err(invalid()),
+        };
+
+        if index > self.index {
+            return Err(invalid());
+        }
+        self.index = index;
+        self.before = before;
+        Ok(())
+    }
+}
+
+/// 基于MongoDB消息盒子的历史消息来源
+///
+/// 消息盒子启用ID化名时按化名查询，会话化名通过用户的好友和群组列表还原，
+/// 已不是好友或已退出的群的会话无法还原，不参与回填。消息盒子按用户所属租户路由
+pub struct MongoHistorySource {
+    mongo: MongoRouter,
+    pseudonymizer: IdPseudonymizer,
+    friend_client: FriendServiceGrpcClient,
+    group_client: GroupServiceGrpcClient,
+}
+
+impl MongoHistorySource {
+    pub async fn new(config: &AppConfig) -> Self {
+        let mongo = MongoRouter::connect(config).await.expect("MongoDB连接失败");
+        let pseudonymizer = IdPseudonymizer::from_config(&config.database.mongodb.pseudonym)
+            .expect("接收盒ID化名配置错误");
+        Self {
+            mongo,
+            pseudonymizer,
+            friend_client: FriendServiceGrpcClient::from_env(),
+            group_client: GroupServiceGrpcClient::from_env(),
+        }
+    }
+
+    fn collection(&self) -> Collection<Document> {
+        self.mongo.database().collection(MSG_BOX_COLLECTION)
+    }
+
+    fn chat_types() -> Bson {
+        Bson::Array(vec![
+            Bson::Int32(MsgType::SingleMsg as i32),
+            Bson::Int32(MsgType::GroupMsg as i32),
+        ])
+    }
+
+    /// 用户好友和群组的化名到原ID的映射，用于还原会话ID
+    async fn conversation_ids(&self, user_id: &str) -> Result<HashMap<String, String>, Error> {
+        let friends = self
+            .friend_client
+            .get_friend_list(user_id)
+            .await
+            .map_err(|e| Error::Internal(format!("查询好友列表失败: {}", e)))?;
+        let groups = self
+            .group_client
+            .get_user_groups(user_id)
+            .await
+            .map_err(|e| Error::Internal(format!("查询群组列表失败: {}", e)))?;
+
+        let ids = friends
+            .friends
+            .into_iter()
+            .map(|friend| friend.id)
+            .chain(groups.groups.into_iter().map(|group| group.id));
+        let mut mapping = HashMap::new();
+        for id in ids {
+            for pseudonym in self.pseudonymizer.candidates(&id) {
+                mapping.insert(pseudonym, id.clone());
+            }
+        }
+        Ok(mapping)
+    }
+
+    /// 用户在会话中的消息的查询条件
+    fn conversation_filter(&self, user_id: &str, conversation: &Conversation) -> Document {
+        let user_keys = self.pseudonymizer.candidates(user_id);
+        let conversation_keys = self.pseudonymizer.candidates(&conversation.id);
+        if conversation.is_group {
+            // 群消息按成员各存一份，只需查询自己的消息盒子
+            doc! {
+                "receiver_id": { "$in": user_keys.as_slice() },
+                "group_id": { "$in": conversation_keys.as_slice() },
+            }
+        } else {
+            doc! {
+                "msg_type": MsgType::SingleMsg as i32,
+                "$or": [
+                    {
+                        "send_id": { "$in": user_keys.as_slice() },
+                        "receiver_id": { "$in": conversation_keys.as_slice() },
+                    },
+                    {
+                        "send_id": { "$in": conversation_keys.as_slice() },
+                        "receiver_id": { "$in": user_keys.as_slice() },
+                    },
+                ],
+            }
+        }
+    }
+
+    /// 按条件查询消息并还原化名
+    async fn find_messages(
+        &self,
+        user_id: &str,
+        conversation: &Conversation,
+        filter: Document,
+        options: FindOptions,
+    ) -> Result<Vec<Msg>, Error> {
+        let mut cursor = self
+            .collection()
+            .find(filter, options)
+            .await
+            .map_err(|e| Error::Internal(format!("查询历史消息失败: {}", e)))?;
+
+        let mut messages = Vec::new();
+        while let Some(document) = cursor
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("读取历史消息失败: {}", e)))?
+        {
+            let mut msg = Msg::try_from(document)?;
+            if self.pseudonymizer.is_enabled() {
+                self.restore_ids(&mut msg, user_id, &conversation.id);
+            }
+            messages.push(msg);
+        }
+        Ok(messages)
+    }
+
+    /// 将消息中的化名还原为原ID
+    fn restore_ids(&self, msg: &mut Msg, user_id: &str, conversation_id: &str) {
+        let user_keys = self.pseudonymizer.candidates(user_id);
+        let conversation_keys = self.pseudonymizer.candidates(conversation_id);
+        for field in [&mut msg.send_id, &mut msg.receiver_id, &mut msg.group_id] {
+            if user_keys.contains(&*field) {
+                *field = user_id.to_string();
+            } else if conversation_keys.contains(&*field) {
+                *field = conversation_id.to_string();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl HistorySource for MongoHistorySource {
+    async fn conversations(&self, user_id: &str, limit: i64) -> Result<Vec<Conversation>, Error> {
+        // 未启用化名时只有原ID一个候选
+        let user_keys = self.pseudonymizer.candidates(user_id);
+        let is_group = doc! { "$gt": [{ "$strLenCP": { "$ifNull": ["$group_id", ""] } }, 0] };
+        let pipeline = vec![
+            doc! { "$match": {
+                "$or": [
+                    { "receiver_id": { "$in": user_keys.as_slice() } },
+                    { "send_id": { "$in": user_keys.as_slice() } },
+                ],
+                "msg_type": { "$in": Self::chat_types() },
+            }},
+            doc! { "$project": {
+                "send_time": 1,
+                "is_group": is_group.clone(),
+                "conversation": { "$cond": [
+                    is_group,
+                    "$group_id",
+                    { "$cond": [{ "$in": ["$send_id", user_keys.as_slice()] }, "$receiver_id", "$send_id"] },
+                ]},
+            }},
+            doc! { "$group": {
+                "_id": { "conversation": "$conversation", "is_group": "$is_group" },
+                "last": { "$max": "$send_time" },
+            }},
+            doc! { "$sort": { "last": -1 } },
+            doc! { "$limit": limit },
+        ];
+
+        let mut cursor = self
+            .collection()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| Error::Internal(format!("查询会话列表失败: {}", e)))?;
+
+        let mut conversations = Vec::new();
+        while let Some(document) = cursor
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("读取会话列表失败: {}", e)))?
+        {
+            let Ok(key) = document.get_document("_id") else {
+                continue;
+            };
+            conversations.push(Conversation {
+                id: key.get_str("conversation").unwrap_or_default().to_string(),
+                is_group: key.get_bool("is_group").unwrap_or_default(),
+            });
+        }
+
+        if self.pseudonymizer.is_enabled() {
+            let mapping = self.conversation_ids(user_id).await?;
+            let total = conversations.len();
+            conversations.retain_mut(|conversation| match mapping.get(&conversation.id) {
+                Some(id) => {
+                    conversation.id = id.clone();
+                    true
+                }
+                None => false,
+            });
+            if conversations.len() < total {
+                warn!(
+                    "用户 {} 有 {} 个会话无法还原化名，跳过回填",
+                    user_id,
+                    total - conversations.len()
+                );
+            }
+        }
+        Ok(conversations)
+    }
+
+    async fn messages(
+        &self,
+        user_id: &str,
+        conversation: &Conversation,
+        before: Option<&Position>,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error> {
+        let mut filter = self.conversation_filter(user_id, conversation);
+        if let Some(position) = before {
+            filter.insert(
+                "$and",
+                vec![doc! { "$or": [
+                    { "send_time": { "$lt": position.send_time } },
+                    { "send_time": position.send_time, "server_id": { "$lt": &position.server_id } },
+                ]}],
+            );
+        }
+
+        let options = FindOptions::builder()
+            .sort(doc! { "send_time": -1, "server_id": -1 })
+            .limit(limit)
+            .build();
+        self.find_messages(user_id, conversation, filter, options)
+            .await
+    }
+
+    async fn conversation_range(
+        &self,
+        user_id: &str,
+        conversation: &Conversation,
+        after: i64,
+        until: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error> {
+        let mut filter = self.conversation_filter(user_id, conversation);
+        let mut range = doc! { "$gt": after };
+        if let Some(until) = until {
+            range.insert("$lte", until);
+        }
+        filter.insert("conv_seq", range);
+
+        let options = FindOptions::builder()
+            .sort(doc! { "conv_seq": 1 })
+            .limit(limit)
+            .build();
+        self.find_messages(user_id, conversation, filter, options)
+            .await
+    }
+}
+
+/// 新设备历史消息回填服务
+///
+/// 按会话最近活跃时间从新到旧、每个会话从新到旧分批推送，批次之间按配置限速，
+/// 单实例并发回填数量受限，避免新设备集中登录时压垮MongoDB。
+/// 每推送一批保存一次进度，客户端断开或取消后可以继续。
+pub struct BackfillService {
+    source: Arc<dyn HistorySource>,
+    cache: Arc<dyn Cache>,
+    config: BackfillConfig,
+    permits: Arc<Semaphore>,
+}
+
+impl BackfillService {
+    pub fn new(source: Arc<dyn HistorySource>, cache: Arc<dyn Cache>, config: BackfillConfig) -> Self {
+        let permits = Arc::new(Semaphore::new(config.max_concurrent.max(1)));
+        Self {
+            source,
+            cache,
+            config,
+            permits,
+        }
+    }
+
+    /// 读取或新建回填进度
+    async fn load_cursor(&self, req: &StreamHistoryRequest) -> Result<BackfillCursor, Error> {
+        if !req.restart {
+            if let Some(saved) = self
+                .cache
+                .get_backfill_cursor(&req.user_id, &req.device_id)
+                .await?
+            {
+                match serde_json::from_str::<BackfillCursor>(&saved) {
+                    Ok(mut cursor) => {
+                        if !req.resume_token.is_empty() {
+                            cursor.rewind(&req.resume_token)?;
+                        }
+                        return Ok(cursor);
+                    }
+                    Err(e) => error!("回填进度解析失败，重新开始: {}", e),
+                }
+            }
+        }
+
+        let conversations = self
+            .source
+            .conversations(&req.user_id, self.config.max_conversations)
+            .await?;
+        debug!(
+            "用户 {} 设备 {} 开始回填，共 {} 个会话",
+            req.user_id,
+            req.device_id,
+            conversations.len()
+        );
+        Ok(BackfillCursor::new(conversations))
+    }
+}
+
+#[tonic::async_trait]
+impl HistoryBackfillService for BackfillService {
+    type StreamHistoryStream = ReceiverStream<Result<HistoryChunk, Status>>;
+
+    async fn stream_history(
+        &self,
+        request: Request<StreamHistoryRequest>,
+    ) -> Result<Response<Self::StreamHistoryStream>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() || req.device_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID和设备ID不能为空"));
+        }
+
+        let permit = self
+            .permits
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| Error::TooManyRequests("历史消息回填繁忙，请稍后重试".to_string()))?;
+
+        // 新的回填请求清除之前遗留的取消标记
+        self.cache
+            .take_backfill_cancel(&req.user_id, &req.device_id)
+            .await?;
+        let cursor = self.load_cursor(&req).await?;
+
+        let chunk_size = if req.chunk_size <= 0 {
+            self.config.chunk_size
+        } else {
+            (req.chunk_size as i64).min(self.config.max_chunk_size)
+        };
+        let task = BackfillTask {
+            source: self.source.clone(),
+            cache: self.cache.clone(),
+            user_id: req.user_id,
+            device_id: req.device_id,
+            chunk_size,
+            interval: Duration::from_millis(self.config.chunk_interval_ms),
+        };
+
+        // 回填任务在请求所属租户的上下文中执行，按该租户读取消息盒子
+        let (tx, rx) = mpsc::channel(2);
+        let tenant_id = current_tenant();
+        tokio::spawn(with_tenant(tenant_id, async move {
+            let _permit = permit;
+            task.run(cursor, tx).await;
+        }));
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn cancel_backfill(
+        &self,
+        request: Request<CancelBackfillRequest>,
+    ) -> Result<Response<CancelBackfillResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() || req.device_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID和设备ID不能为空"));
+        }
+        self.cache
+            .cancel_backfill(&req.user_id, &req.device_id)
+            .await?;
+        info!("用户 {} 设备 {} 取消历史消息回填", req.user_id, req.device_id);
+        Ok(Response::new(CancelBackfillResponse {}))
+    }
+
+    async fn get_read_cursors(
+        &self,
+        request: Request<GetReadCursorsRequest>,
+    ) -> Result<Response<GetReadCursorsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID不能为空"));
+        }
+
+        let cursors = self
+            .cache
+            .get_read_cursors(&req.user_id)
+            .await?
+            .into_iter()
+            .map(|(conversation_id, read_seq)| ReadCursor {
+                conversation_id,
+                read_seq,
+            })
+            .collect();
+        Ok(Response::new(GetReadCursorsResponse { cursors }))
+    }
+
+    async fn get_conversation_range(
+        &self,
+        request: Request<GetConversationRangeRequest>,
+    ) -> Result<Response<GetConversationRangeResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() || req.conversation_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID和会话ID不能为空"));
+        }
+        if req.after_conv_seq < 0
+            || (req.until_conv_seq > 0 && req.until_conv_seq <= req.after_conv_seq)
+        {
+            return Err(Status::invalid_argument("会话序号范围无效"));
+        }
+
+        let limit = if req.limit <= 0 {
+            self.config.chunk_size
+        } else {
+            (req.limit as i64).min(self.config.max_chunk_size)
+        };
+        let conversation = Conversation {
+            id: req.conversation_id,
+            is_group: req.is_group,
+        };
+        let until = (req.until_conv_seq > 0).then_some(req.until_conv_seq);
+        // 多取一条判断范围内是否还有更多消息
+        let mut messages = self
+            .source
+            .conversation_range(
+                &req.user_id,
+                &conversation,
+                req.after_conv_seq,
+                until,
+                limit + 1,
+            )
+            .await?;
+        let has_more = messages.len() as i64 > limit;
+        messages.truncate(limit as usize);
+
+        Ok(Response::new(GetConversationRangeResponse {
+            messages: messages.iter().map(|msg| msg.encode_to_vec()).collect(),
+            has_more,
+        }))
+    }
+}
+
+/// 单个设备的回填任务
+struct BackfillTask {
+    source: Arc<dyn HistorySource>,
+    cache: Arc<dyn Cache>,
+    user_id: String,
+    device_id: String,
+    chunk_size: i64,
+    interval: Duration,
+}
+
+impl BackfillTask {
+    async fn run(&self, mut cursor: BackfillCursor, tx: mpsc::Sender<Result<HistoryChunk, Status>>) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            match self
+                .cache
+                .take_backfill_cancel(&self.user_id, &self.device_id)
+                .await
+            {
+                Ok(true) => {
+                    info!("用户 {} 设备 {} 的回填已取消", self.user_id, self.device_id);
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => error!("读取回填取消标记失败: {:?}", e),
+            }
+
+            match self.next_chunk(&mut cursor).await {
+                Ok(Some(chunk)) => {
+                    let done = chunk.done;
+                    // 客户端断开时停止，进度停留在上一批
+                    if tx.send(Ok(chunk)).await.is_err() {
+                        debug!("用户 {} 设备 {} 的回填连接已断开", self.user_id, self.device_id);
+                        return;
+                    }
+                    if let Err(e) = self.save_cursor(&cursor, done).await {
+                        error!("保存回填进度失败: {:?}", e);
+                    }
+                    if done {
+                        info!("用户 {} 设备 {} 回填完成", self.user_id, self.device_id);
+                        return;
+                    }
+                }
+                // 空会话直接跳过，不推送
+                Ok(None) => {}
+                Err(e) => {
+                    error!("回填历史消息失败: {:?}", e);
+                    let _ = tx.send(Err(e.into())).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 读取下一批消息并推进进度，当前会话没有消息且回填未完成时返回None
+    async fn next_chunk(&self, cursor: &mut BackfillCursor) -> Result<Option<HistoryChunk>, Error> {
+        if cursor.is_done() {
+            return Ok(Some(HistoryChunk {
+                resume_token: cursor.token(),
+                done: true,
+                ..Default::default()
+            }));
+        }
+
+        let conversation = cursor.conversations[cursor.index].clone();
+        let messages = self
+            .source
+            .messages(
+                &self.user_id,
+                &conversation,
+                cursor.before.as_ref(),
+                self.chunk_size,
+            )
+            .await?;
+        let remaining = (cursor.conversations.len() - cursor.index) as i32;
+        cursor.advance(&messages, self.chunk_size);
+
+        if messages.is_empty() && !cursor.is_done() {
+            return Ok(None);
+        }
+
+        // 附带会话的已读位置，新设备回填后未读数与其他设备一致
+        let read_seq = self
+            .cache
+            .get_read_cursor(&self.user_id, &conversation.id)
+            .await?;
+
+        Ok(Some(HistoryChunk {
+            conversation_id: conversation.id,
+            is_group: conversation.is_group,
+            messages: messages.iter().map(|msg| msg.encode_to_vec()).collect(),
+            resume_token: cursor.token(),
+            remaining_conversations: remaining,
+            done: cursor.is_done(),
+            read_seq,
+        }))
+    }
+
+    async fn save_cursor(&self, cursor: &BackfillCursor, done: bool) -> Result<(), Error> {
+        if done {
+            return self
+                .cache
+                .delete_backfill_cursor(&self.user_id, &self.device_id)
+                .await;
+        }
+        let value = serde_json::to_string(cursor)?;
+        self.cache
+            .save_backfill_cursor(&self.user_id, &self.device_id, &value)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(send_time: i64, server_id: &str) -> Msg {
+        Msg {
+            send_time,
+            server_id: server_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn cursor() -> BackfillCursor {
+        BackfillCursor::new(vec![
+            Conversation {
+                id: "u2".to_string(),
+                is_group: false,
+            },
+            Conversation {
+                id: "g1".to_string(),
+                is_group: true,
+            },
+        ])
+    }
+
+    #[test]
+    fn advance_and_rewind() {
+        let mut cursor = cursor();
+
+        // 满批时停留在当前会话
+        cursor.advance(&[msg(300, "c"), msg(200, "b")], 2);
+        assert_eq!(cursor.index, 0);
+        assert_eq!(cursor.token(), "0:200:b");
+        let token = cursor.token();
+
+        // 不足一批进入下一个会话
+        cursor.advance(&[msg(100, "a")], 2);
+        assert_eq!(cursor.token(), "1");
+
+        // 客户端只处理到上一批时按令牌回退
+        cursor.rewind(&token).unwrap();
+        assert_eq!(cursor.index, 0);
+        assert_eq!(cursor.before.as_ref().unwrap().send_time, 200);
+
+        // 令牌不能超过服务端进度
+        assert!(cursor.rewind("1").is_err());
+        assert!(cursor.rewind("0:abc:x").is_err());
+
+        cursor.advance(&[], 2);
+        cursor.advance(&[], 2);
+        assert!(cursor.is_done());
+    }
+}
@@ -0,0 +1,263 @@
+use prost::Message as _;
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+use rdkafka::Message;
+
+use common::config::KafkaPayloadFormat;
+use common::error::Error;
+use common::message::Msg;
+
+/// 标识消息编码格式的Kafka消息头
+pub const CONTENT_TYPE_HEADER: &str = "content-type";
+
+/// 信令消息的投递期限（UTC毫秒），超过期限的消息不再投递
+pub const EXPIRE_AT_HEADER: &str = "expire-at";
+
+/// 消息产生的区域，跨区域复制时只复制在对端区域产生的消息，避免循环复制
+pub const ORIGIN_REGION_HEADER: &str = "origin-region";
+
+/// JSON消息的结构版本，JSON消息体无法加信封前缀，版本写在消息头中，取值与信封版本相同
+pub const VERSION_HEADER: &str = "msg-version";
+
+/// protobuf编码
+pub const CONTENT_TYPE_PROTOBUF: &str = "application/x-protobuf";
+
+/// JSON编码
+pub const CONTENT_TYPE_JSON: &str = "application/json";
+
+/// 信封魔数，protobuf编码的消息不会以0开头（字段号0非法），可以与未加信封的消息区分
+pub const ENVELOPE_MAGIC: u8 = 0x00;
+
+/// 当前的消息结构版本，Msg结构有不兼容变更时递增，并在 [`upgrade`] 中补充旧版本的转换
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// 可以解码的最低信封版本，滚动升级期间需要兼容上一个版本（ENVELOPE_VERSION - 1）；
+/// 未加信封的消息和没有版本头的JSON消息视为版本0，始终按当前结构解码
+pub const MIN_ENVELOPE_VERSION: u8 = 1;
+
+/// 按配置的格式编码消息，返回消息体和消息头，有投递期限时写入期限消息头
+///
+/// protobuf消息的版本写在信封中，JSON消息的版本写在 [`VERSION_HEADER`] 消息头中
+pub fn encode(
+    msg: &Msg,
+    format: KafkaPayloadFormat,
+    expire_at: Option<i64>,
+) -> Result<(Vec<u8>, OwnedHeaders), Error> {
+    let (payload, content_type) = match format {
+        KafkaPayloadFormat::Protobuf => (seal(msg), CONTENT_TYPE_PROTOBUF),
+        KafkaPayloadFormat::Json => (serde_json::to_vec(msg)?, CONTENT_TYPE_JSON),
+    };
+    let mut headers = OwnedHeaders::new().insert(Header {
+        key: CONTENT_TYPE_HEADER,
+        value: Some(content_type),
+    });
+    if format == KafkaPayloadFormat::Json {
+        headers = headers.insert(Header {
+            key: VERSION_HEADER,
+            value: Some(&ENVELOPE_VERSION.to_string()),
+        });
+    }
+    if let Some(expire_at) = expire_at {
+        headers = headers.insert(Header {
+            key: EXPIRE_AT_HEADER,
+            value: Some(&expire_at.to_string()),
+        });
+    }
+    Ok((payload, headers))
+}
+
+/// 读取消息的投递期限，没有期限头或格式错误时返回None
+pub fn expire_at<M: Message>(message: &M) -> Option<i64> {
+    let value = message
+        .headers()?
+        .iter()
+        .find(|header| header.key == EXPIRE_AT_HEADER)?
+        .value?;
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+/// 写入消息产生的区域
+pub fn with_origin(headers: OwnedHeaders, region: &str) -> OwnedHeaders {
+    headers.insert(Header {
+        key: ORIGIN_REGION_HEADER,
+        value: Some(region),
+    })
+}
+
+/// 读取消息产生的区域，没有区域头时返回None
+pub fn origin_region<M: Message>(message: &M) -> Option<String> {
+    let value = message
+        .headers()?
+        .iter()
+        .find(|header| header.key == ORIGIN_REGION_HEADER)?
+        .value?;
+    std::str::from_utf8(value).ok().map(String::from)
+}
+
+/// 按消息头解码Kafka消息，没有格式头的旧消息按JSON解析
+pub fn decode<M: Message>(message: &M) -> Result<Msg, Error> {
+    let payload = message
+        .payload()
+        .ok_or_else(|| Error::Internal("消息体为空".to_string()))?;
+    let header = |key: &str| {
+        message.headers().and_then(|headers| {
+            headers
+                .iter()
+                .find(|header| header.key == key)
+                .and_then(|header| header.value)
+        })
+    };
+    decode_payload(payload, header(CONTENT_TYPE_HEADER), header(VERSION_HEADER))
+}
+
+/// 封装信封：魔数 + 版本 + protobuf编码的消息
+fn seal(msg: &Msg) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(2 + msg.encoded_len());
+    payload.push(ENVELOPE_MAGIC);
+    payload.push(ENVELOPE_VERSION);
+    // 预留了足够容量，写入Vec不会失败
+    let _ = msg.encode(&mut payload);
+    payload
+}
+
+/// 拆开信封并按版本解码，没有信封的消息视为未加信封的旧版本protobuf消息
+fn open(payload: &[u8]) -> Result<Msg, Error> {
+    let (version, body) = match payload {
+        [ENVELOPE_MAGIC, version, body @ ..] => (*version, body),
+        _ => return decode_protobuf(payload),
+    };
+    check_version(version)?;
+    Ok(upgrade(version, decode_protobuf(body)?))
+}
+
+/// 按版本头解码JSON消息，没有版本头的消息视为未加版本的旧消息
+fn decode_json(payload: &[u8], version: Option<&[u8]>) -> Result<Msg, Error> {
+    let Some(version) = version else {
+        return Ok(serde_json::from_slice(payload)?);
+    };
+    let version = std::str::from_utf8(version)
+        .ok()
+        .and_then(|v| v.parse::<u8>().ok())
+        .ok_or_else(|| {
+            Error::Internal(format!(
+                "无效的消息版本头: {}",
+                String::from_utf8_lossy(version)
+            ))
+        })?;
+    check_version(version)?;
+    Ok(upgrade(version, serde_json::from_slice(payload)?))
+}
+
+fn check_version(version: u8) -> Result<(), Error> {
+    if version > ENVELOPE_VERSION {
+        return Err(Error::Internal(format!(
+            "消息版本 {} 高于当前支持的版本 {}，请先升级消费端",
+            version, ENVELOPE_VERSION
+        )));
+    }
+    if version < MIN_ENVELOPE_VERSION {
+        return Err(Error::Internal(format!(
+            "消息版本 {} 已不再支持，最低支持版本 {}",
+            version, MIN_ENVELOPE_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// 将旧版本的消息转换为当前版本
+///
+/// protobuf和JSON本身兼容新增字段，这里只处理字段语义变化等不兼容的变更；
+/// 目前只有一个版本，结构变更时在这里按版本逐级转换
+fn upgrade(version: u8, msg: Msg) -> Msg {
+    debug_assert!((MIN_ENVELOPE_VERSION..=ENVELOPE_VERSION).contains(&version));
+    msg
+}
+
+fn decode_protobuf(payload: &[u8]) -> Result<Msg, Error> {
+    Msg::decode(payload).map_err(|e| Error::Internal(format!("解码protobuf消息失败: {}", e)))
+}
+
+fn decode_payload(
+    payload: &[u8],
+    content_type: Option<&[u8]>,
+    version: Option<&[u8]>,
+) -> Result<Msg, Error> {
+    match content_type {
+        Some(t) if t == CONTENT_TYPE_PROTOBUF.as_bytes() => open(payload),
+        Some(t) if t != CONTENT_TYPE_JSON.as_bytes() => Err(Error::Internal(format!(
+            "不支持的消息格式: {}",
+            String::from_utf8_lossy(t)
+        ))),
+        _ => decode_json(payload, version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_payload() {
+        let msg = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "u2".to_string(),
+            content: b"hello".to_vec(),
+            seq: 3,
+            ..Default::default()
+        };
+
+        let sealed = seal(&msg);
+        assert_eq!(&sealed[..2], &[ENVELOPE_MAGIC, ENVELOPE_VERSION]);
+        let decoded =
+            decode_payload(&sealed, Some(CONTENT_TYPE_PROTOBUF.as_bytes()), None).unwrap();
+        assert_eq!(decoded, msg);
+
+        // 未加信封的protobuf消息
+        let proto = msg.encode_to_vec();
+        assert_eq!(
+            decode_payload(&proto, Some(CONTENT_TYPE_PROTOBUF.as_bytes()), None).unwrap(),
+            msg
+        );
+
+        // 高于当前版本的消息无法解码
+        let mut newer = sealed.clone();
+        newer[1] = ENVELOPE_VERSION + 1;
+        assert!(decode_payload(&newer, Some(CONTENT_TYPE_PROTOBUF.as_bytes()), None).is_err());
+
+        // 迁移期间没有格式头的旧消息按JSON解析
+        let json = serde_json::to_vec(&msg).unwrap();
+        assert_eq!(decode_payload(&json, None, None).unwrap(), msg);
+        assert_eq!(
+            decode_payload(&json, Some(CONTENT_TYPE_JSON.as_bytes()), None).unwrap(),
+            msg
+        );
+
+        assert!(decode_payload(&json, Some(b"text/xml"), None).is_err());
+    }
+
+    #[test]
+    fn test_decode_versioned_json() {
+        let msg = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "u2".to_string(),
+            content: b"hello".to_vec(),
+            seq: 3,
+            ..Default::default()
+        };
+
+        let (payload, headers) = encode(&msg, KafkaPayloadFormat::Json, None).unwrap();
+        let version = headers
+            .iter()
+            .find(|header| header.key == VERSION_HEADER)
+            .and_then(|header| header.value)
+            .unwrap();
+        assert_eq!(version, ENVELOPE_VERSION.to_string().as_bytes());
+
+        let json = Some(CONTENT_TYPE_JSON.as_bytes());
+        assert_eq!(decode_payload(&payload, json, Some(version)).unwrap(), msg);
+
+        // 高于当前版本或格式错误的版本头无法解码
+        let newer = (ENVELOPE_VERSION + 1).to_string();
+        assert!(decode_payload(&payload, json, Some(newer.as_bytes())).is_err());
+        assert!(decode_payload(&payload, json, Some(b"v1")).is_err());
+    }
+}
@@ -1,18 +1,50 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future;
 use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
 use rdkafka::{ClientConfig, Message};
+use tokio::signal;
+use tokio::sync::Semaphore;
+use tokio::time::MissedTickBehavior;
 use tracing::{debug, error, info, warn};
 
-use cache::Cache;
+use cache::{Cache, SeqAllocator};
 use common::config::AppConfig;
+use common::conversation_seq::conversation_key;
+use common::dependency_health::DependencyHealth;
 use common::error::Error;
-use common::message::{GroupMemSeq, Msg, MsgRead, MsgType};
+use common::grpc::tenant::with_tenant;
+use common::message::{ContentType, GroupMemSeq, Msg, MsgRead, MsgType};
 use common::db::DbRepo;
 use common::message_box::MsgRecBoxRepo;
+use common::pseudonym::IdPseudonymizer;
+use common::time_sync::now_millis;
+use common::grpc_client::GroupServiceGrpcClient;
+use common::region::HomeRegions;
+use common::tenant_db::TenantRouted;
 use common::utils;
 
-use crate::pusher::{push_service, Pusher};
+use crate::alerting::{ConsumerAlerts, ErrorCategory};
+use crate::codec;
+use crate::group_media::GroupMediaIndexer;
+use crate::history::PgMessageStore;
+use crate::member_cache::GroupMemberCache;
+use crate::message_request::MessageRequestFilter;
+use crate::ops_metrics::OpsReporter;
+use crate::pusher::{push_service, OfflinePusher, Pusher};
+use crate::recall::RecallHandler;
+use crate::receipts::ReceiptForwarder;
+use crate::signaling::{record_expired, SignalingDeadline};
+use crate::tenant_topics::TenantTopics;
+use crate::usage::UsageMeter;
+
+/// 群聊推送失败成员的最大重试次数
+const GROUP_PUSH_MAX_RETRIES: u32 = 3;
+
+/// 群聊推送首次重试间隔，之后按指数退避
+const GROUP_PUSH_RETRY_INTERVAL: Duration = Duration::from_millis(500);
 
 /// 消息类型的简化枚举
 /// 用于内部区分单聊和群聊消息的处理逻辑
@@ -24,21 +56,62 @@ enum MsgType2 {
     Group,
 }
 
+/// 单个主题的Kafka消费者
+/// 默认主题和每个大租户的独立主题各使用一个消费者，拉取和处理互不阻塞
+struct TopicConsumer {
+    topic: String,
+    consumer: Arc<StreamConsumer>,
+    // 每条消息的最小处理间隔，未配置限速时为空
+    throttle: Option<Duration>,
+}
+
 /// 消息消费者服务
 /// 负责从Kafka消费消息，处理消息，并分发到各个目标
 pub struct ConsumerService {
-    // Kafka消费者实例
-    consumer: StreamConsumer,
-    // 数据库操作封装
-    db: Arc<DbRepo>,
-    // 消息盒子仓库，用于存储离线消息
-    msg_box: Arc<dyn MsgRecBoxRepo>,
+    // 各主题的Kafka消费者实例
+    consumers: Vec<TopicConsumer>,
+    // 数据库操作封装，按消息所属租户路由到各自的数据库集群
+    db: TenantRouted<Arc<DbRepo>>,
+    // Postgres消息历史，按消息所属租户路由
+    history: TenantRouted<PgMessageStore>,
+    // 消息盒子仓库，用于存储离线消息，按消息所属租户路由
+    msg_box: TenantRouted<Arc<dyn MsgRecBoxRepo>>,
+    // 消息盒子ID化名，写入消息盒子前替换用户ID和群组ID
+    pseudonymizer: IdPseudonymizer,
     // 消息推送器，用于将消息推送给客户端
     pusher: Arc<dyn Pusher>,
+    // 接收者不在线时的离线推送，未配置推送网关时为空
+    offline: Option<Arc<OfflinePusher>>,
     // 缓存接口，用于存取高频访问数据
     cache: Arc<dyn Cache>,
+    // Redis健康状态，降级期间跳过依赖Redis的统计和已读同步
+    redis: DependencyHealth,
+    // 序号分配，Redis降级期间由Postgres分配
+    seqs: SeqAllocator,
+    // 进程内的群成员缓存
+    group_members: GroupMemberCache,
     // 序列号步长，用于生成消息序列号
     seq_step: i32,
+    // 群媒体库索引
+    media_indexer: GroupMediaIndexer,
+    // 消息撤回时释放原消息的附件
+    recall: RecallHandler,
+    // 信令消息投递期限
+    signaling: SignalingDeadline,
+    // 用户的归属区域，多区域部署时只为归属本区域的用户分配序号
+    regions: HomeRegions,
+    // 同时进行的推送任务数上限，未配置时不限制
+    push_permits: Option<Arc<Semaphore>>,
+    // 计费用的消息用量
+    usage: UsageMeter,
+    // 非联系人单聊消息的消息请求过滤
+    message_requests: MessageRequestFilter,
+    // 运维看板的消费速率和积压上报
+    ops: OpsReporter,
+    // 解析、存储和推送错误率告警
+    alerts: ConsumerAlerts,
+    // 单聊已读回执的合并转发
+    receipts: ReceiptForwarder,
 }
 
 impl ConsumerService {
@@ -46,7 +119,116 @@ impl ConsumerService {
     /// 初始化Kafka消费者和各种依赖组件
     pub async fn new(config: &AppConfig) -> Self {
         info!("启动Kafka消费者:\t{:?}", config.kafka);
-        // 初始化Kafka消费者
+        // TODO: 向服务注册中心注册服务以监控服务状态
+        // 为默认主题和大租户的独立主题分别创建消费者并订阅
+        let topics = TenantTopics::new(config.kafka.topic.clone(), &config.kafka.tenant_topics);
+        let consumers: Vec<TopicConsumer> = topics
+            .topics()
+            .into_iter()
+            .map(|topic| TopicConsumer {
+                consumer: Arc::new(Self::subscribe(config, &topic)),
+                throttle: topics.throttle(&topic),
+                topic,
+            })
+            .collect();
+
+        // 初始化推送服务
+        let pusher = push_service(config).await;
+        // 初始化数据库仓库，配置了数据驻留的租户写入各自的数据库集群
+        let db = TenantRouted::connect(config, |config| async move {
+            Arc::new(DbRepo::new(&config).await)
+        })
+        .await
+        .expect("数据库仓库初始化失败");
+
+        // 获取序列号步长配置
+        let seq_step = config.redis.seq_step;
+
+        // 初始化缓存和消息盒子仓库
+        let cache = cache::cache(config);
+        let msg_box =
+            TenantRouted::connect(
+                config,
+                |config| async move { msg_rec_box_repo(&config).await },
+            )
+            .await
+            .expect("消息盒子仓库初始化失败");
+        let pseudonymizer = IdPseudonymizer::from_config(&config.database.mongodb.pseudonym)
+            .expect("接收盒ID化名配置错误");
+        let history = TenantRouted::connect(config, |config| async move {
+            PgMessageStore::new(&config).expect("消息历史仓库初始化失败")
+        })
+        .await
+        .expect("消息历史仓库初始化失败");
+
+        // 探测Redis，降级期间序号由Postgres分配、群成员查询群组服务
+        let redis = cache::redis_health(config, cache.clone());
+        let seqs = SeqAllocator::new(config, cache.clone(), redis.clone())
+            .expect("序号分配初始化失败");
+        seqs.start();
+        let group_members =
+            GroupMemberCache::new(&config.group_member_cache, cache.clone(), redis.clone());
+        group_members.start();
+
+        // 离线推送按模板生成文案
+        let offline = OfflinePusher::new(&config.push, cache.clone()).map(Arc::new);
+
+        // 启动消息用量的后台写入任务
+        let usage = UsageMeter::new(&config.usage, cache.clone());
+        usage.start();
+
+        // 启动运维看板消费指标的上报任务
+        let ops = OpsReporter::new(
+            &config.ops_metrics,
+            cache.clone(),
+            consumers.iter().map(|c| c.consumer.clone()).collect(),
+        );
+        ops.start();
+
+        // 启动消费错误率告警任务
+        let alerts = ConsumerAlerts::new(&config.consumer_alert);
+        alerts.start();
+
+        // 启动已读回执的合并转发任务
+        let receipts = ReceiptForwarder::new(&config.receipts, pusher.clone());
+        receipts.start();
+
+        // 群媒体库索引，撤回消息时同样需要移除群媒体
+        let media_indexer = GroupMediaIndexer::new(GroupServiceGrpcClient::from_env());
+        let recall = RecallHandler::new(config, media_indexer.clone()).await;
+
+        Self {
+            consumers,
+            db,
+            history,
+            msg_box,
+            pseudonymizer,
+            pusher,
+            offline,
+            group_members,
+            cache,
+            redis,
+            seqs,
+            seq_step,
+            media_indexer,
+            recall,
+            signaling: SignalingDeadline::new(config.signaling.clone()),
+            regions: HomeRegions::new(config.region.clone()),
+            push_permits: config
+                .runtime
+                .for_service("msg-server")
+                .max_push_tasks
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            usage,
+            message_requests: MessageRequestFilter::new(config).await,
+            ops,
+            alerts,
+            receipts,
+        }
+    }
+
+    /// 创建Kafka消费者并订阅指定主题
+    fn subscribe(config: &AppConfig, topic: &str) -> StreamConsumer {
         let consumer: StreamConsumer = ClientConfig::new()
             .set("group.id", &config.kafka.group)
             .set("bootstrap.servers", config.kafka.hosts.join(","))
@@ -67,64 +249,87 @@ impl ConsumerService {
             .create()
             .expect("消费者创建失败");
 
-        // TODO: 向服务注册中心注册服务以监控服务状态
         // 订阅Kafka主题
         consumer
-            .subscribe(&[&config.kafka.topic])
+            .subscribe(&[topic])
             .expect("无法订阅指定的主题");
+        consumer
+    }
 
-        // 初始化推送服务
-        let pusher = push_service(config).await;
-        // 初始化数据库仓库
-        let db = Arc::new(DbRepo::new(config).await);
-
-        // 获取序列号步长配置
-        let seq_step = config.redis.seq_step;
-
-        // 初始化缓存和消息盒子仓库
-        let cache = cache::cache(config);
-        let msg_box = msg_rec_box_repo(config).await;
-
-        Self {
-            consumer,
-            db,
-            msg_box,
-            pusher,
-            cache,
-            seq_step,
+    /// 启动消息消费循环
+    /// 各主题的消费循环并发执行，单个主题的积压或限速不影响其他主题
+    pub async fn consume(&mut self) -> Result<(), Error> {
+        let loops = self
+            .consumers
+            .iter()
+            .map(|consumer| self.consume_topic(consumer));
+        // 收到关闭信号后停止消费，未提交的消息由Kafka重新投递
+        tokio::select! {
+            _ = future::join_all(loops) => {}
+            _ = shutdown_signal() => info!("接收到关闭信号，停止消费消息"),
         }
+        // 退出前把内存中累加的用量写入Redis
+        self.usage.flush().await;
+        Ok(())
     }
 
-    /// 启动消息消费循环
+    /// 单个主题的消费循环
     /// 不断从Kafka获取消息并处理
-    pub async fn consume(&mut self) -> Result<(), Error> {
+    async fn consume_topic(&self, topic: &TopicConsumer) {
+        info!("开始消费主题: {}", topic.topic);
+        let mut throttle = topic.throttle.map(|period| {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
         loop {
-            match self.consumer.recv().await {
+            // 按租户配置的速度限制消费，超出的消息留在Kafka中
+            if let Some(throttle) = throttle.as_mut() {
+                throttle.tick().await;
+            }
+            match topic.consumer.recv().await {
                 Err(e) => error!("Kafka错误: {}", e),
                 Ok(m) => {
-                    // 尝试获取消息内容并处理
-                    if let Some(Ok(payload)) = m.payload_view::<str>() {
-                        if let Err(e) = self.handle_msg(payload).await {
-                            error!("处理消息失败: {:?}", e);
+                    // 按消息头解码消息内容并处理
+                    let decoded = codec::decode(&m);
+                    self.alerts.record(ErrorCategory::Parse, decoded.is_ok());
+                    let msg = match decoded {
+                        Ok(msg) => msg,
+                        Err(e) => {
+                            error!("解码消息失败: {:?}", e);
                             continue;
                         }
-                        // 异步提交消息偏移量，确认消息已处理
-                        if let Err(e) = self.consumer.commit_message(&m, CommitMode::Async) {
-                            error!("提交消息偏移量失败: {:?}", e);
-                        }
+                    };
+                    // 过期的信令消息直接丢弃，没有期限头的旧消息按消息类型计算期限
+                    let expire_at = codec::expire_at(&m).or_else(|| self.signaling.deadline(&msg));
+                    if expire_at.is_some_and(|expire_at| now_millis() > expire_at) {
+                        record_expired("consumer", &msg);
+                    } else if let Err(e) = self.handle_msg_in_tenant(msg).await {
+                        error!("处理消息失败: {:?}", e);
+                        continue;
+                    }
+                    metrics::counter!("im_kafka_consumed_total", "topic" => topic.topic.clone())
+                        .increment(1);
+                    self.ops.record_consumed();
+                    // 异步提交消息偏移量，确认消息已处理
+                    if let Err(e) = topic.consumer.commit_message(&m, CommitMode::Async) {
+                        error!("提交消息偏移量失败: {:?}", e);
                     }
                 }
             }
         }
     }
 
-    /// 处理单条消息的核心逻辑
-    /// 解析消息内容，根据类型进行不同处理
-    async fn handle_msg(&self, payload: &str) -> Result<(), Error> {
-        debug!("收到消息: {:#?}", payload);
+    /// 在消息所属租户的上下文中处理消息，数据库按该租户路由
+    async fn handle_msg_in_tenant(&self, msg: Msg) -> Result<(), Error> {
+        let tenant_id = Some(msg.tenant_id.clone());
+        with_tenant(tenant_id, self.handle_msg(msg)).await
+    }
 
-        // 将JSON字符串解析为消息对象
-        let mut msg: Msg = serde_json::from_str(payload)?;
+    /// 处理单条消息的核心逻辑
+    /// 根据消息类型进行不同处理
+    async fn handle_msg(&self, mut msg: Msg) -> Result<(), Error> {
+        debug!("收到消息: {:#?}", msg);
 
         // 将整数类型转换为枚举类型，便于处理
         let mt = MsgType::try_from(msg.msg_type).map_err(|e| Error::Internal(e.to_string()))?;
@@ -137,8 +342,44 @@ impl ConsumerService {
         // 根据消息类型进行分类，确定处理策略
         let (msg_type, need_increase_seq, need_history) = self.classify_msg_type(mt).await;
 
-        // 检查发送者序列号，如果需要则增加最大序列号
-        self.handle_send_seq(&msg.send_id).await?;
+        // 多区域部署时单聊消息由接收者的归属区域处理，其他区域只负责复制
+        if msg_type == MsgType2::Single && !self.regions.is_local(&msg.receiver_id) {
+            debug!(
+                "接收者 {} 归属区域 {}，跳过处理",
+                msg.receiver_id,
+                self.regions.home_region(&msg.receiver_id)
+            );
+            return Ok(());
+        }
+
+        // 检查发送者序列号，如果需要则增加最大序列号，发送者的序列号由其归属区域维护
+        if self.regions.is_local(&msg.send_id) {
+            self.handle_send_seq(&msg.send_id).await?;
+        }
+
+        // 非联系人发来的单聊消息进入接收者的消息请求，不分配接收序号、不写入收件箱，
+        // 只通知接收者有新的消息请求，接受后重新投递时再按正常流程处理。
+        // 发送者的已发送消息照常写入消息历史，接收序号在重新投递时补上
+        if self.message_requests.quarantine(&msg).await? {
+            self.history.current().save(&msg).await?;
+            self.notify_message_request(&msg).await;
+            return Ok(());
+        }
+
+        // 撤回消息先释放原消息的附件，原消息不存在或不是撤回者发送的不再投递
+        if RecallHandler::is_recall(&msg) && !self.recall.handle(&msg).await? {
+            return Ok(());
+        }
+
+        // 计量聊天消息用量，多区域部署时单聊由接收者的归属区域计量，群聊由发送者的归属区域计量
+        let billable = match mt {
+            MsgType::SingleMsg => true,
+            MsgType::GroupMsg => self.regions.is_local(&msg.send_id),
+            _ => false,
+        };
+        if billable {
+            self.usage.record(&msg);
+        }
 
         // 处理接收者序列号
         if need_increase_seq {
@@ -147,9 +388,29 @@ impl ConsumerService {
             msg.seq = cur_seq;
         }
 
+        // 为需要保存历史的聊天消息分配会话序号，未启用时为0
+        if need_history {
+            if let Some(conversation) = conversation_key(&msg) {
+                if let Some(conv_seq) = self.seqs.incr_conversation_seq(&conversation).await? {
+                    msg.conv_seq = conv_seq;
+                }
+            }
+        }
+
         // 如果是群聊消息，查询群成员ID并处理群聊序列号
         let members = self.handle_group_seq(&msg_type, &mut msg).await?;
 
+        // 更新群组统计计数器，失败不影响消息投递；统计计数器在Redis中，降级期间不统计
+        if msg_type == MsgType2::Group {
+            if self.redis.is_healthy() {
+                if let Err(e) = self.record_group_stats(mt, &msg).await {
+                    warn!("更新群组统计失败: {:?}", e);
+                }
+            }
+            // 群聊附件写入群媒体库
+            self.media_indexer.index(&msg);
+        }
+
         // 创建任务集合，包含数据库存储和消息推送
         let mut tasks = Vec::with_capacity(2);
         
@@ -159,22 +420,28 @@ impl ConsumerService {
             let cloned_type = msg_type.clone();
             let cloned_members = members.clone();
             
-            // 克隆数据库和消息盒子引用用于异步任务
-            let db = self.db.clone();
-            let msg_box = self.msg_box.clone();
+            // 取出消息所属租户的数据库和消息盒子用于异步任务，异步任务不在租户上下文中
+            let db = self.db.current().clone();
+            let history = self.history.current().clone();
+            let msg_box = self.msg_box.current().clone();
+            let pseudonymizer = self.pseudonymizer.clone();
+            let alerts = self.alerts.clone();
             
             // 创建发送到数据库的异步任务
             let to_db = tokio::spawn(async move {
-                if let Err(e) = Self::send_to_db(
+                let result = Self::send_to_db(
                     db,
+                    history,
                     msg_box,
+                    pseudonymizer,
                     cloned_msg,
                     cloned_type,
                     need_history,
                     cloned_members,
                 )
-                .await
-                {
+                .await;
+                alerts.record(ErrorCategory::Storage, result.is_ok());
+                if let Err(e) = result {
                     error!("发送消息到数据库失败: {:?}", e);
                 }
             });
@@ -182,20 +449,85 @@ impl ConsumerService {
             tasks.push(to_db);
         }
 
-        // 创建发送到推送服务的异步任务
+        // 创建发送到推送服务的异步任务，推送任务达到上限时等待，减缓消费速度
+        let permit = match &self.push_permits {
+            Some(permits) => Some(
+                permits
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))?,
+            ),
+            None => None,
+        };
+        let push_permits = self.push_permits.clone();
         let pusher = self.pusher.clone();
+        let offline = self.offline.clone();
+        let alerts = self.alerts.clone();
         let to_pusher = tokio::spawn(async move {
+            let _permit = permit;
             match msg_type {
                 // 处理单聊消息推送
                 MsgType2::Single => {
-                    if let Err(e) = pusher.push_single_msg(msg).await {
+                    let result = pusher.push_single_msg(msg.clone()).await;
+                    alerts.record(ErrorCategory::Push, result.is_ok());
+                    if let Err(e) = result {
                         error!("发送消息到推送服务失败: {:?}", e);
                     }
+                    // 接收者不在线时发送离线推送
+                    if let Some(offline) = offline {
+                        offline.push_single(&msg).await;
+                    }
                 }
                 // 处理群聊消息推送
                 MsgType2::Group => {
-                    if let Err(e) = pusher.push_group_msg(msg, members).await {
-                        error!("发送消息到推送服务失败: {:?}", e);
+                    let result = pusher.push_group_msg(msg.clone(), members.clone()).await;
+                    // 按成员统计推送结果，部分网关失败时只计入失败的成员，离线成员不计为错误
+                    match &result {
+                        Ok(result) => alerts.record_many(
+                            ErrorCategory::Push,
+                            members.len() as u64,
+                            result.failed.len() as u64,
+                        ),
+                        Err(_) => alerts.record_many(
+                            ErrorCategory::Push,
+                            members.len() as u64,
+                            members.len() as u64,
+                        ),
+                    }
+                    // 离线成员上线后通过收件箱同步，先发送离线推送提醒
+                    if let (Some(offline), Ok(result)) = (&offline, &result) {
+                        offline.push_group(&msg, &result.offline).await;
+                    }
+                    // 推送服务出错时还没有发送到任何网关，重试时重新向所有网关推送
+                    let (failed, gateways) = match result {
+                        Ok(result) => (result.failed, Some(result.failed_gateways)),
+                        Err(e) => {
+                            error!("发送消息到推送服务失败: {:?}", e);
+                            (members, None)
+                        }
+                    };
+                    // 推送失败的成员在后台重试，不阻塞消费；推送任务已达上限时放弃重试，
+                    // 成员上线后通过收件箱同步
+                    if !failed.is_empty() {
+                        let retry_permit = match &push_permits {
+                            Some(permits) => match permits.clone().try_acquire_owned() {
+                                Ok(permit) => Some(permit),
+                                Err(_) => {
+                                    warn!(
+                                        "推送任务已达上限，放弃重试群聊消息 {}，成员数: {}",
+                                        msg.server_id,
+                                        failed.len()
+                                    );
+                                    return;
+                                }
+                            },
+                            None => None,
+                        };
+                        tokio::spawn(async move {
+                            let _permit = retry_permit;
+                            Self::retry_group_push(pusher, msg, failed, gateways).await;
+                        });
                     }
                 }
             }
@@ -210,6 +542,71 @@ impl ConsumerService {
         Ok(())
     }
 
+    /// 通知接收者收到新的消息请求，只推送给在线设备，不写入收件箱
+    async fn notify_message_request(&self, msg: &Msg) {
+        let content = serde_json::json!({
+            "type": "message_request",
+            "sender_id": msg.send_id,
+        });
+        let notification = Msg {
+            receiver_id: msg.receiver_id.clone(),
+            send_time: now_millis(),
+            msg_type: MsgType::Notification as i32,
+            content: content.to_string().into_bytes(),
+            related_msg_id: Some(msg.server_id.clone()),
+            ..Default::default()
+        };
+        if let Err(e) = self.pusher.push_single_msg(notification).await {
+            warn!("推送消息请求通知失败: {:?}", e);
+        }
+    }
+
+    /// 重新推送群聊消息给推送失败的成员
+    ///
+    /// 例如成员所在的网关正在重启，按指数退避重试，仍失败的成员上线后通过收件箱同步。
+    /// 只向推送失败的网关重试，已送达的成员和发送者的其他设备不会重复收到；
+    /// `gateways` 为 `None` 表示还没有推送过，重试时向所有网关推送
+    async fn retry_group_push(
+        pusher: Arc<dyn Pusher>,
+        msg: Msg,
+        mut pending: Vec<GroupMemSeq>,
+        mut gateways: Option<Vec<SocketAddr>>,
+    ) {
+        let mut interval = GROUP_PUSH_RETRY_INTERVAL;
+        for attempt in 1..=GROUP_PUSH_MAX_RETRIES {
+            tokio::time::sleep(interval).await;
+            interval *= 2;
+
+            let count = pending.len();
+            let result = match &gateways {
+                Some(gateways) => {
+                    pusher
+                        .retry_group_msg(msg.clone(), pending.clone(), gateways.clone())
+                        .await
+                }
+                None => pusher.push_group_msg(msg.clone(), pending.clone()).await,
+            };
+            match result {
+                Ok(result) => {
+                    pending = result.failed;
+                    gateways = Some(result.failed_gateways);
+                }
+                Err(e) => warn!("第 {} 次重试推送群聊消息失败: {:?}", attempt, e),
+            }
+            if pending.is_empty() {
+                debug!("群聊消息 {} 重试推送成功，成员数: {}", msg.server_id, count);
+                return;
+            }
+        }
+
+        error!(
+            "群聊消息 {} 重试 {} 次后仍有 {} 个成员推送失败",
+            msg.server_id,
+            GROUP_PUSH_MAX_RETRIES,
+            pending.len()
+        );
+    }
+
     /// 根据消息类型分类，确定处理策略
     /// 返回值: (消息类型, 是否需要增加序列号, 是否需要存储历史记录)
     async fn classify_msg_type(&self, mt: MsgType) -> (MsgType2, bool, bool) {
@@ -261,7 +658,8 @@ impl ConsumerService {
             | MsgType::MsgRecResp
             | MsgType::Notification
             | MsgType::Service
-            | MsgType::FriendshipReceived => {
+            | MsgType::FriendshipReceived
+            | MsgType::Typing => {
                 msg_type = MsgType2::Single;
                 need_history = false;
             }
@@ -273,7 +671,7 @@ impl ConsumerService {
     /// query members id from cache
     /// if not found, query from db
     async fn get_members_id(&self, group_id: &str) -> Result<Vec<String>, Error> {
-        match self.cache.query_group_members_id(group_id).await {
+        match self.group_members.query_group_members_id(group_id).await {
             Ok(list) if !list.is_empty() => Ok(list),
             Ok(_) => {
                 warn!("group members id is empty from cache");
@@ -288,29 +686,125 @@ impl ConsumerService {
     }
 
     async fn handle_send_seq(&self, user_id: &str) -> Result<(), Error> {
+        // 降级期间发送序号由Postgres分配，不需要保存
+        if self.seqs.is_degraded() {
+            return Ok(());
+        }
         let send_seq = self.cache.get_send_seq(user_id).await?;
 
         if send_seq.0 == send_seq.1 - self.seq_step as i64 {
-            self.db.seq.save_max_seq(user_id).await?;
+            self.db.current().seq.save_max_seq(user_id).await?;
         }
         Ok(())
     }
 
     async fn increase_message_seq(&self, user_id: &str) -> Result<i64, Error> {
-        let (cur_seq, _, updated) = self.cache.increase_seq(user_id).await?;
+        let (cur_seq, _, updated) = self.seqs.increase_seq(user_id).await?;
         if updated {
-            self.db.seq.save_max_seq(user_id).await?;
+            self.db.current().seq.save_max_seq(user_id).await?;
         }
         Ok(cur_seq)
     }
 
     async fn handle_msg_read(&self, msg: Msg) -> Result<(), Error> {
         let data: MsgRead = bincode::deserialize(&msg.content)?;
+        // 网关把发送者设为连接的用户，读者与发送者不一致的已读消息不处理
+        if data.user_id != msg.send_id {
+            warn!(
+                "已读消息的读者 {} 与发送者 {} 不一致，已忽略",
+                data.user_id, msg.send_id
+            );
+            return Ok(());
+        }
+        // 已读状态保存在用户归属区域的消息盒子中
+        if !self.regions.is_local(&data.user_id) {
+            return Ok(());
+        }
 
-        self.msg_box.msg_read(&data.user_id, &data.msg_seq).await?;
+        // 批量确认的全部序号在一次更新中标记已读，密钥轮换期间旧密钥化名下的消息也要标记
+        for user_key in self.pseudonymizer.candidates(&data.user_id) {
+            self.msg_box
+                .current()
+                .msg_read(&user_key, &data.msg_seq)
+                .await?;
+        }
+
+        // 单聊的已读回执合并后转发给对方，序号不超过对方当前的发送序号
+        if msg.group_id.is_empty() && !msg.receiver_id.is_empty() {
+            if let Some(read_send_seq) = msg
+                .extensions
+                .get(MsgRead::READ_SEND_SEQ)
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|seq| *seq > 0)
+            {
+                match self.cache.get_send_seq(&msg.receiver_id).await {
+                    Ok((cur_send_seq, _)) if cur_send_seq > 0 => self.receipts.record(
+                        &msg.receiver_id,
+                        &data.user_id,
+                        read_send_seq.min(cur_send_seq),
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!("查询用户 {} 的发送序号失败: {:?}", msg.receiver_id, e),
+                }
+            }
+        }
+
+        // 推进会话已读位置，位置前进时同步给该用户的其他设备清除未读数
+        let conversation_id = if msg.receiver_id.is_empty() {
+            &msg.group_id
+        } else {
+            &msg.receiver_id
+        };
+        let Some(read_seq) = data.msg_seq.iter().copied().max() else {
+            return Ok(());
+        };
+        // 已读位置保存在Redis中，降级期间不同步已读状态
+        if self.redis.is_degraded() {
+            return Ok(());
+        }
+        if conversation_id.is_empty()
+            || !self
+                .cache
+                .advance_read_cursor(&data.user_id, conversation_id, read_seq)
+                .await?
+        {
+            return Ok(());
+        }
+        self.sync_read_state(&data.user_id, conversation_id, read_seq, &msg)
+            .await;
         Ok(())
     }
 
+    /// 把已读位置推送给用户的全部在线设备，客户端按平台忽略自己上报的已读
+    ///
+    /// 不设置发送者，避免网关再转发给发送者的其他平台
+    async fn sync_read_state(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        read_seq: i64,
+        msg: &Msg,
+    ) {
+        let content = serde_json::json!({
+            "type": "read_sync",
+            "conversation_id": conversation_id,
+            "read_seq": read_seq,
+            "platform": msg.platform,
+        });
+        let sync = Msg {
+            receiver_id: user_id.to_string(),
+            send_time: now_millis(),
+            msg_type: MsgType::Read as i32,
+            content_type: ContentType::Text as i32,
+            platform: msg.platform,
+            content: content.to_string().into_bytes(),
+            ..Default::default()
+        };
+        if let Err(e) = self.pusher.push_single_msg(sync).await {
+            warn!("同步已读状态失败: {:?}", e);
+        }
+    }
+
     async fn handle_group_seq(
         &self,
         msg_type: &MsgType2,
@@ -320,20 +814,37 @@ impl ConsumerService {
             return Ok(vec![]);
         }
         // query group members id from the cache
-        let mut members = self.get_members_id(&msg.receiver_id).await?;
+        // 降级期间按消息的租户查询群组服务
+        let mut members = with_tenant(
+            Some(msg.tenant_id.clone()),
+            self.get_members_id(&msg.receiver_id),
+        )
+        .await?;
 
         // retain the members id
-        members.retain(|id| id != &msg.send_id);
+        // 多区域部署时只处理归属本区域的成员，其他成员由其归属区域分配序号和推送
+        members.retain(|id| id != &msg.send_id && self.regions.is_local(id));
 
         // increase the members seq
-        let seq = self.cache.incr_group_seq(members).await?;
+        let seq = self.seqs.incr_group_seq(members).await?;
 
         // we should send the whole list to db module and db module will handle the data
 
         // judge the message type;
         // we should delete the cache data if the type is group dismiss
         // update the cache if the type is group member exit
-        if msg.msg_type == MsgType::GroupDismiss as i32 {
+        // 降级期间只记录成员变化的群，Redis恢复后重新加载
+        let member_changed = msg.msg_type == MsgType::GroupDismiss as i32
+            || msg.msg_type == MsgType::GroupMemberExit as i32
+            || msg.msg_type == MsgType::GroupRemoveMember as i32;
+        if member_changed && self.redis.is_degraded() {
+            let group_id = if msg.msg_type == MsgType::GroupRemoveMember as i32 {
+                &msg.group_id
+            } else {
+                &msg.receiver_id
+            };
+            self.group_members.member_changed(group_id).await;
+        } else if msg.msg_type == MsgType::GroupDismiss as i32 {
             self.cache.del_group_members(&msg.receiver_id).await?;
         } else if msg.msg_type == MsgType::GroupMemberExit as i32 {
             self.cache
@@ -352,6 +863,37 @@ impl ConsumerService {
         Ok(seq)
     }
 
+    /// 根据群消息类型更新Redis中的群组每日统计
+    async fn record_group_stats(&self, mt: MsgType, msg: &Msg) -> Result<(), Error> {
+        let date = chrono::Utc::now().format("%Y%m%d").to_string();
+
+        match mt {
+            MsgType::GroupMsg => {
+                self.cache
+                    .incr_group_msg_stats(&msg.receiver_id, &msg.send_id, &date)
+                    .await
+            }
+            MsgType::GroupInviteNew => {
+                self.cache
+                    .incr_group_churn(&msg.receiver_id, 1, 0, &date)
+                    .await
+            }
+            MsgType::GroupMemberExit => {
+                self.cache
+                    .incr_group_churn(&msg.receiver_id, 0, 1, &date)
+                    .await
+            }
+            MsgType::GroupRemoveMember => {
+                let removed: Vec<String> = bincode::deserialize(&msg.content)
+                    .map_err(|e| Error::Internal(e.to_string()))?;
+                self.cache
+                    .incr_group_churn(&msg.group_id, 0, removed.len() as i64, &date)
+                    .await
+            }
+            _ => Ok(()),
+        }
+    }
+
     /// there is no need to send to db
     /// if the message type is related to call protocol
     #[inline]
@@ -363,12 +905,15 @@ impl ConsumerService {
                 | MsgType::Candidate
                 | MsgType::SingleCallOffer
                 | MsgType::SingleCallInvite
+                | MsgType::Typing
         )
     }
 
     async fn send_to_db(
         db: Arc<DbRepo>,
+        history: PgMessageStore,
         msg_box: Arc<dyn MsgRecBoxRepo>,
+        pseudonymizer: IdPseudonymizer,
         msg: Msg,
         msg_type: MsgType2,
         need_to_history: bool,
@@ -377,10 +922,19 @@ impl ConsumerService {
         // match the message type to procedure the different method
         match msg_type {
             MsgType2::Single => {
-                Self::handle_message(db, msg_box, msg, need_to_history).await?;
+                Self::handle_message(history, msg_box, pseudonymizer, msg, need_to_history).await?;
             }
             MsgType2::Group => {
-                Self::handle_group_message(db, msg_box, msg, need_to_history, members).await?;
+                Self::handle_group_message(
+                    db,
+                    history,
+                    msg_box,
+                    pseudonymizer,
+                    msg,
+                    need_to_history,
+                    members,
+                )
+                .await?;
             }
         }
 
@@ -390,7 +944,12 @@ impl ConsumerService {
     /// query members id from database
     /// and set it to cache
     async fn query_group_members_id_from_db(&self, group_id: &str) -> Result<Vec<String>, Error> {
-        let members_id = self.db.group.query_group_members_id(group_id).await?;
+        let members_id = self
+            .db
+            .current()
+            .group
+            .query_group_members_id(group_id)
+            .await?;
 
         // save it to cache
         if let Err(e) = self
@@ -405,8 +964,9 @@ impl ConsumerService {
     }
 
     async fn handle_message(
-        db: Arc<DbRepo>,
+        history: PgMessageStore,
         msg_box: Arc<dyn MsgRecBoxRepo>,
+        pseudonymizer: IdPseudonymizer,
         message: Msg,
         need_to_history: bool,
     ) -> Result<(), Error> {
@@ -416,15 +976,19 @@ impl ConsumerService {
         if !need_to_history {
             let cloned_msg = message.clone();
             let db_task = tokio::spawn(async move {
-                if let Err(e) = db.msg.save_message(cloned_msg).await {
+                if let Err(e) = history.save(&cloned_msg).await {
                     tracing::error!("save message to db failed: {}", e);
+                    return Err(e);
                 }
+                Ok(())
             });
             tasks.push(db_task);
         }
 
         // task 2 save message to mongodb
         let msg_rec_box_task = tokio::spawn(async move {
+            let mut message = message;
+            pseudonymizer.pseudonymize_msg(&mut message);
             // if the message type is friendship/group-operation delivery, we should delete it from mongodb
             if message.msg_type == MsgType::GroupDismissOrExitReceived as i32
                 || message.msg_type == MsgType::GroupInvitationReceived as i32
@@ -432,25 +996,33 @@ impl ConsumerService {
             {
                 if let Err(e) = msg_box.delete_message(&message.server_id).await {
                     tracing::error!("delete message from mongodb failed: {}", e);
+                    return Err(e);
                 }
-                return;
+                return Ok(());
             }
             if let Err(e) = msg_box.save_message(&message).await {
                 tracing::error!("save message to mongodb failed: {}", e);
+                return Err(e);
             }
+            Ok(())
         });
         tasks.push(msg_rec_box_task);
 
         // wait all tasks
-        futures::future::try_join_all(tasks)
+        let results = futures::future::try_join_all(tasks)
             .await
             .map_err(|e| Error::Internal(e.to_string()))?;
+        for result in results {
+            result?;
+        }
         Ok(())
     }
 
     async fn handle_group_message(
         db: Arc<DbRepo>,
+        history: PgMessageStore,
         msg_box: Arc<dyn MsgRecBoxRepo>,
+        pseudonymizer: IdPseudonymizer,
         message: Msg,
         need_to_history: bool,
         members: Vec<GroupMemSeq>,
@@ -484,7 +1056,7 @@ impl ConsumerService {
             }
 
             if let Some(cloned_msg) = cloned_msg {
-                if let Err(e) = db.msg.save_message(cloned_msg).await {
+                if let Err(e) = history.save(&cloned_msg).await {
                     tracing::error!("save message to db failed: {}", e);
                     return Err(e);
                 }
@@ -494,6 +1066,9 @@ impl ConsumerService {
 
         // task 2 save message to mongodb
         let msg_rec_box_task = tokio::spawn(async move {
+            let (mut message, mut members) = (message, members);
+            pseudonymizer.pseudonymize_msg(&mut message);
+            pseudonymizer.pseudonymize_members(&mut members);
             if let Err(e) = msg_box.save_group_msg(message, members).await {
                 tracing::error!("save message to mongodb failed: {}", e);
                 return Err(e);
@@ -511,3 +1086,26 @@ impl ConsumerService {
         Ok(())
     }
 }
+
+// 等待Ctrl+C或SIGTERM关闭信号
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("无法安装Ctrl+C处理器");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("无法安装SIGTERM处理器")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::UpdateOptions;
+use mongodb::Collection;
+use serde::{Deserialize, Serialize};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, warn};
+
+use cache::Cache;
+use common::config::DraftConfig;
+use common::error::Error;
+use common::grpc::subject::check_subject;
+use common::grpc::tenant::current_tenant;
+use common::message::{ContentType, Msg, MsgType};
+use common::proto::draft::draft_service_server::DraftService;
+use common::proto::draft::{
+    ClearDraftRequest, ClearDraftResponse, Draft, DraftResponse, GetDraftsRequest,
+    GetDraftsResponse, SetDraftRequest,
+};
+use common::tenant_db::MongoRouter;
+use common::time_sync::now_millis;
+
+use crate::pusher::Pusher;
+
+/// 持久化的会话草稿集合名称
+const DRAFT_COLLECTION: &str = "conversation_drafts";
+
+/// 单次持久化的最大用户数
+const FLUSH_BATCH_SIZE: usize = 500;
+
+/// Redis中保存的草稿内容，会话ID是哈希表的字段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredDraft {
+    text: String,
+    updated_at: i64,
+    platform: i32,
+    /// 编辑草稿时所在的租户，后台持久化时据此写入租户的数据库集群
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tenant_id: Option<String>,
+}
+
+impl StoredDraft {
+    fn into_draft(self, conversation_id: String) -> Draft {
+        Draft {
+            conversation_id,
+            text: self.text,
+            updated_at: self.updated_at,
+            platform: self.platform,
+        }
+    }
+}
+
+/// 合并Redis和持久化的草稿，同一会话保留较新的一份，按更新时间从新到旧；
+/// 较新的一份是清除草稿时留下的空草稿时不返回
+fn merge_drafts(cached: Vec<Draft>, persisted: Vec<Draft>) -> Vec<Draft> {
+    let mut merged: HashMap<String, Draft> = HashMap::new();
+    for draft in persisted.into_iter().chain(cached) {
+        match merged.get(&draft.conversation_id) {
+            Some(existing) if existing.updated_at > draft.updated_at => {}
+            _ => {
+                merged.insert(draft.conversation_id.clone(), draft);
+            }
+        }
+    }
+    let mut drafts: Vec<Draft> = merged
+        .into_values()
+        .filter(|draft| !draft.text.is_empty())
+        .collect();
+    drafts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    drafts
+}
+
+/// 按更新时间写入持久化草稿的更新，只有比已保存的一份更新时才覆盖
+///
+/// 清除草稿时写入一份空草稿，后台任务在清除之前读到的旧草稿比它旧，不会再覆盖回去
+fn versioned_update(text: &str, updated_at: i64, platform: i32) -> Vec<Document> {
+    let newer = doc! { "$gt": [updated_at, { "$ifNull": ["$updated_at", 0_i64] }] };
+    vec![doc! { "$set": {
+        "text": { "$cond": [newer.clone(), { "$literal": text }, "$text"] },
+        "platform": { "$cond": [newer.clone(), platform, "$platform"] },
+        "updated_at": { "$cond": [newer, updated_at, "$updated_at"] },
+    }}]
+}
+
+/// 会话草稿的存储
+///
+/// 编辑中的草稿只写Redis，后台任务定期把有变更用户的草稿写入MongoDB；
+/// Redis中的草稿长期不编辑会过期，读取时合并两边的草稿。清除草稿时删除Redis中的草稿，
+/// 并把持久化的一份更新为空草稿，两边都按更新时间只进不退
+#[derive(Clone)]
+pub struct DraftStore {
+    cache: Arc<dyn Cache>,
+    mongo: MongoRouter,
+    config: DraftConfig,
+}
+
+impl DraftStore {
+    pub fn new(mongo: MongoRouter, cache: Arc<dyn Cache>, config: &DraftConfig) -> Self {
+        Self {
+            cache,
+            mongo,
+            config: config.clone(),
+        }
+    }
+
+    /// 租户所在数据库集群的草稿集合
+    fn collection(&self, tenant_id: Option<&str>) -> Collection<Document> {
+        self.mongo
+            .database_for(tenant_id)
+            .collection(DRAFT_COLLECTION)
+    }
+
+    /// 启动后台持久化任务
+    pub fn start(&self) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(store.config.flush_interval_secs.max(1)));
+            loop {
+                interval.tick().await;
+                store.flush().await;
+            }
+        });
+    }
+
+    /// 保存草稿
+    async fn save(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        draft: &StoredDraft,
+    ) -> Result<(), Error> {
+        let value = serde_json::to_string(draft).map_err(|e| Error::Internal(e.to_string()))?;
+        self.cache
+            .save_draft(user_id, conversation_id, &value, self.config.ttl_secs)
+            .await
+    }
+
+    /// 清除草稿，`cleared_at` 之前的草稿不再生效
+    async fn remove(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        cleared_at: i64,
+    ) -> Result<(), Error> {
+        self.cache.del_draft(user_id, conversation_id).await?;
+        self.collection(current_tenant().as_deref())
+            .update_one(
+                doc! { "user_id": user_id, "conversation_id": conversation_id },
+                versioned_update("", cleared_at, 0),
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("删除会话草稿失败: {}", e)))?;
+        Ok(())
+    }
+
+    /// 查询用户的全部草稿
+    async fn load(&self, user_id: &str) -> Result<Vec<Draft>, Error> {
+        let cached = self.cached(user_id).await?;
+
+        let mut cursor = self
+            .collection(current_tenant().as_deref())
+            .find(doc! { "user_id": user_id }, None)
+            .await
+            .map_err(|e| Error::Internal(format!("查询会话草稿失败: {}", e)))?;
+        let mut persisted = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("查询会话草稿失败: {}", e)))?
+        {
+            persisted.push(Draft {
+                conversation_id: doc
+                    .get_str("conversation_id")
+                    .unwrap_or_default()
+                    .to_string(),
+                text: doc.get_str("text").unwrap_or_default().to_string(),
+                updated_at: doc.get_i64("updated_at").unwrap_or_default(),
+                platform: doc.get_i32("platform").unwrap_or_default(),
+            });
+        }
+
+        Ok(merge_drafts(cached, persisted))
+    }
+
+    /// Redis中用户的草稿，无法解析的草稿跳过
+    async fn cached(&self, user_id: &str) -> Result<Vec<Draft>, Error> {
+        Ok(self
+            .cached_entries(user_id)
+            .await?
+            .into_iter()
+            .map(|(conversation_id, draft)| draft.into_draft(conversation_id))
+            .collect())
+    }
+
+    /// Redis中用户的草稿原文，按会话ID
+    async fn cached_entries(&self, user_id: &str) -> Result<Vec<(String, StoredDraft)>, Error> {
+        let entries = self.cache.get_drafts(user_id).await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(conversation_id, value)| {
+                serde_json::from_str::<StoredDraft>(&value)
+                    .map_err(|e| warn!("解析用户 {} 的会话草稿失败: {}", user_id, e))
+                    .ok()
+                    .map(|draft| (conversation_id, draft))
+            })
+            .collect())
+    }
+
+    /// 把有变更用户的草稿写入MongoDB
+    async fn flush(&self) {
+        loop {
+            let users = match self.cache.drain_dirty_drafts(FLUSH_BATCH_SIZE).await {
+                Ok(users) => users,
+                Err(e) => {
+                    error!("读取待持久化草稿的用户失败: {}", e);
+                    return;
+                }
+            };
+            if users.is_empty() {
+                return;
+            }
+
+            for user_id in &users {
+                // 失败的用户下次编辑草稿时重新持久化，期间Redis中的草稿仍然有效
+                if let Err(e) = self.persist(user_id).await {
+                    error!("持久化用户 {} 的会话草稿失败: {}", user_id, e);
+                }
+            }
+            debug!("已持久化 {} 个用户的会话草稿", users.len());
+
+            if users.len() < FLUSH_BATCH_SIZE {
+                return;
+            }
+        }
+    }
+
+    async fn persist(&self, user_id: &str) -> Result<(), Error> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        // 读取之后被清除的草稿比清除时写入的空草稿旧，按更新时间写入不会覆盖清除
+        // 后台任务不在租户上下文中，按编辑草稿时记录的租户写入
+        for (conversation_id, draft) in self.cached_entries(user_id).await? {
+            self.collection(draft.tenant_id.as_deref())
+                .update_one(
+                    doc! { "user_id": user_id, "conversation_id": &conversation_id },
+                    versioned_update(&draft.text, draft.updated_at, draft.platform),
+                    options.clone(),
+                )
+                .await
+                .map_err(|e| Error::Internal(format!("写入会话草稿失败: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// 会话草稿gRPC服务
+pub struct DraftRpcService {
+    store: DraftStore,
+    pusher: Arc<dyn Pusher>,
+    config: DraftConfig,
+}
+
+impl DraftRpcService {
+    pub fn new(store: DraftStore, pusher: Arc<dyn Pusher>, config: &DraftConfig) -> Self {
+        Self {
+            store,
+            pusher,
+            config: config.clone(),
+        }
+    }
+
+    /// 把草稿变更推送给用户的全部在线设备，客户端按平台忽略自己发出的更新
+    async fn notify(&self, user_id: &str, draft: &Draft) {
+        let content = serde_json::json!({
+            "type": "draft",
+            "conversation_id": draft.conversation_id,
+            "text": draft.text,
+            "updated_at": draft.updated_at,
+            "platform": draft.platform,
+        });
+        let msg = Msg {
+            receiver_id: user_id.to_string(),
+            send_time: draft.updated_at,
+            msg_type: MsgType::Service as i32,
+            content_type: ContentType::Text as i32,
+            platform: draft.platform,
+            content: content.to_string().into_bytes(),
+            ..Default::default()
+        };
+        if let Err(e) = self.pusher.push_single_msg(msg).await {
+            warn!("推送会话草稿失败: {:?}", e);
+        }
+    }
+
+    /// 只能操作自己的草稿
+    fn check_ids(
+        metadata: &MetadataMap,
+        user_id: &str,
+        conversation_id: &str,
+    ) -> Result<(), Status> {
+        check_subject(metadata, user_id)?;
+        if user_id.is_empty() || conversation_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID和会话ID不能为空"));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl DraftService for DraftRpcService {
+    async fn set_draft(
+        &self,
+        request: Request<SetDraftRequest>,
+    ) -> Result<Response<DraftResponse>, Status> {
+        Self::check_ids(
+            request.metadata(),
+            &request.get_ref().user_id,
+            &request.get_ref().conversation_id,
+        )?;
+        let req = request.into_inner();
+        if req.text.len() > self.config.max_text_bytes {
+            return Err(Status::invalid_argument(format!(
+                "草稿不能超过 {} 字节",
+                self.config.max_text_bytes
+            )));
+        }
+
+        let updated_at = now_millis();
+        let draft = if req.text.is_empty() {
+            self.store
+                .remove(&req.user_id, &req.conversation_id, updated_at)
+                .await?;
+            Draft {
+                conversation_id: req.conversation_id,
+                updated_at,
+                platform: req.platform,
+                ..Default::default()
+            }
+        } else {
+            let stored = StoredDraft {
+                text: req.text,
+                updated_at,
+                platform: req.platform,
+                tenant_id: current_tenant(),
+            };
+            self.store
+                .save(&req.user_id, &req.conversation_id, &stored)
+                .await?;
+            stored.into_draft(req.conversation_id)
+        };
+
+        self.notify(&req.user_id, &draft).await;
+        Ok(Response::new(DraftResponse { draft: Some(draft) }))
+    }
+
+    async fn get_drafts(
+        &self,
+        request: Request<GetDraftsRequest>,
+    ) -> Result<Response<GetDraftsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID不能为空"));
+        }
+
+        let mut drafts = self.store.load(&req.user_id).await?;
+        if !req.conversation_id.is_empty() {
+            drafts.retain(|draft| draft.conversation_id == req.conversation_id);
+        }
+        Ok(Response::new(GetDraftsResponse { drafts }))
+    }
+
+    async fn clear_draft(
+        &self,
+        request: Request<ClearDraftRequest>,
+    ) -> Result<Response<ClearDraftResponse>, Status> {
+        Self::check_ids(
+            request.metadata(),
+            &request.get_ref().user_id,
+            &request.get_ref().conversation_id,
+        )?;
+        let req = request.into_inner();
+
+        let updated_at = now_millis();
+        self.store
+            .remove(&req.user_id, &req.conversation_id, updated_at)
+            .await?;
+
+        let draft = Draft {
+            conversation_id: req.conversation_id,
+            updated_at,
+            platform: req.platform,
+            ..Default::default()
+        };
+        self.notify(&req.user_id, &draft).await;
+        Ok(Response::new(ClearDraftResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common::grpc::subject::SUBJECT_METADATA_KEY;
+
+    fn draft(conversation_id: &str, text: &str, updated_at: i64) -> Draft {
+        Draft {
+            conversation_id: conversation_id.to_string(),
+            text: text.to_string(),
+            updated_at,
+            platform: 0,
+        }
+    }
+
+    #[test]
+    fn test_merge_drafts_keeps_newer() {
+        let cached = vec![draft("a", "新草稿", 200), draft("b", "旧缓存", 100)];
+        let persisted = vec![
+            draft("a", "旧草稿", 100),
+            draft("b", "新副本", 300),
+            draft("c", "过期", 50),
+        ];
+
+        let merged = merge_drafts(cached, persisted);
+        assert_eq!(
+            merged,
+            vec![
+                draft("b", "新副本", 300),
+                draft("a", "新草稿", 200),
+                draft("c", "过期", 50)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_drafts_skips_cleared() {
+        // 清除之后持久化的空草稿比缓存中残留的旧草稿新
+        let merged = merge_drafts(vec![draft("a", "旧草稿", 100)], vec![draft("a", "", 200)]);
+        assert!(merged.is_empty());
+
+        // 清除之后重新编辑的草稿
+        let merged = merge_drafts(vec![draft("a", "新草稿", 300)], vec![draft("a", "", 200)]);
+        assert_eq!(merged, vec![draft("a", "新草稿", 300)]);
+    }
+
+    #[test]
+    fn test_versioned_update() {
+        let update = versioned_update("$text", 100, 2);
+        let set = update[0].get_document("$set").unwrap();
+        // 草稿内容按字面值写入，不会被当作字段路径
+        let text = set
+            .get_document("text")
+            .unwrap()
+            .get_array("$cond")
+            .unwrap();
+        assert_eq!(
+            text[1].as_document().unwrap().get_str("$literal").unwrap(),
+            "$text"
+        );
+        // 已保存的一份较新时保留原值
+        let updated_at = set
+            .get_document("updated_at")
+            .unwrap()
+            .get_array("$cond")
+            .unwrap();
+        assert_eq!(updated_at[2].as_str().unwrap(), "$updated_at");
+    }
+
+    #[test]
+    fn test_check_ids() {
+        let mut metadata = MetadataMap::new();
+        assert!(DraftRpcService::check_ids(&metadata, "u1", "c1").is_ok());
+        assert_eq!(
+            DraftRpcService::check_ids(&metadata, "u1", "")
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+
+        metadata.insert(SUBJECT_METADATA_KEY, "u1".parse().unwrap());
+        assert_eq!(
+            DraftRpcService::check_ids(&metadata, "u2", "c1")
+                .unwrap_err()
+                .code(),
+            tonic::Code::PermissionDenied
+        );
+    }
+}
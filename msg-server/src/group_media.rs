@@ -0,0 +1,129 @@
+use tracing::{info, warn};
+
+use common::attachment::AttachmentDescriptor;
+use common::grpc_client::GroupServiceGrpcClient;
+use common::message::{ContentType, Msg, MsgType};
+use common::proto::group::GroupMedia;
+
+/// 群媒体库索引
+///
+/// 消费到带附件的群聊消息时写入群组服务的媒体库，原消息撤回或附件被拦截时移除；
+/// 调用在后台进行，失败只记录日志，不影响消息投递
+#[derive(Clone)]
+pub struct GroupMediaIndexer {
+    client: GroupServiceGrpcClient,
+}
+
+impl GroupMediaIndexer {
+    pub fn new(client: GroupServiceGrpcClient) -> Self {
+        Self { client }
+    }
+
+    /// 索引群聊消息中的附件，非群聊附件消息直接忽略
+    pub fn index(&self, msg: &Msg) {
+        let Some(media) = media_from_msg(msg) else {
+            return;
+        };
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.index_group_media(media).await {
+                warn!("索引群媒体失败: {:?}", e);
+            }
+        });
+    }
+
+    /// 从媒体库移除原消息对应的附件
+    pub fn remove(&self, group_id: &str, msg_id: &str) {
+        if group_id.is_empty() || msg_id.is_empty() {
+            return;
+        }
+        let client = self.client.clone();
+        let group_id = group_id.to_string();
+        let msg_id = msg_id.to_string();
+        tokio::spawn(async move {
+            match client.remove_group_media(&group_id, &msg_id).await {
+                Ok(removed) if removed > 0 => {
+                    info!("已从群 {} 媒体库移除消息 {} 的附件", group_id, msg_id)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("移除群媒体失败: {:?}", e),
+            }
+        });
+    }
+}
+
+/// 从群聊附件消息构造媒体库条目，附件描述已在写入Kafka前校验过
+fn media_from_msg(msg: &Msg) -> Option<GroupMedia> {
+    if msg.msg_type != MsgType::GroupMsg as i32 || msg.server_id.is_empty() {
+        return None;
+    }
+    let media_type = match ContentType::try_from(msg.content_type).ok()? {
+        ContentType::Image => "image",
+        ContentType::Video => "video",
+        ContentType::Audio => "audio",
+        ContentType::File => "file",
+        _ => return None,
+    };
+    let attachment: AttachmentDescriptor = serde_json::from_slice(&msg.content).ok()?;
+
+    Some(GroupMedia {
+        group_id: msg.receiver_id.clone(),
+        msg_id: msg.server_id.clone(),
+        sender_id: msg.send_id.clone(),
+        media_type: media_type.to_string(),
+        object_key: attachment.object_key,
+        url: attachment.url,
+        file_name: attachment.file_name,
+        mime_type: attachment.mime_type,
+        size: attachment.size as i64,
+        thumbnail_url: attachment.thumbnail_url,
+        sent_at: Some(prost_types::Timestamp {
+            seconds: msg.send_time.div_euclid(1000),
+            nanos: (msg.send_time.rem_euclid(1000) * 1_000_000) as i32,
+        }),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_from_msg() {
+        let mut msg = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "g1".to_string(),
+            server_id: "m1".to_string(),
+            send_time: 1_700_000_000_123,
+            msg_type: MsgType::GroupMsg as i32,
+            content_type: ContentType::File as i32,
+            content: serde_json::json!({
+                "objectKey": "chat/a.pdf",
+                "url": "https://oss.example.com/chat/a.pdf",
+                "fileName": "a.pdf",
+                "mimeType": "application/pdf",
+                "size": 2048
+            })
+            .to_string()
+            .into_bytes(),
+            ..Default::default()
+        };
+
+        let media = media_from_msg(&msg).unwrap();
+        assert_eq!(media.group_id, "g1");
+        assert_eq!(media.msg_id, "m1");
+        assert_eq!(media.media_type, "file");
+        assert_eq!(media.file_name, "a.pdf");
+        assert_eq!(media.size, 2048);
+        let sent_at = media.sent_at.unwrap();
+        assert_eq!((sent_at.seconds, sent_at.nanos), (1_700_000_000, 123_000_000));
+
+        // 单聊消息和文本消息不入库
+        msg.msg_type = MsgType::SingleMsg as i32;
+        assert!(media_from_msg(&msg).is_none());
+        msg.msg_type = MsgType::GroupMsg as i32;
+        msg.content_type = ContentType::Text as i32;
+        assert!(media_from_msg(&msg).is_none());
+    }
+}
@@ -0,0 +1,405 @@
+//! Postgres中的消息历史
+//!
+//! 单聊消息写入 private_messages，群聊消息写入 group_messages，每条消息一行，按服务端ID去重。
+//! 除了表中原有的列，还保存消息信封中的本地ID、序号、扩展字段等，接收盒重建和消息查询从这里读取，
+//! 读出的消息与写入时一致
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDateTime};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+use common::config::AppConfig;
+use common::error::Error;
+use common::message::{ContentType, Msg, MsgType};
+
+/// 内容类型的最大编号，用于把表中的类型名还原为编号
+const MAX_CONTENT_TYPE: i32 = ContentType::Recall as i32;
+
+/// 单聊消息的查询列
+const PRIVATE_COLUMNS: &str = r#"
+    id AS server_id, local_id, create_time, sent_at, content_type, content, raw_content,
+    sender_id AS send_id, receiver_id, seq, send_seq, conv_seq, msg_type, is_read, '' AS group_id,
+    platform, avatar, nickname, related_msg_id, tenant_id, extensions
+"#;
+
+/// 群聊消息的查询列，接收序号按成员分配，不保存在历史中
+const GROUP_COLUMNS: &str = r#"
+    id AS server_id, local_id, create_time, sent_at, content_type, content, raw_content,
+    sender_id AS send_id, group_id AS receiver_id, 0::BIGINT AS seq, send_seq, conv_seq, msg_type,
+    FALSE AS is_read, group_id, platform, avatar, nickname, related_msg_id, tenant_id, extensions
+"#;
+
+/// 按会话分页时的位置：上一页最后一条消息的发送时间和服务端ID
+pub type PageCursor = (NaiveDateTime, String);
+
+/// 消息历史中的一行
+#[derive(Debug, sqlx::FromRow)]
+struct HistoryRow {
+    server_id: String,
+    local_id: String,
+    create_time: i64,
+    sent_at: NaiveDateTime,
+    content_type: String,
+    content: String,
+    raw_content: Option<Vec<u8>>,
+    send_id: String,
+    receiver_id: String,
+    seq: i64,
+    send_seq: i64,
+    conv_seq: i64,
+    msg_type: i32,
+    is_read: bool,
+    group_id: String,
+    platform: i32,
+    avatar: String,
+    nickname: String,
+    related_msg_id: Option<String>,
+    tenant_id: String,
+    extensions: Json<HashMap<String, String>>,
+}
+
+impl From<HistoryRow> for Msg {
+    fn from(row: HistoryRow) -> Self {
+        Msg {
+            local_id: row.local_id,
+            server_id: row.server_id,
+            create_time: row.create_time,
+            send_time: row.sent_at.and_utc().timestamp_millis(),
+            content_type: content_type_code(&row.content_type),
+            content: row.raw_content.unwrap_or_else(|| row.content.into_bytes()),
+            send_id: row.send_id,
+            receiver_id: row.receiver_id,
+            seq: row.seq,
+            send_seq: row.send_seq,
+            conv_seq: row.conv_seq,
+            msg_type: row.msg_type,
+            is_read: row.is_read,
+            group_id: row.group_id,
+            platform: row.platform,
+            avatar: row.avatar,
+            nickname: row.nickname,
+            related_msg_id: row.related_msg_id,
+            tenant_id: row.tenant_id,
+            extensions: row.extensions.0,
+        }
+    }
+}
+
+/// 表中保存的内容类型名，与原有的 TEXT、IMAGE 等取值一致
+fn content_type_name(content_type: i32) -> String {
+    ContentType::try_from(content_type)
+        .unwrap_or(ContentType::Default)
+        .as_str_name()
+        .to_uppercase()
+}
+
+/// 把表中的内容类型名还原为编号，无法识别时为 Default
+fn content_type_code(name: &str) -> i32 {
+    (0..=MAX_CONTENT_TYPE)
+        .filter_map(|code| ContentType::try_from(code).ok())
+        .find(|content_type| content_type.as_str_name().eq_ignore_ascii_case(name))
+        .unwrap_or(ContentType::Default) as i32
+}
+
+/// 内容列保存文本形式便于检索和统计，不是有效UTF-8的内容另存原始字节
+fn split_content(content: &[u8]) -> (String, Option<Vec<u8>>) {
+    match std::str::from_utf8(content) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (
+            String::from_utf8_lossy(content).into_owned(),
+            Some(content.to_vec()),
+        ),
+    }
+}
+
+/// Postgres中的消息历史
+#[derive(Debug, Clone)]
+pub struct PgMessageStore {
+    pool: PgPool,
+}
+
+impl PgMessageStore {
+    /// 连接配置中的Postgres，连接在首次使用时建立；多集群部署时通过
+    /// [`common::tenant_db::TenantRouted`] 为每个集群各创建一个实例
+    pub fn new(config: &AppConfig) -> Result<Self, Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_lazy(&config.database.url())?;
+        Ok(Self { pool })
+    }
+
+    /// 保存一条消息，单聊消息写入 private_messages，群聊消息写入 group_messages，
+    /// 重复消费的消息不会重复写入
+    ///
+    /// 进入消息请求的单聊消息先以接收序号0写入，接受后重新投递时补上接收序号和会话序号
+    pub async fn save(&self, msg: &Msg) -> Result<(), Error> {
+        let (content, raw_content) = split_content(&msg.content);
+        let sent_at = DateTime::from_timestamp_millis(msg.send_time)
+            .unwrap_or_default()
+            .naive_utc();
+
+        if msg.msg_type == MsgType::GroupMsg as i32 {
+            let group_id = if msg.group_id.is_empty() {
+                &msg.receiver_id
+            } else {
+                &msg.group_id
+            };
+            sqlx::query(
+                r#"
+                INSERT INTO group_messages (id, group_id, sender_id, content, raw_content, content_type,
+                    sent_at, local_id, create_time, send_seq, conv_seq, msg_type, platform, avatar,
+                    nickname, related_msg_id, tenant_id, extensions)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+                ON CONFLICT (id) DO NOTHING
+                "#,
+            )
+            .bind(&msg.server_id)
+            .bind(group_id)
+            .bind(&msg.send_id)
+            .bind(content)
+            .bind(raw_content)
+            .bind(content_type_name(msg.content_type))
+            .bind(sent_at)
+            .bind(&msg.local_id)
+            .bind(msg.create_time)
+            .bind(msg.send_seq)
+            .bind(msg.conv_seq)
+            .bind(msg.msg_type)
+            .bind(msg.platform)
+            .bind(&msg.avatar)
+            .bind(&msg.nickname)
+            .bind(&msg.related_msg_id)
+            .bind(&msg.tenant_id)
+            .bind(Json(&msg.extensions))
+            .execute(&self.pool)
+            .await?;
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO private_messages (id, sender_id, receiver_id, content, raw_content, content_type,
+                    sent_at, is_read, local_id, create_time, seq, send_seq, conv_seq, msg_type, platform,
+                    avatar, nickname, related_msg_id, tenant_id, extensions)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+                ON CONFLICT (id) DO UPDATE SET seq = EXCLUDED.seq, conv_seq = EXCLUDED.conv_seq
+                WHERE private_messages.seq = 0 AND EXCLUDED.seq > 0
+                "#,
+            )
+            .bind(&msg.server_id)
+            .bind(&msg.send_id)
+            .bind(&msg.receiver_id)
+            .bind(content)
+            .bind(raw_content)
+            .bind(content_type_name(msg.content_type))
+            .bind(sent_at)
+            .bind(msg.is_read)
+            .bind(&msg.local_id)
+            .bind(msg.create_time)
+            .bind(msg.seq)
+            .bind(msg.send_seq)
+            .bind(msg.conv_seq)
+            .bind(msg.msg_type)
+            .bind(msg.platform)
+            .bind(&msg.avatar)
+            .bind(&msg.nickname)
+            .bind(&msg.related_msg_id)
+            .bind(&msg.tenant_id)
+            .bind(Json(&msg.extensions))
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// 用户收到的接收序号大于 `after_seq` 的一批单聊消息，按序号从小到大
+    pub async fn received(
+        &self,
+        user_id: &str,
+        after_seq: i64,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error> {
+        let rows = sqlx::query_as::<_, HistoryRow>(&format!(
+            r#"
+            SELECT {PRIVATE_COLUMNS} FROM private_messages
+            WHERE receiver_id = $1 AND seq > $2 AND NOT is_deleted
+            ORDER BY seq
+            LIMIT $3
+            "#
+        ))
+        .bind(user_id)
+        .bind(after_seq)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Msg::from).collect())
+    }
+
+    /// 用户收到的接收序号在 `[start_seq, end_seq]` 内的单聊消息，按序号从小到大，`end_seq` 为0时不限
+    ///
+    /// 进入消息请求、尚未分配接收序号的消息不返回
+    pub async fn received_range(
+        &self,
+        user_id: &str,
+        start_seq: i64,
+        end_seq: i64,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error> {
+        let rows = sqlx::query_as::<_, HistoryRow>(&format!(
+            r#"
+            SELECT {PRIVATE_COLUMNS} FROM private_messages
+            WHERE receiver_id = $1 AND seq >= $2 AND ($3 = 0 OR seq <= $3) AND NOT is_deleted
+            ORDER BY seq
+            LIMIT $4
+            "#
+        ))
+        .bind(user_id)
+        .bind(start_seq.max(1))
+        .bind(end_seq)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Msg::from).collect())
+    }
+
+    /// 两个用户之间的单聊消息，按发送时间从新到旧，从 `before` 之后的一条开始
+    pub async fn private_conversation(
+        &self,
+        user_id: &str,
+        peer_id: &str,
+        before: Option<PageCursor>,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error> {
+        let (before_time, before_id) = before.unzip();
+        let rows = sqlx::query_as::<_, HistoryRow>(&format!(
+            r#"
+            SELECT {PRIVATE_COLUMNS} FROM private_messages
+            WHERE ((sender_id = $1 AND receiver_id = $2) OR (sender_id = $2 AND receiver_id = $1))
+                AND NOT is_deleted
+                AND ($3::TIMESTAMP IS NULL OR (sent_at, id) < ($3, $4))
+            ORDER BY sent_at DESC, id DESC
+            LIMIT $5
+            "#
+        ))
+        .bind(user_id)
+        .bind(peer_id)
+        .bind(before_time)
+        .bind(before_id.unwrap_or_default())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Msg::from).collect())
+    }
+
+    /// 群聊消息，按发送时间从新到旧，从 `before` 之后的一条开始
+    pub async fn group_conversation(
+        &self,
+        group_id: &str,
+        before: Option<PageCursor>,
+        limit: i64,
+    ) -> Result<Vec<Msg>, Error> {
+        let (before_time, before_id) = before.unzip();
+        let rows = sqlx::query_as::<_, HistoryRow>(&format!(
+            r#"
+            SELECT {GROUP_COLUMNS} FROM group_messages
+            WHERE group_id = $1 AND NOT is_deleted
+                AND ($2::TIMESTAMP IS NULL OR (sent_at, id) < ($2, $3))
+            ORDER BY sent_at DESC, id DESC
+            LIMIT $4
+            "#
+        ))
+        .bind(group_id)
+        .bind(before_time)
+        .bind(before_id.unwrap_or_default())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Msg::from).collect())
+    }
+
+    /// 删除一条消息，单聊消息限发送者或接收者，群聊消息限发送者，返回是否删除
+    pub async fn delete(
+        &self,
+        user_id: &str,
+        server_id: &str,
+        is_group: bool,
+    ) -> Result<bool, Error> {
+        let sql = if is_group {
+            r#"
+            UPDATE group_messages SET is_deleted = TRUE
+            WHERE id = $1 AND sender_id = $2 AND NOT is_deleted
+            "#
+        } else {
+            r#"
+            UPDATE private_messages SET is_deleted = TRUE
+            WHERE id = $1 AND (sender_id = $2 OR receiver_id = $2) AND NOT is_deleted
+            "#
+        };
+        let result = sqlx::query(sql)
+            .bind(server_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 将用户收到的对方的单聊消息标记为已读，`server_ids` 为空时标记全部，返回标记的条数
+    pub async fn mark_read(
+        &self,
+        user_id: &str,
+        peer_id: &str,
+        server_ids: &[String],
+    ) -> Result<u64, Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE private_messages SET is_read = TRUE
+            WHERE receiver_id = $1 AND sender_id = $2 AND NOT is_read
+                AND (cardinality($3::VARCHAR[]) = 0 OR id = ANY($3))
+            "#,
+        )
+        .bind(user_id)
+        .bind(peer_id)
+        .bind(server_ids)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// 用户收到的单聊消息中最大的接收序号，没有消息时返回0
+    pub async fn max_received_seq(&self, user_id: &str) -> Result<i64, Error> {
+        let seq: Option<i64> =
+            sqlx::query_scalar("SELECT MAX(seq) FROM private_messages WHERE receiver_id = $1")
+                .bind(user_id)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(seq.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type_round_trip() {
+        for code in 0..=MAX_CONTENT_TYPE {
+            assert_eq!(content_type_code(&content_type_name(code)), code);
+        }
+        // 原有数据中的类型名
+        assert_eq!(content_type_code("IMAGE"), ContentType::Image as i32);
+        assert_eq!(content_type_code("unknown"), ContentType::Default as i32);
+        assert_eq!(content_type_name(ContentType::Text as i32), "TEXT");
+    }
+
+    #[test]
+    fn test_split_content() {
+        let (text, raw) = split_content("你好".as_bytes());
+        assert_eq!(text, "你好");
+        assert!(raw.is_none());
+
+        let bytes = vec![0xff, 0x00, 0x61];
+        let (_, raw) = split_content(&bytes);
+        assert_eq!(raw, Some(bytes));
+    }
+}
@@ -1,10 +1,39 @@
 use common::config::AppConfig;
 use consumer::ConsumerService;
+use msg_store::DbRpcService;
 use productor::ChatRpcService;
+use replication::ReplicationService;
+use thumbnail::ThumbnailService;
 
+pub mod alerting;
+pub mod backfill;
+pub mod codec;
 pub mod consumer;
+pub mod draft;
+pub mod group_media;
+pub mod history;
+pub mod link_preview;
+pub mod member_cache;
+#[cfg(any(test, feature = "test-util"))]
+pub mod message_box_mock;
+pub mod message_request;
+pub mod msg_store;
+pub mod notify;
+pub mod ops_metrics;
 pub mod productor;
-mod pusher;
+pub mod pusher;
+pub mod receipts;
+pub mod rebuild;
+pub mod recall;
+pub mod replication;
+pub mod scanner;
+pub mod signaling;
+pub mod spill;
+pub mod tenant_topics;
+pub mod thumbnail;
+pub mod transaction;
+pub mod usage;
+pub mod validation;
 
 pub async fn start(config: &AppConfig) {
     let cloned_conf = config.clone();
@@ -12,6 +41,11 @@ pub async fn start(config: &AppConfig) {
         ChatRpcService::start(&cloned_conf).await;
     });
 
+    let cloned_conf = config.clone();
+    let db = tokio::spawn(async move {
+        DbRpcService::start(&cloned_conf).await;
+    });
+
     let cloned_conf = config.clone();
     let con = tokio::spawn(async move {
         ConsumerService::new(&cloned_conf)
@@ -21,5 +55,21 @@ pub async fn start(config: &AppConfig) {
             .unwrap();
     });
 
-    tokio::try_join!(pro, con).unwrap();
+    let cloned_conf = config.clone();
+    let thumb = tokio::spawn(async move {
+        ThumbnailService::new(&cloned_conf)
+            .await
+            .consume()
+            .await
+            .unwrap();
+    });
+
+    // 多区域部署时从对端区域复制消息
+    if config.region.enabled {
+        for peer in config.region.peers.clone() {
+            tokio::spawn(ReplicationService::new(config, peer).run());
+        }
+    }
+
+    tokio::try_join!(pro, db, con, thumb).unwrap();
 }
@@ -0,0 +1,430 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::{mpsc, Semaphore};
+use tracing::{debug, error, info, warn};
+
+use cache::Cache;
+use common::error::Error;
+use common::message::Msg;
+
+use crate::notify::ConversationNotifier;
+
+/// 待处理队列长度，队列满时跳过预览而不阻塞发消息
+const PREVIEW_QUEUE_SIZE: usize = 1024;
+
+/// 同时进行的抓取数量
+const MAX_CONCURRENT_FETCHES: usize = 16;
+
+/// 单次抓取超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 最多跟随的重定向次数，每一跳都重新做地址校验
+const MAX_REDIRECTS: usize = 3;
+
+/// 最多读取的页面字节数，OpenGraph标签都在head中
+const MAX_BODY_BYTES: usize = 512 * 1024;
+
+/// 预览缓存时间（秒）
+const PREVIEW_TTL: i64 = 24 * 3600;
+
+/// 无可用预览的链接缓存时间（秒）
+const EMPTY_PREVIEW_TTL: i64 = 3600;
+
+const MAX_TITLE_CHARS: usize = 200;
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// 链接预览
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub site_name: String,
+}
+
+/// 链接预览任务
+#[derive(Debug)]
+pub struct LinkPreviewTask {
+    pub msg: Msg,
+    pub url: String,
+}
+
+/// 链接预览后台任务
+///
+/// 文本消息投递后，抓取其中第一个链接的OpenGraph信息，按URL缓存，
+/// 并以通知的形式推送给会话参与者，客户端据此展示链接卡片。
+pub struct LinkPreviewWorker {
+    rx: mpsc::Receiver<LinkPreviewTask>,
+    cache: Arc<dyn Cache>,
+    notifier: ConversationNotifier,
+}
+
+impl LinkPreviewWorker {
+    /// 创建链接预览任务，返回投递任务的发送端
+    pub fn new(
+        cache: Arc<dyn Cache>,
+        notifier: ConversationNotifier,
+    ) -> (Self, mpsc::Sender<LinkPreviewTask>) {
+        let (tx, rx) = mpsc::channel(PREVIEW_QUEUE_SIZE);
+        (
+            Self {
+                rx,
+                cache,
+                notifier,
+            },
+            tx,
+        )
+    }
+
+    pub fn start(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("链接预览任务已启动");
+            let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_FETCHES));
+            while let Some(task) = self.rx.recv().await {
+                let permit = match semaphore.clone().acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                };
+                let cache = self.cache.clone();
+                let notifier = self.notifier.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = Self::handle(cache, notifier, task).await {
+                        error!("生成链接预览失败: {:?}", e);
+                    }
+                    drop(permit);
+                });
+            }
+        })
+    }
+
+    async fn handle(
+        cache: Arc<dyn Cache>,
+        notifier: ConversationNotifier,
+        task: LinkPreviewTask,
+    ) -> Result<(), Error> {
+        let preview = match cache.get_link_preview(&task.url).await? {
+            Some(cached) if cached.is_empty() => return Ok(()),
+            Some(cached) => serde_json::from_str::<LinkPreview>(&cached)?,
+            None => match fetch_preview(&task.url).await {
+                Ok(Some(preview)) => {
+                    let value = serde_json::to_string(&preview)?;
+                    cache.save_link_preview(&task.url, &value, PREVIEW_TTL).await?;
+                    preview
+                }
+                Ok(None) => {
+                    cache.save_link_preview(&task.url, "", EMPTY_PREVIEW_TTL).await?;
+                    return Ok(());
+                }
+                Err(e) => {
+                    debug!("抓取链接 {} 失败: {:?}", task.url, e);
+                    cache.save_link_preview(&task.url, "", EMPTY_PREVIEW_TTL).await?;
+                    return Ok(());
+                }
+            },
+        };
+
+        let content = json!({
+            "type": "link_preview",
+            "serverId": task.msg.server_id,
+            "groupId": task.msg.group_id,
+            "preview": preview,
+        })
+        .to_string()
+        .into_bytes();
+        notifier.notify(&task.msg, content).await
+    }
+}
+
+/// 提取文本中的第一个 http/https 链接
+pub fn extract_url(text: &str) -> Option<String> {
+    let start = match (text.find("http://"), text.find("https://")) {
+        (Some(a), Some(b)) => a.min(b),
+        (Some(a), None) | (None, Some(a)) => a,
+        (None, None) => return None,
+    };
+
+    let rest = &text[start..];
+    let end = rest
+        .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '，' | '。' | '）' | '、'))
+        .unwrap_or(rest.len());
+    let url = rest[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'']);
+
+    Url::parse(url).ok().map(|_| url.to_string())
+}
+
+/// 抓取链接的OpenGraph信息
+///
+/// 只允许 http/https，解析出的地址必须是公网地址，且请求固定使用校验过的地址，
+/// 避免DNS重绑定；重定向手动跟随，每一跳重新校验。
+async fn fetch_preview(url: &str) -> Result<Option<LinkPreview>, Error> {
+    let mut current = Url::parse(url).map_err(|e| Error::BadRequest(e.to_string()))?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let (host, addr) = resolve_public(&current).await?;
+        let client = reqwest::Client::builder()
+            .redirect(Policy::none())
+            .timeout(FETCH_TIMEOUT)
+            .resolve(&host, addr)
+            .build()
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        let mut resp = client
+            .get(current.clone())
+            .header(reqwest::header::ACCEPT, "text/html")
+            .send()
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?;
+
+        if resp.status().is_redirection() {
+            let location = resp
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| Error::Internal("重定向缺少Location".to_string()))?;
+            current = current
+                .join(location)
+                .map_err(|e| Error::Internal(e.to_string()))?;
+            continue;
+        }
+
+        if !resp.status().is_success() {
+            return Ok(None);
+        }
+        let is_html = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.to_ascii_lowercase().contains("text/html"));
+        if !is_html {
+            return Ok(None);
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| Error::Internal(e.to_string()))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_BYTES {
+                body.truncate(MAX_BODY_BYTES);
+                break;
+            }
+        }
+
+        let html = String::from_utf8_lossy(&body);
+        return Ok(parse_open_graph(url, &html));
+    }
+
+    warn!("链接 {} 重定向次数过多", url);
+    Ok(None)
+}
+
+/// 解析主机地址，所有解析结果都必须是公网地址
+async fn resolve_public(url: &Url) -> Result<(String, SocketAddr), Error> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Error::BadRequest(format!("不支持的协议: {}", url.scheme())));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::BadRequest("链接缺少主机名".to_string()))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|e| Error::Internal(format!("解析主机 {} 失败: {}", host, e)))?
+        .collect();
+
+    if addrs.is_empty() || addrs.iter().any(|addr| is_forbidden_ip(addr.ip())) {
+        return Err(Error::BadRequest(format!("不允许访问的地址: {}", host)));
+    }
+    Ok((host, addrs[0]))
+}
+
+/// 内网、回环、链路本地等非公网地址
+fn is_forbidden_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                // 0.0.0.0/8 与运营商级NAT 100.64.0.0/10
+                || octets[0] == 0
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_forbidden_ip(IpAddr::V4(v4));
+            }
+            let segments = v6.segments();
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // 唯一本地地址 fc00::/7 与链路本地地址 fe80::/10
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// 从HTML中提取OpenGraph信息，缺少og:title时回退到 `<title>`
+fn parse_open_graph(url: &str, html: &str) -> Option<LinkPreview> {
+    // ASCII小写不改变字节长度，下标可以直接用于原文
+    let lower = html.to_ascii_lowercase();
+    let mut preview = LinkPreview {
+        url: url.to_string(),
+        ..Default::default()
+    };
+    let mut fallback_description = String::new();
+
+    let mut offset = 0;
+    while let Some(pos) = lower[offset..].find("<meta") {
+        let start = offset + pos;
+        let end = match lower[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = &html[start..end];
+        offset = end;
+
+        let key = attr(tag, "property").or_else(|| attr(tag, "name"));
+        let content = match attr(tag, "content") {
+            Some(content) => decode_entities(&content),
+            None => continue,
+        };
+        match key.map(|k| k.to_ascii_lowercase()).as_deref() {
+            Some("og:title") => preview.title = content,
+            Some("og:description") => preview.description = content,
+            Some("og:image") => preview.image = content,
+            Some("og:site_name") => preview.site_name = content,
+            Some("description") => fallback_description = content,
+            _ => {}
+        }
+    }
+
+    if preview.title.is_empty() {
+        if let Some(start) = lower.find("<title") {
+            let text_start = lower[start..].find('>').map(|p| start + p + 1);
+            let text_end = lower.find("</title>");
+            if let (Some(s), Some(e)) = (text_start, text_end) {
+                if s < e {
+                    preview.title = decode_entities(html[s..e].trim());
+                }
+            }
+        }
+    }
+    if preview.description.is_empty() {
+        preview.description = fallback_description;
+    }
+    if preview.title.is_empty() {
+        return None;
+    }
+
+    preview.title = truncate(&preview.title, MAX_TITLE_CHARS);
+    preview.description = truncate(&preview.description, MAX_DESCRIPTION_CHARS);
+    Some(preview)
+}
+
+/// 读取标签属性值，支持单引号和双引号
+fn attr(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let mut offset = 0;
+    while let Some(pos) = lower[offset..].find(name) {
+        let start = offset + pos;
+        offset = start + name.len();
+        // 属性名前必须是空白，避免 og:name 之类的误匹配
+        if !lower[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let rest = lower[offset..].trim_start();
+        if !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - rest[1..].trim_start().len();
+        let quote = tag[value_start..].chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let value = &tag[value_start + 1..];
+        return value.find(quote).map(|end| value[..end].to_string());
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    text.chars().take(max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_url() {
+        assert_eq!(
+            extract_url("看看这个 https://example.com/a?b=1, 很有意思"),
+            Some("https://example.com/a?b=1".to_string())
+        );
+        assert_eq!(
+            extract_url("链接：http://example.com/path。"),
+            Some("http://example.com/path".to_string())
+        );
+        assert_eq!(extract_url("没有链接"), None);
+    }
+
+    #[test]
+    fn test_parse_open_graph() {
+        let html = r#"<html><head><title>Fallback</title>
+            <meta property="og:title" content="Rust &amp; IM">
+            <meta name='description' content='desc'>
+            <meta property="og:image" content="https://example.com/a.png" />
+            </head></html>"#;
+        let preview = parse_open_graph("https://example.com", html).unwrap();
+        assert_eq!(preview.title, "Rust & IM");
+        assert_eq!(preview.description, "desc");
+        assert_eq!(preview.image, "https://example.com/a.png");
+
+        assert!(parse_open_graph("https://example.com", "<p>no title</p>").is_none());
+    }
+
+    #[test]
+    fn test_forbidden_ip() {
+        assert!(is_forbidden_ip("127.0.0.1".parse().unwrap()));
+        assert!(is_forbidden_ip("10.1.2.3".parse().unwrap()));
+        assert!(is_forbidden_ip("169.254.169.254".parse().unwrap()));
+        assert!(is_forbidden_ip("::ffff:192.168.1.1".parse().unwrap()));
+        assert!(is_forbidden_ip("fd00::1".parse().unwrap()));
+        assert!(!is_forbidden_ip("93.184.216.34".parse().unwrap()));
+    }
+}
@@ -2,14 +2,20 @@ use tracing::info;
 
 use common::config::AppConfig;
 
+use msg_server::msg_store::DbRpcService;
 use msg_server::productor::ChatRpcService;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
     // 加载配置文件
     // 从指定路径读取系统配置，如果失败则panic
     let config = AppConfig::from_file(Some("./config/config.yaml")).unwrap();
-    
+
+    // 按配置的线程数创建运行时
+    common::runtime::build("msg-server", &config.runtime.for_service("msg-server"))?
+        .block_on(run(config))
+}
+
+async fn run(config: AppConfig) -> anyhow::Result<()> {
     // 初始化日志和链路追踪系统
     // 根据配置判断是否启用分布式链路追踪
     if config.telemetry.enabled {
@@ -24,11 +30,19 @@ async fn main() -> anyhow::Result<()> {
     }
     
     info!("正在启动消息服务...");
+
+    // 首次启用Redis键前缀时，在分配序号之前把旧键迁移到新的命名空间，
+    // 迁移失败时不启动，避免序号从0重新分配
+    if config.redis.migrate_legacy_keys {
+        let migrated = cache::migrate_key_prefix(&config).await?;
+        info!("已迁移 {} 个Redis旧键", migrated);
+    }
     
     // 启动消息RPC服务
     // 这是消息服务的核心组件，负责接收客户端消息并处理
     // 包括消息生产者功能、消息存储和转发等
-    ChatRpcService::start(&config).await;
+    // 同时启动消息存储RPC服务，供其他服务查询和修改消息历史
+    tokio::join!(ChatRpcService::start(&config), DbRpcService::start(&config));
     
     // 在程序结束前关闭链路追踪，确保所有追踪数据都被发送
     // 这是一个优雅关闭的步骤，防止数据丢失
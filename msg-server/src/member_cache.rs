@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use moka::future::Cache as MokaCache;
+use tracing::{error, info};
+
+use cache::Cache;
+use common::config::GroupMemberCacheConfig;
+use common::dependency_health::DependencyHealth;
+use common::error::Error;
+use common::grpc_client::GroupServiceGrpcClient;
+
+/// Redis降级期间从群组服务加载的成员使用的版本号，Redis恢复后与任何版本号都不相等，会重新加载
+const DEGRADED_VERSION: i64 = -1;
+
+/// 缓存的群成员
+#[derive(Clone)]
+struct CachedMembers {
+    // 加载成员时Redis中的版本号
+    version: i64,
+    members: Arc<Vec<String>>,
+    // 最近一次确认版本号的时间
+    checked_at: Instant,
+}
+
+/// 进程内的群成员缓存
+///
+/// 位于Redis的群成员集合之前，有效期内直接返回缓存的成员，超过有效期后先读取成员版本号，
+/// 版本号未变化时继续使用缓存，变化时重新加载；版本号在加载成员之前读取，
+/// 加载期间成员发生变化时下次检查会重新加载。成员为空的群不缓存，由调用方回源数据库。
+///
+/// Redis降级期间改为查询群组服务，成员变化的群记录下来，Redis恢复后删除这些群在Redis中的成员，
+/// 下次查询时从数据库重新加载
+#[derive(Clone)]
+pub struct GroupMemberCache {
+    cache: Arc<dyn Cache>,
+    entries: Option<MokaCache<String, CachedMembers>>,
+    fresh: Duration,
+    redis: DependencyHealth,
+    groups: GroupServiceGrpcClient,
+    // 降级期间成员发生变化的群
+    stale: Arc<Mutex<HashSet<String>>>,
+}
+
+impl GroupMemberCache {
+    pub fn new(
+        config: &GroupMemberCacheConfig,
+        cache: Arc<dyn Cache>,
+        redis: DependencyHealth,
+    ) -> Self {
+        let entries = config.enabled.then(|| {
+            MokaCache::builder()
+                .max_capacity(config.max_groups)
+                .time_to_live(Duration::from_secs(config.ttl_secs))
+                .build()
+        });
+        Self {
+            cache,
+            entries,
+            fresh: Duration::from_millis(config.fresh_ms),
+            redis,
+            groups: GroupServiceGrpcClient::from_env(),
+            stale: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// 启动后台任务，Redis恢复后清除降级期间成员发生变化的群
+    pub fn start(&self) -> tokio::task::JoinHandle<()> {
+        let members = self.clone();
+        tokio::spawn(async move {
+            let mut state = members.redis.subscribe();
+            while state.changed().await.is_ok() {
+                if *state.borrow_and_update() {
+                    members.clear_stale().await;
+                }
+            }
+        })
+    }
+
+    /// 群成员发生变化
+    ///
+    /// Redis可用时由调用方直接更新Redis；降级期间只失效进程内的缓存，记录下来等Redis恢复后处理
+    pub async fn member_changed(&self, group_id: &str) {
+        if let Some(entries) = &self.entries {
+            entries.invalidate(group_id).await;
+        }
+        self.stale.lock().unwrap().insert(group_id.to_string());
+    }
+
+    /// 查询群成员ID
+    pub async fn query_group_members_id(&self, group_id: &str) -> Result<Vec<String>, Error> {
+        if self.redis.is_degraded() {
+            return self.query_degraded(group_id).await;
+        }
+
+        let Some(entries) = &self.entries else {
+            return self.cache.query_group_members_id(group_id).await;
+        };
+
+        let cached = entries.get(group_id).await;
+        if let Some(cached) = &cached {
+            if cached.checked_at.elapsed() < self.fresh {
+                record("hit");
+                return Ok(cached.members.as_ref().clone());
+            }
+        }
+
+        let version = self.cache.group_members_version(group_id).await?;
+        if let Some(cached) = cached.filter(|cached| cached.version == version) {
+            record("revalidated");
+            let members = cached.members.as_ref().clone();
+            entries
+                .insert(
+                    group_id.to_string(),
+                    CachedMembers {
+                        checked_at: Instant::now(),
+                        ..cached
+                    },
+                )
+                .await;
+            return Ok(members);
+        }
+
+        record("miss");
+        let members = self.cache.query_group_members_id(group_id).await?;
+        if members.is_empty() {
+            entries.invalidate(group_id).await;
+        } else {
+            entries
+                .insert(
+                    group_id.to_string(),
+                    CachedMembers {
+                        version,
+                        members: Arc::new(members.clone()),
+                        checked_at: Instant::now(),
+                    },
+                )
+                .await;
+        }
+        metrics::gauge!("im_group_member_cache_entries").set(entries.entry_count() as f64);
+        Ok(members)
+    }
+
+    /// Redis降级期间的查询，有效期内使用进程内的缓存，否则查询群组服务
+    async fn query_degraded(&self, group_id: &str) -> Result<Vec<String>, Error> {
+        if let Some(cached) = self.fresh_entry(group_id).await {
+            record("hit");
+            return Ok(cached.members.as_ref().clone());
+        }
+
+        record("degraded");
+        let members: Vec<String> = self
+            .groups
+            .get_members(group_id)
+            .await
+            .map_err(|e| Error::Internal(format!("从群组服务查询群成员失败: {}", e)))?
+            .members
+            .into_iter()
+            .map(|member| member.user_id)
+            .collect();
+        if let (Some(entries), false) = (&self.entries, members.is_empty()) {
+            entries
+                .insert(
+                    group_id.to_string(),
+                    CachedMembers {
+                        version: DEGRADED_VERSION,
+                        members: Arc::new(members.clone()),
+                        checked_at: Instant::now(),
+                    },
+                )
+                .await;
+        }
+        Ok(members)
+    }
+
+    async fn fresh_entry(&self, group_id: &str) -> Option<CachedMembers> {
+        let entries = self.entries.as_ref()?;
+        entries
+            .get(group_id)
+            .await
+            .filter(|cached| cached.checked_at.elapsed() < self.fresh)
+    }
+
+    // 删除降级期间成员发生变化的群在Redis中的成员，失败的留到下次恢复时处理
+    async fn clear_stale(&self) {
+        let groups: Vec<String> = self.stale.lock().unwrap().drain().collect();
+        if groups.is_empty() {
+            return;
+        }
+        let mut failed = 0;
+        for group_id in groups {
+            if let Err(e) = self.cache.del_group_members(&group_id).await {
+                error!("清除群 {} 在Redis中的成员失败: {:?}", group_id, e);
+                self.stale.lock().unwrap().insert(group_id);
+                failed += 1;
+            }
+        }
+        info!("Redis已恢复，已清除降级期间成员变化的群，失败 {} 个", failed);
+    }
+}
+
+/// 记录缓存命中情况，命中率为 hit 与 revalidated 之和占全部查询的比例
+fn record(result: &'static str) {
+    metrics::counter!("im_group_member_cache_total", "result" => result).increment(1);
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use common::db::MsgStoreRepo;
+use common::error::Error;
+use common::message::{GroupMemSeq, Msg};
+use common::message_box::MsgRecBoxRepo;
+use tonic::async_trait;
+
+/// 内存版消息接收盒，按保存顺序记录消息和各用户标记已读的序号
+///
+/// 群聊消息按成员展开保存，每份的接收者为成员ID、序号为成员的当前序号
+#[derive(Debug, Clone, Default)]
+pub struct MemoryMsgRecBox {
+    messages: Arc<Mutex<Vec<Msg>>>,
+    read: Arc<Mutex<HashMap<String, Vec<i64>>>>,
+}
+
+impl MemoryMsgRecBox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 用户收到或发出的全部消息
+    pub fn messages_of(&self, user_id: &str) -> Vec<Msg> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|msg| msg.receiver_id == user_id || msg.send_id == user_id)
+            .cloned()
+            .collect()
+    }
+
+    /// 用户标记已读的序号，按标记顺序排列
+    pub fn read_seqs(&self, user_id: &str) -> Vec<i64> {
+        self.read
+            .lock()
+            .unwrap()
+            .get(user_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl MsgRecBoxRepo for MemoryMsgRecBox {
+    async fn save_message(&self, message: &Msg) -> Result<(), Error> {
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+
+    async fn save_group_msg(&self, message: Msg, members: Vec<GroupMemSeq>) -> Result<(), Error> {
+        let mut messages = self.messages.lock().unwrap();
+        for member in members {
+            let mut msg = message.clone();
+            msg.receiver_id = member.mem_id;
+            msg.seq = member.cur_seq;
+            messages.push(msg);
+        }
+        Ok(())
+    }
+
+    async fn delete_message(&self, message_id: &str) -> Result<(), Error> {
+        self.messages
+            .lock()
+            .unwrap()
+            .retain(|msg| msg.server_id != message_id);
+        Ok(())
+    }
+
+    async fn msg_read(&self, user_id: &str, msg_seq: &[i64]) -> Result<(), Error> {
+        self.read
+            .lock()
+            .unwrap()
+            .entry(user_id.to_string())
+            .or_default()
+            .extend_from_slice(msg_seq);
+        Ok(())
+    }
+}
+
+/// 内存版消息存储，按保存顺序记录消息
+#[derive(Debug, Clone, Default)]
+pub struct MemoryMsgStore {
+    messages: Arc<Mutex<Vec<Msg>>>,
+}
+
+impl MemoryMsgStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 已保存的全部消息
+    pub fn messages(&self) -> Vec<Msg> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl MsgStoreRepo for MemoryMsgStore {
+    async fn save_message(&self, message: &Msg) -> Result<(), Error> {
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_msg_rec_box() {
+        let msg_box = MemoryMsgRecBox::new();
+        let single = Msg {
+            send_id: "alice".to_string(),
+            receiver_id: "bob".to_string(),
+            server_id: "m1".to_string(),
+            ..Default::default()
+        };
+        msg_box.save_message(&single).await.unwrap();
+
+        let group = Msg {
+            send_id: "alice".to_string(),
+            group_id: "group-1".to_string(),
+            server_id: "m2".to_string(),
+            ..Default::default()
+        };
+        let members = ["bob", "carol"]
+            .iter()
+            .map(|id| GroupMemSeq {
+                mem_id: id.to_string(),
+                cur_seq: 7,
+                ..Default::default()
+            })
+            .collect();
+        msg_box
+            .save_group_msg(group.clone(), members)
+            .await
+            .unwrap();
+
+        let bob = msg_box.messages_of("bob");
+        assert_eq!(bob.len(), 2);
+        assert_eq!(bob[1].seq, 7);
+        assert_eq!(msg_box.messages_of("carol").len(), 1);
+
+        msg_box.delete_message(&group.server_id).await.unwrap();
+        assert_eq!(msg_box.messages_of("bob").len(), 1);
+
+        msg_box.msg_read("bob", &[1, 2]).await.unwrap();
+        assert_eq!(msg_box.read_seqs("bob"), vec![1, 2]);
+        assert!(msg_box.read_seqs("carol").is_empty());
+    }
+}
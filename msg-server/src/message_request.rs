@@ -0,0 +1,544 @@
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use moka::future::Cache as MokaCache;
+use mongodb::bson::spec::BinarySubtype;
+use mongodb::bson::{doc, Binary, Bson, Document};
+use mongodb::options::{FindOptions, UpdateOptions};
+use mongodb::Collection;
+use prost::Message;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+use tracing::{debug, info, warn};
+
+use common::config::{AppConfig, KafkaPayloadFormat, MessageRequestConfig};
+use common::error::Error;
+use common::grpc::subject::check_subject;
+use common::grpc_client::{FriendServiceGrpcClient, GroupServiceGrpcClient};
+use common::message::{Msg, MsgType};
+use common::proto::friend::FriendshipStatus;
+use common::proto::message_request::message_request_service_server::MessageRequestService;
+use common::proto::message_request::{
+    GetMessageRequestRequest, GetMessageRequestResponse, ListMessageRequestsRequest,
+    ListMessageRequestsResponse, MessageRequestActionRequest, MessageRequestActionResponse,
+    MessageRequestThread,
+};
+use common::tenant_db::MongoRouter;
+use common::time_sync::now_millis;
+
+use crate::codec;
+use crate::tenant_topics::TenantTopics;
+
+/// 暂存的消息请求集合名称
+const MSG_REQUEST_COLLECTION: &str = "msg_requests";
+
+/// 接收者已接受的发送者集合名称
+const MSG_REQUEST_ACCEPTED_COLLECTION: &str = "msg_request_accepted";
+
+/// 进程内缓存的联系人关系数量上限
+const CONTACT_CACHE_CAPACITY: u64 = 100_000;
+
+/// 消息请求的存储
+///
+/// 暂存的消息以prost编码保存，按接收者和发送者归组；接收者接受过的发送者单独记录，
+/// 之后该发送者的消息不再进入消息请求。数据存放在接收者租户的数据库集群
+#[derive(Clone)]
+pub struct MessageRequestStore {
+    mongo: MongoRouter,
+}
+
+impl MessageRequestStore {
+    pub async fn new(config: &AppConfig) -> Self {
+        let mongo = MongoRouter::connect(config).await.expect("MongoDB连接失败");
+        Self { mongo }
+    }
+
+    fn requests(&self) -> Collection<Document> {
+        self.mongo.database().collection(MSG_REQUEST_COLLECTION)
+    }
+
+    fn accepted(&self) -> Collection<Document> {
+        self.mongo
+            .database()
+            .collection(MSG_REQUEST_ACCEPTED_COLLECTION)
+    }
+
+    fn thread_filter(receiver_id: &str, send_id: &str) -> Document {
+        doc! { "receiver_id": receiver_id, "send_id": send_id }
+    }
+
+    fn decode(document: &Document, field: &str) -> Option<Msg> {
+        let bytes = document.get_binary_generic(field).ok()?;
+        Msg::decode(bytes.as_slice())
+            .map_err(|e| warn!("解码消息请求中的消息失败: {}", e))
+            .ok()
+    }
+
+    /// 暂存一条消息，按服务端消息ID去重，Kafka重复投递不会重复暂存
+    ///
+    /// 返回是否暂存，同一发送者暂存的消息达到上限时丢弃（并发写入时可能略微超出上限）
+    pub async fn save(&self, msg: &Msg, max_messages: u64) -> Result<bool, Error> {
+        let filter = Self::thread_filter(&msg.receiver_id, &msg.send_id);
+        let count = self
+            .requests()
+            .count_documents(filter, None)
+            .await
+            .map_err(|e| Error::Internal(format!("统计消息请求失败: {}", e)))?;
+        if count >= max_messages {
+            return Ok(false);
+        }
+
+        let document = doc! {
+            "server_id": &msg.server_id,
+            "receiver_id": &msg.receiver_id,
+            "send_id": &msg.send_id,
+            "send_time": msg.send_time,
+            "payload": Bson::Binary(Binary {
+                subtype: BinarySubtype::Generic,
+                bytes: msg.encode_to_vec(),
+            }),
+        };
+        self.requests()
+            .update_one(
+                doc! { "server_id": &msg.server_id },
+                doc! { "$setOnInsert": document },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("暂存消息请求失败: {}", e)))?;
+        Ok(true)
+    }
+
+    /// 接收者的消息请求，每个发送者一条，按最近一条消息的时间从新到旧
+    pub async fn threads(
+        &self,
+        receiver_id: &str,
+        limit: i64,
+    ) -> Result<Vec<MessageRequestThread>, Error> {
+        let pipeline = vec![
+            doc! { "$match": { "receiver_id": receiver_id } },
+            doc! { "$sort": { "send_time": -1 } },
+            doc! { "$group": {
+                "_id": "$send_id",
+                "count": { "$sum": 1 },
+                "last_send_time": { "$first": "$send_time" },
+                "last": { "$first": "$payload" },
+            }},
+            doc! { "$sort": { "last_send_time": -1 } },
+            doc! { "$limit": limit },
+        ];
+
+        let mut cursor = self
+            .requests()
+            .aggregate(pipeline, None)
+            .await
+            .map_err(|e| Error::Internal(format!("查询消息请求失败: {}", e)))?;
+
+        let mut threads = Vec::new();
+        while let Some(document) = cursor
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("读取消息请求失败: {}", e)))?
+        {
+            threads.push(MessageRequestThread {
+                sender_id: document.get_str("_id").unwrap_or_default().to_string(),
+                message_count: document
+                    .get_i32("count")
+                    .map(i64::from)
+                    .or_else(|_| document.get_i64("count"))
+                    .unwrap_or_default(),
+                last_send_time: document.get_i64("last_send_time").unwrap_or_default(),
+                last_message: document
+                    .get_binary_generic("last")
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+        Ok(threads)
+    }
+
+    /// 某个发送者暂存的全部消息，按发送时间从旧到新
+    pub async fn messages(&self, receiver_id: &str, send_id: &str) -> Result<Vec<Msg>, Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "send_time": 1, "server_id": 1 })
+            .build();
+        let mut cursor = self
+            .requests()
+            .find(Self::thread_filter(receiver_id, send_id), options)
+            .await
+            .map_err(|e| Error::Internal(format!("查询消息请求失败: {}", e)))?;
+
+        let mut messages = Vec::new();
+        while let Some(document) = cursor
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("读取消息请求失败: {}", e)))?
+        {
+            messages.extend(Self::decode(&document, "payload"));
+        }
+        Ok(messages)
+    }
+
+    /// 删除一条已投递的暂存消息
+    pub async fn remove(&self, server_id: &str) -> Result<(), Error> {
+        self.requests()
+            .delete_one(doc! { "server_id": server_id }, None)
+            .await
+            .map_err(|e| Error::Internal(format!("删除消息请求失败: {}", e)))?;
+        Ok(())
+    }
+
+    /// 删除某个发送者暂存的全部消息，返回删除的条数
+    pub async fn purge(&self, receiver_id: &str, send_id: &str) -> Result<u64, Error> {
+        let result = self
+            .requests()
+            .delete_many(Self::thread_filter(receiver_id, send_id), None)
+            .await
+            .map_err(|e| Error::Internal(format!("删除消息请求失败: {}", e)))?;
+        Ok(result.deleted_count)
+    }
+
+    /// 记录接收者接受了发送者的消息请求
+    pub async fn accept(&self, receiver_id: &str, send_id: &str) -> Result<(), Error> {
+        self.accepted()
+            .update_one(
+                Self::thread_filter(receiver_id, send_id),
+                doc! { "$setOnInsert": { "accepted_at": now_millis() } },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("记录接受消息请求失败: {}", e)))?;
+        Ok(())
+    }
+
+    /// 接收者是否接受过发送者的消息请求
+    pub async fn is_accepted(&self, receiver_id: &str, send_id: &str) -> Result<bool, Error> {
+        let accepted = self
+            .accepted()
+            .find_one(Self::thread_filter(receiver_id, send_id), None)
+            .await
+            .map_err(|e| Error::Internal(format!("查询已接受的消息请求失败: {}", e)))?;
+        Ok(accepted.is_some())
+    }
+}
+
+/// 消费者使用的消息请求过滤
+///
+/// 单聊消息的发送者与接收者是好友、有共同群组或接收者接受过发送者时正常投递，
+/// 否则暂存到接收者的消息请求中。只缓存“是联系人”的结果，接受消息请求后立即生效；
+/// 查询关系失败时按联系人处理，宁可放行也不丢失消息
+#[derive(Clone)]
+pub struct MessageRequestFilter {
+    config: MessageRequestConfig,
+    store: MessageRequestStore,
+    friend_client: FriendServiceGrpcClient,
+    group_client: GroupServiceGrpcClient,
+    // (发送者, 接收者) 是联系人的缓存
+    contacts: MokaCache<(String, String), ()>,
+}
+
+impl MessageRequestFilter {
+    pub async fn new(config: &AppConfig) -> Self {
+        Self {
+            config: config.message_requests.clone(),
+            store: MessageRequestStore::new(config).await,
+            friend_client: FriendServiceGrpcClient::from_env(),
+            group_client: GroupServiceGrpcClient::from_env(),
+            contacts: MokaCache::builder()
+                .max_capacity(CONTACT_CACHE_CAPACITY)
+                .time_to_live(Duration::from_secs(
+                    config.message_requests.contact_cache_secs,
+                ))
+                .build(),
+        }
+    }
+
+    /// 是否需要检查消息的发送者：只检查启用时发给他人的单聊消息
+    fn applies(config: &MessageRequestConfig, msg: &Msg) -> bool {
+        config.enabled
+            && msg.msg_type == MsgType::SingleMsg as i32
+            && msg.send_id != msg.receiver_id
+    }
+
+    /// 消息是否进入消息请求，进入时已完成暂存
+    pub async fn quarantine(&self, msg: &Msg) -> Result<bool, Error> {
+        if !Self::applies(&self.config, msg) {
+            return Ok(false);
+        }
+
+        let key = (msg.send_id.clone(), msg.receiver_id.clone());
+        if self.contacts.get(&key).await.is_some() {
+            return Ok(false);
+        }
+        match self.is_contact(&msg.send_id, &msg.receiver_id).await {
+            Ok(true) => {
+                self.contacts.insert(key, ()).await;
+                return Ok(false);
+            }
+            Ok(false) => {}
+            Err(e) => {
+                warn!(
+                    "查询 {} 与 {} 的联系人关系失败，按联系人投递: {:?}",
+                    msg.send_id, msg.receiver_id, e
+                );
+                return Ok(false);
+            }
+        }
+
+        let saved = self
+            .store
+            .save(msg, self.config.max_messages_per_sender)
+            .await?;
+        if saved {
+            metrics::counter!("im_message_requests_total", "result" => "quarantined").increment(1);
+            debug!(
+                "消息 {} 来自非联系人 {}，进入 {} 的消息请求",
+                msg.server_id, msg.send_id, msg.receiver_id
+            );
+        } else {
+            metrics::counter!("im_message_requests_total", "result" => "dropped").increment(1);
+            warn!(
+                "{} 发给 {} 的消息请求已达上限，丢弃消息 {}",
+                msg.send_id, msg.receiver_id, msg.server_id
+            );
+        }
+        Ok(true)
+    }
+
+    /// 发送者与接收者是否为联系人：接收者接受过发送者、是好友或有共同群组
+    async fn is_contact(&self, send_id: &str, receiver_id: &str) -> Result<bool, Error> {
+        if self.store.is_accepted(receiver_id, send_id).await? {
+            return Ok(true);
+        }
+
+        let friendship = self
+            .friend_client
+            .check_friendship(send_id, receiver_id)
+            .await
+            .map_err(|e| Error::Internal(format!("查询好友关系失败: {}", e)))?;
+        if friendship.status == FriendshipStatus::Accepted as i32 {
+            return Ok(true);
+        }
+
+        let (sender_groups, receiver_groups) = futures::try_join!(
+            self.group_client.get_user_groups(send_id),
+            self.group_client.get_user_groups(receiver_id),
+        )
+        .map_err(|e| Error::Internal(format!("查询群组列表失败: {}", e)))?;
+        Ok(sender_groups.groups.iter().any(|group| {
+            receiver_groups
+                .groups
+                .iter()
+                .any(|other| other.id == group.id)
+        }))
+    }
+}
+
+/// 消息请求的gRPC服务
+///
+/// 接受时先记录接受关系，再将暂存的消息按原顺序重新写入Kafka，由消费者正常分配序号、
+/// 写入收件箱并推送；每条消息写入Kafka后才从暂存中删除，中途失败时重试只会投递剩余的消息
+pub struct MessageRequestRpcService {
+    store: MessageRequestStore,
+    kafka: FutureProducer,
+    topics: TenantTopics,
+    payload_format: KafkaPayloadFormat,
+    // 本区域名称，多区域部署时写入消息的来源区域头
+    region: Option<String>,
+    list_limit: i64,
+}
+
+impl MessageRequestRpcService {
+    pub async fn new(
+        config: &AppConfig,
+        kafka: FutureProducer,
+        topics: TenantTopics,
+        payload_format: KafkaPayloadFormat,
+        region: Option<String>,
+    ) -> Self {
+        Self {
+            store: MessageRequestStore::new(config).await,
+            kafka,
+            topics,
+            payload_format,
+            region,
+            list_limit: config.message_requests.list_limit,
+        }
+    }
+
+    /// 只能操作自己的消息请求
+    fn check_ids(metadata: &MetadataMap, user_id: &str, sender_id: &str) -> Result<(), Status> {
+        check_subject(metadata, user_id)?;
+        if user_id.is_empty() || sender_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID和发送者ID不能为空"));
+        }
+        Ok(())
+    }
+
+    /// 将暂存的消息重新写入Kafka
+    async fn redeliver(&self, msg: &Msg) -> Result<(), Error> {
+        let (payload, mut headers) = codec::encode(msg, self.payload_format, None)?;
+        if let Some(region) = &self.region {
+            headers = codec::with_origin(headers, region);
+        }
+        let record: FutureRecord<String, Vec<u8>> =
+            FutureRecord::to(self.topics.topic(&msg.tenant_id))
+                .payload(&payload)
+                .headers(headers);
+        self.kafka
+            .send(record, Duration::from_secs(0))
+            .await
+            .map_err(|(e, _)| Error::Internal(format!("投递消息请求失败: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl MessageRequestService for MessageRequestRpcService {
+    async fn list_message_requests(
+        &self,
+        request: Request<ListMessageRequestsRequest>,
+    ) -> Result<Response<ListMessageRequestsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID不能为空"));
+        }
+        let limit = if req.limit <= 0 {
+            self.list_limit
+        } else {
+            req.limit as i64
+        };
+        let threads = self.store.threads(&req.user_id, limit).await?;
+        Ok(Response::new(ListMessageRequestsResponse { threads }))
+    }
+
+    async fn get_message_request(
+        &self,
+        request: Request<GetMessageRequestRequest>,
+    ) -> Result<Response<GetMessageRequestResponse>, Status> {
+        Self::check_ids(
+            request.metadata(),
+            &request.get_ref().user_id,
+            &request.get_ref().sender_id,
+        )?;
+        let req = request.into_inner();
+        let messages = self.store.messages(&req.user_id, &req.sender_id).await?;
+        Ok(Response::new(GetMessageRequestResponse {
+            messages: messages.iter().map(Message::encode_to_vec).collect(),
+        }))
+    }
+
+    async fn accept_message_request(
+        &self,
+        request: Request<MessageRequestActionRequest>,
+    ) -> Result<Response<MessageRequestActionResponse>, Status> {
+        Self::check_ids(
+            request.metadata(),
+            &request.get_ref().user_id,
+            &request.get_ref().sender_id,
+        )?;
+        let req = request.into_inner();
+
+        self.store.accept(&req.user_id, &req.sender_id).await?;
+        let messages = self.store.messages(&req.user_id, &req.sender_id).await?;
+        for msg in &messages {
+            self.redeliver(msg).await?;
+            self.store.remove(&msg.server_id).await?;
+        }
+
+        metrics::counter!("im_message_requests_total", "result" => "accepted").increment(1);
+        info!(
+            "用户 {} 接受了 {} 的消息请求，投递 {} 条消息",
+            req.user_id,
+            req.sender_id,
+            messages.len()
+        );
+        Ok(Response::new(MessageRequestActionResponse {
+            affected: messages.len() as i64,
+        }))
+    }
+
+    async fn decline_message_request(
+        &self,
+        request: Request<MessageRequestActionRequest>,
+    ) -> Result<Response<MessageRequestActionResponse>, Status> {
+        Self::check_ids(
+            request.metadata(),
+            &request.get_ref().user_id,
+            &request.get_ref().sender_id,
+        )?;
+        let req = request.into_inner();
+
+        let purged = self.store.purge(&req.user_id, &req.sender_id).await?;
+        metrics::counter!("im_message_requests_total", "result" => "declined").increment(1);
+        info!(
+            "用户 {} 拒绝了 {} 的消息请求，删除 {} 条消息",
+            req.user_id, req.sender_id, purged
+        );
+        Ok(Response::new(MessageRequestActionResponse {
+            affected: purged as i64,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common::grpc::subject::SUBJECT_METADATA_KEY;
+
+    fn single(send_id: &str, receiver_id: &str) -> Msg {
+        Msg {
+            send_id: send_id.to_string(),
+            receiver_id: receiver_id.to_string(),
+            msg_type: MsgType::SingleMsg as i32,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_applies() {
+        let config = MessageRequestConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(MessageRequestFilter::applies(&config, &single("a", "b")));
+        // 发给自己的消息
+        assert!(!MessageRequestFilter::applies(&config, &single("a", "a")));
+        // 群聊消息
+        let group = Msg {
+            msg_type: MsgType::GroupMsg as i32,
+            ..single("a", "g")
+        };
+        assert!(!MessageRequestFilter::applies(&config, &group));
+        // 未启用
+        assert!(!MessageRequestFilter::applies(
+            &MessageRequestConfig::default(),
+            &single("a", "b")
+        ));
+    }
+
+    #[test]
+    fn test_check_ids() {
+        let mut metadata = MetadataMap::new();
+        // 内部调用没有认证用户
+        assert!(MessageRequestRpcService::check_ids(&metadata, "u1", "u2").is_ok());
+        assert_eq!(
+            MessageRequestRpcService::check_ids(&metadata, "u1", "")
+                .unwrap_err()
+                .code(),
+            tonic::Code::InvalidArgument
+        );
+
+        metadata.insert(SUBJECT_METADATA_KEY, "u1".parse().unwrap());
+        assert!(MessageRequestRpcService::check_ids(&metadata, "u1", "u2").is_ok());
+        assert_eq!(
+            MessageRequestRpcService::check_ids(&metadata, "u2", "u1")
+                .unwrap_err()
+                .code(),
+            tonic::Code::PermissionDenied
+        );
+    }
+}
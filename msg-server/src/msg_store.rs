@@ -0,0 +1,392 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::routing::get;
+use axum::Router;
+use chrono::DateTime;
+use prost::Message;
+use tokio::signal;
+use tokio::sync::oneshot;
+use tonic::metadata::MetadataMap;
+use tonic::transport::server::Routes;
+use tonic::{Request, Response, Status};
+use tonic_reflection::server::Builder as ReflectionBuilder;
+use tracing::{error, info, warn};
+
+use common::config::AppConfig;
+use common::error::Error;
+use common::grpc::maintenance::MaintenanceMode;
+use common::grpc::server::serve_with_drain;
+use common::grpc::service_auth::{ServiceAuth, ServiceAuthInterceptor};
+use common::grpc::subject::check_subject;
+use common::grpc::LoggingInterceptor;
+use common::grpc_client::GroupServiceGrpcClient;
+use common::message::Msg;
+use common::proto::msg_store::msg_store_service_server::{MsgStoreService, MsgStoreServiceServer};
+use common::proto::msg_store::{
+    DeleteMessageRequest, DeleteMessageResponse, GetConversationMessagesRequest,
+    GetMessagesBySeqRequest, MarkReadRequest, MarkReadResponse, MessagesResponse,
+    FILE_DESCRIPTOR_SET,
+};
+use common::service_registry::{ServiceRegister, ServiceRegistry};
+use common::tenant_db::TenantRouted;
+
+use crate::history::{PageCursor, PgMessageStore};
+
+/// 单次查询默认返回的消息数
+const DEFAULT_LIMIT: i64 = 50;
+
+/// 单次查询最多返回的消息数
+const MAX_LIMIT: i64 = 500;
+
+/// 注册到服务注册中心的服务名
+const SERVICE_NAME: &str = "msg-store";
+
+/// 查询条数，未指定时使用默认值，超过上限时按上限
+fn page_limit(limit: i32) -> i64 {
+    if limit <= 0 {
+        DEFAULT_LIMIT
+    } else {
+        (limit as i64).min(MAX_LIMIT)
+    }
+}
+
+/// 按会话分页的位置，`before_time` 为0时从最新的消息开始
+fn page_cursor(before_time: i64, before_id: String) -> Result<Option<PageCursor>, Status> {
+    if before_time <= 0 {
+        return Ok(None);
+    }
+    let sent_at = DateTime::from_timestamp_millis(before_time)
+        .ok_or_else(|| Status::invalid_argument("分页时间无效"))?
+        .naive_utc();
+    Ok(Some((sent_at, before_id)))
+}
+
+/// 只能查询和修改自己的消息
+fn check_user(metadata: &MetadataMap, user_id: &str) -> Result<(), Status> {
+    check_subject(metadata, user_id)?;
+    if user_id.is_empty() {
+        return Err(Status::invalid_argument("用户ID不能为空"));
+    }
+    Ok(())
+}
+
+fn encode_messages(messages: Vec<Msg>) -> MessagesResponse {
+    MessagesResponse {
+        messages: messages.iter().map(Message::encode_to_vec).collect(),
+    }
+}
+
+/// 消息存储的gRPC服务
+///
+/// 查询和修改Postgres中的消息历史，按请求所属的租户路由到消息写入时的数据库集群。
+/// 经网关转发的请求只能操作认证用户自己的消息，群聊消息只有群成员可以查询
+pub struct DbRpcService {
+    stores: TenantRouted<PgMessageStore>,
+    group_client: GroupServiceGrpcClient,
+}
+
+impl DbRpcService {
+    pub fn new(stores: TenantRouted<PgMessageStore>, group_client: GroupServiceGrpcClient) -> Self {
+        Self {
+            stores,
+            group_client,
+        }
+    }
+
+    /// 启动消息存储服务
+    /// 注册到服务注册中心，添加反射服务，收到关闭信号后注销并排空连接再返回
+    pub async fn start(config: &AppConfig) {
+        // 未配置服务间认证密钥时拒绝启动，避免RPC端口对外无认证暴露
+        ServiceAuth::global()
+            .ensure_enabled()
+            .expect("服务间认证未配置");
+
+        let stores = TenantRouted::connect(config, |config| async move {
+            PgMessageStore::new(&config).expect("消息历史仓库初始化失败")
+        })
+        .await
+        .expect("消息历史仓库初始化失败");
+        let service = Self::new(stores, GroupServiceGrpcClient::from_env());
+
+        let rpc = &config.rpc.db;
+        let addr: SocketAddr = rpc
+            .rpc_server_url()
+            .parse()
+            .expect("消息存储服务监听地址无效");
+
+        // 健康检查使用gRPC端口的下一个端口
+        let health_port = rpc.port + 1;
+        let health_check_url = format!("http://{}:{}/health", rpc.host, health_port);
+        let health_service = start_health_service(&rpc.host, health_port)
+            .await
+            .expect("健康检查服务启动失败");
+
+        let service_registry = ServiceRegistry::from_env()
+            .with_region(config.region.local())
+            .shared();
+        let service_id = service_registry
+            .register(
+                SERVICE_NAME,
+                &rpc.host,
+                rpc.port as u32,
+                rpc.tags.clone(),
+                &health_check_url,
+                "15s",
+            )
+            .await
+            .expect("服务注册失败");
+        info!(
+            "<db> 消息存储服务已注册到服务注册中心, 服务ID: {}",
+            service_id
+        );
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let shutdown_signal_task =
+            tokio::spawn(shutdown_signal(shutdown_tx, service_registry.clone()));
+
+        let reflection_service = ReflectionBuilder::configure()
+            .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+            .build()
+            .expect("反射服务创建失败");
+
+        // 业务服务外层校验服务间令牌
+        let routes =
+            Routes::new(reflection_service).add_service(MsgStoreServiceServer::with_interceptor(
+                service,
+                ServiceAuthInterceptor::new(LoggingInterceptor::new()),
+            ));
+        info!("<db> 消息存储服务已启动，监听地址: {}", addr);
+
+        // 数据库迁移期间可以切换到只读维护模式，拒绝删除和标记已读
+        let maintenance = MaintenanceMode::new("msg-store", &config.maintenance);
+        maintenance.start(&config.redis);
+        let server = serve_with_drain(routes, addr, &config.grpc_server, maintenance, async {
+            let _ = shutdown_rx.await;
+            info!("接收到关闭信号，消息存储服务准备关闭");
+        });
+
+        tokio::select! {
+            result = server => {
+                if let Err(e) = result {
+                    error!("消息存储服务错误: {}", e);
+                }
+                info!("消息存储服务已关闭");
+            }
+            _ = health_service => {
+                info!("健康检查服务已关闭");
+            }
+        }
+
+        let _ = shutdown_signal_task.await;
+    }
+
+    /// 群聊消息只有群成员可以查询
+    async fn check_member(&self, group_id: &str, user_id: &str) -> Result<(), Status> {
+        let membership = self
+            .group_client
+            .check_membership(group_id, user_id)
+            .await
+            .map_err(|e| {
+                warn!("查询 {} 是否为群 {} 的成员失败: {:?}", user_id, group_id, e);
+                Status::unavailable("暂时无法查询群成员")
+            })?;
+        if !membership.is_member {
+            return Err(Status::permission_denied("不是群成员"));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl MsgStoreService for DbRpcService {
+    async fn get_messages_by_seq(
+        &self,
+        request: Request<GetMessagesBySeqRequest>,
+    ) -> Result<Response<MessagesResponse>, Status> {
+        check_user(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.start_seq < 0 || (req.end_seq > 0 && req.end_seq < req.start_seq) {
+            return Err(Status::invalid_argument("序号范围无效"));
+        }
+
+        let messages = self
+            .stores
+            .current()
+            .received_range(
+                &req.user_id,
+                req.start_seq,
+                req.end_seq,
+                page_limit(req.limit),
+            )
+            .await?;
+        Ok(Response::new(encode_messages(messages)))
+    }
+
+    async fn get_conversation_messages(
+        &self,
+        request: Request<GetConversationMessagesRequest>,
+    ) -> Result<Response<MessagesResponse>, Status> {
+        check_user(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.peer_id.is_empty() {
+            return Err(Status::invalid_argument("会话ID不能为空"));
+        }
+        let before = page_cursor(req.before_time, req.before_id)?;
+        let limit = page_limit(req.limit);
+
+        let store = self.stores.current();
+        let messages = if req.is_group {
+            self.check_member(&req.peer_id, &req.user_id).await?;
+            store
+                .group_conversation(&req.peer_id, before, limit)
+                .await?
+        } else {
+            store
+                .private_conversation(&req.user_id, &req.peer_id, before, limit)
+                .await?
+        };
+        Ok(Response::new(encode_messages(messages)))
+    }
+
+    async fn delete_message(
+        &self,
+        request: Request<DeleteMessageRequest>,
+    ) -> Result<Response<DeleteMessageResponse>, Status> {
+        check_user(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.server_id.is_empty() {
+            return Err(Status::invalid_argument("消息ID不能为空"));
+        }
+
+        let deleted = self
+            .stores
+            .current()
+            .delete(&req.user_id, &req.server_id, req.is_group)
+            .await?;
+        if deleted {
+            info!("用户 {} 删除了消息 {}", req.user_id, req.server_id);
+        }
+        Ok(Response::new(DeleteMessageResponse { deleted }))
+    }
+
+    async fn mark_read(
+        &self,
+        request: Request<MarkReadRequest>,
+    ) -> Result<Response<MarkReadResponse>, Status> {
+        check_user(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.peer_id.is_empty() {
+            return Err(Status::invalid_argument("会话ID不能为空"));
+        }
+
+        let affected = self
+            .stores
+            .current()
+            .mark_read(&req.user_id, &req.peer_id, &req.server_ids)
+            .await?;
+        Ok(Response::new(MarkReadResponse {
+            affected: affected as i64,
+        }))
+    }
+}
+
+// 健康检查HTTP服务
+async fn start_health_service(
+    host: &str,
+    port: u16,
+) -> Result<impl std::future::Future<Output = ()>, Error> {
+    let health_addr = format!("{}:{}", host, port)
+        .parse::<SocketAddr>()
+        .map_err(|e| Error::Internal(format!("健康检查监听地址无效: {}", e)))?;
+
+    let app = Router::new().route("/health", get(|| async { "OK" }));
+    info!("健康检查服务启动，监听地址: {}", health_addr);
+
+    let health_server = axum_server::bind(health_addr).serve(app.into_make_service());
+    let server_task = tokio::spawn(async move {
+        if let Err(e) = health_server.await {
+            error!("健康检查服务错误: {}", e);
+        }
+    });
+
+    Ok(async move {
+        let _ = server_task.await;
+    })
+}
+
+// 优雅关闭信号处理：先从服务注册中心注销，再通知gRPC服务排空连接
+async fn shutdown_signal(tx: oneshot::Sender<()>, service_registry: Arc<dyn ServiceRegister>) {
+    let ctrl_c = async {
+        signal::ctrl_c().await.expect("无法安装Ctrl+C处理器");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("无法安装SIGTERM处理器")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("接收到关闭信号，准备优雅关闭消息存储服务...");
+    match service_registry.deregister().await {
+        Ok(_) => info!("已从Consul注销消息存储服务"),
+        Err(e) => error!("从Consul注销消息存储服务失败: {}", e),
+    }
+
+    if tx.send(()).is_err() {
+        warn!("无法发送关闭信号，接收端可能已关闭");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common::grpc::subject::SUBJECT_METADATA_KEY;
+
+    #[test]
+    fn test_page_limit() {
+        assert_eq!(page_limit(0), DEFAULT_LIMIT);
+        assert_eq!(page_limit(-1), DEFAULT_LIMIT);
+        assert_eq!(page_limit(20), 20);
+        assert_eq!(page_limit(10_000), MAX_LIMIT);
+    }
+
+    #[test]
+    fn test_page_cursor() {
+        assert!(page_cursor(0, String::new()).unwrap().is_none());
+
+        let (sent_at, id) = page_cursor(1_700_000_000_123, "m1".to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(sent_at.and_utc().timestamp_millis(), 1_700_000_000_123);
+        assert_eq!(id, "m1");
+    }
+
+    #[test]
+    fn test_check_user() {
+        let mut metadata = MetadataMap::new();
+        assert!(check_user(&metadata, "u1").is_ok());
+        assert_eq!(
+            check_user(&metadata, "").unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+
+        metadata.insert(SUBJECT_METADATA_KEY, "u1".parse().unwrap());
+        assert!(check_user(&metadata, "u1").is_ok());
+        assert_eq!(
+            check_user(&metadata, "u2").unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use tracing::error;
+
+use cache::Cache;
+use common::config::KafkaPayloadFormat;
+use common::error::Error;
+use common::message::{Msg, MsgType};
+use common::time_sync::now_millis;
+
+use crate::codec;
+
+/// 会话通知
+///
+/// 后台任务（附件扫描、链接预览等）在消息投递后产生的补充信息，
+/// 以通知消息的形式写回Kafka，推送给会话的所有参与者。
+#[derive(Clone)]
+pub struct ConversationNotifier {
+    cache: Arc<dyn Cache>,
+    kafka: FutureProducer,
+    topic: String,
+    payload_format: KafkaPayloadFormat,
+}
+
+impl ConversationNotifier {
+    pub fn new(
+        cache: Arc<dyn Cache>,
+        kafka: FutureProducer,
+        topic: String,
+        payload_format: KafkaPayloadFormat,
+    ) -> Self {
+        Self {
+            cache,
+            kafka,
+            topic,
+            payload_format,
+        }
+    }
+
+    /// 向原消息所在会话的参与者发送通知：单聊为收发双方，群聊为全体成员
+    ///
+    /// # 参数
+    /// * `msg` - 原消息
+    /// * `content` - 通知内容（JSON）
+    pub async fn notify(&self, msg: &Msg, content: Vec<u8>) -> Result<(), Error> {
+        let mut receivers = if msg.msg_type == MsgType::GroupMsg as i32 {
+            self.cache.query_group_members_id(&msg.receiver_id).await?
+        } else {
+            vec![msg.receiver_id.clone()]
+        };
+        if !receivers.contains(&msg.send_id) {
+            receivers.push(msg.send_id.clone());
+        }
+
+        for receiver_id in receivers {
+            let notification = Msg {
+                receiver_id,
+                send_time: now_millis(),
+                msg_type: MsgType::Notification as i32,
+                content: content.clone(),
+                related_msg_id: Some(msg.server_id.clone()),
+                ..Default::default()
+            };
+            let (payload, headers) = codec::encode(&notification, self.payload_format, None)?;
+            let record: FutureRecord<String, Vec<u8>> = FutureRecord::to(&self.topic)
+                .payload(&payload)
+                .headers(headers);
+            if let Err((e, _)) = self.kafka.send(record, Duration::from_secs(0)).await {
+                error!("发送会话通知失败: {:?}", e);
+            }
+        }
+        Ok(())
+    }
+}
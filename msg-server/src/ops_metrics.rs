@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nanoid::nanoid;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::Offset;
+use tracing::{debug, warn};
+
+use cache::Cache;
+use common::config::OpsMetricsConfig;
+use common::ops_metrics::ConsumerSample;
+use common::time_sync::now_millis;
+
+/// 查询分区水位的超时时间
+const WATERMARK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 运维看板的消费指标上报
+///
+/// 消费循环每处理一条消息计数一次，后台任务按采样间隔计算每秒处理的消息数和
+/// 已分配分区的消费积压，写入Redis供网关汇总推送
+#[derive(Clone)]
+pub struct OpsReporter {
+    cache: Arc<dyn Cache>,
+    consumers: Vec<Arc<StreamConsumer>>,
+    consumed: Arc<AtomicU64>,
+    instance_id: String,
+    config: OpsMetricsConfig,
+}
+
+impl OpsReporter {
+    pub fn new(
+        config: &OpsMetricsConfig,
+        cache: Arc<dyn Cache>,
+        consumers: Vec<Arc<StreamConsumer>>,
+    ) -> Self {
+        Self {
+            cache,
+            consumers,
+            consumed: Arc::new(AtomicU64::new(0)),
+            instance_id: format!("msg-server-{}", nanoid!(8)),
+            config: config.clone(),
+        }
+    }
+
+    /// 启动后台上报任务
+    pub fn start(&self) {
+        if !self.config.enabled {
+            return;
+        }
+        let reporter = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(reporter.config.interval_secs.max(1)));
+            let mut last = (Instant::now(), 0);
+            loop {
+                interval.tick().await;
+                last = reporter.report(last).await;
+            }
+        });
+    }
+
+    /// 记录处理完成的一条消息
+    pub fn record_consumed(&self) {
+        self.consumed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 上报一次采样，返回本次采样的时间和累计处理数，作为下次计算速率的起点
+    async fn report(&self, (last_at, last_consumed): (Instant, u64)) -> (Instant, u64) {
+        let now = Instant::now();
+        let consumed = self.consumed.load(Ordering::Relaxed);
+        let elapsed = now.duration_since(last_at).as_secs_f64();
+        let msgs_per_sec = if elapsed > 0.0 {
+            consumed.saturating_sub(last_consumed) as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        // 查询水位是阻塞调用，放到阻塞线程池执行
+        let consumers = self.consumers.clone();
+        let consumer_lag = tokio::task::spawn_blocking(move || consumer_lag(&consumers))
+            .await
+            .unwrap_or_default();
+
+        let sample = ConsumerSample {
+            instance_id: self.instance_id.clone(),
+            sampled_at: now_millis(),
+            msgs_per_sec,
+            consumer_lag,
+        };
+        metrics::gauge!("im_kafka_consumer_lag").set(consumer_lag.unwrap_or_default() as f64);
+
+        match serde_json::to_string(&sample) {
+            Ok(sample) => {
+                // 保留三个采样间隔，全部实例下线后采样自动清除
+                let ttl = (self.config.interval_secs.max(1) * 3) as i64;
+                if let Err(e) = self
+                    .cache
+                    .save_ops_sample(&self.instance_id, &sample, ttl)
+                    .await
+                {
+                    warn!("上报消费指标失败: {:?}", e);
+                }
+            }
+            Err(e) => warn!("序列化消费指标失败: {}", e),
+        }
+        (now, consumed)
+    }
+}
+
+/// 已分配分区的消费积压：分区最高水位与当前消费位置之差，尚未开始消费的分区不计入
+fn consumer_lag(consumers: &[Arc<StreamConsumer>]) -> Option<i64> {
+    let mut lag = None;
+    for consumer in consumers {
+        let positions = match consumer.position() {
+            Ok(positions) => positions,
+            Err(e) => {
+                debug!("查询消费位置失败: {}", e);
+                continue;
+            }
+        };
+        for elem in positions.elements() {
+            let Offset::Offset(offset) = elem.offset() else {
+                continue;
+            };
+            match consumer.fetch_watermarks(elem.topic(), elem.partition(), WATERMARK_TIMEOUT) {
+                Ok((_, high)) => *lag.get_or_insert(0) += (high - offset).max(0),
+                Err(e) => debug!(
+                    "查询分区水位失败: {}:{}, {}",
+                    elem.topic(),
+                    elem.partition(),
+                    e
+                ),
+            }
+        }
+    }
+    lag
+}
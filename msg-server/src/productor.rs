@@ -1,34 +1,130 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::{future, stream, StreamExt};
 use nanoid::nanoid;
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic, TopicReplication};
 use rdkafka::client::DefaultClientContext;
 use rdkafka::error::KafkaError;
+use rdkafka::message::OwnedHeaders;
 use rdkafka::producer::{FutureProducer, FutureRecord};
 use rdkafka::ClientConfig;
+use tokio::sync::mpsc;
 use tonic::transport::Server;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use common::config::{AppConfig, Component};
-use common::grpc::LoggingInterceptor;
+use cache::Cache;
+use common::attachment::AttachmentDescriptor;
+use common::contact_card::ContactCard;
+use common::config::{AppConfig, AttachmentConfig, Component, KafkaPayloadFormat};
+use common::grpc::maintenance::{MaintenanceLayer, MaintenanceMode};
+use common::grpc::service_auth::{ServiceAuth, ServiceAuthInterceptor, ServiceClaims};
+use common::grpc::subject::check_subject;
+use common::grpc::tenant::{with_tenant, TenantScopeLayer};
+use common::grpc::{LoggingInterceptor, LoggingLayer};
+use common::grpc_client::{GroupServiceGrpcClient, UserServiceGrpcClient};
 use common::message::chat_service_server::{ChatService, ChatServiceServer};
-use common::message::{MsgResponse, MsgType, SendMsgRequest};
+use common::message::{
+    ContentType, Msg, MsgResponse, MsgType, SendBatchRequest, SendBatchResponse, SendMsgRequest,
+};
+use common::proto::backfill::history_backfill_service_server::HistoryBackfillServiceServer;
+use common::proto::draft::draft_service_server::DraftServiceServer;
+use common::proto::mailbox::mailbox_repair_service_server::MailboxRepairServiceServer;
+use common::proto::message_request::message_request_service_server::MessageRequestServiceServer;
+use common::proto::transaction::transaction_service_server::TransactionServiceServer;
+use common::tenant_db::MongoRouter;
+use common::time_sync::now_millis;
+use oss::Oss;
 use tonic_health::server::{Health, HealthServer};
 
+use crate::backfill::{BackfillService, MongoHistorySource};
+use crate::codec;
+use crate::draft::{DraftRpcService, DraftStore};
+use crate::group_media::GroupMediaIndexer;
+use crate::link_preview::{extract_url, LinkPreviewTask, LinkPreviewWorker};
+use crate::member_cache::GroupMemberCache;
+use crate::message_request::MessageRequestRpcService;
+use crate::notify::ConversationNotifier;
+use crate::pusher::push_service;
+use crate::rebuild::{MongoReceiveBox, PgHistory, RebuildService};
+use crate::scanner::{HttpScanner, ScanTask, ScanWorker};
+use crate::signaling::SignalingDeadline;
+use crate::spill::{self, SpillQueue};
+use crate::tenant_topics::TenantTopics;
+use crate::transaction::{TransactionRpcService, TransactionStore};
+use crate::validation::{check_poll, MessageValidator};
+
+/// 缩略图签名下载地址的有效期，S3签名地址最长有效7天
+const THUMBNAIL_URL_TTL: Duration = Duration::from_secs(7 * 24 * 3600);
+
 /// 消息RPC服务实现
 /// 负责接收客户端消息并发送到Kafka消息队列
 pub struct ChatRpcService {
     // Kafka生产者实例，用于发送消息到Kafka
     kafka: FutureProducer,
-    // Kafka主题名称，消息将被发送到此主题
-    topic: String,
+    // Kafka主题，消息按租户发送到默认主题或大租户的独立主题
+    topics: TenantTopics,
+    // 写入Kafka的消息编码格式
+    payload_format: KafkaPayloadFormat,
+    // 附件校验配置
+    attachment: AttachmentConfig,
+    // 缓存，用于查询已拦截的附件
+    cache: Arc<dyn Cache>,
+    // 附件扫描任务队列，未配置扫描服务时为空
+    scan_tx: Option<mpsc::Sender<ScanTask>>,
+    // 链接预览任务队列
+    preview_tx: mpsc::Sender<LinkPreviewTask>,
+    // 信令消息投递期限
+    signaling: SignalingDeadline,
+    // Kafka不可用时的本地暂存队列，未启用时为空
+    spill: Option<SpillQueue>,
+    // 写入Kafka前的消息校验
+    validator: MessageValidator,
+    // 多区域部署时本区域的名称，写入消息头用于跨区域复制
+    region: Option<String>,
+    // 用户服务客户端，用于填充名片快照
+    user_client: UserServiceGrpcClient,
+    // 事务消息存储，未配置外部交易服务时为空
+    transactions: Option<TransactionStore>,
+    // 对象存储，用于签名缩略图的下载地址，为空时不填充缩略图
+    oss: Option<Arc<dyn Oss>>,
 }
 
 impl ChatRpcService {
     /// 创建一个新的ChatRpcService实例
-    pub fn new(kafka: FutureProducer, topic: String) -> Self {
-        Self { kafka, topic }
+    pub fn new(
+        kafka: FutureProducer,
+        topics: TenantTopics,
+        payload_format: KafkaPayloadFormat,
+        attachment: AttachmentConfig,
+        cache: Arc<dyn Cache>,
+        scan_tx: Option<mpsc::Sender<ScanTask>>,
+        preview_tx: mpsc::Sender<LinkPreviewTask>,
+        signaling: SignalingDeadline,
+        spill: Option<SpillQueue>,
+        validator: MessageValidator,
+        region: Option<String>,
+        user_client: UserServiceGrpcClient,
+        transactions: Option<TransactionStore>,
+        oss: Option<Arc<dyn Oss>>,
+    ) -> Self {
+        Self {
+            kafka,
+            topics,
+            payload_format,
+            attachment,
+            cache,
+            scan_tx,
+            preview_tx,
+            signaling,
+            spill,
+            validator,
+            region,
+            user_client,
+            transactions,
+            oss,
+        }
     }
     
     /// 启动消息服务
@@ -58,10 +154,35 @@ impl ChatRpcService {
             .create()
             .expect("生产者创建失败");
 
-        // 确保Kafka主题存在，如不存在则创建
-        Self::ensure_topic_exists(&config.kafka.topic, &broker, config.kafka.connect_timeout)
-            .await
-            .expect("主题创建失败");
+        // 确保默认主题和大租户的独立主题存在，如不存在则创建
+        let topics = TenantTopics::new(config.kafka.topic.clone(), &config.kafka.tenant_topics);
+        for topic in topics.topics() {
+            Self::ensure_topic_exists(&topic, &broker, config.kafka.connect_timeout)
+                .await
+                .expect("主题创建失败");
+        }
+
+        // 打开本地暂存队列并启动补发任务
+        let spill = if config.kafka.producer.spill.enabled {
+            let spill = SpillQueue::open(config.kafka.producer.spill.clone())
+                .await
+                .expect("本地暂存队列初始化失败");
+            spill.clone().start(
+                producer.clone(),
+                topics.clone(),
+                config.kafka.producer.payload_format,
+                SignalingDeadline::new(config.signaling.clone()),
+                config.region.local().map(String::from),
+            );
+            Some(spill)
+        } else {
+            None
+        };
+
+        // 未配置服务间认证密钥时拒绝启动，避免RPC端口对外无认证暴露
+        ServiceAuth::global()
+            .ensure_enabled()
+            .expect("服务间认证未配置");
 
         // 向服务注册中心注册消息服务
         utils::register_service(config, Component::MessageServer)
@@ -75,22 +196,140 @@ impl ChatRpcService {
         info!("<chat> RPC服务健康检查已启动");
 
         // 创建日志拦截器
-        // 用于记录和跟踪所有RPC请求
-        let logging_interceptor = LoggingInterceptor::new();
+        // 用于记录和跟踪所有RPC请求，业务服务外层再校验服务间令牌
+        let interceptor = ServiceAuthInterceptor::new(LoggingInterceptor::new());
+
+        // 启动附件扫描任务
+        let cache = cache::cache(config);
+        let notifier = ConversationNotifier::new(
+            cache.clone(),
+            producer.clone(),
+            config.kafka.topic.clone(),
+            config.kafka.producer.payload_format,
+        );
+        let scan_tx = config.attachment.scanner.as_ref().map(|scanner_config| {
+            let (worker, tx) = ScanWorker::new(
+                Arc::new(HttpScanner::new(scanner_config)),
+                cache.clone(),
+                notifier.clone(),
+                GroupMediaIndexer::new(GroupServiceGrpcClient::from_env()),
+            );
+            worker.start();
+            tx
+        });
+
+        // 启动链接预览任务
+        let (preview_worker, preview_tx) = LinkPreviewWorker::new(cache.clone(), notifier);
+        preview_worker.start();
+
+        // 创建历史消息回填服务
+        let backfill = BackfillService::new(
+            Arc::new(MongoHistorySource::new(config).await),
+            cache.clone(),
+            config.backfill.clone(),
+        );
+        let backfill_service =
+            HistoryBackfillServiceServer::with_interceptor(backfill, interceptor.clone());
+
+        // 创建接收盒重建服务
+        let rebuild = RebuildService::new(
+            Arc::new(
+                PgHistory::new(config)
+                    .await
+                    .expect("创建Postgres连接池失败"),
+            ),
+            Arc::new(MongoReceiveBox::new(config).await),
+            cache.clone(),
+            config.receive_box_rebuild.clone(),
+        );
+        let rebuild_service =
+            MailboxRepairServiceServer::with_interceptor(rebuild, interceptor.clone());
+
+        // 创建消息请求服务
+        let message_requests = MessageRequestRpcService::new(
+            config,
+            producer.clone(),
+            topics.clone(),
+            config.kafka.producer.payload_format,
+            config.region.local().map(String::from),
+        )
+        .await;
+        let message_request_service =
+            MessageRequestServiceServer::with_interceptor(message_requests, interceptor.clone());
+
+        // 会话草稿和事务消息按租户存放在各自的数据库集群，
+        // 与Kafka消费端按消息所属租户写入的消息请求和历史消息一致
+        let mongo = MongoRouter::connect(config)
+            .await
+            .expect("MongoDB连接失败");
+
+        // 创建会话草稿服务，并启动草稿持久化任务
+        let draft_store = DraftStore::new(mongo.clone(), cache.clone(), &config.drafts);
+        draft_store.start();
+        let drafts = DraftRpcService::new(draft_store, push_service(config).await, &config.drafts);
+        let draft_service = DraftServiceServer::with_interceptor(drafts, interceptor.clone());
+
+        // Redis降级期间群成员改为查询群组服务
+        let redis = cache::redis_health(config, cache.clone());
+        let member_cache = GroupMemberCache::new(&config.group_member_cache, cache.clone(), redis);
+        member_cache.start();
+        let validator = MessageValidator::new(
+            config.message_limits.clone(),
+            member_cache.clone(),
+            UserServiceGrpcClient::from_env(),
+        );
+
+        // 创建事务消息服务，并启动到期退还任务
+        let transactions = TransactionStore::new(config, mongo, member_cache);
+        let transaction_service = transactions.clone().map(|store| {
+            store.start();
+            TransactionServiceServer::with_interceptor(
+                TransactionRpcService::new(store),
+                interceptor.clone(),
+            )
+        });
 
         // 创建聊天RPC服务实例
-        let chat_rpc = Self::new(producer, config.kafka.topic.clone());
+        let chat_rpc = Self::new(
+            producer,
+            topics,
+            config.kafka.producer.payload_format,
+            config.attachment.clone(),
+            cache,
+            scan_tx,
+            preview_tx,
+            SignalingDeadline::new(config.signaling.clone()),
+            spill,
+            validator,
+            config.region.local().map(String::from),
+            UserServiceGrpcClient::from_env(),
+            transactions,
+            Some(oss::oss(config).await),
+        );
         // 包装服务并添加日志拦截器
-        let service = ChatServiceServer::with_interceptor(chat_rpc, logging_interceptor);
+        let service = ChatServiceServer::with_interceptor(chat_rpc, interceptor);
         info!(
             "<chat> RPC服务已启动，监听地址: {}",
             config.rpc.chat.rpc_server_url()
         );
 
-        // 启动RPC服务器，添加健康检查和聊天服务
+        // 数据库迁移期间可以切换到只读维护模式，拒绝发送消息等写操作
+        let maintenance = MaintenanceMode::new("msg-server", &config.maintenance);
+        maintenance.start(&config.redis);
+
+        // 启动RPC服务器，添加健康检查和聊天服务，请求在携带的租户上下文中处理，
+        // 最外层记录方法级指标和span
         Server::builder()
+            .layer(LoggingLayer)
+            .layer(TenantScopeLayer)
+            .layer(MaintenanceLayer::new(maintenance))
             .add_service(health_service)
             .add_service(service)
+            .add_service(backfill_service)
+            .add_service(rebuild_service)
+            .add_service(message_request_service)
+            .add_service(draft_service)
+            .add_optional_service(transaction_service)
             .serve(config.rpc.chat.rpc_server_url().parse().unwrap())
             .await
             .unwrap();
@@ -136,6 +375,181 @@ impl ChatRpcService {
     }
 }
 
+impl ChatRpcService {
+    /// 文本消息包含链接时投递链接预览任务
+    fn enqueue_link_preview(&self, msg: &Msg) {
+        let is_chat = msg.msg_type == MsgType::SingleMsg as i32
+            || msg.msg_type == MsgType::GroupMsg as i32;
+        if !is_chat || msg.content_type != ContentType::Text as i32 {
+            return;
+        }
+        let Some(url) = extract_url(&String::from_utf8_lossy(&msg.content)) else {
+            return;
+        };
+        let task = LinkPreviewTask {
+            msg: msg.clone(),
+            url,
+        };
+        if let Err(e) = self.preview_tx.try_send(task) {
+            warn!("链接预览队列已满，跳过预览: {}", e);
+        }
+    }
+
+    /// 投递前的校验和内容处理
+    ///
+    /// 校验消息格式和接收者，拒绝已被拦截的附件并写入缩略图，写入名片快照；
+    /// 返回投递后需要扫描的附件
+    async fn prepare(&self, msg: &mut Msg) -> Result<Option<AttachmentDescriptor>, tonic::Status> {
+        // 消息网关不在租户上下文中转发，按消息的租户访问群组服务
+        let tenant_id = Some(msg.tenant_id.clone());
+
+        // 校验消息格式和大小，避免异常消息进入Kafka阻塞消费端
+        with_tenant(tenant_id, self.validator.validate(msg)).await?;
+
+        let is_chat = msg.msg_type == MsgType::SingleMsg as i32
+            || msg.msg_type == MsgType::GroupMsg as i32;
+
+        // 校验附件描述，拒绝已被拦截的附件再次转发
+        let mut attachment = if is_chat {
+            AttachmentDescriptor::from_msg_content(msg.content_type, &msg.content, &self.attachment)?
+        } else {
+            None
+        };
+        if let Some(attachment) = attachment.as_mut() {
+            if let Some(reason) = self.cache.get_attachment_block(&attachment.object_key).await? {
+                return Err(tonic::Status::failed_precondition(format!(
+                    "附件已被拦截: {}",
+                    reason
+                )));
+            }
+            self.fill_thumbnail(msg, attachment).await;
+        }
+
+        // 名片消息按发送者的权限查询名片用户，写入昵称和头像快照
+        if is_chat {
+            if let Some(card) = ContactCard::from_msg_content(msg.content_type, &msg.content)? {
+                self.embed_contact_card(msg, card).await?;
+            }
+        }
+        Ok(attachment)
+    }
+
+    /// 生成服务器ID和发送时间，按配置的格式编码消息，消息头标识编码格式和信令消息的投递期限
+    fn encode(&self, msg: &mut Msg) -> Result<(Vec<u8>, OwnedHeaders), tonic::Status> {
+        // 为特定类型的消息生成服务器ID
+        // 某些系统消息不需要生成新的服务器ID
+        if !(msg.msg_type == MsgType::GroupDismissOrExitReceived as i32
+            || msg.msg_type == MsgType::GroupInvitationReceived as i32
+            || msg.msg_type == MsgType::FriendshipReceived as i32)
+        {
+            // 使用nanoid生成唯一的消息ID
+            msg.server_id = nanoid!();
+        }
+        // 设置消息发送时间为当前时间戳
+        msg.send_time = now_millis();
+
+        let (payload, mut headers) =
+            codec::encode(msg, self.payload_format, self.signaling.deadline(msg))?;
+        if let Some(region) = &self.region {
+            headers = codec::with_origin(headers, region);
+        }
+        Ok((payload, headers))
+    }
+
+    /// 投递成功后异步扫描附件、生成链接预览
+    fn after_delivered(&self, msg: &Msg, attachment: Option<AttachmentDescriptor>) {
+        if let (Some(attachment), Some(scan_tx)) = (attachment, &self.scan_tx) {
+            let task = ScanTask {
+                msg: msg.clone(),
+                attachment,
+            };
+            if let Err(e) = scan_tx.try_send(task) {
+                warn!("附件扫描队列已满，跳过扫描: {}", e);
+            }
+        }
+        self.enqueue_link_preview(msg);
+    }
+
+    /// 将消息写入本地暂存队列，返回错误信息和是否降级接收
+    async fn spill(&self, msg: &Msg) -> (String, bool) {
+        let Some(spill) = &self.spill else {
+            return ("Kafka不可用".to_string(), false);
+        };
+        match spill.push(msg).await {
+            Ok(()) => {
+                warn!("Kafka不可用，消息已暂存到本地等待补发: {}", msg.server_id);
+                (String::new(), true)
+            }
+            Err(e) => {
+                error!("写入本地暂存队列失败: {:?}", e);
+                (e.to_string(), false)
+            }
+        }
+    }
+
+    /// 用名片用户的当前资料覆盖客户端填写的名片快照
+    ///
+    /// 发送者无权查看名片或无法确认权限时拒绝发送，避免绕过名片分享设置
+    async fn embed_contact_card(
+        &self,
+        msg: &mut Msg,
+        mut card: ContactCard,
+    ) -> Result<(), tonic::Status> {
+        let resolved = self
+            .user_client
+            .resolve_contact_card(&msg.send_id, &card.user_id)
+            .await
+            .map_err(|e| match e.downcast::<tonic::Status>() {
+                Ok(status) => status,
+                Err(e) => {
+                    warn!("解析名片 {} 失败: {:?}", card.user_id, e);
+                    tonic::Status::unavailable("暂时无法发送名片")
+                }
+            })?;
+
+        card.nickname = resolved.nickname;
+        card.avatar = resolved.avatar_url;
+        msg.content = serde_json::to_vec(&card)
+            .map_err(|e| tonic::Status::internal(format!("序列化名片失败: {}", e)))?;
+        Ok(())
+    }
+
+    /// 图片缩略图已生成时，将缩略图的签名下载地址和blurhash写入附件描述
+    ///
+    /// 附件存放在私有存储桶中，缩略图和原图一样只能通过有时效的签名地址访问
+    async fn fill_thumbnail(&self, msg: &mut Msg, attachment: &mut AttachmentDescriptor) {
+        if msg.content_type != ContentType::Image as i32 || attachment.thumbnail_url.is_some() {
+            return;
+        }
+        let Some(oss) = &self.oss else {
+            return;
+        };
+        match self.cache.get_image_derivatives(&attachment.object_key).await {
+            Ok(Some(derivatives)) => {
+                let url = match oss
+                    .presigned_url(&derivatives.thumbnail_key, THUMBNAIL_URL_TTL)
+                    .await
+                {
+                    Ok(url) => url,
+                    Err(e) => {
+                        warn!("签名缩略图地址失败: {:?}", e);
+                        return;
+                    }
+                };
+                attachment.thumbnail_url = Some(url);
+                attachment.blurhash = Some(derivatives.blurhash);
+                match serde_json::to_vec(attachment) {
+                    Ok(content) => msg.content = content,
+                    Err(e) => warn!("序列化附件描述失败: {}", e),
+                }
+            }
+            // 缩略图尚未生成，客户端回退为加载原图
+            Ok(None) => {}
+            Err(e) => warn!("查询图片缩略图失败: {:?}", e),
+        }
+    }
+}
+
 #[async_trait]
 impl ChatService for ChatRpcService {
     /// 发送消息到消息队列
@@ -144,48 +558,336 @@ impl ChatService for ChatRpcService {
         &self,
         request: tonic::Request<SendMsgRequest>,
     ) -> Result<tonic::Response<MsgResponse>, tonic::Status> {
+        // 校验过的调用方服务，投票消息只接受群组服务发出
+        let caller = request
+            .extensions()
+            .get::<ServiceClaims>()
+            .map(|claims| claims.iss.clone());
         // 从请求中提取消息
         let mut msg = request
             .into_inner()
             .message
             .ok_or(tonic::Status::invalid_argument("消息为空"))?;
+        check_poll(&msg, caller.as_deref())?;
+        // 消息网关不在租户上下文中转发，按消息的租户访问群组服务和数据库集群
+        let tenant_id = Some(msg.tenant_id.clone());
 
-        // 为特定类型的消息生成服务器ID
-        // 某些系统消息不需要生成新的服务器ID
-        if !(msg.msg_type == MsgType::GroupDismissOrExitReceived as i32
-            || msg.msg_type == MsgType::GroupInvitationReceived as i32
-            || msg.msg_type == MsgType::FriendshipReceived as i32)
-        {
-            // 使用nanoid生成唯一的消息ID
-            msg.server_id = nanoid!();
-        }
-        // 设置消息发送时间为当前时间戳
-        msg.send_time = chrono::Utc::now().timestamp_millis();
-
-        // 将消息序列化为JSON并发送到Kafka
-        let payload = serde_json::to_string(&msg).unwrap();
-        // 让Kafka自动生成消息键
-        let record: FutureRecord<String, String> = FutureRecord::to(&self.topic).payload(&payload);
-
-        info!("将消息发送到Kafka: {:?}", record);
-        // 发送消息到Kafka并处理结果
-        let err = match self.kafka.send(record, Duration::from_secs(0)).await {
-            Ok(_) => String::new(),
-            Err((err, msg)) => {
-                error!(
-                    "发送消息到Kafka失败: {:?}; 原始消息: {:?}",
-                    err, msg
-                );
-                err.to_string()
+        // 校验消息，处理附件和名片
+        let attachment = self.prepare(&mut msg).await?;
+
+        // 事务消息经外部交易服务确认后写入事务ID再投递，事务存放在发送者租户的数据库集群
+        let transaction_id = if msg.content_type == ContentType::Transactional as i32 {
+            let Some(transactions) = &self.transactions else {
+                return Err(tonic::Status::invalid_argument("未启用事务消息"));
+            };
+            Some(with_tenant(tenant_id.clone(), transactions.authorize(&mut msg)).await?)
+        } else {
+            None
+        };
+
+        // 生成服务器ID和发送时间，按配置的格式编码消息
+        let (payload, headers) = self.encode(&mut msg)?;
+        // 暂存队列中还有未补发的消息时直接排队，保证补发期间的消息顺序
+        let (err, degraded) = if self.spill.as_ref().is_some_and(SpillQueue::is_pending) {
+            self.spill(&msg).await
+        } else {
+            // 让Kafka自动生成消息键
+            let record: FutureRecord<String, Vec<u8>> =
+                FutureRecord::to(self.topics.topic(&msg.tenant_id))
+                    .payload(&payload)
+                    .headers(headers);
+
+            info!("将消息发送到Kafka: {:?}", record);
+            // 发送消息到Kafka并处理结果，确定没有写入时写入本地暂存队列
+            match self.kafka.send(record, Duration::from_secs(0)).await {
+                Ok(_) => (String::new(), false),
+                Err((err, _)) if spill::is_undelivered(&err) => {
+                    warn!("发送消息到Kafka失败，消息未写入: {:?}", err);
+                    self.spill(&msg).await
+                }
+                Err((err, msg)) => {
+                    error!(
+                        "发送消息到Kafka失败: {:?}; 原始消息: {:?}",
+                        err, msg
+                    );
+                    (err.to_string(), false)
+                }
             }
         };
 
+        // 投递成功后异步扫描附件、生成链接预览
+        if err.is_empty() {
+            self.after_delivered(&msg, attachment);
+        }
+
+        // 消息未能投递时没有人可以领取，提前退还
+        if let (Some(transaction_id), Some(transactions), false) =
+            (&transaction_id, &self.transactions, err.is_empty())
+        {
+            with_tenant(tenant_id, transactions.abandon(transaction_id)).await;
+        }
+
         // 返回消息响应，包含本地ID、服务器ID、发送时间和错误信息
         return Ok(tonic::Response::new(MsgResponse {
             local_id: msg.local_id,
             server_id: msg.server_id,
             send_time: msg.send_time,
             err,
+            degraded,
         }));
     }
+
+    /// 批量发送消息
+    ///
+    /// 供机器人和系统通知使用：只接受经服务间认证的调用，转发了认证用户的请求只能以该用户的身份发送。
+    /// 整体校验消息数和发送者后按配置的并发数校验每条消息，
+    /// 通过校验的消息一次性写入Kafka生产者队列后统一等待投递结果，逐条返回结果。
+    /// 不支持事务消息
+    async fn send_batch(
+        &self,
+        request: tonic::Request<SendBatchRequest>,
+    ) -> Result<tonic::Response<SendBatchResponse>, tonic::Status> {
+        let (metadata, extensions, request) = request.into_parts();
+        let caller = extensions
+            .get::<ServiceClaims>()
+            .map(|claims| claims.iss.clone())
+            .ok_or_else(|| tonic::Status::unauthenticated("缺少服务间令牌"))?;
+        let mut messages = request.messages;
+        self.validator.check_batch(&messages)?;
+        check_subject(&metadata, &messages[0].send_id)?;
+        info!(
+            "批量发送消息，发送者: {}，数量: {}",
+            messages[0].send_id,
+            messages.len()
+        );
+
+        // 按配置的并发数校验每条消息，未通过的消息记录原因，不影响其他消息
+        let caller = Some(caller.as_str());
+        let mut prepared: Vec<_> = stream::iter(messages.iter_mut().enumerate())
+            .map(|(i, msg)| async move {
+                let result = async {
+                    if msg.content_type == ContentType::Transactional as i32 {
+                        return Err(tonic::Status::invalid_argument("批量发送不支持事务消息"));
+                    }
+                    check_poll(msg, caller)?;
+                    let attachment = self.prepare(msg).await?;
+                    let encoded = self.encode(msg)?;
+                    Ok((attachment, encoded))
+                }
+                .await;
+                (i, result)
+            })
+            .buffer_unordered(self.validator.batch_concurrency())
+            .collect()
+            .await;
+        prepared.sort_by_key(|(i, _)| *i);
+
+        let mut errors: Vec<String> = vec![String::new(); messages.len()];
+        let mut degraded = vec![false; messages.len()];
+        let mut attachments = Vec::with_capacity(messages.len());
+        let mut encoded = Vec::with_capacity(messages.len());
+        for (i, result) in prepared {
+            match result {
+                Ok((attachment, payload)) => {
+                    attachments.push(attachment);
+                    encoded.push(Some(payload));
+                }
+                Err(status) => {
+                    errors[i] = status.message().to_string();
+                    attachments.push(None);
+                    encoded.push(None);
+                }
+            }
+        }
+
+        if self.spill.as_ref().is_some_and(SpillQueue::is_pending) {
+            // 暂存队列中还有未补发的消息时全部排队，保证补发期间的消息顺序
+            for (i, msg) in messages.iter().enumerate() {
+                if encoded[i].is_some() {
+                    (errors[i], degraded[i]) = self.spill(msg).await;
+                }
+            }
+        } else {
+            // 先把全部消息写入生产者队列，由生产者合并成批发送，再统一等待投递结果
+            let mut deliveries = Vec::with_capacity(messages.len());
+            for (i, msg) in messages.iter().enumerate() {
+                let Some((payload, headers)) = encoded[i].take() else {
+                    continue;
+                };
+                let record: FutureRecord<String, Vec<u8>> =
+                    FutureRecord::to(self.topics.topic(&msg.tenant_id))
+                        .payload(&payload)
+                        .headers(headers);
+                match self.kafka.send_result(record) {
+                    Ok(delivery) => deliveries.push((i, delivery)),
+                    Err((err, _)) => errors[i] = err.to_string(),
+                }
+            }
+
+            let (indexes, deliveries): (Vec<_>, Vec<_>) = deliveries.into_iter().unzip();
+            let results = future::join_all(deliveries).await;
+            for (i, result) in indexes.into_iter().zip(results) {
+                (errors[i], degraded[i]) = match result {
+                    Ok(Ok(_)) => (String::new(), false),
+                    Ok(Err((err, _))) if spill::is_undelivered(&err) => {
+                        self.spill(&messages[i]).await
+                    }
+                    Ok(Err((err, _))) => (err.to_string(), false),
+                    Err(_) => ("消息投递已取消".to_string(), false),
+                };
+            }
+        }
+
+        let failed = errors.iter().filter(|err| !err.is_empty()).count();
+        if failed > 0 {
+            warn!("批量发送消息部分失败: {}/{}", failed, messages.len());
+        }
+
+        let results = messages
+            .into_iter()
+            .zip(attachments)
+            .zip(errors.into_iter().zip(degraded))
+            .map(|((msg, attachment), (err, degraded))| {
+                if err.is_empty() {
+                    self.after_delivered(&msg, attachment);
+                }
+                MsgResponse {
+                    local_id: msg.local_id,
+                    server_id: msg.server_id,
+                    send_time: msg.send_time,
+                    err,
+                    degraded,
+                }
+            })
+            .collect();
+
+        Ok(tonic::Response::new(SendBatchResponse { results }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cache::MemoryCache;
+    use common::config::{
+        GroupMemberCacheConfig, MessageLimitsConfig, SignalingConfig, TenantTopicsConfig,
+    };
+    use common::dependency_health::DependencyHealth;
+    use common::grpc::subject::SUBJECT_METADATA_KEY;
+
+    fn service() -> ChatRpcService {
+        // 生产者在首次发送时才连接代理，校验失败的消息不会写入Kafka
+        let kafka: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", "127.0.0.1:9")
+            .create()
+            .unwrap();
+        let cache: Arc<dyn Cache> = Arc::new(MemoryCache::new());
+        let group_members = GroupMemberCache::new(
+            &GroupMemberCacheConfig::default(),
+            cache.clone(),
+            DependencyHealth::always_healthy("redis"),
+        );
+        let (preview_tx, _) = mpsc::channel(1);
+        ChatRpcService::new(
+            kafka,
+            TenantTopics::new("im-msg".to_string(), &TenantTopicsConfig::default()),
+            KafkaPayloadFormat::default(),
+            AttachmentConfig::default(),
+            cache,
+            None,
+            preview_tx,
+            SignalingDeadline::new(SignalingConfig::default()),
+            None,
+            MessageValidator::new(
+                MessageLimitsConfig::default(),
+                group_members,
+                UserServiceGrpcClient::from_env(),
+            ),
+            None,
+            UserServiceGrpcClient::from_env(),
+            None,
+            None,
+        )
+    }
+
+    fn claims(iss: &str) -> ServiceClaims {
+        ServiceClaims {
+            iss: iss.to_string(),
+            aud: "chrisim-internal".to_string(),
+            iat: 0,
+            exp: 0,
+            sub: None,
+            tenant: None,
+        }
+    }
+
+    fn transactional(local_id: &str) -> Msg {
+        Msg {
+            local_id: local_id.to_string(),
+            send_id: "u1".to_string(),
+            receiver_id: "u2".to_string(),
+            msg_type: MsgType::SingleMsg as i32,
+            content_type: ContentType::Transactional as i32,
+            ..Default::default()
+        }
+    }
+
+    fn batch(
+        messages: Vec<Msg>,
+        claims: Option<ServiceClaims>,
+        subject: Option<&str>,
+    ) -> tonic::Request<SendBatchRequest> {
+        let mut request = tonic::Request::new(SendBatchRequest { messages });
+        if let Some(claims) = claims {
+            request.extensions_mut().insert(claims);
+        }
+        if let Some(subject) = subject {
+            request
+                .metadata_mut()
+                .insert(SUBJECT_METADATA_KEY, subject.parse().unwrap());
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_requires_service_caller() {
+        let service = service();
+        let status = service
+            .send_batch(batch(vec![transactional("1")], None, None))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_binds_sender_to_subject() {
+        let service = service();
+        let status = service
+            .send_batch(batch(
+                vec![transactional("1")],
+                Some(claims("api-gateway")),
+                Some("u2"),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_send_batch_reports_each_message_in_order() {
+        let service = service();
+        let messages = (0..40).map(|i| transactional(&i.to_string())).collect();
+        let response = service
+            .send_batch(batch(messages, Some(claims("bot-service")), Some("u1")))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.results.len(), 40);
+        for (i, result) in response.results.iter().enumerate() {
+            assert_eq!(result.local_id, i.to_string());
+            assert_eq!(result.err, "批量发送不支持事务消息");
+        }
+    }
 }
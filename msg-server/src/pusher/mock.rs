@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use common::{
+    error::Error,
+    message::{GroupMemSeq, Msg},
+};
+use tonic::async_trait;
+
+use super::{GroupPushResult, Pusher};
+
+/// 记录推送内容的推送实现，供单元测试使用
+///
+/// 默认所有成员都在线，可以通过 [`RecordingPusher::set_offline`] 指定离线成员，
+/// 通过 [`RecordingPusher::set_failing`] 模拟网关推送失败
+#[derive(Debug, Clone, Default)]
+pub struct RecordingPusher {
+    single: Arc<Mutex<Vec<Msg>>>,
+    group: Arc<Mutex<Vec<(Msg, Vec<GroupMemSeq>)>>>,
+    retried: Arc<Mutex<Vec<(Msg, Vec<GroupMemSeq>)>>>,
+    offline: Arc<Mutex<HashSet<String>>>,
+    failing: Arc<AtomicBool>,
+}
+
+impl RecordingPusher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置不在线的成员，群聊推送时计入离线成员
+    pub fn set_offline(&self, user_ids: &[&str]) {
+        *self.offline.lock().unwrap() = user_ids.iter().map(|id| id.to_string()).collect();
+    }
+
+    /// 设置后单聊推送返回错误，群聊推送的在线成员全部按失败处理，失败的网关为 [`RecordingPusher::GATEWAY`]
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::Relaxed);
+    }
+
+    /// 已推送的单聊消息
+    pub fn single_msgs(&self) -> Vec<Msg> {
+        self.single.lock().unwrap().clone()
+    }
+
+    /// 已推送的群聊消息和推送时的成员序列号
+    pub fn group_msgs(&self) -> Vec<(Msg, Vec<GroupMemSeq>)> {
+        self.group.lock().unwrap().clone()
+    }
+
+    /// 重试推送的群聊消息和重试的成员
+    pub fn retried_msgs(&self) -> Vec<(Msg, Vec<GroupMemSeq>)> {
+        self.retried.lock().unwrap().clone()
+    }
+
+    /// 清空推送记录
+    pub fn clear(&self) {
+        self.single.lock().unwrap().clear();
+        self.group.lock().unwrap().clear();
+        self.retried.lock().unwrap().clear();
+    }
+
+    /// 模拟的网关地址
+    pub const GATEWAY: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+
+    /// 按当前设置汇总群聊推送结果
+    fn result(&self, members: Vec<GroupMemSeq>) -> GroupPushResult {
+        if self.failing.load(Ordering::Relaxed) {
+            return GroupPushResult::collect(members, &HashSet::new(), vec![Self::GATEWAY]);
+        }
+        let delivered: HashSet<String> = {
+            let offline = self.offline.lock().unwrap();
+            members
+                .iter()
+                .filter(|member| !offline.contains(&member.mem_id))
+                .map(|member| member.mem_id.clone())
+                .collect()
+        };
+        GroupPushResult::collect(members, &delivered, Vec::new())
+    }
+}
+
+#[async_trait]
+impl Pusher for RecordingPusher {
+    async fn push_single_msg(&self, msg: Msg) -> Result<(), Error> {
+        if self.failing.load(Ordering::Relaxed) {
+            return Err(Error::Internal("模拟推送失败".to_string()));
+        }
+        self.single.lock().unwrap().push(msg);
+        Ok(())
+    }
+
+    async fn push_group_msg(
+        &self,
+        msg: Msg,
+        members: Vec<GroupMemSeq>,
+    ) -> Result<GroupPushResult, Error> {
+        self.group.lock().unwrap().push((msg, members.clone()));
+        Ok(self.result(members))
+    }
+
+    async fn retry_group_msg(
+        &self,
+        msg: Msg,
+        members: Vec<GroupMemSeq>,
+        _gateways: Vec<SocketAddr>,
+    ) -> Result<GroupPushResult, Error> {
+        self.retried.lock().unwrap().push((msg, members.clone()));
+        Ok(self.result(members))
+    }
+}
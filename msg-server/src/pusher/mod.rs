@@ -1,4 +1,4 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{collections::HashSet, fmt::Debug, net::SocketAddr, sync::Arc};
 
 use common::{
     config::AppConfig,
@@ -7,14 +7,106 @@ use common::{
 };
 use tonic::async_trait;
 
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+mod offline;
 mod service;
+mod stream;
+pub mod template;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::RecordingPusher;
+pub use offline::OfflinePusher;
+pub use template::{PrivacyMode, PushPayload, PushRecipient, PushTemplates};
+
+/// 群聊消息按成员统计的推送结果
+#[derive(Debug, Clone, Default)]
+pub struct GroupPushResult {
+    /// 已送达的成员
+    pub delivered: Vec<String>,
+    /// 不在任何网关在线的成员，上线后通过收件箱同步
+    pub offline: Vec<String>,
+    /// 可能连接在推送失败的网关上的成员，需要重新推送
+    pub failed: Vec<GroupMemSeq>,
+    /// 推送失败的网关，重试时只向这些网关推送
+    pub failed_gateways: Vec<SocketAddr>,
+}
+
+impl GroupPushResult {
+    /// 根据各网关返回的送达成员汇总结果
+    ///
+    /// 无法得知成员连接在哪个网关，只要有网关推送失败，未送达的成员都按失败处理
+    pub fn collect(
+        members: Vec<GroupMemSeq>,
+        delivered: &HashSet<String>,
+        failed_gateways: Vec<SocketAddr>,
+    ) -> Self {
+        let mut result = Self {
+            failed_gateways,
+            ..Default::default()
+        };
+        for member in members {
+            if delivered.contains(&member.mem_id) {
+                result.delivered.push(member.mem_id);
+            } else if !result.failed_gateways.is_empty() {
+                result.failed.push(member);
+            } else {
+                result.offline.push(member.mem_id);
+            }
+        }
+        result
+    }
+}
 
 #[async_trait]
 pub trait Pusher: Send + Sync + Debug {
     async fn push_single_msg(&self, msg: Msg) -> Result<(), Error>;
-    async fn push_group_msg(&self, msg: Msg, members: Vec<GroupMemSeq>) -> Result<(), Error>;
+    async fn push_group_msg(
+        &self,
+        msg: Msg,
+        members: Vec<GroupMemSeq>,
+    ) -> Result<GroupPushResult, Error>;
+
+    /// 只向指定的网关重新推送群聊消息，不再转发给发送者的其他设备
+    ///
+    /// 已经下线的网关上的成员会重新连接并通过收件箱同步，按离线处理
+    async fn retry_group_msg(
+        &self,
+        msg: Msg,
+        members: Vec<GroupMemSeq>,
+        gateways: Vec<SocketAddr>,
+    ) -> Result<GroupPushResult, Error>;
 }
 
 pub async fn push_service(config: &AppConfig) -> Arc<dyn Pusher> {
     Arc::new(service::PusherService::new(config).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: &str) -> GroupMemSeq {
+        GroupMemSeq {
+            mem_id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collect_group_push_result() {
+        let members = vec![member("a"), member("b"), member("c")];
+        let delivered: HashSet<String> = ["a".to_string()].into_iter().collect();
+
+        let result = GroupPushResult::collect(members.clone(), &delivered, Vec::new());
+        assert_eq!(result.delivered, vec!["a"]);
+        assert_eq!(result.offline, vec!["b", "c"]);
+        assert!(result.failed.is_empty());
+
+        let gateway: SocketAddr = "127.0.0.1:8000".parse().unwrap();
+        let result = GroupPushResult::collect(members, &delivered, vec![gateway]);
+        assert!(result.offline.is_empty());
+        assert_eq!(result.failed.len(), 2);
+        assert_eq!(result.failed_gateways, vec![gateway]);
+    }
+}
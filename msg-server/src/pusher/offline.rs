@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use cache::Cache;
+use common::config::PushConfig;
+use common::grpc_client::GroupServiceGrpcClient;
+use common::message::Msg;
+
+use super::template::{PushPayload, PushRecipient, PushTemplates};
+
+/// 推送网关请求
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OfflinePushRequest<'a> {
+    user_id: &'a str,
+    tenant_id: &'a str,
+    msg_id: &'a str,
+    title: &'a str,
+    body: &'a str,
+}
+
+/// 离线推送
+///
+/// 接收者不在任何网关在线时，按消息类型和接收者语言渲染推送文案，通过HTTP调用推送网关。
+/// 请求: `POST {endpoint}`，body 为 `{"userId","tenantId","msgId","title","body"}`，返回2xx即视为发送成功；
+/// 离线推送只是提醒，发送失败只记录日志，消息仍通过收件箱同步
+pub struct OfflinePusher {
+    templates: PushTemplates,
+    client: reqwest::Client,
+    endpoint: String,
+    cache: Arc<dyn Cache>,
+    groups: GroupServiceGrpcClient,
+}
+
+impl OfflinePusher {
+    /// 未配置推送网关时返回 `None`
+    pub fn new(config: &PushConfig, cache: Arc<dyn Cache>) -> Option<Self> {
+        let endpoint = config.endpoint.clone().filter(|e| !e.is_empty())?;
+        let timeout_ms = if config.timeout_ms == 0 {
+            5000
+        } else {
+            config.timeout_ms
+        };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(timeout_ms))
+            .build()
+            .expect("创建推送网关HTTP客户端失败");
+        Some(Self {
+            templates: PushTemplates::new(config),
+            client,
+            endpoint,
+            cache,
+            groups: GroupServiceGrpcClient::from_env(),
+        })
+    }
+
+    /// 单聊消息的接收者不在线时发送离线推送
+    pub async fn push_single(&self, msg: &Msg) {
+        let recipient = recipient(msg);
+        // 不需要离线推送的消息类型不查询在线状态
+        let Some(payload) = self.templates.render(msg, &recipient, None) else {
+            return;
+        };
+        let receiver = vec![msg.receiver_id.clone()];
+        match self.cache.online_status(&receiver).await {
+            Ok(status) if status.first().copied().unwrap_or(false) => {}
+            Ok(_) => self.send(&msg.receiver_id, msg, &payload).await,
+            Err(e) => warn!(
+                "查询用户 {} 在线状态失败，不发送离线推送: {:?}",
+                msg.receiver_id, e
+            ),
+        }
+    }
+
+    /// 向群聊消息的离线成员发送离线推送，标题使用群名称
+    pub async fn push_group(&self, msg: &Msg, offline: &[String]) {
+        if offline.is_empty() {
+            return;
+        }
+        let group_name = match self.groups.get_group(&msg.group_id).await {
+            Ok(response) => response.group.map(|group| group.name),
+            Err(e) => {
+                warn!(
+                    "查询群组 {} 名称失败，推送标题使用群ID: {}",
+                    msg.group_id, e
+                );
+                None
+            }
+        };
+        let recipient = recipient(msg);
+        let Some(payload) = self
+            .templates
+            .render(msg, &recipient, group_name.as_deref())
+        else {
+            return;
+        };
+        for user_id in offline.iter().filter(|id| **id != msg.send_id) {
+            self.send(user_id, msg, &payload).await;
+        }
+    }
+
+    async fn send(&self, user_id: &str, msg: &Msg, payload: &PushPayload) {
+        let result = self
+            .client
+            .post(&self.endpoint)
+            .json(&OfflinePushRequest {
+                user_id,
+                tenant_id: &msg.tenant_id,
+                msg_id: &msg.server_id,
+                title: &payload.title,
+                body: &payload.body,
+            })
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match result {
+            Ok(_) => debug!("已向用户 {} 发送消息 {} 的离线推送", user_id, msg.server_id),
+            Err(e) => warn!("向用户 {} 发送离线推送失败: {}", user_id, e),
+        }
+    }
+}
+
+/// 接收者的语言和隐私偏好尚未保存，使用默认语言并显示内容，按消息所属租户应用租户模板
+fn recipient(msg: &Msg) -> PushRecipient {
+    PushRecipient {
+        tenant_id: msg.tenant_id.clone(),
+        ..Default::default()
+    }
+}
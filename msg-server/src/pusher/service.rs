@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -6,26 +7,36 @@ use async_trait::async_trait;
 use common::error::Error;
 use dashmap::DashMap;
 use tokio::sync::mpsc;
-use tonic::transport::{Channel, Endpoint};
+use tonic::transport::Endpoint;
 use tower::discover::Change;
 use tracing::{debug, error};
 
 use common::config::AppConfig;
+use common::grpc::service_auth::{authorized, AuthorizedChannel};
 use common::message::msg_service_client::MsgServiceClient;
 use common::message::{GroupMemSeq, Msg, SendGroupMsgRequest, SendMsgRequest};
+use common::time_sync::now_millis;
+use prost::Message;
 
-use super::Pusher;
+use crate::signaling::{record_expired, SignalingDeadline};
+
+use super::stream::GatewayStream;
+use super::{GroupPushResult, Pusher};
 
 /// 消息推送服务的具体实现
 /// 负责与多个WebSocket网关通信，将消息推送给在线客户端
 #[derive(Debug)]
 pub struct PusherService {
     // WebSocket RPC客户端列表，以网关的网络地址为键
-    ws_rpc_list: Arc<DashMap<SocketAddr, MsgServiceClient<Channel>>>,
+    ws_rpc_list: Arc<DashMap<SocketAddr, MsgServiceClient<AuthorizedChannel>>>,
+    // 到各网关的长连接推送流，不可用时回退到一元调用
+    streams: Arc<DashMap<SocketAddr, GatewayStream>>,
     // 服务中心客户端，用于查询WebSocket网关服务
     service_center: ServiceClient,
     // WebSocket服务名称
     sub_svr_name: String,
+    // 信令消息投递期限，过期的消息不再推送
+    signaling: SignalingDeadline,
 }
 
 impl PusherService {
@@ -37,6 +48,8 @@ impl PusherService {
         // 创建WebSocket RPC客户端映射表
         let ws_rpc_list = Arc::new(DashMap::new());
         let cloned_list = ws_rpc_list.clone();
+        let streams = Arc::new(DashMap::new());
+        let cloned_streams = streams.clone();
         // 创建服务变更通知通道
         let (tx, mut rx) = mpsc::channel::<Change<SocketAddr, Endpoint>>(100);
 
@@ -47,9 +60,17 @@ impl PusherService {
                 match change {
                     // 添加新的WebSocket服务
                     Change::Insert(service_id, client) => {
-                        match MsgServiceClient::connect(client).await {
-                            Ok(client) => {
-                                cloned_list.insert(service_id, client);
+                        match client.connect().await {
+                            Ok(channel) => {
+                                cloned_list.insert(
+                                    service_id,
+                                    MsgServiceClient::new(authorized(channel.clone())),
+                                );
+                                Self::replace_stream(
+                                    &cloned_streams,
+                                    service_id,
+                                    GatewayStream::connect(service_id, channel),
+                                );
                             }
                             Err(err) => {
                                 error!("连接WebSocket服务失败: {:?}", err);
@@ -59,6 +80,9 @@ impl PusherService {
                     // 移除已下线的WebSocket服务
                     Change::Remove(service_id) => {
                         cloned_list.remove(&service_id);
+                        if let Some((_, stream)) = cloned_streams.remove(&service_id) {
+                            stream.close();
+                        }
                     }
                 }
             }
@@ -80,8 +104,10 @@ impl PusherService {
             
         Self {
             ws_rpc_list,
+            streams,
             service_center,
             sub_svr_name,
+            signaling: SignalingDeadline::new(config.signaling.clone()),
         }
     }
 
@@ -110,16 +136,112 @@ impl PusherService {
                 }
             };
             // 创建RPC客户端
-            let ws = match MsgServiceClient::connect(endpoint).await {
-                Ok(client) => client,
+            let channel = match endpoint.connect().await {
+                Ok(channel) => channel,
                 Err(err) => {
                     error!("连接WebSocket服务失败: {:?}", err);
                     continue;
                 }
             };
-            // 添加到客户端列表
-            self.ws_rpc_list.insert(socket, ws);
+            // 添加到客户端列表，并建立推送流
+            self.ws_rpc_list
+                .insert(socket, MsgServiceClient::new(authorized(channel.clone())));
+            Self::replace_stream(&self.streams, socket, GatewayStream::connect(socket, channel));
+        }
+    }
+
+    /// 替换网关的推送流，关闭旧的推送流
+    fn replace_stream(
+        streams: &DashMap<SocketAddr, GatewayStream>,
+        service_id: SocketAddr,
+        stream: GatewayStream,
+    ) {
+        if let Some(old) = streams.insert(service_id, stream) {
+            old.close();
+        }
+    }
+
+    /// 移除推送失败的网关
+    fn remove_gateway(&self, service_id: &SocketAddr) {
+        self.ws_rpc_list.remove(service_id);
+        if let Some((_, stream)) = self.streams.remove(service_id) {
+            stream.close();
+        }
+    }
+
+    /// 向单个网关推送单聊消息，推送流可用时走推送流，否则回退到一元调用
+    async fn push_single_to_gateway(
+        mut client: MsgServiceClient<AuthorizedChannel>,
+        stream: Option<GatewayStream>,
+        request: SendMsgRequest,
+    ) -> Result<(), Error> {
+        if let Some(stream) = stream {
+            if let Some(result) = stream.send(false, request.encode_to_vec()).await {
+                return result.map(|_| ());
+            }
+        }
+        client
+            .send_msg_to_user(request)
+            .await
+            .map(|_| ())
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// 向单个网关推送群聊消息，返回本网关送达的成员
+    async fn push_group_to_gateway(
+        mut client: MsgServiceClient<AuthorizedChannel>,
+        stream: Option<GatewayStream>,
+        request: SendGroupMsgRequest,
+    ) -> Result<Vec<String>, Error> {
+        if let Some(stream) = stream {
+            if let Some(result) = stream.send(true, request.encode_to_vec()).await {
+                return result.map(|ack| ack.delivered);
+            }
+        }
+        client
+            .send_group_msg_to_user(request)
+            .await
+            .map(|response| response.into_inner().delivered)
+            .map_err(|e| Error::Internal(e.to_string()))
+    }
+
+    /// 向指定的网关推送群聊消息，汇总每个成员的送达情况，并从列表中移除推送失败的网关
+    ///
+    /// 没有可用网关时不会有成员在线，全部按离线处理
+    async fn push_group_to(
+        &self,
+        request: SendGroupMsgRequest,
+        gateways: Vec<(SocketAddr, MsgServiceClient<AuthorizedChannel>)>,
+    ) -> GroupPushResult {
+        let (tx, mut rx) = mpsc::channel(gateways.len().max(1));
+        for (service_id, client) in gateways {
+            let tx = tx.clone();
+            let stream = self.streams.get(&service_id).map(|s| s.clone());
+            let request = request.clone();
+            // 为每个网关创建单独的发送任务
+            tokio::spawn(async move {
+                let result = Self::push_group_to_gateway(client, stream, request)
+                    .await
+                    .map_err(|err| (service_id, err));
+                let _ = tx.send(result).await;
+            });
+        }
+        drop(tx);
+
+        let mut delivered = HashSet::new();
+        let mut failed_gateways = Vec::new();
+        while let Some(result) = rx.recv().await {
+            match result {
+                Ok(members) => delivered.extend(members),
+                Err((service_id, err)) => {
+                    self.remove_gateway(&service_id);
+                    error!("向网关 {} 推送群聊消息失败: {}", service_id, err);
+                    failed_gateways.push(service_id);
+                }
+            }
         }
+
+        GroupPushResult::collect(request.members, &delivered, failed_gateways)
     }
 }
 
@@ -130,6 +252,12 @@ impl Pusher for PusherService {
     async fn push_single_msg(&self, request: Msg) -> Result<(), Error> {
         debug!("推送单聊消息请求: {:?}", request);
 
+        // 过期的信令消息不再推送
+        if self.signaling.is_expired(&request, now_millis()) {
+            record_expired("pusher", &request);
+            return Ok(());
+        }
+
         // 获取WebSocket RPC客户端列表
         let ws_rpc = self.ws_rpc_list.clone();
         // 如果列表为空，则从服务中心查询WebSocket服务
@@ -154,12 +282,13 @@ impl Pusher for PusherService {
         for v in ws_rpc.iter() {
             let tx = tx.clone();
             let service_id = *v.key();
-            let mut v = v.clone();
+            let client = v.clone();
+            let stream = self.streams.get(&service_id).map(|s| s.clone());
             let request = request.clone();
             // 为每个网关创建单独的发送任务
             tokio::spawn(async move {
-                if let Err(err) = v.send_msg_to_user(request).await {
-                    tx.send((service_id, err)).await.unwrap();
+                if let Err(err) = Self::push_single_to_gateway(client, stream, request).await {
+                    let _ = tx.send((service_id, err)).await;
                 };
             });
         }
@@ -170,16 +299,26 @@ impl Pusher for PusherService {
         // 处理发送错误，从列表中移除失败的服务
         // TODO: 需要更新客户端列表并处理错误
         while let Some((service_id, err)) = rx.recv().await {
-            ws_rpc.remove(&service_id);
+            self.remove_gateway(&service_id);
             error!("向网关 {} 推送消息失败: {}", service_id, err);
         }
         Ok(())
     }
 
     /// 推送群聊消息
-    /// 将消息发送到所有WebSocket网关，由网关转发给群成员
-    async fn push_group_msg(&self, msg: Msg, members: Vec<GroupMemSeq>) -> Result<(), Error> {
+    /// 将消息发送到所有WebSocket网关，由网关转发给群成员，并汇总每个成员的送达情况
+    async fn push_group_msg(
+        &self,
+        msg: Msg,
+        members: Vec<GroupMemSeq>,
+    ) -> Result<GroupPushResult, Error> {
         debug!("推送群聊消息请求: {:?}, 成员: {:?}", msg, members);
+
+        // 过期的信令消息不再推送，也不需要重试
+        if self.signaling.is_expired(&msg, now_millis()) {
+            record_expired("pusher", &msg);
+            return Ok(GroupPushResult::default());
+        }
         
         // 获取WebSocket RPC客户端列表
         let ws_rpc = self.ws_rpc_list.clone();
@@ -194,41 +333,45 @@ impl Pusher for PusherService {
             self.handle_sub_services(list).await;
         }
 
-        // 构建群聊消息请求
+        // 构建群聊消息请求，向所有网关推送
         let request = SendGroupMsgRequest {
             message: Some(msg),
             members,
+            skip_sender: false,
         };
-        // 创建结果收集通道
-        let (tx, mut rx) = mpsc::channel(ws_rpc.len());
-        
-        // 异步方式向所有WebSocket网关发送群聊消息
-        for v in ws_rpc.iter() {
-            let tx = tx.clone();
-            let service_id = *v.key();
-            let mut v = v.clone();
-            let request = request.clone();
-            // 为每个网关创建单独的发送任务
-            tokio::spawn(async move {
-                match v.send_group_msg_to_user(request).await {
-                    Ok(_) => {
-                        tx.send(Ok(())).await.unwrap();
-                    }
-                    Err(err) => {
-                        tx.send(Err((service_id, err))).await.unwrap();
-                    }
-                };
-            });
-        }
-        // 关闭发送端
-        drop(tx);
-        
-        // 处理发送错误，从列表中移除失败的服务
-        // TODO: 需要更新客户端列表
-        while let Some(Err((service_id, err))) = rx.recv().await {
-            ws_rpc.remove(&service_id);
-            error!("向网关 {} 推送群聊消息失败: {}", service_id, err);
+        let gateways = ws_rpc
+            .iter()
+            .map(|v| (*v.key(), v.value().clone()))
+            .collect();
+        Ok(self.push_group_to(request, gateways).await)
+    }
+
+    /// 重新推送群聊消息
+    /// 只发送到上次推送失败且仍然可用的网关，网关不再转发给发送者的其他设备
+    async fn retry_group_msg(
+        &self,
+        msg: Msg,
+        members: Vec<GroupMemSeq>,
+        gateways: Vec<SocketAddr>,
+    ) -> Result<GroupPushResult, Error> {
+        if self.signaling.is_expired(&msg, now_millis()) {
+            record_expired("pusher", &msg);
+            return Ok(GroupPushResult::default());
         }
-        Ok(())
+
+        let request = SendGroupMsgRequest {
+            message: Some(msg),
+            members,
+            skip_sender: true,
+        };
+        let gateways = gateways
+            .into_iter()
+            .filter_map(|service_id| {
+                self.ws_rpc_list
+                    .get(&service_id)
+                    .map(|client| (service_id, client.clone()))
+            })
+            .collect();
+        Ok(self.push_group_to(request, gateways).await)
     }
 }
@@ -0,0 +1,192 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use common::error::Error;
+use common::grpc::service_auth::{authorized, AuthorizedChannel};
+use common::proto::push_stream::push_stream_service_client::PushStreamServiceClient;
+use common::proto::push_stream::{PushAck, PushFrame};
+use dashmap::DashMap;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tokio::time::timeout;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::Channel;
+use tonic::Streaming;
+use tracing::{debug, info, warn};
+
+/// 等待额度和网关确认的超时时间
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 首次重连间隔，之后按指数退避
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 重连间隔上限
+const MAX_RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 发送队列容量，实际在途帧数由网关发放的额度限制
+const FRAME_BUFFER: usize = 1024;
+
+/// 到单个网关实例的长连接推送流
+///
+/// 断开后在后台按指数退避重连，未连接期间 [`GatewayStream::send`] 返回None，
+/// 由调用方回退到一元调用
+#[derive(Debug, Clone)]
+pub struct GatewayStream {
+    inner: Arc<StreamState>,
+}
+
+#[derive(Debug)]
+struct StreamState {
+    addr: SocketAddr,
+    // 当前连接，断开期间为None
+    conn: RwLock<Option<Connection>>,
+    next_id: AtomicU64,
+    // 网关下线后不再重连
+    closed: AtomicBool,
+}
+
+#[derive(Debug, Clone)]
+struct Connection {
+    tx: mpsc::Sender<PushFrame>,
+    // 网关发放的剩余额度
+    credits: Arc<Semaphore>,
+    // 等待确认的帧
+    pending: Arc<DashMap<u64, oneshot::Sender<PushAck>>>,
+}
+
+impl Connection {
+    /// 连接断开时让所有等待额度和确认的发送方立即失败
+    fn shutdown(&self) {
+        self.credits.close();
+        self.pending.clear();
+    }
+}
+
+impl GatewayStream {
+    /// 建立到网关的推送流，并在后台维持连接
+    pub fn connect(addr: SocketAddr, channel: Channel) -> Self {
+        let inner = Arc::new(StreamState {
+            addr,
+            conn: RwLock::new(None),
+            next_id: AtomicU64::new(1),
+            closed: AtomicBool::new(false),
+        });
+        tokio::spawn(Self::run(
+            inner.clone(),
+            PushStreamServiceClient::new(authorized(channel)),
+        ));
+        Self { inner }
+    }
+
+    /// 网关下线，关闭推送流
+    pub fn close(&self) {
+        self.inner.closed.store(true, Ordering::Relaxed);
+        if let Some(conn) = self.inner.conn.write().unwrap().take() {
+            conn.shutdown();
+        }
+    }
+
+    /// 发送一帧并等待网关确认
+    ///
+    /// 推送流未连接时返回None；发送后超时或断开时无法确定网关是否已处理，按失败返回
+    pub async fn send(&self, group: bool, payload: Vec<u8>) -> Option<Result<PushAck, Error>> {
+        let conn = self.inner.conn.read().unwrap().clone()?;
+
+        // 额度由网关在确认中归还，这里只消耗不释放
+        match timeout(ACK_TIMEOUT, conn.credits.acquire()).await {
+            Ok(Ok(permit)) => permit.forget(),
+            Ok(Err(_)) => return Some(Err(Error::Internal("推送流已断开".to_string()))),
+            Err(_) => return Some(Err(Error::Internal("等待推送额度超时".to_string()))),
+        }
+
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let (ack_tx, ack_rx) = oneshot::channel();
+        conn.pending.insert(id, ack_tx);
+
+        if conn.tx.send(PushFrame { id, group, payload }).await.is_err() {
+            conn.pending.remove(&id);
+            return Some(Err(Error::Internal("推送流已断开".to_string())));
+        }
+
+        let result = match timeout(ACK_TIMEOUT, ack_rx).await {
+            Ok(Ok(ack)) if ack.error.is_empty() => Ok(ack),
+            Ok(Ok(ack)) => Err(Error::Internal(ack.error)),
+            Ok(Err(_)) => Err(Error::Internal("推送流已断开".to_string())),
+            Err(_) => {
+                conn.pending.remove(&id);
+                Err(Error::Internal("等待网关确认超时".to_string()))
+            }
+        };
+        Some(result)
+    }
+
+    /// 维持推送流连接，断开后按指数退避重连
+    async fn run(
+        state: Arc<StreamState>,
+        mut client: PushStreamServiceClient<AuthorizedChannel>,
+    ) {
+        let mut interval = RECONNECT_INTERVAL;
+
+        while !state.closed.load(Ordering::Relaxed) {
+            match Self::open(&mut client).await {
+                Ok((conn, mut inbound)) => {
+                    info!("到网关 {} 的推送流已建立", state.addr);
+                    *state.conn.write().unwrap() = Some(conn.clone());
+                    interval = RECONNECT_INTERVAL;
+
+                    loop {
+                        match inbound.message().await {
+                            Ok(Some(ack)) => Self::handle_ack(&conn, ack),
+                            Ok(None) => break,
+                            Err(e) => {
+                                warn!("到网关 {} 的推送流异常: {}", state.addr, e);
+                                break;
+                            }
+                        }
+                    }
+
+                    if let Some(conn) = state.conn.write().unwrap().take() {
+                        conn.shutdown();
+                    }
+                    warn!("到网关 {} 的推送流已断开", state.addr);
+                }
+                Err(e) => {
+                    debug!("建立到网关 {} 的推送流失败: {}", state.addr, e);
+                }
+            }
+
+            if state.closed.load(Ordering::Relaxed) {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+            interval = (interval * 2).min(MAX_RECONNECT_INTERVAL);
+        }
+    }
+
+    async fn open(
+        client: &mut PushStreamServiceClient<AuthorizedChannel>,
+    ) -> Result<(Connection, Streaming<PushAck>), tonic::Status> {
+        let (tx, rx) = mpsc::channel(FRAME_BUFFER);
+        let inbound = client.push(ReceiverStream::new(rx)).await?.into_inner();
+
+        // 初始额度为0，等待网关发放
+        let conn = Connection {
+            tx,
+            credits: Arc::new(Semaphore::new(0)),
+            pending: Arc::new(DashMap::new()),
+        };
+        Ok((conn, inbound))
+    }
+
+    fn handle_ack(conn: &Connection, ack: PushAck) {
+        if ack.credits > 0 {
+            conn.credits.add_permits(ack.credits as usize);
+        }
+        if ack.id != 0 {
+            if let Some((_, waiter)) = conn.pending.remove(&ack.id) {
+                let _ = waiter.send(ack);
+            }
+        }
+    }
+}
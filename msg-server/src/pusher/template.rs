@@ -0,0 +1,415 @@
+//! 离线推送文案模板
+//!
+//! 离线推送的标题和正文（如 "Alice: [图片]"）统一在服务端按消息类型和接收者语言生成，
+//! 客户端不再拼接文案。支持隐私模式（隐藏内容 / 隐藏发送者和内容），
+//! 租户可以在配置中按语言覆盖任意模板键。
+//!
+//! 模板中可用的占位符：`{sender}` 发送者昵称，`{group}` 群名称，`{content}` 文本内容预览。
+
+use std::collections::HashMap;
+
+use common::config::PushConfig;
+use common::message::{ContentType, Msg, MsgType};
+use serde::{Deserialize, Serialize};
+
+/// 内置默认语言
+pub const DEFAULT_LOCALE: &str = "zh-cn";
+
+/// 文本消息预览的最大字符数
+const MAX_PREVIEW_CHARS: usize = 60;
+
+/// 内置中文模板
+const ZH_CN_TEMPLATES: &[(&str, &str)] = &[
+    ("title.single", "{sender}"),
+    ("title.group", "{group}"),
+    ("title.hidden", "新消息"),
+    ("body.group", "{sender}: {content}"),
+    ("body.text", "{content}"),
+    ("body.image", "[图片]"),
+    ("body.video", "[视频]"),
+    ("body.audio", "[语音]"),
+    ("body.file", "[文件]"),
+    ("body.emoji", "[表情]"),
+    ("body.contact_card", "[名片]"),
+    ("body.poll", "[投票]"),
+    ("body.transactional", "[红包]"),
+    ("body.other", "[新消息]"),
+    ("body.video_call", "邀请你进行视频通话"),
+    ("body.audio_call", "邀请你进行语音通话"),
+    ("body.friend_apply", "请求添加你为好友"),
+    ("body.hidden", "{sender}发来一条新消息"),
+    ("body.hidden_all", "你收到一条新消息"),
+];
+
+/// 内置英文模板
+const EN_TEMPLATES: &[(&str, &str)] = &[
+    ("title.single", "{sender}"),
+    ("title.group", "{group}"),
+    ("title.hidden", "New message"),
+    ("body.group", "{sender}: {content}"),
+    ("body.text", "{content}"),
+    ("body.image", "[Photo]"),
+    ("body.video", "[Video]"),
+    ("body.audio", "[Voice message]"),
+    ("body.file", "[File]"),
+    ("body.emoji", "[Sticker]"),
+    ("body.contact_card", "[Contact card]"),
+    ("body.poll", "[Poll]"),
+    ("body.transactional", "[Red packet]"),
+    ("body.other", "[New message]"),
+    ("body.video_call", "is inviting you to a video call"),
+    ("body.audio_call", "is inviting you to a voice call"),
+    ("body.friend_apply", "wants to add you as a friend"),
+    ("body.hidden", "{sender} sent you a new message"),
+    ("body.hidden_all", "You have a new message"),
+];
+
+/// 推送隐私模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyMode {
+    /// 显示发送者和内容
+    #[default]
+    ShowAll,
+    /// 只显示发送者，隐藏内容
+    HideContent,
+    /// 发送者和内容都隐藏
+    HideAll,
+}
+
+/// 推送接收者的偏好
+#[derive(Debug, Clone, Default)]
+pub struct PushRecipient {
+    /// 租户ID，为空时不应用租户模板
+    pub tenant_id: String,
+    /// 接收者语言，如 zh-CN、en-US，为空时使用默认语言
+    pub locale: String,
+    /// 隐私模式
+    pub privacy: PrivacyMode,
+}
+
+/// 渲染后的离线推送文案
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushPayload {
+    pub title: String,
+    pub body: String,
+}
+
+/// 推送模板注册表
+#[derive(Debug, Clone)]
+pub struct PushTemplates {
+    default_locale: String,
+    builtin: HashMap<String, HashMap<String, String>>,
+    /// 租户ID -> 语言 -> 模板键 -> 模板
+    tenants: HashMap<String, HashMap<String, HashMap<String, String>>>,
+}
+
+impl Default for PushTemplates {
+    fn default() -> Self {
+        Self::new(&PushConfig::default())
+    }
+}
+
+impl PushTemplates {
+    /// 根据推送配置创建模板注册表，加载内置模板和租户覆盖模板
+    pub fn new(config: &PushConfig) -> Self {
+        let mut builtin = HashMap::new();
+        builtin.insert("zh-cn".to_string(), to_map(ZH_CN_TEMPLATES));
+        builtin.insert("en".to_string(), to_map(EN_TEMPLATES));
+
+        let default_locale = config
+            .default_locale
+            .as_deref()
+            .map(normalize_locale)
+            .filter(|locale| builtin.contains_key(locale))
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+
+        let tenants = config
+            .tenant_templates
+            .iter()
+            .map(|(tenant, locales)| {
+                let locales = locales
+                    .iter()
+                    .map(|(locale, templates)| (normalize_locale(locale), templates.clone()))
+                    .collect();
+                (tenant.clone(), locales)
+            })
+            .collect();
+
+        Self {
+            default_locale,
+            builtin,
+            tenants,
+        }
+    }
+
+    /// 为接收者渲染一条消息的离线推送文案
+    ///
+    /// # 参数
+    /// * `msg` - 待推送的消息
+    /// * `recipient` - 接收者的租户、语言和隐私模式
+    /// * `group_name` - 群聊消息的群名称，为空时使用群ID
+    ///
+    /// # 返回
+    /// 不需要离线推送的消息类型（已读回执、信令等）返回 `None`
+    pub fn render(
+        &self,
+        msg: &Msg,
+        recipient: &PushRecipient,
+        group_name: Option<&str>,
+    ) -> Option<PushPayload> {
+        let msg_type = MsgType::try_from(msg.msg_type).ok()?;
+        let is_group = msg_type == MsgType::GroupMsg;
+
+        let body_key = match msg_type {
+            MsgType::SingleMsg | MsgType::GroupMsg => content_key(msg.content_type),
+            MsgType::SingleCallInvite => {
+                if msg.content_type == ContentType::VideoCall as i32 {
+                    "body.video_call"
+                } else {
+                    "body.audio_call"
+                }
+            }
+            MsgType::FriendApplyReq => "body.friend_apply",
+            _ => return None,
+        };
+
+        let sender = if msg.nickname.is_empty() {
+            msg.send_id.as_str()
+        } else {
+            msg.nickname.as_str()
+        };
+        let group = group_name
+            .filter(|name| !name.is_empty())
+            .unwrap_or(msg.group_id.as_str());
+        let content = if body_key == "body.text" {
+            preview(&msg.content)
+        } else {
+            String::new()
+        };
+
+        let lookup = |key: &str| self.template(recipient, key);
+        let vars = |content: &str| -> Vec<(&str, String)> {
+            vec![
+                ("sender", sender.to_string()),
+                ("group", group.to_string()),
+                ("content", content.to_string()),
+            ]
+        };
+
+        let title_key = if is_group { "title.group" } else { "title.single" };
+
+        let payload = match recipient.privacy {
+            PrivacyMode::HideAll => PushPayload {
+                title: render_template(&lookup("title.hidden"), &vars("")),
+                body: render_template(&lookup("body.hidden_all"), &vars("")),
+            },
+            PrivacyMode::HideContent => PushPayload {
+                title: render_template(&lookup(title_key), &vars("")),
+                body: render_template(&lookup("body.hidden"), &vars("")),
+            },
+            PrivacyMode::ShowAll => {
+                let mut body = render_template(&lookup(body_key), &vars(&content));
+                // 通话邀请和好友申请的文案不含发送者，单聊时标题已是发送者
+                if is_group {
+                    body = render_template(&lookup("body.group"), &vars(&body));
+                }
+                PushPayload {
+                    title: render_template(&lookup(title_key), &vars("")),
+                    body,
+                }
+            }
+        };
+
+        Some(payload)
+    }
+
+    /// 查找模板：租户模板（精确语言 -> 语言前缀 -> 默认语言）优先，其次为内置模板
+    fn template(&self, recipient: &PushRecipient, key: &str) -> String {
+        let locale = normalize_locale(&recipient.locale);
+        let language = locale.split('-').next().unwrap_or_default().to_string();
+        let builtin_locale = self.resolve_builtin(&locale, &language);
+
+        if let Some(locales) = self.tenants.get(&recipient.tenant_id) {
+            for candidate in [&locale, &language, &builtin_locale] {
+                if let Some(template) = locales.get(candidate).and_then(|t| t.get(key)) {
+                    return template.clone();
+                }
+            }
+        }
+
+        self.builtin
+            .get(&builtin_locale)
+            .and_then(|t| t.get(key))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 将接收者语言映射到内置语言，无法匹配时使用默认语言
+    fn resolve_builtin(&self, locale: &str, language: &str) -> String {
+        if self.builtin.contains_key(locale) {
+            return locale.to_string();
+        }
+        if let Some(found) = self
+            .builtin
+            .keys()
+            .find(|k| k.as_str() == language || k.split('-').next() == Some(language))
+        {
+            return found.clone();
+        }
+        self.default_locale.clone()
+    }
+}
+
+/// 消息内容类型对应的正文模板键
+fn content_key(content_type: i32) -> &'static str {
+    match ContentType::try_from(content_type) {
+        Ok(ContentType::Text) | Ok(ContentType::Default) => "body.text",
+        Ok(ContentType::Image) => "body.image",
+        Ok(ContentType::Video) => "body.video",
+        Ok(ContentType::Audio) => "body.audio",
+        Ok(ContentType::File) => "body.file",
+        Ok(ContentType::Emoji) => "body.emoji",
+        Ok(ContentType::VideoCall) => "body.video_call",
+        Ok(ContentType::AudioCall) => "body.audio_call",
+        Ok(ContentType::ContactCard) => "body.contact_card",
+        Ok(ContentType::Poll) => "body.poll",
+        Ok(ContentType::Transactional) => "body.transactional",
+        _ => "body.other",
+    }
+}
+
+/// 截取文本内容预览
+fn preview(content: &[u8]) -> String {
+    let text = String::from_utf8_lossy(content);
+    let text = text.trim();
+    if text.chars().count() <= MAX_PREVIEW_CHARS {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MAX_PREVIEW_CHARS).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// 统一语言标识：小写并将下划线替换为连字符，如 zh_CN -> zh-cn
+fn normalize_locale(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_lowercase()
+}
+
+fn to_map(templates: &[(&str, &str)]) -> HashMap<String, String> {
+    templates
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// 单次扫描替换占位符，替换进来的值不会再被解析，避免用户内容中的花括号被当作占位符
+fn render_template(template: &str, vars: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match vars.iter().find(|(k, _)| *k == name) {
+                    Some((_, value)) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + end + 2]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(msg_type: MsgType, content_type: ContentType, content: &str) -> Msg {
+        Msg {
+            send_id: "1001".to_string(),
+            nickname: "Alice".to_string(),
+            group_id: "g1".to_string(),
+            msg_type: msg_type as i32,
+            content_type: content_type as i32,
+            content: content.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_by_locale_and_privacy() {
+        let templates = PushTemplates::default();
+        let image = msg(MsgType::SingleMsg, ContentType::Image, "");
+
+        let en = PushRecipient {
+            locale: "en-US".to_string(),
+            ..Default::default()
+        };
+        let payload = templates.render(&image, &en, None).unwrap();
+        assert_eq!(payload.title, "Alice");
+        assert_eq!(payload.body, "[Photo]");
+
+        // 未知语言回退到默认中文
+        let group = msg(MsgType::GroupMsg, ContentType::Text, "hi {sender}");
+        let fr = PushRecipient {
+            locale: "fr".to_string(),
+            ..Default::default()
+        };
+        let payload = templates.render(&group, &fr, Some("Team")).unwrap();
+        assert_eq!(payload.title, "Team");
+        assert_eq!(payload.body, "Alice: hi {sender}");
+
+        let hidden = PushRecipient {
+            privacy: PrivacyMode::HideContent,
+            ..Default::default()
+        };
+        let payload = templates.render(&group, &hidden, Some("Team")).unwrap();
+        assert_eq!(payload.body, "Alice发来一条新消息");
+
+        let read = msg(MsgType::Read, ContentType::Default, "");
+        assert!(templates.render(&read, &fr, None).is_none());
+    }
+
+    #[test]
+    fn test_tenant_override() {
+        let mut en = HashMap::new();
+        en.insert(
+            "body.image".to_string(),
+            "{sender} shared a picture".to_string(),
+        );
+        let mut locales = HashMap::new();
+        locales.insert("en".to_string(), en);
+        let mut config = PushConfig::default();
+        config.tenant_templates.insert("t1".to_string(), locales);
+
+        let templates = PushTemplates::new(&config);
+        let image = msg(MsgType::SingleMsg, ContentType::Image, "");
+
+        let tenant = PushRecipient {
+            tenant_id: "t1".to_string(),
+            locale: "en_GB".to_string(),
+            ..Default::default()
+        };
+        let payload = templates.render(&image, &tenant, None).unwrap();
+        assert_eq!(payload.body, "Alice shared a picture");
+        // 未覆盖的键仍使用内置模板
+        assert_eq!(payload.title, "Alice");
+
+        let other = PushRecipient {
+            tenant_id: "t2".to_string(),
+            locale: "en".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(templates.render(&image, &other, None).unwrap().body, "[Photo]");
+    }
+}
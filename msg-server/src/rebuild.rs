@@ -0,0 +1,472 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+use mongodb::bson::{doc, Document};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info, warn};
+
+use cache::Cache;
+use common::config::{AppConfig, ReceiveBoxRebuildConfig};
+use common::error::Error;
+use common::grpc::service_auth::ServiceClaims;
+use common::grpc::subject::forwarded_subject;
+use common::grpc::tenant::{current_tenant, with_tenant};
+use common::message::Msg;
+use common::proto::mailbox::mailbox_repair_service_server::MailboxRepairService;
+use common::proto::mailbox::{RebuildProgress, RebuildReceiveBoxRequest};
+use common::pseudonym::IdPseudonymizer;
+use common::tenant_db::{MongoRouter, TenantRouted};
+
+use crate::history::PgMessageStore;
+
+/// 消息盒子集合名称
+const MSG_BOX_COLLECTION: &str = "msg_box";
+
+/// 权威的消息历史
+#[async_trait]
+pub trait CanonicalHistory: Send + Sync {
+    /// 用户接收序号大于 `after_seq` 的一批消息，按序号从小到大
+    async fn messages(&self, user_id: &str, after_seq: i64, limit: i64) -> Result<Vec<Msg>, Error>;
+
+    /// 用户当前最大的接收序号，没有消息时返回0
+    async fn max_seq(&self, user_id: &str) -> Result<i64, Error>;
+}
+
+/// 基于Postgres的消息历史
+///
+/// 读取Kafka消费端写入 private_messages 的单聊消息。群聊消息的接收序号按成员分配，
+/// 不保存在历史中，重建只恢复单聊消息。消费端在消息所属租户的上下文中写入历史，
+/// 这里按当前租户路由到同一个数据库集群，连接在首次使用时建立
+pub struct PgHistory {
+    stores: TenantRouted<PgMessageStore>,
+}
+
+impl PgHistory {
+    pub async fn new(config: &AppConfig) -> Result<Self, Error> {
+        let stores = TenantRouted::connect(config, |config| async move {
+            PgMessageStore::new(&config).expect("创建Postgres连接池失败")
+        })
+        .await?;
+        Ok(Self { stores })
+    }
+}
+
+#[async_trait]
+impl CanonicalHistory for PgHistory {
+    async fn messages(&self, user_id: &str, after_seq: i64, limit: i64) -> Result<Vec<Msg>, Error> {
+        self.stores
+            .current()
+            .received(user_id, after_seq, limit)
+            .await
+    }
+
+    async fn max_seq(&self, user_id: &str) -> Result<i64, Error> {
+        self.stores.current().max_received_seq(user_id).await
+    }
+}
+
+/// MongoDB中的用户接收盒，按当前租户路由到所在集群
+pub struct MongoReceiveBox {
+    mongo: MongoRouter,
+    pseudonymizer: IdPseudonymizer,
+}
+
+impl MongoReceiveBox {
+    pub async fn new(config: &AppConfig) -> Self {
+        let mongo = MongoRouter::connect(config).await.expect("MongoDB连接失败");
+        let pseudonymizer = IdPseudonymizer::from_config(&config.database.mongodb.pseudonym)
+            .expect("接收盒ID化名配置错误");
+        Self {
+            mongo,
+            pseudonymizer,
+        }
+    }
+
+    /// 一条消息的写入语句：按服务端ID写入，已存在的消息覆盖内容，但保留接收盒中的已读状态
+    fn upsert_statement(&self, user_keys: &[String], msg: &Msg) -> Document {
+        let mut document = Document::from(msg);
+        self.pseudonymizer.pseudonymize_document(&mut document);
+        let is_read = document.remove("is_read").unwrap_or_else(|| false.into());
+
+        doc! {
+            "q": {
+                "server_id": &msg.server_id,
+                "receiver_id": { "$in": user_keys },
+            },
+            "u": {
+                "$set": document,
+                "$setOnInsert": { "is_read": is_read },
+            },
+            "upsert": true,
+        }
+    }
+
+    /// 把一批消息写入用户的接收盒，整批作为一条update命令发送
+    ///
+    /// 单聊消息的发送方和接收方共用接收方的这份文档，重建接收方的接收盒即可同时修复发送方的已发送消息
+    async fn upsert(&self, user_id: &str, messages: &[Msg]) -> Result<(), Error> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let user_keys = self.pseudonymizer.candidates(user_id);
+        let updates: Vec<Document> = messages
+            .iter()
+            .map(|msg| self.upsert_statement(&user_keys, msg))
+            .collect();
+
+        let reply = self
+            .mongo
+            .database()
+            .run_command(
+                doc! {
+                    "update": MSG_BOX_COLLECTION,
+                    "updates": updates,
+                    "ordered": false,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("写入接收盒失败: {}", e)))?;
+        // 命令本身成功时，单条语句的失败记录在 writeErrors 中
+        if let Ok(errors) = reply.get_array("writeErrors") {
+            if let Some(first) = errors.first() {
+                return Err(Error::Internal(format!(
+                    "写入接收盒失败，{} 条消息出错: {}",
+                    errors.len(),
+                    first
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 重建接收盒的操作人
+///
+/// 只接受经服务间认证的调用：转发了认证用户的请求只允许配置的管理员，
+/// 内部服务直接调用时以调用方服务名记录。请求中的 operator_id 由调用方填写，只作参考
+fn authorize_operator(
+    claims: Option<&ServiceClaims>,
+    metadata: &MetadataMap,
+    admin_user_ids: &[String],
+) -> Result<String, Status> {
+    let claims = claims.ok_or_else(|| Status::unauthenticated("缺少服务间令牌"))?;
+    match forwarded_subject(metadata) {
+        Some(subject) if admin_user_ids.iter().any(|id| id == subject) => Ok(subject.to_string()),
+        Some(subject) => {
+            warn!("用户 {} 无权重建接收盒", subject);
+            Err(Status::permission_denied("只有管理员可以重建接收盒"))
+        }
+        None => Ok(format!("service:{}", claims.iss)),
+    }
+}
+
+/// 是否已完成重建：不足一批或已到开始时的最大序号
+fn is_done(count: usize, batch_size: i64, last_seq: i64, target_seq: i64) -> bool {
+    (count as i64) < batch_size || last_seq >= target_seq
+}
+
+/// 本次重建从哪个接收序号之后开始：指定了 `from_seq` 时从该序号（含）开始，
+/// 否则从保存的进度之后继续，没有进度时从头开始
+fn resume_after(from_seq: i64, saved: Option<i64>) -> i64 {
+    if from_seq > 0 {
+        from_seq - 1
+    } else {
+        saved.unwrap_or_default()
+    }
+}
+
+/// 接收盒重建服务
+///
+/// 接收盒损坏或迁移后，按接收序号从Postgres中的消息历史分批重新写入MongoDB，
+/// 两者都按请求所属的租户路由。批次之间按配置限速，单实例并发重建数量受限，同一用户同时只能有一个重建任务；
+/// 每写入一批保存一次进度并返回给调用方，连接断开后可以从保存的进度继续
+pub struct RebuildService {
+    history: Arc<dyn CanonicalHistory>,
+    receive_box: Arc<MongoReceiveBox>,
+    cache: Arc<dyn Cache>,
+    config: ReceiveBoxRebuildConfig,
+    permits: Arc<Semaphore>,
+    running: Arc<DashMap<String, ()>>,
+}
+
+impl RebuildService {
+    pub fn new(
+        history: Arc<dyn CanonicalHistory>,
+        receive_box: Arc<MongoReceiveBox>,
+        cache: Arc<dyn Cache>,
+        config: ReceiveBoxRebuildConfig,
+    ) -> Self {
+        let permits = Arc::new(Semaphore::new(config.max_concurrent.max(1)));
+        Self {
+            history,
+            receive_box,
+            cache,
+            config,
+            permits,
+            running: Arc::new(DashMap::new()),
+        }
+    }
+}
+
+/// 重建结束（包括调用方断开）时释放用户
+struct RunningGuard {
+    running: Arc<DashMap<String, ()>>,
+    user_id: String,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.running.remove(&self.user_id);
+    }
+}
+
+#[tonic::async_trait]
+impl MailboxRepairService for RebuildService {
+    type RebuildReceiveBoxStream = ReceiverStream<Result<RebuildProgress, Status>>;
+
+    async fn rebuild_receive_box(
+        &self,
+        request: Request<RebuildReceiveBoxRequest>,
+    ) -> Result<Response<Self::RebuildReceiveBoxStream>, Status> {
+        let operator = authorize_operator(
+            request.extensions().get::<ServiceClaims>(),
+            request.metadata(),
+            &self.config.admin_user_ids,
+        )?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Status::invalid_argument("用户ID不能为空"));
+        }
+        if req.from_seq < 0 {
+            return Err(Status::invalid_argument("起始序号不能为负数"));
+        }
+
+        let permit = self
+            .permits
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| Error::TooManyRequests("接收盒重建繁忙，请稍后重试".to_string()))?;
+        if self.running.insert(req.user_id.clone(), ()).is_some() {
+            return Err(Status::already_exists(format!(
+                "用户 {} 的接收盒正在重建",
+                req.user_id
+            )));
+        }
+        let guard = RunningGuard {
+            running: self.running.clone(),
+            user_id: req.user_id.clone(),
+        };
+
+        let saved = self.cache.get_rebuild_progress(&req.user_id).await?;
+        let after_seq = resume_after(req.from_seq, saved);
+        let target_seq = self.history.max_seq(&req.user_id).await?;
+        info!(
+            "{}（请求中填写为 {}）开始重建用户 {} 的接收盒，序号 {} 之后，目标序号 {}",
+            operator, req.operator_id, req.user_id, after_seq, target_seq
+        );
+
+        let task = RebuildTask {
+            history: self.history.clone(),
+            receive_box: self.receive_box.clone(),
+            cache: self.cache.clone(),
+            user_id: req.user_id,
+            target_seq,
+            batch_size: self.config.batch_size.max(1),
+            interval: Duration::from_millis(self.config.batch_interval_ms),
+        };
+
+        // 后台任务不继承请求的租户上下文，需要重新进入
+        let tenant_id = current_tenant();
+        let (tx, rx) = mpsc::channel(2);
+        tokio::spawn(async move {
+            let _permit = permit;
+            let _guard = guard;
+            with_tenant(tenant_id, task.run(after_seq, tx)).await;
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// 单个用户的重建任务
+struct RebuildTask {
+    history: Arc<dyn CanonicalHistory>,
+    receive_box: Arc<MongoReceiveBox>,
+    cache: Arc<dyn Cache>,
+    user_id: String,
+    target_seq: i64,
+    batch_size: i64,
+    interval: Duration,
+}
+
+impl RebuildTask {
+    async fn run(&self, mut last_seq: i64, tx: mpsc::Sender<Result<RebuildProgress, Status>>) {
+        let mut ticker = tokio::time::interval(self.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut rebuilt = 0;
+
+        loop {
+            ticker.tick().await;
+
+            let messages = match self.next_batch(last_seq).await {
+                Ok(messages) => messages,
+                Err(e) => {
+                    error!("重建用户 {} 的接收盒失败: {:?}", self.user_id, e);
+                    let _ = tx.send(Err(e.into())).await;
+                    return;
+                }
+            };
+            rebuilt += messages.len() as i64;
+            if let Some(last) = messages.last() {
+                last_seq = last.seq;
+            }
+            // 重建期间的新消息由消费端照常写入
+            let done = is_done(messages.len(), self.batch_size, last_seq, self.target_seq);
+
+            if let Err(e) = self.save_progress(last_seq, done).await {
+                error!("保存接收盒重建进度失败: {:?}", e);
+            }
+            let progress = RebuildProgress {
+                rebuilt,
+                last_seq,
+                target_seq: self.target_seq,
+                done,
+            };
+            // 调用方断开时停止，进度已保存
+            if tx.send(Ok(progress)).await.is_err() {
+                debug!("用户 {} 的接收盒重建连接已断开", self.user_id);
+                return;
+            }
+            if done {
+                info!(
+                    "用户 {} 的接收盒重建完成，共写入 {} 条消息",
+                    self.user_id, rebuilt
+                );
+                return;
+            }
+        }
+    }
+
+    /// 读取下一批消息并写入接收盒
+    async fn next_batch(&self, last_seq: i64) -> Result<Vec<Msg>, Error> {
+        let messages = self
+            .history
+            .messages(&self.user_id, last_seq, self.batch_size)
+            .await?;
+        self.receive_box.upsert(&self.user_id, &messages).await?;
+        Ok(messages)
+    }
+
+    async fn save_progress(&self, last_seq: i64, done: bool) -> Result<(), Error> {
+        if done {
+            return self.cache.delete_rebuild_progress(&self.user_id).await;
+        }
+        self.cache
+            .save_rebuild_progress(&self.user_id, last_seq)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common::config::PseudonymConfig;
+    use common::grpc::subject::SUBJECT_METADATA_KEY;
+
+    #[test]
+    fn test_resume_after() {
+        // 指定起始序号时从该序号（含）开始，忽略保存的进度
+        assert_eq!(resume_after(100, Some(500)), 99);
+        assert_eq!(resume_after(1, None), 0);
+        // 未指定时从保存的进度之后继续
+        assert_eq!(resume_after(0, Some(500)), 500);
+        assert_eq!(resume_after(0, None), 0);
+    }
+
+    #[test]
+    fn test_is_done() {
+        assert!(!is_done(500, 500, 500, 1200));
+        // 不足一批
+        assert!(is_done(200, 500, 700, 1200));
+        // 已到目标序号
+        assert!(is_done(500, 500, 1200, 1200));
+        // 没有任何消息
+        assert!(is_done(0, 500, 0, 0));
+    }
+
+    fn claims() -> ServiceClaims {
+        ServiceClaims {
+            iss: "ops-tool".to_string(),
+            aud: "chrisim-internal".to_string(),
+            iat: 0,
+            exp: 0,
+            sub: None,
+            tenant: None,
+        }
+    }
+
+    #[test]
+    fn test_authorize_operator() {
+        let admins = vec!["admin".to_string()];
+        let mut metadata = MetadataMap::new();
+
+        // 未经服务间认证
+        let status = authorize_operator(None, &metadata, &admins).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        // 内部服务直接调用
+        assert_eq!(
+            authorize_operator(Some(&claims()), &metadata, &admins).unwrap(),
+            "service:ops-tool"
+        );
+
+        metadata.insert(SUBJECT_METADATA_KEY, "admin".parse().unwrap());
+        assert_eq!(
+            authorize_operator(Some(&claims()), &metadata, &admins).unwrap(),
+            "admin"
+        );
+        metadata.insert(SUBJECT_METADATA_KEY, "u1".parse().unwrap());
+        let status = authorize_operator(Some(&claims()), &metadata, &admins).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_statement_keeps_read_state() {
+        let receive_box = MongoReceiveBox {
+            mongo: MongoRouter::single(
+                mongodb::Client::with_uri_str("mongodb://127.0.0.1:27017")
+                    .await
+                    .unwrap()
+                    .database("im"),
+            ),
+            pseudonymizer: IdPseudonymizer::from_config(&PseudonymConfig::default()).unwrap(),
+        };
+        let msg = Msg {
+            server_id: "s1".to_string(),
+            receiver_id: "u1".to_string(),
+            seq: 3,
+            is_read: true,
+            ..Default::default()
+        };
+        let keys = vec!["u1".to_string()];
+        let statement = receive_box.upsert_statement(&keys, &msg);
+
+        assert!(statement.get_bool("upsert").unwrap());
+        let filter = statement.get_document("q").unwrap();
+        assert_eq!(filter.get_str("server_id").unwrap(), "s1");
+        let update = statement.get_document("u").unwrap();
+        // 已读状态只在新写入时设置，不覆盖接收盒中已有的状态
+        assert!(!update.get_document("$set").unwrap().contains_key("is_read"));
+        assert!(update
+            .get_document("$setOnInsert")
+            .unwrap()
+            .get_bool("is_read")
+            .unwrap());
+    }
+}
@@ -0,0 +1,114 @@
+use mongodb::bson::{doc, Document};
+use mongodb::Collection;
+use tracing::{debug, info, warn};
+
+use common::attachment::AttachmentDescriptor;
+use common::config::AppConfig;
+use common::error::Error;
+use common::grpc_client::StorageGrpcClient;
+use common::message::{ContentType, Msg, MsgType};
+use common::pseudonym::IdPseudonymizer;
+use common::tenant_db::MongoRouter;
+
+use crate::group_media::GroupMediaIndexer;
+
+/// 消息盒子集合名称
+const MSG_BOX_COLLECTION: &str = "msg_box";
+
+/// 消息撤回处理
+///
+/// 撤回消息是内容类型为 [`ContentType::Recall`] 的聊天消息，`related_msg_id` 为原消息的服务端ID；
+/// 只有原消息的发送者可以撤回，原消息中的附件释放存储配额，群聊附件同时从群媒体库移除
+#[derive(Clone)]
+pub struct RecallHandler {
+    mongo: MongoRouter,
+    pseudonymizer: IdPseudonymizer,
+    storage: StorageGrpcClient,
+    media_indexer: GroupMediaIndexer,
+}
+
+impl RecallHandler {
+    pub async fn new(config: &AppConfig, media_indexer: GroupMediaIndexer) -> Self {
+        let mongo = MongoRouter::connect(config).await.expect("MongoDB连接失败");
+        let pseudonymizer = IdPseudonymizer::from_config(&config.database.mongodb.pseudonym)
+            .expect("消息盒子ID假名化配置无效");
+        Self {
+            mongo,
+            pseudonymizer,
+            storage: StorageGrpcClient::from_env(),
+            media_indexer,
+        }
+    }
+
+    fn msg_box(&self) -> Collection<Document> {
+        self.mongo.database().collection(MSG_BOX_COLLECTION)
+    }
+
+    /// 是否为撤回消息
+    pub fn is_recall(msg: &Msg) -> bool {
+        msg.content_type == ContentType::Recall as i32
+            && matches!(
+                MsgType::try_from(msg.msg_type),
+                Ok(MsgType::SingleMsg) | Ok(MsgType::GroupMsg)
+            )
+    }
+
+    /// 处理撤回消息，返回是否继续投递
+    ///
+    /// 原消息不存在或不是撤回者发送的，撤回消息直接丢弃
+    pub async fn handle(&self, msg: &Msg) -> Result<bool, Error> {
+        let Some(original_id) = msg.related_msg_id.as_deref().filter(|id| !id.is_empty()) else {
+            debug!("撤回消息 {} 没有关联的原消息，丢弃", msg.server_id);
+            return Ok(false);
+        };
+
+        // 消息盒子中的用户ID可能已假名化，按发送者的所有候选ID查询
+        let filter = doc! {
+            "server_id": original_id,
+            "send_id": { "$in": self.pseudonymizer.candidates(&msg.send_id) },
+        };
+        let original = self
+            .msg_box()
+            .find_one(filter, None)
+            .await
+            .map_err(|e| Error::Internal(format!("查询被撤回的消息失败: {}", e)))?;
+        let Some(original) = original else {
+            warn!(
+                "用户 {} 撤回的消息 {} 不存在或不是本人发送，丢弃",
+                msg.send_id, original_id
+            );
+            return Ok(false);
+        };
+        let original = Msg::try_from(original)?;
+
+        if let Some(attachment) = attachment_of(&original) {
+            match self
+                .storage
+                .release_attachments(vec![attachment.object_key.clone()])
+                .await
+            {
+                Ok(response) if response.released > 0 => info!(
+                    "消息 {} 撤回，释放附件 {}，共 {} 字节",
+                    original_id, attachment.object_key, response.released_bytes
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("释放撤回消息 {} 的附件失败: {:?}", original_id, e),
+            }
+        }
+        if original.msg_type == MsgType::GroupMsg as i32 {
+            self.media_indexer
+                .remove(&original.receiver_id, original_id);
+        }
+        Ok(true)
+    }
+}
+
+/// 附件消息中的附件描述
+fn attachment_of(msg: &Msg) -> Option<AttachmentDescriptor> {
+    match ContentType::try_from(msg.content_type).ok()? {
+        ContentType::Image | ContentType::Video | ContentType::Audio | ContentType::File => {
+            serde_json::from_slice(&msg.content).ok()
+        }
+        _ => None,
+    }
+}
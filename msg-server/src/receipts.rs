@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::warn;
+
+use common::config::ReceiptConfig;
+use common::message::{ContentType, Msg, MsgType};
+use common::time_sync::now_millis;
+
+use crate::pusher::Pusher;
+
+/// 待转发回执的合并键：消息发送者ID、读者ID
+type ReceiptKey = (String, String);
+
+/// 单聊已读回执的合并转发
+///
+/// 消费者处理已读消息时只记录读者已读到的对方最大发送序号，后台任务按间隔把每对发送者和读者的
+/// 最新回执推送给发送者，同一间隔内的多次已读只转发一次。发送者收到回执后把发送序号不大于
+/// read_send_seq 的消息标记为对方已读；推送失败不重试，下一次已读时会携带更大的序号
+#[derive(Clone)]
+pub struct ReceiptForwarder {
+    pusher: Arc<dyn Pusher>,
+    pending: Arc<Mutex<HashMap<ReceiptKey, i64>>>,
+    config: ReceiptConfig,
+}
+
+impl ReceiptForwarder {
+    pub fn new(config: &ReceiptConfig, pusher: Arc<dyn Pusher>) -> Self {
+        Self {
+            pusher,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            config: config.clone(),
+        }
+    }
+
+    /// 启动后台转发任务
+    pub fn start(&self) {
+        if !self.config.forward_to_sender {
+            return;
+        }
+        let forwarder = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(
+                forwarder.config.flush_interval_ms.max(1),
+            ));
+            loop {
+                interval.tick().await;
+                forwarder.flush().await;
+            }
+        });
+    }
+
+    /// 记录读者已读到发送者的消息的发送序号，只保留最大值
+    pub fn record(&self, sender_id: &str, reader_id: &str, read_send_seq: i64) {
+        if !self.config.forward_to_sender || sender_id == reader_id {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        merge(
+            &mut pending,
+            (sender_id.to_string(), reader_id.to_string()),
+            read_send_seq,
+        );
+    }
+
+    /// 把合并后的回执推送给发送者
+    async fn flush(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+        metrics::counter!("im_read_receipts_forwarded_total").increment(pending.len() as u64);
+
+        let read_time = now_millis();
+        for ((sender_id, reader_id), read_send_seq) in pending {
+            let content = serde_json::json!({
+                "type": "read_receipt",
+                "conversation_id": reader_id,
+                "read_send_seq": read_send_seq,
+                "read_time": read_time,
+            });
+            let receipt = Msg {
+                receiver_id: sender_id,
+                send_time: read_time,
+                msg_type: MsgType::Read as i32,
+                content_type: ContentType::Text as i32,
+                content: content.to_string().into_bytes(),
+                ..Default::default()
+            };
+            if let Err(e) = self.pusher.push_single_msg(receipt).await {
+                warn!("转发已读回执失败: {:?}", e);
+            }
+        }
+    }
+}
+
+/// 合并同一发送者和读者的回执，序号只增不减
+fn merge(pending: &mut HashMap<ReceiptKey, i64>, key: ReceiptKey, read_send_seq: i64) {
+    let entry = pending.entry(key).or_insert(read_send_seq);
+    *entry = (*entry).max(read_send_seq);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_receipts() {
+        let key = |sender: &str, reader: &str| (sender.to_string(), reader.to_string());
+        let mut pending = HashMap::new();
+        merge(&mut pending, key("alice", "bob"), 5);
+        merge(&mut pending, key("alice", "bob"), 9);
+        // 乱序到达的较小序号不会使回执倒退
+        merge(&mut pending, key("alice", "bob"), 7);
+        merge(&mut pending, key("carol", "bob"), 3);
+
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[&key("alice", "bob")], 9);
+        assert_eq!(pending[&key("carol", "bob")], 3);
+    }
+}
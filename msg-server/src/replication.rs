@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::message::Headers;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::{ClientConfig, Message};
+use tracing::{debug, error, info, warn};
+
+use common::config::{AppConfig, RegionPeerConfig};
+use common::time_sync::now_millis;
+
+use crate::codec;
+use crate::tenant_topics::TenantTopics;
+
+/// 写入本区域失败后的重试间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 跨区域消息复制
+///
+/// 每个对端区域一个复制任务，从对端的消息主题读取在对端产生的消息（按来源区域消息头判断），
+/// 原样写入本区域的消息主题，由本区域的消费者为归属本区域的用户分配序号、保存和推送。
+/// 复制过来的消息保留对端的来源区域，不会被其他区域再次复制，避免循环。
+/// 各区域的大租户主题配置一致，对端租户主题的消息写入本区域同名的租户主题
+pub struct ReplicationService {
+    peer: RegionPeerConfig,
+    consumer: StreamConsumer,
+    producer: FutureProducer,
+    topics: TenantTopics,
+}
+
+impl ReplicationService {
+    pub fn new(config: &AppConfig, peer: RegionPeerConfig) -> Self {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("group.id", &config.region.replication_group)
+            .set("bootstrap.servers", peer.hosts.join(","))
+            .set("enable.auto.commit", "false")
+            .set(
+                "session.timeout.ms",
+                config.kafka.consumer.session_timeout.to_string(),
+            )
+            .set("enable.partition.eof", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .expect("复制消费者创建失败");
+        let topics = TenantTopics::new(config.kafka.topic.clone(), &config.kafka.tenant_topics);
+        let mut peer_topics = vec![peer.topic.clone()];
+        peer_topics.extend(topics.tenant_topics());
+        let peer_topics: Vec<&str> = peer_topics.iter().map(String::as_str).collect();
+        consumer
+            .subscribe(&peer_topics)
+            .expect("无法订阅对端区域的消息主题");
+
+        let producer: FutureProducer = ClientConfig::new()
+            .set("bootstrap.servers", config.kafka.hosts.join(","))
+            .set(
+                "message.timeout.ms",
+                config.kafka.producer.timeout.to_string(),
+            )
+            .set("acks", config.kafka.producer.acks.clone())
+            .set("enable.idempotence", "true")
+            .create()
+            .expect("复制生产者创建失败");
+
+        Self {
+            peer,
+            consumer,
+            producer,
+            topics,
+        }
+    }
+
+    /// 持续复制对端区域产生的消息，写入成功后才提交对端的偏移量
+    pub async fn run(self) {
+        info!(
+            "开始从区域 {} 复制消息: {}",
+            self.peer.name, self.peer.topic
+        );
+        loop {
+            let message = match self.consumer.recv().await {
+                Ok(message) => message,
+                Err(e) => {
+                    error!("读取区域 {} 的消息失败: {}", self.peer.name, e);
+                    continue;
+                }
+            };
+
+            if self.should_replicate(&message) {
+                let headers = message.headers().map(|headers| headers.detach());
+                // 对端默认主题的消息写入本区域默认主题，租户主题写入同名主题
+                let topic = if message.topic() == self.peer.topic {
+                    self.topics.default_topic()
+                } else {
+                    message.topic()
+                };
+                // 按顺序复制，写入失败时重试直到成功
+                loop {
+                    let mut record: FutureRecord<[u8], [u8]> = FutureRecord::to(topic);
+                    if let Some(payload) = message.payload() {
+                        record = record.payload(payload);
+                    }
+                    if let Some(headers) = headers.clone() {
+                        record = record.headers(headers);
+                    }
+                    match self.producer.send(record, Duration::from_secs(0)).await {
+                        Ok(_) => {
+                            metrics::counter!("im_region_replicated_total", "peer" => self.peer.name.clone())
+                                .increment(1);
+                            break;
+                        }
+                        Err((e, _)) => {
+                            warn!("复制区域 {} 的消息失败，稍后重试: {:?}", self.peer.name, e);
+                            tokio::time::sleep(RETRY_INTERVAL).await;
+                        }
+                    }
+                }
+            }
+
+            if let Err(e) = self.consumer.commit_message(&message, CommitMode::Async) {
+                error!("提交区域 {} 的复制偏移量失败: {:?}", self.peer.name, e);
+            }
+        }
+    }
+
+    /// 只复制在对端区域产生且未过期的消息
+    fn should_replicate<M: Message>(&self, message: &M) -> bool {
+        if codec::origin_region(message).as_deref() != Some(self.peer.name.as_str()) {
+            return false;
+        }
+        if codec::expire_at(message).is_some_and(|expire_at| now_millis() > expire_at) {
+            debug!("区域 {} 的信令消息已过期，不再复制", self.peer.name);
+            return false;
+        }
+        true
+    }
+}
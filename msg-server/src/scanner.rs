@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use cache::Cache;
+use common::attachment::AttachmentDescriptor;
+use common::config::ScannerConfig;
+use common::error::Error;
+use common::message::{Msg, MsgType};
+
+use crate::group_media::GroupMediaIndexer;
+use crate::notify::ConversationNotifier;
+
+/// 待扫描队列长度，队列满时跳过扫描而不阻塞发消息
+const SCAN_QUEUE_SIZE: usize = 1024;
+
+/// 扫描结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    /// 未发现威胁
+    Clean,
+    /// 发现威胁，附带威胁名称
+    Infected(String),
+}
+
+/// 附件扫描器
+#[async_trait]
+pub trait AttachmentScanner: Send + Sync {
+    async fn scan(&self, attachment: &AttachmentDescriptor) -> Result<ScanVerdict, Error>;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanRequest<'a> {
+    object_key: &'a str,
+    url: &'a str,
+    sha256: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct ScanResponse {
+    clean: bool,
+    #[serde(default)]
+    threat: Option<String>,
+}
+
+/// 通过HTTP调用外部扫描服务（ClamAV REST网关或第三方API）
+///
+/// 请求: `POST {endpoint}`，body 为 `{"objectKey","url","sha256"}`；
+/// 响应: `{"clean": bool, "threat": "..."}`
+pub struct HttpScanner {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl HttpScanner {
+    pub fn new(config: &ScannerConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("创建扫描服务HTTP客户端失败");
+        Self {
+            client,
+            endpoint: config.endpoint.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl AttachmentScanner for HttpScanner {
+    async fn scan(&self, attachment: &AttachmentDescriptor) -> Result<ScanVerdict, Error> {
+        let request = ScanRequest {
+            object_key: &attachment.object_key,
+            url: &attachment.url,
+            sha256: attachment.sha256.as_deref(),
+        };
+        let response: ScanResponse = self
+            .client
+            .post(&self.endpoint)
+            .json(&request)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| Error::Internal(format!("调用扫描服务失败: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| Error::Internal(format!("解析扫描结果失败: {}", e)))?;
+
+        if response.clean {
+            Ok(ScanVerdict::Clean)
+        } else {
+            Ok(ScanVerdict::Infected(
+                response.threat.unwrap_or_else(|| "unknown".to_string()),
+            ))
+        }
+    }
+}
+
+/// 扫描任务
+#[derive(Debug)]
+pub struct ScanTask {
+    pub msg: Msg,
+    pub attachment: AttachmentDescriptor,
+}
+
+/// 附件扫描后台任务
+///
+/// 消息先正常投递，扫描在后台异步进行；发现威胁后将附件标记为已拦截，
+/// 并向发送者和接收者（群聊为全体成员）发送通知，客户端据此隐藏该附件。
+pub struct ScanWorker {
+    rx: mpsc::Receiver<ScanTask>,
+    scanner: Arc<dyn AttachmentScanner>,
+    cache: Arc<dyn Cache>,
+    notifier: ConversationNotifier,
+    media_indexer: GroupMediaIndexer,
+}
+
+impl ScanWorker {
+    /// 创建扫描任务，返回投递扫描任务的发送端
+    pub fn new(
+        scanner: Arc<dyn AttachmentScanner>,
+        cache: Arc<dyn Cache>,
+        notifier: ConversationNotifier,
+        media_indexer: GroupMediaIndexer,
+    ) -> (Self, mpsc::Sender<ScanTask>) {
+        let (tx, rx) = mpsc::channel(SCAN_QUEUE_SIZE);
+        (
+            Self {
+                rx,
+                scanner,
+                cache,
+                notifier,
+                media_indexer,
+            },
+            tx,
+        )
+    }
+
+    pub fn start(mut self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("附件扫描任务已启动");
+            while let Some(task) = self.rx.recv().await {
+                if let Err(e) = self.handle(task).await {
+                    error!("附件扫描失败: {:?}", e);
+                }
+            }
+        })
+    }
+
+    async fn handle(&self, task: ScanTask) -> Result<(), Error> {
+        let threat = match self.scanner.scan(&task.attachment).await? {
+            ScanVerdict::Clean => return Ok(()),
+            ScanVerdict::Infected(threat) => threat,
+        };
+
+        warn!(
+            "附件 {} 扫描发现威胁: {}，消息: {}",
+            task.attachment.object_key, threat, task.msg.server_id
+        );
+        self.cache
+            .block_attachment(&task.attachment.object_key, &threat)
+            .await?;
+
+        // 被拦截的附件从群媒体库移除
+        if task.msg.msg_type == MsgType::GroupMsg as i32 {
+            self.media_indexer
+                .remove(&task.msg.receiver_id, &task.msg.server_id);
+        }
+
+        self.notify_blocked(&task.msg, &task.attachment, &threat)
+            .await
+    }
+
+    /// 通知会话参与者附件已被拦截
+    async fn notify_blocked(
+        &self,
+        msg: &Msg,
+        attachment: &AttachmentDescriptor,
+        threat: &str,
+    ) -> Result<(), Error> {
+        let content = json!({
+            "type": "attachment_blocked",
+            "serverId": msg.server_id,
+            "groupId": msg.group_id,
+            "objectKey": attachment.object_key,
+            "reason": threat,
+        })
+        .to_string()
+        .into_bytes();
+
+        self.notifier.notify(msg, content).await
+    }
+}
@@ -0,0 +1,94 @@
+use tracing::debug;
+
+use common::config::SignalingConfig;
+use common::message::{Msg, MsgType};
+
+/// 信令消息的投递期限
+///
+/// 通话信令和正在输入提示只在短时间内有意义，生产者按消息类型计算期限写入Kafka消息头，
+/// 消费者和推送器在处理前检查，过期的消息直接丢弃并计数，避免客户端收到迟到的信令
+#[derive(Debug, Clone)]
+pub struct SignalingDeadline {
+    config: SignalingConfig,
+}
+
+impl SignalingDeadline {
+    pub fn new(config: SignalingConfig) -> Self {
+        Self { config }
+    }
+
+    /// 消息的有效期（毫秒），非信令消息返回None
+    pub fn ttl_ms(&self, msg_type: i32) -> Option<i64> {
+        if !self.config.enabled {
+            return None;
+        }
+        match MsgType::try_from(msg_type).ok()? {
+            MsgType::SingleCallInvite
+            | MsgType::AgreeSingleCall
+            | MsgType::SingleCallOffer
+            | MsgType::Candidate => Some(self.config.call_ttl_ms),
+            MsgType::Typing => Some(self.config.typing_ttl_ms),
+            _ => None,
+        }
+    }
+
+    /// 消息的投递期限（UTC毫秒），从服务端接收消息的时间开始计算
+    pub fn deadline(&self, msg: &Msg) -> Option<i64> {
+        self.ttl_ms(msg.msg_type)
+            .map(|ttl| msg.send_time.saturating_add(ttl))
+    }
+
+    /// 消息是否已超过投递期限
+    pub fn is_expired(&self, msg: &Msg, now: i64) -> bool {
+        self.deadline(msg).is_some_and(|deadline| now > deadline)
+    }
+}
+
+/// 记录一条因过期被丢弃的信令消息
+///
+/// `stage` 为丢弃的环节：consumer 或 pusher
+pub fn record_expired(stage: &'static str, msg: &Msg) {
+    let msg_type = MsgType::try_from(msg.msg_type)
+        .map(|t| t.as_str_name())
+        .unwrap_or("unknown");
+    metrics::counter!("im_signaling_expired_total", "stage" => stage, "msg_type" => msg_type)
+        .increment(1);
+    debug!(
+        "信令消息已过期，丢弃: {} {} -> {}，环节: {}",
+        msg_type, msg.send_id, msg.receiver_id, stage
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signaling_deadline() {
+        let deadline = SignalingDeadline::new(SignalingConfig::default());
+        let mut msg = Msg {
+            send_time: 1_000,
+            msg_type: MsgType::Candidate as i32,
+            ..Default::default()
+        };
+        assert_eq!(deadline.deadline(&msg), Some(31_000));
+        assert!(!deadline.is_expired(&msg, 31_000));
+        assert!(deadline.is_expired(&msg, 31_001));
+
+        msg.msg_type = MsgType::Typing as i32;
+        assert_eq!(deadline.deadline(&msg), Some(6_000));
+
+        // 普通消息和通话记录类消息不过期
+        msg.msg_type = MsgType::SingleMsg as i32;
+        assert!(!deadline.is_expired(&msg, i64::MAX));
+        msg.msg_type = MsgType::Hangup as i32;
+        assert!(deadline.deadline(&msg).is_none());
+
+        let disabled = SignalingDeadline::new(SignalingConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        msg.msg_type = MsgType::Candidate as i32;
+        assert!(!disabled.is_expired(&msg, i64::MAX));
+    }
+}
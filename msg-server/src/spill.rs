@@ -0,0 +1,360 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use prost::Message as _;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use common::config::{KafkaPayloadFormat, KafkaSpillConfig};
+use common::error::Error;
+use common::message::Msg;
+use common::time_sync::now_millis;
+
+use crate::codec;
+use crate::signaling::{self, SignalingDeadline};
+use crate::tenant_topics::TenantTopics;
+
+/// 暂存文件名
+const SPILL_FILE: &str = "spill.log";
+
+/// 单条暂存记录的长度前缀字节数
+const LEN_PREFIX: usize = 4;
+
+/// 每补发多少条消息重写一次暂存文件
+const REWRITE_EVERY: usize = 100;
+
+/// 补发前检查Kafka是否可用的超时时间
+const METADATA_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Kafka不可用时的本地暂存队列
+///
+/// 确定没有写入Kafka的消息按到达顺序追加到本地文件并保存在内存中，后台任务定期按顺序补发，
+/// 补发后从队列移除并重写文件，服务重启时从文件恢复未补发的消息；
+/// 队列非空时新消息也直接排队，保证补发期间的消息顺序。
+///
+/// 消费端不对消息去重，因此只有确定失败的消息才会暂存和补发，见 [`is_undelivered`]；
+/// 补发时结果不确定的消息不再重发，记录错误后丢弃。
+/// 每补发 [`REWRITE_EVERY`] 条消息重写一次文件，进程在重写前退出时，
+/// 重启后最多会重复补发这些消息
+#[derive(Clone)]
+pub struct SpillQueue {
+    queue: Arc<Mutex<VecDeque<Msg>>>,
+    // 队列长度，发送消息时无需加锁即可判断是否有待补发的消息
+    pending: Arc<AtomicUsize>,
+    path: PathBuf,
+    config: KafkaSpillConfig,
+}
+
+impl SpillQueue {
+    /// 打开暂存目录并恢复上次未补发的消息
+    pub async fn open(config: KafkaSpillConfig) -> Result<Self, Error> {
+        fs::create_dir_all(&config.dir).await?;
+        let path = Path::new(&config.dir).join(SPILL_FILE);
+
+        let queue = match fs::read(&path).await {
+            Ok(data) => {
+                let (messages, consumed) = decode_records(&data);
+                if consumed < data.len() {
+                    // 写入中途退出会留下不完整的记录，丢弃尾部
+                    warn!(
+                        "暂存文件尾部有 {} 字节不完整的记录，已忽略",
+                        data.len() - consumed
+                    );
+                }
+                if !messages.is_empty() {
+                    info!("从暂存文件恢复 {} 条待补发的消息", messages.len());
+                }
+                VecDeque::from(messages)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let spill = Self {
+            pending: Arc::new(AtomicUsize::new(queue.len())),
+            queue: Arc::new(Mutex::new(queue)),
+            path,
+            config,
+        };
+        // 重写文件，去掉可能存在的不完整记录
+        let queue = spill.queue.lock().await;
+        spill.rewrite(&queue).await?;
+        drop(queue);
+        Ok(spill)
+    }
+
+    /// 是否还有待补发的消息
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::Acquire) > 0
+    }
+
+    /// 将消息写入暂存队列，队列已满或写文件失败时返回错误
+    pub async fn push(&self, msg: &Msg) -> Result<(), Error> {
+        let mut queue = self.queue.lock().await;
+        if queue.len() >= self.config.max_messages {
+            return Err(Error::Internal("Kafka不可用且本地暂存队列已满".to_string()));
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(&encode_record(msg)).await?;
+        file.sync_data().await?;
+
+        queue.push_back(msg.clone());
+        self.set_pending(queue.len());
+        Ok(())
+    }
+
+    /// 启动后台补发任务
+    pub fn start(
+        self,
+        producer: FutureProducer,
+        topics: TenantTopics,
+        format: KafkaPayloadFormat,
+        signaling: SignalingDeadline,
+        region: Option<String>,
+    ) {
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(self.config.replay_interval_ms));
+            loop {
+                interval.tick().await;
+                if self.is_pending() {
+                    self.replay(&producer, &topics, format, &signaling, region.as_deref())
+                        .await;
+                }
+            }
+        });
+    }
+
+    /// 按顺序补发暂存的消息，遇到确定失败时停止，等待下次检查
+    ///
+    /// 补发前先确认Kafka可用，避免代理不可用期间的发送超时被当作结果不确定而丢弃消息
+    async fn replay(
+        &self,
+        producer: &FutureProducer,
+        topics: &TenantTopics,
+        format: KafkaPayloadFormat,
+        signaling: &SignalingDeadline,
+        region: Option<&str>,
+    ) {
+        if !is_reachable(producer, topics).await {
+            return;
+        }
+
+        let mut replayed = 0;
+        loop {
+            // 只有补发任务从队首移除消息，发送期间无需持有锁
+            let Some(msg) = self.queue.lock().await.front().cloned() else {
+                break;
+            };
+
+            // 补发时已过期的信令消息直接丢弃
+            if signaling.is_expired(&msg, now_millis()) {
+                signaling::record_expired("spill", &msg);
+                self.pop_front().await;
+                continue;
+            }
+
+            let (payload, headers) = match codec::encode(&msg, format, signaling.deadline(&msg)) {
+                Ok((payload, headers)) => match region {
+                    Some(region) => (payload, codec::with_origin(headers, region)),
+                    None => (payload, headers),
+                },
+                Err(e) => {
+                    warn!("编码暂存消息失败，丢弃: {:?}，消息ID: {}", e, msg.server_id);
+                    self.pop_front().await;
+                    continue;
+                }
+            };
+            let record: FutureRecord<String, Vec<u8>> =
+                FutureRecord::to(topics.topic(&msg.tenant_id))
+                    .payload(&payload)
+                    .headers(headers);
+            match producer.send(record, Duration::from_secs(0)).await {
+                Ok(_) => {
+                    self.pop_front().await;
+                    replayed += 1;
+                    if replayed % REWRITE_EVERY == 0 {
+                        let queue = self.queue.lock().await;
+                        if let Err(e) = self.rewrite(&queue).await {
+                            warn!("重写暂存文件失败: {:?}", e);
+                        }
+                    }
+                }
+                Err((err, _)) if is_undelivered(&err) => {
+                    warn!("补发暂存消息失败，稍后重试: {:?}", err);
+                    break;
+                }
+                Err((err, _)) => {
+                    // 消息可能已经写入Kafka，重发会产生重复消息
+                    error!(
+                        "补发暂存消息结果不确定，不再重发: {:?}，消息ID: {}",
+                        err, msg.server_id
+                    );
+                    metrics::counter!("im_kafka_spill_dropped_total").increment(1);
+                    self.pop_front().await;
+                }
+            }
+        }
+
+        let queue = self.queue.lock().await;
+        if let Err(e) = self.rewrite(&queue).await {
+            warn!("重写暂存文件失败: {:?}", e);
+        }
+        if replayed > 0 {
+            info!("已补发 {} 条暂存消息，剩余 {} 条", replayed, queue.len());
+        }
+    }
+
+    async fn pop_front(&self) {
+        let mut queue = self.queue.lock().await;
+        queue.pop_front();
+        self.set_pending(queue.len());
+    }
+
+    fn set_pending(&self, len: usize) {
+        self.pending.store(len, Ordering::Release);
+        metrics::gauge!("im_kafka_spill_pending").set(len as f64);
+    }
+
+    /// 用队列中的消息重写暂存文件，先写临时文件再替换，避免中途退出损坏文件
+    async fn rewrite(&self, queue: &VecDeque<Msg>) -> Result<(), Error> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp).await?;
+        for msg in queue {
+            file.write_all(&encode_record(msg)).await?;
+        }
+        file.sync_all().await?;
+        fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// 是否为确定没有写入Kafka的发送失败，只有这类错误写入暂存队列
+///
+/// 本地队列已满、所有代理不可用或代理拒绝写入时消息一定没有写入；
+/// 发送超时、连接中断等错误发生时消息可能已经写入，和消息过大等错误一样直接返回给发送方
+pub fn is_undelivered(err: &KafkaError) -> bool {
+    matches!(
+        err,
+        KafkaError::MessageProduction(
+            RDKafkaErrorCode::QueueFull
+                | RDKafkaErrorCode::AllBrokersDown
+                | RDKafkaErrorCode::BrokerNotAvailable
+                | RDKafkaErrorCode::LeaderNotAvailable
+                | RDKafkaErrorCode::NotLeaderForPartition
+        )
+    )
+}
+
+/// 能否从Kafka获取元数据，获取元数据是阻塞调用，放到阻塞线程池执行
+async fn is_reachable(producer: &FutureProducer, topics: &TenantTopics) -> bool {
+    let producer = producer.clone();
+    let topic = topics.topic("").to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        producer
+            .client()
+            .fetch_metadata(Some(&topic), METADATA_TIMEOUT)
+            .map(|_| ())
+    })
+    .await;
+    match result {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            warn!("Kafka仍不可用，稍后补发暂存消息: {:?}", e);
+            false
+        }
+        Err(e) => {
+            warn!("检查Kafka是否可用失败: {:?}", e);
+            false
+        }
+    }
+}
+
+/// 编码一条暂存记录：4字节大端长度 + protobuf编码的消息
+fn encode_record(msg: &Msg) -> Vec<u8> {
+    let mut record = Vec::with_capacity(LEN_PREFIX + msg.encoded_len());
+    record.extend_from_slice(&(msg.encoded_len() as u32).to_be_bytes());
+    // 预留了足够容量，写入Vec不会失败
+    let _ = msg.encode(&mut record);
+    record
+}
+
+/// 解码暂存文件中的记录，返回完整的消息和已解码的字节数，遇到不完整或损坏的记录时停止
+fn decode_records(data: &[u8]) -> (Vec<Msg>, usize) {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while let Some(prefix) = data.get(offset..offset + LEN_PREFIX) {
+        let len = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        let start = offset + LEN_PREFIX;
+        let Some(body) = data.get(start..start + len) else {
+            break;
+        };
+        let Ok(msg) = Msg::decode(body) else {
+            break;
+        };
+        messages.push(msg);
+        offset = start + len;
+    }
+    (messages, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_records() {
+        let first = Msg {
+            server_id: "m1".to_string(),
+            send_id: "u1".to_string(),
+            content: b"hello".to_vec(),
+            ..Default::default()
+        };
+        let second = Msg {
+            server_id: "m2".to_string(),
+            ..Default::default()
+        };
+        let mut data = encode_record(&first);
+        data.extend(encode_record(&second));
+        let complete = data.len();
+
+        let (messages, consumed) = decode_records(&data);
+        assert_eq!(messages, vec![first.clone(), second]);
+        assert_eq!(consumed, complete);
+
+        // 写入中途退出留下的不完整记录被忽略
+        data.extend(encode_record(&first));
+        data.truncate(data.len() - 2);
+        let (messages, consumed) = decode_records(&data);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(consumed, complete);
+
+        assert_eq!(decode_records(&[]), (vec![], 0));
+    }
+
+    #[test]
+    fn test_is_undelivered() {
+        let err = KafkaError::MessageProduction;
+        assert!(is_undelivered(&err(RDKafkaErrorCode::QueueFull)));
+        assert!(is_undelivered(&err(RDKafkaErrorCode::LeaderNotAvailable)));
+        // 结果不确定的错误不暂存，避免重复写入
+        assert!(!is_undelivered(&err(RDKafkaErrorCode::MessageTimedOut)));
+        assert!(!is_undelivered(&err(RDKafkaErrorCode::RequestTimedOut)));
+        assert!(!is_undelivered(&err(
+            RDKafkaErrorCode::BrokerTransportFailure
+        )));
+        assert!(!is_undelivered(&err(RDKafkaErrorCode::MessageSizeTooLarge)));
+    }
+}
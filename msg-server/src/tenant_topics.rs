@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use common::config::TenantTopicsConfig;
+
+/// 主题名模板中租户ID的占位符
+const TENANT_PLACEHOLDER: &str = "{tenant}";
+
+/// 租户消息主题路由
+///
+/// 生产端按消息的租户选择主题，配置了独立主题的大租户写入按模板生成的主题，
+/// 其他租户写入默认主题；消费端按全部主题分别订阅，并按租户的配置限制消费速度
+#[derive(Debug, Clone)]
+pub struct TenantTopics {
+    default_topic: String,
+    // 租户ID -> 租户主题
+    tenants: HashMap<String, String>,
+    // 租户主题 -> 每条消息的最小处理间隔
+    throttles: HashMap<String, Duration>,
+}
+
+impl TenantTopics {
+    pub fn new(default_topic: String, config: &TenantTopicsConfig) -> Self {
+        let mut tenants = HashMap::new();
+        let mut throttles = HashMap::new();
+        if config.enabled {
+            for tenant in &config.tenants {
+                let topic = config.template.replace(TENANT_PLACEHOLDER, &tenant.id);
+                if let Some(limit) = tenant.max_msgs_per_sec.filter(|limit| *limit > 0) {
+                    throttles.insert(topic.clone(), Duration::from_secs(1) / limit);
+                }
+                tenants.insert(tenant.id.clone(), topic);
+            }
+        }
+        Self {
+            default_topic,
+            tenants,
+            throttles,
+        }
+    }
+
+    /// 默认主题
+    pub fn default_topic(&self) -> &str {
+        &self.default_topic
+    }
+
+    /// 消息写入的主题
+    pub fn topic(&self, tenant_id: &str) -> &str {
+        self.tenants
+            .get(tenant_id)
+            .map_or(self.default_topic.as_str(), String::as_str)
+    }
+
+    /// 大租户的独立主题
+    pub fn tenant_topics(&self) -> Vec<String> {
+        let mut topics: Vec<String> = self.tenants.values().cloned().collect();
+        topics.sort();
+        topics.dedup();
+        topics
+    }
+
+    /// 需要订阅的全部主题，默认主题排在第一位
+    pub fn topics(&self) -> Vec<String> {
+        let mut topics = vec![self.default_topic.clone()];
+        topics.extend(
+            self.tenant_topics()
+                .into_iter()
+                .filter(|topic| *topic != self.default_topic),
+        );
+        topics
+    }
+
+    /// 主题的消费限速，返回每条消息的最小处理间隔
+    pub fn throttle(&self, topic: &str) -> Option<Duration> {
+        self.throttles.get(topic).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::config::TenantTopicConfig;
+
+    #[test]
+    fn test_tenant_topics() {
+        let mut config = TenantTopicsConfig {
+            tenants: vec![
+                TenantTopicConfig {
+                    id: "10001".to_string(),
+                    max_msgs_per_sec: Some(100),
+                },
+                TenantTopicConfig {
+                    id: "10002".to_string(),
+                    max_msgs_per_sec: None,
+                },
+            ],
+            ..Default::default()
+        };
+
+        // 未启用时全部写入默认主题
+        let disabled = TenantTopics::new("rustIM-chat".to_string(), &config);
+        assert_eq!(disabled.topic("10001"), "rustIM-chat");
+        assert_eq!(disabled.topics(), vec!["rustIM-chat".to_string()]);
+
+        config.enabled = true;
+        let topics = TenantTopics::new("rustIM-chat".to_string(), &config);
+        assert_eq!(topics.topic("10001"), "im-msg-10001");
+        assert_eq!(topics.topic("10003"), "rustIM-chat");
+        assert_eq!(topics.topic(""), "rustIM-chat");
+        assert_eq!(
+            topics.topics(),
+            vec![
+                "rustIM-chat".to_string(),
+                "im-msg-10001".to_string(),
+                "im-msg-10002".to_string()
+            ]
+        );
+        assert_eq!(
+            topics.throttle("im-msg-10001"),
+            Some(Duration::from_millis(10))
+        );
+        assert_eq!(topics.throttle("im-msg-10002"), None);
+        assert_eq!(topics.throttle("rustIM-chat"), None);
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::{ClientConfig, Message};
+use tracing::{debug, error, info};
+
+use cache::{Cache, ImageDerivatives};
+use common::attachment::UploadCompletedEvent;
+use common::config::AppConfig;
+use common::error::Error;
+use oss::thumbnail::{generate_thumbnail, thumbnail_key, THUMBNAIL_MAX_SIDE};
+use oss::Oss;
+
+/// 缩略图生成服务
+///
+/// 消费文件上传完成事件，为图片生成缩略图和blurhash，缩略图写回OSS，
+/// 衍生数据写入缓存，发送图片消息时由消息服务填充到附件描述中。
+pub struct ThumbnailService {
+    consumer: StreamConsumer,
+    oss: Arc<dyn Oss>,
+    cache: Arc<dyn Cache>,
+}
+
+impl ThumbnailService {
+    pub async fn new(config: &AppConfig) -> Self {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("group.id", format!("{}-thumbnail", config.kafka.group))
+            .set("bootstrap.servers", config.kafka.hosts.join(","))
+            .set("enable.auto.commit", "false")
+            .set(
+                "session.timeout.ms",
+                config.kafka.consumer.session_timeout.to_string(),
+            )
+            .set(
+                "socket.timeout.ms",
+                config.kafka.connect_timeout.to_string(),
+            )
+            .set("enable.partition.eof", "false")
+            .set(
+                "auto.offset.reset",
+                config.kafka.consumer.auto_offset_reset.clone(),
+            )
+            .create()
+            .expect("缩略图消费者创建失败");
+
+        consumer
+            .subscribe(&[&config.kafka.upload_topic])
+            .expect("无法订阅上传事件主题");
+
+        Self {
+            consumer,
+            oss: oss::oss(config).await,
+            cache: cache::cache(config),
+        }
+    }
+
+    /// 启动消费循环
+    pub async fn consume(&self) -> Result<(), Error> {
+        info!("缩略图生成服务已启动");
+        loop {
+            match self.consumer.recv().await {
+                Err(e) => error!("Kafka错误: {}", e),
+                Ok(m) => {
+                    if let Some(Ok(payload)) = m.payload_view::<str>() {
+                        // 单张图片处理失败（格式损坏等）不阻塞后续事件，客户端回退为加载原图
+                        if let Err(e) = self.handle_event(payload).await {
+                            error!("生成缩略图失败: {:?}", e);
+                        }
+                    }
+                    if let Err(e) = self.consumer.commit_message(&m, CommitMode::Async) {
+                        error!("提交消息偏移量失败: {:?}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_event(&self, payload: &str) -> Result<(), Error> {
+        let event: UploadCompletedEvent = serde_json::from_str(payload)?;
+        if !event.mime_type.to_lowercase().starts_with("image/") {
+            return Ok(());
+        }
+        debug!("为图片生成缩略图: {}", event.object_key);
+
+        let data = self.oss.download_file(&event.object_key).await?;
+        let thumbnail =
+            tokio::task::spawn_blocking(move || generate_thumbnail(&data, THUMBNAIL_MAX_SIDE))
+                .await
+                .map_err(|e| Error::Internal(e.to_string()))??;
+
+        let key = thumbnail_key(&event.object_key);
+        self.oss.upload_file(&key, thumbnail.data).await?;
+
+        let derivatives = ImageDerivatives {
+            thumbnail_key: key,
+            blurhash: thumbnail.blurhash,
+            width: thumbnail.width,
+            height: thumbnail.height,
+        };
+        self.cache
+            .save_image_derivatives(&event.object_key, &derivatives)
+            .await
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use common::config::TransactionProviderConfig;
+use common::error::Error;
+
+/// 外部交易服务的处理结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookDecision {
+    /// 已确认，附带外部交易服务返回的回执（JSON）
+    Approved(String),
+    /// 已拒绝，附带拒绝原因
+    Declined(String),
+}
+
+/// 交给外部交易服务的事务信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionRecord {
+    pub transaction_id: String,
+    pub sender_id: String,
+    pub receiver_id: String,
+    pub msg_type: i32,
+    pub total_shares: u32,
+    pub expires_at: i64,
+    /// 客户端填写的参数，原样转交
+    pub payload: Value,
+}
+
+/// 事务消息的外部交易服务回调
+///
+/// 网络超时后会以相同的事务ID和领取者重试，实现方必须按事务ID（领取时加上领取者）保证幂等
+#[async_trait]
+pub trait TransactionHook: Send + Sync {
+    /// 发送前确认，例如冻结发送者的余额
+    async fn authorize(&self, record: &TransactionRecord) -> Result<HookDecision, Error>;
+
+    /// 领取一份，例如转账给领取者
+    async fn claim(
+        &self,
+        record: &TransactionRecord,
+        claimer_id: &str,
+        share_index: u32,
+    ) -> Result<HookDecision, Error>;
+
+    /// 到期后退还未领取的份数
+    async fn refund(&self, record: &TransactionRecord, remaining_shares: u32) -> Result<(), Error>;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HookRequest<'a> {
+    #[serde(flatten)]
+    record: &'a TransactionRecord,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    claimer_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    share_index: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining_shares: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct HookResponse {
+    approved: bool,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    receipt: Option<Value>,
+}
+
+/// 通过HTTP回调外部交易服务
+///
+/// 请求: `POST {endpoint}/authorize`、`/claim`、`/refund`，body 为事务信息，
+/// 领取时附带 `claimerId`、`shareIndex`，退还时附带 `remainingShares`；
+/// 响应: `{"approved": bool, "reason": "...", "receipt": {...}}`
+pub struct HttpTransactionHook {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl HttpTransactionHook {
+    pub fn new(config: &TransactionProviderConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("创建外部交易服务HTTP客户端失败");
+        Self {
+            client,
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn call(&self, action: &str, request: &HookRequest<'_>) -> Result<HookDecision, Error> {
+        let response: HookResponse = self
+            .client
+            .post(format!("{}/{}", self.endpoint, action))
+            .json(request)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| Error::Internal(format!("调用外部交易服务失败: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| Error::Internal(format!("解析外部交易服务结果失败: {}", e)))?;
+
+        if response.approved {
+            Ok(HookDecision::Approved(
+                response.receipt.unwrap_or(Value::Null).to_string(),
+            ))
+        } else {
+            Ok(HookDecision::Declined(
+                response.reason.unwrap_or_else(|| "unknown".to_string()),
+            ))
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionHook for HttpTransactionHook {
+    async fn authorize(&self, record: &TransactionRecord) -> Result<HookDecision, Error> {
+        let request = HookRequest {
+            record,
+            claimer_id: None,
+            share_index: None,
+            remaining_shares: None,
+        };
+        self.call("authorize", &request).await
+    }
+
+    async fn claim(
+        &self,
+        record: &TransactionRecord,
+        claimer_id: &str,
+        share_index: u32,
+    ) -> Result<HookDecision, Error> {
+        let request = HookRequest {
+            record,
+            claimer_id: Some(claimer_id),
+            share_index: Some(share_index),
+            remaining_shares: None,
+        };
+        self.call("claim", &request).await
+    }
+
+    async fn refund(&self, record: &TransactionRecord, remaining_shares: u32) -> Result<(), Error> {
+        let request = HookRequest {
+            record,
+            claimer_id: None,
+            share_index: None,
+            remaining_shares: Some(remaining_shares),
+        };
+        match self.call("refund", &request).await? {
+            HookDecision::Approved(_) => Ok(()),
+            HookDecision::Declined(reason) => {
+                Err(Error::Internal(format!("外部交易服务拒绝退还: {}", reason)))
+            }
+        }
+    }
+}
@@ -0,0 +1,843 @@
+//! 事务消息
+//!
+//! 红包、礼物等消息的金额由外部交易服务处理，消息服务负责发送前确认、份数分配、
+//! 领取去重和到期退还。每份在Mongo中以待领取的份数编号数组表示，领取时原子弹出一个编号，
+//! 外部交易服务拒绝领取时放回
+
+mod hook;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::Collection;
+use nanoid::nanoid;
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Response, Status};
+use tracing::{error, info, warn};
+
+use common::config::{AppConfig, TransactionConfig};
+use common::error::Error;
+use common::grpc::subject::{check_subject, forwarded_subject};
+use common::message::{Msg, MsgType};
+use common::proto::transaction::transaction_service_server::TransactionService;
+use common::proto::transaction::{
+    ClaimTransactionRequest, ClaimTransactionResponse, GetTransactionRequest, Transaction,
+    TransactionClaim, TransactionResponse, TransactionStatus,
+};
+use common::tenant_db::MongoRouter;
+use common::time_sync::now_millis;
+use common::transaction::TransactionalContent;
+
+use crate::member_cache::GroupMemberCache;
+
+pub use hook::{HookDecision, HttpTransactionHook, TransactionHook, TransactionRecord};
+
+/// 事务消息集合名称
+const TRANSACTION_COLLECTION: &str = "transactional_messages";
+
+/// 领取记录集合名称
+const CLAIM_COLLECTION: &str = "transaction_claims";
+
+/// 单次退还的最大事务数
+const REFUND_BATCH_SIZE: i64 = 100;
+
+/// 领取回调失败后记录保持处理中，超过该时间后同一用户重试时沿用原来的份数再次回调
+const PENDING_CLAIM_TIMEOUT_MS: i64 = 60_000;
+
+/// 领取记录状态
+const CLAIM_PENDING: &str = "pending";
+const CLAIM_DONE: &str = "done";
+
+/// 保存的事务消息
+struct StoredTransaction {
+    record: TransactionRecord,
+    provider: String,
+    status: TransactionStatus,
+    // 待领取的份数编号
+    available_shares: Vec<i32>,
+    created_at: i64,
+}
+
+impl StoredTransaction {
+    fn from_doc(doc: &Document) -> Self {
+        Self {
+            record: TransactionRecord {
+                transaction_id: doc.get_str("_id").unwrap_or_default().to_string(),
+                sender_id: doc.get_str("sender_id").unwrap_or_default().to_string(),
+                receiver_id: doc.get_str("receiver_id").unwrap_or_default().to_string(),
+                msg_type: doc.get_i32("msg_type").unwrap_or_default(),
+                total_shares: doc.get_i32("total_shares").unwrap_or_default() as u32,
+                expires_at: doc.get_i64("expires_at").unwrap_or_default(),
+                payload: doc
+                    .get_str("payload")
+                    .ok()
+                    .and_then(|payload| serde_json::from_str(payload).ok())
+                    .unwrap_or_default(),
+            },
+            provider: doc.get_str("provider").unwrap_or_default().to_string(),
+            status: TransactionStatus::try_from(doc.get_i32("status").unwrap_or_default())
+                .unwrap_or(TransactionStatus::Authorizing),
+            available_shares: doc
+                .get_array("available_shares")
+                .map(|shares| shares.iter().filter_map(|share| share.as_i32()).collect())
+                .unwrap_or_default(),
+            created_at: doc.get_i64("created_at").unwrap_or_default(),
+        }
+    }
+
+    fn to_proto(&self, claims: Vec<TransactionClaim>) -> Transaction {
+        Transaction {
+            id: self.record.transaction_id.clone(),
+            provider: self.provider.clone(),
+            sender_id: self.record.sender_id.clone(),
+            receiver_id: self.record.receiver_id.clone(),
+            msg_type: self.record.msg_type,
+            status: self.status as i32,
+            total_shares: self.record.total_shares as i32,
+            remaining_shares: self.available_shares.len() as i32,
+            expires_at: self.record.expires_at,
+            created_at: self.created_at,
+            claims,
+        }
+    }
+}
+
+/// 同一用户已有的领取记录
+#[derive(Debug, PartialEq)]
+enum ExistingClaim {
+    /// 没有记录，新领取
+    New,
+    /// 已领取，返回原记录
+    Done(TransactionClaim),
+    /// 处理中且未超时
+    InProgress,
+    /// 处理中已超时，接管后沿用原来的份数，尚未分配份数时重新分配
+    Stale {
+        created_at: i64,
+        share_index: Option<i32>,
+    },
+}
+
+impl ExistingClaim {
+    fn from_doc(doc: Option<&Document>, now: i64) -> Self {
+        let Some(doc) = doc else {
+            return Self::New;
+        };
+        if doc.get_str("status").unwrap_or_default() == CLAIM_DONE {
+            return Self::Done(claim_from_doc(doc));
+        }
+        let created_at = doc.get_i64("created_at").unwrap_or_default();
+        if created_at + PENDING_CLAIM_TIMEOUT_MS > now {
+            return Self::InProgress;
+        }
+        Self::Stale {
+            created_at,
+            share_index: doc.get_i32("share_index").ok(),
+        }
+    }
+}
+
+fn claim_from_doc(doc: &Document) -> TransactionClaim {
+    TransactionClaim {
+        claimer_id: doc.get_str("claimer_id").unwrap_or_default().to_string(),
+        share_index: doc.get_i32("share_index").unwrap_or_default(),
+        receipt: doc.get_str("receipt").unwrap_or_default().to_string(),
+        claimed_at: doc.get_i64("claimed_at").unwrap_or_default(),
+    }
+}
+
+/// 事务消息的存储和状态流转
+///
+/// 发送时先记为确认中，外部交易服务确认后才投递；确认回调失败的事务和到期未领完的事务
+/// 由后台任务调用外部交易服务退还，退还回调按事务ID幂等，失败时下次继续重试。
+/// 事务存放在发送者租户的数据库集群，领取请求在同一租户的上下文中处理
+#[derive(Clone)]
+pub struct TransactionStore {
+    mongo: MongoRouter,
+    hooks: Arc<HashMap<String, Arc<dyn TransactionHook>>>,
+    members: GroupMemberCache,
+    config: TransactionConfig,
+}
+
+impl TransactionStore {
+    /// 创建事务消息存储，未配置外部交易服务时返回None
+    pub fn new(config: &AppConfig, mongo: MongoRouter, members: GroupMemberCache) -> Option<Self> {
+        if config.transactions.providers.is_empty() {
+            return None;
+        }
+
+        let hooks = config
+            .transactions
+            .providers
+            .iter()
+            .map(|(name, provider)| {
+                let hook: Arc<dyn TransactionHook> = Arc::new(HttpTransactionHook::new(provider));
+                (name.clone(), hook)
+            })
+            .collect();
+        Some(Self {
+            mongo,
+            hooks: Arc::new(hooks),
+            members,
+            config: config.transactions.clone(),
+        })
+    }
+
+    /// 启动到期退还任务，每个数据库集群各一个
+    pub fn start(&self) {
+        for mongo in self.mongo.targets() {
+            let store = Self {
+                mongo,
+                ..self.clone()
+            };
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(
+                    store.config.refund_interval_secs.max(1),
+                ));
+                loop {
+                    interval.tick().await;
+                    store.refund_expired().await;
+                }
+            });
+        }
+    }
+
+    /// 当前租户的事务集合
+    fn transactions(&self) -> Collection<Document> {
+        self.mongo.database().collection(TRANSACTION_COLLECTION)
+    }
+
+    /// 当前租户的领取记录集合
+    fn claims(&self) -> Collection<Document> {
+        self.mongo.database().collection(CLAIM_COLLECTION)
+    }
+
+    fn hook(&self, provider: &str) -> Result<&Arc<dyn TransactionHook>, Status> {
+        self.hooks.get(provider).ok_or_else(|| {
+            Status::failed_precondition(format!("未配置的外部交易服务: {}", provider))
+        })
+    }
+
+    /// 发送前由外部交易服务确认，确认后把事务ID和到期时间写入消息内容，返回事务ID
+    pub async fn authorize(&self, msg: &mut Msg) -> Result<String, Status> {
+        let Some(mut content) = TransactionalContent::from_msg_content(
+            msg.msg_type,
+            msg.content_type,
+            &msg.content,
+            &self.config,
+        )?
+        else {
+            return Err(Status::invalid_argument("不是事务消息"));
+        };
+        let hook = self.hook(&content.provider)?;
+
+        let now = now_millis();
+        let record = TransactionRecord {
+            transaction_id: nanoid!(),
+            sender_id: msg.send_id.clone(),
+            receiver_id: msg.receiver_id.clone(),
+            msg_type: msg.msg_type,
+            total_shares: content.shares,
+            expires_at: now + content.expire_secs(&self.config) * 1000,
+            payload: content.payload.clone(),
+        };
+        self.transactions()
+            .insert_one(
+                doc! {
+                    "_id": &record.transaction_id,
+                    "provider": &content.provider,
+                    "sender_id": &record.sender_id,
+                    "receiver_id": &record.receiver_id,
+                    "msg_type": record.msg_type,
+                    "total_shares": record.total_shares as i32,
+                    "available_shares": (0..record.total_shares as i32).collect::<Vec<i32>>(),
+                    "status": TransactionStatus::Authorizing as i32,
+                    "expires_at": record.expires_at,
+                    "created_at": now,
+                    "payload": record.payload.to_string(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("保存事务消息失败: {}", e)))?;
+
+        match hook.authorize(&record).await {
+            Ok(HookDecision::Approved(_)) => {
+                self.set_status(
+                    &record.transaction_id,
+                    TransactionStatus::Authorizing,
+                    TransactionStatus::Active,
+                )
+                .await?;
+            }
+            Ok(HookDecision::Declined(reason)) => {
+                self.set_status(
+                    &record.transaction_id,
+                    TransactionStatus::Authorizing,
+                    TransactionStatus::Declined,
+                )
+                .await?;
+                return Err(Status::failed_precondition(format!(
+                    "外部交易服务拒绝: {}",
+                    reason
+                )));
+            }
+            // 外部交易服务可能已经处理，保持确认中，由退还任务按全部份数退还
+            Err(e) => {
+                warn!("确认事务 {} 失败: {:?}", record.transaction_id, e);
+                return Err(Status::unavailable("暂时无法发送，请稍后重试"));
+            }
+        }
+
+        content.transaction_id = Some(record.transaction_id.clone());
+        content.expires_at = Some(record.expires_at);
+        msg.content = serde_json::to_vec(&content)
+            .map_err(|e| Status::internal(format!("序列化事务消息失败: {}", e)))?;
+        Ok(record.transaction_id)
+    }
+
+    /// 消息未能投递时提前结束事务，由退还任务在下次检查时退还
+    pub async fn abandon(&self, transaction_id: &str) {
+        let expires_at = now_millis() - self.config.refund_grace_secs * 1000;
+        if let Err(e) = self
+            .transactions()
+            .update_one(
+                doc! { "_id": transaction_id, "status": TransactionStatus::Active as i32 },
+                doc! { "$set": { "expires_at": expires_at } },
+                None,
+            )
+            .await
+        {
+            error!("结束未投递的事务 {} 失败: {}", transaction_id, e);
+        }
+    }
+
+    async fn set_status(
+        &self,
+        transaction_id: &str,
+        from: TransactionStatus,
+        to: TransactionStatus,
+    ) -> Result<bool, Error> {
+        let result = self
+            .transactions()
+            .update_one(
+                doc! { "_id": transaction_id, "status": from as i32 },
+                doc! { "$set": { "status": to as i32 } },
+                None,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("更新事务状态失败: {}", e)))?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn load(&self, transaction_id: &str) -> Result<Option<StoredTransaction>, Error> {
+        let doc = self
+            .transactions()
+            .find_one(doc! { "_id": transaction_id }, None)
+            .await
+            .map_err(|e| Error::Internal(format!("查询事务消息失败: {}", e)))?;
+        Ok(doc.as_ref().map(StoredTransaction::from_doc))
+    }
+
+    /// 已完成的领取记录，按领取时间排列
+    async fn load_claims(&self, transaction_id: &str) -> Result<Vec<TransactionClaim>, Error> {
+        let options = FindOptions::builder()
+            .sort(doc! { "claimed_at": 1 })
+            .build();
+        let mut cursor = self
+            .claims()
+            .find(
+                doc! { "transaction_id": transaction_id, "status": CLAIM_DONE },
+                options,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("查询领取记录失败: {}", e)))?;
+        let mut claims = Vec::new();
+        while let Some(doc) = cursor
+            .try_next()
+            .await
+            .map_err(|e| Error::Internal(format!("查询领取记录失败: {}", e)))?
+        {
+            claims.push(claim_from_doc(&doc));
+        }
+        Ok(claims)
+    }
+
+    /// 单聊只有接收者可以领取，群聊的群成员都可以领取
+    async fn can_claim(&self, record: &TransactionRecord, user_id: &str) -> Result<bool, Error> {
+        if record.msg_type == MsgType::SingleMsg as i32 {
+            return Ok(record.receiver_id == user_id);
+        }
+        let members = self
+            .members
+            .query_group_members_id(&record.receiver_id)
+            .await?;
+        Ok(members.iter().any(|member| member == user_id))
+    }
+
+    /// 弹出一个待领取的份数编号，已领完、已到期或不可领取时返回None
+    async fn take_share(&self, transaction_id: &str) -> Result<Option<i32>, Error> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::Before)
+            .build();
+        let doc = self
+            .transactions()
+            .find_one_and_update(
+                doc! {
+                    "_id": transaction_id,
+                    "status": TransactionStatus::Active as i32,
+                    "expires_at": { "$gt": now_millis() },
+                    "available_shares.0": { "$exists": true },
+                },
+                doc! { "$pop": { "available_shares": -1 } },
+                options,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("分配领取份数失败: {}", e)))?;
+        Ok(doc.and_then(|doc| {
+            doc.get_array("available_shares")
+                .ok()
+                .and_then(|shares| shares.first())
+                .and_then(|share| share.as_i32())
+        }))
+    }
+
+    /// 外部交易服务拒绝领取时放回份数
+    ///
+    /// 已领完的事务恢复为可领取；退还中的事务保持退还中，放回的份数由退还任务一并退还
+    async fn return_share(&self, transaction_id: &str, share_index: i32) -> Result<(), Error> {
+        let refunding = TransactionStatus::Refunding as i32;
+        self.transactions()
+            .update_one(
+                doc! {
+                    "_id": transaction_id,
+                    "status": { "$in": [
+                        TransactionStatus::Active as i32,
+                        TransactionStatus::Completed as i32,
+                        refunding,
+                    ] },
+                },
+                vec![doc! { "$set": {
+                    "available_shares": { "$concatArrays": ["$available_shares", [share_index]] },
+                    "status": { "$cond": [
+                        { "$eq": ["$status", refunding] },
+                        refunding,
+                        TransactionStatus::Active as i32,
+                    ] },
+                }}],
+                None,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("放回领取份数失败: {}", e)))?;
+        Ok(())
+    }
+
+    /// 全部领完时标记为已完成
+    async fn complete_if_empty(&self, transaction_id: &str) -> Result<(), Error> {
+        self.transactions()
+            .update_one(
+                doc! {
+                    "_id": transaction_id,
+                    "status": TransactionStatus::Active as i32,
+                    "available_shares": { "$size": 0 },
+                },
+                doc! { "$set": { "status": TransactionStatus::Completed as i32 } },
+                None,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("更新事务状态失败: {}", e)))?;
+        Ok(())
+    }
+
+    /// 领取一份，返回领取记录和是否为重复领取
+    pub async fn claim(
+        &self,
+        transaction_id: &str,
+        user_id: &str,
+    ) -> Result<(TransactionClaim, bool), Status> {
+        let txn = self
+            .load(transaction_id)
+            .await?
+            .ok_or_else(|| Status::not_found("事务消息不存在"))?;
+        if !self.can_claim(&txn.record, user_id).await? {
+            return Err(Status::permission_denied("无权领取"));
+        }
+        let hook = self.hook(&txn.provider)?;
+
+        // 插入处理中的领取记录，同一用户已有记录时返回原记录
+        let claim_id = format!("{}:{}", transaction_id, user_id);
+        let now = now_millis();
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::Before)
+            .build();
+        let existing = self
+            .claims()
+            .find_one_and_update(
+                doc! { "_id": &claim_id },
+                doc! { "$setOnInsert": {
+                    "transaction_id": transaction_id,
+                    "claimer_id": user_id,
+                    "status": CLAIM_PENDING,
+                    "created_at": now,
+                }},
+                options,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("保存领取记录失败: {}", e)))?;
+
+        let resumed_share = match ExistingClaim::from_doc(existing.as_ref(), now) {
+            ExistingClaim::New => None,
+            ExistingClaim::Done(claim) => return Ok((claim, true)),
+            ExistingClaim::InProgress => {
+                return Err(Status::aborted("领取处理中，请稍后重试"));
+            }
+            ExistingClaim::Stale {
+                created_at,
+                share_index,
+            } => {
+                // 接管超时的领取记录，并发重试时只有一个请求能接管
+                let result = self
+                    .claims()
+                    .update_one(
+                        doc! { "_id": &claim_id, "created_at": created_at },
+                        doc! { "$set": { "created_at": now } },
+                        None,
+                    )
+                    .await
+                    .map_err(|e| Error::Internal(format!("更新领取记录失败: {}", e)))?;
+                if result.modified_count == 0 {
+                    return Err(Status::aborted("领取处理中，请稍后重试"));
+                }
+                share_index
+            }
+        };
+
+        let share_index = match resumed_share {
+            Some(share_index) => share_index,
+            None => match self.take_share(transaction_id).await? {
+                Some(share_index) => {
+                    self.claims()
+                        .update_one(
+                            doc! { "_id": &claim_id },
+                            doc! { "$set": { "share_index": share_index } },
+                            None,
+                        )
+                        .await
+                        .map_err(|e| Error::Internal(format!("更新领取记录失败: {}", e)))?;
+                    share_index
+                }
+                None => {
+                    self.delete_claim(&claim_id).await;
+                    return Err(Status::failed_precondition("已领完或已过期"));
+                }
+            },
+        };
+
+        match hook.claim(&txn.record, user_id, share_index as u32).await {
+            Ok(HookDecision::Approved(receipt)) => {
+                let claimed_at = now_millis();
+                self.claims()
+                    .update_one(
+                        doc! { "_id": &claim_id },
+                        doc! { "$set": {
+                            "status": CLAIM_DONE,
+                            "receipt": &receipt,
+                            "claimed_at": claimed_at,
+                        }},
+                        None,
+                    )
+                    .await
+                    .map_err(|e| Error::Internal(format!("更新领取记录失败: {}", e)))?;
+                self.complete_if_empty(transaction_id).await?;
+                Ok((
+                    TransactionClaim {
+                        claimer_id: user_id.to_string(),
+                        share_index,
+                        receipt,
+                        claimed_at,
+                    },
+                    false,
+                ))
+            }
+            Ok(HookDecision::Declined(reason)) => {
+                self.return_share(transaction_id, share_index).await?;
+                self.delete_claim(&claim_id).await;
+                Err(Status::failed_precondition(format!("领取失败: {}", reason)))
+            }
+            // 外部交易服务可能已经处理，保持处理中，超时后重试沿用同一份
+            Err(e) => {
+                warn!("领取事务 {} 失败: {:?}", transaction_id, e);
+                Err(Status::unavailable("领取失败，请稍后重试"))
+            }
+        }
+    }
+
+    async fn delete_claim(&self, claim_id: &str) {
+        if let Err(e) = self.claims().delete_one(doc! { "_id": claim_id }, None).await {
+            error!("删除领取记录 {} 失败: {}", claim_id, e);
+        }
+    }
+
+    /// 退还到期未领完的事务和确认失败的事务
+    async fn refund_expired(&self) {
+        loop {
+            let due = now_millis() - self.config.refund_grace_secs * 1000;
+            let options = FindOptions::builder().limit(REFUND_BATCH_SIZE).build();
+            let filter = doc! { "$or": [
+                { "status": TransactionStatus::Active as i32, "expires_at": { "$lte": due } },
+                { "status": TransactionStatus::Authorizing as i32, "created_at": { "$lte": due } },
+                { "status": TransactionStatus::Refunding as i32 },
+            ]};
+            let docs: Vec<Document> = match self.transactions().find(filter, options).await {
+                Ok(cursor) => match cursor.try_collect().await {
+                    Ok(docs) => docs,
+                    Err(e) => {
+                        error!("查询到期事务失败: {}", e);
+                        return;
+                    }
+                },
+                Err(e) => {
+                    error!("查询到期事务失败: {}", e);
+                    return;
+                }
+            };
+
+            let mut failed = 0;
+            for doc in &docs {
+                let txn = StoredTransaction::from_doc(doc);
+                if let Err(e) = self.refund(&txn).await {
+                    error!("退还事务 {} 失败: {:?}", txn.record.transaction_id, e);
+                    failed += 1;
+                }
+            }
+            if !docs.is_empty() {
+                info!("已退还 {} 个到期事务", docs.len() - failed);
+            }
+            // 有失败的事务时等下次检查再重试，避免同一批反复失败
+            if (docs.len() as i64) < REFUND_BATCH_SIZE || failed > 0 {
+                return;
+            }
+        }
+    }
+
+    async fn refund(&self, txn: &StoredTransaction) -> Result<(), Error> {
+        let transaction_id = &txn.record.transaction_id;
+        if txn.status != TransactionStatus::Refunding
+            && !self
+                .set_status(transaction_id, txn.status, TransactionStatus::Refunding)
+                .await?
+        {
+            // 已被领完或由其他实例处理
+            return Ok(());
+        }
+
+        // 进行中的领取被拒绝时会放回份数，等这些领取结束后再退还，保持退还中由下次检查继续
+        if self.has_pending_claims(transaction_id).await? {
+            return Ok(());
+        }
+
+        // 重新读取转为退还中之后的待领取份数；确认失败的事务没有投递，待领取的是全部份数
+        let Some(current) = self.load(transaction_id).await? else {
+            return Ok(());
+        };
+        let remaining_shares = current.available_shares.len() as u32;
+        if remaining_shares > 0 {
+            let hook = self.hooks.get(&txn.provider).ok_or_else(|| {
+                Error::Internal(format!("未配置的外部交易服务: {}", txn.provider))
+            })?;
+            hook.refund(&txn.record, remaining_shares).await?;
+        }
+
+        self.set_status(
+            transaction_id,
+            TransactionStatus::Refunding,
+            TransactionStatus::Refunded,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// 是否有已分配份数、尚未超时的领取
+    async fn has_pending_claims(&self, transaction_id: &str) -> Result<bool, Error> {
+        let count = self
+            .claims()
+            .count_documents(
+                doc! {
+                    "transaction_id": transaction_id,
+                    "status": CLAIM_PENDING,
+                    "share_index": { "$exists": true },
+                    "created_at": { "$gt": now_millis() - PENDING_CLAIM_TIMEOUT_MS },
+                },
+                None,
+            )
+            .await
+            .map_err(|e| Error::Internal(format!("查询领取记录失败: {}", e)))?;
+        Ok(count > 0)
+    }
+}
+
+/// 事务消息gRPC服务
+pub struct TransactionRpcService {
+    store: TransactionStore,
+}
+
+impl TransactionRpcService {
+    pub fn new(store: TransactionStore) -> Self {
+        Self { store }
+    }
+
+    /// 查询和领取只接受网关转发的用户请求，操作人必须是请求中的用户
+    fn check_caller(
+        metadata: &MetadataMap,
+        transaction_id: &str,
+        user_id: &str,
+    ) -> Result<(), Status> {
+        if forwarded_subject(metadata).is_none() {
+            return Err(Status::unauthenticated("缺少认证用户"));
+        }
+        check_subject(metadata, user_id)?;
+        if transaction_id.is_empty() || user_id.is_empty() {
+            return Err(Status::invalid_argument("事务ID和用户ID不能为空"));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl TransactionService for TransactionRpcService {
+    async fn get_transaction(
+        &self,
+        request: Request<GetTransactionRequest>,
+    ) -> Result<Response<TransactionResponse>, Status> {
+        let req = request.get_ref();
+        Self::check_caller(request.metadata(), &req.transaction_id, &req.user_id)?;
+        let req = request.into_inner();
+
+        let txn = self
+            .store
+            .load(&req.transaction_id)
+            .await?
+            .ok_or_else(|| Status::not_found("事务消息不存在"))?;
+        if txn.record.sender_id != req.user_id
+            && !self.store.can_claim(&txn.record, &req.user_id).await?
+        {
+            return Err(Status::permission_denied("无权查看该事务消息"));
+        }
+
+        let claims = self.store.load_claims(&req.transaction_id).await?;
+        Ok(Response::new(TransactionResponse {
+            transaction: Some(txn.to_proto(claims)),
+        }))
+    }
+
+    async fn claim_transaction(
+        &self,
+        request: Request<ClaimTransactionRequest>,
+    ) -> Result<Response<ClaimTransactionResponse>, Status> {
+        let req = request.get_ref();
+        Self::check_caller(request.metadata(), &req.transaction_id, &req.user_id)?;
+        let req = request.into_inner();
+
+        let (claim, duplicate) = self.store.claim(&req.transaction_id, &req.user_id).await?;
+        Ok(Response::new(ClaimTransactionResponse {
+            claim: Some(claim),
+            duplicate,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::grpc::subject::SUBJECT_METADATA_KEY;
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn test_existing_claim() {
+        let now = 1_000_000;
+        assert_eq!(ExistingClaim::from_doc(None, now), ExistingClaim::New);
+
+        // 已领取的记录原样返回
+        let done = doc! {
+            "claimer_id": "u1", "status": CLAIM_DONE, "share_index": 2,
+            "receipt": "r1", "claimed_at": now - 10, "created_at": now - 20,
+        };
+        assert_eq!(
+            ExistingClaim::from_doc(Some(&done), now),
+            ExistingClaim::Done(TransactionClaim {
+                claimer_id: "u1".to_string(),
+                share_index: 2,
+                receipt: "r1".to_string(),
+                claimed_at: now - 10,
+            })
+        );
+
+        // 未超时的处理中记录不能重复领取
+        let pending = doc! { "status": CLAIM_PENDING, "share_index": 1, "created_at": now - 10 };
+        assert_eq!(
+            ExistingClaim::from_doc(Some(&pending), now),
+            ExistingClaim::InProgress
+        );
+
+        // 超时后接管，沿用原来的份数；尚未分配份数时重新分配
+        let created_at = now - PENDING_CLAIM_TIMEOUT_MS;
+        let stale = doc! { "status": CLAIM_PENDING, "share_index": 1, "created_at": created_at };
+        assert_eq!(
+            ExistingClaim::from_doc(Some(&stale), now),
+            ExistingClaim::Stale {
+                created_at,
+                share_index: Some(1),
+            }
+        );
+        let stale = doc! { "status": CLAIM_PENDING, "created_at": created_at };
+        assert_eq!(
+            ExistingClaim::from_doc(Some(&stale), now),
+            ExistingClaim::Stale {
+                created_at,
+                share_index: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stored_transaction() {
+        let doc = doc! {
+            "_id": "t1", "provider": "wallet", "sender_id": "u1", "receiver_id": "g1",
+            "msg_type": MsgType::GroupMsg as i32, "total_shares": 3, "available_shares": [2],
+            "status": TransactionStatus::Refunding as i32, "expires_at": 200_i64,
+            "created_at": 100_i64, "payload": "{\"amount\":300}",
+        };
+        let txn = StoredTransaction::from_doc(&doc);
+        assert_eq!(txn.status, TransactionStatus::Refunding);
+        assert_eq!(txn.record.payload["amount"], 300);
+
+        let proto = txn.to_proto(Vec::new());
+        assert_eq!(proto.total_shares, 3);
+        assert_eq!(proto.remaining_shares, 1);
+
+        // 无法识别的状态按确认中处理，由退还任务处理
+        let txn = StoredTransaction::from_doc(&doc! { "_id": "t2", "status": 99 });
+        assert_eq!(txn.status, TransactionStatus::Authorizing);
+    }
+
+    #[test]
+    fn test_check_caller() {
+        let mut metadata = MetadataMap::new();
+        let err = TransactionRpcService::check_caller(&metadata, "t1", "u1").unwrap_err();
+        assert_eq!(err.code(), Code::Unauthenticated);
+
+        metadata.insert(SUBJECT_METADATA_KEY, "u2".parse().unwrap());
+        let err = TransactionRpcService::check_caller(&metadata, "t1", "u1").unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        let err = TransactionRpcService::check_caller(&metadata, "", "u2").unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        assert!(TransactionRpcService::check_caller(&metadata, "t1", "u2").is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::warn;
+
+use cache::{Cache, MessageUsage};
+use common::config::UsageConfig;
+use common::message::Msg;
+
+/// 用量的累加键：小时（yyyyMMddHH）、租户ID、发送者ID
+type UsageKey = (String, String, String);
+
+/// 消息用量计量
+///
+/// 消费者处理聊天消息时在内存中按小时、租户和发送者累加条数和字节数，
+/// 后台任务定期批量写入Redis，由用户服务每小时汇总到数据库。
+/// 写入Redis失败时用量留在内存中等待下次写入；Kafka重复投递的消息会被重复计量，
+/// 关闭前最后一次写入仍失败的用量会丢失
+#[derive(Clone)]
+pub struct UsageMeter {
+    cache: Arc<dyn Cache>,
+    pending: Arc<Mutex<HashMap<UsageKey, (i64, i64)>>>,
+    config: UsageConfig,
+}
+
+impl UsageMeter {
+    pub fn new(config: &UsageConfig, cache: Arc<dyn Cache>) -> Self {
+        Self {
+            cache,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            config: config.clone(),
+        }
+    }
+
+    /// 启动后台写入任务
+    pub fn start(&self) {
+        if !self.config.enabled {
+            return;
+        }
+        let meter = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(meter.config.flush_interval_secs.max(1)));
+            loop {
+                interval.tick().await;
+                meter.flush().await;
+            }
+        });
+    }
+
+    /// 记录一条消息的用量
+    pub fn record(&self, msg: &Msg) {
+        if !self.config.enabled {
+            return;
+        }
+        let bytes = msg.content.len() as i64;
+        metrics::counter!("im_tenant_messages_total", "tenant" => msg.tenant_id.clone())
+            .increment(1);
+        metrics::counter!("im_tenant_message_bytes_total", "tenant" => msg.tenant_id.clone())
+            .increment(bytes as u64);
+
+        let hour = chrono::Utc::now().format("%Y%m%d%H").to_string();
+        let mut pending = self.pending.lock().unwrap();
+        let entry = pending
+            .entry((hour, msg.tenant_id.clone(), msg.send_id.clone()))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += bytes;
+    }
+
+    /// 将累加的用量写入Redis，定期执行，服务关闭前再执行一次
+    pub async fn flush(&self) {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut hours: HashMap<String, Vec<MessageUsage>> = HashMap::new();
+        for ((hour, tenant_id, user_id), (messages, bytes)) in pending {
+            hours.entry(hour).or_default().push(MessageUsage {
+                tenant_id,
+                user_id,
+                messages,
+                bytes,
+            });
+        }
+
+        for (hour, usage) in hours {
+            if let Err(e) = self.cache.incr_message_usage(&hour, &usage).await {
+                warn!("写入消息用量失败，稍后重试: {:?}", e);
+                self.restore(&hour, usage);
+            }
+        }
+        metrics::gauge!("im_usage_pending_entries").set(self.pending.lock().unwrap().len() as f64);
+    }
+
+    /// 写入失败的用量放回内存
+    fn restore(&self, hour: &str, usage: Vec<MessageUsage>) {
+        let mut pending = self.pending.lock().unwrap();
+        for item in usage {
+            let entry = pending
+                .entry((hour.to_string(), item.tenant_id, item.user_id))
+                .or_default();
+            entry.0 += item.messages;
+            entry.1 += item.bytes;
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use dashmap::DashMap;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+use tracing::{debug, warn};
+
+use common::config::MessageLimitsConfig;
+use common::grpc_client::UserServiceGrpcClient;
+use common::message::{ContentType, Msg, MsgType};
+
+use crate::member_cache::GroupMemberCache;
+
+/// 拒绝原因的gRPC元数据键，客户端和网关按原因码区分处理
+pub const REJECT_REASON_METADATA_KEY: &str = "x-reject-reason";
+
+/// 已确认接收者缓存的条目上限，超过后清理过期条目
+const KNOWN_USERS_LIMIT: usize = 100_000;
+
+/// 投票消息只能由群组服务发出
+pub const POLL_SENDER_SERVICE: &str = "group-service";
+
+/// 消息被拒绝的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    InvalidMsgType,
+    InvalidContentType,
+    InvalidSender,
+    InvalidReceiver,
+    ReceiverNotFound,
+    NotGroupMember,
+    EmptyContent,
+    InvalidUtf8,
+    ContentTooLarge,
+    InvalidExtensions,
+    InvalidPoll,
+}
+
+impl RejectReason {
+    /// 原因码，写入错误信息和元数据
+    pub fn code(&self) -> &'static str {
+        match self {
+            RejectReason::InvalidMsgType => "invalid_msg_type",
+            RejectReason::InvalidContentType => "invalid_content_type",
+            RejectReason::InvalidSender => "invalid_sender",
+            RejectReason::InvalidReceiver => "invalid_receiver",
+            RejectReason::ReceiverNotFound => "receiver_not_found",
+            RejectReason::NotGroupMember => "not_group_member",
+            RejectReason::EmptyContent => "empty_content",
+            RejectReason::InvalidUtf8 => "invalid_utf8",
+            RejectReason::ContentTooLarge => "content_too_large",
+            RejectReason::InvalidExtensions => "invalid_extensions",
+            RejectReason::InvalidPoll => "invalid_poll",
+        }
+    }
+}
+
+/// 消息校验失败
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub reason: RejectReason,
+    pub detail: String,
+}
+
+impl Rejection {
+    fn new(reason: RejectReason, detail: impl Into<String>) -> Self {
+        Self {
+            reason,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl From<Rejection> for Status {
+    fn from(rejection: Rejection) -> Self {
+        let code = match rejection.reason {
+            RejectReason::ReceiverNotFound => Code::NotFound,
+            RejectReason::NotGroupMember => Code::PermissionDenied,
+            _ => Code::InvalidArgument,
+        };
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            REJECT_REASON_METADATA_KEY,
+            MetadataValue::from_static(rejection.reason.code()),
+        );
+        Status::with_metadata(
+            code,
+            format!("{}: {}", rejection.reason.code(), rejection.detail),
+            metadata,
+        )
+    }
+}
+
+/// 消息写入Kafka前的校验
+///
+/// 检查消息类型、发送者和接收者ID格式、内容编码和大小，并确认单聊接收者存在、
+/// 群聊发送者在群内；依赖的服务或缓存不可用时不拦截消息，由消费端按原逻辑处理
+pub struct MessageValidator {
+    config: MessageLimitsConfig,
+    group_members: GroupMemberCache,
+    user_client: UserServiceGrpcClient,
+    // 已确认存在的单聊接收者及确认时间（秒）
+    known_users: DashMap<String, i64>,
+}
+
+impl MessageValidator {
+    pub fn new(
+        config: MessageLimitsConfig,
+        group_members: GroupMemberCache,
+        user_client: UserServiceGrpcClient,
+    ) -> Self {
+        Self {
+            config,
+            group_members,
+            user_client,
+            known_users: DashMap::new(),
+        }
+    }
+
+    /// 校验消息，不通过时返回拒绝原因
+    pub async fn validate(&self, msg: &Msg) -> Result<(), Rejection> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let result = match check_format(msg, &self.config) {
+            Ok(()) if self.config.check_receiver => self.check_receiver(msg).await,
+            result => result,
+        };
+        if let Err(rejection) = &result {
+            metrics::counter!("im_msg_rejected_total", "reason" => rejection.reason.code())
+                .increment(1);
+            debug!(
+                "拒绝消息 {} -> {}: {}",
+                msg.send_id, msg.receiver_id, rejection.detail
+            );
+        }
+        result
+    }
+
+    /// 批量发送的整体校验：消息数不超过上限，全部消息的发送者和租户相同
+    pub fn check_batch(&self, messages: &[Msg]) -> Result<(), Status> {
+        check_batch(messages, self.config.max_batch_messages)
+    }
+
+    /// 批量发送时同时校验的消息数
+    pub fn batch_concurrency(&self) -> usize {
+        self.config.batch_concurrency.max(1)
+    }
+
+    async fn check_receiver(&self, msg: &Msg) -> Result<(), Rejection> {
+        match MsgType::try_from(msg.msg_type) {
+            Ok(MsgType::SingleMsg) => self.check_user_exists(&msg.receiver_id).await,
+            // 投票通知以群的名义发送，发送者已由 check_poll 校验
+            Ok(MsgType::GroupMsg) if is_group_notice(msg) => Ok(()),
+            Ok(MsgType::GroupMsg) => self.check_group_member(&msg.receiver_id, &msg.send_id).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// 确认单聊接收者存在，结果缓存一段时间，避免每条消息都查询用户服务
+    async fn check_user_exists(&self, user_id: &str) -> Result<(), Rejection> {
+        let now = chrono::Utc::now().timestamp();
+        let ttl = self.config.receiver_cache_secs as i64;
+        if self
+            .known_users
+            .get(user_id)
+            .is_some_and(|confirmed_at| now - *confirmed_at < ttl)
+        {
+            return Ok(());
+        }
+
+        match self.user_client.get_user(user_id).await {
+            Ok(_) => {
+                if self.known_users.len() >= KNOWN_USERS_LIMIT {
+                    self.known_users
+                        .retain(|_, confirmed_at| now - *confirmed_at < ttl);
+                }
+                self.known_users.insert(user_id.to_string(), now);
+                Ok(())
+            }
+            Err(e)
+                if e.downcast_ref::<Status>()
+                    .is_some_and(|status| status.code() == Code::NotFound) =>
+            {
+                Err(Rejection::new(
+                    RejectReason::ReceiverNotFound,
+                    format!("接收者 {} 不存在", user_id),
+                ))
+            }
+            // 用户服务不可用时不拦截消息
+            Err(e) => {
+                warn!("查询接收者 {} 失败，跳过校验: {:?}", user_id, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// 确认群聊发送者在群内，缓存中没有群成员时交给消费端从数据库加载
+    async fn check_group_member(&self, group_id: &str, sender_id: &str) -> Result<(), Rejection> {
+        match self.group_members.query_group_members_id(group_id).await {
+            Ok(members) if !members.is_empty() && !members.iter().any(|id| id == sender_id) => {
+                Err(Rejection::new(
+                    RejectReason::NotGroupMember,
+                    format!("发送者 {} 不在群 {} 中", sender_id, group_id),
+                ))
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                warn!("查询群 {} 成员失败，跳过校验: {:?}", group_id, e);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 批量发送的消息数和发送者、租户一致性校验
+fn check_batch(messages: &[Msg], max_messages: usize) -> Result<(), Status> {
+    let Some(first) = messages.first() else {
+        return Err(Status::invalid_argument("消息为空"));
+    };
+    if messages.len() > max_messages {
+        return Err(Status::invalid_argument(format!(
+            "单次最多发送 {} 条消息",
+            max_messages
+        )));
+    }
+    if messages
+        .iter()
+        .any(|msg| msg.send_id != first.send_id || msg.tenant_id != first.tenant_id)
+    {
+        return Err(Status::invalid_argument("批量发送的消息必须属于同一发送者和租户"));
+    }
+    Ok(())
+}
+
+/// 校验消息类型、ID格式、内容编码和大小
+fn check_format(msg: &Msg, config: &MessageLimitsConfig) -> Result<(), Rejection> {
+    let msg_type = MsgType::try_from(msg.msg_type).map_err(|_| {
+        Rejection::new(
+            RejectReason::InvalidMsgType,
+            format!("未知的消息类型 {}", msg.msg_type),
+        )
+    })?;
+    let content_type = ContentType::try_from(msg.content_type).map_err(|_| {
+        Rejection::new(
+            RejectReason::InvalidContentType,
+            format!("未知的内容类型 {}", msg.content_type),
+        )
+    })?;
+
+    let is_chat = matches!(msg_type, MsgType::SingleMsg | MsgType::GroupMsg);
+    // 系统消息可能没有发送者，只有聊天消息要求发送者和接收者都存在
+    if (is_chat || !msg.send_id.is_empty()) && !is_valid_id(&msg.send_id, config.max_id_len) {
+        return Err(Rejection::new(RejectReason::InvalidSender, "发送者ID格式错误"));
+    }
+    if (is_chat || !msg.receiver_id.is_empty()) && !is_valid_id(&msg.receiver_id, config.max_id_len)
+    {
+        return Err(Rejection::new(RejectReason::InvalidReceiver, "接收者ID格式错误"));
+    }
+
+    let max_bytes = match content_type {
+        _ if !is_chat => config.max_content_bytes,
+        ContentType::Text | ContentType::Emoji => {
+            if msg.content.is_empty() {
+                return Err(Rejection::new(RejectReason::EmptyContent, "消息内容不能为空"));
+            }
+            if std::str::from_utf8(&msg.content).is_err() {
+                return Err(Rejection::new(
+                    RejectReason::InvalidUtf8,
+                    "文本消息不是有效的UTF-8",
+                ));
+            }
+            config.max_text_bytes
+        }
+        ContentType::Image | ContentType::Video | ContentType::Audio | ContentType::File => {
+            config.max_attachment_bytes
+        }
+        _ => config.max_content_bytes,
+    };
+    if msg.content.len() > max_bytes {
+        return Err(Rejection::new(
+            RejectReason::ContentTooLarge,
+            format!("消息内容 {} 字节，超过上限 {} 字节", msg.content.len(), max_bytes),
+        ));
+    }
+
+    check_extensions(msg, config)
+}
+
+/// 校验扩展字段的条数、键格式和总大小，值的内容由客户端自行约定，服务端不解析
+fn check_extensions(msg: &Msg, config: &MessageLimitsConfig) -> Result<(), Rejection> {
+    if msg.extensions.is_empty() {
+        return Ok(());
+    }
+    if msg.extensions.len() > config.max_extension_entries {
+        return Err(Rejection::new(
+            RejectReason::InvalidExtensions,
+            format!("扩展字段最多 {} 条", config.max_extension_entries),
+        ));
+    }
+    if let Some(key) = msg
+        .extensions
+        .keys()
+        .find(|key| !is_valid_extension_key(key, config.max_extension_key_len))
+    {
+        return Err(Rejection::new(
+            RejectReason::InvalidExtensions,
+            format!("扩展字段键 {:?} 格式错误", key),
+        ));
+    }
+    let bytes: usize = msg
+        .extensions
+        .iter()
+        .map(|(key, value)| key.len() + value.len())
+        .sum();
+    if bytes > config.max_extensions_bytes {
+        return Err(Rejection::new(
+            RejectReason::InvalidExtensions,
+            format!(
+                "扩展字段 {} 字节，超过上限 {} 字节",
+                bytes, config.max_extensions_bytes
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// 校验投票消息：只接受群组服务以群的名义发到群聊的投票事件，客户端不能伪造投票结果
+///
+/// `caller` 为服务间令牌中的调用方服务名，非投票消息直接通过
+pub fn check_poll(msg: &Msg, caller: Option<&str>) -> Result<(), Rejection> {
+    if msg.content_type != ContentType::Poll as i32 {
+        return Ok(());
+    }
+    if caller != Some(POLL_SENDER_SERVICE) {
+        return Err(Rejection::new(
+            RejectReason::InvalidPoll,
+            "投票消息只能由群组服务发送",
+        ));
+    }
+    if msg.msg_type != MsgType::GroupMsg as i32 || !is_group_notice(msg) {
+        return Err(Rejection::new(
+            RejectReason::InvalidPoll,
+            "投票消息必须以群的名义发送到群聊",
+        ));
+    }
+
+    let content: serde_json::Value = serde_json::from_slice(&msg.content)
+        .map_err(|_| Rejection::new(RejectReason::InvalidPoll, "投票消息内容不是有效的JSON"))?;
+    let event = content.get("event").and_then(|event| event.as_str());
+    let poll_id = content.get("pollId").and_then(|id| id.as_str());
+    if !matches!(event, Some("created" | "closed")) || poll_id.map_or(true, str::is_empty) {
+        return Err(Rejection::new(
+            RejectReason::InvalidPoll,
+            "投票消息内容无效",
+        ));
+    }
+    Ok(())
+}
+
+/// 以群的名义发出的群通知，发送者ID与群ID相同
+fn is_group_notice(msg: &Msg) -> bool {
+    msg.content_type == ContentType::Poll as i32 && msg.send_id == msg.receiver_id
+}
+
+/// 扩展字段键只允许字母、数字、点、短横线和下划线，便于客户端按命名空间区分
+fn is_valid_extension_key(key: &str, max_len: usize) -> bool {
+    !key.is_empty()
+        && key.len() <= max_len
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-' || b == b'_')
+}
+
+/// ID只允许字母、数字、短横线和下划线
+fn is_valid_id(id: &str, max_len: usize) -> bool {
+    !id.is_empty()
+        && id.len() <= max_len
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(msg: &Msg) -> Option<RejectReason> {
+        check_format(msg, &MessageLimitsConfig::default())
+            .err()
+            .map(|rejection| rejection.reason)
+    }
+
+    #[test]
+    fn test_check_format() {
+        let mut msg = Msg {
+            send_id: "9b2f7c1e-3a4d-4e5f-8a9b-0c1d2e3f4a5b".to_string(),
+            receiver_id: "u_2".to_string(),
+            msg_type: MsgType::SingleMsg as i32,
+            content_type: ContentType::Text as i32,
+            content: "你好".as_bytes().to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(reason(&msg), None);
+
+        msg.content = vec![0xff, 0xfe];
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidUtf8));
+        msg.content = vec![];
+        assert_eq!(reason(&msg), Some(RejectReason::EmptyContent));
+        msg.content = vec![b'a'; 16 * 1024 + 1];
+        assert_eq!(reason(&msg), Some(RejectReason::ContentTooLarge));
+
+        msg.content = b"hi".to_vec();
+        msg.receiver_id = "../u2".to_string();
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidReceiver));
+        msg.receiver_id = String::new();
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidReceiver));
+
+        msg.receiver_id = "u2".to_string();
+        msg.msg_type = 999;
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidMsgType));
+
+        // 系统通知没有发送者，内容按通用上限校验
+        msg.msg_type = MsgType::Notification as i32;
+        msg.send_id = String::new();
+        msg.content_type = ContentType::Default as i32;
+        msg.content = vec![0xff; 1024];
+        assert_eq!(reason(&msg), None);
+    }
+
+    #[test]
+    fn test_check_extensions() {
+        let mut msg = Msg {
+            send_id: "u1".to_string(),
+            receiver_id: "u2".to_string(),
+            msg_type: MsgType::SingleMsg as i32,
+            content_type: ContentType::Text as i32,
+            content: b"hi".to_vec(),
+            ..Default::default()
+        };
+        msg.extensions
+            .insert("shop.order_id".to_string(), "20250607-0001".to_string());
+        assert_eq!(reason(&msg), None);
+
+        msg.extensions.insert("bad key".to_string(), String::new());
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidExtensions));
+
+        msg.extensions.clear();
+        msg.extensions
+            .insert("payload".to_string(), "a".repeat(4 * 1024));
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidExtensions));
+
+        msg.extensions = (0..17).map(|i| (format!("k{}", i), String::new())).collect();
+        assert_eq!(reason(&msg), Some(RejectReason::InvalidExtensions));
+    }
+
+    #[test]
+    fn test_check_batch() {
+        let msg = |send_id: &str| Msg {
+            send_id: send_id.to_string(),
+            tenant_id: "t1".to_string(),
+            ..Default::default()
+        };
+        assert!(check_batch(&[msg("bot"), msg("bot")], 2).is_ok());
+        assert!(check_batch(&[], 2).is_err());
+        assert!(check_batch(&[msg("bot"), msg("bot"), msg("bot")], 2).is_err());
+        assert!(check_batch(&[msg("bot"), msg("other")], 2).is_err());
+    }
+
+    #[test]
+    fn test_check_poll() {
+        let content = serde_json::json!({ "event": "closed", "pollId": "p1", "options": [] });
+        let mut msg = Msg {
+            send_id: "g1".to_string(),
+            receiver_id: "g1".to_string(),
+            msg_type: MsgType::GroupMsg as i32,
+            content_type: ContentType::Poll as i32,
+            content: content.to_string().into_bytes(),
+            ..Default::default()
+        };
+        let reason = |msg: &Msg, caller| check_poll(msg, caller).err().map(|r| r.reason);
+        assert_eq!(reason(&msg, Some(POLL_SENDER_SERVICE)), None);
+
+        // 客户端经消息网关发出的投票消息
+        assert_eq!(
+            reason(&msg, Some("msg-gateway")),
+            Some(RejectReason::InvalidPoll)
+        );
+        assert_eq!(reason(&msg, None), Some(RejectReason::InvalidPoll));
+
+        // 不是以群的名义发送
+        msg.send_id = "u1".to_string();
+        assert_eq!(
+            reason(&msg, Some(POLL_SENDER_SERVICE)),
+            Some(RejectReason::InvalidPoll)
+        );
+        msg.send_id = "g1".to_string();
+        msg.msg_type = MsgType::SingleMsg as i32;
+        assert_eq!(
+            reason(&msg, Some(POLL_SENDER_SERVICE)),
+            Some(RejectReason::InvalidPoll)
+        );
+        msg.msg_type = MsgType::GroupMsg as i32;
+
+        msg.content = br#"{"event":"voted","pollId":"p1"}"#.to_vec();
+        assert_eq!(
+            reason(&msg, Some(POLL_SENDER_SERVICE)),
+            Some(RejectReason::InvalidPoll)
+        );
+        msg.content = b"not json".to_vec();
+        assert_eq!(
+            reason(&msg, Some(POLL_SENDER_SERVICE)),
+            Some(RejectReason::InvalidPoll)
+        );
+
+        // 其他内容类型不受影响
+        msg.content_type = ContentType::Text as i32;
+        assert_eq!(reason(&msg, Some("msg-gateway")), None);
+    }
+}
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
 use aws_sdk_s3::config::{Builder, Credentials, Region};
+use aws_sdk_s3::presigning::PresigningConfig;
 use aws_sdk_s3::Client;
 use aws_smithy_runtime_api::client::result::SdkError;
 use bytes::Bytes;
 use common::config::AppConfig;
 use common::error::Error;
+use std::time::Duration;
 use tokio::fs;
 use tracing::error;
 
@@ -12,6 +14,7 @@ use crate::{default_avatars, Oss};
 
 #[derive(Debug, Clone)]
 pub(crate) struct S3Client {
+    endpoint: String,
     bucket: String,
     avatar_bucket: String,
     client: Client,
@@ -27,6 +30,7 @@ impl S3Client {
             "MinioCredentials",
         );
 
+        let endpoint = config.oss.endpoint.trim_end_matches('/').to_string();
         let bucket = config.oss.bucket.clone();
         let avatar_bucket = config.oss.avatar_bucket.clone();
 
@@ -42,6 +46,7 @@ impl S3Client {
         let client = Client::from_conf(config);
 
         let self_ = Self {
+            endpoint,
             client,
             bucket,
             avatar_bucket,
@@ -146,6 +151,48 @@ impl Oss for S3Client {
         self.delete(&self.bucket, key).await
     }
 
+    async fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, Error> {
+        let presigning = PresigningConfig::expires_in(expires_in)
+            .map_err(|e| Error::Internal(format!("invalid presigning config: {}", e)))?;
+
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(presigning)
+            .await?;
+
+        Ok(request.uri().to_string())
+    }
+
+    async fn presigned_upload_url(
+        &self,
+        key: &str,
+        mime_type: &str,
+        size: i64,
+        expires_in: Duration,
+    ) -> Result<String, Error> {
+        let presigning = PresigningConfig::expires_in(expires_in)
+            .map_err(|e| Error::Internal(format!("invalid presigning config: {}", e)))?;
+
+        let request = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(mime_type)
+            .content_length(size)
+            .presigned(presigning)
+            .await?;
+
+        Ok(request.uri().to_string())
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!("{}/{}/{}", self.endpoint, self.bucket, key)
+    }
+
     async fn upload_avatar(&self, key: &str, content: Vec<u8>) -> Result<(), Error> {
         self.upload(&self.avatar_bucket, key, content).await
     }
@@ -5,8 +5,10 @@ use common::error::Error;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 
 mod client;
+pub mod thumbnail;
 
 #[async_trait]
 pub trait Oss: Debug + Send + Sync {
@@ -14,6 +16,18 @@ pub trait Oss: Debug + Send + Sync {
     async fn upload_file(&self, key: &str, content: Vec<u8>) -> Result<(), Error>;
     async fn download_file(&self, key: &str) -> Result<Bytes, Error>;
     async fn delete_file(&self, key: &str) -> Result<(), Error>;
+    /// 生成文件的签名下载地址，过期后失效
+    async fn presigned_url(&self, key: &str, expires_in: Duration) -> Result<String, Error>;
+    /// 生成文件的签名上传地址（PUT），上传时的内容类型和大小必须与签名一致
+    async fn presigned_upload_url(
+        &self,
+        key: &str,
+        mime_type: &str,
+        size: i64,
+        expires_in: Duration,
+    ) -> Result<String, Error>;
+    /// 文件的永久访问地址，要求存储桶允许公开读取
+    fn object_url(&self, key: &str) -> String;
 
     async fn upload_avatar(&self, key: &str, content: Vec<u8>) -> Result<(), Error>;
     async fn download_avatar(&self, key: &str) -> Result<Bytes, Error>;
@@ -0,0 +1,71 @@
+use std::io::Cursor;
+
+use common::error::Error;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+/// 缩略图最长边（像素）
+pub const THUMBNAIL_MAX_SIDE: u32 = 320;
+
+/// 计算blurhash前先缩小到该尺寸，避免对原图逐像素计算
+const BLURHASH_SAMPLE_SIDE: u32 = 32;
+
+/// blurhash横向/纵向分量数
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+/// 图片衍生数据
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// JPEG格式的缩略图
+    pub data: Vec<u8>,
+    /// 原图宽度
+    pub width: u32,
+    /// 原图高度
+    pub height: u32,
+    /// blurhash占位符
+    pub blurhash: String,
+}
+
+/// 缩略图对象键，与原图放在同一存储桶的 thumbnails/ 目录下
+pub fn thumbnail_key(object_key: &str) -> String {
+    format!("thumbnails/{}.jpg", object_key)
+}
+
+/// 生成缩略图和blurhash
+///
+/// 解码和缩放是CPU密集操作，调用方应在 `spawn_blocking` 中执行
+pub fn generate_thumbnail(data: &[u8], max_side: u32) -> Result<Thumbnail, Error> {
+    let image = image::load_from_memory(data)
+        .map_err(|e| Error::Internal(format!("解码图片失败: {}", e)))?;
+    let (width, height) = image.dimensions();
+
+    // 小图不放大，直接重新编码为JPEG
+    let thumb = if width > max_side || height > max_side {
+        image.thumbnail(max_side, max_side)
+    } else {
+        image.clone()
+    };
+
+    // JPEG不支持透明通道，先转为RGB
+    let mut buf = Vec::new();
+    DynamicImage::ImageRgb8(thumb.to_rgb8())
+        .write_to(&mut Cursor::new(&mut buf), ImageFormat::Jpeg)
+        .map_err(|e| Error::Internal(format!("编码缩略图失败: {}", e)))?;
+
+    let sample = thumb.thumbnail(BLURHASH_SAMPLE_SIDE, BLURHASH_SAMPLE_SIDE);
+    let (sample_width, sample_height) = sample.dimensions();
+    let blurhash = blurhash::encode(
+        BLURHASH_COMPONENTS.0,
+        BLURHASH_COMPONENTS.1,
+        sample_width,
+        sample_height,
+        sample.to_rgba8().as_raw(),
+    )
+    .map_err(|e| Error::Internal(format!("计算blurhash失败: {}", e)))?;
+
+    Ok(Thumbnail {
+        data: buf,
+        width,
+        height,
+        blurhash,
+    })
+}
@@ -3,13 +3,17 @@ use axum::{routing::get, Router};
 use axum_server;
 use clap::Parser;
 use common::config::AppConfig;
+use common::grpc::maintenance::MaintenanceMode;
+use common::grpc::server::serve_with_drain;
+use common::grpc::service_auth::{ServiceAuth, ServiceAuthInterceptor};
 use common::grpc::LoggingInterceptor;
-use common::service_registry::ServiceRegistry;
-use sqlx::postgres::PgPoolOptions;
+use common::schema_migration::SchemaMigration;
+use common::service_registry::{ServiceRegister, ServiceRegistry};
+use common::tenant_db::PgRouter;
 use std::net::SocketAddr;
 use tokio::signal;
 use tokio::sync::oneshot;
-use tonic::transport::Server;
+use tonic::transport::server::Routes;
 use tonic_reflection::server::Builder as ReflectionBuilder;
 use tracing::{error, info, warn};
 
@@ -17,8 +21,44 @@ mod model;
 mod repository;
 mod service;
 
+use common::proto::access_log::access_log_service_server::AccessLogServiceServer;
+use common::proto::auth::auth_service_server::AuthServiceServer;
+use common::proto::job::job_service_server::JobServiceServer;
+use common::proto::retention::retention_service_server::RetentionServiceServer;
+use common::proto::storage::storage_service_server::StorageServiceServer;
 use common::proto::user::user_service_server::UserServiceServer;
+use common::grpc_client::ChatServiceGrpcClient;
+use repository::access_log_repository::AccessLogRepository;
+use repository::email_digest_repository::EmailDigestRepository;
+use repository::invite_repository::InviteRepository;
+use repository::job_repository::JobRepository;
+use repository::login_history_repository::LoginHistoryRepository;
+use repository::retention_repository::RetentionRepository;
+use repository::storage_repository::StorageRepository;
+use repository::usage_repository::UsageRepository;
+use repository::user_repository::{UserRepository, USERS_UUID_MIGRATION};
+use service::access_log_retention::AccessLogRetention;
+use service::access_log_service::AccessLogServiceImpl;
+use service::auth_service::AuthServiceImpl;
+use service::email::{EmailSender, HttpEmailSender, LogEmailSender};
+use service::email_digest::EmailDigestSender;
+use service::job_service::JobServiceImpl;
+use service::job_worker::{JobWorker, ProfileExportHandler, JOB_KIND_EXPORT_PROFILE};
+use service::last_active::LastActiveFlusher;
+use service::login_security::LoginSecurity;
+use service::phone_invite::PhoneInvites;
+use service::profile_view::ProfileViewLimiter;
+use service::sms::{HttpSmsSender, LogSmsSender, SmsSender};
+use service::pinyin_backfill::PinyinBackfill;
+use service::retention_cleaner::RetentionCleaner;
+use service::retention_service::RetentionServiceImpl;
+use service::storage_service::{AttachmentReleaser, StorageServiceImpl};
+use service::upload_events::UploadEventPublisher;
+use service::upload_reservation::UploadReservationSweeper;
+use service::usage_export::{UsageExportHandler, JOB_KIND_EXPORT_USAGE};
+use service::usage_rollup::UsageRollup;
 use service::user_service::UserServiceImpl;
+use std::sync::Arc;
 
 // 导入用户服务proto文件描述符，用于gRPC反射
 const FILE_DESCRIPTOR_SET: &[u8] = common::proto::user::FILE_DESCRIPTOR_SET;
@@ -31,8 +71,7 @@ struct Args {
     config: String,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     // 初始化命令行参数
     let args = Args::parse();
 
@@ -42,6 +81,12 @@ async fn main() -> Result<()> {
     // 加载配置
     let config = AppConfig::new()?;
 
+    // 按配置的线程数创建运行时
+    common::runtime::build("user-service", &config.runtime.for_service("user-service"))?
+        .block_on(run(config))
+}
+
+async fn run(config: AppConfig) -> Result<()> {
     // 初始化日志和链路追踪
     // 根据配置判断是否启用链路追踪
     if config.telemetry.enabled {
@@ -56,20 +101,19 @@ async fn main() -> Result<()> {
 
     info!("正在启动用户服务...");
 
+    // 未配置服务间认证密钥时拒绝启动，避免RPC端口对外无认证暴露
+    ServiceAuth::global().ensure_enabled()?;
+
     // 使用已加载的配置
     let host = &config.server.host;
     let port = config.server.port;
     let addr = format!("{}:{}", host, port).parse::<SocketAddr>()?;
 
-    // 初始化数据库连接池
-    let db_pool = match PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&config.database.url())
-        .await
-    {
-        Ok(pool) => {
+    // 初始化数据库连接池，配置了数据驻留的租户使用各自的数据库集群
+    let db = match PgRouter::connect(&config, 10).await {
+        Ok(db) => {
             info!("数据库连接成功");
-            pool
+            db
         }
         Err(err) => {
             error!("数据库连接失败: {}", err);
@@ -77,8 +121,141 @@ async fn main() -> Result<()> {
         }
     };
 
+    // 初始化对象存储，用于保存异步任务结果和签发附件上传地址
+    let oss = oss::oss(&config).await;
+
+    // 启动异步任务工作进程，任务保存在租户所在的数据库集群，每个集群各启动一个
+    let mut job_kinds = Vec::new();
+    for target in db.targets() {
+        let job_worker = JobWorker::new(JobRepository::new(target.clone()), oss.clone())
+            .register(
+                JOB_KIND_EXPORT_PROFILE,
+                Arc::new(ProfileExportHandler::new(target.clone())),
+            )
+            .register(
+                JOB_KIND_EXPORT_USAGE,
+                Arc::new(UsageExportHandler::new(
+                    target,
+                    config.usage.max_export_hours,
+                )),
+            );
+        job_kinds = job_worker.kinds();
+        job_worker.start();
+    }
+    let job_service = JobServiceImpl::new(JobRepository::new(db.clone()), oss.clone(), job_kinds);
+
+    // 每个数据库集群各启动一个过期消息清理任务，清理的消息中的附件同时释放存储配额，
+    // 并初始化保留策略管理服务
+    for target in db.targets() {
+        let attachments = AttachmentReleaser::new(StorageRepository::new(target.clone()), oss.clone());
+        RetentionCleaner::new(RetentionRepository::new(target), attachments).start();
+    }
+    let retention_repository = RetentionRepository::new(db.clone());
+    let retention_service = RetentionServiceImpl::new(retention_repository.clone());
+
+    // 每个数据库集群各启动一个访问日志分区维护任务，并初始化访问日志服务
+    for target in db.targets() {
+        AccessLogRetention::new(AccessLogRepository::new(target), config.access_logs.clone())
+            .start();
+    }
+    let access_log_repository = AccessLogRepository::new(db.clone());
+    let access_log_service = AccessLogServiceImpl::new(
+        access_log_repository,
+        retention_repository,
+        config.access_logs.clone(),
+    );
+
+    // 每个数据库集群各启动一个过期上传预占清理任务，并初始化附件存储配额服务
+    for target in db.targets() {
+        let attachments = AttachmentReleaser::new(StorageRepository::new(target.clone()), oss.clone());
+        UploadReservationSweeper::new(StorageRepository::new(target), attachments).start();
+    }
+    let storage_service = StorageServiceImpl::new(
+        StorageRepository::new(db.clone()),
+        oss,
+        config.storage_quota.clone(),
+        config.attachment.clone(),
+        Some(UploadEventPublisher::new(&config)?),
+    );
+
+    // 启动用户最后活跃时间落库任务，Redis降级期间暂停
+    let cache = cache::cache(&config);
+    LastActiveFlusher::new(
+        cache.clone(),
+        cache::redis_health(&config, cache.clone()),
+        db.targets().into_iter().map(UserRepository::new).collect(),
+    )
+    .start();
+
+    // 启动消息用量每小时汇总任务，用量按租户写入各自的数据库集群
+    UsageRollup::new(
+        cache.clone(),
+        UsageRepository::new(db.clone()),
+        config.usage.clone(),
+    )
+    .start();
+
+    // 每个数据库集群各启动一个离线摘要邮件发送任务，未配置邮件服务时只记录日志
+    let email: Arc<dyn EmailSender> = match &config.email_digest.email {
+        Some(email_config) => Arc::new(HttpEmailSender::new(email_config)),
+        None => Arc::new(LogEmailSender),
+    };
+    for target in db.targets() {
+        EmailDigestSender::new(
+            Arc::new(EmailDigestRepository::new(target)),
+            email.clone(),
+            config.email_digest.clone(),
+        )
+        .start();
+    }
+
+    // 补全历史用户的昵称拼音
+    for target in db.targets() {
+        PinyinBackfill::new(UserRepository::new(target)).start();
+    }
+
+    // 初始化令牌服务，密钥需与网关签发令牌的密钥一致
+    let auth_service = AuthServiceImpl::new(&config.jwt.secret, cache.clone());
+
+    // 初始化公开资料查看频率限制
+    let profile_views = ProfileViewLimiter::new(cache.clone(), config.profile.clone());
+
+    // 初始化手机号邀请，未配置短信服务时只记录日志
+    let sms: Arc<dyn SmsSender> = match &config.invite.sms {
+        Some(sms_config) => Arc::new(HttpSmsSender::new(sms_config)),
+        None => Arc::new(LogSmsSender),
+    };
+
+    // 初始化登录安全检查，二次验证码使用已配置的短信和邮件服务发送
+    let mut login_security = LoginSecurity::new(
+        LoginHistoryRepository::new(db.clone()),
+        UserRepository::new(db.clone()),
+        cache,
+        ChatServiceGrpcClient::from_env(),
+    );
+    if let Some(sms_config) = &config.invite.sms {
+        login_security = login_security.with_sms(sms.clone(), sms_config.login_template.clone());
+    }
+    if config.email_digest.email.is_some() {
+        login_security = login_security.with_email(email);
+    }
+    let phone_invites = PhoneInvites::new(
+        InviteRepository::new(db.clone()),
+        UserRepository::new(db.clone()),
+        ChatServiceGrpcClient::from_env(),
+        sms,
+        config.invite.clone(),
+    );
+
     // 初始化用户服务
-    let user_service = UserServiceImpl::new(db_pool);
+    let user_service = UserServiceImpl::new(
+        db,
+        login_security,
+        phone_invites,
+        profile_views,
+        SchemaMigration::new(USERS_UUID_MIGRATION, &config.schema_migrations),
+        config.email_digest.unsubscribe_secret.clone(),
+    );
 
     // 创建HTTP服务器用于健康检查
     let health_port = port + 1;
@@ -86,9 +263,11 @@ async fn main() -> Result<()> {
     let health_service = start_health_service(host, health_port).await?;
 
     // 创建并注册到Consul
-    let service_registry = ServiceRegistry::from_env();
+    let service_registry = ServiceRegistry::from_env()
+        .with_region(config.region.local())
+        .shared();
     let service_id = service_registry
-        .register_service(
+        .register(
             "user-service",
             host,
             port as u32, // 注册gRPC服务端口
@@ -107,25 +286,52 @@ async fn main() -> Result<()> {
     // 创建反射服务
     let reflection_service = ReflectionBuilder::configure()
         .register_encoded_file_descriptor_set(FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(common::proto::job::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(common::proto::auth::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(common::proto::retention::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(common::proto::access_log::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(common::proto::storage::FILE_DESCRIPTOR_SET)
         .build()?;
 
-    // 创建日志拦截器
+    // 创建日志拦截器，业务服务外层再校验服务间令牌
     let logging_interceptor = LoggingInterceptor::new();
 
     // 启动gRPC服务
     info!("用户服务启动，监听地址: {}", addr);
 
-    // 创建服务器并运行，添加反射服务和拦截器
-    let server = Server::builder()
+    // 创建服务器并运行，添加反射服务和拦截器，连接到期和下线时通知客户端
+    let routes = Routes::new(reflection_service) // 添加反射服务
         .add_service(UserServiceServer::with_interceptor(
             user_service, 
-            logging_interceptor
+            ServiceAuthInterceptor::new(logging_interceptor.clone())
+        ))
+        .add_service(JobServiceServer::with_interceptor(
+            job_service,
+            ServiceAuthInterceptor::new(logging_interceptor.clone())
+        ))
+        .add_service(AuthServiceServer::with_interceptor(
+            auth_service,
+            ServiceAuthInterceptor::new(logging_interceptor.clone())
         ))
-        .add_service(reflection_service) // 添加反射服务
-        .serve_with_shutdown(addr, async {
-            let _ = shutdown_rx.await;
-            info!("接收到关闭信号，gRPC服务准备关闭");
-        });
+        .add_service(RetentionServiceServer::with_interceptor(
+            retention_service,
+            ServiceAuthInterceptor::new(logging_interceptor.clone())
+        ))
+        .add_service(AccessLogServiceServer::with_interceptor(
+            access_log_service,
+            ServiceAuthInterceptor::new(logging_interceptor.clone())
+        ))
+        .add_service(StorageServiceServer::with_interceptor(
+            storage_service,
+            ServiceAuthInterceptor::new(logging_interceptor)
+        ));
+    // 数据库迁移期间可以切换到只读维护模式，拒绝写操作
+    let maintenance = MaintenanceMode::new("user-service", &config.maintenance);
+    maintenance.start(&config.redis);
+    let server = serve_with_drain(routes, addr, &config.grpc_server, maintenance, async {
+        let _ = shutdown_rx.await;
+        info!("接收到关闭信号，gRPC服务准备关闭");
+    });
 
     tokio::select! {
         _ = server => {
@@ -181,7 +387,10 @@ async fn health_check() -> &'static str {
 }
 
 // 优雅关闭信号处理
-async fn shutdown_signal(tx: oneshot::Sender<()>, service_registry: ServiceRegistry) -> Result<()> {
+async fn shutdown_signal(
+    tx: oneshot::Sender<()>,
+    service_registry: Arc<dyn ServiceRegister>,
+) -> Result<()> {
     let ctrl_c = async {
         signal::ctrl_c().await.expect("无法安装Ctrl+C处理器");
     };
@@ -205,7 +414,7 @@ async fn shutdown_signal(tx: oneshot::Sender<()>, service_registry: ServiceRegis
     info!("接收到关闭信号，准备优雅关闭...");
 
     // 从Consul注销服务
-    match service_registry.deregister_service().await {
+    match service_registry.deregister().await {
         Ok(_) => info!("已从Consul注销服务"),
         Err(e) => error!("从Consul注销服务失败: {}", e),
     }
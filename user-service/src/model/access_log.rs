@@ -0,0 +1,111 @@
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use common::proto::access_log;
+use prost_types::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 访问日志分区表名前缀，分区名为 api_access_log_pYYYYMMDD
+pub const ACCESS_LOG_PARTITION_PREFIX: &str = "api_access_log_p";
+
+/// 请求路径、User-Agent的最大保存长度，与表结构一致
+pub const MAX_PATH_CHARS: usize = 512;
+pub const MAX_USER_AGENT_CHARS: usize = 256;
+
+/// 访问日志数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AccessLog {
+    pub id: i64,
+    pub user_id: String,
+    pub tenant_id: String,
+    pub method: String,
+    pub path: String,
+    pub status: i16,
+    pub latency_ms: i32,
+    pub ip: String,
+    pub user_agent: String,
+    pub request_id: String,
+    pub accessed_at: DateTime<Utc>,
+}
+
+/// 访问日志查询条件
+#[derive(Debug, Clone, Default)]
+pub struct AccessLogFilter {
+    pub tenant_id: String,
+    pub user_id: Option<String>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub path_prefix: Option<String>,
+    pub status: Option<i16>,
+    pub ip: Option<String>,
+}
+
+/// 某天的分区名
+pub fn partition_name(day: NaiveDate) -> String {
+    format!("{}{}", ACCESS_LOG_PARTITION_PREFIX, day.format("%Y%m%d"))
+}
+
+/// 从分区名解析日期，不是访问日志分区时返回None
+pub fn partition_day(name: &str) -> Option<NaiveDate> {
+    let day = name.strip_prefix(ACCESS_LOG_PARTITION_PREFIX)?;
+    NaiveDate::parse_from_str(day, "%Y%m%d").ok()
+}
+
+/// 分区的时间范围 [当天0点, 次日0点)，UTC
+pub fn partition_bounds(day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = day.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let end = (day + Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    (start, end)
+}
+
+/// 按字符截断，避免超出列长度导致整批写入失败
+pub fn truncate_chars(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> Timestamp {
+    Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl From<AccessLog> for access_log::AccessLog {
+    fn from(log: AccessLog) -> Self {
+        Self {
+            id: log.id,
+            user_id: log.user_id,
+            tenant_id: log.tenant_id,
+            method: log.method,
+            path: log.path,
+            status: log.status as i32,
+            latency_ms: log.latency_ms,
+            ip: log.ip,
+            user_agent: log.user_agent,
+            request_id: log.request_id,
+            accessed_at: Some(to_timestamp(log.accessed_at)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_name_and_bounds() {
+        let day = NaiveDate::from_ymd_opt(2025, 6, 30).unwrap();
+        assert_eq!(partition_name(day), "api_access_log_p20250630");
+        assert_eq!(partition_day("api_access_log_p20250630"), Some(day));
+        assert_eq!(partition_day("api_access_log"), None);
+        assert_eq!(partition_day("api_access_log_pdefault"), None);
+
+        let (start, end) = partition_bounds(day);
+        assert_eq!(start.to_rfc3339(), "2025-06-30T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2025-07-01T00:00:00+00:00");
+
+        assert_eq!(truncate_chars("访问日志", 2), "访问");
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 待发送离线摘要的用户及其离线期间的未读统计
+#[derive(Debug, Clone, FromRow)]
+pub struct DigestCandidate {
+    pub user_id: String,
+    pub email: String,
+    /// 昵称，未设置时为用户名
+    pub name: String,
+    pub tenant_id: String,
+    pub last_active_at: NaiveDateTime,
+    /// 上一次发送摘要的时间
+    pub last_sent_at: Option<NaiveDateTime>,
+    /// 离线期间收到的未读私聊消息数
+    pub unread_messages: i64,
+    /// 离线期间收到的未读私聊消息的发送者数
+    pub unread_senders: i64,
+    /// 离线期间收到的待处理好友请求数
+    pub friend_requests: i64,
+}
+
+/// 摘要邮件退订令牌声明
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsubscribeClaims {
+    /// 用户ID
+    pub sub: String,
+    /// 用户所属租户，退订时按租户路由到所在的数据库集群
+    pub tenant_id: String,
+    /// 令牌用途，固定为 email_digest_unsubscribe
+    pub purpose: String,
+    /// 过期时间
+    pub exp: u64,
+}
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 手机号邀请数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PhoneInvite {
+    pub id: String,
+    pub sender_id: String,
+    pub phone: String,
+    pub content: String,
+    pub sms_sent: bool,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use common::proto::job;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 任务状态：排队中
+pub const JOB_STATUS_PENDING: i16 = 0;
+/// 任务状态：执行中
+pub const JOB_STATUS_RUNNING: i16 = 1;
+/// 任务状态：已完成
+pub const JOB_STATUS_SUCCEEDED: i16 = 2;
+/// 任务状态：执行失败
+pub const JOB_STATUS_FAILED: i16 = 3;
+
+/// 异步任务数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Job {
+    pub id: String,
+    pub user_id: String,
+    pub kind: String,
+    pub status: i16,
+    pub progress: i32,
+    pub params: String,
+    pub result_key: Option<String>,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Job {
+    /// 是否已结束（成功或失败）
+    pub fn is_finished(&self) -> bool {
+        self.status == JOB_STATUS_SUCCEEDED || self.status == JOB_STATUS_FAILED
+    }
+}
+
+/// 创建任务请求数据
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateJobData {
+    pub user_id: String,
+    pub kind: String,
+    pub params: String,
+}
+
+impl From<job::CreateJobRequest> for CreateJobData {
+    fn from(req: job::CreateJobRequest) -> Self {
+        Self {
+            user_id: req.user_id,
+            kind: req.kind,
+            params: if req.params.is_empty() {
+                "{}".to_string()
+            } else {
+                req.params
+            },
+        }
+    }
+}
+
+impl From<Job> for job::Job {
+    fn from(j: Job) -> Self {
+        use prost_types::Timestamp;
+
+        let status = match j.status {
+            JOB_STATUS_RUNNING => job::JobStatus::Running,
+            JOB_STATUS_SUCCEEDED => job::JobStatus::Succeeded,
+            JOB_STATUS_FAILED => job::JobStatus::Failed,
+            _ => job::JobStatus::Pending,
+        };
+
+        Self {
+            id: j.id,
+            user_id: j.user_id,
+            kind: j.kind,
+            status: status as i32,
+            progress: j.progress,
+            params: j.params,
+            // 签名URL由服务层按需生成
+            result_url: None,
+            result_expires_at: None,
+            error: j.error,
+            created_at: Some(Timestamp {
+                seconds: j.created_at.timestamp(),
+                nanos: j.created_at.timestamp_subsec_nanos() as i32,
+            }),
+            updated_at: Some(Timestamp {
+                seconds: j.updated_at.timestamp(),
+                nanos: j.updated_at.timestamp_subsec_nanos() as i32,
+            }),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use common::proto::user;
+use prost_types::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 登录历史数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LoginHistory {
+    pub id: String,
+    pub user_id: String,
+    pub device_id: String,
+    pub device_name: String,
+    pub ip: String,
+    pub ip_network: String,
+    pub user_agent: String,
+    pub risk_score: i32,
+    pub trusted: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 本次登录的设备与网络信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoginContext {
+    pub device_id: String,
+    pub device_name: String,
+    pub ip: String,
+    pub user_agent: String,
+}
+
+/// 用户登录过的设备，由登录历史按设备聚合得到
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserDevice {
+    pub device_id: String,
+    pub device_name: String,
+    pub last_ip: String,
+    pub last_login_at: DateTime<Utc>,
+    pub trusted: bool,
+}
+
+impl From<UserDevice> for user::UserDevice {
+    fn from(device: UserDevice) -> Self {
+        Self {
+            device_id: device.device_id,
+            device_name: device.device_name,
+            last_ip: device.last_ip,
+            last_login_at: Some(Timestamp {
+                seconds: device.last_login_at.timestamp(),
+                nanos: device.last_login_at.timestamp_subsec_nanos() as i32,
+            }),
+            trusted: device.trusted,
+        }
+    }
+}
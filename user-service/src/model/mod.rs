@@ -1 +1,12 @@
 pub mod user;
+pub mod job;
+pub mod user_config;
+pub mod profile;
+pub mod login_history;
+pub mod token;
+pub mod retention;
+pub mod invite;
+pub mod usage;
+pub mod access_log;
+pub mod email_digest;
+pub mod storage;
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use common::proto::user::PublicProfileResponse;
+use prost_types::Timestamp;
+
+use crate::model::user::User;
+use crate::model::user_config::UserConfig;
+
+/// 正常状态的用户
+const USER_STAT_NORMAL: i32 = 1;
+
+/// 查看者与资料所属用户的关系
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ViewerRelation {
+    /// 资料所属用户把查看者加为好友
+    pub is_friend: bool,
+    /// 资料所属用户把查看者拉黑
+    pub blocked: bool,
+}
+
+/// 查看者能否看到该用户的资料
+///
+/// 已注销、已停用或拉黑了查看者的用户与不存在的用户一样不可见，避免通过返回结果探测账号状态
+pub fn profile_visible(user: &User, viewer_id: &str, relation: ViewerRelation) -> bool {
+    if user.id == viewer_id {
+        return true;
+    }
+    user.user_stat == USER_STAT_NORMAL && !relation.blocked
+}
+
+/// 按隐私设置生成查看者可见的公开资料
+///
+/// 所有返回用户资料字段的接口都应通过这里按查看者过滤，不可见的字段不返回
+pub fn public_profile(
+    user: User,
+    config: &UserConfig,
+    viewer_id: &str,
+    relation: ViewerRelation,
+    last_active_at: Option<DateTime<Utc>>,
+) -> PublicProfileResponse {
+    let is_friend = relation.is_friend;
+    let visible = |visibility: i16| config.field_visible_to(visibility, viewer_id, is_friend);
+
+    PublicProfileResponse {
+        user_id: user.id,
+        username: user.username,
+        nickname: user.nickname.unwrap_or_default(),
+        avatar_url: user.avatar_url.unwrap_or_default(),
+        head_image: user.head_image.unwrap_or_default(),
+        head_image_thumb: user.head_image_thumb.unwrap_or_default(),
+        is_friend,
+        email: user
+            .email
+            .filter(|_| visible(config.email_visibility))
+            .filter(|email| !email.is_empty()),
+        phone: Some(user.phone)
+            .filter(|_| visible(config.phone_visibility))
+            .filter(|phone| !phone.is_empty()),
+        sex: user.sex.filter(|_| visible(config.sex_visibility)),
+        address: user
+            .address
+            .filter(|_| visible(config.address_visibility))
+            .filter(|address| !address.is_empty()),
+        last_active_at: last_active_at
+            .filter(|_| config.last_seen_visible_to(viewer_id, is_friend))
+            .map(|dt| Timestamp {
+                seconds: dt.timestamp(),
+                nanos: dt.timestamp_subsec_nanos() as i32,
+            }),
+    }
+}
+
+/// 按隐私设置隐藏查看者不可见的用户字段
+///
+/// 用于直接返回完整用户信息的接口，规则与 [`public_profile`] 一致
+pub fn redact_user(
+    user: &mut User,
+    config: &UserConfig,
+    viewer_id: &str,
+    relation: ViewerRelation,
+) {
+    let visible =
+        |visibility: i16| config.field_visible_to(visibility, viewer_id, relation.is_friend);
+
+    if !visible(config.email_visibility) {
+        user.email = None;
+    }
+    if !visible(config.phone_visibility) {
+        user.phone = String::new();
+    }
+    if !visible(config.sex_visibility) {
+        user.sex = None;
+    }
+    if !visible(config.address_visibility) {
+        user.address = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::proto::user::LastSeenVisibility;
+
+    fn user(user_stat: i32) -> User {
+        User {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            email: Some("alice@example.com".to_string()),
+            password: "hash".to_string(),
+            nickname: Some("Alice".to_string()),
+            avatar_url: None,
+            created_at: Utc::now(),
+            updated_at: None,
+            phone: "13800000000".to_string(),
+            address: Some("Shanghai".to_string()),
+            head_image: None,
+            head_image_thumb: None,
+            sex: Some(2),
+            user_stat,
+            tenant_id: String::new(),
+            last_login_time: None,
+            user_idx: None,
+        }
+    }
+
+    #[test]
+    fn test_field_visibility() {
+        let mut config = UserConfig::default_for("u1");
+        config.phone_visibility = LastSeenVisibility::Friends as i16;
+        let stranger = ViewerRelation::default();
+        let friend = ViewerRelation {
+            is_friend: true,
+            blocked: false,
+        };
+
+        let profile = public_profile(user(1), &config, "u2", stranger, Some(Utc::now()));
+        assert_eq!(profile.email, None);
+        assert_eq!(profile.phone, None);
+        assert_eq!(profile.sex, Some(2));
+        assert_eq!(profile.address, None);
+        assert!(profile.last_active_at.is_none());
+
+        let profile = public_profile(user(1), &config, "u2", friend, None);
+        assert_eq!(profile.phone.as_deref(), Some("13800000000"));
+        assert_eq!(profile.address.as_deref(), Some("Shanghai"));
+        assert_eq!(profile.email, None);
+
+        // 本人可以看到全部字段
+        let profile = public_profile(user(1), &config, "u1", stranger, None);
+        assert_eq!(profile.email.as_deref(), Some("alice@example.com"));
+    }
+
+    #[test]
+    fn test_redact_user() {
+        let mut config = UserConfig::default_for("u1");
+        config.phone_visibility = LastSeenVisibility::Friends as i16;
+        let stranger = ViewerRelation::default();
+
+        let mut redacted = user(1);
+        redact_user(&mut redacted, &config, "u2", stranger);
+        assert_eq!(redacted.email, None);
+        assert_eq!(redacted.phone, "");
+        assert_eq!(redacted.sex, Some(2));
+        assert_eq!(redacted.address, None);
+        assert_eq!(redacted.username, "alice");
+
+        let mut own = user(1);
+        redact_user(&mut own, &config, "u1", stranger);
+        assert_eq!(own.email.as_deref(), Some("alice@example.com"));
+        assert_eq!(own.phone, "13800000000");
+    }
+
+    #[test]
+    fn test_profile_visible() {
+        let blocked = ViewerRelation {
+            is_friend: false,
+            blocked: true,
+        };
+        assert!(profile_visible(&user(1), "u2", ViewerRelation::default()));
+        assert!(!profile_visible(&user(1), "u2", blocked));
+        assert!(!profile_visible(&user(2), "u2", ViewerRelation::default()));
+        assert!(!profile_visible(&user(3), "u2", ViewerRelation::default()));
+        assert!(profile_visible(&user(3), "u1", ViewerRelation::default()));
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use common::proto::retention;
+use prost_types::Timestamp;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::storage::attachment_object_key;
+
+/// 法律保全对象：用户
+pub const HOLD_TARGET_USER: i16 = 0;
+/// 法律保全对象：会话（群组）
+pub const HOLD_TARGET_CONVERSATION: i16 = 1;
+
+/// 审计动作：设置保留策略
+pub const AUDIT_SET_POLICY: &str = "SET_POLICY";
+/// 审计动作：设置法律保全
+pub const AUDIT_PLACE_HOLD: &str = "PLACE_HOLD";
+/// 审计动作：解除法律保全
+pub const AUDIT_RELEASE_HOLD: &str = "RELEASE_HOLD";
+/// 审计动作：清理过期消息
+pub const AUDIT_PURGE: &str = "PURGE";
+
+/// 清理任务在审计记录中的操作人
+pub const SYSTEM_OPERATOR: &str = "system";
+
+/// 消息保留策略数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RetentionPolicy {
+    pub tenant_id: String,
+    pub retain_days: i32,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 法律保全数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LegalHold {
+    pub id: String,
+    pub tenant_id: String,
+    pub target_type: i16,
+    pub target_id: String,
+    pub reason: String,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub released_at: Option<DateTime<Utc>>,
+}
+
+/// 审计记录数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct RetentionAudit {
+    pub id: i64,
+    pub tenant_id: String,
+    pub action: String,
+    pub operator_id: String,
+    pub detail: String,
+    pub affected_rows: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 一批被清理的过期消息
+#[derive(Debug, Clone, Default)]
+pub struct PurgedMessages {
+    /// 删除的消息数
+    pub rows: u64,
+    /// 被删除消息中的附件对象键，需要释放存储配额
+    pub object_keys: Vec<String>,
+}
+
+impl PurgedMessages {
+    /// 从删除返回的 (内容类型, 内容) 中统计消息数并取出附件
+    pub fn from_rows(rows: Vec<(String, String)>) -> Self {
+        Self {
+            rows: rows.len() as u64,
+            object_keys: rows
+                .iter()
+                .filter_map(|(content_type, content)| attachment_object_key(content_type, content))
+                .collect(),
+        }
+    }
+}
+
+fn to_timestamp(time: DateTime<Utc>) -> Timestamp {
+    Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl From<RetentionPolicy> for retention::RetentionPolicy {
+    fn from(p: RetentionPolicy) -> Self {
+        Self {
+            tenant_id: p.tenant_id,
+            retain_days: p.retain_days,
+            updated_by: p.updated_by,
+            updated_at: Some(to_timestamp(p.updated_at)),
+        }
+    }
+}
+
+impl From<LegalHold> for retention::LegalHold {
+    fn from(h: LegalHold) -> Self {
+        let target_type = if h.target_type == HOLD_TARGET_CONVERSATION {
+            retention::LegalHoldTarget::Conversation
+        } else {
+            retention::LegalHoldTarget::User
+        };
+
+        Self {
+            id: h.id,
+            tenant_id: h.tenant_id,
+            target_type: target_type as i32,
+            target_id: h.target_id,
+            reason: h.reason,
+            created_by: h.created_by,
+            created_at: Some(to_timestamp(h.created_at)),
+            released_at: h.released_at.map(to_timestamp),
+        }
+    }
+}
+
+impl From<RetentionAudit> for retention::RetentionAudit {
+    fn from(a: RetentionAudit) -> Self {
+        Self {
+            id: a.id,
+            tenant_id: a.tenant_id,
+            action: a.action,
+            operator_id: a.operator_id,
+            detail: a.detail,
+            affected_rows: a.affected_rows,
+            created_at: Some(to_timestamp(a.created_at)),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use common::proto::storage;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// 对象键中保留的扩展名最大长度
+const MAX_EXTENSION_CHARS: usize = 10;
+
+/// 用户附件存储用量数据库模型
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct StorageUsage {
+    pub user_id: String,
+    pub used_bytes: i64,
+    pub attachment_count: i64,
+}
+
+impl StorageUsage {
+    pub fn into_proto(self, quota_bytes: i64) -> storage::StorageUsage {
+        storage::StorageUsage {
+            user_id: self.user_id,
+            used_bytes: self.used_bytes,
+            quota_bytes,
+            attachment_count: self.attachment_count,
+        }
+    }
+}
+
+/// 生成附件的对象键：{前缀}/{用户ID}/{年}/{月}/{随机ID}.{扩展名}
+///
+/// 扩展名取自原始文件名，只保留字母和数字，避免客户端通过文件名控制对象路径
+pub fn object_key(prefix: &str, user_id: &str, file_name: &str, now: DateTime<Utc>) -> String {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .filter(|ext| {
+            !ext.is_empty()
+                && ext.len() <= MAX_EXTENSION_CHARS
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        .map(|ext| format!(".{}", ext.to_ascii_lowercase()))
+        .unwrap_or_default();
+    format!(
+        "{}/{}/{}/{}{}",
+        prefix.trim_end_matches('/'),
+        user_id,
+        now.format("%Y/%m"),
+        Uuid::new_v4().simple(),
+        extension
+    )
+}
+
+/// 从消息表的内容中取出附件的对象键，非附件消息返回 None
+pub fn attachment_object_key(content_type: &str, content: &str) -> Option<String> {
+    if content_type.eq_ignore_ascii_case("TEXT") {
+        return None;
+    }
+    let descriptor: serde_json::Value = serde_json::from_str(content).ok()?;
+    descriptor
+        .get("objectKey")
+        .and_then(|key| key.as_str())
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_key() {
+        let now = DateTime::parse_from_rfc3339("2025-06-10T08:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let key = object_key("chat/", "u1", "报告.PDF", now);
+        assert!(key.starts_with("chat/u1/2025/06/"));
+        assert!(key.ends_with(".pdf"));
+
+        // 异常的扩展名不写入对象键
+        let key = object_key("chat", "u1", "a.p/../x", now);
+        assert!(!key.contains(".."));
+        assert!(!object_key("chat", "u1", "noext", now).contains('.'));
+    }
+
+    #[test]
+    fn test_attachment_object_key() {
+        let content = r#"{"objectKey":"chat/u1/2025/06/a.png","url":"https://oss/a.png"}"#;
+        assert_eq!(
+            attachment_object_key("IMAGE", content).as_deref(),
+            Some("chat/u1/2025/06/a.png")
+        );
+        assert!(attachment_object_key("TEXT", content).is_none());
+        assert!(attachment_object_key("FILE", "not json").is_none());
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 网关签发的访问令牌声明，字段与网关保持一致
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenClaims {
+    /// 主题 (用户ID)
+    pub sub: String,
+    /// 签发者
+    pub iss: Option<String>,
+    /// 过期时间
+    pub exp: u64,
+    /// 签发时间
+    pub iat: u64,
+    /// 用户名
+    pub username: String,
+    /// 租户ID
+    pub tenant_id: i64,
+    /// 租户名称
+    pub tenant_name: String,
+    /// 额外信息
+    #[serde(default)]
+    pub extra: HashMap<String, String>,
+}
+
+/// 令牌标识，取JWT的签名段
+///
+/// 签名段对每个令牌唯一，可直接作为吊销记录的键，无需额外的jti字段
+pub fn token_id(token: &str) -> &str {
+    token.rsplit('.').next().unwrap_or_default()
+}
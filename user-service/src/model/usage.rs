@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 每小时消息用量数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MessageUsageRecord {
+    /// 小时，格式 yyyyMMddHH（UTC）
+    pub stat_hour: String,
+    pub tenant_id: String,
+    pub user_id: String,
+    pub messages: i64,
+    pub bytes: i64,
+}
+
+/// 消息用量导出任务参数
+#[derive(Debug, Clone, Deserialize)]
+pub struct UsageExportParams {
+    pub tenant_id: String,
+    /// 起始小时（含），格式 yyyyMMddHH
+    pub from: String,
+    /// 结束小时（不含），格式 yyyyMMddHH
+    pub to: String,
+    /// 导出格式：csv 或 parquet
+    #[serde(default = "default_export_format")]
+    pub format: String,
+}
+
+fn default_export_format() -> String {
+    "csv".to_string()
+}
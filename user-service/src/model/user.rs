@@ -1,11 +1,12 @@
 use chrono::{DateTime, Utc};
 use common::proto::user;
+use pinyin::ToPinyin;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 
 /// 用户数据库模型
-#[derive(Debug, Clone, Serialize, Deserialize,FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,FromRow)]
 pub struct User {
     pub id: String,
     pub username: String,
@@ -26,6 +27,28 @@ pub struct User {
     pub user_idx: Option<String>,
 }
 
+/// 生成昵称的拼音检索键：全拼 + 空格 + 首字母，如 `张三` => `zhangsan zs`
+///
+/// 非汉字字符按小写原样保留，便于中英文混合昵称的检索
+pub fn nickname_pinyin(nickname: &str) -> String {
+    let mut full = String::new();
+    let mut initials = String::new();
+    for (c, py) in nickname.chars().zip(nickname.to_pinyin()) {
+        match py {
+            Some(py) => {
+                full.push_str(py.plain());
+                initials.push_str(py.first_letter());
+            }
+            None if c.is_alphanumeric() => {
+                full.extend(c.to_lowercase());
+                initials.extend(c.to_lowercase());
+            }
+            None => {}
+        }
+    }
+    format!("{} {}", full, initials)
+}
+
 /// 创建用户请求数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUserData {
@@ -80,6 +103,8 @@ impl From<User> for user::User {
                 nanos: dt.timestamp_subsec_nanos() as i32,
             }),
             user_idx: user.user_idx,
+            // 最后活跃时间受隐私设置约束，由批量查询按查看者单独填充
+            last_active_at: None,
         }
     }
 }
@@ -161,3 +186,15 @@ impl From<user::ForgetPasswordRequest> for ForgetPasswordData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nickname_pinyin() {
+        assert_eq!(nickname_pinyin("张三"), "zhangsan zs");
+        assert_eq!(nickname_pinyin("Tom李"), "tomli toml");
+        assert_eq!(nickname_pinyin("小 明!"), "xiaoming xm");
+    }
+}
@@ -0,0 +1,131 @@
+use common::proto::user::{self, EmailDigest, LastSeenVisibility};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// 用户配置数据库模型
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserConfig {
+    pub user_id: String,
+    pub last_seen_visibility: i16,
+    /// 不允许该用户发送的消息被搜索索引
+    pub search_opt_out: bool,
+    /// 允许其他用户通过用户名/邮箱搜索到该用户
+    pub allow_id_search: bool,
+    /// 允许其他用户通过手机号搜索到该用户
+    pub allow_phone_search: bool,
+    /// 允许非好友查看分享的名片
+    pub allow_card_share: bool,
+    /// 公开资料中邮箱的可见范围
+    pub email_visibility: i16,
+    /// 公开资料中手机号的可见范围
+    pub phone_visibility: i16,
+    /// 公开资料中性别的可见范围
+    pub sex_visibility: i16,
+    /// 公开资料中地址的可见范围
+    pub address_visibility: i16,
+    /// 离线摘要邮件的发送频率
+    pub email_digest: i16,
+}
+
+/// 用户配置更新数据，None表示保持不变
+#[derive(Debug, Clone, Default)]
+pub struct UpdateUserConfigData {
+    pub last_seen_visibility: Option<i16>,
+    pub search_opt_out: Option<bool>,
+    pub allow_id_search: Option<bool>,
+    pub allow_phone_search: Option<bool>,
+    pub allow_card_share: Option<bool>,
+    pub email_visibility: Option<i16>,
+    pub phone_visibility: Option<i16>,
+    pub sex_visibility: Option<i16>,
+    pub address_visibility: Option<i16>,
+    pub email_digest: Option<i16>,
+}
+
+impl UpdateUserConfigData {
+    pub fn is_empty(&self) -> bool {
+        self.last_seen_visibility.is_none()
+            && self.search_opt_out.is_none()
+            && self.allow_id_search.is_none()
+            && self.allow_phone_search.is_none()
+            && self.allow_card_share.is_none()
+            && self.email_visibility.is_none()
+            && self.phone_visibility.is_none()
+            && self.sex_visibility.is_none()
+            && self.address_visibility.is_none()
+            && self.email_digest.is_none()
+    }
+}
+
+impl UserConfig {
+    /// 未设置过配置的用户使用默认值
+    pub fn default_for(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            last_seen_visibility: LastSeenVisibility::Everyone as i16,
+            search_opt_out: false,
+            allow_id_search: true,
+            allow_phone_search: true,
+            allow_card_share: true,
+            email_visibility: LastSeenVisibility::Nobody as i16,
+            phone_visibility: LastSeenVisibility::Nobody as i16,
+            sex_visibility: LastSeenVisibility::Everyone as i16,
+            address_visibility: LastSeenVisibility::Friends as i16,
+            email_digest: EmailDigest::Off as i16,
+        }
+    }
+
+    /// 最后活跃时间可见范围，数据库中的非法值按所有人不可见处理
+    pub fn visibility(&self) -> LastSeenVisibility {
+        parse_visibility(self.last_seen_visibility)
+    }
+
+    /// 查看者是否可以看到该用户的最后活跃时间
+    ///
+    /// 用户本人始终可见
+    pub fn last_seen_visible_to(&self, viewer_id: &str, is_friend: bool) -> bool {
+        self.field_visible_to(self.last_seen_visibility, viewer_id, is_friend)
+    }
+
+    /// 查看者是否可以看到可见范围为 `visibility` 的字段，用户本人始终可见
+    pub fn field_visible_to(&self, visibility: i16, viewer_id: &str, is_friend: bool) -> bool {
+        if viewer_id == self.user_id {
+            return true;
+        }
+        match parse_visibility(visibility) {
+            LastSeenVisibility::Everyone => true,
+            LastSeenVisibility::Friends => is_friend,
+            LastSeenVisibility::Nobody => false,
+        }
+    }
+
+    /// 查看者是否可以查看该用户的名片
+    ///
+    /// 用户本人和好友始终可见，非好友由名片分享设置决定
+    pub fn card_visible_to(&self, viewer_id: &str, is_friend: bool) -> bool {
+        viewer_id == self.user_id || is_friend || self.allow_card_share
+    }
+}
+
+/// 数据库中的非法值按所有人不可见处理
+fn parse_visibility(visibility: i16) -> LastSeenVisibility {
+    LastSeenVisibility::try_from(visibility as i32).unwrap_or(LastSeenVisibility::Nobody)
+}
+
+impl From<UserConfig> for user::UserConfig {
+    fn from(config: UserConfig) -> Self {
+        Self {
+            user_id: config.user_id,
+            last_seen_visibility: config.last_seen_visibility as i32,
+            search_opt_out: config.search_opt_out,
+            allow_id_search: config.allow_id_search,
+            allow_phone_search: config.allow_phone_search,
+            allow_card_share: config.allow_card_share,
+            email_visibility: config.email_visibility as i32,
+            phone_visibility: config.phone_visibility as i32,
+            sex_visibility: config.sex_visibility as i32,
+            address_visibility: config.address_visibility as i32,
+            email_digest: config.email_digest as i32,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::model::access_log::{
+    partition_bounds, partition_day, partition_name, AccessLog, AccessLogFilter,
+    ACCESS_LOG_PARTITION_PREFIX,
+};
+use chrono::NaiveDate;
+use common::Result;
+use common::tenant_db::PgRouter;
+use sqlx::{Postgres, QueryBuilder, Row};
+
+const ACCESS_LOG_COLUMNS: &str =
+    "id, user_id, tenant_id, method, path, status, latency_ms, ip, user_agent, request_id, accessed_at";
+
+/// 访问日志仓库实现
+#[derive(Clone)]
+pub struct AccessLogRepository {
+    db: PgRouter,
+}
+
+impl AccessLogRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 批量写入访问日志，返回写入的条数
+    pub async fn insert_batch(&self, logs: &[AccessLog]) -> Result<u64> {
+        if logs.is_empty() {
+            return Ok(0);
+        }
+        let user_ids: Vec<&str> = logs.iter().map(|l| l.user_id.as_str()).collect();
+        let tenant_ids: Vec<&str> = logs.iter().map(|l| l.tenant_id.as_str()).collect();
+        let methods: Vec<&str> = logs.iter().map(|l| l.method.as_str()).collect();
+        let paths: Vec<&str> = logs.iter().map(|l| l.path.as_str()).collect();
+        let statuses: Vec<i16> = logs.iter().map(|l| l.status).collect();
+        let latencies: Vec<i32> = logs.iter().map(|l| l.latency_ms).collect();
+        let ips: Vec<&str> = logs.iter().map(|l| l.ip.as_str()).collect();
+        let user_agents: Vec<&str> = logs.iter().map(|l| l.user_agent.as_str()).collect();
+        let request_ids: Vec<&str> = logs.iter().map(|l| l.request_id.as_str()).collect();
+        let accessed_at: Vec<_> = logs.iter().map(|l| l.accessed_at).collect();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO api_access_log (user_id, tenant_id, method, path, status, latency_ms,
+                                        ip, user_agent, request_id, accessed_at)
+            SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::varchar[], $4::varchar[],
+                                 $5::int2[], $6::int4[], $7::varchar[], $8::varchar[],
+                                 $9::varchar[], $10::timestamptz[])
+            "#,
+        )
+        .bind(&user_ids)
+        .bind(&tenant_ids)
+        .bind(&methods)
+        .bind(&paths)
+        .bind(&statuses)
+        .bind(&latencies)
+        .bind(&ips)
+        .bind(&user_agents)
+        .bind(&request_ids)
+        .bind(&accessed_at)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 分页查询访问日志，按访问时间倒序，返回当前页和总数
+    pub async fn list(
+        &self,
+        filter: &AccessLogFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<AccessLog>, i64)> {
+        let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM api_access_log");
+        push_conditions(&mut count_query, filter);
+        let total: i64 = count_query.build().fetch_one(self.db.pool()).await?.get(0);
+
+        let mut query =
+            QueryBuilder::new(format!("SELECT {} FROM api_access_log", ACCESS_LOG_COLUMNS));
+        push_conditions(&mut query, filter);
+        query
+            .push(" ORDER BY accessed_at DESC, id DESC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let logs = query
+            .build_query_as::<AccessLog>()
+            .fetch_all(self.db.pool())
+            .await?;
+
+        Ok((logs, total))
+    }
+
+    /// 创建某天的分区，已存在时忽略
+    pub async fn create_partition(&self, day: NaiveDate) -> Result<()> {
+        let (start, end) = partition_bounds(day);
+        // 分区DDL不支持绑定参数，表名和边界均由日期生成
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF api_access_log FOR VALUES FROM ('{}') TO ('{}')",
+            partition_name(day),
+            start.to_rfc3339(),
+            end.to_rfc3339()
+        ))
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// 访问日志的全部分区，按日期升序
+    pub async fn list_partitions(&self) -> Result<Vec<NaiveDate>> {
+        let names: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT child.relname::text
+            FROM pg_inherits
+            JOIN pg_class parent ON pg_inherits.inhparent = parent.oid
+            JOIN pg_class child ON pg_inherits.inhrelid = child.oid
+            WHERE parent.relname = 'api_access_log' AND child.relname LIKE $1
+            "#,
+        )
+        .bind(format!("{}%", ACCESS_LOG_PARTITION_PREFIX))
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut days: Vec<NaiveDate> = names
+            .iter()
+            .filter_map(|name| partition_day(name))
+            .collect();
+        days.sort();
+        Ok(days)
+    }
+
+    /// 删除某天的分区
+    pub async fn drop_partition(&self, day: NaiveDate) -> Result<()> {
+        sqlx::query(&format!("DROP TABLE IF EXISTS {}", partition_name(day)))
+            .execute(self.db.pool())
+            .await?;
+
+        Ok(())
+    }
+}
+
+// 拼接查询条件，时间范围用于分区裁剪
+fn push_conditions(query: &mut QueryBuilder<'_, Postgres>, filter: &AccessLogFilter) {
+    query
+        .push(" WHERE tenant_id = ")
+        .push_bind(filter.tenant_id.clone())
+        .push(" AND accessed_at >= ")
+        .push_bind(filter.from)
+        .push(" AND accessed_at < ")
+        .push_bind(filter.to);
+    if let Some(user_id) = &filter.user_id {
+        query.push(" AND user_id = ").push_bind(user_id.clone());
+    }
+    if let Some(path_prefix) = &filter.path_prefix {
+        query
+            .push(" AND path LIKE ")
+            .push_bind(format!("{}%", escape_like(path_prefix)))
+            .push(" ESCAPE '\\'");
+    }
+    if let Some(status) = filter.status {
+        query.push(" AND status = ").push_bind(status);
+    }
+    if let Some(ip) = &filter.ip {
+        query.push(" AND ip = ").push_bind(ip.clone());
+    }
+}
+
+// 转义LIKE通配符，路径前缀按字面匹配
+fn escape_like(keyword: &str) -> String {
+    keyword
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use common::tenant_db::PgRouter;
+use common::{Error, Result};
+use tracing::error;
+
+use crate::model::email_digest::DigestCandidate;
+
+/// 离线摘要邮件仓库实现
+#[derive(Clone)]
+pub struct EmailDigestRepository {
+    db: PgRouter,
+}
+
+/// 离线摘要邮件的发送记录存储
+#[async_trait]
+pub trait DigestStore: Send + Sync {
+    /// 查询待发送摘要的用户，按用户ID分页
+    ///
+    /// 条件：最后活跃时间早于 `offline_before`、选择了 `frequency` 频率、上一次摘要早于 `sent_before`，
+    /// 并且在离线和上一次摘要之后有新的未读私聊消息或好友请求
+    async fn list_candidates(
+        &self,
+        frequency: i16,
+        offline_before: NaiveDateTime,
+        sent_before: NaiveDateTime,
+        after_user_id: &str,
+        limit: i64,
+    ) -> Result<Vec<DigestCandidate>>;
+
+    /// 占用本周期的发送机会，上一次摘要早于 `sent_before` 时记录为 `now` 并返回true，
+    /// 多个服务实例同时扫描时只有一个实例发送
+    async fn claim(
+        &self,
+        user_id: &str,
+        now: NaiveDateTime,
+        sent_before: NaiveDateTime,
+    ) -> Result<bool>;
+
+    /// 发送失败时恢复上一次的发送时间，下一轮扫描重试
+    async fn release(&self, user_id: &str, last_sent_at: Option<NaiveDateTime>) -> Result<()>;
+}
+
+impl EmailDigestRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl DigestStore for EmailDigestRepository {
+    async fn list_candidates(
+        &self,
+        frequency: i16,
+        offline_before: NaiveDateTime,
+        sent_before: NaiveDateTime,
+        after_user_id: &str,
+        limit: i64,
+    ) -> Result<Vec<DigestCandidate>> {
+        sqlx::query_as::<_, DigestCandidate>(
+            r#"
+            SELECT u.id AS user_id, COALESCE(u.email, '') AS email, COALESCE(NULLIF(u.nickname, ''), u.username, '') AS name,
+                COALESCE(u.tenant_id, '') AS tenant_id, u.last_active_at, d.sent_at AS last_sent_at,
+                (SELECT COUNT(*) FROM private_messages m
+                 WHERE m.receiver_id = u.id AND NOT m.is_read AND NOT m.is_deleted
+                   AND m.sent_at > u.last_active_at) AS unread_messages,
+                (SELECT COUNT(DISTINCT m.sender_id) FROM private_messages m
+                 WHERE m.receiver_id = u.id AND NOT m.is_read AND NOT m.is_deleted
+                   AND m.sent_at > u.last_active_at) AS unread_senders,
+                (SELECT COUNT(*) FROM friendships f
+                 WHERE f.friend_id = u.id AND f.status = '0'
+                   AND f.created_at > u.last_active_at) AS friend_requests
+            FROM users u
+            LEFT JOIN user_config c ON c.user_id = u.id
+            LEFT JOIN email_digest_log d ON d.user_id = u.id
+            WHERE u.id > $1
+              AND u.last_active_at < $2
+              AND COALESCE(c.email_digest, 0) = $3
+              AND (d.sent_at IS NULL OR d.sent_at < $4)
+              AND COALESCE(u.email, '') <> ''
+              AND (
+                  EXISTS (
+                      SELECT 1 FROM private_messages m
+                      WHERE m.receiver_id = u.id AND NOT m.is_read AND NOT m.is_deleted
+                        AND m.sent_at > GREATEST(u.last_active_at, d.sent_at)
+                  )
+                  OR EXISTS (
+                      SELECT 1 FROM friendships f
+                      WHERE f.friend_id = u.id AND f.status = '0'
+                        AND f.created_at > GREATEST(u.last_active_at, d.sent_at)
+                  )
+              )
+            ORDER BY u.id
+            LIMIT $5
+            "#,
+        )
+        .bind(after_user_id)
+        .bind(offline_before)
+        .bind(frequency)
+        .bind(sent_before)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询待发送摘要的用户失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    async fn claim(
+        &self,
+        user_id: &str,
+        now: NaiveDateTime,
+        sent_before: NaiveDateTime,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO email_digest_log (user_id, sent_at)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET sent_at = EXCLUDED.sent_at
+            WHERE email_digest_log.sent_at < $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .bind(sent_before)
+        .execute(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("记录摘要发送时间失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn release(&self, user_id: &str, last_sent_at: Option<NaiveDateTime>) -> Result<()> {
+        let query = match last_sent_at {
+            Some(sent_at) => {
+                sqlx::query("UPDATE email_digest_log SET sent_at = $2 WHERE user_id = $1")
+                    .bind(user_id)
+                    .bind(sent_at)
+            }
+            None => sqlx::query("DELETE FROM email_digest_log WHERE user_id = $1").bind(user_id),
+        };
+        query.execute(self.db.pool()).await.map_err(|err| {
+            error!("恢复摘要发送时间失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(())
+    }
+}
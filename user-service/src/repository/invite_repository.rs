@@ -0,0 +1,110 @@
+use crate::model::invite::PhoneInvite;
+use chrono::{Duration, Utc};
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use tracing::error;
+use uuid::Uuid;
+
+/// 手机号邀请仓库实现
+#[derive(Clone)]
+pub struct InviteRepository {
+    db: PgRouter,
+}
+
+impl InviteRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 保存一条待投递的邀请
+    pub async fn create(
+        &self,
+        sender_id: &str,
+        phone: &str,
+        content: &str,
+        expire_days: i64,
+    ) -> Result<PhoneInvite> {
+        sqlx::query_as::<_, PhoneInvite>(
+            r#"
+            INSERT INTO phone_invites (id, sender_id, phone, content, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, sender_id, phone, content, sms_sent, created_at, expires_at, delivered_at
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(sender_id)
+        .bind(phone)
+        .bind(content)
+        .bind(Utc::now() + Duration::days(expire_days))
+        .fetch_one(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("保存手机号邀请失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 标记邀请短信已发送
+    pub async fn mark_sms_sent(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE phone_invites SET sms_sent = TRUE WHERE id = $1")
+            .bind(id)
+            .execute(self.db.pool())
+            .await
+            .map_err(|err| {
+                error!("更新手机号邀请失败: {}", err);
+                Error::Database(err)
+            })?;
+        Ok(())
+    }
+
+    /// 统计发送人最近一段时间内发出的邀请数
+    pub async fn count_recent_by_sender(&self, sender_id: &str, hours: i64) -> Result<i64> {
+        sqlx::query_scalar(
+            "SELECT COUNT(*) FROM phone_invites WHERE sender_id = $1 AND created_at > $2",
+        )
+        .bind(sender_id)
+        .bind(Utc::now() - Duration::hours(hours))
+        .fetch_one(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("统计手机号邀请失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 查询手机号下所有未过期且未投递的邀请，按创建时间从旧到新
+    pub async fn pending(&self, phone: &str) -> Result<Vec<PhoneInvite>> {
+        sqlx::query_as::<_, PhoneInvite>(
+            r#"
+            SELECT id, sender_id, phone, content, sms_sent, created_at, expires_at, delivered_at
+            FROM phone_invites
+            WHERE phone = $1 AND delivered_at IS NULL AND expires_at > $2
+            ORDER BY created_at
+            "#,
+        )
+        .bind(phone)
+        .bind(Utc::now())
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询待投递的手机号邀请失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 邀请消息投递成功后标记为已投递，返回是否由本次标记（已被标记过时返回 false）
+    pub async fn mark_delivered(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE phone_invites SET delivered_at = $2 WHERE id = $1 AND delivered_at IS NULL",
+        )
+        .bind(id)
+        .bind(Utc::now())
+        .execute(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("标记手机号邀请已投递失败: {}", err);
+            Error::Database(err)
+        })?;
+        Ok(result.rows_affected() > 0)
+    }
+}
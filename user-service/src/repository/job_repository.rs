@@ -0,0 +1,149 @@
+use crate::model::job::{
+    CreateJobData, Job, JOB_STATUS_FAILED, JOB_STATUS_PENDING, JOB_STATUS_RUNNING,
+    JOB_STATUS_SUCCEEDED,
+};
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use tracing::error;
+use uuid::Uuid;
+
+const JOB_COLUMNS: &str = "id, user_id, kind, status, progress, params, result_key, error, attempts, created_at, updated_at";
+
+/// 异步任务仓库实现
+#[derive(Clone)]
+pub struct JobRepository {
+    db: PgRouter,
+}
+
+impl JobRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 在同类未结束任务数低于上限时创建任务，达到上限返回 `None`
+    ///
+    /// 计数和插入在同一事务内，并按 (用户, 类型) 加事务级咨询锁，
+    /// 并发提交不会同时通过上限检查
+    pub async fn create_job_within_limit(
+        &self,
+        data: CreateJobData,
+        max_active: i64,
+    ) -> Result<Option<Job>> {
+        let mut tx = self.db.pool().begin().await?;
+
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1 || ':' || $2))")
+            .bind(&data.user_id)
+            .bind(&data.kind)
+            .execute(&mut *tx)
+            .await?;
+
+        let active: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM async_jobs WHERE user_id = $1 AND kind = $2 AND status IN ($3, $4)",
+        )
+        .bind(&data.user_id)
+        .bind(&data.kind)
+        .bind(JOB_STATUS_PENDING)
+        .bind(JOB_STATUS_RUNNING)
+        .fetch_one(&mut *tx)
+        .await?;
+        if active >= max_active {
+            return Ok(None);
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let job = sqlx::query_as::<_, Job>(&format!(
+            "INSERT INTO async_jobs (id, user_id, kind, status, params) VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+            JOB_COLUMNS
+        ))
+        .bind(&id)
+        .bind(&data.user_id)
+        .bind(&data.kind)
+        .bind(JOB_STATUS_PENDING)
+        .bind(&data.params)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("创建任务失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        tx.commit().await?;
+        Ok(Some(job))
+    }
+
+    /// 根据ID查询任务
+    pub async fn get_job(&self, id: &str) -> Result<Job> {
+        sqlx::query_as::<_, Job>(&format!("SELECT {} FROM async_jobs WHERE id = $1", JOB_COLUMNS))
+            .bind(id)
+            .fetch_one(self.db.pool())
+            .await
+            .map_err(|err| {
+                if let sqlx::Error::RowNotFound = err {
+                    Error::NotFound(format!("任务 {} 不存在", id))
+                } else {
+                    error!("查询任务失败: {}", err);
+                    Error::Database(err)
+                }
+            })
+    }
+
+    /// 领取下一个待执行任务
+    ///
+    /// 使用 `FOR UPDATE SKIP LOCKED`，多个工作进程可以同时拉取而不会重复执行
+    pub async fn claim_next(&self) -> Result<Option<Job>> {
+        let job = sqlx::query_as::<_, Job>(&format!(
+            r#"
+            UPDATE async_jobs
+            SET status = $1, attempts = attempts + 1
+            WHERE id = (
+                SELECT id FROM async_jobs
+                WHERE status = $2
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {}
+            "#,
+            JOB_COLUMNS
+        ))
+        .bind(JOB_STATUS_RUNNING)
+        .bind(JOB_STATUS_PENDING)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(job)
+    }
+
+    /// 更新任务进度
+    pub async fn update_progress(&self, id: &str, progress: i32) -> Result<()> {
+        sqlx::query("UPDATE async_jobs SET progress = $1 WHERE id = $2 AND status = $3")
+            .bind(progress.clamp(0, 100))
+            .bind(id)
+            .bind(JOB_STATUS_RUNNING)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// 标记任务成功
+    pub async fn mark_succeeded(&self, id: &str, result_key: &str) -> Result<()> {
+        sqlx::query("UPDATE async_jobs SET status = $1, progress = 100, result_key = $2 WHERE id = $3")
+            .bind(JOB_STATUS_SUCCEEDED)
+            .bind(result_key)
+            .bind(id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// 标记任务失败
+    pub async fn mark_failed(&self, id: &str, reason: &str) -> Result<()> {
+        sqlx::query("UPDATE async_jobs SET status = $1, error = $2 WHERE id = $3")
+            .bind(JOB_STATUS_FAILED)
+            .bind(reason)
+            .bind(id)
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use crate::model::login_history::{LoginContext, LoginHistory, UserDevice};
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use tracing::error;
+use uuid::Uuid;
+
+/// 登录历史仓库实现
+#[derive(Clone)]
+pub struct LoginHistoryRepository {
+    db: PgRouter,
+}
+
+impl LoginHistoryRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 记录一次登录
+    pub async fn record(
+        &self,
+        user_id: &str,
+        ctx: &LoginContext,
+        ip_network: &str,
+        risk_score: i32,
+        trusted: bool,
+    ) -> Result<LoginHistory> {
+        sqlx::query_as::<_, LoginHistory>(
+            r#"
+            INSERT INTO login_history
+                (id, user_id, device_id, device_name, ip, ip_network, user_agent, risk_score, trusted)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, user_id, device_id, device_name, ip, ip_network, user_agent,
+                      risk_score, trusted, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(user_id)
+        .bind(&ctx.device_id)
+        .bind(&ctx.device_name)
+        .bind(&ctx.ip)
+        .bind(ip_network)
+        .bind(&ctx.user_agent)
+        .bind(risk_score)
+        .bind(trusted)
+        .fetch_one(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("记录登录历史失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 查询用户最近的可信登录记录，作为风险比对的基线
+    pub async fn recent_trusted(&self, user_id: &str, limit: i64) -> Result<Vec<LoginHistory>> {
+        sqlx::query_as::<_, LoginHistory>(
+            r#"
+            SELECT id, user_id, device_id, device_name, ip, ip_network, user_agent,
+                   risk_score, trusted, created_at
+            FROM login_history
+            WHERE user_id = $1 AND trusted = TRUE
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询登录历史失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 二次验证通过后将登录记录标记为可信
+    pub async fn mark_trusted(&self, id: &str) -> Result<()> {
+        sqlx::query("UPDATE login_history SET trusted = TRUE WHERE id = $1")
+            .bind(id)
+            .execute(self.db.pool())
+            .await
+            .map_err(|err| {
+                error!("更新登录历史失败: {}", err);
+                Error::Database(err)
+            })?;
+        Ok(())
+    }
+
+    /// 查询用户登录过的设备，按最近登录时间倒序
+    pub async fn list_devices(&self, user_id: &str, limit: i64) -> Result<Vec<UserDevice>> {
+        sqlx::query_as::<_, UserDevice>(
+            r#"
+            SELECT device_id, device_name, last_ip, last_login_at, trusted FROM (
+                SELECT DISTINCT ON (device_id)
+                       device_id, device_name, ip AS last_ip, created_at AS last_login_at,
+                       BOOL_OR(trusted) OVER (PARTITION BY device_id) AS trusted
+                FROM login_history
+                WHERE user_id = $1 AND device_id <> ''
+                ORDER BY device_id, created_at DESC
+            ) devices
+            ORDER BY last_login_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询用户设备失败: {}", err);
+            Error::Database(err)
+        })
+    }
+}
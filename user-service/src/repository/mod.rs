@@ -1 +1,10 @@
 pub mod user_repository;
+pub mod job_repository;
+pub mod user_config_repository;
+pub mod login_history_repository;
+pub mod retention_repository;
+pub mod invite_repository;
+pub mod usage_repository;
+pub mod access_log_repository;
+pub mod email_digest_repository;
+pub mod storage_repository;
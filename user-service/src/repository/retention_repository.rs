@@ -0,0 +1,344 @@
+use crate::model::retention::{
+    LegalHold, PurgedMessages, RetentionAudit, RetentionPolicy, AUDIT_PLACE_HOLD, AUDIT_PURGE,
+    AUDIT_RELEASE_HOLD, AUDIT_SET_POLICY, HOLD_TARGET_CONVERSATION, HOLD_TARGET_USER,
+    SYSTEM_OPERATOR,
+};
+use chrono::{DateTime, Utc};
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use tracing::error;
+use uuid::Uuid;
+
+const HOLD_COLUMNS: &str =
+    "id, tenant_id, target_type, target_id, reason, created_by, created_at, released_at";
+
+const AUDIT_COLUMNS: &str =
+    "id, tenant_id, action, operator_id, detail, affected_rows, created_at";
+
+/// 消息保留策略仓库实现
+#[derive(Clone)]
+pub struct RetentionRepository {
+    db: PgRouter,
+}
+
+impl RetentionRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 用户是否为租户管理员
+    pub async fn is_tenant_admin(&self, tenant_id: &str, user_id: &str) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM tenant_admins WHERE tenant_id = $1 AND user_id = $2)",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .fetch_one(self.db.pool())
+        .await?;
+
+        Ok(exists)
+    }
+
+    /// 查询租户的保留策略
+    pub async fn get_policy(&self, tenant_id: &str) -> Result<Option<RetentionPolicy>> {
+        sqlx::query_as::<_, RetentionPolicy>(
+            "SELECT tenant_id, retain_days, updated_by, updated_at FROM retention_policies WHERE tenant_id = $1",
+        )
+        .bind(tenant_id)
+        .fetch_optional(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询保留策略失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 所有设置了保留期限的策略，供清理任务使用
+    pub async fn list_active_policies(&self) -> Result<Vec<RetentionPolicy>> {
+        sqlx::query_as::<_, RetentionPolicy>(
+            "SELECT tenant_id, retain_days, updated_by, updated_at FROM retention_policies WHERE retain_days > 0",
+        )
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询保留策略列表失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 设置保留策略并写入审计记录
+    pub async fn set_policy(
+        &self,
+        tenant_id: &str,
+        retain_days: i32,
+        operator_id: &str,
+        detail: &str,
+    ) -> Result<RetentionPolicy> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let policy = sqlx::query_as::<_, RetentionPolicy>(
+            r#"
+            INSERT INTO retention_policies (tenant_id, retain_days, updated_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (tenant_id) DO UPDATE
+                SET retain_days = EXCLUDED.retain_days, updated_by = EXCLUDED.updated_by
+            RETURNING tenant_id, retain_days, updated_by, updated_at
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(retain_days)
+        .bind(operator_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("设置保留策略失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Self::insert_audit(&mut tx, tenant_id, AUDIT_SET_POLICY, operator_id, detail, 0).await?;
+        tx.commit().await?;
+
+        Ok(policy)
+    }
+
+    /// 设置法律保全并写入审计记录
+    pub async fn place_hold(
+        &self,
+        tenant_id: &str,
+        target_type: i16,
+        target_id: &str,
+        reason: &str,
+        operator_id: &str,
+        detail: &str,
+    ) -> Result<LegalHold> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let hold = sqlx::query_as::<_, LegalHold>(&format!(
+            r#"
+            INSERT INTO legal_holds (id, tenant_id, target_type, target_id, reason, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING {}
+            "#,
+            HOLD_COLUMNS
+        ))
+        .bind(Uuid::new_v4().to_string())
+        .bind(tenant_id)
+        .bind(target_type)
+        .bind(target_id)
+        .bind(reason)
+        .bind(operator_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("设置法律保全失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Self::insert_audit(&mut tx, tenant_id, AUDIT_PLACE_HOLD, operator_id, detail, 0).await?;
+        tx.commit().await?;
+
+        Ok(hold)
+    }
+
+    /// 解除法律保全并写入审计记录
+    pub async fn release_hold(
+        &self,
+        tenant_id: &str,
+        hold_id: &str,
+        operator_id: &str,
+        detail: &str,
+    ) -> Result<LegalHold> {
+        let mut tx = self.db.pool().begin().await?;
+
+        let hold = sqlx::query_as::<_, LegalHold>(&format!(
+            r#"
+            UPDATE legal_holds SET released_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND tenant_id = $2 AND released_at IS NULL
+            RETURNING {}
+            "#,
+            HOLD_COLUMNS
+        ))
+        .bind(hold_id)
+        .bind(tenant_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("解除法律保全失败: {}", err);
+            Error::Database(err)
+        })?
+        .ok_or_else(|| Error::NotFound(format!("法律保全 {} 不存在或已解除", hold_id)))?;
+
+        Self::insert_audit(&mut tx, tenant_id, AUDIT_RELEASE_HOLD, operator_id, detail, 0).await?;
+        tx.commit().await?;
+
+        Ok(hold)
+    }
+
+    /// 查询租户生效中的法律保全
+    pub async fn list_active_holds(&self, tenant_id: &str) -> Result<Vec<LegalHold>> {
+        sqlx::query_as::<_, LegalHold>(&format!(
+            "SELECT {} FROM legal_holds WHERE tenant_id = $1 AND released_at IS NULL ORDER BY created_at DESC",
+            HOLD_COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询法律保全失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 分页查询审计记录
+    pub async fn list_audits(
+        &self,
+        tenant_id: &str,
+        page: i64,
+        page_size: i64,
+    ) -> Result<(Vec<RetentionAudit>, i64)> {
+        let offset = (page - 1).max(0) * page_size;
+
+        let audits = sqlx::query_as::<_, RetentionAudit>(&format!(
+            "SELECT {} FROM retention_audits WHERE tenant_id = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+            AUDIT_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(page_size)
+        .bind(offset)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询审计记录失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        let total: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM retention_audits WHERE tenant_id = $1")
+                .bind(tenant_id)
+                .fetch_one(self.db.pool())
+                .await?;
+
+        Ok((audits, total))
+    }
+
+    /// 删除一批过期的私聊消息，跳过处于法律保全中的用户
+    ///
+    /// 消息按发送者所属租户归属
+    pub async fn purge_private_messages(
+        &self,
+        tenant_id: &str,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<PurgedMessages> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            DELETE FROM private_messages WHERE id IN (
+                SELECT m.id FROM private_messages m
+                JOIN users u ON u.id = m.sender_id
+                WHERE u.tenant_id = $1
+                  AND m.sent_at < $2
+                  AND NOT EXISTS (
+                      SELECT 1 FROM legal_holds h
+                      WHERE h.tenant_id = $1 AND h.released_at IS NULL
+                        AND h.target_type = $3 AND h.target_id IN (m.sender_id, m.receiver_id)
+                  )
+                LIMIT $4
+            )
+            RETURNING content_type, content
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(cutoff.naive_utc())
+        .bind(HOLD_TARGET_USER)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("清理私聊消息失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(PurgedMessages::from_rows(rows))
+    }
+
+    /// 删除一批过期的群聊消息，跳过处于法律保全中的发送者和群组
+    pub async fn purge_group_messages(
+        &self,
+        tenant_id: &str,
+        cutoff: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<PurgedMessages> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            DELETE FROM group_messages WHERE id IN (
+                SELECT m.id FROM group_messages m
+                JOIN users u ON u.id = m.sender_id
+                WHERE u.tenant_id = $1
+                  AND m.sent_at < $2
+                  AND NOT EXISTS (
+                      SELECT 1 FROM legal_holds h
+                      WHERE h.tenant_id = $1 AND h.released_at IS NULL
+                        AND ((h.target_type = $3 AND h.target_id = m.sender_id)
+                          OR (h.target_type = $4 AND h.target_id = m.group_id))
+                  )
+                LIMIT $5
+            )
+            RETURNING content_type, content
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(cutoff.naive_utc())
+        .bind(HOLD_TARGET_USER)
+        .bind(HOLD_TARGET_CONVERSATION)
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("清理群聊消息失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(PurgedMessages::from_rows(rows))
+    }
+
+    /// 写入清理审计记录
+    pub async fn record_purge(&self, tenant_id: &str, detail: &str, affected_rows: i64) -> Result<()> {
+        let mut tx = self.db.pool().begin().await?;
+        Self::insert_audit(
+            &mut tx,
+            tenant_id,
+            AUDIT_PURGE,
+            SYSTEM_OPERATOR,
+            detail,
+            affected_rows,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_audit(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: &str,
+        action: &str,
+        operator_id: &str,
+        detail: &str,
+        affected_rows: i64,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO retention_audits (tenant_id, action, operator_id, detail, affected_rows) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(tenant_id)
+        .bind(action)
+        .bind(operator_id)
+        .bind(detail)
+        .bind(affected_rows)
+        .execute(&mut **tx)
+        .await
+        .map_err(|err| {
+            error!("写入保留策略审计记录失败: {}", err);
+            Error::Database(err)
+        })?;
+        Ok(())
+    }
+}
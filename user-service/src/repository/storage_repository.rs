@@ -0,0 +1,195 @@
+use crate::model::storage::StorageUsage;
+use common::tenant_db::PgRouter;
+use common::{Error, Result};
+use std::collections::HashMap;
+use tracing::error;
+
+/// 用户附件存储配额仓库实现
+#[derive(Clone)]
+pub struct StorageRepository {
+    db: PgRouter,
+}
+
+impl StorageRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 查询用户的存储用量，没有记录时用量为0
+    pub async fn usage(&self, user_id: &str) -> Result<StorageUsage> {
+        let usage = sqlx::query_as::<_, StorageUsage>(
+            "SELECT user_id, used_bytes, attachment_count FROM user_storage_usage WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        Ok(usage.unwrap_or_else(|| StorageUsage {
+            user_id: user_id.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    /// 登记附件并预占配额
+    ///
+    /// 用量加上附件大小超过配额时不登记，返回 None；`quota_bytes` 为0时不限制。
+    /// 预占在 `ttl_secs` 秒内未通过 [`commit`](Self::commit) 确认时由后台任务释放
+    pub async fn reserve(
+        &self,
+        user_id: &str,
+        object_key: &str,
+        size: i64,
+        mime_type: &str,
+        quota_bytes: i64,
+        ttl_secs: i64,
+    ) -> Result<Option<StorageUsage>> {
+        let mut tx = self.db.pool().begin().await?;
+
+        // 同一用户的并发申请在行锁上排队，条件更新保证用量不会超过配额
+        let usage = sqlx::query_as::<_, StorageUsage>(
+            r#"
+            INSERT INTO user_storage_usage (user_id, used_bytes, attachment_count)
+            VALUES ($1, $2, 1)
+            ON CONFLICT (user_id) DO UPDATE
+                SET used_bytes = user_storage_usage.used_bytes + EXCLUDED.used_bytes,
+                    attachment_count = user_storage_usage.attachment_count + 1,
+                    updated_at = CURRENT_TIMESTAMP
+                WHERE $3 = 0 OR user_storage_usage.used_bytes + EXCLUDED.used_bytes <= $3
+            RETURNING user_id, used_bytes, attachment_count
+            "#,
+        )
+        .bind(user_id)
+        .bind(size)
+        .bind(quota_bytes)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("预占存储配额失败: {}", err);
+            Error::Database(err)
+        })?;
+        let Some(usage) = usage else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_attachments (object_key, user_id, size, mime_type, committed, reserved_until)
+            VALUES ($1, $2, $3, $4, FALSE, CURRENT_TIMESTAMP + make_interval(secs => $5))
+            "#,
+        )
+        .bind(object_key)
+        .bind(user_id)
+        .bind(size)
+        .bind(mime_type)
+        .bind(ttl_secs as f64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("登记附件失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        tx.commit().await?;
+        Ok(Some(usage))
+    }
+
+    /// 确认附件已上传完成，确认后的附件不再被过期清理释放，返回附件的 (大小, MIME类型)
+    ///
+    /// 附件不属于该用户、已释放或预占已过期时返回 None
+    pub async fn commit(&self, user_id: &str, object_key: &str) -> Result<Option<(i64, String)>> {
+        sqlx::query_as(
+            r#"
+            UPDATE user_attachments
+            SET committed = TRUE, reserved_until = NULL
+            WHERE object_key = $1 AND user_id = $2
+              AND (committed OR reserved_until > CURRENT_TIMESTAMP)
+            RETURNING size, mime_type
+            "#,
+        )
+        .bind(object_key)
+        .bind(user_id)
+        .fetch_optional(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("确认附件上传失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 查询预占已过期且未确认的附件对象键
+    pub async fn expired_reservations(&self, limit: i64) -> Result<Vec<String>> {
+        let keys: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT object_key FROM user_attachments
+            WHERE NOT committed AND reserved_until <= CURRENT_TIMESTAMP
+            ORDER BY reserved_until
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(keys.into_iter().map(|(key,)| key).collect())
+    }
+
+    /// 删除附件登记并扣减上传者的用量，返回实际释放的 (对象键, 大小)
+    ///
+    /// 未登记或已释放的对象键直接忽略，重复释放不会重复扣减
+    pub async fn release(&self, object_keys: &[String]) -> Result<Vec<(String, i64)>> {
+        if object_keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut tx = self.db.pool().begin().await?;
+
+        let released: Vec<(String, String, i64)> = sqlx::query_as(
+            "DELETE FROM user_attachments WHERE object_key = ANY($1) RETURNING object_key, user_id, size",
+        )
+        .bind(object_keys)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("删除附件登记失败: {}", err);
+            Error::Database(err)
+        })?;
+        if released.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut totals: HashMap<&str, (i64, i64)> = HashMap::new();
+        for (_, user_id, size) in &released {
+            let total = totals.entry(user_id.as_str()).or_default();
+            total.0 += size;
+            total.1 += 1;
+        }
+        let user_ids: Vec<&str> = totals.keys().copied().collect();
+        let bytes: Vec<i64> = user_ids.iter().map(|id| totals[id].0).collect();
+        let counts: Vec<i64> = user_ids.iter().map(|id| totals[id].1).collect();
+
+        sqlx::query(
+            r#"
+            UPDATE user_storage_usage u
+            SET used_bytes = GREATEST(u.used_bytes - t.bytes, 0),
+                attachment_count = GREATEST(u.attachment_count - t.count, 0),
+                updated_at = CURRENT_TIMESTAMP
+            FROM UNNEST($1::varchar[], $2::int8[], $3::int8[]) AS t(user_id, bytes, count)
+            WHERE u.user_id = t.user_id
+            "#,
+        )
+        .bind(&user_ids)
+        .bind(&bytes)
+        .bind(&counts)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| {
+            error!("扣减存储用量失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        tx.commit().await?;
+        Ok(released
+            .into_iter()
+            .map(|(object_key, _, size)| (object_key, size))
+            .collect())
+    }
+}
@@ -0,0 +1,70 @@
+use crate::model::usage::MessageUsageRecord;
+use cache::MessageUsage;
+use common::Result;
+use common::tenant_db::PgRouter;
+
+/// 消息用量仓库实现
+#[derive(Clone)]
+pub struct UsageRepository {
+    db: PgRouter,
+}
+
+impl UsageRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 写入某小时的消息用量，已存在的记录以本次汇总结果覆盖
+    pub async fn upsert_hourly(&self, hour: &str, usage: &[MessageUsage]) -> Result<()> {
+        if usage.is_empty() {
+            return Ok(());
+        }
+        let tenant_ids: Vec<&str> = usage.iter().map(|u| u.tenant_id.as_str()).collect();
+        let user_ids: Vec<&str> = usage.iter().map(|u| u.user_id.as_str()).collect();
+        let messages: Vec<i64> = usage.iter().map(|u| u.messages).collect();
+        let bytes: Vec<i64> = usage.iter().map(|u| u.bytes).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO message_usage_hourly (stat_hour, tenant_id, user_id, messages, bytes)
+            SELECT $1, * FROM UNNEST($2::varchar[], $3::varchar[], $4::bigint[], $5::bigint[])
+            ON CONFLICT (stat_hour, tenant_id, user_id) DO UPDATE
+            SET messages = EXCLUDED.messages,
+                bytes = EXCLUDED.bytes
+            "#,
+        )
+        .bind(hour)
+        .bind(&tenant_ids)
+        .bind(&user_ids)
+        .bind(&messages)
+        .bind(&bytes)
+        .execute(self.db.pool())
+        .await?;
+
+        Ok(())
+    }
+
+    /// 查询租户在 [from, to) 小时范围内的用量，按小时和用户排序
+    pub async fn list_by_tenant(
+        &self,
+        tenant_id: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<MessageUsageRecord>> {
+        let records = sqlx::query_as::<_, MessageUsageRecord>(
+            r#"
+            SELECT stat_hour, tenant_id, user_id, messages, bytes
+            FROM message_usage_hourly
+            WHERE tenant_id = $1 AND stat_hour >= $2 AND stat_hour < $3
+            ORDER BY stat_hour, user_id
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        Ok(records)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::model::user_config::{UpdateUserConfigData, UserConfig};
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use std::collections::HashMap;
+use tracing::error;
+
+/// 用户配置仓库实现
+#[derive(Clone)]
+pub struct UserConfigRepository {
+    db: PgRouter,
+}
+
+impl UserConfigRepository {
+    pub fn new(db: PgRouter) -> Self {
+        Self { db }
+    }
+
+    /// 获取用户配置，未设置过时返回默认配置
+    pub async fn get_config(&self, user_id: &str) -> Result<UserConfig> {
+        let config = sqlx::query_as::<_, UserConfig>(
+            r#"
+            SELECT user_id, last_seen_visibility, search_opt_out, allow_id_search, allow_phone_search, allow_card_share,
+            email_visibility, phone_visibility, sex_visibility, address_visibility, email_digest
+            FROM user_config
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询用户配置失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(config.unwrap_or_else(|| UserConfig::default_for(user_id)))
+    }
+
+    /// 批量获取用户配置，未设置过的用户使用默认配置
+    pub async fn get_configs(&self, user_ids: &[String]) -> Result<HashMap<String, UserConfig>> {
+        let rows = sqlx::query_as::<_, UserConfig>(
+            r#"
+            SELECT user_id, last_seen_visibility, search_opt_out, allow_id_search, allow_phone_search, allow_card_share,
+            email_visibility, phone_visibility, sex_visibility, address_visibility, email_digest
+            FROM user_config
+            WHERE user_id = ANY($1)
+            "#,
+        )
+        .bind(user_ids)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("批量查询用户配置失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        let mut configs: HashMap<String, UserConfig> = rows
+            .into_iter()
+            .map(|config| (config.user_id.clone(), config))
+            .collect();
+        for user_id in user_ids {
+            configs
+                .entry(user_id.clone())
+                .or_insert_with(|| UserConfig::default_for(user_id));
+        }
+        Ok(configs)
+    }
+
+    /// 更新用户配置，未传入的配置项保持不变
+    pub async fn update_config(
+        &self,
+        user_id: &str,
+        data: UpdateUserConfigData,
+    ) -> Result<UserConfig> {
+        sqlx::query_as::<_, UserConfig>(
+            r#"
+            INSERT INTO user_config (user_id, last_seen_visibility, search_opt_out, allow_id_search, allow_phone_search, allow_card_share,
+                email_visibility, phone_visibility, sex_visibility, address_visibility, email_digest)
+            VALUES ($1, COALESCE($2, 0), COALESCE($3, FALSE), COALESCE($4, TRUE), COALESCE($5, TRUE), COALESCE($6, TRUE),
+                COALESCE($7, 2), COALESCE($8, 2), COALESCE($9, 0), COALESCE($10, 1), COALESCE($11, 0))
+            ON CONFLICT (user_id)
+            DO UPDATE SET
+                last_seen_visibility = COALESCE($2, user_config.last_seen_visibility),
+                search_opt_out = COALESCE($3, user_config.search_opt_out),
+                allow_id_search = COALESCE($4, user_config.allow_id_search),
+                allow_phone_search = COALESCE($5, user_config.allow_phone_search),
+                allow_card_share = COALESCE($6, user_config.allow_card_share),
+                email_visibility = COALESCE($7, user_config.email_visibility),
+                phone_visibility = COALESCE($8, user_config.phone_visibility),
+                sex_visibility = COALESCE($9, user_config.sex_visibility),
+                address_visibility = COALESCE($10, user_config.address_visibility),
+                email_digest = COALESCE($11, user_config.email_digest)
+            RETURNING user_id, last_seen_visibility, search_opt_out, allow_id_search, allow_phone_search, allow_card_share,
+            email_visibility, phone_visibility, sex_visibility, address_visibility, email_digest
+            "#,
+        )
+        .bind(user_id)
+        .bind(data.last_seen_visibility)
+        .bind(data.search_opt_out)
+        .bind(data.allow_id_search)
+        .bind(data.allow_phone_search)
+        .bind(data.allow_card_share)
+        .bind(data.email_visibility)
+        .bind(data.phone_visibility)
+        .bind(data.sex_visibility)
+        .bind(data.address_visibility)
+        .bind(data.email_digest)
+        .fetch_one(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("更新用户配置失败: {}", err);
+            Error::Database(err)
+        })
+    }
+
+    /// 设置会话是否排除在用户的消息搜索之外，重复设置不报错
+    pub async fn set_conversation_search_opt_out(
+        &self,
+        user_id: &str,
+        conversation_id: &str,
+        opt_out: bool,
+    ) -> Result<()> {
+        let query = if opt_out {
+            sqlx::query(
+                r#"
+                INSERT INTO conversation_search_opt_out (user_id, conversation_id)
+                VALUES ($1, $2)
+                ON CONFLICT (user_id, conversation_id) DO NOTHING
+                "#,
+            )
+        } else {
+            sqlx::query(
+                r#"
+                DELETE FROM conversation_search_opt_out
+                WHERE user_id = $1 AND conversation_id = $2
+                "#,
+            )
+        };
+
+        query
+            .bind(user_id)
+            .bind(conversation_id)
+            .execute(self.db.pool())
+            .await
+            .map_err(|err| {
+                error!("设置会话搜索排除失败: {}", err);
+                Error::Database(err)
+            })?;
+        Ok(())
+    }
+
+    /// 获取用户排除在消息搜索之外的会话
+    pub async fn get_search_excluded_conversations(&self, user_id: &str) -> Result<Vec<String>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT conversation_id
+            FROM conversation_search_opt_out
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("获取会话搜索排除失败: {}", err);
+            Error::Database(err)
+        })
+    }
+}
@@ -1,20 +1,54 @@
-use crate::model::user::{CreateUserData, ForgetPasswordData, RegisterUserData, UpdateUserData, User};
-use chrono::{TimeZone, Utc};
+use crate::model::user::{nickname_pinyin, CreateUserData, ForgetPasswordData, RegisterUserData, UpdateUserData, User};
+use crate::model::profile::ViewerRelation;
+use chrono::{DateTime, TimeZone, Utc};
+use common::schema_migration::SchemaMigration;
 use common::utils::{hash_password, verify_password};
 use common::{Error, Result};
-use sqlx::{PgPool, QueryBuilder, Row};
+use common::tenant_db::PgRouter;
+use sqlx::{FromRow, QueryBuilder, Row};
+use std::collections::HashSet;
 use tracing::{debug, error};
 use tracing::log::info;
 use uuid::Uuid;
 
+/// 用户ID迁移为UUID列的表结构迁移名称
+pub const USERS_UUID_MIGRATION: &str = "users_uuid";
+
 /// 用户仓库实现
+#[derive(Clone)]
 pub struct UserRepository {
-    pool: PgPool,
+    db: PgRouter,
+    migration: SchemaMigration,
 }
 
 impl UserRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(db: PgRouter) -> Self {
+        Self {
+            db,
+            migration: SchemaMigration::disabled(USERS_UUID_MIGRATION),
+        }
+    }
+
+    /// 设置用户ID的表结构迁移开关
+    pub fn with_migration(mut self, migration: SchemaMigration) -> Self {
+        self.migration = migration;
+        self
+    }
+
+    /// 迁移期间将用户ID同步写入新的UUID列
+    ///
+    /// 迁移列在执行DDL之前不存在，使用运行时检查的查询
+    async fn sync_id_uuid(&self, id: &str, op: &str) {
+        if !self.migration.dual_write() {
+            return;
+        }
+        if let Err(e) = sqlx::query("UPDATE users SET id_uuid = id::uuid WHERE id = $1")
+            .bind(id)
+            .execute(self.db.pool())
+            .await
+        {
+            self.migration.write_failed(op, e);
+        }
     }
 
     /// 用户注册
@@ -56,7 +90,7 @@ impl UserRepository {
             data.phone,
             data.tenant_id
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             error!("用户注册失败: {}", err);
@@ -82,6 +116,7 @@ impl UserRepository {
             last_login_time: row.last_login_time,
             user_idx: row.user_idx,
         };
+        self.sync_id_uuid(&user.id, "register_user").await;
         debug!("用户注册成功: {}", user.id);
         Ok(user)
     }
@@ -112,7 +147,7 @@ impl UserRepository {
             data.username,
             data.phone
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             error!("修改密码失败: {}", err);
@@ -179,7 +214,7 @@ impl UserRepository {
             data.nickname,
             data.avatar_url
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             error!("创建用户失败: {}", err);
@@ -206,6 +241,11 @@ impl UserRepository {
             user_idx: row.user_idx,
         };
 
+        if let Some(nickname) = &user.nickname {
+            self.update_nickname_pinyin(&user.id, nickname).await?;
+        }
+
+        self.sync_id_uuid(&user.id, "create_user").await;
         debug!("用户创建成功: {}", user.id);
         Ok(user)
     }
@@ -215,6 +255,17 @@ impl UserRepository {
         let uuid = Uuid::parse_str(id)
             .map_err(|_| Error::BadRequest(format!("无效的用户ID格式: {}", id)))?;
 
+        self.migration
+            .read(
+                "get_user_by_id",
+                self.get_user_by_id_legacy(uuid, id),
+                self.get_user_by_id_uuid(uuid, id),
+            )
+            .await
+    }
+
+    /// 按旧的字符串ID列查询用户
+    async fn get_user_by_id_legacy(&self, uuid: Uuid, id: &str) -> Result<User> {
         let row = sqlx::query!(
             r#"
             SELECT id, username, email, password, nickname, avatar_url, created_at, updated_at,
@@ -225,7 +276,7 @@ impl UserRepository {
             "#,
             uuid.to_string()
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             if let sqlx::Error::RowNotFound = err {
@@ -259,6 +310,31 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// 按新的UUID列查询用户，列类型与旧查询一致
+    async fn get_user_by_id_uuid(&self, uuid: Uuid, id: &str) -> Result<User> {
+        sqlx::query_as::<_, User>(
+            r#"
+            SELECT id, COALESCE(username, '') AS username, email, password, nickname, avatar_url,
+            created_at, updated_at, COALESCE(phone, '') AS phone, address, head_image,
+            head_image_thumb, sex::INT4 AS sex, COALESCE(user_stat, 0)::INT4 AS user_stat,
+            COALESCE(tenant_id, '') AS tenant_id, last_login_time, user_idx
+            FROM users
+            WHERE id_uuid = $1
+            "#,
+        )
+        .bind(uuid)
+        .fetch_one(self.db.pool())
+        .await
+        .map_err(|err| {
+            if let sqlx::Error::RowNotFound = err {
+                Error::NotFound(format!("用户ID {} 不存在", id))
+            } else {
+                error!("按UUID列查询用户失败: {}", err);
+                Error::Database(err)
+            }
+        })
+    }
+
     /// 根据用户名查询用户
     pub async fn get_user_by_username(&self, username: &str) -> Result<User> {
         let row = sqlx::query!(
@@ -271,7 +347,7 @@ impl UserRepository {
             "#,
             username
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             if let sqlx::Error::RowNotFound = err {
@@ -317,7 +393,7 @@ impl UserRepository {
             "#,
             email
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             if let sqlx::Error::RowNotFound = err {
@@ -363,7 +439,7 @@ impl UserRepository {
             "#,
             phone
         )
-        .fetch_one(&self.pool)
+        .fetch_one(self.db.pool())
         .await
         .map_err(|err| {
             if let sqlx::Error::RowNotFound = err {
@@ -395,6 +471,27 @@ impl UserRepository {
         Ok(user)
     }
 
+    /// 用户名是否已被使用，只做存在性检查，不读取用户数据
+    pub async fn username_exists(&self, username: &str) -> Result<bool> {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)")
+                .bind(username)
+                .fetch_one(self.db.pool())
+                .await?;
+
+        Ok(exists)
+    }
+
+    /// 手机号是否已被使用，只做存在性检查，不读取用户数据
+    pub async fn phone_exists(&self, phone: &str) -> Result<bool> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE phone = $1)")
+            .bind(phone)
+            .fetch_one(self.db.pool())
+            .await?;
+
+        Ok(exists)
+    }
+
     /// 更新用户信息
     pub async fn update_user(&self, id: &str, data: UpdateUserData) -> Result<User> {
         let uuid = Uuid::parse_str(id)
@@ -420,7 +517,8 @@ impl UserRepository {
         }
         if let Some(nickname) = data.nickname {
             if !first { builder.push(","); }
-            builder.push(" nickname = COALESCE( ").push_bind(nickname).push(", nickname) ");
+            builder.push(" nickname_pinyin = ").push_bind(nickname_pinyin(&nickname));
+            builder.push(", nickname = COALESCE( ").push_bind(nickname).push(", nickname) ");
             first = false;
         }
         if let Some(head_image) = data.head_image {
@@ -452,7 +550,7 @@ impl UserRepository {
         );
         // 生成最终SQL
         let query = builder.build_query_as::<User>();
-        let row = query.fetch_one(&self.pool).await?;
+        let row = query.fetch_one(self.db.pool()).await?;
 
         // 更新用户数据
         // let row = sqlx::query!(
@@ -475,7 +573,7 @@ impl UserRepository {
         //     password_hash.as_deref(),
         //     uuid.to_string()
         // )
-        // .fetch_one(&self.pool)
+        // .fetch_one(self.db.pool())
         // .await
         // .map_err(|err| {
         //     error!("更新用户失败: {}", err);
@@ -522,8 +620,14 @@ impl UserRepository {
     }
 
     /// 搜索用户
+    ///
+    /// - 用户名/邮箱、手机号只做精确匹配，且分别受被搜索者的 allow_id_search、allow_phone_search 控制
+    /// - 昵称支持模糊匹配和拼音（全拼、首字母）匹配
+    /// - 排除与搜索者互相拉黑的用户
+    /// - 按匹配程度排序：精确匹配 > 昵称完全一致 > 昵称前缀 > 拼音 > 相似度
     pub async fn search_users(
         &self,
+        viewer_id: &str,
         query: &str,
         page: i32,
         page_size: i32,
@@ -531,71 +635,235 @@ impl UserRepository {
         // 计算分页
         let offset = (page - 1) * page_size;
 
-        // 构造搜索条件
-        let search_pattern = format!("%{}%", query);
+        // 构造搜索条件，转义LIKE通配符
+        let keyword = escape_like(&query.to_lowercase());
+        let contains_pattern = format!("%{}%", keyword);
+        let prefix_pattern = format!("{}%", keyword);
 
-        // 查询符合条件的用户
-        let rows = sqlx::query!(
+        let rows = sqlx::query(
             r#"
             SELECT id, username, email, password, nickname, avatar_url, created_at, updated_at,
-            phone, address, head_image, head_image_thumb, sex, user_stat, tenant_id, last_login_time,
-            user_idx
-            FROM users
-            WHERE username ILIKE $1 OR email ILIKE $1 OR COALESCE(nickname, '') ILIKE $1
-            ORDER BY username
-            LIMIT $2 OFFSET $3
+            phone, address, head_image, head_image_thumb, sex, user_stat, tenant_id,
+            last_login_time, user_idx, COUNT(*) OVER() AS total
+            FROM (
+                SELECT u.id, COALESCE(u.username, '') AS username, u.email, u.password, u.nickname,
+                u.avatar_url, u.created_at, u.updated_at, COALESCE(u.phone, '') AS phone, u.address,
+                u.head_image, u.head_image_thumb, u.sex::int4 AS sex,
+                COALESCE(u.user_stat, 0)::int4 AS user_stat, COALESCE(u.tenant_id, '') AS tenant_id,
+                u.last_login_time, u.user_idx,
+                (CASE WHEN COALESCE(uc.allow_id_search, TRUE)
+                        AND (lower(u.username) = lower($1) OR lower(u.email) = lower($1)) THEN 100
+                      WHEN COALESCE(uc.allow_phone_search, TRUE) AND u.phone = $1 THEN 100
+                      WHEN lower(u.nickname) = lower($1) THEN 80
+                      WHEN u.nickname ILIKE $3 THEN 60
+                      WHEN u.nickname_pinyin ILIKE $3 OR u.nickname_pinyin ILIKE $4 THEN 40
+                      ELSE 0 END
+                 + GREATEST(similarity(COALESCE(u.nickname, ''), $1),
+                            similarity(COALESCE(u.nickname_pinyin, ''), lower($1))) * 20) AS score
+                FROM users u
+                LEFT JOIN user_config uc ON uc.user_id = u.id
+                WHERE (
+                    (COALESCE(uc.allow_id_search, TRUE)
+                        AND (lower(u.username) = lower($1) OR lower(u.email) = lower($1)))
+                    OR (COALESCE(uc.allow_phone_search, TRUE) AND u.phone = $1)
+                    OR u.nickname ILIKE $2
+                    OR u.nickname_pinyin ILIKE $2
+                )
+                AND NOT EXISTS (
+                    SELECT 1 FROM friend_relation fr
+                    WHERE fr.status = 2
+                    AND ((fr.user_id = $5 AND fr.friend_id = u.id)
+                        OR (fr.user_id = u.id AND fr.friend_id = $5))
+                )
+            ) matched
+            ORDER BY score DESC, username
+            LIMIT $6 OFFSET $7
             "#,
-            search_pattern,
-            page_size as i64,
-            offset as i64
         )
-        .fetch_all(&self.pool)
+        .bind(query)
+        .bind(&contains_pattern)
+        .bind(&prefix_pattern)
+        .bind(format!("% {}", prefix_pattern))
+        .bind(viewer_id)
+        .bind(page_size as i64)
+        .bind(offset as i64)
+        .fetch_all(self.db.pool())
         .await
         .map_err(|err| {
             error!("搜索用户失败: {}", err);
             Error::Database(err)
         })?;
 
+        let total: i64 = rows.first().map(|row| row.get("total")).unwrap_or_default();
         let users = rows
-            .into_iter()
-            .map(|row| User {
-                id: row.id,
-                username: row.username.unwrap_or_default(),
-                email: row.email,
-                password: row.password,
-                nickname: row.nickname,
-                avatar_url: row.avatar_url,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-                phone: row.phone.unwrap_or_default(),
-                address: row.address,
-                head_image: row.head_image,
-                head_image_thumb: row.head_image_thumb,
-                sex: row.sex.map(|x| x as i32),
-                user_stat: row.user_stat.unwrap_or_default() as i32,
-                tenant_id: row.tenant_id.unwrap_or_default(),
-                last_login_time: row.last_login_time,
-                user_idx: row.user_idx
-            })
-            .collect();
+            .iter()
+            .map(|row| User::from_row(row).map_err(Error::Database))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok((users, total as i32))
+    }
 
-        // 查询总数
-        let total: i64 = sqlx::query(
+    /// 补全历史用户的昵称拼音，返回本批处理的数量
+    pub async fn backfill_nickname_pinyin(&self, batch_size: i64) -> Result<usize> {
+        let rows = sqlx::query(
             r#"
-            SELECT COUNT(*) as total
+            SELECT id, nickname FROM users
+            WHERE nickname IS NOT NULL AND nickname_pinyin IS NULL
+            LIMIT $1
+            "#,
+        )
+        .bind(batch_size)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询待补全拼音的用户失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        for row in &rows {
+            let id: String = row.get("id");
+            let nickname: String = row.get("nickname");
+            self.update_nickname_pinyin(&id, &nickname).await?;
+        }
+        Ok(rows.len())
+    }
+
+    /// 更新昵称拼音检索键
+    async fn update_nickname_pinyin(&self, id: &str, nickname: &str) -> Result<()> {
+        sqlx::query("UPDATE users SET nickname_pinyin = $2 WHERE id = $1")
+            .bind(id)
+            .bind(nickname_pinyin(nickname))
+            .execute(self.db.pool())
+            .await
+            .map_err(|err| {
+                error!("更新昵称拼音失败: {}", err);
+                Error::Database(err)
+            })?;
+        Ok(())
+    }
+
+    /// 批量获取用户
+    ///
+    /// 返回用户及其最后活跃时间，不存在的ID会被忽略
+    pub async fn get_users_by_ids(
+        &self,
+        user_ids: &[String],
+    ) -> Result<Vec<(User, Option<DateTime<Utc>>)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, COALESCE(username, '') AS username, email, password, nickname, avatar_url,
+            created_at, updated_at, COALESCE(phone, '') AS phone, address, head_image,
+            head_image_thumb, sex::int4 AS sex, COALESCE(user_stat, 0)::int4 AS user_stat,
+            COALESCE(tenant_id, '') AS tenant_id, last_login_time, user_idx, last_active_at
             FROM users
-            WHERE username ILIKE $1 OR email ILIKE $1 OR COALESCE(nickname, '') ILIKE $1
+            WHERE id = ANY($1)
             "#,
         )
-        .bind(&search_pattern)
-        .fetch_one(&self.pool)
+        .bind(user_ids)
+        .fetch_all(self.db.pool())
         .await
         .map_err(|err| {
-            error!("查询用户总数失败: {}", err);
+            error!("批量获取用户失败: {}", err);
             Error::Database(err)
-        })?
-        .get("total");
+        })?;
 
-        Ok((users, total as i32))
+        rows.iter()
+            .map(|row| {
+                let user = User::from_row(row).map_err(Error::Database)?;
+                let last_active_at = row.try_get("last_active_at").map_err(Error::Database)?;
+                Ok((user, last_active_at))
+            })
+            .collect()
+    }
+
+    /// 在给定用户中筛选出把查看者加为好友的用户
+    pub async fn get_friends_of_viewer(
+        &self,
+        viewer_id: &str,
+        user_ids: &[String],
+    ) -> Result<HashSet<String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT user_id
+            FROM friend_relation
+            WHERE friend_id = $1 AND user_id = ANY($2) AND status = 1
+            "#,
+        )
+        .bind(viewer_id)
+        .bind(user_ids)
+        .fetch_all(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询好友关系失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(rows.iter().map(|row| row.get("user_id")).collect())
     }
+
+    /// 查询用户对查看者的好友关系（是否为好友、是否拉黑了查看者）
+    pub async fn get_viewer_relation(
+        &self,
+        viewer_id: &str,
+        user_id: &str,
+    ) -> Result<ViewerRelation> {
+        let status: Option<i32> = sqlx::query_scalar(
+            r#"
+            SELECT status::int4
+            FROM friend_relation
+            WHERE user_id = $1 AND friend_id = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(viewer_id)
+        .fetch_optional(self.db.pool())
+        .await
+        .map_err(|err| {
+            error!("查询好友关系失败: {}", err);
+            Error::Database(err)
+        })?;
+
+        Ok(ViewerRelation {
+            is_friend: status == Some(1),
+            blocked: status == Some(2),
+        })
+    }
+
+    /// 批量更新用户最后活跃时间
+    ///
+    /// 只会把时间往后推，乱序到达的旧时间戳不会覆盖新值
+    pub async fn update_last_active_batch(&self, entries: &[(String, i64)]) -> Result<u64> {
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        let mut builder = QueryBuilder::new(
+            "UPDATE users SET last_active_at = v.active_at FROM (",
+        );
+        builder.push_values(entries, |mut b, (user_id, timestamp)| {
+            b.push_bind(user_id)
+                .push("to_timestamp(")
+                .push_bind_unseparated(*timestamp as f64)
+                .push_unseparated(")");
+        });
+        builder.push(
+            ") AS v(id, active_at) \
+             WHERE users.id = v.id \
+             AND (users.last_active_at IS NULL OR users.last_active_at < v.active_at)",
+        );
+
+        let result = builder.build().execute(self.db.pool()).await.map_err(|err| {
+            error!("批量更新用户最后活跃时间失败: {}", err);
+            Error::Database(err)
+        })?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// 转义LIKE模式中的通配符
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use chrono::{Days, Utc};
+use common::config::AccessLogConfig;
+use tracing::{error, info};
+
+use crate::repository::access_log_repository::AccessLogRepository;
+
+/// 分区维护间隔
+const MAINTAIN_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 访问日志分区维护任务
+///
+/// 启动时和每小时提前创建未来几天的分区，并整体删除超过保留天数的分区；
+/// 删除分区不产生大量死元组，比按行删除代价小得多
+pub struct AccessLogRetention {
+    repository: AccessLogRepository,
+    config: AccessLogConfig,
+}
+
+impl AccessLogRetention {
+    pub fn new(repository: AccessLogRepository, config: AccessLogConfig) -> Self {
+        Self { repository, config }
+    }
+
+    // 启动后台维护任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MAINTAIN_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.maintain().await {
+                    error!("维护访问日志分区失败: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn maintain(&self) -> common::Result<()> {
+        let today = Utc::now().date_naive();
+
+        // 网关上报有延迟，当天的分区同样需要保证存在
+        for offset in 0..=self.config.precreate_days.max(1) {
+            self.repository
+                .create_partition(today + Days::new(offset as u64))
+                .await?;
+        }
+
+        let cutoff = today - Days::new(self.config.retention_days.max(1) as u64);
+        for day in self.repository.list_partitions().await? {
+            if day >= cutoff {
+                break;
+            }
+            self.repository.drop_partition(day).await?;
+            info!("已删除过期的访问日志分区: {}", day);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use crate::model::access_log::{
+    truncate_chars, AccessLog, AccessLogFilter, MAX_PATH_CHARS, MAX_USER_AGENT_CHARS,
+};
+use crate::repository::access_log_repository::AccessLogRepository;
+use crate::repository::retention_repository::RetentionRepository;
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use common::config::AccessLogConfig;
+use common::grpc::subject::{check_subject, SUBJECT_METADATA_KEY};
+use common::grpc::tenant::with_tenant;
+use common::proto::access_log::{
+    access_log_service_server::AccessLogService, AccessLog as ProtoAccessLog,
+    ListAccessLogsRequest, ListAccessLogsResponse, RecordAccessLogsRequest,
+    RecordAccessLogsResponse,
+};
+use common::{Error, Result};
+use prost_types::Timestamp;
+use tonic::{Request, Response, Status};
+use tracing::debug;
+
+/// 访问日志默认每页条数
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// 访问日志每页最大条数
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// 单次写入的最大条数
+const MAX_RECORD_BATCH: usize = 5000;
+
+/// 用户级API访问日志服务实现
+pub struct AccessLogServiceImpl {
+    repository: AccessLogRepository,
+    tenants: RetentionRepository,
+    config: AccessLogConfig,
+}
+
+impl AccessLogServiceImpl {
+    pub fn new(
+        repository: AccessLogRepository,
+        tenants: RetentionRepository,
+        config: AccessLogConfig,
+    ) -> Self {
+        Self {
+            repository,
+            tenants,
+            config,
+        }
+    }
+
+    /// 只有租户管理员可以查询本租户的访问日志
+    async fn check_admin(&self, tenant_id: &str, operator_id: &str) -> Result<()> {
+        if tenant_id.is_empty() || operator_id.is_empty() {
+            return Err(Error::BadRequest("租户ID和操作人不能为空".to_string()));
+        }
+        if !self.tenants.is_tenant_admin(tenant_id, operator_id).await? {
+            return Err(Error::Authorization(format!(
+                "用户 {} 不是租户 {} 的管理员",
+                operator_id, tenant_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// 校验查询条件，时间范围必须指定且不超过配置的天数
+    fn build_filter(&self, req: ListAccessLogsRequest) -> Result<AccessLogFilter> {
+        let (Some(from), Some(to)) = (
+            req.from.as_ref().and_then(to_datetime),
+            req.to.as_ref().and_then(to_datetime),
+        ) else {
+            return Err(Error::BadRequest("必须指定查询的时间范围".to_string()));
+        };
+        if to <= from || to - from > chrono::Duration::days(self.config.max_query_days) {
+            return Err(Error::BadRequest(format!(
+                "查询范围应在{}天以内",
+                self.config.max_query_days
+            )));
+        }
+
+        let non_empty = |value: String| (!value.is_empty()).then_some(value);
+        Ok(AccessLogFilter {
+            tenant_id: req.tenant_id,
+            user_id: non_empty(req.user_id),
+            from,
+            to,
+            path_prefix: non_empty(req.path_prefix),
+            status: (req.status != 0).then_some(req.status as i16),
+            ip: non_empty(req.ip),
+        })
+    }
+}
+
+fn to_datetime(timestamp: &Timestamp) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp.seconds, timestamp.nanos.max(0) as u32)
+}
+
+impl From<ProtoAccessLog> for AccessLog {
+    fn from(log: ProtoAccessLog) -> Self {
+        Self {
+            id: 0,
+            user_id: log.user_id,
+            tenant_id: log.tenant_id,
+            method: log.method,
+            path: truncate_chars(&log.path, MAX_PATH_CHARS),
+            status: log.status as i16,
+            latency_ms: log.latency_ms,
+            ip: log.ip,
+            user_agent: truncate_chars(&log.user_agent, MAX_USER_AGENT_CHARS),
+            request_id: log.request_id,
+            accessed_at: log
+                .accessed_at
+                .as_ref()
+                .and_then(to_datetime)
+                .unwrap_or_else(Utc::now),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AccessLogService for AccessLogServiceImpl {
+    /// 批量写入网关上报的访问日志
+    async fn record_access_logs(
+        &self,
+        request: Request<RecordAccessLogsRequest>,
+    ) -> std::result::Result<Response<RecordAccessLogsResponse>, Status> {
+        // 网关代用户转发的请求会携带认证用户，访问日志只接受网关自身的上报
+        if request.metadata().contains_key(SUBJECT_METADATA_KEY) {
+            return Err(Status::permission_denied("访问日志只能由网关写入"));
+        }
+        let req = request.into_inner();
+        if req.logs.len() > MAX_RECORD_BATCH {
+            return Err(
+                Error::BadRequest(format!("单次最多写入 {} 条访问日志", MAX_RECORD_BATCH)).into(),
+            );
+        }
+
+        let logs: Vec<AccessLog> = req
+            .logs
+            .into_iter()
+            .filter(|log| !log.user_id.is_empty())
+            .map(Into::into)
+            .collect();
+        // 网关的批量上报混有多个租户的日志，按租户分别写入各自的数据库集群
+        let mut by_tenant: HashMap<String, Vec<AccessLog>> = HashMap::new();
+        for log in logs {
+            by_tenant.entry(log.tenant_id.clone()).or_default().push(log);
+        }
+        let mut recorded = 0;
+        for (tenant_id, logs) in by_tenant {
+            recorded += with_tenant(Some(tenant_id), self.repository.insert_batch(&logs)).await?;
+        }
+        debug!("写入访问日志 {} 条", recorded);
+
+        Ok(Response::new(RecordAccessLogsResponse {
+            recorded: recorded as i64,
+        }))
+    }
+
+    /// 查询租户内的访问日志
+    async fn list_access_logs(
+        &self,
+        request: Request<ListAccessLogsRequest>,
+    ) -> std::result::Result<Response<ListAccessLogsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+
+        let page = req.page.max(1);
+        let page_size = if req.page_size <= 0 {
+            DEFAULT_PAGE_SIZE
+        } else {
+            req.page_size.min(MAX_PAGE_SIZE)
+        };
+        let filter = self.build_filter(req)?;
+
+        let (logs, total) = self
+            .repository
+            .list(&filter, page_size, (page - 1) * page_size)
+            .await?;
+
+        Ok(Response::new(ListAccessLogsResponse {
+            logs: logs.into_iter().map(Into::into).collect(),
+            total,
+        }))
+    }
+}
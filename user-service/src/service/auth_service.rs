@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use cache::Cache;
+use chrono::Utc;
+use common::proto::auth::{
+    auth_service_server::AuthService, IntrospectTokenRequest, IntrospectTokenResponse,
+    RevokeTokenRequest, RevokeTokenResponse,
+};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+
+use crate::model::token::{token_id, TokenClaims};
+
+/// 吊销用户全部令牌时记录的保留时间，需覆盖刷新令牌的最长有效期
+const REVOKE_ALL_TTL: i64 = 30 * 24 * 3600;
+
+/// 令牌服务实现
+pub struct AuthServiceImpl {
+    decoding_key: DecodingKey,
+    cache: Arc<dyn Cache>,
+}
+
+impl AuthServiceImpl {
+    /// `secret` 需与网关签发令牌使用的密钥一致
+    pub fn new(secret: &str, cache: Arc<dyn Cache>) -> Self {
+        Self {
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            cache,
+        }
+    }
+
+    /// 校验签名和过期时间
+    fn decode(&self, token: &str) -> Result<TokenClaims, &'static str> {
+        decode::<TokenClaims>(token, &self.decoding_key, &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => "expired",
+                _ => "invalid",
+            })
+    }
+
+    fn inactive(reason: &str) -> IntrospectTokenResponse {
+        IntrospectTokenResponse {
+            active: false,
+            reason: reason.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AuthService for AuthServiceImpl {
+    /// 令牌自省
+    async fn introspect_token(
+        &self,
+        request: Request<IntrospectTokenRequest>,
+    ) -> Result<Response<IntrospectTokenResponse>, Status> {
+        let req = request.into_inner();
+
+        let claims = match self.decode(&req.token) {
+            Ok(claims) => claims,
+            Err(reason) => {
+                debug!("令牌校验失败: {}", reason);
+                return Ok(Response::new(Self::inactive(reason)));
+            }
+        };
+
+        let revoked = self
+            .cache
+            .is_token_revoked(token_id(&req.token), &claims.sub, claims.iat as i64)
+            .await
+            .map_err(|e| {
+                error!("查询令牌吊销状态失败: {}", e);
+                Status::unavailable("令牌吊销状态不可用")
+            })?;
+        if revoked {
+            debug!("令牌已被吊销，用户ID: {}", claims.sub);
+            return Ok(Response::new(Self::inactive("revoked")));
+        }
+
+        Ok(Response::new(IntrospectTokenResponse {
+            active: true,
+            user_id: claims.sub,
+            username: claims.username,
+            tenant_id: claims.tenant_id,
+            tenant_name: claims.tenant_name,
+            issued_at: claims.iat as i64,
+            expires_at: claims.exp as i64,
+            extra: claims.extra,
+            reason: String::new(),
+        }))
+    }
+
+    /// 吊销令牌
+    async fn revoke_token(
+        &self,
+        request: Request<RevokeTokenRequest>,
+    ) -> Result<Response<RevokeTokenResponse>, Status> {
+        let req = request.into_inner();
+
+        // 无效或已过期的令牌无需吊销
+        let claims = match self.decode(&req.token) {
+            Ok(claims) => claims,
+            Err(_) => return Ok(Response::new(RevokeTokenResponse { revoked: false })),
+        };
+
+        let now = Utc::now().timestamp();
+        let result = if req.all_sessions {
+            self.cache
+                .revoke_user_tokens(&claims.sub, now, REVOKE_ALL_TTL)
+                .await
+        } else {
+            self.cache
+                .revoke_token(token_id(&req.token), claims.exp as i64 - now)
+                .await
+        };
+
+        if let Err(e) = result {
+            error!("吊销令牌失败: {}", e);
+            return Err(Status::internal("吊销令牌失败"));
+        }
+
+        info!(
+            "已吊销用户 {} 的{}",
+            claims.sub,
+            if req.all_sessions { "所有令牌" } else { "令牌" }
+        );
+        Ok(Response::new(RevokeTokenResponse { revoked: true }))
+    }
+}
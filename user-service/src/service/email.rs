@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::warn;
+
+use common::config::EmailConfig;
+use common::{Error, Result};
+
+/// 邮件发送
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    /// 向邮箱发送纯文本邮件
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<()>;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    body: &'a str,
+}
+
+/// 通过HTTP调用邮件网关
+///
+/// 请求: `POST {endpoint}`，body 为 `{"from","to","subject","body"}`，返回2xx即视为发送成功
+pub struct HttpEmailSender {
+    client: reqwest::Client,
+    endpoint: String,
+    from: String,
+}
+
+impl HttpEmailSender {
+    pub fn new(config: &EmailConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("创建邮件服务HTTP客户端失败");
+        Self {
+            client,
+            endpoint: config.endpoint.clone(),
+            from: config.from.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl EmailSender for HttpEmailSender {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<()> {
+        self.client
+            .post(&self.endpoint)
+            .json(&EmailRequest {
+                from: &self.from,
+                to,
+                subject,
+                body,
+            })
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| Error::Internal(format!("调用邮件服务失败: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// 未配置邮件服务时使用，只记录日志
+pub struct LogEmailSender;
+
+#[async_trait]
+impl EmailSender for LogEmailSender {
+    async fn send(&self, to: &str, subject: &str, _body: &str) -> Result<()> {
+        warn!("邮件服务未配置，未向 {} 发送邮件，主题: {}", to, subject);
+        Ok(())
+    }
+}
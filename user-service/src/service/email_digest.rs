@@ -0,0 +1,419 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use common::config::EmailDigestConfig;
+use common::proto::user::EmailDigest;
+use common::{Error, Result};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use tracing::{error, info, warn};
+
+use crate::model::email_digest::{DigestCandidate, UnsubscribeClaims};
+use crate::repository::email_digest_repository::DigestStore;
+use crate::service::email::EmailSender;
+
+/// 退订令牌的用途
+const UNSUBSCRIBE_PURPOSE: &str = "email_digest_unsubscribe";
+
+/// 退订令牌的有效天数，覆盖用户翻看旧邮件的时间
+const UNSUBSCRIBE_TOKEN_DAYS: i64 = 90;
+
+/// 离线摘要邮件发送任务
+///
+/// 定期查询离线超过阈值、在离线期间收到未读私聊消息或好友请求的用户，按用户选择的频率发送摘要邮件。
+/// 发送前先记录发送时间占用本周期，多个服务实例同时扫描时同一用户只会收到一封
+pub struct EmailDigestSender {
+    repository: Arc<dyn DigestStore>,
+    email: Arc<dyn EmailSender>,
+    config: EmailDigestConfig,
+}
+
+impl EmailDigestSender {
+    pub fn new(
+        repository: Arc<dyn DigestStore>,
+        email: Arc<dyn EmailSender>,
+        config: EmailDigestConfig,
+    ) -> Self {
+        Self {
+            repository,
+            email,
+            config,
+        }
+    }
+
+    // 启动后台发送任务，未启用或未配置退订密钥时不发送
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            if !self.config.enabled {
+                return;
+            }
+            if self.config.unsubscribe_secret.is_empty() {
+                warn!("未配置摘要邮件的退订密钥，不发送离线摘要邮件");
+                return;
+            }
+            let mut interval =
+                tokio::time::interval(Duration::from_secs(self.config.scan_interval_secs.max(1)));
+            loop {
+                interval.tick().await;
+                for frequency in [EmailDigest::Hourly, EmailDigest::Daily] {
+                    if let Err(e) = self.send_digests(frequency).await {
+                        error!("发送离线摘要邮件失败: {}", e);
+                    }
+                }
+            }
+        })
+    }
+
+    // 向选择了该频率的用户发送本周期的摘要
+    async fn send_digests(&self, frequency: EmailDigest) -> Result<()> {
+        let period = match frequency {
+            EmailDigest::Hourly => chrono::Duration::hours(1),
+            EmailDigest::Daily => chrono::Duration::days(1),
+            EmailDigest::Off => return Ok(()),
+        };
+        let now = Utc::now().naive_utc();
+        let offline_before = now - chrono::Duration::hours(self.config.offline_threshold_hours);
+        let sent_before = now - period;
+
+        let mut cursor = String::new();
+        let mut sent = 0;
+        loop {
+            let candidates = self
+                .repository
+                .list_candidates(
+                    frequency as i16,
+                    offline_before,
+                    sent_before,
+                    &cursor,
+                    self.config.batch_size,
+                )
+                .await?;
+            let Some(last) = candidates.last() else {
+                break;
+            };
+            cursor = last.user_id.clone();
+
+            for candidate in &candidates {
+                if !self
+                    .repository
+                    .claim(&candidate.user_id, now, sent_before)
+                    .await?
+                {
+                    continue;
+                }
+                match self.send(candidate).await {
+                    Ok(()) => sent += 1,
+                    Err(e) => {
+                        warn!("向用户 {} 发送离线摘要失败: {}", candidate.user_id, e);
+                        self.repository
+                            .release(&candidate.user_id, candidate.last_sent_at)
+                            .await?;
+                    }
+                }
+            }
+            if (candidates.len() as i64) < self.config.batch_size {
+                break;
+            }
+        }
+
+        if sent > 0 {
+            info!(
+                "已发送 {} 封离线摘要邮件（{}）",
+                sent,
+                frequency.as_str_name()
+            );
+        }
+        Ok(())
+    }
+
+    async fn send(&self, candidate: &DigestCandidate) -> Result<()> {
+        let token = sign_unsubscribe_token(
+            &self.config.unsubscribe_secret,
+            &candidate.user_id,
+            &candidate.tenant_id,
+        )?;
+        let unsubscribe_link = format!("{}?token={}", self.config.unsubscribe_url, token);
+        let (subject, body) = render_digest(candidate, &unsubscribe_link);
+        self.email.send(&candidate.email, &subject, &body).await
+    }
+}
+
+/// 生成摘要邮件的主题和正文
+fn render_digest(candidate: &DigestCandidate, unsubscribe_link: &str) -> (String, String) {
+    let mut summary = Vec::new();
+    let mut lines = vec![format!("{}，你好：", candidate.name), String::new()];
+    if candidate.unread_messages > 0 {
+        summary.push(format!("{} 条未读消息", candidate.unread_messages));
+        lines.push(format!(
+            "你有来自 {} 位联系人的 {} 条未读消息。",
+            candidate.unread_senders, candidate.unread_messages
+        ));
+    }
+    if candidate.friend_requests > 0 {
+        summary.push(format!("{} 条好友请求", candidate.friend_requests));
+        lines.push(format!(
+            "你有 {} 条待处理的好友请求。",
+            candidate.friend_requests
+        ));
+    }
+    lines.push(String::new());
+    lines.push(format!(
+        "不想再收到此类邮件？点击退订：{}",
+        unsubscribe_link
+    ));
+
+    let subject = format!("你有 {} 待查看", summary.join("和"));
+    (subject, lines.join("\n"))
+}
+
+/// 签发摘要邮件的退订令牌
+pub fn sign_unsubscribe_token(secret: &str, user_id: &str, tenant_id: &str) -> Result<String> {
+    let claims = UnsubscribeClaims {
+        sub: user_id.to_string(),
+        tenant_id: tenant_id.to_string(),
+        purpose: UNSUBSCRIBE_PURPOSE.to_string(),
+        exp: (Utc::now() + chrono::Duration::days(UNSUBSCRIBE_TOKEN_DAYS)).timestamp() as u64,
+    };
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .map_err(|e| Error::Internal(format!("签发退订令牌失败: {}", e)))
+}
+
+/// 校验摘要邮件的退订令牌，返回令牌中的用户
+pub fn verify_unsubscribe_token(secret: &str, token: &str) -> Result<UnsubscribeClaims> {
+    if secret.is_empty() {
+        return Err(Error::BadRequest("退订链接无效".to_string()));
+    }
+    let claims = decode::<UnsubscribeClaims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map_err(|_| Error::BadRequest("退订链接无效或已过期".to_string()))?
+    .claims;
+    if claims.purpose != UNSUBSCRIBE_PURPOSE {
+        return Err(Error::BadRequest("退订链接无效".to_string()));
+    }
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use chrono::NaiveDateTime;
+
+    use super::*;
+
+    /// 内存中的发送记录，按SQL的语义筛选和占用
+    struct MemoryDigestStore {
+        candidates: Vec<DigestCandidate>,
+        sent: Mutex<HashMap<String, NaiveDateTime>>,
+        queries: Mutex<usize>,
+    }
+
+    impl MemoryDigestStore {
+        fn new(user_ids: &[&str]) -> Self {
+            Self {
+                candidates: user_ids.iter().copied().map(candidate).collect(),
+                sent: Mutex::new(HashMap::new()),
+                queries: Mutex::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl DigestStore for MemoryDigestStore {
+        async fn list_candidates(
+            &self,
+            _frequency: i16,
+            _offline_before: NaiveDateTime,
+            sent_before: NaiveDateTime,
+            after_user_id: &str,
+            limit: i64,
+        ) -> Result<Vec<DigestCandidate>> {
+            *self.queries.lock().unwrap() += 1;
+            let sent = self.sent.lock().unwrap();
+            Ok(self
+                .candidates
+                .iter()
+                .filter(|c| c.user_id.as_str() > after_user_id)
+                .filter(|c| !sent.get(&c.user_id).is_some_and(|at| *at >= sent_before))
+                .take(limit as usize)
+                .map(|c| DigestCandidate {
+                    last_sent_at: sent.get(&c.user_id).copied(),
+                    ..c.clone()
+                })
+                .collect())
+        }
+
+        async fn claim(
+            &self,
+            user_id: &str,
+            now: NaiveDateTime,
+            sent_before: NaiveDateTime,
+        ) -> Result<bool> {
+            let mut sent = self.sent.lock().unwrap();
+            if sent.get(user_id).is_some_and(|at| *at >= sent_before) {
+                return Ok(false);
+            }
+            sent.insert(user_id.to_string(), now);
+            Ok(true)
+        }
+
+        async fn release(&self, user_id: &str, last_sent_at: Option<NaiveDateTime>) -> Result<()> {
+            let mut sent = self.sent.lock().unwrap();
+            match last_sent_at {
+                Some(at) => sent.insert(user_id.to_string(), at),
+                None => sent.remove(user_id),
+            };
+            Ok(())
+        }
+    }
+
+    /// 记录收件人，向 `fail_to` 发送时返回错误
+    #[derive(Default)]
+    struct RecordingEmailSender {
+        sent_to: Mutex<Vec<String>>,
+        fail_to: Option<String>,
+    }
+
+    #[async_trait]
+    impl EmailSender for RecordingEmailSender {
+        async fn send(&self, to: &str, _subject: &str, _body: &str) -> Result<()> {
+            if self.fail_to.as_deref() == Some(to) {
+                return Err(Error::Internal("邮件服务不可用".to_string()));
+            }
+            self.sent_to.lock().unwrap().push(to.to_string());
+            Ok(())
+        }
+    }
+
+    fn candidate(user_id: &str) -> DigestCandidate {
+        DigestCandidate {
+            user_id: user_id.to_string(),
+            email: format!("{}@example.com", user_id),
+            name: user_id.to_string(),
+            tenant_id: String::new(),
+            last_active_at: Utc::now().naive_utc() - chrono::Duration::days(2),
+            last_sent_at: None,
+            unread_messages: 1,
+            unread_senders: 1,
+            friend_requests: 0,
+        }
+    }
+
+    fn digest_sender(
+        store: Arc<MemoryDigestStore>,
+        email: Arc<RecordingEmailSender>,
+    ) -> EmailDigestSender {
+        let config = EmailDigestConfig {
+            enabled: true,
+            batch_size: 2,
+            unsubscribe_secret: "secret".to_string(),
+            ..Default::default()
+        };
+        EmailDigestSender::new(store, email, config)
+    }
+
+    #[tokio::test]
+    async fn test_send_digests_scans_all_pages_and_claims_once() {
+        let store = Arc::new(MemoryDigestStore::new(&["user-1", "user-2", "user-3"]));
+        let first = Arc::new(RecordingEmailSender::default());
+        let second = Arc::new(RecordingEmailSender::default());
+
+        // 两个实例在同一周期内先后扫描，每个用户只收到一封
+        digest_sender(store.clone(), first.clone())
+            .send_digests(EmailDigest::Daily)
+            .await
+            .unwrap();
+        digest_sender(store.clone(), second.clone())
+            .send_digests(EmailDigest::Daily)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *first.sent_to.lock().unwrap(),
+            vec![
+                "user-1@example.com",
+                "user-2@example.com",
+                "user-3@example.com"
+            ]
+        );
+        assert!(second.sent_to.lock().unwrap().is_empty());
+        // 第一个实例按批次大小分两页查询，第二个实例查询不到待发送的用户
+        assert_eq!(*store.queries.lock().unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_digests_releases_claim_on_failure() {
+        let store = Arc::new(MemoryDigestStore::new(&["user-1", "user-2"]));
+        let failing = Arc::new(RecordingEmailSender {
+            fail_to: Some("user-2@example.com".to_string()),
+            ..Default::default()
+        });
+        digest_sender(store.clone(), failing.clone())
+            .send_digests(EmailDigest::Daily)
+            .await
+            .unwrap();
+        assert_eq!(*failing.sent_to.lock().unwrap(), vec!["user-1@example.com"]);
+        assert!(!store.sent.lock().unwrap().contains_key("user-2"));
+
+        // 发送失败的用户在下一轮扫描中重试
+        let retry = Arc::new(RecordingEmailSender::default());
+        digest_sender(store.clone(), retry.clone())
+            .send_digests(EmailDigest::Daily)
+            .await
+            .unwrap();
+        assert_eq!(*retry.sent_to.lock().unwrap(), vec!["user-2@example.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_send_digests_skips_off() {
+        let store = Arc::new(MemoryDigestStore::new(&["user-1"]));
+        let email = Arc::new(RecordingEmailSender::default());
+        digest_sender(store.clone(), email.clone())
+            .send_digests(EmailDigest::Off)
+            .await
+            .unwrap();
+        assert!(email.sent_to.lock().unwrap().is_empty());
+        assert_eq!(*store.queries.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_unsubscribe_token() {
+        let token = sign_unsubscribe_token("secret", "user-1", "tenant-1").unwrap();
+        let claims = verify_unsubscribe_token("secret", &token).unwrap();
+        assert_eq!(claims.sub, "user-1");
+        assert_eq!(claims.tenant_id, "tenant-1");
+
+        assert!(verify_unsubscribe_token("other", &token).is_err());
+        assert!(verify_unsubscribe_token("", &token).is_err());
+        assert!(verify_unsubscribe_token("secret", "invalid").is_err());
+    }
+
+    #[test]
+    fn test_render_digest() {
+        let candidate = DigestCandidate {
+            user_id: "user-1".to_string(),
+            email: "a@example.com".to_string(),
+            name: "小明".to_string(),
+            tenant_id: String::new(),
+            last_active_at: Utc::now().naive_utc(),
+            last_sent_at: None,
+            unread_messages: 0,
+            unread_senders: 0,
+            friend_requests: 2,
+        };
+        let (subject, body) = render_digest(&candidate, "http://localhost/unsubscribe?token=t");
+        assert_eq!(subject, "你有 2 条好友请求待查看");
+        assert!(!body.contains("未读消息。"));
+        assert!(body.contains("2 条待处理的好友请求"));
+        assert!(body.ends_with("http://localhost/unsubscribe?token=t"));
+    }
+}
@@ -0,0 +1,116 @@
+use crate::model::job::{CreateJobData, JOB_STATUS_SUCCEEDED};
+use crate::repository::job_repository::JobRepository;
+use chrono::Utc;
+use common::grpc::subject::check_subject;
+use common::proto::job::{
+    job_service_server::JobService, CreateJobRequest, GetJobRequest, Job as ProtoJob, JobResponse,
+};
+use common::Error;
+use oss::Oss;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::{Request, Response, Status};
+use tracing::{debug, error, info};
+
+/// 每个用户同一类型未结束任务的上限
+const MAX_ACTIVE_JOBS_PER_KIND: i64 = 1;
+
+/// 结果签名URL的有效期
+const RESULT_URL_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// 异步任务服务实现
+pub struct JobServiceImpl {
+    repository: JobRepository,
+    oss: Arc<dyn Oss>,
+    kinds: Vec<String>,
+}
+
+impl JobServiceImpl {
+    pub fn new(repository: JobRepository, oss: Arc<dyn Oss>, kinds: Vec<String>) -> Self {
+        Self {
+            repository,
+            oss,
+            kinds,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl JobService for JobServiceImpl {
+    /// 创建异步任务
+    async fn create_job(
+        &self,
+        request: Request<CreateJobRequest>,
+    ) -> std::result::Result<Response<JobResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let data = CreateJobData::from(request.into_inner());
+        debug!("创建任务请求，用户: {}, 类型: {}", data.user_id, data.kind);
+
+        if data.user_id.is_empty() {
+            return Err(Error::BadRequest("用户ID不能为空".to_string()).into());
+        }
+        if !self.kinds.contains(&data.kind) {
+            return Err(Error::BadRequest(format!("不支持的任务类型: {}", data.kind)).into());
+        }
+
+        // 同类任务未结束前不允许重复提交
+        let job = match self
+            .repository
+            .create_job_within_limit(data, MAX_ACTIVE_JOBS_PER_KIND)
+            .await
+        {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                return Err(
+                    Error::TooManyRequests("已有同类任务正在执行，请稍后再试".to_string()).into(),
+                );
+            }
+            Err(err) => {
+                error!("创建任务失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        info!("成功创建任务 {} ({})", job.id, job.kind);
+
+        Ok(Response::new(JobResponse {
+            job: Some(ProtoJob::from(job)),
+        }))
+    }
+
+    /// 查询任务状态
+    async fn get_job(
+        &self,
+        request: Request<GetJobRequest>,
+    ) -> std::result::Result<Response<JobResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        debug!("查询任务请求，任务ID: {}", req.job_id);
+
+        let job = self.repository.get_job(&req.job_id).await?;
+
+        // 只有任务创建者可以查看
+        if job.user_id != req.user_id {
+            return Err(Error::NotFound(format!("任务 {} 不存在", req.job_id)).into());
+        }
+
+        let result_key = job.result_key.clone();
+        let succeeded = job.status == JOB_STATUS_SUCCEEDED;
+        let mut proto_job = ProtoJob::from(job);
+
+        // 成功的任务返回有时效的签名下载地址
+        if let (true, Some(key)) = (succeeded, result_key) {
+            let url = self.oss.presigned_url(&key, RESULT_URL_TTL).await?;
+            let expires_at = Utc::now() + chrono::Duration::from_std(RESULT_URL_TTL).unwrap_or_default();
+            proto_job.result_url = Some(url);
+            proto_job.result_expires_at = Some(prost_types::Timestamp {
+                seconds: expires_at.timestamp(),
+                nanos: 0,
+            });
+        }
+
+        Ok(Response::new(JobResponse {
+            job: Some(proto_job),
+        }))
+    }
+}
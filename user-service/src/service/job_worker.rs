@@ -0,0 +1,150 @@
+use crate::model::job::Job;
+use crate::repository::job_repository::JobRepository;
+use crate::repository::user_repository::UserRepository;
+use async_trait::async_trait;
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use oss::Oss;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+/// 任务类型：导出个人资料
+pub const JOB_KIND_EXPORT_PROFILE: &str = "export_profile";
+
+/// 任务执行器
+///
+/// 每种任务类型注册一个执行器，执行结果以文件形式上传到对象存储
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    /// 执行任务，返回结果文件内容
+    async fn run(&self, job: &Job, progress: &JobProgress) -> Result<Vec<u8>>;
+}
+
+/// 任务进度上报
+pub struct JobProgress {
+    job_id: String,
+    repository: JobRepository,
+}
+
+impl JobProgress {
+    /// 上报进度（0-100）
+    pub async fn report(&self, progress: i32) {
+        if let Err(e) = self.repository.update_progress(&self.job_id, progress).await {
+            error!("更新任务进度失败 {}: {}", self.job_id, e);
+        }
+    }
+}
+
+/// 异步任务工作进程
+pub struct JobWorker {
+    repository: JobRepository,
+    oss: Arc<dyn Oss>,
+    handlers: HashMap<String, Arc<dyn JobHandler>>,
+    poll_interval: Duration,
+}
+
+impl JobWorker {
+    pub fn new(repository: JobRepository, oss: Arc<dyn Oss>) -> Self {
+        Self {
+            repository,
+            oss,
+            handlers: HashMap::new(),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+
+    /// 注册任务执行器
+    pub fn register(mut self, kind: &str, handler: Arc<dyn JobHandler>) -> Self {
+        self.handlers.insert(kind.to_string(), handler);
+        self
+    }
+
+    /// 已注册的任务类型
+    pub fn kinds(&self) -> Vec<String> {
+        self.handlers.keys().cloned().collect()
+    }
+
+    /// 启动工作循环
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        info!("异步任务工作进程启动，支持的任务类型: {:?}", self.kinds());
+        tokio::spawn(async move {
+            loop {
+                match self.repository.claim_next().await {
+                    Ok(Some(job)) => self.execute(job).await,
+                    Ok(None) => tokio::time::sleep(self.poll_interval).await,
+                    Err(e) => {
+                        error!("拉取待执行任务失败: {}", e);
+                        tokio::time::sleep(self.poll_interval).await;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn execute(&self, job: Job) {
+        debug!("开始执行任务 {} ({})", job.id, job.kind);
+
+        let handler = match self.handlers.get(&job.kind) {
+            Some(handler) => handler.clone(),
+            None => {
+                let _ = self.repository.mark_failed(&job.id, "不支持的任务类型").await;
+                return;
+            }
+        };
+
+        let progress = JobProgress {
+            job_id: job.id.clone(),
+            repository: self.repository.clone(),
+        };
+
+        let result = match handler.run(&job, &progress).await {
+            Ok(content) => {
+                let key = format!("jobs/{}/{}", job.user_id, job.id);
+                self.oss.upload_file(&key, content).await.map(|_| key)
+            }
+            Err(e) => Err(e),
+        };
+
+        let updated = match result {
+            Ok(key) => {
+                info!("任务 {} 执行完成", job.id);
+                self.repository.mark_succeeded(&job.id, &key).await
+            }
+            Err(e) => {
+                error!("任务 {} 执行失败: {}", job.id, e);
+                self.repository.mark_failed(&job.id, &e.to_string()).await
+            }
+        };
+
+        if let Err(e) = updated {
+            error!("更新任务状态失败 {}: {}", job.id, e);
+        }
+    }
+}
+
+/// 个人资料导出
+pub struct ProfileExportHandler {
+    repository: UserRepository,
+}
+
+impl ProfileExportHandler {
+    pub fn new(db: PgRouter) -> Self {
+        Self {
+            repository: UserRepository::new(db),
+        }
+    }
+}
+
+#[async_trait]
+impl JobHandler for ProfileExportHandler {
+    async fn run(&self, job: &Job, progress: &JobProgress) -> Result<Vec<u8>> {
+        let mut user = self.repository.get_user_by_id(&job.user_id).await?;
+        progress.report(50).await;
+
+        // 导出文件中不包含密码哈希
+        user.password = String::new();
+        serde_json::to_vec_pretty(&user).map_err(Error::from)
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use cache::Cache;
+use common::dependency_health::DependencyHealth;
+use tracing::{debug, error};
+
+use crate::repository::user_repository::UserRepository;
+
+/// 最后活跃时间落库间隔
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 单批落库的最大条数
+const FLUSH_BATCH_SIZE: usize = 500;
+
+/// 用户最后活跃时间落库任务
+///
+/// 网关心跳只写Redis，同一用户在一个周期内的多次心跳被合并为一次数据库更新。
+/// 心跳中没有租户信息，每批都更新到全部数据库集群，只有用户所在的集群有对应的行。
+/// Redis降级期间网关不上报心跳，落库任务也暂停
+pub struct LastActiveFlusher {
+    cache: Arc<dyn Cache>,
+    redis: DependencyHealth,
+    repositories: Vec<UserRepository>,
+}
+
+impl LastActiveFlusher {
+    pub fn new(
+        cache: Arc<dyn Cache>,
+        redis: DependencyHealth,
+        repositories: Vec<UserRepository>,
+    ) -> Self {
+        Self {
+            cache,
+            redis,
+            repositories,
+        }
+    }
+
+    // 启动后台落库任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                if self.redis.is_healthy() {
+                    self.flush().await;
+                }
+            }
+        })
+    }
+
+    async fn flush(&self) {
+        loop {
+            let entries = match self.cache.drain_user_active(FLUSH_BATCH_SIZE).await {
+                Ok(entries) => entries,
+                Err(e) => {
+                    error!("读取用户最后活跃时间失败: {}", e);
+                    return;
+                }
+            };
+
+            if entries.is_empty() {
+                return;
+            }
+
+            match self.update_all(&entries).await {
+                Ok(rows) => debug!("已落库 {} 条用户最后活跃时间", rows),
+                Err(e) => {
+                    error!("用户最后活跃时间落库失败: {}", e);
+                    // 放回Redis，下个周期重试；期间有新心跳的用户保留较新的时间
+                    for (user_id, timestamp) in &entries {
+                        if let Err(e) = self.cache.record_user_active(user_id, *timestamp).await {
+                            error!("回写用户最后活跃时间失败: {}", e);
+                        }
+                    }
+                    return;
+                }
+            }
+
+            if entries.len() < FLUSH_BATCH_SIZE {
+                return;
+            }
+        }
+    }
+
+    // 更新到全部集群，任一集群失败时整批重试，重复更新不会覆盖较新的时间
+    async fn update_all(&self, entries: &[(String, i64)]) -> common::Result<u64> {
+        let mut rows = 0;
+        for repository in &self.repositories {
+            rows += repository.update_last_active_batch(entries).await?;
+        }
+        Ok(rows)
+    }
+}
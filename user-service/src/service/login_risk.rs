@@ -0,0 +1,154 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::model::login_history::{LoginContext, LoginHistory};
+
+/// 达到该分值的登录需要二次验证
+pub const VERIFY_THRESHOLD: i32 = 50;
+
+/// 参与比对的历史可信登录条数
+pub const HISTORY_WINDOW: i64 = 50;
+
+/// 未见过的设备
+const SCORE_NEW_DEVICE: i32 = 40;
+/// 客户端未上报设备标识
+const SCORE_UNKNOWN_DEVICE: i32 = 20;
+/// 未见过的网段（粗粒度的异地判断）
+const SCORE_NEW_NETWORK: i32 = 30;
+/// 未见过的客户端类型
+const SCORE_NEW_CLIENT: i32 = 10;
+/// 短时间内从另一网段登录
+const SCORE_RAPID_NETWORK_CHANGE: i32 = 30;
+
+/// 短时间网段切换的判定窗口
+const RAPID_NETWORK_CHANGE_WINDOW: Duration = Duration::hours(1);
+
+/// 登录风险评估结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RiskAssessment {
+    pub score: i32,
+    pub reasons: Vec<&'static str>,
+}
+
+impl RiskAssessment {
+    /// 是否需要二次验证
+    pub fn require_verification(&self) -> bool {
+        self.score >= VERIFY_THRESHOLD
+    }
+
+    fn add(&mut self, score: i32, reason: &'static str) {
+        self.score += score;
+        self.reasons.push(reason);
+    }
+}
+
+/// IP所属网段，IPv4取/16，IPv6取/32
+///
+/// 没有接入地理位置库，用网段变化近似地域变化
+pub fn ip_network(ip: &str) -> String {
+    match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(v4)) => {
+            let o = v4.octets();
+            format!("{}.{}.0.0/16", o[0], o[1])
+        }
+        Ok(IpAddr::V6(v6)) => {
+            let s = v6.segments();
+            format!("{:x}:{:x}::/32", s[0], s[1])
+        }
+        Err(_) => String::new(),
+    }
+}
+
+/// 客户端类型，取UA的第一段（如 "Mozilla"、"okhttp"）
+fn client_family(user_agent: &str) -> &str {
+    user_agent.split('/').next().unwrap_or_default().trim()
+}
+
+/// 对比历史可信登录评估本次登录的风险
+///
+/// 没有任何历史记录时视为首次登录，直接作为基线
+pub fn assess(history: &[LoginHistory], ctx: &LoginContext, now: DateTime<Utc>) -> RiskAssessment {
+    let mut assessment = RiskAssessment::default();
+    if history.is_empty() {
+        return assessment;
+    }
+
+    if ctx.device_id.is_empty() {
+        assessment.add(SCORE_UNKNOWN_DEVICE, "unknown_device");
+    } else if !history.iter().any(|h| h.device_id == ctx.device_id) {
+        assessment.add(SCORE_NEW_DEVICE, "new_device");
+    }
+
+    let network = ip_network(&ctx.ip);
+    if !network.is_empty() {
+        if !history.iter().any(|h| h.ip_network == network) {
+            assessment.add(SCORE_NEW_NETWORK, "new_network");
+        }
+
+        // 历史按时间倒序，第一条即最近一次可信登录
+        if let Some(last) = history.first() {
+            if !last.ip_network.is_empty()
+                && last.ip_network != network
+                && now - last.created_at < RAPID_NETWORK_CHANGE_WINDOW
+            {
+                assessment.add(SCORE_RAPID_NETWORK_CHANGE, "rapid_network_change");
+            }
+        }
+    }
+
+    let family = client_family(&ctx.user_agent);
+    if !family.is_empty() && !history.iter().any(|h| client_family(&h.user_agent) == family) {
+        assessment.add(SCORE_NEW_CLIENT, "new_client");
+    }
+
+    assessment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(device_id: &str, ip: &str, minutes_ago: i64) -> LoginHistory {
+        LoginHistory {
+            id: "1".to_string(),
+            user_id: "u1".to_string(),
+            device_id: device_id.to_string(),
+            device_name: String::new(),
+            ip: ip.to_string(),
+            ip_network: ip_network(ip),
+            user_agent: "okhttp/4.9".to_string(),
+            risk_score: 0,
+            trusted: true,
+            created_at: Utc::now() - Duration::minutes(minutes_ago),
+        }
+    }
+
+    fn ctx(device_id: &str, ip: &str) -> LoginContext {
+        LoginContext {
+            device_id: device_id.to_string(),
+            device_name: String::new(),
+            ip: ip.to_string(),
+            user_agent: "okhttp/4.10".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_known_device_and_network_is_trusted() {
+        let history = vec![history("d1", "10.1.2.3", 600)];
+        let result = assess(&history, &ctx("d1", "10.1.9.9"), Utc::now());
+        assert_eq!(result.score, 0);
+        assert!(!result.require_verification());
+    }
+
+    #[test]
+    fn test_new_device_from_new_network_requires_verification() {
+        let history = vec![history("d1", "10.1.2.3", 10)];
+        let result = assess(&history, &ctx("d2", "172.16.0.1"), Utc::now());
+        assert_eq!(
+            result.reasons,
+            vec!["new_device", "new_network", "rapid_network_change"]
+        );
+        assert!(result.require_verification());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cache::Cache;
+use chrono::Utc;
+use common::grpc_client::ChatServiceGrpcClient;
+use common::message::SendMsgRequest;
+use common::{Error, Result};
+use rand::Rng;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::model::login_history::{LoginContext, LoginHistory};
+use crate::repository::login_history_repository::LoginHistoryRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::service::email::EmailSender;
+use crate::service::login_risk::{self, RiskAssessment, HISTORY_WINDOW};
+use crate::service::sms::SmsSender;
+
+/// 单个登录挑战允许的最大验证次数
+const MAX_CHALLENGE_ATTEMPTS: i64 = 5;
+
+/// 登录评估结果
+pub struct LoginEvaluation {
+    pub record: LoginHistory,
+    pub assessment: RiskAssessment,
+    /// 是否已发出验证码，需要二次验证
+    pub challenged: bool,
+}
+
+/// 登录安全检查
+///
+/// 登录成功后由网关调用：记录登录历史、评估风险，高风险登录通过短信或邮件发送验证码
+/// 进行二次验证，同时向该用户已在线的会话推送安全提醒。短信和邮件都未配置时无法送达
+/// 验证码，不发起二次验证
+#[derive(Clone)]
+pub struct LoginSecurity {
+    repository: LoginHistoryRepository,
+    users: UserRepository,
+    cache: Arc<dyn Cache>,
+    chat_client: ChatServiceGrpcClient,
+    sms: Option<(Arc<dyn SmsSender>, String)>,
+    email: Option<Arc<dyn EmailSender>>,
+}
+
+impl LoginSecurity {
+    pub fn new(
+        repository: LoginHistoryRepository,
+        users: UserRepository,
+        cache: Arc<dyn Cache>,
+        chat_client: ChatServiceGrpcClient,
+    ) -> Self {
+        Self {
+            repository,
+            users,
+            cache,
+            chat_client,
+            sms: None,
+            email: None,
+        }
+    }
+
+    /// 通过短信发送验证码，`template` 为短信模板ID
+    pub fn with_sms(mut self, sms: Arc<dyn SmsSender>, template: String) -> Self {
+        self.sms = Some((sms, template));
+        self
+    }
+
+    /// 用户没有手机号或未配置短信时通过邮件发送验证码
+    pub fn with_email(mut self, email: Arc<dyn EmailSender>) -> Self {
+        self.email = Some(email);
+        self
+    }
+
+    /// 评估本次登录并记录登录历史
+    pub async fn evaluate(&self, user_id: &str, ctx: &LoginContext) -> Result<LoginEvaluation> {
+        let history = self.repository.recent_trusted(user_id, HISTORY_WINDOW).await?;
+        let assessment = login_risk::assess(&history, ctx, Utc::now());
+
+        let record = self
+            .repository
+            .record(
+                user_id,
+                ctx,
+                &login_risk::ip_network(&ctx.ip),
+                assessment.score,
+                !assessment.require_verification(),
+            )
+            .await?;
+
+        if !assessment.require_verification() {
+            return Ok(LoginEvaluation {
+                record,
+                assessment,
+                challenged: false,
+            });
+        }
+
+        info!(
+            "用户 {} 登录存在风险，评分: {}，原因: {:?}",
+            user_id, assessment.score, assessment.reasons
+        );
+        self.notify_sessions(user_id, "login_attempt", ctx).await;
+
+        if self.sms.is_none() && self.email.is_none() {
+            warn!(
+                "验证码发送通道未配置，用户 {} 的登录不进行二次验证",
+                user_id
+            );
+            return Ok(LoginEvaluation {
+                record,
+                assessment,
+                challenged: false,
+            });
+        }
+
+        let code = format!("{:06}", rand::rng().random_range(0..1_000_000));
+        self.cache
+            .save_login_challenge(&record.id, user_id, &code)
+            .await?;
+        if let Err(err) = self.send_verification_code(user_id, &code).await {
+            // 验证码无法送达时不保留挑战，由调用方按评估失败处理
+            if let Err(e) = self.cache.del_login_challenge(&record.id).await {
+                warn!("删除登录挑战失败: {}", e);
+            }
+            return Err(err);
+        }
+
+        Ok(LoginEvaluation {
+            record,
+            assessment,
+            challenged: true,
+        })
+    }
+
+    /// 校验二次验证码，成功后返回用户ID
+    pub async fn verify_challenge(&self, login_id: &str, code: &str) -> Result<String> {
+        let challenge = self
+            .cache
+            .get_login_challenge(login_id)
+            .await?
+            .ok_or_else(|| Error::Authentication("验证码已过期，请重新登录".to_string()))?;
+
+        let attempts = self.cache.incr_login_challenge_attempts(login_id).await?;
+        if attempts > MAX_CHALLENGE_ATTEMPTS {
+            self.cache.del_login_challenge(login_id).await?;
+            return Err(Error::TooManyRequests("验证次数过多，请重新登录".to_string()));
+        }
+
+        if challenge.code != code {
+            return Err(Error::Authentication("验证码不正确".to_string()));
+        }
+
+        self.cache.del_login_challenge(login_id).await?;
+        self.repository.mark_trusted(login_id).await?;
+        info!("用户 {} 通过登录二次验证", challenge.user_id);
+
+        Ok(challenge.user_id)
+    }
+
+    /// 发送二次验证码，优先发短信，没有手机号或未配置短信时发邮件
+    async fn send_verification_code(&self, user_id: &str, code: &str) -> Result<()> {
+        let user = self.users.get_user_by_id(user_id).await?;
+
+        if let Some((sms, template)) = self.sms.as_ref().filter(|_| !user.phone.is_empty()) {
+            let params = HashMap::from([("code", code.to_string())]);
+            return sms.send(&user.phone, template, &params).await;
+        }
+
+        let email = user.email.as_deref().filter(|email| !email.is_empty());
+        if let (Some(sender), Some(to)) = (self.email.as_ref(), email) {
+            let body = format!("您的登录验证码为 {}。如非本人操作，请尽快修改密码。", code);
+            return sender.send(to, "登录验证码", &body).await;
+        }
+
+        Err(Error::Internal(format!(
+            "用户 {} 没有可接收验证码的手机号或邮箱",
+            user_id
+        )))
+    }
+
+    /// 向用户的在线会话推送安全提醒，失败只记录日志
+    async fn notify_sessions(&self, user_id: &str, event: &str, ctx: &LoginContext) {
+        let content = json!({
+            "type": "security",
+            "event": event,
+            "deviceName": ctx.device_name,
+            "ip": ctx.ip,
+            "time": Utc::now().timestamp_millis(),
+        })
+        .to_string()
+        .into_bytes();
+
+        let request = SendMsgRequest::new_with_notification(user_id.to_string(), content);
+        if let Err(e) = self.chat_client.send_msg(request).await {
+            warn!("推送登录安全提醒失败: {}", e);
+        }
+    }
+}
@@ -1 +1,22 @@
 pub mod user_service;
+pub mod job_service;
+pub mod job_worker;
+pub mod last_active;
+pub mod login_risk;
+pub mod login_security;
+pub mod pinyin_backfill;
+pub mod auth_service;
+pub mod retention_cleaner;
+pub mod retention_service;
+pub mod sms;
+pub mod email;
+pub mod email_digest;
+pub mod phone_invite;
+pub mod usage_export;
+pub mod usage_rollup;
+pub mod access_log_retention;
+pub mod access_log_service;
+pub mod profile_view;
+pub mod storage_service;
+pub mod upload_events;
+pub mod upload_reservation;
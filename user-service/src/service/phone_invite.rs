@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::config::InviteConfig;
+use common::grpc_client::ChatServiceGrpcClient;
+use common::message::SendMsgRequest;
+use common::{Error, Result};
+use tracing::{info, warn};
+
+use crate::model::invite::PhoneInvite;
+use crate::repository::invite_repository::InviteRepository;
+use crate::repository::user_repository::UserRepository;
+use crate::service::sms::SmsSender;
+
+/// 邀请短信模板未配置时使用的模板名
+const DEFAULT_INVITE_TEMPLATE: &str = "invite";
+
+/// 邀请消息的最大发送次数
+const DELIVERY_ATTEMPTS: u32 = 3;
+
+/// 邀请消息首次重试间隔，之后按指数退避
+const DELIVERY_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 手机号邀请
+///
+/// 发给未注册手机号的消息先保存为邀请并发送邀请短信，对方注册后自动以单聊消息投递；
+/// 手机号已注册时直接投递
+#[derive(Clone)]
+pub struct PhoneInvites {
+    repository: InviteRepository,
+    user_repository: UserRepository,
+    chat_client: ChatServiceGrpcClient,
+    sms: Arc<dyn SmsSender>,
+    config: InviteConfig,
+}
+
+impl PhoneInvites {
+    pub fn new(
+        repository: InviteRepository,
+        user_repository: UserRepository,
+        chat_client: ChatServiceGrpcClient,
+        sms: Arc<dyn SmsSender>,
+        config: InviteConfig,
+    ) -> Self {
+        Self {
+            repository,
+            user_repository,
+            chat_client,
+            sms,
+            config,
+        }
+    }
+
+    /// 向手机号发送邀请消息，返回邀请记录和是否已投递
+    pub async fn send(
+        &self,
+        sender_id: &str,
+        phone: &str,
+        content: &str,
+    ) -> Result<(PhoneInvite, bool)> {
+        let phone = normalize_phone(phone)
+            .ok_or_else(|| Error::BadRequest("手机号格式不正确".to_string()))?;
+        let content = content.trim();
+        if content.is_empty() {
+            return Err(Error::BadRequest("邀请内容不能为空".to_string()));
+        }
+        if content.chars().count() > self.config.max_content_len {
+            return Err(Error::BadRequest("邀请内容过长".to_string()));
+        }
+
+        let sender = self.user_repository.get_user_by_id(sender_id).await?;
+        if sender.phone == phone {
+            return Err(Error::BadRequest("不能邀请自己".to_string()));
+        }
+
+        let sent = self.repository.count_recent_by_sender(sender_id, 24).await?;
+        if sent >= self.config.max_per_sender_daily {
+            return Err(Error::TooManyRequests("今日邀请次数已达上限".to_string()));
+        }
+
+        let invite = self
+            .repository
+            .create(sender_id, &phone, content, self.config.expire_days)
+            .await?;
+
+        // 对方已注册则直接投递，否则发送邀请短信等待注册
+        match self.user_repository.get_user_by_phone(&phone).await {
+            Ok(receiver) => {
+                let delivered = self.deliver_pending(&receiver.id, &phone).await;
+                return Ok((invite, delivered > 0));
+            }
+            Err(Error::NotFound(_)) => {}
+            Err(err) => return Err(err),
+        }
+
+        let inviter = sender.nickname.unwrap_or(sender.username);
+        let params = HashMap::from([
+            ("inviter", inviter),
+            ("url", self.config.download_url.clone()),
+        ]);
+        let template = self
+            .config
+            .sms
+            .as_ref()
+            .map_or(DEFAULT_INVITE_TEMPLATE, |sms| sms.invite_template.as_str());
+        match self.sms.send(&phone, template, &params).await {
+            Ok(()) => self.repository.mark_sms_sent(&invite.id).await?,
+            // 短信发送失败不影响邀请保存，对方注册后仍会收到消息
+            Err(e) => warn!("发送邀请短信失败，邀请ID: {}，错误: {}", invite.id, e),
+        }
+        info!("用户 {} 向未注册手机号发送邀请，邀请ID: {}", sender_id, invite.id);
+
+        Ok((invite, false))
+    }
+
+    /// 投递手机号下待投递的邀请，返回投递成功的数量，失败只记录日志
+    ///
+    /// 投递成功后才标记为已投递，失败时按退避重试，仍然失败的邀请保持待投递，
+    /// 发送人再次邀请时重新投递；邀请消息以邀请ID作为客户端消息ID，重复投递时接收端可据此去重
+    pub async fn deliver_pending(&self, user_id: &str, phone: &str) -> usize {
+        let Some(phone) = normalize_phone(phone) else {
+            return 0;
+        };
+        let invites = match self.repository.pending(&phone).await {
+            Ok(invites) => invites,
+            Err(e) => {
+                warn!("领取用户 {} 的手机号邀请失败: {}", user_id, e);
+                return 0;
+            }
+        };
+
+        let mut delivered = 0;
+        for invite in invites {
+            let request = SendMsgRequest::new_with_invite(
+                invite.sender_id.clone(),
+                user_id.to_string(),
+                invite.id.clone(),
+                invite.content.into_bytes(),
+                invite.created_at.timestamp_millis(),
+            );
+            if !self.send_with_retry(&invite.id, request).await {
+                continue;
+            }
+            delivered += 1;
+            if let Err(e) = self.repository.mark_delivered(&invite.id).await {
+                warn!("标记邀请已投递失败，邀请ID: {}，错误: {}", invite.id, e);
+            }
+        }
+        if delivered > 0 {
+            info!("已向用户 {} 投递 {} 条邀请消息", user_id, delivered);
+        }
+        delivered
+    }
+
+    /// 发送邀请消息，失败时按退避重试，返回是否发送成功
+    async fn send_with_retry(&self, invite_id: &str, request: SendMsgRequest) -> bool {
+        let mut backoff = DELIVERY_RETRY_INTERVAL;
+        for attempt in 1..=DELIVERY_ATTEMPTS {
+            match self.chat_client.send_msg(request.clone()).await {
+                Ok(_) => return true,
+                Err(e) => warn!(
+                    "投递邀请消息失败（第 {} 次），邀请ID: {}，错误: {}",
+                    attempt, invite_id, e
+                ),
+            }
+            if attempt < DELIVERY_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+        false
+    }
+}
+
+/// 规范化手机号，去掉空格和短横线，只接受可选的+号加数字
+fn normalize_phone(phone: &str) -> Option<String> {
+    let phone: String = phone
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    let digits = phone.strip_prefix('+').unwrap_or(&phone);
+    if (5..=20).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(phone)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(normalize_phone("138 0013-8000").as_deref(), Some("13800138000"));
+        assert_eq!(normalize_phone("+8613800138000").as_deref(), Some("+8613800138000"));
+        assert_eq!(normalize_phone("1380013800a"), None);
+        assert_eq!(normalize_phone("++123456"), None);
+        assert_eq!(normalize_phone("123"), None);
+        assert_eq!(normalize_phone(""), None);
+    }
+}
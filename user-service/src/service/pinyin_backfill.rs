@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use tracing::{error, info};
+
+use crate::repository::user_repository::UserRepository;
+
+/// 单批补全的用户数
+const BACKFILL_BATCH_SIZE: i64 = 500;
+
+/// 两批之间的间隔，避免集中写库
+const BACKFILL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 昵称拼音补全任务
+///
+/// 新增拼音检索后，历史用户的昵称拼音为空，启动时分批补全，完成后退出
+pub struct PinyinBackfill {
+    repository: UserRepository,
+}
+
+impl PinyinBackfill {
+    pub fn new(repository: UserRepository) -> Self {
+        Self { repository }
+    }
+
+    // 启动后台补全任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut total = 0;
+            loop {
+                match self.repository.backfill_nickname_pinyin(BACKFILL_BATCH_SIZE).await {
+                    Ok(0) => break,
+                    Ok(count) => total += count,
+                    Err(e) => {
+                        error!("补全昵称拼音失败: {}", e);
+                        return;
+                    }
+                }
+                tokio::time::sleep(BACKFILL_INTERVAL).await;
+            }
+            if total > 0 {
+                info!("已补全 {} 个用户的昵称拼音", total);
+            }
+        })
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use cache::Cache;
+use common::config::ProfileConfig;
+use common::{Error, Result};
+use tracing::warn;
+
+/// 公开资料查看频率限制
+///
+/// 按查看者统计一个窗口内查看过的不同用户数，超过上限时拒绝，防止批量抓取用户资料；
+/// 重复查看同一用户不计数。Redis不可用时不限制
+#[derive(Clone)]
+pub struct ProfileViewLimiter {
+    cache: Arc<dyn Cache>,
+    config: ProfileConfig,
+}
+
+impl ProfileViewLimiter {
+    pub fn new(cache: Arc<dyn Cache>, config: ProfileConfig) -> Self {
+        Self { cache, config }
+    }
+
+    /// 记录一次资料查看，超过上限时返回错误，查看自己的资料不计数
+    pub async fn check(&self, viewer_id: &str, user_id: &str) -> Result<()> {
+        if self.config.max_viewed_per_window <= 0 || viewer_id == user_id {
+            return Ok(());
+        }
+
+        let viewed = match self
+            .cache
+            .record_profile_view(viewer_id, user_id, self.config.window_secs)
+            .await
+        {
+            Ok(viewed) => viewed,
+            Err(err) => {
+                warn!("记录资料查看失败，跳过频率限制: {}", err);
+                return Ok(());
+            }
+        };
+
+        if viewed > self.config.max_viewed_per_window {
+            warn!(
+                "用户 {} 查看资料过于频繁，窗口内已查看 {} 人",
+                viewer_id, viewed
+            );
+            return Err(Error::TooManyRequests(
+                "查看资料过于频繁，请稍后再试".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::model::retention::{PurgedMessages, RetentionPolicy};
+use crate::repository::retention_repository::RetentionRepository;
+use crate::service::storage_service::AttachmentReleaser;
+
+/// 清理任务执行间隔
+const CLEAN_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// 单批删除的最大消息数，避免长事务锁表
+const CLEAN_BATCH_SIZE: i64 = 1000;
+
+/// 过期消息清理任务
+///
+/// 按租户保留策略删除超过保留期限的私聊和群聊消息，处于法律保全中的用户和会话不会被清理，
+/// 被删除消息中的附件释放存储配额并删除文件，每个租户每轮的删除数量写入审计记录
+pub struct RetentionCleaner {
+    repository: RetentionRepository,
+    attachments: AttachmentReleaser,
+}
+
+impl RetentionCleaner {
+    pub fn new(repository: RetentionRepository, attachments: AttachmentReleaser) -> Self {
+        Self {
+            repository,
+            attachments,
+        }
+    }
+
+    // 启动后台清理任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEAN_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.clean().await;
+            }
+        })
+    }
+
+    async fn clean(&self) {
+        let policies = match self.repository.list_active_policies().await {
+            Ok(policies) => policies,
+            Err(e) => {
+                error!("读取消息保留策略失败: {}", e);
+                return;
+            }
+        };
+
+        for policy in &policies {
+            if let Err(e) = self.clean_tenant(policy).await {
+                error!("清理租户 {} 的过期消息失败: {}", policy.tenant_id, e);
+            }
+        }
+    }
+
+    async fn clean_tenant(&self, policy: &RetentionPolicy) -> common::Result<()> {
+        let cutoff = Utc::now() - chrono::Duration::days(policy.retain_days as i64);
+
+        let mut private_count = 0u64;
+        let mut attachment_count = 0i64;
+        loop {
+            let purged = self
+                .repository
+                .purge_private_messages(&policy.tenant_id, cutoff, CLEAN_BATCH_SIZE)
+                .await?;
+            private_count += purged.rows;
+            attachment_count += self.release_attachments(&policy.tenant_id, &purged).await;
+            if purged.rows < CLEAN_BATCH_SIZE as u64 {
+                break;
+            }
+        }
+
+        let mut group_count = 0u64;
+        loop {
+            let purged = self
+                .repository
+                .purge_group_messages(&policy.tenant_id, cutoff, CLEAN_BATCH_SIZE)
+                .await?;
+            group_count += purged.rows;
+            attachment_count += self.release_attachments(&policy.tenant_id, &purged).await;
+            if purged.rows < CLEAN_BATCH_SIZE as u64 {
+                break;
+            }
+        }
+
+        let total = private_count + group_count;
+        if total == 0 {
+            return Ok(());
+        }
+
+        let detail = json!({
+            "cutoff": cutoff.to_rfc3339(),
+            "retainDays": policy.retain_days,
+            "privateMessages": private_count,
+            "groupMessages": group_count,
+            "attachments": attachment_count,
+        })
+        .to_string();
+        self.repository
+            .record_purge(&policy.tenant_id, &detail, total as i64)
+            .await?;
+
+        info!(
+            "租户 {} 已清理 {} 条过期消息（私聊 {}，群聊 {}）",
+            policy.tenant_id, total, private_count, group_count
+        );
+        Ok(())
+    }
+
+    /// 释放被删除消息中的附件，返回释放的附件数；消息已经删除，释放失败只记录日志
+    async fn release_attachments(&self, tenant_id: &str, purged: &PurgedMessages) -> i64 {
+        if purged.object_keys.is_empty() {
+            return 0;
+        }
+        match self.attachments.release(&purged.object_keys).await {
+            Ok((released, _)) => released,
+            Err(e) => {
+                error!("释放租户 {} 过期消息的附件失败: {}", tenant_id, e);
+                0
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use crate::model::retention::{HOLD_TARGET_CONVERSATION, HOLD_TARGET_USER};
+use crate::repository::retention_repository::RetentionRepository;
+use common::grpc::subject::check_subject;
+use common::proto::retention::{
+    retention_service_server::RetentionService, GetRetentionPolicyRequest, LegalHoldResponse,
+    LegalHoldTarget, ListLegalHoldsRequest, ListLegalHoldsResponse, ListRetentionAuditsRequest,
+    ListRetentionAuditsResponse, PlaceLegalHoldRequest, ReleaseLegalHoldRequest,
+    RetentionPolicy as ProtoPolicy, RetentionPolicyResponse, SetRetentionPolicyRequest,
+};
+use common::{Error, Result};
+use serde_json::json;
+use tonic::{Request, Response, Status};
+use tracing::{debug, info};
+
+/// 保留天数上限（10年）
+const MAX_RETAIN_DAYS: i32 = 3650;
+
+/// 审计记录默认每页条数
+const DEFAULT_AUDIT_PAGE_SIZE: i64 = 20;
+
+/// 审计记录每页最大条数
+const MAX_AUDIT_PAGE_SIZE: i64 = 100;
+
+/// 消息保留策略服务实现
+pub struct RetentionServiceImpl {
+    repository: RetentionRepository,
+}
+
+impl RetentionServiceImpl {
+    pub fn new(repository: RetentionRepository) -> Self {
+        Self { repository }
+    }
+
+    /// 只有租户管理员可以管理本租户的保留策略
+    async fn check_admin(&self, tenant_id: &str, operator_id: &str) -> Result<()> {
+        if tenant_id.is_empty() || operator_id.is_empty() {
+            return Err(Error::BadRequest("租户ID和操作人不能为空".to_string()));
+        }
+        if !self.repository.is_tenant_admin(tenant_id, operator_id).await? {
+            return Err(Error::Authorization(format!(
+                "用户 {} 不是租户 {} 的管理员",
+                operator_id, tenant_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl RetentionService for RetentionServiceImpl {
+    /// 设置租户的消息保留策略
+    async fn set_retention_policy(
+        &self,
+        request: Request<SetRetentionPolicyRequest>,
+    ) -> std::result::Result<Response<RetentionPolicyResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        debug!("设置保留策略，租户: {}, 天数: {}", req.tenant_id, req.retain_days);
+
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+        if req.retain_days < 0 || req.retain_days > MAX_RETAIN_DAYS {
+            return Err(Error::BadRequest(format!(
+                "保留天数必须在 0-{} 之间",
+                MAX_RETAIN_DAYS
+            ))
+            .into());
+        }
+
+        let previous = self.repository.get_policy(&req.tenant_id).await?;
+        let detail = json!({
+            "previousRetainDays": previous.map(|p| p.retain_days),
+            "retainDays": req.retain_days,
+        })
+        .to_string();
+
+        let policy = self
+            .repository
+            .set_policy(&req.tenant_id, req.retain_days, &req.operator_id, &detail)
+            .await?;
+        info!(
+            "租户 {} 的消息保留策略已由 {} 设置为 {} 天",
+            req.tenant_id, req.operator_id, req.retain_days
+        );
+
+        Ok(Response::new(RetentionPolicyResponse {
+            policy: Some(policy.into()),
+        }))
+    }
+
+    /// 查询租户的消息保留策略，未设置时返回永久保留
+    async fn get_retention_policy(
+        &self,
+        request: Request<GetRetentionPolicyRequest>,
+    ) -> std::result::Result<Response<RetentionPolicyResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+
+        let policy = match self.repository.get_policy(&req.tenant_id).await? {
+            Some(policy) => policy.into(),
+            None => ProtoPolicy {
+                tenant_id: req.tenant_id,
+                ..Default::default()
+            },
+        };
+
+        Ok(Response::new(RetentionPolicyResponse {
+            policy: Some(policy),
+        }))
+    }
+
+    /// 设置法律保全
+    async fn place_legal_hold(
+        &self,
+        request: Request<PlaceLegalHoldRequest>,
+    ) -> std::result::Result<Response<LegalHoldResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+
+        if req.target_id.is_empty() {
+            return Err(Error::BadRequest("保全对象不能为空".to_string()).into());
+        }
+        let target_type = match LegalHoldTarget::try_from(req.target_type) {
+            Ok(LegalHoldTarget::User) => HOLD_TARGET_USER,
+            Ok(LegalHoldTarget::Conversation) => HOLD_TARGET_CONVERSATION,
+            Err(_) => return Err(Error::BadRequest("无效的保全对象类型".to_string()).into()),
+        };
+
+        let detail = json!({
+            "targetType": target_type,
+            "targetId": req.target_id,
+            "reason": req.reason,
+        })
+        .to_string();
+
+        let hold = self
+            .repository
+            .place_hold(
+                &req.tenant_id,
+                target_type,
+                &req.target_id,
+                &req.reason,
+                &req.operator_id,
+                &detail,
+            )
+            .await?;
+        info!(
+            "租户 {} 设置法律保全 {}，对象: {}",
+            req.tenant_id, hold.id, req.target_id
+        );
+
+        Ok(Response::new(LegalHoldResponse {
+            hold: Some(hold.into()),
+        }))
+    }
+
+    /// 解除法律保全
+    async fn release_legal_hold(
+        &self,
+        request: Request<ReleaseLegalHoldRequest>,
+    ) -> std::result::Result<Response<LegalHoldResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+
+        let detail = json!({ "holdId": req.hold_id }).to_string();
+        let hold = self
+            .repository
+            .release_hold(&req.tenant_id, &req.hold_id, &req.operator_id, &detail)
+            .await?;
+        info!("租户 {} 解除法律保全 {}", req.tenant_id, req.hold_id);
+
+        Ok(Response::new(LegalHoldResponse {
+            hold: Some(hold.into()),
+        }))
+    }
+
+    /// 查询生效中的法律保全
+    async fn list_legal_holds(
+        &self,
+        request: Request<ListLegalHoldsRequest>,
+    ) -> std::result::Result<Response<ListLegalHoldsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+
+        let holds = self.repository.list_active_holds(&req.tenant_id).await?;
+
+        Ok(Response::new(ListLegalHoldsResponse {
+            holds: holds.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    /// 分页查询审计记录
+    async fn list_retention_audits(
+        &self,
+        request: Request<ListRetentionAuditsRequest>,
+    ) -> std::result::Result<Response<ListRetentionAuditsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().operator_id)?;
+        let req = request.into_inner();
+        self.check_admin(&req.tenant_id, &req.operator_id).await?;
+
+        let page = req.page.max(1);
+        let page_size = if req.page_size <= 0 {
+            DEFAULT_AUDIT_PAGE_SIZE
+        } else {
+            req.page_size.min(MAX_AUDIT_PAGE_SIZE)
+        };
+
+        let (audits, total) = self
+            .repository
+            .list_audits(&req.tenant_id, page, page_size)
+            .await?;
+
+        Ok(Response::new(ListRetentionAuditsResponse {
+            audits: audits.into_iter().map(Into::into).collect(),
+            total,
+        }))
+    }
+}
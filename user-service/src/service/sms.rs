@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use tracing::warn;
+
+use common::config::SmsConfig;
+use common::{Error, Result};
+
+/// 短信发送
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    /// 按模板向手机号发送短信
+    async fn send(&self, phone: &str, template: &str, params: &HashMap<&str, String>) -> Result<()>;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SmsRequest<'a> {
+    phone: &'a str,
+    template: &'a str,
+    params: &'a HashMap<&'a str, String>,
+}
+
+/// 通过HTTP调用短信网关
+///
+/// 请求: `POST {endpoint}`，body 为 `{"phone","template","params"}`，返回2xx即视为发送成功
+pub struct HttpSmsSender {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl HttpSmsSender {
+    pub fn new(config: &SmsConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("创建短信服务HTTP客户端失败");
+        Self {
+            client,
+            endpoint: config.endpoint.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl SmsSender for HttpSmsSender {
+    async fn send(&self, phone: &str, template: &str, params: &HashMap<&str, String>) -> Result<()> {
+        self.client
+            .post(&self.endpoint)
+            .json(&SmsRequest {
+                phone,
+                template,
+                params,
+            })
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| Error::Internal(format!("调用短信服务失败: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// 未配置短信服务时使用，只记录日志
+pub struct LogSmsSender;
+
+#[async_trait]
+impl SmsSender for LogSmsSender {
+    async fn send(&self, phone: &str, template: &str, _params: &HashMap<&str, String>) -> Result<()> {
+        warn!("短信服务未配置，未向 {} 发送短信，模板: {}", phone, template);
+        Ok(())
+    }
+}
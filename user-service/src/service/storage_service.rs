@@ -0,0 +1,245 @@
+use crate::model::storage::object_key;
+use crate::repository::storage_repository::StorageRepository;
+use crate::service::upload_events::UploadEventPublisher;
+use chrono::Utc;
+use common::attachment::{validate_upload, UploadCompletedEvent};
+use common::config::{AttachmentConfig, StorageQuotaConfig};
+use common::grpc::subject::{check_subject, SUBJECT_METADATA_KEY};
+use common::grpc::tenant::current_tenant;
+use common::proto::storage::{
+    storage_service_server::StorageService, CompleteUploadRequest, CompleteUploadResponse,
+    CreateUploadUrlRequest, CreateUploadUrlResponse, GetStorageUsageRequest,
+    ReleaseAttachmentsRequest, ReleaseAttachmentsResponse, StorageUsage,
+};
+use common::{Error, Result};
+use oss::Oss;
+use prost_types::Timestamp;
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::{Request, Response, Status};
+use tracing::{debug, info, warn};
+
+/// 单次释放的最大附件数
+const MAX_RELEASE_BATCH: usize = 1000;
+
+/// 附件释放：删除登记、扣减上传者的用量并删除OSS上的文件
+///
+/// 存储配额服务和过期消息清理任务共用
+#[derive(Clone)]
+pub struct AttachmentReleaser {
+    repository: StorageRepository,
+    oss: Arc<dyn Oss>,
+}
+
+impl AttachmentReleaser {
+    pub fn new(repository: StorageRepository, oss: Arc<dyn Oss>) -> Self {
+        Self { repository, oss }
+    }
+
+    /// 释放附件，返回 (释放的附件数, 释放的字节数)
+    ///
+    /// 用量扣减后才删除文件，文件删除失败只记录日志，不影响已释放的配额
+    pub async fn release(&self, object_keys: &[String]) -> Result<(i64, i64)> {
+        let released = self.repository.release(object_keys).await?;
+        let mut released_bytes = 0;
+        for (object_key, size) in &released {
+            released_bytes += size;
+            if let Err(e) = self.oss.delete_file(object_key).await {
+                warn!("删除附件文件 {} 失败: {}", object_key, e);
+            }
+        }
+        Ok((released.len() as i64, released_bytes))
+    }
+}
+
+/// 附件存储配额服务实现
+pub struct StorageServiceImpl {
+    repository: StorageRepository,
+    releaser: AttachmentReleaser,
+    oss: Arc<dyn Oss>,
+    config: StorageQuotaConfig,
+    attachment: AttachmentConfig,
+    // 上传完成事件发布者，为空时不生成缩略图
+    events: Option<UploadEventPublisher>,
+}
+
+impl StorageServiceImpl {
+    pub fn new(
+        repository: StorageRepository,
+        oss: Arc<dyn Oss>,
+        config: StorageQuotaConfig,
+        attachment: AttachmentConfig,
+        events: Option<UploadEventPublisher>,
+    ) -> Self {
+        Self {
+            releaser: AttachmentReleaser::new(repository.clone(), oss.clone()),
+            repository,
+            oss,
+            config,
+            attachment,
+            events,
+        }
+    }
+
+    /// 当前租户生效的配额
+    fn quota(&self) -> i64 {
+        self.config.quota_for(current_tenant().as_deref())
+    }
+}
+
+#[tonic::async_trait]
+impl StorageService for StorageServiceImpl {
+    /// 申请附件的签名上传地址，按声明的大小预占配额
+    async fn create_upload_url(
+        &self,
+        request: Request<CreateUploadUrlRequest>,
+    ) -> std::result::Result<Response<CreateUploadUrlResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Error::BadRequest("用户ID不能为空".to_string()).into());
+        }
+        if req.size <= 0 {
+            return Err(Error::BadRequest("附件大小无效".to_string()).into());
+        }
+        validate_upload(&req.mime_type, req.size as u64, &self.attachment)?;
+
+        let quota = self.quota();
+        if quota > 0 && req.size > quota {
+            return Err(Status::resource_exhausted(format!(
+                "附件大小超过存储配额: {} > {}",
+                req.size, quota
+            )));
+        }
+
+        let key = object_key(
+            &self.config.key_prefix,
+            &req.user_id,
+            &req.file_name,
+            Utc::now(),
+        );
+        let Some(usage) = self
+            .repository
+            .reserve(
+                &req.user_id,
+                &key,
+                req.size,
+                &req.mime_type,
+                quota,
+                self.config.reservation_ttl_secs(),
+            )
+            .await?
+        else {
+            return Err(Status::resource_exhausted("存储空间不足，请清理后再上传"));
+        };
+
+        let ttl = Duration::from_secs(self.config.upload_url_ttl_secs.max(1));
+        let upload_url = match self
+            .oss
+            .presigned_upload_url(&key, &req.mime_type, req.size, ttl)
+            .await
+        {
+            Ok(url) => url,
+            Err(e) => {
+                // 没有拿到上传地址时退回预占的配额
+                if let Err(release_err) = self.repository.release(&[key.clone()]).await {
+                    warn!("退回附件 {} 预占的配额失败: {}", key, release_err);
+                }
+                return Err(e.into());
+            }
+        };
+        debug!(
+            "用户 {} 申请上传附件 {}，大小 {}，已用 {}",
+            req.user_id, key, req.size, usage.used_bytes
+        );
+
+        let expires_at = Utc::now() + chrono::Duration::seconds(ttl.as_secs() as i64);
+        Ok(Response::new(CreateUploadUrlResponse {
+            object_key: key,
+            upload_url,
+            expires_at: Some(Timestamp {
+                seconds: expires_at.timestamp(),
+                nanos: 0,
+            }),
+            usage: Some(usage.into_proto(quota)),
+        }))
+    }
+
+    /// 确认附件已上传完成，确认后预占的配额不再过期释放，并发布上传完成事件用于生成缩略图
+    async fn complete_upload(
+        &self,
+        request: Request<CompleteUploadRequest>,
+    ) -> std::result::Result<Response<CompleteUploadResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() || req.object_key.is_empty() {
+            return Err(Error::BadRequest("用户ID和对象键不能为空".to_string()).into());
+        }
+
+        let Some((size, mime_type)) = self
+            .repository
+            .commit(&req.user_id, &req.object_key)
+            .await?
+        else {
+            return Err(Status::not_found("附件不存在或上传地址已过期"));
+        };
+        debug!("用户 {} 确认附件 {} 上传完成", req.user_id, req.object_key);
+
+        if let Some(events) = &self.events {
+            events
+                .publish(&UploadCompletedEvent {
+                    object_key: req.object_key.clone(),
+                    mime_type,
+                    size: size as u64,
+                    uploader_id: req.user_id.clone(),
+                })
+                .await;
+        }
+
+        let usage = self.repository.usage(&req.user_id).await?;
+        Ok(Response::new(CompleteUploadResponse {
+            object_key: req.object_key,
+            usage: Some(usage.into_proto(self.quota())),
+        }))
+    }
+
+    /// 查询用户的附件存储用量
+    async fn get_storage_usage(
+        &self,
+        request: Request<GetStorageUsageRequest>,
+    ) -> std::result::Result<Response<StorageUsage>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        if req.user_id.is_empty() {
+            return Err(Error::BadRequest("用户ID不能为空".to_string()).into());
+        }
+
+        let usage = self.repository.usage(&req.user_id).await?;
+        Ok(Response::new(usage.into_proto(self.quota())))
+    }
+
+    /// 释放附件，只接受内部服务的调用
+    async fn release_attachments(
+        &self,
+        request: Request<ReleaseAttachmentsRequest>,
+    ) -> std::result::Result<Response<ReleaseAttachmentsResponse>, Status> {
+        if request.metadata().contains_key(SUBJECT_METADATA_KEY) {
+            return Err(Status::permission_denied("附件只能由消息服务释放"));
+        }
+        let req = request.into_inner();
+        if req.object_keys.len() > MAX_RELEASE_BATCH {
+            return Err(
+                Error::BadRequest(format!("单次最多释放 {} 个附件", MAX_RELEASE_BATCH)).into(),
+            );
+        }
+
+        let (released, released_bytes) = self.releaser.release(&req.object_keys).await?;
+        if released > 0 {
+            info!("释放附件 {} 个，共 {} 字节", released, released_bytes);
+        }
+        Ok(Response::new(ReleaseAttachmentsResponse {
+            released,
+            released_bytes,
+        }))
+    }
+}
@@ -0,0 +1,56 @@
+use common::attachment::UploadCompletedEvent;
+use common::config::AppConfig;
+use common::{Error, Result};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::ClientConfig;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// 文件上传完成事件的发布者
+///
+/// 客户端确认上传完成后写入 `kafka.upload_topic`，由消息服务为图片生成缩略图；
+/// 重复确认会重复发布，缩略图按对象键覆盖写入
+#[derive(Clone)]
+pub struct UploadEventPublisher {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl UploadEventPublisher {
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", config.kafka.hosts.join(","))
+            .set(
+                "message.timeout.ms",
+                config.kafka.producer.timeout.to_string(),
+            )
+            .set(
+                "socket.timeout.ms",
+                config.kafka.connect_timeout.to_string(),
+            )
+            .set("acks", config.kafka.producer.acks.clone())
+            .create()
+            .map_err(|e| Error::Internal(format!("上传事件生产者创建失败: {}", e)))?;
+
+        Ok(Self {
+            producer,
+            topic: config.kafka.upload_topic.clone(),
+        })
+    }
+
+    /// 发布上传完成事件，缩略图不影响上传结果，发布失败只记录日志
+    pub async fn publish(&self, event: &UploadCompletedEvent) {
+        let payload = match serde_json::to_string(event) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("序列化上传完成事件失败: {}", e);
+                return;
+            }
+        };
+        let record: FutureRecord<String, String> = FutureRecord::to(&self.topic).payload(&payload);
+        match self.producer.send(record, Duration::from_secs(0)).await {
+            Ok(_) => debug!("已发布附件 {} 的上传完成事件", event.object_key),
+            Err((e, _)) => warn!("发布附件 {} 的上传完成事件失败: {}", event.object_key, e),
+        }
+    }
+}
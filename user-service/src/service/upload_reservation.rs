@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use tracing::{error, info};
+
+use crate::repository::storage_repository::StorageRepository;
+use crate::service::storage_service::AttachmentReleaser;
+
+/// 过期预占的检查间隔
+const SWEEP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// 单批释放的最大附件数
+const SWEEP_BATCH_SIZE: i64 = 500;
+
+/// 过期上传预占清理任务
+///
+/// 申请上传地址时预占的配额需要客户端在上传完成后确认，上传地址过期后超过确认等待时间仍未确认的，
+/// 视为上传失败或被放弃，释放预占的配额并删除可能已上传的文件
+pub struct UploadReservationSweeper {
+    repository: StorageRepository,
+    attachments: AttachmentReleaser,
+}
+
+impl UploadReservationSweeper {
+    pub fn new(repository: StorageRepository, attachments: AttachmentReleaser) -> Self {
+        Self {
+            repository,
+            attachments,
+        }
+    }
+
+    // 启动后台清理任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.sweep().await {
+                    error!("释放过期的上传预占失败: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn sweep(&self) -> common::Result<()> {
+        let mut released = 0;
+        let mut released_bytes = 0;
+        loop {
+            let keys = self
+                .repository
+                .expired_reservations(SWEEP_BATCH_SIZE)
+                .await?;
+            if keys.is_empty() {
+                break;
+            }
+            let (count, bytes) = self.attachments.release(&keys).await?;
+            released += count;
+            released_bytes += bytes;
+            if (keys.len() as i64) < SWEEP_BATCH_SIZE {
+                break;
+            }
+        }
+
+        if released > 0 {
+            info!(
+                "释放过期未确认的上传预占 {} 个，共 {} 字节",
+                released, released_bytes
+            );
+        }
+        Ok(())
+    }
+}
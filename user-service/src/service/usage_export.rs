@@ -0,0 +1,128 @@
+use crate::model::job::Job;
+use crate::model::usage::{MessageUsageRecord, UsageExportParams};
+use crate::repository::retention_repository::RetentionRepository;
+use crate::repository::usage_repository::UsageRepository;
+use crate::service::job_worker::{JobHandler, JobProgress};
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use common::{Error, Result};
+use common::tenant_db::PgRouter;
+use std::fmt::Write;
+
+/// 任务类型：导出消息用量
+pub const JOB_KIND_EXPORT_USAGE: &str = "export_message_usage";
+
+/// 消息用量导出，供计费系统使用
+///
+/// 只有租户管理员可以导出本租户的用量，结果为CSV或Parquet文件（Parquet需启用 parquet 特性）
+pub struct UsageExportHandler {
+    usage: UsageRepository,
+    tenants: RetentionRepository,
+    max_hours: i64,
+}
+
+impl UsageExportHandler {
+    pub fn new(db: PgRouter, max_hours: i64) -> Self {
+        Self {
+            usage: UsageRepository::new(db.clone()),
+            tenants: RetentionRepository::new(db),
+            max_hours,
+        }
+    }
+
+    // 校验导出的小时范围
+    fn check_range(&self, params: &UsageExportParams) -> Result<()> {
+        let parse = |hour: &str| {
+            NaiveDateTime::parse_from_str(&format!("{}0000", hour), "%Y%m%d%H%M%S")
+                .map_err(|_| Error::BadRequest(format!("无效的小时: {}，格式应为yyyyMMddHH", hour)))
+        };
+        let hours = (parse(&params.to)? - parse(&params.from)?).num_hours();
+        if hours <= 0 || hours > self.max_hours {
+            return Err(Error::BadRequest(format!(
+                "导出范围应为1到{}小时",
+                self.max_hours
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobHandler for UsageExportHandler {
+    async fn run(&self, job: &Job, progress: &JobProgress) -> Result<Vec<u8>> {
+        let params: UsageExportParams = serde_json::from_str(&job.params)
+            .map_err(|e| Error::BadRequest(format!("无效的导出参数: {}", e)))?;
+        self.check_range(&params)?;
+        if !self.tenants.is_tenant_admin(&params.tenant_id, &job.user_id).await? {
+            return Err(Error::Authorization(format!(
+                "用户 {} 不是租户 {} 的管理员",
+                job.user_id, params.tenant_id
+            )));
+        }
+
+        let records = self
+            .usage
+            .list_by_tenant(&params.tenant_id, &params.from, &params.to)
+            .await?;
+        progress.report(50).await;
+
+        match params.format.as_str() {
+            "csv" => Ok(to_csv(&records)),
+            "parquet" => to_parquet(&records),
+            format => Err(Error::BadRequest(format!("不支持的导出格式: {}", format))),
+        }
+    }
+}
+
+/// 导出为CSV，ID只包含字母、数字和连字符，无需转义
+fn to_csv(records: &[MessageUsageRecord]) -> Vec<u8> {
+    let mut csv = String::from("stat_hour,tenant_id,user_id,messages,bytes\n");
+    for record in records {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{}",
+            record.stat_hour, record.tenant_id, record.user_id, record.messages, record.bytes
+        );
+    }
+    csv.into_bytes()
+}
+
+/// 导出为Parquet
+#[cfg(feature = "parquet")]
+fn to_parquet(records: &[MessageUsageRecord]) -> Result<Vec<u8>> {
+    use arrow::array::{ArrayRef, Int64Array, StringArray};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    let strings = |field: fn(&MessageUsageRecord) -> &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(records.iter().map(field)))
+    };
+    let numbers = |field: fn(&MessageUsageRecord) -> i64| -> ArrayRef {
+        Arc::new(Int64Array::from_iter_values(records.iter().map(field)))
+    };
+    let batch = RecordBatch::try_from_iter(vec![
+        ("stat_hour", strings(|r| &r.stat_hour)),
+        ("tenant_id", strings(|r| &r.tenant_id)),
+        ("user_id", strings(|r| &r.user_id)),
+        ("messages", numbers(|r| r.messages)),
+        ("bytes", numbers(|r| r.bytes)),
+    ])
+    .map_err(|e| Error::Internal(e.to_string()))?;
+
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, batch.schema(), None)
+        .map_err(|e| Error::Internal(e.to_string()))?;
+    writer
+        .write(&batch)
+        .map_err(|e| Error::Internal(e.to_string()))?;
+    writer.close().map_err(|e| Error::Internal(e.to_string()))?;
+    Ok(buffer)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn to_parquet(_records: &[MessageUsageRecord]) -> Result<Vec<u8>> {
+    Err(Error::BadRequest(
+        "未启用Parquet导出，请使用csv格式".to_string(),
+    ))
+}
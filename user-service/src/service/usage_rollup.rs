@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cache::{Cache, MessageUsage};
+use chrono::{NaiveDateTime, Utc};
+use common::config::UsageConfig;
+use common::grpc::tenant::with_tenant;
+use tracing::{error, info, warn};
+
+use crate::repository::usage_repository::UsageRepository;
+
+/// 检查待汇总小时的间隔
+const ROLLUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 消息用量每小时汇总任务
+///
+/// 将Redis中已结束的小时的消息用量写入Postgres，汇总结果覆盖已有记录，重复执行不会重复计量
+pub struct UsageRollup {
+    cache: Arc<dyn Cache>,
+    repository: UsageRepository,
+    config: UsageConfig,
+}
+
+impl UsageRollup {
+    pub fn new(cache: Arc<dyn Cache>, repository: UsageRepository, config: UsageConfig) -> Self {
+        Self {
+            cache,
+            repository,
+            config,
+        }
+    }
+
+    // 启动后台汇总任务
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            if !self.config.enabled {
+                return;
+            }
+            loop {
+                if let Err(e) = self.rollup_finished_hours().await {
+                    error!("消息用量汇总失败: {}", e);
+                }
+                tokio::time::sleep(ROLLUP_INTERVAL).await;
+            }
+        })
+    }
+
+    // 汇总全部已结束且超过延迟时间的小时
+    async fn rollup_finished_hours(&self) -> anyhow::Result<()> {
+        let mut hours = self.cache.message_usage_hours().await?;
+        hours.sort();
+
+        for hour in hours {
+            if !self.is_ready(&hour) {
+                continue;
+            }
+            let usage = self.cache.get_message_usage(&hour).await?;
+            // 按租户分组写入租户所在的数据库集群
+            let mut by_tenant: HashMap<String, Vec<MessageUsage>> = HashMap::new();
+            for entry in &usage {
+                by_tenant
+                    .entry(entry.tenant_id.clone())
+                    .or_default()
+                    .push(entry.clone());
+            }
+            for (tenant_id, entries) in by_tenant {
+                with_tenant(Some(tenant_id), self.repository.upsert_hourly(&hour, &entries))
+                    .await?;
+            }
+            self.cache.finish_message_usage_hour(&hour).await?;
+            info!("消息用量汇总完成: {}，共 {} 条记录", hour, usage.len());
+        }
+        Ok(())
+    }
+
+    // 小时结束后超过延迟时间才汇总，等待消息服务写入内存中的用量
+    fn is_ready(&self, hour: &str) -> bool {
+        let Ok(start) = NaiveDateTime::parse_from_str(&format!("{}0000", hour), "%Y%m%d%H%M%S")
+        else {
+            warn!("无效的消息用量小时: {}", hour);
+            return false;
+        };
+        let ready_at = start.and_utc()
+            + chrono::Duration::hours(1)
+            + chrono::Duration::seconds(self.config.rollup_delay_secs as i64);
+        Utc::now() >= ready_at
+    }
+}
@@ -1,21 +1,143 @@
-use crate::model::user::{CreateUserData, ForgetPasswordData, RegisterUserData, UpdateUserData};
+use crate::model::login_history::LoginContext;
+use crate::model::profile::{self, ViewerRelation};
+use crate::model::user::{CreateUserData, ForgetPasswordData, RegisterUserData, UpdateUserData, User};
+use crate::model::user_config::{UpdateUserConfigData, UserConfig};
+use crate::repository::login_history_repository::LoginHistoryRepository;
+use crate::repository::user_config_repository::UserConfigRepository;
 use crate::repository::user_repository::UserRepository;
-use common::proto::user::{user_service_server::UserService, CreateUserRequest, ForgetPasswordRequest, GetUserByIdRequest, GetUserByUsernameRequest, RegisterRequest, SearchUsersRequest, SearchUsersResponse, UpdateUserRequest, User as ProtoUser, UserResponse, VerifyPasswordRequest, VerifyPasswordResponse};
+use crate::service::login_security::LoginSecurity;
+use crate::service::phone_invite::PhoneInvites;
+use crate::service::email_digest::verify_unsubscribe_token;
+use crate::service::profile_view::ProfileViewLimiter;
+use common::grpc::subject::{check_subject, forwarded_subject};
+use common::grpc::tenant::{current_tenant, with_tenant};
+use common::schema_migration::SchemaMigration;
+use common::tenant_db::PgRouter;
+use common::proto::user::{user_service_server::UserService, CheckAvailabilityRequest, CheckAvailabilityResponse, ContactCardResponse, CreateUserRequest, EmailDigest, EvaluateLoginRequest, EvaluateLoginResponse, ForgetPasswordRequest, GetUserByIdRequest, GetUserByUsernameRequest, GetUserConfigRequest, GetUsersByIdsRequest, GetPublicProfileRequest, GetSearchExclusionsRequest, GetUsersByIdsResponse, LastSeenVisibility, ListUserDevicesRequest, ListUserDevicesResponse, PublicProfileResponse, RegisterRequest, ResolveContactCardRequest, SearchExclusionsResponse, SearchUsersRequest, SearchUsersResponse, SendPhoneInviteRequest, SendPhoneInviteResponse, SetConversationSearchOptOutRequest, UnsubscribeEmailDigestRequest, UnsubscribeEmailDigestResponse, UpdateUserConfigRequest, UpdateUserRequest, User as ProtoUser, UserConfigResponse, UserResponse, VerifyLoginChallengeRequest, VerifyPasswordRequest, VerifyPasswordResponse};
 use common::Error;
-use sqlx::PgPool;
+use prost_types::Timestamp;
 use tonic::{Request, Response, Status};
 use tracing::{debug, error, info};
 
+/// 批量获取用户的最大数量
+const MAX_BATCH_USERS: usize = 100;
+
+/// 设备列表的最大数量
+const MAX_USER_DEVICES: i64 = 50;
+
+/// 可用性检查时用户名、手机号的最大长度
+const MAX_AVAILABILITY_INPUT_LEN: usize = 64;
+
 /// 用户服务实现
 pub struct UserServiceImpl {
     repository: UserRepository,
+    config_repository: UserConfigRepository,
+    login_history_repository: LoginHistoryRepository,
+    login_security: LoginSecurity,
+    phone_invites: PhoneInvites,
+    profile_views: ProfileViewLimiter,
+    /// 摘要邮件退订令牌的签名密钥
+    digest_secret: String,
 }
 
 impl UserServiceImpl {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(
+        db: PgRouter,
+        login_security: LoginSecurity,
+        phone_invites: PhoneInvites,
+        profile_views: ProfileViewLimiter,
+        migration: SchemaMigration,
+        digest_secret: String,
+    ) -> Self {
         Self {
-            repository: UserRepository::new(pool),
+            repository: UserRepository::new(db.clone()).with_migration(migration),
+            config_repository: UserConfigRepository::new(db.clone()),
+            login_history_repository: LoginHistoryRepository::new(db),
+            login_security,
+            phone_invites,
+            profile_views,
+            digest_secret,
+        }
+    }
+
+    /// 注册成功后在后台投递发给该手机号的邀请消息，不影响注册结果
+    fn deliver_phone_invites(&self, user_id: &str, phone: &str) {
+        if phone.is_empty() {
+            return;
+        }
+        let phone_invites = self.phone_invites.clone();
+        let user_id = user_id.to_string();
+        let phone = phone.to_string();
+        // 后台任务不继承请求的租户上下文，需要显式传入
+        let tenant_id = current_tenant();
+        tokio::spawn(with_tenant(tenant_id, async move {
+            phone_invites.deliver_pending(&user_id, &phone).await;
+        }));
+    }
+
+    /// 按查看者过滤返回的完整用户信息
+    ///
+    /// 查看者为空（内部服务调用）或查看自己时不过滤；资料对查看者不可见时按用户不存在处理
+    async fn apply_viewer_privacy(
+        &self,
+        user: &mut User,
+        viewer_id: Option<&str>,
+    ) -> std::result::Result<(), Status> {
+        let Some(viewer_id) = viewer_id.filter(|viewer_id| *viewer_id != user.id) else {
+            return Ok(());
+        };
+
+        let relation = match self.repository.get_viewer_relation(viewer_id, &user.id).await {
+            Ok(relation) => relation,
+            Err(err) => {
+                error!("查询好友关系失败: {}", err);
+                return Err(err.into());
+            }
+        };
+        if !profile::profile_visible(user, viewer_id, relation) {
+            return Err(Error::NotFound("用户不存在".to_string()).into());
         }
+
+        let config = match self.config_repository.get_config(&user.id).await {
+            Ok(config) => config,
+            Err(err) => {
+                error!("获取用户时获取用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+        profile::redact_user(user, &config, viewer_id, relation);
+        Ok(())
+    }
+
+    /// 查询用户级和会话级的消息搜索排除设置
+    async fn search_exclusions(
+        &self,
+        user_id: &str,
+    ) -> std::result::Result<SearchExclusionsResponse, Status> {
+        let config = match self.config_repository.get_config(user_id).await {
+            Ok(config) => config,
+            Err(err) => {
+                error!("获取消息搜索排除设置时获取用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+        let conversation_ids = match self
+            .config_repository
+            .get_search_excluded_conversations(user_id)
+            .await
+        {
+            Ok(conversation_ids) => conversation_ids,
+            Err(err) => {
+                error!("获取会话搜索排除失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(SearchExclusionsResponse {
+            user_id: user_id.to_string(),
+            search_opt_out: config.search_opt_out,
+            conversation_ids,
+        })
     }
 }
 
@@ -40,6 +162,7 @@ impl UserService for UserServiceImpl {
             }
         };
         info!("注册用户成功 {}", user.username);
+        self.deliver_phone_invites(&user.id, &user.phone);
         // 返回响应
         Ok(Response::new(UserResponse {
             user: Some(ProtoUser::from(user)),
@@ -68,6 +191,7 @@ impl UserService for UserServiceImpl {
             }
         };
         info!("注册用户成功 {}", user.phone);
+        self.deliver_phone_invites(&user.id, &user.phone);
         // 返回响应
         Ok(Response::new(UserResponse {
             user: Some(ProtoUser::from(user)),
@@ -133,11 +257,12 @@ impl UserService for UserServiceImpl {
         &self,
         request: Request<GetUserByIdRequest>,
     ) -> std::result::Result<Response<UserResponse>, Status> {
+        let viewer_id = forwarded_subject(request.metadata()).map(str::to_string);
         let req = request.into_inner();
         debug!("通过ID获取用户请求，ID: {}", req.user_id);
 
         // 查询用户
-        let user = match self.repository.get_user_by_id(&req.user_id).await {
+        let mut user = match self.repository.get_user_by_id(&req.user_id).await {
             Ok(user) => user,
             Err(err) => {
                 error!("通过ID获取用户失败: {}", err);
@@ -145,6 +270,9 @@ impl UserService for UserServiceImpl {
             }
         };
 
+        // 网关转发的用户请求按资料所属用户的隐私设置过滤字段，内部服务调用返回完整信息
+        self.apply_viewer_privacy(&mut user, viewer_id.as_deref()).await?;
+
         // 返回响应
         Ok(Response::new(UserResponse {
             user: Some(ProtoUser::from(user)),
@@ -156,11 +284,12 @@ impl UserService for UserServiceImpl {
         &self,
         request: Request<GetUserByUsernameRequest>,
     ) -> std::result::Result<Response<UserResponse>, Status> {
+        let viewer_id = forwarded_subject(request.metadata()).map(str::to_string);
         let req = request.into_inner();
         debug!("通过用户名获取用户请求，用户名: {}", req.username);
 
         // 查询用户
-        let user = match self.repository.get_user_by_username(&req.username).await {
+        let mut user = match self.repository.get_user_by_username(&req.username).await {
             Ok(user) => user,
             Err(err) => {
                 error!("通过用户名获取用户失败: {}", err);
@@ -168,6 +297,9 @@ impl UserService for UserServiceImpl {
             }
         };
 
+        // 与通过ID获取用户一致，网关转发的请求按隐私设置过滤字段
+        self.apply_viewer_privacy(&mut user, viewer_id.as_deref()).await?;
+
         // 返回响应
         Ok(Response::new(UserResponse {
             user: Some(ProtoUser::from(user)),
@@ -179,6 +311,7 @@ impl UserService for UserServiceImpl {
         &self,
         request: Request<UpdateUserRequest>,
     ) -> std::result::Result<Response<UserResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
         let req = request.into_inner();
         debug!("更新用户请求，用户ID: {}", req.user_id);
 
@@ -248,7 +381,12 @@ impl UserService for UserServiceImpl {
         request: Request<SearchUsersRequest>,
     ) -> std::result::Result<Response<SearchUsersResponse>, Status> {
         let req = request.into_inner();
-        debug!("搜索用户请求，关键词: {}", req.query);
+        debug!("搜索用户请求，搜索者: {}，关键词: {}", req.viewer_id, req.query);
+
+        let query = req.query.trim();
+        if query.is_empty() {
+            return Err(Error::BadRequest("搜索关键词不能为空".to_string()).into());
+        }
 
         // 设置默认分页参数
         let page = if req.page <= 0 { 1 } else { req.page };
@@ -261,7 +399,7 @@ impl UserService for UserServiceImpl {
         // 搜索用户
         let (users, total) = match self
             .repository
-            .search_users(&req.query, page, page_size)
+            .search_users(&req.viewer_id, query, page, page_size)
             .await
         {
             Ok(result) => result,
@@ -271,10 +409,542 @@ impl UserService for UserServiceImpl {
             }
         };
 
-        // 转换为响应格式
-        let users: Vec<ProtoUser> = users.into_iter().map(ProtoUser::from).collect();
+        // 转换为响应格式，搜索结果不返回联系方式
+        let users: Vec<ProtoUser> = users
+            .into_iter()
+            .map(|user| ProtoUser {
+                email: String::new(),
+                phone: String::new(),
+                address: None,
+                ..ProtoUser::from(user)
+            })
+            .collect();
 
         // 返回响应
         Ok(Response::new(SearchUsersResponse { users, total }))
     }
+
+    /// 批量获取用户
+    async fn get_users_by_ids(
+        &self,
+        request: Request<GetUsersByIdsRequest>,
+    ) -> std::result::Result<Response<GetUsersByIdsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().viewer_id)?;
+        let req = request.into_inner();
+        debug!("批量获取用户请求，查看者: {}，数量: {}", req.viewer_id, req.user_ids.len());
+
+        if req.user_ids.len() > MAX_BATCH_USERS {
+            return Err(Error::BadRequest(format!("单次最多查询 {} 个用户", MAX_BATCH_USERS)).into());
+        }
+
+        let users = match self.repository.get_users_by_ids(&req.user_ids).await {
+            Ok(users) => users,
+            Err(err) => {
+                error!("批量获取用户失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        // 查询隐私设置和好友关系，决定返回哪些字段和是否返回最后活跃时间
+        let configs = match self.config_repository.get_configs(&req.user_ids).await {
+            Ok(configs) => configs,
+            Err(err) => {
+                error!("批量获取用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+        let friends = if req.viewer_id.is_empty() {
+            Default::default()
+        } else {
+            match self
+                .repository
+                .get_friends_of_viewer(&req.viewer_id, &req.user_ids)
+                .await
+            {
+                Ok(friends) => friends,
+                Err(err) => {
+                    error!("查询好友关系失败: {}", err);
+                    return Err(err.into());
+                }
+            }
+        };
+
+        let users = users
+            .into_iter()
+            .map(|(mut user, last_active_at)| {
+                let visible = !req.viewer_id.is_empty()
+                    && configs
+                        .get(&user.id)
+                        .is_some_and(|c| c.last_seen_visible_to(&req.viewer_id, friends.contains(&user.id)));
+
+                // 邮箱、手机号等字段同样按资料所属用户的隐私设置过滤，未保存配置的用户使用默认设置
+                if !req.viewer_id.is_empty() && user.id != req.viewer_id {
+                    let relation = ViewerRelation {
+                        is_friend: friends.contains(&user.id),
+                        blocked: false,
+                    };
+                    let config = configs
+                        .get(&user.id)
+                        .cloned()
+                        .unwrap_or_else(|| UserConfig::default_for(&user.id));
+                    profile::redact_user(&mut user, &config, &req.viewer_id, relation);
+                }
+
+                let mut user = ProtoUser::from(user);
+                if visible {
+                    user.last_active_at = last_active_at.map(|dt| Timestamp {
+                        seconds: dt.timestamp(),
+                        nanos: dt.timestamp_subsec_nanos() as i32,
+                    });
+                }
+                user
+            })
+            .collect();
+
+        Ok(Response::new(GetUsersByIdsResponse { users }))
+    }
+
+    /// 获取用户配置
+    async fn get_user_config(
+        &self,
+        request: Request<GetUserConfigRequest>,
+    ) -> std::result::Result<Response<UserConfigResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        debug!("获取用户配置请求，用户ID: {}", req.user_id);
+
+        let config = match self.config_repository.get_config(&req.user_id).await {
+            Ok(config) => config,
+            Err(err) => {
+                error!("获取用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Response::new(UserConfigResponse {
+            config: Some(config.into()),
+        }))
+    }
+
+    /// 更新用户配置
+    async fn update_user_config(
+        &self,
+        request: Request<UpdateUserConfigRequest>,
+    ) -> std::result::Result<Response<UserConfigResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        debug!("更新用户配置请求，用户ID: {}", req.user_id);
+
+        if let Some(visibility) = req.last_seen_visibility {
+            if LastSeenVisibility::try_from(visibility).is_err() {
+                return Err(Error::BadRequest("无效的最后活跃时间可见范围".to_string()).into());
+            }
+        }
+        let field_visibilities = [
+            req.email_visibility,
+            req.phone_visibility,
+            req.sex_visibility,
+            req.address_visibility,
+        ];
+        if field_visibilities
+            .into_iter()
+            .flatten()
+            .any(|visibility| LastSeenVisibility::try_from(visibility).is_err())
+        {
+            return Err(Error::BadRequest("无效的资料可见范围".to_string()).into());
+        }
+        if let Some(digest) = req.email_digest {
+            if EmailDigest::try_from(digest).is_err() {
+                return Err(Error::BadRequest("无效的摘要邮件频率".to_string()).into());
+            }
+        }
+
+        let data = UpdateUserConfigData {
+            last_seen_visibility: req.last_seen_visibility.map(|v| v as i16),
+            search_opt_out: req.search_opt_out,
+            allow_id_search: req.allow_id_search,
+            allow_phone_search: req.allow_phone_search,
+            allow_card_share: req.allow_card_share,
+            email_visibility: req.email_visibility.map(|v| v as i16),
+            phone_visibility: req.phone_visibility.map(|v| v as i16),
+            sex_visibility: req.sex_visibility.map(|v| v as i16),
+            address_visibility: req.address_visibility.map(|v| v as i16),
+            email_digest: req.email_digest.map(|v| v as i16),
+        };
+        let config = if data.is_empty() {
+            self.config_repository.get_config(&req.user_id).await
+        } else {
+            self.config_repository.update_config(&req.user_id, data).await
+        };
+
+        let config = match config {
+            Ok(config) => config,
+            Err(err) => {
+                error!("更新用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        info!("成功更新用户配置 {}", config.user_id);
+
+        Ok(Response::new(UserConfigResponse {
+            config: Some(config.into()),
+        }))
+    }
+
+    /// 登录风险评估
+    async fn evaluate_login(
+        &self,
+        request: Request<EvaluateLoginRequest>,
+    ) -> std::result::Result<Response<EvaluateLoginResponse>, Status> {
+        let req = request.into_inner();
+        debug!("登录风险评估请求，用户ID: {}，IP: {}", req.user_id, req.ip);
+
+        let ctx = LoginContext {
+            device_id: req.device_id,
+            device_name: req.device_name,
+            ip: req.ip,
+            user_agent: req.user_agent,
+        };
+
+        let evaluation = match self.login_security.evaluate(&req.user_id, &ctx).await {
+            Ok(evaluation) => evaluation,
+            Err(err) => {
+                error!("登录风险评估失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Response::new(EvaluateLoginResponse {
+            login_id: evaluation.record.id,
+            risk_score: evaluation.assessment.score,
+            require_verification: evaluation.challenged,
+            reasons: evaluation
+                .assessment
+                .reasons
+                .iter()
+                .map(|r| r.to_string())
+                .collect(),
+        }))
+    }
+
+    /// 校验登录二次验证码
+    async fn verify_login_challenge(
+        &self,
+        request: Request<VerifyLoginChallengeRequest>,
+    ) -> std::result::Result<Response<UserResponse>, Status> {
+        let req = request.into_inner();
+        debug!("校验登录二次验证码，登录ID: {}", req.login_id);
+
+        let user_id = match self.login_security.verify_challenge(&req.login_id, &req.code).await {
+            Ok(user_id) => user_id,
+            Err(err) => {
+                error!("登录二次验证失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        let user = match self.repository.get_user_by_id(&user_id).await {
+            Ok(user) => user,
+            Err(err) => {
+                error!("通过ID获取用户失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Response::new(UserResponse {
+            user: Some(ProtoUser::from(user)),
+        }))
+    }
+
+    /// 查询用户登录过的设备
+    async fn list_user_devices(
+        &self,
+        request: Request<ListUserDevicesRequest>,
+    ) -> std::result::Result<Response<ListUserDevicesResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        debug!("查询用户设备请求，用户ID: {}", req.user_id);
+
+        let devices = match self
+            .login_history_repository
+            .list_devices(&req.user_id, MAX_USER_DEVICES)
+            .await
+        {
+            Ok(devices) => devices,
+            Err(err) => {
+                error!("查询用户设备失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Response::new(ListUserDevicesResponse {
+            devices: devices.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    /// 注册前检查用户名、手机号是否可用
+    async fn check_availability(
+        &self,
+        request: Request<CheckAvailabilityRequest>,
+    ) -> std::result::Result<Response<CheckAvailabilityResponse>, Status> {
+        let req = request.into_inner();
+        let username = req.username.trim();
+        let phone = req.phone.trim();
+
+        if username.is_empty() && phone.is_empty() {
+            return Err(Error::BadRequest("用户名或者手机号不能为空".to_string()).into());
+        }
+        if username.len() > MAX_AVAILABILITY_INPUT_LEN || phone.len() > MAX_AVAILABILITY_INPUT_LEN {
+            return Err(Error::BadRequest("用户名或者手机号过长".to_string()).into());
+        }
+
+        let mut response = CheckAvailabilityResponse::default();
+        if !username.is_empty() {
+            let exists = self.repository.username_exists(username).await.map_err(|err| {
+                error!("检查用户名是否可用失败: {}", err);
+                Status::from(err)
+            })?;
+            response.username_available = Some(!exists);
+        }
+        if !phone.is_empty() {
+            let exists = self.repository.phone_exists(phone).await.map_err(|err| {
+                error!("检查手机号是否可用失败: {}", err);
+                Status::from(err)
+            })?;
+            response.phone_available = Some(!exists);
+        }
+
+        Ok(Response::new(response))
+    }
+
+    /// 向手机号发送邀请消息
+    async fn send_phone_invite(
+        &self,
+        request: Request<SendPhoneInviteRequest>,
+    ) -> std::result::Result<Response<SendPhoneInviteResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().sender_id)?;
+        let req = request.into_inner();
+        debug!("发送手机号邀请请求，发送人ID: {}", req.sender_id);
+
+        let (invite, delivered) = match self
+            .phone_invites
+            .send(&req.sender_id, &req.phone, &req.content)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => {
+                error!("发送手机号邀请失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Response::new(SendPhoneInviteResponse {
+            invite_id: invite.id,
+            delivered,
+        }))
+    }
+
+    /// 解析名片
+    async fn resolve_contact_card(
+        &self,
+        request: Request<ResolveContactCardRequest>,
+    ) -> std::result::Result<Response<ContactCardResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().viewer_id)?;
+        let req = request.into_inner();
+        debug!(
+            "解析名片请求，查看者ID: {}，名片用户ID: {}",
+            req.viewer_id, req.user_id
+        );
+
+        if req.viewer_id.is_empty() || req.user_id.is_empty() {
+            return Err(Error::BadRequest("查看者ID和名片用户ID不能为空".to_string()).into());
+        }
+
+        let ids = vec![req.user_id.clone()];
+        let (user, last_active_at) = match self.repository.get_users_by_ids(&ids).await {
+            Ok(users) => match users.into_iter().next() {
+                Some(user) => user,
+                None => return Err(Error::NotFound("名片用户不存在".to_string()).into()),
+            },
+            Err(err) => {
+                error!("解析名片时获取用户失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        let config = match self.config_repository.get_config(&req.user_id).await {
+            Ok(config) => config,
+            Err(err) => {
+                error!("解析名片时获取用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+        let is_friend = match self
+            .repository
+            .get_friends_of_viewer(&req.viewer_id, &ids)
+            .await
+        {
+            Ok(friends) => friends.contains(&req.user_id),
+            Err(err) => {
+                error!("查询好友关系失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        if !config.card_visible_to(&req.viewer_id, is_friend) {
+            return Err(Status::permission_denied("对方未公开名片"));
+        }
+
+        let last_active_at = if config.last_seen_visible_to(&req.viewer_id, is_friend) {
+            last_active_at.map(|dt| Timestamp {
+                seconds: dt.timestamp(),
+                nanos: dt.timestamp_subsec_nanos() as i32,
+            })
+        } else {
+            None
+        };
+
+        Ok(Response::new(ContactCardResponse {
+            user_id: user.id,
+            username: user.username,
+            nickname: user.nickname.unwrap_or_default(),
+            avatar_url: user.avatar_url.unwrap_or_default(),
+            is_friend,
+            last_active_at,
+        }))
+    }
+    /// 获取公开资料
+    ///
+    /// 按资料所属用户的隐私设置和与查看者的好友关系过滤字段；不存在、已注销、已停用
+    /// 或拉黑了查看者的用户统一返回不存在
+    async fn get_public_profile(
+        &self,
+        request: Request<GetPublicProfileRequest>,
+    ) -> std::result::Result<Response<PublicProfileResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().viewer_id)?;
+        let req = request.into_inner();
+        debug!(
+            "获取公开资料请求，查看者ID: {}，用户ID: {}",
+            req.viewer_id, req.user_id
+        );
+
+        if req.viewer_id.is_empty() || req.user_id.is_empty() {
+            return Err(Error::BadRequest("查看者ID和用户ID不能为空".to_string()).into());
+        }
+
+        self.profile_views.check(&req.viewer_id, &req.user_id).await?;
+
+        let ids = vec![req.user_id.clone()];
+        let (user, last_active_at) = match self.repository.get_users_by_ids(&ids).await {
+            Ok(users) => match users.into_iter().next() {
+                Some(user) => user,
+                None => return Err(Error::NotFound("用户不存在".to_string()).into()),
+            },
+            Err(err) => {
+                error!("获取公开资料时获取用户失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        let relation = match self
+            .repository
+            .get_viewer_relation(&req.viewer_id, &req.user_id)
+            .await
+        {
+            Ok(relation) => relation,
+            Err(err) => {
+                error!("查询好友关系失败: {}", err);
+                return Err(err.into());
+            }
+        };
+        if !profile::profile_visible(&user, &req.viewer_id, relation) {
+            return Err(Error::NotFound("用户不存在".to_string()).into());
+        }
+
+        let config = match self.config_repository.get_config(&req.user_id).await {
+            Ok(config) => config,
+            Err(err) => {
+                error!("获取公开资料时获取用户配置失败: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        Ok(Response::new(profile::public_profile(
+            user,
+            &config,
+            &req.viewer_id,
+            relation,
+            last_active_at,
+        )))
+    }
+
+    /// 通过摘要邮件中的退订链接关闭离线摘要邮件
+    async fn unsubscribe_email_digest(
+        &self,
+        request: Request<UnsubscribeEmailDigestRequest>,
+    ) -> std::result::Result<Response<UnsubscribeEmailDigestResponse>, Status> {
+        let req = request.into_inner();
+        let claims = verify_unsubscribe_token(&self.digest_secret, &req.token)?;
+
+        // 退订链接不经过登录，按令牌中的租户路由到用户所在的数据库集群
+        let data = UpdateUserConfigData {
+            email_digest: Some(EmailDigest::Off as i16),
+            ..Default::default()
+        };
+        if let Err(err) = with_tenant(
+            Some(claims.tenant_id),
+            self.config_repository.update_config(&claims.sub, data),
+        )
+        .await
+        {
+            error!("退订离线摘要邮件失败: {}", err);
+            return Err(err.into());
+        }
+
+        info!("用户 {} 已退订离线摘要邮件", claims.sub);
+        Ok(Response::new(UnsubscribeEmailDigestResponse {
+            user_id: claims.sub,
+        }))
+    }
+
+    /// 设置会话消息搜索排除
+    async fn set_conversation_search_opt_out(
+        &self,
+        request: Request<SetConversationSearchOptOutRequest>,
+    ) -> std::result::Result<Response<SearchExclusionsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        debug!(
+            "设置会话搜索排除请求，用户ID: {}，会话: {}，排除: {}",
+            req.user_id, req.conversation_id, req.opt_out
+        );
+
+        if req.conversation_id.is_empty() {
+            return Err(Error::BadRequest("会话ID不能为空".to_string()).into());
+        }
+
+        if let Err(err) = self
+            .config_repository
+            .set_conversation_search_opt_out(&req.user_id, &req.conversation_id, req.opt_out)
+            .await
+        {
+            error!("设置会话搜索排除失败: {}", err);
+            return Err(err.into());
+        }
+
+        self.search_exclusions(&req.user_id).await.map(Response::new)
+    }
+
+    /// 获取消息搜索排除设置
+    async fn get_search_exclusions(
+        &self,
+        request: Request<GetSearchExclusionsRequest>,
+    ) -> std::result::Result<Response<SearchExclusionsResponse>, Status> {
+        check_subject(request.metadata(), &request.get_ref().user_id)?;
+        let req = request.into_inner();
+        debug!("获取消息搜索排除设置请求，用户ID: {}", req.user_id);
+
+        self.search_exclusions(&req.user_id).await.map(Response::new)
+    }
 }